@@ -1,22 +1,34 @@
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, Command};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::time::timeout;
 
 use crate::backend::events::{AppServerEvent, EventSink};
+use crate::shared::output_decode_core::lossy_lines;
 use crate::shared::process_core::tokio_command;
+use crate::shared::resource_guard_core;
 use crate::codex::args::apply_codex_args;
 use crate::types::WorkspaceEntry;
 
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(15);
+/// Restarts in a row before a crashed app-server is given up on.
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(30);
+/// A process that ran this long counts as healthy, so its exit starts a fresh backoff.
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+/// How often a retired session checks whether its turns have finished.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 fn extract_thread_id(value: &Value) -> Option<String> {
     let params = value.get("params")?;
 
@@ -55,6 +67,12 @@ pub(crate) struct WorkspaceSession {
     pub(crate) next_id: AtomicU64,
     /// Callbacks for background threads - events for these threadIds are sent through the channel
     pub(crate) background_thread_callbacks: Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
+    /// Set by [`WorkspaceSession::close`]; the supervisor only restarts sessions still open.
+    pub(crate) closed: AtomicBool,
+    /// Started with `--oss`, so turns run on a model server on this machine.
+    pub(crate) local_models: bool,
+    /// Turns started on this process and not yet finished.
+    pub(crate) active_turns: Mutex<HashSet<String>>,
 }
 
 impl WorkspaceSession {
@@ -94,6 +112,48 @@ impl WorkspaceSession {
         self.write_message(json!({ "id": id, "result": result }))
             .await
     }
+
+    /// Stops the process for good. Killing the child directly looks like a crash and gets it
+    /// restarted.
+    pub(crate) async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = self.child.lock().await.kill().await;
+    }
+
+    /// Closes the process once its running turns finish, or after `max_wait` regardless. Used
+    /// for a session that was already replaced, so no new turns reach it.
+    pub(crate) async fn close_when_idle(&self, max_wait: Duration) {
+        let started = Instant::now();
+        while !self.active_turns.lock().await.is_empty() && started.elapsed() < max_wait {
+            tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+        }
+        self.close().await;
+    }
+}
+
+/// Keeps [`WorkspaceSession::active_turns`] in step with the turn notifications.
+async fn track_active_turn(session: &WorkspaceSession, value: &Value) {
+    let params = &value["params"];
+    let Some(turn_id) = params["turn"]["id"]
+        .as_str()
+        .or_else(|| params["turnId"].as_str())
+    else {
+        return;
+    };
+    match value["method"].as_str() {
+        Some("turn/started") => {
+            session
+                .active_turns
+                .lock()
+                .await
+                .insert(turn_id.to_string());
+        }
+        // Some failures end a turn without a `turn/completed`.
+        Some("turn/completed") | Some("error") if params["willRetry"].as_bool() != Some(true) => {
+            session.active_turns.lock().await.remove(turn_id);
+        }
+        _ => {}
+    }
 }
 
 pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
@@ -220,129 +280,132 @@ pub(crate) async fn check_codex_installation(
     Ok(if version.is_empty() { None } else { Some(version) })
 }
 
-pub(crate) async fn spawn_workspace_session<E: EventSink>(
-    entry: WorkspaceEntry,
-    default_codex_bin: Option<String>,
+/// How an app-server was started, so the supervisor can start it the same way again.
+#[derive(Clone)]
+struct AppServerLaunch {
+    codex_bin: Option<String>,
     codex_args: Option<String>,
+    cwd: String,
     codex_home: Option<PathBuf>,
-    client_version: String,
-    event_sink: E,
-) -> Result<Arc<WorkspaceSession>, String> {
-    let codex_bin = entry
-        .codex_bin
-        .clone()
-        .filter(|value| !value.trim().is_empty())
-        .or(default_codex_bin);
-    let _ = check_codex_installation(codex_bin.clone()).await?;
+}
 
-    let mut command = build_codex_command_with_bin(codex_bin);
-    apply_codex_args(&mut command, codex_args.as_deref())?;
-    command.current_dir(&entry.path);
-    command.arg("app-server");
-    if let Some(codex_home) = codex_home {
-        command.env("CODEX_HOME", codex_home);
+struct AppServerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    stderr: ChildStderr,
+}
+
+impl AppServerLaunch {
+    fn spawn(&self) -> Result<AppServerProcess, String> {
+        let mut command = build_codex_command_with_bin(self.codex_bin.clone());
+        apply_codex_args(&mut command, self.codex_args.as_deref())?;
+        command.current_dir(&self.cwd);
+        command.arg("app-server");
+        if let Some(codex_home) = &self.codex_home {
+            command.env("CODEX_HOME", codex_home);
+        }
+        command.stdin(std::process::Stdio::piped());
+        command.stdout(std::process::Stdio::piped());
+        command.stderr(std::process::Stdio::piped());
+
+        let mut child = command.spawn().map_err(|e| e.to_string())?;
+        let stdin = child.stdin.take().ok_or("missing stdin")?;
+        let stdout = child.stdout.take().ok_or("missing stdout")?;
+        let stderr = child.stderr.take().ok_or("missing stderr")?;
+        Ok(AppServerProcess {
+            child,
+            stdin,
+            stdout,
+            stderr,
+        })
     }
-    command.stdin(std::process::Stdio::piped());
-    command.stdout(std::process::Stdio::piped());
-    command.stderr(std::process::Stdio::piped());
+}
 
-    let mut child = command.spawn().map_err(|e| e.to_string())?;
-    let stdin = child.stdin.take().ok_or("missing stdin")?;
-    let stdout = child.stdout.take().ok_or("missing stdout")?;
-    let stderr = child.stderr.take().ok_or("missing stderr")?;
+async fn route_app_server_line<E: EventSink>(
+    session: &WorkspaceSession,
+    event_sink: &E,
+    line: &str,
+) {
+    let workspace_id = &session.entry.id;
+    if line.trim().is_empty() {
+        return;
+    }
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(err) => {
+            let payload = AppServerEvent {
+                workspace_id: workspace_id.clone(),
+                message: json!({
+                    "method": "codex/parseError",
+                    "params": { "error": err.to_string(), "raw": line },
+                }),
+            };
+            event_sink.emit_app_server_event(payload);
+            return;
+        }
+    };
 
-    let session = Arc::new(WorkspaceSession {
-        entry: entry.clone(),
-        child: Mutex::new(child),
-        stdin: Mutex::new(stdin),
-        pending: Mutex::new(HashMap::new()),
-        next_id: AtomicU64::new(1),
-        background_thread_callbacks: Mutex::new(HashMap::new()),
-    });
+    let maybe_id = value.get("id").and_then(|id| id.as_u64());
+    let has_method = value.get("method").is_some();
+    let has_result_or_error = value.get("result").is_some() || value.get("error").is_some();
 
-    let session_clone = Arc::clone(&session);
-    let workspace_id = entry.id.clone();
-    let event_sink_clone = event_sink.clone();
-    tokio::spawn(async move {
-        let mut lines = BufReader::new(stdout).lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            if line.trim().is_empty() {
-                continue;
-            }
-            let value: Value = match serde_json::from_str(&line) {
-                Ok(value) => value,
-                Err(err) => {
-                    let payload = AppServerEvent {
-                        workspace_id: workspace_id.clone(),
-                        message: json!({
-                            "method": "codex/parseError",
-                            "params": { "error": err.to_string(), "raw": line },
-                        }),
-                    };
-                    event_sink_clone.emit_app_server_event(payload);
-                    continue;
-                }
-            };
+    // Check if this event is for a background thread
+    let thread_id = extract_thread_id(&value);
+    if has_method && !has_result_or_error {
+        track_active_turn(session, &value).await;
+    }
 
-            let maybe_id = value.get("id").and_then(|id| id.as_u64());
-            let has_method = value.get("method").is_some();
-            let has_result_or_error = value.get("result").is_some() || value.get("error").is_some();
-
-            // Check if this event is for a background thread
-            let thread_id = extract_thread_id(&value);
-
-            if let Some(id) = maybe_id {
-                if has_result_or_error {
-                    if let Some(tx) = session_clone.pending.lock().await.remove(&id) {
-                        let _ = tx.send(value);
-                    }
-                } else if has_method {
-                    // Check for background thread callback
-                    let mut sent_to_background = false;
-                    if let Some(ref tid) = thread_id {
-                        let callbacks = session_clone.background_thread_callbacks.lock().await;
-                        if let Some(tx) = callbacks.get(tid) {
-                            let _ = tx.send(value.clone());
-                            sent_to_background = true;
-                        }
-                    }
-                    // Don't emit to frontend if this is a background thread event
-                    if !sent_to_background {
-                        let payload = AppServerEvent {
-                            workspace_id: workspace_id.clone(),
-                            message: value,
-                        };
-                        event_sink_clone.emit_app_server_event(payload);
-                    }
-                } else if let Some(tx) = session_clone.pending.lock().await.remove(&id) {
-                    let _ = tx.send(value);
-                }
-            } else if has_method {
-                // Check for background thread callback
-                let mut sent_to_background = false;
-                if let Some(ref tid) = thread_id {
-                    let callbacks = session_clone.background_thread_callbacks.lock().await;
-                    if let Some(tx) = callbacks.get(tid) {
-                        let _ = tx.send(value.clone());
-                        sent_to_background = true;
-                    }
-                }
-                // Don't emit to frontend if this is a background thread event
-                if !sent_to_background {
-                    let payload = AppServerEvent {
-                        workspace_id: workspace_id.clone(),
-                        message: value,
-                    };
-                    event_sink_clone.emit_app_server_event(payload);
+    if let Some(id) = maybe_id {
+        if has_result_or_error {
+            if let Some(tx) = session.pending.lock().await.remove(&id) {
+                let _ = tx.send(value);
+            }
+        } else if has_method {
+            // Check for background thread callback
+            let mut sent_to_background = false;
+            if let Some(ref tid) = thread_id {
+                let callbacks = session.background_thread_callbacks.lock().await;
+                if let Some(tx) = callbacks.get(tid) {
+                    let _ = tx.send(value.clone());
+                    sent_to_background = true;
                 }
             }
+            // Don't emit to frontend if this is a background thread event
+            if !sent_to_background {
+                let payload = AppServerEvent {
+                    workspace_id: workspace_id.clone(),
+                    message: value,
+                };
+                event_sink.emit_app_server_event(payload);
+            }
+        } else if let Some(tx) = session.pending.lock().await.remove(&id) {
+            let _ = tx.send(value);
         }
-    });
+    } else if has_method {
+        // Check for background thread callback
+        let mut sent_to_background = false;
+        if let Some(ref tid) = thread_id {
+            let callbacks = session.background_thread_callbacks.lock().await;
+            if let Some(tx) = callbacks.get(tid) {
+                let _ = tx.send(value.clone());
+                sent_to_background = true;
+            }
+        }
+        // Don't emit to frontend if this is a background thread event
+        if !sent_to_background {
+            let payload = AppServerEvent {
+                workspace_id: workspace_id.clone(),
+                message: value,
+            };
+            event_sink.emit_app_server_event(payload);
+        }
+    }
+}
 
-    let workspace_id = entry.id.clone();
-    let event_sink_clone = event_sink.clone();
+fn spawn_stderr_reader<E: EventSink>(workspace_id: String, stderr: ChildStderr, event_sink: E) {
     tokio::spawn(async move {
-        let mut lines = BufReader::new(stderr).lines();
+        let mut lines = lossy_lines(BufReader::new(stderr));
         while let Ok(Some(line)) = lines.next_line().await {
             if line.trim().is_empty() {
                 continue;
@@ -354,21 +417,24 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
                     "params": { "message": line },
                 }),
             };
-            event_sink_clone.emit_app_server_event(payload);
+            event_sink.emit_app_server_event(payload);
         }
     });
+}
 
-    let init_params = build_initialize_params(&client_version);
+async fn initialize_session(
+    session: &WorkspaceSession,
+    client_version: &str,
+) -> Result<(), String> {
+    let init_params = build_initialize_params(client_version);
     let init_result = timeout(
-        Duration::from_secs(15),
+        INITIALIZE_TIMEOUT,
         session.send_request("initialize", init_params),
     )
     .await;
     let init_response = match init_result {
         Ok(response) => response,
         Err(_) => {
-            let mut child = session.child.lock().await;
-            let _ = child.kill().await;
             return Err(
                 "Codex app-server did not respond to initialize. Check that `codex app-server` works in Terminal."
                     .to_string(),
@@ -376,16 +442,175 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
         }
     };
     init_response?;
-    session.send_notification("initialized", None).await?;
+    session.send_notification("initialized", None).await
+}
 
-    let payload = AppServerEvent {
-        workspace_id: entry.id.clone(),
+fn emit_connected<E: EventSink>(event_sink: &E, workspace_id: &str, restarted: bool) {
+    event_sink.emit_app_server_event(AppServerEvent {
+        workspace_id: workspace_id.to_string(),
         message: json!({
             "method": "codex/connected",
-            "params": { "workspaceId": entry.id.clone() }
+            "params": { "workspaceId": workspace_id }
         }),
+    });
+    emit_connection_state(
+        event_sink,
+        workspace_id,
+        json!({ "state": "connected", "restarted": restarted }),
+    );
+}
+
+/// `provider/connectionState` with `state` one of `connected`, `reconnecting` or `failed`.
+fn emit_connection_state<E: EventSink>(event_sink: &E, workspace_id: &str, mut params: Value) {
+    params["workspaceId"] = json!(workspace_id);
+    params["provider"] = json!("codex");
+    event_sink.emit_app_server_event(AppServerEvent {
+        workspace_id: workspace_id.to_string(),
+        message: json!({ "method": "provider/connectionState", "params": params }),
+    });
+}
+
+fn restart_delay(attempt: u32) -> Duration {
+    RESTART_BASE_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(RESTART_MAX_DELAY)
+}
+
+/// Reads the app-server's output for as long as the session lives. When the process exits
+/// without [`WorkspaceSession::close`], it is started again with backoff and initialized anew;
+/// after `MAX_RESTART_ATTEMPTS` failed restarts in a row the workspace is reported `failed`.
+async fn supervise_app_server<E: EventSink>(
+    session: Arc<WorkspaceSession>,
+    mut stdout: ChildStdout,
+    launch: AppServerLaunch,
+    client_version: String,
+    event_sink: E,
+) {
+    let workspace_id = session.entry.id.clone();
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let mut lines = lossy_lines(BufReader::new(stdout));
+        while let Ok(Some(line)) = lines.next_line().await {
+            route_app_server_line(&session, &event_sink, &line).await;
+        }
+        // Requests to the dead process would never be answered; dropping their senders fails
+        // them with "request canceled".
+        session.pending.lock().await.clear();
+        session.background_thread_callbacks.lock().await.clear();
+        session.active_turns.lock().await.clear();
+        if session.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        let _ = session.child.lock().await.kill().await;
+        if started.elapsed() >= STABLE_UPTIME {
+            attempt = 0;
+        }
+
+        let mut error: Option<String> = None;
+        stdout = loop {
+            attempt += 1;
+            if attempt > MAX_RESTART_ATTEMPTS {
+                emit_connection_state(
+                    &event_sink,
+                    &workspace_id,
+                    json!({ "state": "failed", "error": error }),
+                );
+                return;
+            }
+            let delay = restart_delay(attempt);
+            emit_connection_state(
+                &event_sink,
+                &workspace_id,
+                json!({
+                    "state": "reconnecting",
+                    "attempt": attempt,
+                    "maxAttempts": MAX_RESTART_ATTEMPTS,
+                    "retryInMs": delay.as_millis() as u64,
+                    "error": error,
+                }),
+            );
+            tokio::time::sleep(delay).await;
+            if session.closed.load(Ordering::SeqCst) {
+                return;
+            }
+            match launch.spawn() {
+                Ok(process) => {
+                    *session.child.lock().await = process.child;
+                    *session.stdin.lock().await = process.stdin;
+                    spawn_stderr_reader(workspace_id.clone(), process.stderr, event_sink.clone());
+                    break process.stdout;
+                }
+                Err(spawn_error) => error = Some(spawn_error),
+            }
+        };
+
+        // The handshake needs this task reading the new process's output, so it runs apart.
+        // If it fails the process is killed and the loop above tries again.
+        let restarted = Arc::clone(&session);
+        let client_version = client_version.clone();
+        let event_sink = event_sink.clone();
+        tokio::spawn(async move {
+            match initialize_session(&restarted, &client_version).await {
+                Ok(()) => emit_connected(&event_sink, &restarted.entry.id, true),
+                Err(_) => {
+                    let _ = restarted.child.lock().await.kill().await;
+                }
+            }
+        });
+    }
+}
+
+pub(crate) async fn spawn_workspace_session<E: EventSink>(
+    entry: WorkspaceEntry,
+    default_codex_bin: Option<String>,
+    codex_args: Option<String>,
+    codex_home: Option<PathBuf>,
+    client_version: String,
+    event_sink: E,
+) -> Result<Arc<WorkspaceSession>, String> {
+    let codex_bin = entry
+        .codex_bin
+        .clone()
+        .filter(|value| !value.trim().is_empty())
+        .or(default_codex_bin);
+    let _ = check_codex_installation(codex_bin.clone()).await?;
+    let local_models = resource_guard_core::uses_local_models(codex_args.as_deref(), &[]);
+
+    let launch = AppServerLaunch {
+        codex_bin,
+        codex_args,
+        cwd: entry.path.clone(),
+        codex_home,
     };
-    event_sink.emit_app_server_event(payload);
+    let process = launch.spawn()?;
+
+    let session = Arc::new(WorkspaceSession {
+        entry: entry.clone(),
+        child: Mutex::new(process.child),
+        stdin: Mutex::new(process.stdin),
+        pending: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(1),
+        background_thread_callbacks: Mutex::new(HashMap::new()),
+        closed: AtomicBool::new(false),
+        local_models,
+        active_turns: Mutex::new(HashSet::new()),
+    });
+
+    tokio::spawn(supervise_app_server(
+        Arc::clone(&session),
+        process.stdout,
+        launch,
+        client_version.clone(),
+        event_sink.clone(),
+    ));
+    spawn_stderr_reader(entry.id.clone(), process.stderr, event_sink.clone());
+
+    if let Err(error) = initialize_session(&session, &client_version).await {
+        session.close().await;
+        return Err(error);
+    }
+    emit_connected(&event_sink, &entry.id, false);
 
     Ok(session)
 }
@@ -401,16 +626,15 @@ pub(crate) async fn spawn_passthrough_workspace_session(
 
     let mut child = command.spawn().map_err(|e| e.to_string())?;
     let stdin = child.stdin.take().ok_or("missing stdin")?;
-    let stdout = child.stdout.take().ok_or("missing stdout")?;
-    let stderr = child.stderr.take().ok_or("missing stderr")?;
+    let mut stdout = child.stdout.take().ok_or("missing stdout")?;
+    let mut stderr = child.stderr.take().ok_or("missing stderr")?;
 
+    // Drained as bytes; the output is never read, it only must not fill the pipes.
     tokio::spawn(async move {
-        let mut lines = BufReader::new(stdout).lines();
-        while let Ok(Some(_line)) = lines.next_line().await {}
+        let _ = tokio::io::copy(&mut stdout, &mut tokio::io::sink()).await;
     });
     tokio::spawn(async move {
-        let mut lines = BufReader::new(stderr).lines();
-        while let Ok(Some(_line)) = lines.next_line().await {}
+        let _ = tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await;
     });
 
     Ok(Arc::new(WorkspaceSession {
@@ -420,13 +644,17 @@ pub(crate) async fn spawn_passthrough_workspace_session(
         pending: Mutex::new(HashMap::new()),
         next_id: AtomicU64::new(1),
         background_thread_callbacks: Mutex::new(HashMap::new()),
+        closed: AtomicBool::new(false),
+        local_models: false,
+        active_turns: Mutex::new(HashSet::new()),
     }))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{build_initialize_params, extract_thread_id};
+    use super::{build_initialize_params, extract_thread_id, restart_delay};
     use serde_json::json;
+    use std::time::Duration;
 
     #[test]
     fn extract_thread_id_reads_camel_case() {
@@ -457,4 +685,16 @@ mod tests {
             Some(true)
         );
     }
+
+    #[test]
+    fn restart_delay_doubles_up_to_the_cap() {
+        let delays = [1, 2, 3, 5, 6, 40]
+            .into_iter()
+            .map(restart_delay)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1, 2, 4, 16, 30, 30].map(Duration::from_secs).to_vec()
+        );
+    }
 }
@@ -62,10 +62,11 @@ use std::fs::File;
 use std::io::Read;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use ignore::WalkBuilder;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, Mutex};
 
@@ -74,8 +75,20 @@ use backend::app_server::{
 };
 use backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
 use shared::codex_core::CodexLoginCancelState;
+use shared::context_files_core::{compose_message_with_context, SelectionContextBlock};
+use shared::redaction_core::RedactionPipeline;
 use shared::{
-    claude_core, codex_core, files_core, git_core, settings_core, workspaces_core, worktree_core,
+    actions_core, answer_diff_core, audit_core, claude_core, claude_follow_core, claude_hooks_core,
+    claude_pool_core, codex_core, codex_import_core, commit_message_core, data_dir_core,
+    drafts_core, event_filter_core, event_mirror_core, external_import_core, files_core,
+    gemini_core, git_core, idle_summary_core, inbox_core, lan_sync_core, memory_core, orphans_core,
+    patches_core, playbooks_core, prompt_estimate_core, prompt_history_core, provider_detect_core,
+    quick_reply_core, rate_limit_core, render_cache_core, retention_core, secrets_core,
+    settings_core, setup_core, shutdown_core, status_summary_core, thread_edit_core,
+    thread_export_core, thread_inheritance_core, thread_locks_core, thread_quick_find_core,
+    thread_rebind_core, thread_sync_core, thread_worktree_core, transcription_core, trash_core,
+    turn_artifacts_core, turn_details_core, turn_slots_core, turn_snapshot_core,
+    workspace_scan_core, workspaces_core, worktree_core,
 };
 use storage::{read_settings, read_workspaces};
 use types::{
@@ -160,6 +173,10 @@ struct DaemonConfig {
     listen: SocketAddr,
     token: Option<String>,
     data_dir: PathBuf,
+    /// The data dir sits next to the executable.
+    portable: bool,
+    /// Serve a single JSON-RPC session over stdin/stdout instead of listening on TCP.
+    stdio: bool,
 }
 
 struct DaemonState {
@@ -174,6 +191,24 @@ struct DaemonState {
     claude_threads_path: PathBuf,
     claude_threads: claude_core::ClaudeThreadsStore,
     claude_turn_cancels: claude_core::ClaudeTurnCancelsStore,
+    claude_follows: claude_follow_core::ClaudeFollowsStore,
+    claude_processes: claude_pool_core::ClaudeProcessPool,
+    claude_approvals: claude_pool_core::ClaudeApprovalsStore,
+    turn_slots: turn_slots_core::TurnSlotsStore,
+    thread_locks: thread_locks_core::ThreadLocksStore,
+    status: status_summary_core::StatusTrackerStore,
+    rate_limits: rate_limit_core::RateLimitsStore,
+    gemini_models: gemini_core::GeminiModelsCache,
+    render_cache: render_cache_core::RenderCacheStore,
+    playbooks_path: PathBuf,
+    playbook_runs: playbooks_core::PlaybookRunsStore,
+    drafts_path: PathBuf,
+    drafts: drafts_core::DraftsStore,
+    prompt_history_path: PathBuf,
+    prompt_history: prompt_history_core::PromptHistoryStore,
+    lan_sync: lan_sync_core::LanSyncState,
+    /// `subscribe_events` filters, keyed by connection.
+    event_filters: event_filter_core::EventFiltersStore,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -276,9 +311,18 @@ impl DaemonState {
         let settings_path = config.data_dir.join("settings.json");
         let claude_threads_path = claude_core::claude_threads_path(&config.data_dir);
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
-        let app_settings = read_settings(&settings_path).unwrap_or_default();
-        let claude_threads = claude_core::read_threads_snapshot(&claude_threads_path)
+        let mut app_settings = read_settings(&settings_path).unwrap_or_default();
+        app_settings.data_dir = Some(config.data_dir.to_string_lossy().to_string());
+        app_settings.portable_mode = config.portable;
+        let mut claude_threads = claude_core::read_threads_snapshot(&claude_threads_path)
             .unwrap_or_default();
+        shutdown_core::recover_after_unclean_shutdown(&config.data_dir, &mut claude_threads);
+        thread_quick_find_core::index_snapshot(&claude_threads);
+        let drafts_path = drafts_core::drafts_path(&config.data_dir);
+        let drafts = drafts_core::read_drafts(&drafts_path).unwrap_or_default();
+        let prompt_history_path = prompt_history_core::prompt_history_path(&config.data_dir);
+        let prompt_history =
+            prompt_history_core::read_prompt_history(&prompt_history_path).unwrap_or_default();
         Self {
             data_dir: config.data_dir.clone(),
             workspaces: Mutex::new(workspaces),
@@ -291,7 +335,41 @@ impl DaemonState {
             claude_threads_path,
             claude_threads: Arc::new(Mutex::new(claude_threads)),
             claude_turn_cancels: Arc::new(Mutex::new(HashMap::new())),
-        }
+            claude_follows: Arc::new(Mutex::new(HashMap::new())),
+            claude_processes: Arc::new(Mutex::new(HashMap::new())),
+            claude_approvals: Arc::new(Mutex::new(HashMap::new())),
+            turn_slots: Default::default(),
+            thread_locks: Default::default(),
+            status: Default::default(),
+            rate_limits: Default::default(),
+            gemini_models: Arc::new(Mutex::new(None)),
+            render_cache: Default::default(),
+            playbooks_path: playbooks_core::playbooks_path(&config.data_dir),
+            playbook_runs: Arc::new(Mutex::new(HashMap::new())),
+            drafts_path,
+            drafts: Arc::new(Mutex::new(drafts)),
+            prompt_history_path,
+            prompt_history: Arc::new(Mutex::new(prompt_history)),
+            lan_sync: Mutex::new(None),
+            event_filters: Default::default(),
+        }
+    }
+
+    async fn shutdown(&self) {
+        let report = shutdown_core::shutdown_core(
+            &self.data_dir,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            &self.claude_turn_cancels,
+            &self.claude_processes,
+            &self.sessions,
+        )
+        .await;
+        eprintln!(
+            "codex-monitor-daemon shut down ({} turn(s) canceled, {} detached, {} process(es) \
+             stopped)",
+            report.canceled_turns, report.detached_turns, report.killed_processes
+        );
     }
 
     async fn list_workspaces(&self) -> Vec<WorkspaceInfo> {
@@ -302,6 +380,24 @@ impl DaemonState {
         workspaces_core::is_workspace_path_dir_core(&path)
     }
 
+    async fn scan_workspaces(
+        &self,
+        root: Option<String>,
+        max_depth: Option<u32>,
+    ) -> Result<Value, String> {
+        workspace_scan_core::scan_workspaces_core(
+            &self.workspaces,
+            &self.app_settings,
+            root,
+            max_depth,
+        )
+        .await
+    }
+
+    async fn setup_probe(&self) -> Result<Value, String> {
+        setup_core::setup_probe_core(&self.workspaces, &self.app_settings).await
+    }
+
     async fn add_workspace(
         &self,
         path: String,
@@ -395,6 +491,27 @@ impl DaemonState {
         .await
     }
 
+    async fn secrets_set(
+        &self,
+        workspace_id: String,
+        name: String,
+        value: String,
+    ) -> Result<Value, String> {
+        secrets_core::set_secret_core(
+            &self.workspaces,
+            &self.storage_path,
+            workspace_id,
+            name,
+            value,
+        )
+        .await
+    }
+
+    async fn secrets_delete(&self, workspace_id: String, name: String) -> Result<Value, String> {
+        secrets_core::delete_secret_core(&self.workspaces, &self.storage_path, workspace_id, name)
+            .await
+    }
+
     async fn remove_workspace(&self, id: String) -> Result<(), String> {
         workspaces_core::remove_workspace_core(
             id,
@@ -595,6 +712,26 @@ impl DaemonState {
         .await
     }
 
+    async fn apply_settings_change(&self, change: settings_core::SettingsChange) {
+        let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+        workspaces_core::apply_settings_change_core(
+            &change,
+            &self.workspaces,
+            &self.sessions,
+            |entry, default_bin, codex_args, codex_home| {
+                spawn_with_client(
+                    self.event_sink.clone(),
+                    client_version.clone(),
+                    entry,
+                    default_bin,
+                    codex_args,
+                    codex_home,
+                )
+            },
+        )
+        .await;
+    }
+
     async fn get_app_settings(&self) -> AppSettings {
         settings_core::get_app_settings_core(&self.app_settings).await
     }
@@ -645,8 +782,11 @@ impl DaemonState {
     }
 
     async fn start_thread(&self, workspace_id: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Starting threads").await?;
         match self.workspace_provider(&workspace_id).await? {
-            ProviderKind::Codex => codex_core::start_thread_core(&self.sessions, workspace_id).await,
+            ProviderKind::Codex => {
+                codex_core::start_thread_core(&self.sessions, &self.workspaces, workspace_id).await
+            }
             ProviderKind::Claude => {
                 claude_core::start_thread_core(
                     &self.workspaces,
@@ -666,6 +806,8 @@ impl DaemonState {
         &self,
         workspace_id: String,
         thread_id: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
     ) -> Result<Value, String> {
         let provider = self.thread_provider(&workspace_id, &thread_id).await?;
         match provider {
@@ -675,9 +817,24 @@ impl DaemonState {
                 Ok(with_thread_provider(response, &ProviderKind::Codex))
             }
             ProviderKind::Claude => {
-                let response =
-                    claude_core::resume_thread_core(&self.claude_threads, workspace_id, thread_id)
-                        .await?;
+                let mut response = claude_core::resume_thread_core(
+                    &self.claude_threads,
+                    &self.render_cache,
+                    workspace_id.clone(),
+                    thread_id.clone(),
+                    cursor,
+                    limit,
+                )
+                .await?;
+                thread_rebind_core::attach_cwd_repair(
+                    &mut response,
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &workspace_id,
+                    &thread_id,
+                )
+                .await;
                 Ok(with_thread_provider(response, &ProviderKind::Claude))
             }
             ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
@@ -685,6 +842,7 @@ impl DaemonState {
     }
 
     async fn fork_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Forking threads").await?;
         codex_core::fork_thread_core(&self.sessions, workspace_id, thread_id).await
     }
 
@@ -696,28 +854,26 @@ impl DaemonState {
     ) -> Result<Value, String> {
         let _ = cursor;
         let _ = limit;
-        let workspace_path = self.workspace_path(&workspace_id).await?;
         let fetch_limit = Some(100);
 
         let codex_response =
             codex_core::list_threads_core(&self.sessions, workspace_id.clone(), None, fetch_limit)
                 .await
                 .ok();
-        let claude_response = claude_core::list_threads_core(
-            &self.claude_threads,
-            &self.claude_threads_path,
-            workspace_id,
-            workspace_path,
-            None,
-            fetch_limit,
-        )
-        .await
-        .ok();
+        let claude_response =
+            claude_core::list_threads_core(&self.claude_threads, workspace_id, None, fetch_limit)
+                .await
+                .ok();
 
         if codex_response.is_none() && claude_response.is_none() {
             return Err("failed to list threads for codex and claude providers".to_string());
         }
 
+        // Only Claude threads track reads; the count covers all of them, not just this page.
+        let unread_count = claude_response
+            .as_ref()
+            .and_then(|response| response["result"]["unreadCount"].as_u64())
+            .unwrap_or(0);
         let mut data = Vec::<Value>::new();
         if let Some(response) = codex_response {
             data.extend(thread_list_entries_with_provider(response, &ProviderKind::Codex));
@@ -731,10 +887,111 @@ impl DaemonState {
             "result": {
                 "data": data,
                 "nextCursor": Value::Null,
+                "unreadCount": unread_count,
             }
         }))
     }
 
+    async fn list_worktree_threads(&self, workspace_id: String) -> Result<Value, String> {
+        let parent_id =
+            thread_inheritance_core::worktree_parent_id(&self.workspaces, &workspace_id).await?;
+        let own = self.list_threads(workspace_id.clone(), None, None).await?;
+        let parent = match parent_id {
+            Some(parent_id) => {
+                let threads = self.list_threads(parent_id.clone(), None, None).await?;
+                Some((parent_id, threads))
+            }
+            None => None,
+        };
+        Ok(thread_inheritance_core::inherited_threads_core(
+            &workspace_id,
+            own,
+            parent,
+        ))
+    }
+
+    async fn threads_sync(&self, workspace_id: String) -> Result<Value, String> {
+        let workspace_path = self.workspace_path(&workspace_id).await?;
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        thread_sync_core::sync_threads_core(
+            &self.app_settings,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            &workspace_id,
+            &workspace_path,
+            &redaction,
+            &self.event_sink,
+        )
+        .await
+    }
+
+    async fn sync_all_threads(&self) -> Result<(), String> {
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        thread_sync_core::sync_all_workspaces_core(
+            &self.app_settings,
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            &redaction,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    fn threads_quick_find(
+        &self,
+        workspace_id: Option<String>,
+        query: String,
+        limit: Option<u32>,
+    ) -> Result<Value, String> {
+        thread_quick_find_core::quick_find_core(workspace_id.as_deref(), &query, limit)
+    }
+
+    async fn inbox_list(&self, limit: Option<u32>) -> Result<Value, String> {
+        inbox_core::inbox_list_core(
+            &self.workspaces,
+            &self.sessions,
+            &self.claude_threads,
+            limit,
+        )
+        .await
+    }
+
+    async fn lan_sync_start(&self) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "LAN sync").await?;
+        lan_sync_core::lan_sync_start_core(
+            &self.lan_sync,
+            &self.app_settings,
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn lan_sync_stop(&self) -> Result<Value, String> {
+        lan_sync_core::lan_sync_stop_core(&self.lan_sync).await
+    }
+
+    async fn lan_sync_discover(&self, timeout_ms: Option<u64>) -> Result<Value, String> {
+        lan_sync_core::lan_sync_discover_core(&self.lan_sync, timeout_ms).await
+    }
+
+    async fn lan_sync_with_peer(&self, address: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "LAN sync").await?;
+        lan_sync_core::lan_sync_with_peer_core(
+            &self.lan_sync,
+            &self.app_settings,
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            address,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
     async fn list_mcp_server_status(
         &self,
         workspace_id: String,
@@ -756,6 +1013,7 @@ impl DaemonState {
         workspace_id: String,
         thread_id: String,
     ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Archiving threads").await?;
         match self.thread_provider(&workspace_id, &thread_id).await? {
             ProviderKind::Codex => {
                 codex_core::archive_thread_core(&self.sessions, workspace_id, thread_id).await
@@ -766,6 +1024,7 @@ impl DaemonState {
                     &self.claude_threads_path,
                     workspace_id,
                     thread_id,
+                    self.event_sink.clone(),
                 )
                 .await
             }
@@ -773,91 +1032,1086 @@ impl DaemonState {
         }
     }
 
-    async fn compact_thread(
+    async fn bulk_thread_op(
         &self,
         workspace_id: String,
-        thread_id: String,
+        thread_ids: Vec<String>,
+        op: String,
+        tags: Option<Vec<String>>,
     ) -> Result<Value, String> {
-        codex_core::compact_thread_core(&self.sessions, workspace_id, thread_id).await
+        if op.trim() != "export" {
+            settings_core::ensure_not_read_only(&self.app_settings, "Bulk thread changes").await?;
+        }
+        let op = claude_core::BulkThreadOp::parse(&op, tags)?;
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        claude_core::bulk_thread_op_core(
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            thread_ids,
+            op,
+            &redaction,
+            self.event_sink.clone(),
+        )
+        .await
     }
 
-    async fn set_thread_name(
+    async fn list_trash(&self, workspace_id: Option<String>) -> Result<Value, String> {
+        trash_core::list_trash_core(&self.claude_threads_path, workspace_id).await
+    }
+
+    async fn restore_trash(
+        &self,
+        workspace_id: String,
+        thread_ids: Vec<String>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Restoring threads").await?;
+        trash_core::restore_trash_core(
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            thread_ids,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn actions_list(&self, query: Option<String>) -> Result<Value, String> {
+        let read_only = self.app_settings.lock().await.read_only;
+        Ok(actions_core::actions_list_core(query, read_only))
+    }
+
+    fn provider_rate_limit_state(&self) -> Value {
+        rate_limit_core::rate_limit_state_core(&self.rate_limits, claude_core::now_ms())
+    }
+
+    async fn status_summary(&self) -> Result<Value, String> {
+        status_summary_core::status_summary_core(
+            &self.status,
+            self.turn_slots.queued(),
+            claude_core::now_ms(),
+        )
+        .await
+    }
+
+    async fn orphans_list(&self) -> Result<Value, String> {
+        orphans_core::list_orphans_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            &self.drafts,
+        )
+        .await
+    }
+
+    async fn orphans_purge(&self, workspace_ids: Vec<String>) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Purging orphaned data").await?;
+        orphans_core::purge_orphans_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            &self.drafts,
+            &self.drafts_path,
+            workspace_ids,
+        )
+        .await
+    }
+
+    async fn import_codex_sessions(&self, workspace_id: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Importing Codex sessions").await?;
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        codex_import_core::import_codex_sessions_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            &redaction,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn import_external(
+        &self,
+        workspace_id: String,
+        format: String,
+        path: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Importing threads").await?;
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        external_import_core::import_external_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            format,
+            path,
+            &redaction,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn export_threads(
+        &self,
+        workspace_id: String,
+        thread_ids: Option<Vec<String>>,
+    ) -> Result<Value, String> {
+        let redaction = RedactionPipeline::compile(&self.app_settings.lock().await.redaction_rules);
+        thread_export_core::export_threads_core(
+            &self.claude_threads,
+            workspace_id,
+            thread_ids,
+            &redaction,
+        )
+        .await
+    }
+
+    async fn import_threads(&self, workspace_id: String, path: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Importing threads").await?;
+        thread_export_core::import_threads_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            path,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn merge_thread_worktree(
         &self,
         workspace_id: String,
         thread_id: String,
-        name: String,
+        remove_worktree: bool,
     ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Merging thread worktrees").await?;
         match self.thread_provider(&workspace_id, &thread_id).await? {
-            ProviderKind::Codex => {
-                codex_core::set_thread_name_core(&self.sessions, workspace_id, thread_id, name)
-                    .await
-            }
             ProviderKind::Claude => {
-                claude_core::set_thread_name_core(
+                thread_worktree_core::merge_thread_worktree_core(
+                    &self.workspaces,
                     &self.claude_threads,
                     &self.claude_threads_path,
                     workspace_id,
                     thread_id,
-                    name,
+                    remove_worktree,
+                    self.event_sink.clone(),
                 )
                 .await
             }
-            ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+            provider => Err(format!(
+                "Provider `{}` does not support thread worktrees.",
+                provider.as_str()
+            )),
         }
     }
 
-    async fn send_user_message(
+    async fn revert_turn_changes(
         &self,
         workspace_id: String,
         thread_id: String,
-        text: String,
-        model: Option<String>,
-        effort: Option<String>,
-        access_mode: Option<String>,
-        images: Option<Vec<String>>,
-        collaboration_mode: Option<Value>,
+        turn_id: String,
     ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Reverting turn changes").await?;
         match self.thread_provider(&workspace_id, &thread_id).await? {
-            ProviderKind::Codex => {
-                codex_core::send_user_message_core(
-                    &self.sessions,
-                    workspace_id,
-                    thread_id,
-                    text,
-                    model,
-                    effort,
-                    access_mode,
-                    images,
-                    collaboration_mode,
-                )
-                .await
-            }
             ProviderKind::Claude => {
-                let _ = model;
-                let _ = effort;
-                let _ = access_mode;
-                let _ = collaboration_mode;
-                claude_core::send_user_message_core(
-                    &self.workspaces,
-                    &self.app_settings,
+                turn_snapshot_core::revert_turn_changes_core(
                     &self.claude_threads,
                     &self.claude_turn_cancels,
-                    &self.claude_threads_path,
                     workspace_id,
                     thread_id,
-                    text,
-                    images,
-                    self.event_sink.clone(),
+                    turn_id,
                 )
                 .await
             }
-            ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+            provider => Err(format!(
+                "Provider `{}` does not support turn snapshots.",
+                provider.as_str()
+            )),
         }
     }
 
-    async fn turn_interrupt(
-        &self,
-        workspace_id: String,
+    async fn memory_read(&self, workspace_id: String) -> Result<Value, String> {
+        memory_core::memory_read_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn memory_write(&self, workspace_id: String, content: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing memory").await?;
+        memory_core::memory_write_core(&self.workspaces, workspace_id, content).await
+    }
+
+    async fn memory_append(
+        &self,
+        workspace_id: String,
+        text: Option<String>,
+        thread_id: Option<String>,
+        turn_id: Option<String>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing memory").await?;
+        memory_core::memory_append_core(
+            &self.workspaces,
+            &self.claude_threads,
+            workspace_id,
+            text,
+            thread_id,
+            turn_id,
+        )
+        .await
+    }
+
+    async fn memory_clear(&self, workspace_id: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing memory").await?;
+        memory_core::memory_clear_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn claude_hooks_read(&self, workspace_id: String) -> Result<Value, String> {
+        claude_hooks_core::claude_hooks_read_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn claude_hooks_write(
+        &self,
+        workspace_id: String,
+        hooks: claude_hooks_core::HooksConfig,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing Claude hooks").await?;
+        claude_hooks_core::claude_hooks_write_core(&self.workspaces, workspace_id, hooks).await
+    }
+
+    async fn save_draft(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        text: String,
+        images: Option<Vec<String>>,
+    ) -> Result<Value, String> {
+        drafts_core::save_draft_core(
+            &self.app_settings,
+            &self.drafts,
+            &self.drafts_path,
+            workspace_id,
+            thread_id,
+            text,
+            images,
+        )
+        .await
+    }
+
+    async fn get_draft(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
+        drafts_core::get_draft_core(&self.drafts, workspace_id, thread_id).await
+    }
+
+    async fn prompt_estimate(
+        &self,
+        workspace_id: String,
+        text: String,
+        images: Option<Vec<String>>,
+        model: Option<String>,
+    ) -> Result<Value, String> {
+        prompt_estimate_core::estimate_prompt_core(
+            &self.workspaces,
+            &self.app_settings,
+            workspace_id,
+            text,
+            images,
+            model,
+        )
+        .await
+    }
+
+    async fn list_playbooks(&self) -> Result<Value, String> {
+        playbooks_core::list_playbooks_core(&self.playbooks_path).await
+    }
+
+    async fn save_playbook(
+        &self,
+        id: Option<String>,
+        name: String,
+        steps: Vec<playbooks_core::PlaybookStep>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Saving playbooks").await?;
+        playbooks_core::save_playbook_core(&self.playbooks_path, id, name, steps).await
+    }
+
+    async fn delete_playbook(&self, id: String) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Deleting playbooks").await?;
+        playbooks_core::delete_playbook_core(&self.playbooks_path, id).await
+    }
+
+    fn playbook_context(&self) -> playbooks_core::PlaybookTurnContext<'_> {
+        playbooks_core::PlaybookTurnContext {
+            workspaces: &self.workspaces,
+            app_settings: &self.app_settings,
+            claude_threads: &self.claude_threads,
+            claude_turn_cancels: &self.claude_turn_cancels,
+            claude_processes: &self.claude_processes,
+            claude_approvals: &self.claude_approvals,
+            turn_slots: &self.turn_slots,
+            thread_locks: &self.thread_locks,
+            rate_limits: &self.rate_limits,
+            claude_threads_path: &self.claude_threads_path,
+            playbook_runs: &self.playbook_runs,
+        }
+    }
+
+    async fn run_playbook(
+        self: Arc<Self>,
+        workspace_id: String,
+        thread_id: String,
+        playbook_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Running playbooks").await?;
+        let provider = self.thread_provider(&workspace_id, &thread_id).await?;
+        if !matches!(provider, ProviderKind::Claude) {
+            return Err(format!(
+                "Provider `{}` does not support playbooks.",
+                provider.as_str()
+            ));
+        }
+        let run = playbooks_core::start_playbook_run_core(
+            &self.playbooks_path,
+            &self.playbook_runs,
+            workspace_id,
+            thread_id,
+            playbook_id,
+        )
+        .await?;
+        let summary = run.summary();
+        tokio::spawn(async move {
+            playbooks_core::run_playbook_core(
+                self.playbook_context(),
+                run,
+                self.event_sink.clone(),
+            )
+            .await;
+        });
+        Ok(summary)
+    }
+
+    async fn respond_playbook_step(&self, run_id: String, approved: bool) -> Result<Value, String> {
+        playbooks_core::respond_playbook_step_core(&self.playbook_runs, run_id, approved).await
+    }
+
+    async fn cancel_playbook(&self, run_id: String) -> Result<Value, String> {
+        playbooks_core::cancel_playbook_core(&self.playbook_runs, &self.claude_turn_cancels, run_id)
+            .await
+    }
+
+    async fn respond_to_approval(
+        &self,
+        approval_id: String,
+        allow: bool,
+        message: Option<String>,
+    ) -> Result<Value, String> {
+        claude_pool_core::respond_to_approval_core(
+            &self.claude_approvals,
+            approval_id,
+            allow,
+            message,
+        )
+        .await
+    }
+
+    async fn storage_usage(&self) -> Result<Value, String> {
+        retention_core::storage_usage_core(
+            &self.workspaces,
+            &self.claude_threads,
+            &self.claude_threads_path,
+        )
+        .await
+    }
+
+    async fn storage_migrate(&self, path: Option<String>, portable: bool) -> Result<Value, String> {
+        data_dir_core::migrate_data_dir_core(
+            &self.app_settings,
+            &default_data_dir(),
+            &self.data_dir,
+            path,
+            portable,
+        )
+        .await
+    }
+
+    /// The daemon does not restart itself; the new profile is used from its next start.
+    async fn profile_switch(&self, name: String) -> Result<Value, String> {
+        if shutdown_core::running_turn_count(&self.claude_turn_cancels, &self.sessions).await > 0 {
+            return Err("Wait for running turns to finish before switching profiles.".to_string());
+        }
+        data_dir_core::switch_profile_core(
+            &self.app_settings,
+            &default_data_dir(),
+            &self.data_dir,
+            name,
+        )
+        .await
+    }
+
+    async fn apply_retention(&self) -> Result<Value, String> {
+        retention_core::apply_retention_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn summarize_idle_threads(&self) -> Result<Value, String> {
+        idle_summary_core::summarize_idle_threads_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn compact_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Compacting threads").await?;
+        codex_core::compact_thread_core(&self.sessions, workspace_id, thread_id).await
+    }
+
+    async fn set_thread_name(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        name: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Renaming threads").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Codex => {
+                codex_core::set_thread_name_core(&self.sessions, workspace_id, thread_id, name)
+                    .await
+            }
+            ProviderKind::Claude => {
+                claude_core::set_thread_name_core(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    name,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+        }
+    }
+
+    async fn mark_thread_read(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::mark_read_core(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not track read state.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn set_item_pinned(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        item_id: String,
+        pinned: bool,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Pinning messages").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::set_item_pinned_core(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    item_id,
+                    pinned,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support pinned messages.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn thread_pinned_items(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::pinned_items_core(&self.claude_threads, workspace_id, thread_id).await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support pinned messages.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn diff_items(
+        &self,
+        workspace_id: String,
+        left_thread_id: String,
+        left_item_id: String,
+        right_thread_id: String,
+        right_item_id: String,
+    ) -> Result<Value, String> {
+        for thread_id in [&left_thread_id, &right_thread_id] {
+            let provider = self.thread_provider(&workspace_id, thread_id).await?;
+            if provider != ProviderKind::Claude {
+                return Err(format!(
+                    "Provider `{}` does not keep messages to compare.",
+                    provider.as_str()
+                ));
+            }
+        }
+        answer_diff_core::diff_items_core(
+            &self.claude_threads,
+            workspace_id,
+            left_thread_id,
+            left_item_id,
+            right_thread_id,
+            right_item_id,
+        )
+        .await
+    }
+
+    async fn duplicate_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Duplicating threads").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::duplicate_thread_core(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support duplicating threads.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn move_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        target_workspace_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Moving threads").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::move_thread_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    target_workspace_id,
+                    thread_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support moving threads between workspaces.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn rebind_thread_workspace(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        target_workspace_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Rebinding threads").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                thread_rebind_core::rebind_thread_workspace_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    target_workspace_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support rebinding threads to workspaces.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn copy_thread(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        target_workspace_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Copying threads").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::copy_thread_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    target_workspace_id,
+                    thread_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support copying threads between workspaces.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn set_thread_cwd(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        cwd: Option<String>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Changing thread directories")
+            .await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::set_thread_cwd_core(
+                    &self.workspaces,
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    cwd,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support thread directories.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn set_thread_generation(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        model: Option<String>,
+        temperature: Option<f64>,
+        max_tokens: Option<u32>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Changing generation settings")
+            .await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::set_thread_generation_core(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    model,
+                    temperature,
+                    max_tokens,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support generation settings.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn turn_patches(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        turn_id: String,
+    ) -> Result<Value, String> {
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                patches_core::turn_patches_core(
+                    &self.claude_threads,
+                    workspace_id,
+                    thread_id,
+                    turn_id,
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support patch extraction.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn apply_patch(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        turn_id: String,
+        patch_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Applying patches").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                patches_core::apply_patch_core(
+                    &self.workspaces,
+                    &self.claude_threads,
+                    workspace_id,
+                    thread_id,
+                    turn_id,
+                    patch_id,
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support patch extraction.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn send_user_message(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        text: String,
+        model: Option<String>,
+        effort: Option<String>,
+        access_mode: Option<String>,
+        images: Option<Vec<String>>,
+        collaboration_mode: Option<Value>,
+        dry_run: bool,
+        plan: bool,
+    ) -> Result<Value, String> {
+        if !dry_run {
+            settings_core::ensure_not_read_only(&self.app_settings, "Sending messages").await?;
+        }
+        let sent = (workspace_id.clone(), thread_id.clone(), text.clone());
+        let result = match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Codex if dry_run => {
+                Err("Dry runs are only supported for Claude threads.".to_string())
+            }
+            ProviderKind::Codex if plan => {
+                Err("Plan mode is only supported for Claude threads.".to_string())
+            }
+            ProviderKind::Codex => {
+                codex_core::send_user_message_core(
+                    &self.sessions,
+                    workspace_id,
+                    thread_id,
+                    text,
+                    model,
+                    effort,
+                    access_mode,
+                    images,
+                    collaboration_mode,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            ProviderKind::Claude => {
+                let _ = model;
+                let _ = effort;
+                let _ = access_mode;
+                let _ = collaboration_mode;
+                claude_core::send_user_message_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_turn_cancels,
+                    &self.claude_processes,
+                    &self.claude_approvals,
+                    &self.turn_slots,
+                    &self.thread_locks,
+                    &self.rate_limits,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    text,
+                    images,
+                    dry_run,
+                    plan,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+        }?;
+        if !dry_run {
+            let (workspace_id, thread_id, text) = sent;
+            if let Err(error) = prompt_history_core::record_prompt(
+                &self.prompt_history,
+                &self.prompt_history_path,
+                &workspace_id,
+                &thread_id,
+                &text,
+            )
+            .await
+            {
+                eprintln!("Failed to save prompt history: {error}");
+            }
+        }
+        Ok(result)
+    }
+
+    async fn prompts_history(
+        &self,
+        workspace_id: String,
+        query: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Value, String> {
+        prompt_history_core::prompt_history_core(&self.prompt_history, workspace_id, query, limit)
+            .await
+    }
+
+    async fn prompts_history_resend(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        prompt_id: String,
+    ) -> Result<Value, String> {
+        let text = prompt_history_core::prompt_history_text(
+            &self.prompt_history,
+            &workspace_id,
+            &prompt_id,
+        )
+        .await?;
+        self.send_user_message(
+            workspace_id,
+            thread_id,
+            text,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+    }
+
+    async fn reply_latest(&self, workspace_id: String, text: String) -> Result<Value, String> {
+        let threads = self.list_threads(workspace_id.clone(), None, None).await?;
+        quick_reply_core::reply_latest_core(threads, text, |thread_id, text| {
+            self.send_user_message(
+                workspace_id,
+                thread_id,
+                text,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+            )
+        })
+        .await
+    }
+
+    async fn execute_plan(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        turn_id: Option<String>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Executing plans").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                claude_core::execute_plan_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_turn_cancels,
+                    &self.claude_processes,
+                    &self.claude_approvals,
+                    &self.turn_slots,
+                    &self.thread_locks,
+                    &self.rate_limits,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    turn_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support plan mode.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn edit_message(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        item_id: String,
+        text: Option<String>,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing messages").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                thread_edit_core::edit_message_core(
+                    &self.workspaces,
+                    &self.app_settings,
+                    &self.claude_threads,
+                    &self.claude_turn_cancels,
+                    &self.claude_processes,
+                    &self.claude_approvals,
+                    &self.turn_slots,
+                    &self.thread_locks,
+                    &self.rate_limits,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    item_id,
+                    text,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support editing messages.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn undo_edit_message(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Editing messages").await?;
+        match self.thread_provider(&workspace_id, &thread_id).await? {
+            ProviderKind::Claude => {
+                thread_edit_core::undo_edit_message_core(
+                    &self.claude_threads,
+                    &self.claude_turn_cancels,
+                    &self.claude_threads_path,
+                    workspace_id,
+                    thread_id,
+                    self.event_sink.clone(),
+                )
+                .await
+            }
+            provider => Err(format!(
+                "Provider `{}` does not support editing messages.",
+                provider.as_str()
+            )),
+        }
+    }
+
+    async fn send_with_context(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        text: String,
+        context: Vec<SelectionContextBlock>,
+        images: Option<Vec<String>>,
+    ) -> Result<Value, String> {
+        let text = compose_message_with_context(&text, &context)?;
+        self.send_user_message(
+            workspace_id,
+            thread_id,
+            text,
+            None,
+            None,
+            None,
+            images,
+            None,
+            false,
+            false,
+        )
+        .await
+    }
+
+    /// The daemon has no local Whisper model, so audio prompts need a dictation endpoint.
+    async fn send_audio_message(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        audio: String,
+        mime_type: Option<String>,
+    ) -> Result<Value, String> {
+        let transcript = transcription_core::transcribe_audio_message_core(
+            &self.app_settings,
+            &audio,
+            mime_type,
+            |_| async {
+                Err("Audio prompts on the daemon require a dictation endpoint.".to_string())
+            },
+        )
+        .await?;
+        let response = self
+            .send_user_message(
+                workspace_id,
+                thread_id,
+                transcript.clone(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+            )
+            .await?;
+        Ok(json!({ "transcript": transcript, "response": response }))
+    }
+
+    async fn turn_interrupt(
+        &self,
+        workspace_id: String,
         thread_id: String,
         turn_id: String,
     ) -> Result<Value, String> {
@@ -867,11 +2121,12 @@ impl DaemonState {
                     .await
             }
             ProviderKind::Claude => {
-                let _ = turn_id;
                 claude_core::turn_interrupt_core(
                     &self.claude_turn_cancels,
+                    &self.claude_threads,
                     workspace_id,
                     thread_id,
+                    Some(turn_id),
                 )
                 .await
             }
@@ -879,6 +2134,33 @@ impl DaemonState {
         }
     }
 
+    async fn follow_claude_session(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        let workspace_path = self.workspace_path(&workspace_id).await?;
+        claude_follow_core::follow_session_start_core(
+            &self.claude_follows,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            workspace_path,
+            thread_id,
+            self.event_sink.clone(),
+        )
+        .await
+    }
+
+    async fn unfollow_claude_session(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<Value, String> {
+        claude_follow_core::follow_session_stop_core(&self.claude_follows, workspace_id, thread_id)
+            .await
+    }
+
     async fn start_review(
         &self,
         workspace_id: String,
@@ -886,6 +2168,7 @@ impl DaemonState {
         target: Value,
         delivery: Option<String>,
     ) -> Result<Value, String> {
+        settings_core::ensure_not_read_only(&self.app_settings, "Starting reviews").await?;
         codex_core::start_review_core(&self.sessions, workspace_id, thread_id, target, delivery)
             .await
     }
@@ -894,7 +2177,7 @@ impl DaemonState {
         match self.workspace_provider(&workspace_id).await? {
             ProviderKind::Codex => codex_core::model_list_core(&self.sessions, workspace_id).await,
             ProviderKind::Claude => Ok(json!({ "result": { "data": [] } })),
-            ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+            ProviderKind::Gemini => gemini_core::model_list_core(&self.gemini_models).await,
         }
     }
 
@@ -986,6 +2269,32 @@ impl DaemonState {
     async fn get_config_model(&self, workspace_id: String) -> Result<Value, String> {
         codex_core::get_config_model_core(&self.workspaces, workspace_id).await
     }
+
+    async fn config_effective(&self, workspace_id: String) -> Result<Value, String> {
+        codex_core::effective_config_core(&self.workspaces, &self.app_settings, workspace_id).await
+    }
+
+    async fn generate_commit_message(
+        &self,
+        workspace_id: String,
+        commit: bool,
+    ) -> Result<Value, String> {
+        if commit {
+            settings_core::ensure_not_read_only(&self.app_settings, "Committing").await?;
+        }
+        commit_message_core::generate_commit_message_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.sessions,
+            &self.claude_threads,
+            &self.claude_threads_path,
+            workspace_id,
+            commit,
+            |entry| Ok(PathBuf::from(entry.path.clone())),
+            self.event_sink.clone(),
+        )
+        .await
+    }
 }
 
 fn should_skip_dir(name: &str) -> bool {
@@ -1094,8 +2403,8 @@ fn default_data_dir() -> PathBuf {
 fn usage() -> String {
     format!(
         "\
-USAGE:\n  codex-monitor-daemon [--listen <addr>] [--data-dir <path>] [--token <token> | --insecure-no-auth]\n\n\
-OPTIONS:\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --data-dir <path>      Data dir holding workspaces.json/settings.json\n  --token <token>        Shared token required by clients\n  --insecure-no-auth      Disable auth (dev only)\n  -h, --help             Show this help\n"
+USAGE:\n  codex-monitor-daemon [--listen <addr>] [--data-dir <path>] [--token <token> | --insecure-no-auth]\n  codex-monitor-daemon --stdio [--data-dir <path>]\n\n\
+OPTIONS:\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --data-dir <path>      Data dir holding workspaces.json/settings.json (overrides portable mode)\n  --token <token>        Shared token required by clients\n  --insecure-no-auth      Disable auth (dev only)\n  --stdio                Speak JSON-RPC over stdin/stdout (headless, no auth)\n  -h, --help             Show this help\n"
     )
 }
 
@@ -1108,6 +2417,7 @@ fn parse_args() -> Result<DaemonConfig, String> {
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
     let mut insecure_no_auth = false;
+    let mut stdio = false;
     let mut data_dir: Option<PathBuf> = None;
 
     let mut args = env::args().skip(1);
@@ -1141,21 +2451,36 @@ fn parse_args() -> Result<DaemonConfig, String> {
                 insecure_no_auth = true;
                 token = None;
             }
+            "--stdio" => stdio = true,
             _ => return Err(format!("Unknown argument: {arg}")),
         }
     }
 
-    if token.is_none() && !insecure_no_auth {
+    // The parent process owns the pipes, so a stdio session needs no token.
+    if stdio {
+        token = None;
+    } else if token.is_none() && !insecure_no_auth {
         return Err(
             "Missing --token (or set CODEX_MONITOR_DAEMON_TOKEN). Use --insecure-no-auth for local dev only."
                 .to_string(),
         );
     }
 
+    // An explicit --data-dir wins over portable mode and `storage_migrate`. Resolved read-only:
+    // a pending move is left for the app to finish on its next start.
+    let location = match data_dir {
+        Some(path) => data_dir_core::DataDirLocation {
+            path,
+            portable: false,
+        },
+        None => data_dir_core::resolve_data_dir(&default_data_dir()),
+    };
     Ok(DaemonConfig {
         listen,
         token,
-        data_dir: data_dir.unwrap_or_else(default_data_dir),
+        data_dir: location.path,
+        portable: location.portable,
+        stdio,
     })
 }
 
@@ -1244,6 +2569,20 @@ fn parse_optional_u32(value: &Value, key: &str) -> Option<u32> {
     }
 }
 
+fn parse_optional_i64(value: &Value, key: &str) -> Option<i64> {
+    match value {
+        Value::Object(map) => map.get(key).and_then(|value| value.as_i64()),
+        _ => None,
+    }
+}
+
+fn parse_optional_f64(value: &Value, key: &str) -> Option<f64> {
+    match value {
+        Value::Object(map) => map.get(key).and_then(|value| value.as_f64()),
+        _ => None,
+    }
+}
+
 fn parse_optional_bool(value: &Value, key: &str) -> Option<bool> {
     match value {
         Value::Object(map) => map.get(key).and_then(|value| value.as_bool()),
@@ -1303,13 +2642,14 @@ fn parse_file_write_request(params: &Value) -> Result<FileWriteRequest, String>
 }
 
 async fn handle_rpc_request(
-    state: &DaemonState,
+    state: &Arc<DaemonState>,
     method: &str,
     params: Value,
     client_version: String,
 ) -> Result<Value, String> {
     match method {
         "ping" => Ok(json!({ "ok": true })),
+        "providers_detect" => provider_detect_core::detect_providers_core().await,
         "list_workspaces" => {
             let workspaces = state.list_workspaces().await;
             serde_json::to_value(workspaces).map_err(|err| err.to_string())
@@ -1319,6 +2659,12 @@ async fn handle_rpc_request(
             let is_dir = state.is_workspace_path_dir(path).await;
             serde_json::to_value(is_dir).map_err(|err| err.to_string())
         }
+        "scan_workspaces" => {
+            let root = parse_optional_string(&params, "root");
+            let max_depth = parse_optional_u32(&params, "maxDepth");
+            state.scan_workspaces(root, max_depth).await
+        }
+        "setup_probe" => state.setup_probe().await,
         "add_workspace" => {
             let path = parse_string(&params, "path")?;
             let codex_bin = parse_optional_string(&params, "codex_bin");
@@ -1345,6 +2691,17 @@ async fn handle_rpc_request(
             state.worktree_setup_mark_ran(workspace_id).await?;
             Ok(json!({ "ok": true }))
         }
+        "secrets_set" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            let value = parse_string(&params, "value")?;
+            state.secrets_set(workspace_id, name, value).await
+        }
+        "secrets_delete" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            state.secrets_delete(workspace_id, name).await
+        }
         "connect_workspace" => {
             let id = parse_string(&params, "id")?;
             state.connect_workspace(id, client_version).await?;
@@ -1446,6 +2803,15 @@ async fn handle_rpc_request(
             let workspace_id = parse_string(&params, "workspaceId")?;
             state.get_config_model(workspace_id).await
         }
+        "config_effective" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.config_effective(workspace_id).await
+        }
+        "generate_commit_message" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let commit = parse_optional_bool(&params, "commit").unwrap_or(false);
+            state.generate_commit_message(workspace_id, commit).await
+        }
         "start_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             state.start_thread(workspace_id).await
@@ -1453,7 +2819,11 @@ async fn handle_rpc_request(
         "resume_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
-            state.resume_thread(workspace_id, thread_id).await
+            let cursor = parse_optional_string(&params, "cursor");
+            let limit = parse_optional_u32(&params, "limit");
+            state
+                .resume_thread(workspace_id, thread_id, cursor, limit)
+                .await
         }
         "fork_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1466,6 +2836,34 @@ async fn handle_rpc_request(
             let limit = parse_optional_u32(&params, "limit");
             state.list_threads(workspace_id, cursor, limit).await
         }
+        "list_worktree_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.list_worktree_threads(workspace_id).await
+        }
+        "threads_sync" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.threads_sync(workspace_id).await
+        }
+        "threads_quick_find" => {
+            let workspace_id = parse_optional_string(&params, "workspaceId");
+            let query = parse_string(&params, "query")?;
+            let limit = parse_optional_u32(&params, "limit");
+            state.threads_quick_find(workspace_id, query, limit)
+        }
+        "inbox_list" => {
+            let limit = parse_optional_u32(&params, "limit");
+            state.inbox_list(limit).await
+        }
+        "lan_sync_start" => state.lan_sync_start().await,
+        "lan_sync_stop" => state.lan_sync_stop().await,
+        "lan_sync_discover" => {
+            let timeout_ms = parse_optional_u32(&params, "timeoutMs").map(u64::from);
+            state.lan_sync_discover(timeout_ms).await
+        }
+        "lan_sync_with_peer" => {
+            let address = parse_string(&params, "address")?;
+            state.lan_sync_with_peer(address).await
+        }
         "list_mcp_server_status" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let cursor = parse_optional_string(&params, "cursor");
@@ -1479,6 +2877,74 @@ async fn handle_rpc_request(
             let thread_id = parse_string(&params, "threadId")?;
             state.archive_thread(workspace_id, thread_id).await
         }
+        "bulk_thread_op" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_ids = parse_string_array(&params, "threadIds")?;
+            let op = parse_string(&params, "op")?;
+            let tags = parse_optional_string_array(&params, "tags");
+            state
+                .bulk_thread_op(workspace_id, thread_ids, op, tags)
+                .await
+        }
+        "list_trash" => {
+            let workspace_id = parse_optional_string(&params, "workspaceId");
+            state.list_trash(workspace_id).await
+        }
+        "restore_trash" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_ids = parse_string_array(&params, "threadIds")?;
+            state.restore_trash(workspace_id, thread_ids).await
+        }
+        "actions_list" => {
+            let query = parse_optional_string(&params, "query");
+            state.actions_list(query).await
+        }
+        "provider_rate_limit_state" => Ok(state.provider_rate_limit_state()),
+        "status_summary" => state.status_summary().await,
+        "orphans_list" => state.orphans_list().await,
+        "orphans_purge" => {
+            let workspace_ids = parse_string_array(&params, "workspaceIds")?;
+            state.orphans_purge(workspace_ids).await
+        }
+        "import_codex_sessions" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.import_codex_sessions(workspace_id).await
+        }
+        "import_external" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let format = parse_string(&params, "format")?;
+            let path = parse_string(&params, "path")?;
+            state.import_external(workspace_id, format, path).await
+        }
+        "export_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_ids = parse_optional_string_array(&params, "threadIds");
+            state.export_threads(workspace_id, thread_ids).await
+        }
+        "import_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            state.import_threads(workspace_id, path).await
+        }
+        "thread_export_schema" => thread_export_core::thread_export_schema_core(),
+        "audit_query" => audit_core::audit_query_core(
+            &state.data_dir,
+            parse_optional_i64(&params, "since"),
+            parse_optional_i64(&params, "until"),
+            parse_optional_string(&params, "workspaceId"),
+            parse_optional_u32(&params, "limit").map(|limit| limit as usize),
+        ),
+        "storage_usage" => state.storage_usage().await,
+        "storage_migrate" => {
+            let path = parse_optional_string(&params, "path");
+            let portable = parse_optional_bool(&params, "portable").unwrap_or(false);
+            state.storage_migrate(path, portable).await
+        }
+        "profile_list" => data_dir_core::list_profiles_core(&state.data_dir),
+        "profile_switch" => {
+            let name = parse_string(&params, "name")?;
+            state.profile_switch(name).await
+        }
         "compact_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1490,6 +2956,236 @@ async fn handle_rpc_request(
             let name = parse_string(&params, "name")?;
             state.set_thread_name(workspace_id, thread_id, name).await
         }
+        "mark_thread_read" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.mark_thread_read(workspace_id, thread_id).await
+        }
+        "set_item_pinned" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let item_id = parse_string(&params, "itemId")?;
+            let pinned = parse_optional_bool(&params, "pinned").unwrap_or(true);
+            state
+                .set_item_pinned(workspace_id, thread_id, item_id, pinned)
+                .await
+        }
+        "thread_pinned_items" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.thread_pinned_items(workspace_id, thread_id).await
+        }
+        "diff_items" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let left_thread_id = parse_string(&params, "leftThreadId")?;
+            let left_item_id = parse_string(&params, "leftItemId")?;
+            let right_thread_id = parse_string(&params, "rightThreadId")?;
+            let right_item_id = parse_string(&params, "rightItemId")?;
+            state
+                .diff_items(
+                    workspace_id,
+                    left_thread_id,
+                    left_item_id,
+                    right_thread_id,
+                    right_item_id,
+                )
+                .await
+        }
+        "set_thread_cwd" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let cwd = parse_optional_string(&params, "cwd");
+            state.set_thread_cwd(workspace_id, thread_id, cwd).await
+        }
+        "set_thread_generation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let model = parse_optional_string(&params, "model");
+            let temperature = parse_optional_f64(&params, "temperature");
+            let max_tokens = parse_optional_u32(&params, "maxTokens");
+            state
+                .set_thread_generation(workspace_id, thread_id, model, temperature, max_tokens)
+                .await
+        }
+        "duplicate_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.duplicate_thread(workspace_id, thread_id).await
+        }
+        "move_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let target_workspace_id = parse_string(&params, "targetWorkspaceId")?;
+            state
+                .move_thread(workspace_id, thread_id, target_workspace_id)
+                .await
+        }
+        "rebind_thread_workspace" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let target_workspace_id = parse_string(&params, "targetWorkspaceId")?;
+            state
+                .rebind_thread_workspace(workspace_id, thread_id, target_workspace_id)
+                .await
+        }
+        "copy_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let target_workspace_id = parse_string(&params, "targetWorkspaceId")?;
+            state
+                .copy_thread(workspace_id, thread_id, target_workspace_id)
+                .await
+        }
+        "merge_thread_worktree" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let remove_worktree = parse_optional_bool(&params, "removeWorktree").unwrap_or(true);
+            state
+                .merge_thread_worktree(workspace_id, thread_id, remove_worktree)
+                .await
+        }
+        "revert_turn_changes" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            state
+                .revert_turn_changes(workspace_id, thread_id, turn_id)
+                .await
+        }
+        "memory_read" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.memory_read(workspace_id).await
+        }
+        "memory_write" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let content = parse_optional_string(&params, "content").unwrap_or_default();
+            state.memory_write(workspace_id, content).await
+        }
+        "memory_append" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let text = parse_optional_string(&params, "text");
+            let thread_id = parse_optional_string(&params, "threadId");
+            let turn_id = parse_optional_string(&params, "turnId");
+            state
+                .memory_append(workspace_id, text, thread_id, turn_id)
+                .await
+        }
+        "memory_clear" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.memory_clear(workspace_id).await
+        }
+        "claude_hooks_read" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.claude_hooks_read(workspace_id).await
+        }
+        "claude_hooks_write" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let hooks = parse_optional_value(&params, "hooks")
+                .map(serde_json::from_value::<claude_hooks_core::HooksConfig>)
+                .transpose()
+                .map_err(|error| format!("invalid `hooks`: {error}"))?
+                .unwrap_or_default();
+            state.claude_hooks_write(workspace_id, hooks).await
+        }
+        "save_draft" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let text = parse_optional_string(&params, "text").unwrap_or_default();
+            let images = parse_optional_string_array(&params, "images");
+            state
+                .save_draft(workspace_id, thread_id, text, images)
+                .await
+        }
+        "get_draft" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.get_draft(workspace_id, thread_id).await
+        }
+        "prompt_estimate" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let text = parse_optional_string(&params, "text").unwrap_or_default();
+            let images = parse_optional_string_array(&params, "images");
+            let model = parse_optional_string(&params, "model");
+            state
+                .prompt_estimate(workspace_id, text, images, model)
+                .await
+        }
+        "list_playbooks" => state.list_playbooks().await,
+        "save_playbook" => {
+            let id = parse_optional_string(&params, "id");
+            let name = parse_string(&params, "name")?;
+            let steps = parse_optional_value(&params, "steps")
+                .map(serde_json::from_value::<Vec<playbooks_core::PlaybookStep>>)
+                .transpose()
+                .map_err(|error| format!("invalid `steps`: {error}"))?
+                .unwrap_or_default();
+            state.save_playbook(id, name, steps).await
+        }
+        "delete_playbook" => {
+            let id = parse_string(&params, "id")?;
+            state.delete_playbook(id).await
+        }
+        "run_playbook" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let playbook_id = parse_string(&params, "playbookId")?;
+            Arc::clone(state)
+                .run_playbook(workspace_id, thread_id, playbook_id)
+                .await
+        }
+        "respond_playbook_step" => {
+            let run_id = parse_string(&params, "runId")?;
+            let approved = parse_optional_bool(&params, "approved").unwrap_or(false);
+            state.respond_playbook_step(run_id, approved).await
+        }
+        "cancel_playbook" => {
+            let run_id = parse_string(&params, "runId")?;
+            state.cancel_playbook(run_id).await
+        }
+        "respond_to_approval" => {
+            let approval_id = parse_string(&params, "approvalId")?;
+            let allow = parse_optional_bool(&params, "allow").unwrap_or(false);
+            let message = parse_optional_string(&params, "message");
+            state.respond_to_approval(approval_id, allow, message).await
+        }
+        "turn_patches" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            state.turn_patches(workspace_id, thread_id, turn_id).await
+        }
+        "turn_artifacts" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            turn_artifacts_core::turn_artifacts_core(
+                &state.data_dir,
+                &workspace_id,
+                &thread_id,
+                &turn_id,
+            )
+        }
+        "turn_details" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            turn_details_core::turn_details_core(
+                &state.claude_threads,
+                &workspace_id,
+                &thread_id,
+                &turn_id,
+            )
+            .await
+        }
+        "apply_patch" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let patch_id = parse_string(&params, "patchId")?;
+            state
+                .apply_patch(workspace_id, thread_id, turn_id, patch_id)
+                .await
+        }
         "send_user_message" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1499,6 +3195,8 @@ async fn handle_rpc_request(
             let access_mode = parse_optional_string(&params, "accessMode");
             let images = parse_optional_string_array(&params, "images");
             let collaboration_mode = parse_optional_value(&params, "collaborationMode");
+            let dry_run = parse_optional_bool(&params, "dryRun").unwrap_or(false);
+            let plan = parse_optional_bool(&params, "plan").unwrap_or(false);
             state
                 .send_user_message(
                     workspace_id,
@@ -1509,15 +3207,89 @@ async fn handle_rpc_request(
                     access_mode,
                     images,
                     collaboration_mode,
+                    dry_run,
+                    plan,
                 )
                 .await
         }
+        "prompts_history" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let query = parse_optional_string(&params, "query");
+            let limit = parse_optional_u32(&params, "limit");
+            state.prompts_history(workspace_id, query, limit).await
+        }
+        "prompts_history_resend" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let prompt_id = parse_string(&params, "promptId")?;
+            state
+                .prompts_history_resend(workspace_id, thread_id, prompt_id)
+                .await
+        }
+        "reply_latest" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let text = parse_string(&params, "text")?;
+            state.reply_latest(workspace_id, text).await
+        }
+        "execute_plan" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_optional_string(&params, "turnId");
+            state.execute_plan(workspace_id, thread_id, turn_id).await
+        }
+        "edit_message" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let item_id = parse_string(&params, "itemId")?;
+            let text = parse_optional_string(&params, "text");
+            state
+                .edit_message(workspace_id, thread_id, item_id, text)
+                .await
+        }
+        "undo_edit_message" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.undo_edit_message(workspace_id, thread_id).await
+        }
+        "send_with_context" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let text = parse_optional_string(&params, "text").unwrap_or_default();
+            let context = parse_optional_value(&params, "context")
+                .map(serde_json::from_value::<Vec<SelectionContextBlock>>)
+                .transpose()
+                .map_err(|error| format!("invalid `context`: {error}"))?
+                .unwrap_or_default();
+            let images = parse_optional_string_array(&params, "images");
+            state
+                .send_with_context(workspace_id, thread_id, text, context, images)
+                .await
+        }
+        "send_audio_message" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let audio = parse_string(&params, "audio")?;
+            let mime_type = parse_optional_string(&params, "mimeType");
+            state
+                .send_audio_message(workspace_id, thread_id, audio, mime_type)
+                .await
+        }
         "turn_interrupt" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
             let turn_id = parse_string(&params, "turnId")?;
             state.turn_interrupt(workspace_id, thread_id, turn_id).await
         }
+        "follow_claude_session" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.follow_claude_session(workspace_id, thread_id).await
+        }
+        "unfollow_claude_session" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            state.unfollow_claude_session(workspace_id, thread_id).await
+        }
         "start_review" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1587,9 +3359,30 @@ async fn handle_rpc_request(
     }
 }
 
+/// Subscriber ids for connections' `subscribe_events` filters.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Sets or clears the event filter of the connection the request came in on.
+fn subscribe_events(
+    state: &DaemonState,
+    subscriber: &str,
+    params: &Value,
+) -> Result<Value, String> {
+    let filter = match params.get("filter") {
+        None | Some(Value::Null) => None,
+        Some(filter) => Some(
+            serde_json::from_value(filter.clone())
+                .map_err(|err| format!("invalid `filter`: {err}"))?,
+        ),
+    };
+    event_filter_core::subscribe_events_core(&state.event_filters, subscriber.to_string(), filter)
+}
+
 async fn forward_events(
     mut rx: broadcast::Receiver<DaemonEvent>,
     out_tx_events: mpsc::UnboundedSender<String>,
+    filters: event_filter_core::EventFiltersStore,
+    subscriber: String,
 ) {
     loop {
         let event = match rx.recv().await {
@@ -1597,6 +3390,11 @@ async fn forward_events(
             Err(broadcast::error::RecvError::Lagged(_)) => continue,
             Err(broadcast::error::RecvError::Closed) => break,
         };
+        if let DaemonEvent::AppServer(event) = &event {
+            if !event_filter_core::accepts_event(&filters, &subscriber, event) {
+                continue;
+            }
+        }
 
         let Some(payload) = build_event_notification(event) else {
             continue;
@@ -1614,7 +3412,21 @@ async fn handle_client(
     state: Arc<DaemonState>,
     events: broadcast::Sender<DaemonEvent>,
 ) {
-    let (reader, mut writer) = socket.into_split();
+    let (reader, writer) = socket.into_split();
+    serve_connection(reader, writer, config, state, events).await;
+}
+
+/// Runs one newline-delimited JSON-RPC session, shared by TCP clients and `--stdio`.
+async fn serve_connection<R, W>(
+    reader: R,
+    mut writer: W,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: broadcast::Sender<DaemonEvent>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
     let mut lines = BufReader::new(reader).lines();
 
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
@@ -1626,16 +3438,26 @@ async fn handle_client(
             if writer.write_all(b"\n").await.is_err() {
                 break;
             }
+            if writer.flush().await.is_err() {
+                break;
+            }
         }
     });
 
     let mut authenticated = config.token.is_none();
     let mut events_task: Option<tokio::task::JoinHandle<()>> = None;
+    let subscriber = format!(
+        "connection-{}",
+        NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let forward = |out_tx_events| {
+        let rx = events.subscribe();
+        let filters = state.event_filters.clone();
+        forward_events(rx, out_tx_events, filters, subscriber.clone())
+    };
 
     if authenticated {
-        let rx = events.subscribe();
-        let out_tx_events = out_tx.clone();
-        events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+        events_task = Some(tokio::spawn(forward(out_tx.clone())));
     }
 
     while let Ok(Some(line)) = lines.next_line().await {
@@ -1679,15 +3501,17 @@ async fn handle_client(
                 let _ = out_tx.send(response);
             }
 
-            let rx = events.subscribe();
-            let out_tx_events = out_tx.clone();
-            events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+            events_task = Some(tokio::spawn(forward(out_tx.clone())));
 
             continue;
         }
 
         let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
-        let result = handle_rpc_request(&state, &method, params, client_version).await;
+        let result = if method == "subscribe_events" {
+            subscribe_events(&state, &subscriber, &params)
+        } else {
+            handle_rpc_request(&state, &method, params, client_version).await
+        };
         let response = match result {
             Ok(result) => build_result_response(id, result),
             Err(message) => build_error_response(id, &message),
@@ -1697,11 +3521,35 @@ async fn handle_client(
         }
     }
 
-    drop(out_tx);
     if let Some(task) = events_task {
         task.abort();
     }
-    write_task.abort();
+    let _ = event_filter_core::subscribe_events_core(&state.event_filters, subscriber, None);
+    // Let queued responses drain so a piped `--stdio` request still gets its answer.
+    drop(out_tx);
+    let _ = write_task.await;
+}
+
+/// Resolves on Ctrl-C, and on SIGTERM where there is one.
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use std::future::Future;
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            let mut interrupt = std::pin::pin!(tokio::signal::ctrl_c());
+            std::future::poll_fn(|cx| {
+                if interrupt.as_mut().poll(cx).is_ready() || terminate.poll_recv(cx).is_ready() {
+                    std::task::Poll::Ready(())
+                } else {
+                    std::task::Poll::Pending
+                }
+            })
+            .await;
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
 }
 
 fn main() {
@@ -1726,6 +3574,95 @@ fn main() {
         let state = Arc::new(DaemonState::load(&config, event_sink));
         let config = Arc::new(config);
 
+        let retention_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = retention_state.apply_retention().await {
+                    eprintln!("retention pass failed: {error}");
+                }
+                tokio::time::sleep(retention_core::RETENTION_INTERVAL).await;
+            }
+        });
+
+        let summary_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(idle_summary_core::IDLE_SUMMARY_INTERVAL).await;
+                if let Err(error) = summary_state.summarize_idle_threads().await {
+                    eprintln!("idle summary pass failed: {error}");
+                }
+            }
+        });
+
+        let sync_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = sync_state.sync_all_threads().await {
+                    eprintln!("history sync pass failed: {error}");
+                }
+                thread_sync_core::wait_for_next_pass(&sync_state.app_settings).await;
+            }
+        });
+
+        let settings_state = Arc::clone(&state);
+        let mut settings_changes = settings_core::subscribe_settings_changes();
+        tokio::spawn(async move {
+            loop {
+                match settings_changes.recv().await {
+                    Ok(change) => settings_state.apply_settings_change(change).await,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let mirror_state = Arc::clone(&state);
+        let mut mirrored_events = events_tx.subscribe();
+        tokio::spawn(async move {
+            let mut mirror = event_mirror_core::EventMirror::default();
+            loop {
+                match mirrored_events.recv().await {
+                    Ok(DaemonEvent::AppServer(event)) => {
+                        mirror.mirror(&mirror_state.workspaces, &event).await;
+                        status_summary_core::track_status_event(
+                            &mirror_state.status,
+                            &event,
+                            mirror_state.turn_slots.queued(),
+                            claude_core::now_ms(),
+                            &mirror_state.event_sink,
+                        )
+                        .await;
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        if config.stdio {
+            eprintln!(
+                "codex-monitor-daemon serving JSON-RPC on stdio (data dir: {})",
+                state.data_dir.display()
+            );
+            serve_connection(
+                tokio::io::stdin(),
+                tokio::io::stdout(),
+                Arc::clone(&config),
+                Arc::clone(&state),
+                events_tx.clone(),
+            )
+            .await;
+            state.shutdown().await;
+            return;
+        }
+
+        let shutdown_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            wait_for_shutdown_signal().await;
+            shutdown_state.shutdown().await;
+            std::process::exit(0);
+        });
+
         let listener = TcpListener::bind(config.listen)
             .await
             .unwrap_or_else(|err| panic!("failed to bind {}: {err}", config.listen));
@@ -0,0 +1,474 @@
+//! Headless runner: sends one prompt to a workspace's Claude thread from the command line and
+//! streams the answer to stdout. Threads and turns go to the same store the app reads.
+
+#[allow(dead_code)]
+#[path = "../backend/mod.rs"]
+mod backend;
+#[allow(dead_code)]
+#[path = "../codex/args.rs"]
+mod codex_args;
+#[allow(dead_code)]
+#[path = "../codex/config.rs"]
+mod codex_config;
+#[allow(dead_code)]
+#[path = "../codex/home.rs"]
+mod codex_home;
+#[allow(dead_code)]
+#[path = "../files/io.rs"]
+mod file_io;
+#[allow(dead_code)]
+#[path = "../files/ops.rs"]
+mod file_ops;
+#[allow(dead_code)]
+#[path = "../files/policy.rs"]
+mod file_policy;
+#[allow(dead_code)]
+#[path = "../providers/mod.rs"]
+mod providers;
+#[allow(dead_code)]
+#[path = "../rules.rs"]
+mod rules;
+#[allow(dead_code)]
+#[path = "../shared/mod.rs"]
+mod shared;
+#[allow(dead_code)]
+#[path = "../storage.rs"]
+mod storage;
+#[allow(dead_code)]
+#[path = "../types.rs"]
+mod types;
+#[allow(dead_code)]
+#[path = "../utils.rs"]
+mod utils;
+#[allow(dead_code)]
+#[path = "../workspaces/settings.rs"]
+mod workspace_settings;
+
+// Provide feature-style module paths for shared cores when compiled in the runner.
+mod codex {
+    pub(crate) mod args {
+        pub(crate) use crate::codex_args::*;
+    }
+    pub(crate) mod config {
+        pub(crate) use crate::codex_config::*;
+    }
+    pub(crate) mod home {
+        pub(crate) use crate::codex_home::*;
+    }
+}
+
+mod files {
+    pub(crate) mod io {
+        pub(crate) use crate::file_io::*;
+    }
+    pub(crate) mod ops {
+        pub(crate) use crate::file_ops::*;
+    }
+    pub(crate) mod policy {
+        pub(crate) use crate::file_policy::*;
+    }
+}
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, Mutex};
+
+use backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use shared::{claude_core, claude_pool_core, data_dir_core};
+use storage::{read_settings, read_workspaces};
+use types::{ProviderKind, WorkspaceEntry};
+
+/// Tauri's bundle identifier; the app keeps its data in a folder of this name.
+const APP_IDENTIFIER: &str = "com.dimillian.codexmonitor";
+const SETTLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const HEADLESS_DENIAL: &str = "Denied by the headless runner; rerun with --approve to allow.";
+
+fn usage() -> String {
+    "\
+USAGE:\n  polycode run --workspace <id|name|path> [--thread <id>] [--plan] [--approve] [--data-dir <path>] [<prompt>...]\n\n\
+Sends one prompt to a Claude workspace and streams the answer to stdout. Without a prompt\n\
+argument the prompt is read from stdin. The turn is saved to the app's thread store; quit the\n\
+app first, or it may overwrite the turn when it next saves.\n\n\
+OPTIONS:\n  --workspace <ws>     Workspace id, name or folder\n  --thread <id>        Continue this thread instead of starting a new one\n  --plan               Ask for a plan instead of changes\n  --approve            Allow tool calls that need approval (denied otherwise)\n  --data-dir <path>    Data dir holding workspaces.json/settings.json\n  -h, --help           Show this help\n"
+        .to_string()
+}
+
+struct RunConfig {
+    workspace: String,
+    thread_id: Option<String>,
+    plan: bool,
+    approve: bool,
+    data_dir: Option<PathBuf>,
+    prompt: Option<String>,
+}
+
+fn parse_args() -> Result<RunConfig, String> {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("run") => {}
+        Some("-h" | "--help") => {
+            print!("{}", usage());
+            std::process::exit(0);
+        }
+        Some(command) => return Err(format!("Unknown command: {command}")),
+        None => return Err("Missing command".to_string()),
+    }
+    let mut workspace = None;
+    let mut thread_id = None;
+    let mut plan = false;
+    let mut approve = false;
+    let mut data_dir = None;
+    let mut prompt = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{}", usage());
+                std::process::exit(0);
+            }
+            "--workspace" => {
+                workspace = Some(args.next().ok_or("--workspace requires a value")?);
+            }
+            "--thread" => thread_id = Some(args.next().ok_or("--thread requires a value")?),
+            "--data-dir" => {
+                let value = args.next().ok_or("--data-dir requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--data-dir requires a non-empty value".to_string());
+                }
+                data_dir = Some(PathBuf::from(trimmed));
+            }
+            "--plan" => plan = true,
+            "--approve" => approve = true,
+            "--" => prompt.extend(args.by_ref()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown argument: {arg}")),
+            _ => prompt.push(arg),
+        }
+    }
+    Ok(RunConfig {
+        workspace: workspace.ok_or("--workspace is required")?,
+        thread_id,
+        plan,
+        approve,
+        data_dir,
+        prompt: (!prompt.is_empty()).then(|| prompt.join(" ")),
+    })
+}
+
+/// Where Tauri puts the app's data dir on each platform.
+fn default_data_dir() -> PathBuf {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    let home = non_empty("HOME")
+        .or_else(|| non_empty("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let base = if cfg!(target_os = "macos") {
+        home.join("Library").join("Application Support")
+    } else if cfg!(windows) {
+        non_empty("APPDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join("AppData").join("Roaming"))
+    } else {
+        non_empty("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".local").join("share"))
+    };
+    base.join(APP_IDENTIFIER)
+}
+
+/// Matches `query` against workspace ids, then names (case-insensitive), then folders.
+fn find_workspace<'a>(
+    workspaces: &'a HashMap<String, WorkspaceEntry>,
+    query: &str,
+) -> Result<&'a WorkspaceEntry, String> {
+    if let Some(entry) = workspaces.get(query) {
+        return Ok(entry);
+    }
+    let by_name = workspaces
+        .values()
+        .filter(|entry| entry.name.eq_ignore_ascii_case(query))
+        .collect::<Vec<_>>();
+    match by_name.as_slice() {
+        [entry] => return Ok(entry),
+        [] => {}
+        matches => {
+            let ids = matches
+                .iter()
+                .map(|entry| format!("{} ({})", entry.id, entry.path))
+                .collect::<Vec<_>>();
+            return Err(format!(
+                "Several workspaces are named `{query}`; pass an id: {}",
+                ids.join(", ")
+            ));
+        }
+    }
+    let normalize = |path: &str| Path::new(path).canonicalize().ok();
+    let wanted = normalize(query);
+    wanted
+        .and_then(|wanted| {
+            workspaces
+                .values()
+                .find(|entry| normalize(&entry.path).as_ref() == Some(&wanted))
+        })
+        .ok_or_else(|| {
+            let mut names = workspaces
+                .values()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            format!(
+                "No workspace matches `{query}`. Workspaces: {}",
+                names.join(", ")
+            )
+        })
+}
+
+#[derive(Clone)]
+struct RunnerEventSink {
+    tx: mpsc::UnboundedSender<Value>,
+}
+
+impl EventSink for RunnerEventSink {
+    fn emit_app_server_event(&self, event: AppServerEvent) {
+        let _ = self.tx.send(event.message);
+    }
+
+    fn emit_terminal_output(&self, _event: TerminalOutput) {}
+
+    fn emit_terminal_exit(&self, _event: TerminalExit) {}
+}
+
+struct Runner {
+    workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: Mutex<types::AppSettings>,
+    claude_threads_path: PathBuf,
+    claude_threads: claude_core::ClaudeThreadsStore,
+    claude_turn_cancels: claude_core::ClaudeTurnCancelsStore,
+    claude_processes: claude_pool_core::ClaudeProcessPool,
+    claude_approvals: claude_pool_core::ClaudeApprovalsStore,
+    turn_slots: shared::turn_slots_core::TurnSlotsStore,
+    thread_locks: shared::thread_locks_core::ThreadLocksStore,
+    rate_limits: shared::rate_limit_core::RateLimitsStore,
+}
+
+impl Runner {
+    fn load(location: &data_dir_core::DataDirLocation) -> Self {
+        let data_dir = location.path.as_path();
+        let workspaces = read_workspaces(&data_dir.join("workspaces.json")).unwrap_or_default();
+        let mut app_settings = read_settings(&data_dir.join("settings.json")).unwrap_or_default();
+        app_settings.data_dir = Some(data_dir.to_string_lossy().to_string());
+        app_settings.portable_mode = location.portable;
+        let claude_threads_path = claude_core::claude_threads_path(data_dir);
+        let claude_threads =
+            claude_core::read_threads_snapshot(&claude_threads_path).unwrap_or_default();
+        Self {
+            workspaces: Mutex::new(workspaces),
+            app_settings: Mutex::new(app_settings),
+            claude_threads_path,
+            claude_threads: Arc::new(Mutex::new(claude_threads)),
+            claude_turn_cancels: Arc::new(Mutex::new(HashMap::new())),
+            claude_processes: Arc::new(Mutex::new(HashMap::new())),
+            claude_approvals: Arc::new(Mutex::new(HashMap::new())),
+            turn_slots: Default::default(),
+            thread_locks: Default::default(),
+            rate_limits: Default::default(),
+        }
+    }
+}
+
+/// Streams the turn's events until it completes or fails. Returns the error that ended it.
+async fn follow_turn(
+    runner: &Runner,
+    events: &mut mpsc::UnboundedReceiver<Value>,
+    thread_id: &str,
+    turn_id: &str,
+    approve: bool,
+) -> Option<String> {
+    let mut failure = None;
+    let mut stdout = std::io::stdout();
+    while let Some(message) = events.recv().await {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        if params["threadId"].as_str() != Some(thread_id) {
+            continue;
+        }
+        match method {
+            "item/agentMessage/delta" => {
+                let _ = stdout.write_all(params["delta"].as_str().unwrap_or_default().as_bytes());
+                let _ = stdout.flush();
+            }
+            "approval/commandRequested" | "approval/fileAccessRequested" => {
+                let approval_id = params["approvalId"].as_str().unwrap_or_default();
+                let detail = params["command"]
+                    .as_str()
+                    .or_else(|| params["path"].as_str())
+                    .unwrap_or_default();
+                eprintln!("{} {detail}", if approve { "Allowed:" } else { "Denied:" });
+                let message = (!approve).then(|| HEADLESS_DENIAL.to_string());
+                let _ = claude_pool_core::respond_to_approval_core(
+                    &runner.claude_approvals,
+                    approval_id.to_string(),
+                    approve,
+                    message,
+                )
+                .await;
+            }
+            "error" => {
+                let error = params["error"]["message"]
+                    .as_str()
+                    .unwrap_or("Turn failed.")
+                    .to_string();
+                if params["willRetry"].as_bool() == Some(true) {
+                    eprintln!("{error} Retrying.");
+                } else if params["turnId"].as_str() == Some(turn_id) {
+                    // Turns that fail to start end here without a `turn/completed`.
+                    failure = Some(error);
+                    break;
+                }
+            }
+            "turn/completed" if params["turn"]["id"].as_str() == Some(turn_id) => break,
+            _ => {}
+        }
+    }
+    let _ = writeln!(stdout);
+    failure
+}
+
+/// Every way a turn ends drops its cancel handle last, after the store is saved.
+async fn wait_for_turn_to_settle(runner: &Runner, turn_id: &str) {
+    let cancels = &runner.claude_turn_cancels;
+    while cancels.lock().await.contains_key(turn_id) {
+        tokio::time::sleep(SETTLE_POLL_INTERVAL).await;
+    }
+}
+
+async fn run(config: RunConfig) -> Result<(), String> {
+    let prompt = match config.prompt {
+        Some(prompt) => prompt,
+        None => {
+            let mut prompt = String::new();
+            std::io::stdin()
+                .read_to_string(&mut prompt)
+                .map_err(|error| format!("Failed to read the prompt from stdin: {error}"))?;
+            prompt
+        }
+    };
+    if prompt.trim().is_empty() {
+        return Err("The prompt is empty.".to_string());
+    }
+    // An explicit --data-dir wins over portable mode and `storage_migrate`, as in the daemon.
+    // Resolved read-only: a pending move is left for the app to finish on its next start.
+    let location = match config.data_dir {
+        Some(path) => data_dir_core::DataDirLocation {
+            path,
+            portable: false,
+        },
+        None => data_dir_core::resolve_data_dir(&default_data_dir()),
+    };
+    let runner = Runner::load(&location);
+    let entry = {
+        let workspaces = runner.workspaces.lock().await;
+        find_workspace(&workspaces, &config.workspace)?.clone()
+    };
+    let settings = runner.app_settings.lock().await.clone();
+    let provider = providers::resolve_workspace_provider(&entry, Some(&settings));
+    if provider != ProviderKind::Claude {
+        return Err(format!(
+            "`{}` uses {}; the headless runner only drives Claude workspaces.",
+            entry.name,
+            provider.as_str()
+        ));
+    }
+
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let event_sink = RunnerEventSink { tx };
+    let thread_id = match config.thread_id {
+        Some(thread_id) => thread_id,
+        None => {
+            let started = claude_core::start_thread_core(
+                &runner.workspaces,
+                &runner.app_settings,
+                &runner.claude_threads,
+                &runner.claude_threads_path,
+                entry.id.clone(),
+                event_sink.clone(),
+            )
+            .await?;
+            started["result"]["thread"]["id"]
+                .as_str()
+                .ok_or("The new thread has no id.")?
+                .to_string()
+        }
+    };
+    let sent = claude_core::send_user_message_core(
+        &runner.workspaces,
+        &runner.app_settings,
+        &runner.claude_threads,
+        &runner.claude_turn_cancels,
+        &runner.claude_processes,
+        &runner.claude_approvals,
+        &runner.turn_slots,
+        &runner.thread_locks,
+        &runner.rate_limits,
+        &runner.claude_threads_path,
+        entry.id.clone(),
+        thread_id.clone(),
+        prompt,
+        None,
+        false,
+        config.plan,
+        event_sink,
+    )
+    .await?;
+    let turn_id = sent["result"]["turn"]["id"]
+        .as_str()
+        .ok_or("The turn has no id.")?
+        .to_string();
+    eprintln!("{} · thread {thread_id} · turn {turn_id}", entry.name);
+
+    let failure = tokio::select! {
+        failure = follow_turn(&runner, &mut events, &thread_id, &turn_id, config.approve) => failure,
+        _ = tokio::signal::ctrl_c() => {
+            let _ = claude_core::turn_interrupt_core(
+                &runner.claude_turn_cancels,
+                &runner.claude_threads,
+                entry.id.clone(),
+                thread_id.clone(),
+                Some(turn_id.clone()),
+            )
+            .await;
+            Some("Canceled.".to_string())
+        }
+    };
+    wait_for_turn_to_settle(&runner, &turn_id).await;
+    claude_pool_core::shutdown_pool(&runner.claude_processes).await;
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+fn main() {
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", usage());
+            std::process::exit(2);
+        }
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build tokio runtime");
+
+    if let Err(error) = runtime.block_on(run(config)) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+}
@@ -1,6 +1,71 @@
+use serde::Serialize;
 use tokio::process::Command;
 
-use crate::types::{AppSettings, WorkspaceEntry};
+use crate::types::{AppSettings, CodexOptions, WorkspaceEntry};
+
+/// Value flags that set one setting each, with their short forms.
+const VALUE_FLAGS: &[(&str, Option<&str>, &str)] = &[
+    ("--model", Some("-m"), "model"),
+    ("--profile", Some("-p"), "profile"),
+    ("--sandbox", Some("-s"), "sandbox"),
+    ("--ask-for-approval", Some("-a"), "approval"),
+    ("--cd", Some("-C"), "cd"),
+];
+/// Flags that may be given more than once; every occurrence is kept.
+const LIST_FLAGS: &[(&str, Option<&str>)] = &[("--image", Some("-i")), ("--add-dir", None)];
+/// `-c key=value` keys that set the same thing as one of [`VALUE_FLAGS`].
+const CONFIG_KEY_SETTINGS: &[(&str, &str)] = &[
+    ("model", "model"),
+    ("profile", "profile"),
+    ("sandbox_mode", "sandbox"),
+    ("approval_policy", "approval"),
+];
+/// Shortcut switches and the sandbox and approval settings they stand for.
+const SWITCH_SETTINGS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "--full-auto",
+        &[("sandbox", "workspace-write"), ("approval", "on-request")],
+    ),
+    (
+        "--dangerously-bypass-approvals-and-sandbox",
+        &[("sandbox", "danger-full-access"), ("approval", "never")],
+    ),
+    (
+        "--yolo",
+        &[("sandbox", "danger-full-access"), ("approval", "never")],
+    ),
+];
+
+/// Codex args from one settings layer: `options` for the typed Codex options, `app` (or the
+/// provider profile standing in for it), `parent` for a worktree's parent workspace, or
+/// `workspace`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct CodexArgLayer {
+    pub(crate) layer: &'static str,
+    pub(crate) args: Vec<String>,
+}
+
+/// A setting given different values, either by several layers or twice in one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct CodexArgConflict {
+    pub(crate) setting: String,
+    /// The args that are used and the layer they come from.
+    pub(crate) winner: CodexArgLayer,
+    pub(crate) overridden: Vec<CodexArgLayer>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct LayeredCodexArgs {
+    pub(crate) argv: Vec<String>,
+    pub(crate) conflicts: Vec<CodexArgConflict>,
+}
+
+/// One flag with its value, or a positional arg, and the settings it sets.
+struct CodexArgOption {
+    layer: usize,
+    tokens: Vec<String>,
+    settings: Vec<(String, String)>,
+}
 
 pub(crate) fn parse_codex_args(value: Option<&str>) -> Result<Vec<String>, String> {
     let raw = match value {
@@ -20,10 +85,224 @@ pub(crate) fn apply_codex_args(command: &mut Command, value: Option<&str>) -> Re
     Ok(())
 }
 
+fn parse_layer_args(layer: usize, args: &[String]) -> Vec<CodexArgOption> {
+    let mut options = Vec::new();
+    let mut index = 0;
+    while index < args.len() {
+        let arg = &args[index];
+        index += 1;
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut tokens = vec![arg.clone()];
+        let mut take_value = |tokens: &mut Vec<String>| match &inline_value {
+            Some(value) => Some(value.clone()),
+            None => {
+                let value = args.get(index).filter(|value| !value.starts_with('-'))?;
+                index += 1;
+                tokens.push(value.clone());
+                Some(value.clone())
+            }
+        };
+        let settings = if !name.starts_with('-') {
+            Vec::new()
+        } else if let Some((_, _, setting)) = VALUE_FLAGS
+            .iter()
+            .find(|(long, short, _)| name == *long || Some(name) == *short)
+        {
+            let value = take_value(&mut tokens).unwrap_or_default();
+            vec![(setting.to_string(), value)]
+        } else if LIST_FLAGS
+            .iter()
+            .any(|(long, short)| name == *long || Some(name) == *short)
+        {
+            take_value(&mut tokens);
+            Vec::new()
+        } else if name == "--config" || name == "-c" {
+            let value = take_value(&mut tokens).unwrap_or_default();
+            let (key, value) = value.split_once('=').unwrap_or((value.as_str(), ""));
+            let key = key.trim();
+            let setting = CONFIG_KEY_SETTINGS
+                .iter()
+                .find(|(config_key, _)| *config_key == key)
+                .map(|(_, setting)| setting.to_string())
+                .unwrap_or_else(|| format!("config.{key}"));
+            vec![(setting, value.trim().to_string())]
+        } else if name == "--enable" || name == "--disable" {
+            let feature = take_value(&mut tokens).unwrap_or_default();
+            let state = if name == "--enable" {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            vec![(format!("feature.{feature}"), state.to_string())]
+        } else if let Some((_, settings)) =
+            SWITCH_SETTINGS.iter().find(|(switch, _)| name == *switch)
+        {
+            settings
+                .iter()
+                .map(|(setting, value)| (setting.to_string(), value.to_string()))
+                .collect()
+        } else {
+            // Unknown flags are taken to have a value when a non-flag follows them.
+            let value = take_value(&mut tokens).unwrap_or_else(|| "true".to_string());
+            vec![(name.to_string(), value)]
+        };
+        options.push(CodexArgOption {
+            layer,
+            tokens,
+            settings,
+        });
+    }
+    options
+}
+
+/// Merges layers given lowest precedence first. The last option to set a setting wins it, and
+/// an option that loses any of its settings is left out, so Codex never sees a flag twice.
+pub(crate) fn layer_codex_args(layers: &[CodexArgLayer]) -> LayeredCodexArgs {
+    let options = layers
+        .iter()
+        .enumerate()
+        .flat_map(|(layer, entry)| parse_layer_args(layer, &entry.args))
+        .collect::<Vec<_>>();
+    let winner_of = |setting: &str| {
+        options
+            .iter()
+            .rposition(|option| option.settings.iter().any(|(name, _)| name == setting))
+    };
+    let describe = |option: &CodexArgOption| CodexArgLayer {
+        layer: layers[option.layer].layer,
+        args: option.tokens.clone(),
+    };
+
+    let mut conflicts = Vec::new();
+    let mut seen = Vec::new();
+    for (setting, _) in options.iter().flat_map(|option| &option.settings) {
+        if seen.contains(&setting) {
+            continue;
+        }
+        seen.push(setting);
+        let Some(winner) = winner_of(setting) else {
+            continue;
+        };
+        let value_of = |option: &CodexArgOption| {
+            option
+                .settings
+                .iter()
+                .find(|(name, _)| name == setting)
+                .map(|(_, value)| value.clone())
+        };
+        let winning_value = value_of(&options[winner]);
+        let overridden = options
+            .iter()
+            .enumerate()
+            .filter(|(index, option)| *index != winner && value_of(option).is_some())
+            .collect::<Vec<_>>();
+        if overridden
+            .iter()
+            .any(|(_, option)| value_of(option) != winning_value)
+        {
+            conflicts.push(CodexArgConflict {
+                setting: setting.clone(),
+                winner: describe(&options[winner]),
+                overridden: overridden
+                    .iter()
+                    .map(|(_, option)| describe(option))
+                    .collect(),
+            });
+        }
+    }
+
+    let argv = options
+        .iter()
+        .enumerate()
+        .filter(|(index, option)| {
+            option
+                .settings
+                .iter()
+                .all(|(setting, _)| winner_of(setting) == Some(*index))
+        })
+        .flat_map(|(_, option)| option.tokens.clone())
+        .collect();
+    LayeredCodexArgs { argv, conflicts }
+}
+
+/// `--sandbox` and `--model` for the typed options picked in settings.
+fn render_codex_options(options: &CodexOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(sandbox) = options.sandbox {
+        args.extend(["--sandbox".to_string(), sandbox.as_str().to_string()]);
+    }
+    if let Some(model) = options
+        .model
+        .as_deref()
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+    {
+        args.extend(["--model".to_string(), model.to_string()]);
+    }
+    args
+}
+
+/// The non-empty Codex args layers for a workspace, lowest precedence first: the typed options,
+/// then free-form args, with a worktree's parent between the app settings and the worktree.
+pub(crate) fn codex_arg_layers(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
+) -> Result<Vec<CodexArgLayer>, String> {
+    let parent_args = parent_entry
+        .filter(|_| entry.kind.is_worktree())
+        .and_then(|parent| parent.settings.codex_args.as_deref());
+    let sources = [
+        (
+            "app",
+            app_settings.and_then(|settings| settings.codex_args.as_deref()),
+        ),
+        ("parent", parent_args),
+        ("workspace", entry.settings.codex_args.as_deref()),
+    ];
+    let mut layers = Vec::new();
+    let options = app_settings
+        .map(|settings| render_codex_options(&settings.codex_options))
+        .unwrap_or_default();
+    if !options.is_empty() {
+        layers.push(CodexArgLayer {
+            layer: "options",
+            args: options,
+        });
+    }
+    for (layer, value) in sources {
+        let args = parse_codex_args(value)?;
+        if !args.is_empty() {
+            layers.push(CodexArgLayer { layer, args });
+        }
+    }
+    Ok(layers)
+}
+
+/// Codex args for a workspace with its worktree parent and the app settings layered
+/// underneath, as one string for [`apply_codex_args`].
 pub(crate) fn resolve_workspace_codex_args(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
     app_settings: Option<&AppSettings>,
+) -> Option<String> {
+    match codex_arg_layers(entry, parent_entry, app_settings) {
+        Ok(layers) => {
+            let argv = layer_codex_args(&layers).argv;
+            (!argv.is_empty()).then(|| shell_words::join(argv))
+        }
+        // Hand the unparsable layer on so `apply_codex_args` reports it when Codex starts.
+        Err(_) => first_codex_args(entry, parent_entry, app_settings),
+    }
+}
+
+fn first_codex_args(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
 ) -> Option<String> {
     if let Some(value) = entry.settings.codex_args.as_deref() {
         if let Some(normalized) = normalize_codex_args(value) {
@@ -58,7 +337,7 @@ fn normalize_codex_args(value: &str) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_codex_args, resolve_workspace_codex_args};
+    use super::{layer_codex_args, parse_codex_args, resolve_workspace_codex_args, CodexArgLayer};
     use crate::types::{AppSettings, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
 
     #[test]
@@ -133,4 +412,59 @@ mod tests {
         let resolved_main = resolve_workspace_codex_args(&main, None, Some(&app_settings));
         assert_eq!(resolved_main.as_deref(), Some("--profile app"));
     }
+
+    #[test]
+    fn layers_codex_args_and_reports_conflicts() {
+        let layer = |layer, args: &str| CodexArgLayer {
+            layer,
+            args: parse_codex_args(Some(args)).expect("parse args"),
+        };
+        let layered = layer_codex_args(&[
+            layer(
+                "app",
+                "--model o3 --full-auto -c model_reasoning_effort=high --add-dir /a",
+            ),
+            layer("parent", "-m gpt-5 --add-dir /b"),
+            layer(
+                "workspace",
+                "--sandbox=read-only --model gpt-5 --enable web_search",
+            ),
+        ]);
+        assert_eq!(
+            layered.argv,
+            vec![
+                "-c",
+                "model_reasoning_effort=high",
+                "--add-dir",
+                "/a",
+                "--add-dir",
+                "/b",
+                "--sandbox=read-only",
+                "--model",
+                "gpt-5",
+                "--enable",
+                "web_search",
+            ]
+        );
+        let conflicts = layered
+            .conflicts
+            .iter()
+            .map(|conflict| {
+                let overridden = conflict
+                    .overridden
+                    .iter()
+                    .map(|layer| layer.layer)
+                    .collect::<Vec<_>>();
+                (conflict.setting.as_str(), conflict.winner.layer, overridden)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            conflicts,
+            vec![
+                ("model", "workspace", vec!["app", "parent"]),
+                ("sandbox", "workspace", vec!["app"]),
+            ]
+        );
+        assert_eq!(layered.conflicts[1].overridden[0].args, vec!["--full-auto"]);
+    }
 }
@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Manager, State};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 
@@ -18,10 +18,23 @@ use crate::backend::app_server::{
     build_codex_command_with_bin, build_codex_path_env, check_codex_installation,
     spawn_workspace_session as spawn_workspace_session_inner,
 };
-use crate::event_sink::TauriEventSink;
+use crate::event_sink::{emit_app_server_event, TauriEventSink};
 use crate::providers;
 use crate::remote_backend;
-use crate::shared::{claude_core, codex_core};
+use crate::dictation;
+use crate::shared::{
+    actions_core, answer_diff_core, audit_core, claude_core, claude_follow_core, claude_hooks_core,
+    claude_pool_core, codex_core, codex_import_core, commit_message_core, data_dir_core,
+    drafts_core, external_import_core, gemini_core, inbox_core, lan_sync_core, memory_core,
+    orphans_core, patches_core, playbooks_core, prompt_estimate_core, prompt_history_core,
+    provider_detect_core, quick_reply_core, rate_limit_core, retention_core, settings_core,
+    shutdown_core, status_summary_core, thread_edit_core, thread_export_core,
+    thread_inheritance_core, thread_quick_find_core, thread_rebind_core, thread_sync_core,
+    thread_worktree_core, transcription_core, trash_core, turn_artifacts_core, turn_details_core,
+    turn_snapshot_core,
+};
+use crate::shared::context_files_core::{compose_message_with_context, SelectionContextBlock};
+use crate::shared::redaction_core::RedactionPipeline;
 use crate::shared::process_core::tokio_command;
 use crate::state::AppState;
 use crate::types::{ProviderKind, WorkspaceEntry};
@@ -236,12 +249,25 @@ pub(crate) async fn codex_doctor(
     }))
 }
 
+#[tauri::command]
+pub(crate) async fn providers_detect(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "providers_detect", json!({})).await;
+    }
+
+    provider_detect_core::detect_providers_core().await
+}
+
 #[tauri::command]
 pub(crate) async fn start_thread(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Starting threads").await?;
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -253,7 +279,9 @@ pub(crate) async fn start_thread(
     }
 
     match workspace_provider(&state, &workspace_id).await? {
-        ProviderKind::Codex => codex_core::start_thread_core(&state.sessions, workspace_id).await,
+        ProviderKind::Codex => {
+            codex_core::start_thread_core(&state.sessions, &state.workspaces, workspace_id).await
+        }
         ProviderKind::Claude => {
             let event_sink = TauriEventSink::new(app);
             claude_core::start_thread_core(
@@ -274,6 +302,8 @@ pub(crate) async fn start_thread(
 pub(crate) async fn resume_thread(
     workspace_id: String,
     thread_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -282,7 +312,12 @@ pub(crate) async fn resume_thread(
             &*state,
             app,
             "resume_thread",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "cursor": cursor,
+                "limit": limit,
+            }),
         )
         .await;
     }
@@ -295,9 +330,24 @@ pub(crate) async fn resume_thread(
             Ok(with_thread_provider(response, &ProviderKind::Codex))
         }
         ProviderKind::Claude => {
-            let response =
-                claude_core::resume_thread_core(&state.claude_threads, workspace_id, thread_id)
-                    .await?;
+            let mut response = claude_core::resume_thread_core(
+                &state.claude_threads,
+                &state.render_cache,
+                workspace_id.clone(),
+                thread_id.clone(),
+                cursor,
+                limit,
+            )
+            .await?;
+            thread_rebind_core::attach_cwd_repair(
+                &mut response,
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &workspace_id,
+                &thread_id,
+            )
+            .await;
             Ok(with_thread_provider(response, &ProviderKind::Claude))
         }
         ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
@@ -311,6 +361,7 @@ pub(crate) async fn fork_thread(
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Forking threads").await?;
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
@@ -344,28 +395,31 @@ pub(crate) async fn list_threads(
 
     let _ = cursor;
     let _ = limit;
-    let workspace_path = workspace_path(&state, &workspace_id).await?;
+    list_workspace_threads(&state, workspace_id).await
+}
+
+/// Codex and Claude threads of a workspace in one list, newest first.
+async fn list_workspace_threads(state: &AppState, workspace_id: String) -> Result<Value, String> {
     let fetch_limit = Some(100);
 
     let codex_response =
         codex_core::list_threads_core(&state.sessions, workspace_id.clone(), None, fetch_limit)
             .await
             .ok();
-    let claude_response = claude_core::list_threads_core(
-        &state.claude_threads,
-        &state.claude_threads_path,
-        workspace_id,
-        workspace_path,
-        None,
-        fetch_limit,
-    )
-    .await
-    .ok();
+    let claude_response =
+        claude_core::list_threads_core(&state.claude_threads, workspace_id, None, fetch_limit)
+            .await
+            .ok();
 
     if codex_response.is_none() && claude_response.is_none() {
         return Err("failed to list threads for codex and claude providers".to_string());
     }
 
+    // Only Claude threads track reads; the count covers all of them, not just this page.
+    let unread_count = claude_response
+        .as_ref()
+        .and_then(|response| response["result"]["unreadCount"].as_u64())
+        .unwrap_or(0);
     let mut data = Vec::<Value>::new();
     if let Some(response) = codex_response {
         data.extend(thread_list_entries_with_provider(response, &ProviderKind::Codex));
@@ -379,15 +433,14 @@ pub(crate) async fn list_threads(
         "result": {
             "data": data,
             "nextCursor": Value::Null,
+            "unreadCount": unread_count,
         }
     }))
 }
 
 #[tauri::command]
-pub(crate) async fn list_mcp_server_status(
+pub(crate) async fn list_worktree_threads(
     workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -395,26 +448,32 @@ pub(crate) async fn list_mcp_server_status(
         return remote_backend::call_remote(
             &*state,
             app,
-            "list_mcp_server_status",
-            json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
+            "list_worktree_threads",
+            json!({ "workspaceId": workspace_id }),
         )
         .await;
     }
 
-    match workspace_provider(&state, &workspace_id).await? {
-        ProviderKind::Codex => {
-            codex_core::list_mcp_server_status_core(&state.sessions, workspace_id, cursor, limit)
-                .await
+    let parent_id =
+        thread_inheritance_core::worktree_parent_id(&state.workspaces, &workspace_id).await?;
+    let own = list_workspace_threads(&state, workspace_id.clone()).await?;
+    let parent = match parent_id {
+        Some(parent_id) => {
+            let threads = list_workspace_threads(&state, parent_id.clone()).await?;
+            Some((parent_id, threads))
         }
-        ProviderKind::Claude => Ok(json!({ "result": { "data": [], "nextCursor": null } })),
-        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
-    }
+        None => None,
+    };
+    Ok(thread_inheritance_core::inherited_threads_core(
+        &workspace_id,
+        own,
+        parent,
+    ))
 }
 
 #[tauri::command]
-pub(crate) async fn archive_thread(
+pub(crate) async fn threads_sync(
     workspace_id: String,
-    thread_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -422,33 +481,31 @@ pub(crate) async fn archive_thread(
         return remote_backend::call_remote(
             &*state,
             app,
-            "archive_thread",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+            "threads_sync",
+            json!({ "workspaceId": workspace_id }),
         )
         .await;
     }
 
-    match thread_provider(&state, &workspace_id, &thread_id).await? {
-        ProviderKind::Codex => {
-            codex_core::archive_thread_core(&state.sessions, workspace_id, thread_id).await
-        }
-        ProviderKind::Claude => {
-            claude_core::archive_thread_core(
-                &state.claude_threads,
-                &state.claude_threads_path,
-                workspace_id,
-                thread_id,
-            )
-            .await
-        }
-        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
-    }
+    let workspace_path = workspace_path(&state, &workspace_id).await?;
+    let redaction = RedactionPipeline::compile(&state.app_settings.lock().await.redaction_rules);
+    thread_sync_core::sync_threads_core(
+        &state.app_settings,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        &workspace_id,
+        &workspace_path,
+        &redaction,
+        &TauriEventSink::new(app),
+    )
+    .await
 }
 
 #[tauri::command]
-pub(crate) async fn compact_thread(
-    workspace_id: String,
-    thread_id: String,
+pub(crate) async fn threads_quick_find(
+    workspace_id: Option<String>,
+    query: String,
+    limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -456,20 +513,71 @@ pub(crate) async fn compact_thread(
         return remote_backend::call_remote(
             &*state,
             app,
-            "compact_thread",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+            "threads_quick_find",
+            json!({ "workspaceId": workspace_id, "query": query, "limit": limit }),
         )
         .await;
     }
 
-    codex_core::compact_thread_core(&state.sessions, workspace_id, thread_id).await
+    thread_quick_find_core::quick_find_core(workspace_id.as_deref(), &query, limit)
 }
 
 #[tauri::command]
-pub(crate) async fn set_thread_name(
-    workspace_id: String,
-    thread_id: String,
-    name: String,
+pub(crate) async fn inbox_list(
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "inbox_list", json!({ "limit": limit }))
+            .await;
+    }
+
+    inbox_core::inbox_list_core(
+        &state.workspaces,
+        &state.sessions,
+        &state.claude_threads,
+        limit,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn lan_sync_start(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "LAN sync").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "lan_sync_start", json!({})).await;
+    }
+
+    lan_sync_core::lan_sync_start_core(
+        &state.lan_sync,
+        &state.app_settings,
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn lan_sync_stop(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "lan_sync_stop", json!({})).await;
+    }
+
+    lan_sync_core::lan_sync_stop_core(&state.lan_sync).await
+}
+
+#[tauri::command]
+pub(crate) async fn lan_sync_discover(
+    timeout_ms: Option<u64>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -477,114 +585,49 @@ pub(crate) async fn set_thread_name(
         return remote_backend::call_remote(
             &*state,
             app,
-            "set_thread_name",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id, "name": name }),
+            "lan_sync_discover",
+            json!({ "timeoutMs": timeout_ms }),
         )
         .await;
     }
 
-    match thread_provider(&state, &workspace_id, &thread_id).await? {
-        ProviderKind::Codex => {
-            codex_core::set_thread_name_core(&state.sessions, workspace_id, thread_id, name).await
-        }
-        ProviderKind::Claude => {
-            claude_core::set_thread_name_core(
-                &state.claude_threads,
-                &state.claude_threads_path,
-                workspace_id,
-                thread_id,
-                name,
-            )
-            .await
-        }
-        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
-    }
+    lan_sync_core::lan_sync_discover_core(&state.lan_sync, timeout_ms).await
 }
 
 #[tauri::command]
-pub(crate) async fn send_user_message(
-    workspace_id: String,
-    thread_id: String,
-    text: String,
-    model: Option<String>,
-    effort: Option<String>,
-    access_mode: Option<String>,
-    images: Option<Vec<String>>,
-    collaboration_mode: Option<Value>,
+pub(crate) async fn lan_sync_with_peer(
+    address: String,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "LAN sync").await?;
     if remote_backend::is_remote_mode(&*state).await {
-        let images = images.map(|paths| {
-            paths
-                .into_iter()
-                .map(remote_backend::normalize_path_for_remote)
-                .collect::<Vec<_>>()
-        });
-        let mut payload = Map::new();
-        payload.insert("workspaceId".to_string(), json!(workspace_id));
-        payload.insert("threadId".to_string(), json!(thread_id));
-        payload.insert("text".to_string(), json!(text));
-        payload.insert("model".to_string(), json!(model));
-        payload.insert("effort".to_string(), json!(effort));
-        payload.insert("accessMode".to_string(), json!(access_mode));
-        payload.insert("images".to_string(), json!(images));
-        if let Some(mode) = collaboration_mode {
-            if !mode.is_null() {
-                payload.insert("collaborationMode".to_string(), mode);
-            }
-        }
         return remote_backend::call_remote(
             &*state,
             app,
-            "send_user_message",
-            Value::Object(payload),
+            "lan_sync_with_peer",
+            json!({ "address": address }),
         )
         .await;
     }
 
-    match thread_provider(&state, &workspace_id, &thread_id).await? {
-        ProviderKind::Codex => {
-            codex_core::send_user_message_core(
-                &state.sessions,
-                workspace_id,
-                thread_id,
-                text,
-                model,
-                effort,
-                access_mode,
-                images,
-                collaboration_mode,
-            )
-            .await
-        }
-        ProviderKind::Claude => {
-            let _ = model;
-            let _ = effort;
-            let _ = access_mode;
-            let _ = collaboration_mode;
-            let event_sink = TauriEventSink::new(app);
-            claude_core::send_user_message_core(
-                &state.workspaces,
-                &state.app_settings,
-                &state.claude_threads,
-                &state.claude_turn_cancels,
-                &state.claude_threads_path,
-                workspace_id,
-                thread_id,
-                text,
-                images,
-                event_sink,
-            )
-            .await
-        }
-        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
-    }
+    lan_sync_core::lan_sync_with_peer_core(
+        &state.lan_sync,
+        &state.app_settings,
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        address,
+        TauriEventSink::new(app),
+    )
+    .await
 }
 
 #[tauri::command]
-pub(crate) async fn collaboration_mode_list(
+pub(crate) async fn list_mcp_server_status(
     workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -592,43 +635,51 @@ pub(crate) async fn collaboration_mode_list(
         return remote_backend::call_remote(
             &*state,
             app,
-            "collaboration_mode_list",
-            json!({ "workspaceId": workspace_id }),
+            "list_mcp_server_status",
+            json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
         )
         .await;
     }
 
-    codex_core::collaboration_mode_list_core(&state.sessions, workspace_id).await
+    match workspace_provider(&state, &workspace_id).await? {
+        ProviderKind::Codex => {
+            codex_core::list_mcp_server_status_core(&state.sessions, workspace_id, cursor, limit)
+                .await
+        }
+        ProviderKind::Claude => Ok(json!({ "result": { "data": [], "nextCursor": null } })),
+        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+    }
 }
 
 #[tauri::command]
-pub(crate) async fn turn_interrupt(
+pub(crate) async fn archive_thread(
     workspace_id: String,
     thread_id: String,
-    turn_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Archiving threads").await?;
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
-            "turn_interrupt",
-            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+            "archive_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
         )
         .await;
     }
 
     match thread_provider(&state, &workspace_id, &thread_id).await? {
         ProviderKind::Codex => {
-            codex_core::turn_interrupt_core(&state.sessions, workspace_id, thread_id, turn_id).await
+            codex_core::archive_thread_core(&state.sessions, workspace_id, thread_id).await
         }
         ProviderKind::Claude => {
-            let _ = turn_id;
-            claude_core::turn_interrupt_core(
-                &state.claude_turn_cancels,
+            claude_core::archive_thread_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
                 workspace_id,
                 thread_id,
+                TauriEventSink::new(app),
             )
             .await
         }
@@ -637,30 +688,2058 @@ pub(crate) async fn turn_interrupt(
 }
 
 #[tauri::command]
-pub(crate) async fn start_review(
+pub(crate) async fn bulk_thread_op(
     workspace_id: String,
-    thread_id: String,
-    target: Value,
-    delivery: Option<String>,
+    thread_ids: Vec<String>,
+    op: String,
+    tags: Option<Vec<String>>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
+    if op.trim() != "export" {
+        settings_core::ensure_not_read_only(&state.app_settings, "Bulk thread changes").await?;
+    }
     if remote_backend::is_remote_mode(&*state).await {
         return remote_backend::call_remote(
             &*state,
             app,
-            "start_review",
-            json!({
-                "workspaceId": workspace_id,
-                "threadId": thread_id,
-                "target": target,
-                "delivery": delivery,
-            }),
+            "bulk_thread_op",
+            json!({ "workspaceId": workspace_id, "threadIds": thread_ids, "op": op, "tags": tags }),
         )
         .await;
     }
 
-    codex_core::start_review_core(&state.sessions, workspace_id, thread_id, target, delivery).await
+    let op = claude_core::BulkThreadOp::parse(&op, tags)?;
+    let redaction = RedactionPipeline::compile(&state.app_settings.lock().await.redaction_rules);
+    claude_core::bulk_thread_op_core(
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        thread_ids,
+        op,
+        &redaction,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_trash(
+    workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "list_trash",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    trash_core::list_trash_core(&state.claude_threads_path, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn restore_trash(
+    workspace_id: String,
+    thread_ids: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Restoring threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "restore_trash",
+            json!({ "workspaceId": workspace_id, "threadIds": thread_ids }),
+        )
+        .await;
+    }
+
+    trash_core::restore_trash_core(
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        thread_ids,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn actions_list(
+    query: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "actions_list",
+            json!({ "query": query }),
+        )
+        .await;
+    }
+
+    let read_only = state.app_settings.lock().await.read_only;
+    Ok(actions_core::actions_list_core(query, read_only))
+}
+
+#[tauri::command]
+pub(crate) async fn provider_rate_limit_state(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "provider_rate_limit_state", json!({}))
+            .await;
+    }
+
+    Ok(rate_limit_core::rate_limit_state_core(
+        &state.rate_limits,
+        claude_core::now_ms(),
+    ))
+}
+
+/// Running and queued turns and recent errors across all workspaces, for a tray or menu bar.
+#[tauri::command]
+pub(crate) async fn status_summary(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "status_summary", json!({})).await;
+    }
+
+    status_summary_core::status_summary_core(
+        &state.status,
+        state.turn_slots.queued(),
+        claude_core::now_ms(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn orphans_list(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "orphans_list", json!({})).await;
+    }
+
+    orphans_core::list_orphans_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        &state.drafts,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn orphans_purge(
+    workspace_ids: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Purging orphaned data").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "orphans_purge",
+            json!({ "workspaceIds": workspace_ids }),
+        )
+        .await;
+    }
+
+    orphans_core::purge_orphans_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        &state.drafts,
+        &state.drafts_path,
+        workspace_ids,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn import_codex_sessions(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Importing Codex sessions").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "import_codex_sessions",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    let redaction = RedactionPipeline::compile(&state.app_settings.lock().await.redaction_rules);
+    codex_import_core::import_codex_sessions_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        &redaction,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn import_external(
+    workspace_id: String,
+    format: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Importing threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        let path = remote_backend::normalize_path_for_remote(path);
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "import_external",
+            json!({ "workspaceId": workspace_id, "format": format, "path": path }),
+        )
+        .await;
+    }
+
+    let redaction = RedactionPipeline::compile(&state.app_settings.lock().await.redaction_rules);
+    external_import_core::import_external_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        format,
+        path,
+        &redaction,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn export_threads(
+    workspace_id: String,
+    thread_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "export_threads",
+            json!({ "workspaceId": workspace_id, "threadIds": thread_ids }),
+        )
+        .await;
+    }
+
+    let redaction = RedactionPipeline::compile(&state.app_settings.lock().await.redaction_rules);
+    thread_export_core::export_threads_core(
+        &state.claude_threads,
+        workspace_id,
+        thread_ids,
+        &redaction,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn import_threads(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Importing threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        let path = remote_backend::normalize_path_for_remote(path);
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "import_threads",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await;
+    }
+
+    thread_export_core::import_threads_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        path,
+        TauriEventSink::new(app),
+    )
+    .await
+}
+
+/// The JSON Schema exports follow; the same as `docs/thread-export.schema.json`.
+#[tauri::command]
+pub(crate) async fn thread_export_schema() -> Result<Value, String> {
+    thread_export_core::thread_export_schema_core()
+}
+
+/// Agent processes from the audit log, newest first. `since` and `until` are ms since the epoch.
+#[tauri::command]
+pub(crate) async fn audit_query(
+    since: Option<i64>,
+    until: Option<i64>,
+    workspace_id: Option<String>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "audit_query",
+            json!({ "since": since, "until": until, "workspaceId": workspace_id, "limit": limit }),
+        )
+        .await;
+    }
+
+    audit_core::audit_query_core(
+        &state.data_dir,
+        since,
+        until,
+        workspace_id,
+        limit.map(|limit| limit as usize),
+    )
+}
+
+#[tauri::command]
+pub(crate) async fn storage_usage(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "storage_usage", json!({})).await;
+    }
+
+    retention_core::storage_usage_core(
+        &state.workspaces,
+        &state.claude_threads,
+        &state.claude_threads_path,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn storage_migrate(
+    path: Option<String>,
+    portable: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "storage_migrate",
+            json!({ "path": path, "portable": portable }),
+        )
+        .await;
+    }
+
+    let default_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
+    data_dir_core::migrate_data_dir_core(
+        &state.app_settings,
+        &default_dir,
+        &state.data_dir,
+        path,
+        portable.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn profile_list(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "profile_list", json!({})).await;
+    }
+
+    data_dir_core::list_profiles_core(&state.data_dir)
+}
+
+/// Switches to another profile by restarting the app into it; the shutdown on exit saves the
+/// current profile's stores before the new profile's are loaded.
+#[tauri::command]
+pub(crate) async fn profile_switch(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "profile_switch",
+            json!({ "name": name }),
+        )
+        .await;
+    }
+
+    if shutdown_core::running_turn_count(&state.claude_turn_cancels, &state.sessions).await > 0 {
+        return Err("Wait for running turns to finish before switching profiles.".to_string());
+    }
+    let default_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
+    let response = data_dir_core::switch_profile_core(
+        &state.app_settings,
+        &default_dir,
+        &state.data_dir,
+        name,
+    )
+    .await?;
+    app.request_restart();
+    Ok(response)
+}
+
+#[tauri::command]
+pub(crate) async fn compact_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Compacting threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "compact_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    codex_core::compact_thread_core(&state.sessions, workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_name(
+    workspace_id: String,
+    thread_id: String,
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Renaming threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_name",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "name": name }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Codex => {
+            codex_core::set_thread_name_core(&state.sessions, workspace_id, thread_id, name).await
+        }
+        ProviderKind::Claude => {
+            claude_core::set_thread_name_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                name,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn mark_thread_read(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "mark_thread_read",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::mark_read_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not track read state.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_item_pinned(
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    pinned: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Pinning messages").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_item_pinned",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "itemId": item_id,
+                "pinned": pinned,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::set_item_pinned_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                item_id,
+                pinned,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support pinned messages.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn thread_pinned_items(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "thread_pinned_items",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::pinned_items_core(&state.claude_threads, workspace_id, thread_id).await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support pinned messages.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn diff_items(
+    workspace_id: String,
+    left_thread_id: String,
+    left_item_id: String,
+    right_thread_id: String,
+    right_item_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "diff_items",
+            json!({
+                "workspaceId": workspace_id,
+                "leftThreadId": left_thread_id,
+                "leftItemId": left_item_id,
+                "rightThreadId": right_thread_id,
+                "rightItemId": right_item_id,
+            }),
+        )
+        .await;
+    }
+
+    for thread_id in [&left_thread_id, &right_thread_id] {
+        let provider = thread_provider(&state, &workspace_id, thread_id).await?;
+        if provider != ProviderKind::Claude {
+            return Err(format!(
+                "Provider `{}` does not keep messages to compare.",
+                provider.as_str()
+            ));
+        }
+    }
+    answer_diff_core::diff_items_core(
+        &state.claude_threads,
+        workspace_id,
+        left_thread_id,
+        left_item_id,
+        right_thread_id,
+        right_item_id,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn duplicate_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Duplicating threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "duplicate_thread",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::duplicate_thread_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support duplicating threads.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn move_thread(
+    workspace_id: String,
+    thread_id: String,
+    target_workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Moving threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "move_thread",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "targetWorkspaceId": target_workspace_id,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::move_thread_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                target_workspace_id,
+                thread_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support moving threads between workspaces.",
+            provider.as_str()
+        )),
+    }
+}
+
+/// Fixes a thread whose directory is gone by pointing it at a workspace that exists.
+#[tauri::command]
+pub(crate) async fn rebind_thread_workspace(
+    workspace_id: String,
+    thread_id: String,
+    target_workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Rebinding threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "rebind_thread_workspace",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "targetWorkspaceId": target_workspace_id,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            thread_rebind_core::rebind_thread_workspace_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                target_workspace_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support rebinding threads to workspaces.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn copy_thread(
+    workspace_id: String,
+    thread_id: String,
+    target_workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Copying threads").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "copy_thread",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "targetWorkspaceId": target_workspace_id,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::copy_thread_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                target_workspace_id,
+                thread_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support copying threads between workspaces.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_cwd(
+    workspace_id: String,
+    thread_id: String,
+    cwd: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Changing thread directories").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_cwd",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "cwd": cwd }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::set_thread_cwd_core(
+                &state.workspaces,
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                cwd,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support thread directories.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn set_thread_generation(
+    workspace_id: String,
+    thread_id: String,
+    model: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Changing generation settings")
+        .await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "set_thread_generation",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "model": model,
+                "temperature": temperature,
+                "maxTokens": max_tokens,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::set_thread_generation_core(
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                model,
+                temperature,
+                max_tokens,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support generation settings.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn merge_thread_worktree(
+    workspace_id: String,
+    thread_id: String,
+    remove_worktree: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Merging thread worktrees").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "merge_thread_worktree",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "removeWorktree": remove_worktree,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            thread_worktree_core::merge_thread_worktree_core(
+                &state.workspaces,
+                &state.claude_threads,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                remove_worktree.unwrap_or(true),
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support thread worktrees.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn revert_turn_changes(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Reverting turn changes").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "revert_turn_changes",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            turn_snapshot_core::revert_turn_changes_core(
+                &state.claude_threads,
+                &state.claude_turn_cancels,
+                workspace_id,
+                thread_id,
+                turn_id,
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support turn snapshots.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn memory_read(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "memory_read",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    memory_core::memory_read_core(&state.workspaces, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn memory_write(
+    workspace_id: String,
+    content: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing memory").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "memory_write",
+            json!({ "workspaceId": workspace_id, "content": content }),
+        )
+        .await;
+    }
+
+    memory_core::memory_write_core(&state.workspaces, workspace_id, content).await
+}
+
+#[tauri::command]
+pub(crate) async fn memory_append(
+    workspace_id: String,
+    text: Option<String>,
+    thread_id: Option<String>,
+    turn_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing memory").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "memory_append",
+            json!({
+                "workspaceId": workspace_id,
+                "text": text,
+                "threadId": thread_id,
+                "turnId": turn_id,
+            }),
+        )
+        .await;
+    }
+
+    memory_core::memory_append_core(
+        &state.workspaces,
+        &state.claude_threads,
+        workspace_id,
+        text,
+        thread_id,
+        turn_id,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn memory_clear(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing memory").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "memory_clear",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    memory_core::memory_clear_core(&state.workspaces, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn claude_hooks_read(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "claude_hooks_read",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    claude_hooks_core::claude_hooks_read_core(&state.workspaces, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn claude_hooks_write(
+    workspace_id: String,
+    hooks: claude_hooks_core::HooksConfig,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing Claude hooks").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "claude_hooks_write",
+            json!({ "workspaceId": workspace_id, "hooks": hooks }),
+        )
+        .await;
+    }
+
+    claude_hooks_core::claude_hooks_write_core(&state.workspaces, workspace_id, hooks).await
+}
+
+#[tauri::command]
+pub(crate) async fn save_draft(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "save_draft",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "text": text,
+                "images": images,
+            }),
+        )
+        .await;
+    }
+
+    drafts_core::save_draft_core(
+        &state.app_settings,
+        &state.drafts,
+        &state.drafts_path,
+        workspace_id,
+        thread_id,
+        text,
+        images,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn get_draft(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "get_draft",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    drafts_core::get_draft_core(&state.drafts, workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn prompt_estimate(
+    workspace_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+    model: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "prompt_estimate",
+            json!({
+                "workspaceId": workspace_id,
+                "text": text,
+                "images": images,
+                "model": model,
+            }),
+        )
+        .await;
+    }
+
+    prompt_estimate_core::estimate_prompt_core(
+        &state.workspaces,
+        &state.app_settings,
+        workspace_id,
+        text,
+        images,
+        model,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_playbooks(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "list_playbooks", json!({})).await;
+    }
+
+    playbooks_core::list_playbooks_core(&state.playbooks_path).await
+}
+
+#[tauri::command]
+pub(crate) async fn save_playbook(
+    id: Option<String>,
+    name: String,
+    steps: Vec<playbooks_core::PlaybookStep>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Saving playbooks").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "save_playbook",
+            json!({ "id": id, "name": name, "steps": steps }),
+        )
+        .await;
+    }
+
+    playbooks_core::save_playbook_core(&state.playbooks_path, id, name, steps).await
+}
+
+#[tauri::command]
+pub(crate) async fn delete_playbook(
+    id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Deleting playbooks").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "delete_playbook", json!({ "id": id }))
+            .await;
+    }
+
+    playbooks_core::delete_playbook_core(&state.playbooks_path, id).await
+}
+
+#[tauri::command]
+pub(crate) async fn run_playbook(
+    workspace_id: String,
+    thread_id: String,
+    playbook_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Running playbooks").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "run_playbook",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "playbookId": playbook_id,
+            }),
+        )
+        .await;
+    }
+
+    let provider = thread_provider(&state, &workspace_id, &thread_id).await?;
+    if !matches!(provider, ProviderKind::Claude) {
+        return Err(format!(
+            "Provider `{}` does not support playbooks.",
+            provider.as_str()
+        ));
+    }
+    let run = playbooks_core::start_playbook_run_core(
+        &state.playbooks_path,
+        &state.playbook_runs,
+        workspace_id,
+        thread_id,
+        playbook_id,
+    )
+    .await?;
+    let summary = run.summary();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let ctx = playbooks_core::PlaybookTurnContext {
+            workspaces: &state.workspaces,
+            app_settings: &state.app_settings,
+            claude_threads: &state.claude_threads,
+            claude_turn_cancels: &state.claude_turn_cancels,
+            claude_processes: &state.claude_processes,
+            claude_approvals: &state.claude_approvals,
+            turn_slots: &state.turn_slots,
+            thread_locks: &state.thread_locks,
+            rate_limits: &state.rate_limits,
+            claude_threads_path: &state.claude_threads_path,
+            playbook_runs: &state.playbook_runs,
+        };
+        playbooks_core::run_playbook_core(ctx, run, TauriEventSink::new(app.clone())).await;
+    });
+    Ok(summary)
+}
+
+#[tauri::command]
+pub(crate) async fn respond_playbook_step(
+    run_id: String,
+    approved: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "respond_playbook_step",
+            json!({ "runId": run_id, "approved": approved }),
+        )
+        .await;
+    }
+
+    playbooks_core::respond_playbook_step_core(&state.playbook_runs, run_id, approved).await
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_playbook(
+    run_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "cancel_playbook",
+            json!({ "runId": run_id }),
+        )
+        .await;
+    }
+
+    playbooks_core::cancel_playbook_core(&state.playbook_runs, &state.claude_turn_cancels, run_id)
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn respond_to_approval(
+    approval_id: String,
+    allow: bool,
+    message: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "respond_to_approval",
+            json!({ "approvalId": approval_id, "allow": allow, "message": message }),
+        )
+        .await;
+    }
+
+    claude_pool_core::respond_to_approval_core(&state.claude_approvals, approval_id, allow, message)
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn turn_patches(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "turn_patches",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            patches_core::turn_patches_core(&state.claude_threads, workspace_id, thread_id, turn_id)
+                .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support patch extraction.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn turn_artifacts(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "turn_artifacts",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    turn_artifacts_core::turn_artifacts_core(&state.data_dir, &workspace_id, &thread_id, &turn_id)
+}
+
+#[tauri::command]
+pub(crate) async fn turn_details(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "turn_details",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    turn_details_core::turn_details_core(&state.claude_threads, &workspace_id, &thread_id, &turn_id)
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn apply_patch(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    patch_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Applying patches").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "apply_patch",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "turnId": turn_id,
+                "patchId": patch_id,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            patches_core::apply_patch_core(
+                &state.workspaces,
+                &state.claude_threads,
+                workspace_id,
+                thread_id,
+                turn_id,
+                patch_id,
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support patch extraction.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn send_user_message(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    images: Option<Vec<String>>,
+    collaboration_mode: Option<Value>,
+    dry_run: Option<bool>,
+    plan: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let plan = plan.unwrap_or(false);
+    if !dry_run {
+        settings_core::ensure_not_read_only(&state.app_settings, "Sending messages").await?;
+    }
+    if remote_backend::is_remote_mode(&*state).await {
+        let images = images.map(|paths| {
+            paths
+                .into_iter()
+                .map(remote_backend::normalize_path_for_remote)
+                .collect::<Vec<_>>()
+        });
+        let mut payload = Map::new();
+        payload.insert("workspaceId".to_string(), json!(workspace_id));
+        payload.insert("threadId".to_string(), json!(thread_id));
+        payload.insert("text".to_string(), json!(text));
+        payload.insert("model".to_string(), json!(model));
+        payload.insert("effort".to_string(), json!(effort));
+        payload.insert("accessMode".to_string(), json!(access_mode));
+        payload.insert("images".to_string(), json!(images));
+        if dry_run {
+            payload.insert("dryRun".to_string(), json!(true));
+        }
+        if plan {
+            payload.insert("plan".to_string(), json!(true));
+        }
+        if let Some(mode) = collaboration_mode {
+            if !mode.is_null() {
+                payload.insert("collaborationMode".to_string(), mode);
+            }
+        }
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "send_user_message",
+            Value::Object(payload),
+        )
+        .await;
+    }
+
+    let sent = (workspace_id.clone(), thread_id.clone(), text.clone());
+    let result = match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Codex if dry_run => {
+            Err("Dry runs are only supported for Claude threads.".to_string())
+        }
+        ProviderKind::Codex if plan => {
+            Err("Plan mode is only supported for Claude threads.".to_string())
+        }
+        ProviderKind::Codex => {
+            codex_core::send_user_message_core(
+                &state.sessions,
+                workspace_id,
+                thread_id,
+                text,
+                model,
+                effort,
+                access_mode,
+                images,
+                collaboration_mode,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        ProviderKind::Claude => {
+            let _ = model;
+            let _ = effort;
+            let _ = access_mode;
+            let _ = collaboration_mode;
+            let event_sink = TauriEventSink::new(app);
+            claude_core::send_user_message_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_turn_cancels,
+                &state.claude_processes,
+                &state.claude_approvals,
+                &state.turn_slots,
+                &state.thread_locks,
+                &state.rate_limits,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                text,
+                images,
+                dry_run,
+                plan,
+                event_sink,
+            )
+            .await
+        }
+        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+    }?;
+    if !dry_run {
+        let (workspace_id, thread_id, text) = sent;
+        if let Err(error) = prompt_history_core::record_prompt(
+            &state.prompt_history,
+            &state.prompt_history_path,
+            &workspace_id,
+            &thread_id,
+            &text,
+        )
+        .await
+        {
+            eprintln!("Failed to save prompt history: {error}");
+        }
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub(crate) async fn execute_plan(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Executing plans").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "execute_plan",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            claude_core::execute_plan_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_turn_cancels,
+                &state.claude_processes,
+                &state.claude_approvals,
+                &state.turn_slots,
+                &state.thread_locks,
+                &state.rate_limits,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                turn_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support plan mode.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn edit_message(
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    text: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing messages").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "edit_message",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "itemId": item_id,
+                "text": text,
+            }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            thread_edit_core::edit_message_core(
+                &state.workspaces,
+                &state.app_settings,
+                &state.claude_threads,
+                &state.claude_turn_cancels,
+                &state.claude_processes,
+                &state.claude_approvals,
+                &state.turn_slots,
+                &state.thread_locks,
+                &state.rate_limits,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                item_id,
+                text,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support editing messages.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn undo_edit_message(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Editing messages").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "undo_edit_message",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Claude => {
+            thread_edit_core::undo_edit_message_core(
+                &state.claude_threads,
+                &state.claude_turn_cancels,
+                &state.claude_threads_path,
+                workspace_id,
+                thread_id,
+                TauriEventSink::new(app),
+            )
+            .await
+        }
+        provider => Err(format!(
+            "Provider `{}` does not support editing messages.",
+            provider.as_str()
+        )),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn send_with_context(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    context: Vec<SelectionContextBlock>,
+    images: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    let text = compose_message_with_context(&text, &context)?;
+    send_user_message(
+        workspace_id,
+        thread_id,
+        text,
+        None,
+        None,
+        None,
+        images,
+        None,
+        None,
+        None,
+        state,
+        app,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn prompts_history(
+    workspace_id: String,
+    query: Option<String>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "prompts_history",
+            json!({ "workspaceId": workspace_id, "query": query, "limit": limit }),
+        )
+        .await;
+    }
+
+    prompt_history_core::prompt_history_core(&state.prompt_history, workspace_id, query, limit)
+        .await
+}
+
+/// Sends a prompt from the workspace's history to `thread_id`, which may be any thread.
+#[tauri::command]
+pub(crate) async fn prompts_history_resend(
+    workspace_id: String,
+    thread_id: String,
+    prompt_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "prompts_history_resend",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "promptId": prompt_id }),
+        )
+        .await;
+    }
+
+    let text =
+        prompt_history_core::prompt_history_text(&state.prompt_history, &workspace_id, &prompt_id)
+            .await?;
+    send_user_message(
+        workspace_id,
+        thread_id,
+        text,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        state,
+        app,
+    )
+    .await
+}
+
+/// Sends `text` to the workspace's most recently updated thread.
+#[tauri::command]
+pub(crate) async fn reply_latest(
+    workspace_id: String,
+    text: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "reply_latest",
+            json!({ "workspaceId": workspace_id, "text": text }),
+        )
+        .await;
+    }
+
+    let threads = list_workspace_threads(&state, workspace_id.clone()).await?;
+    quick_reply_core::reply_latest_core(threads, text, |thread_id, text| {
+        send_user_message(
+            workspace_id,
+            thread_id,
+            text,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            state,
+            app,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn send_audio_message(
+    workspace_id: String,
+    thread_id: String,
+    audio: String,
+    mime_type: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    let dictation_app = &app;
+    let transcript = transcription_core::transcribe_audio_message_core(
+        &state.app_settings,
+        &audio,
+        mime_type,
+        |bytes| async move { dictation::dictation_transcribe_wav(dictation_app, &bytes).await },
+    )
+    .await?;
+    let response = send_user_message(
+        workspace_id,
+        thread_id,
+        transcript.clone(),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        state,
+        app,
+    )
+    .await?;
+    Ok(json!({ "transcript": transcript, "response": response }))
+}
+
+#[tauri::command]
+pub(crate) async fn collaboration_mode_list(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "collaboration_mode_list",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
+    }
+
+    codex_core::collaboration_mode_list_core(&state.sessions, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn turn_interrupt(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "turn_interrupt",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "turnId": turn_id }),
+        )
+        .await;
+    }
+
+    match thread_provider(&state, &workspace_id, &thread_id).await? {
+        ProviderKind::Codex => {
+            codex_core::turn_interrupt_core(&state.sessions, workspace_id, thread_id, turn_id).await
+        }
+        ProviderKind::Claude => {
+            claude_core::turn_interrupt_core(
+                &state.claude_turn_cancels,
+                &state.claude_threads,
+                workspace_id,
+                thread_id,
+                Some(turn_id),
+            )
+            .await
+        }
+        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn follow_claude_session(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "follow_claude_session",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    let workspace_path = workspace_path(&state, &workspace_id).await?;
+    let event_sink = TauriEventSink::new(app);
+    claude_follow_core::follow_session_start_core(
+        &state.claude_follows,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        workspace_path,
+        thread_id,
+        event_sink,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn unfollow_claude_session(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "unfollow_claude_session",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await;
+    }
+
+    claude_follow_core::follow_session_stop_core(&state.claude_follows, workspace_id, thread_id)
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn start_review(
+    workspace_id: String,
+    thread_id: String,
+    target: Value,
+    delivery: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    settings_core::ensure_not_read_only(&state.app_settings, "Starting reviews").await?;
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "start_review",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "target": target,
+                "delivery": delivery,
+            }),
+        )
+        .await;
+    }
+
+    codex_core::start_review_core(&state.sessions, workspace_id, thread_id, target, delivery).await
 }
 
 #[tauri::command]
@@ -682,7 +2761,7 @@ pub(crate) async fn model_list(
     match workspace_provider(&state, &workspace_id).await? {
         ProviderKind::Codex => codex_core::model_list_core(&state.sessions, workspace_id).await,
         ProviderKind::Claude => Ok(json!({ "result": { "data": [] } })),
-        ProviderKind::Gemini => Err("Provider `gemini` is not implemented yet.".to_string()),
+        ProviderKind::Gemini => gemini_core::model_list_core(&state.gemini_models).await,
     }
 }
 
@@ -858,16 +2937,6 @@ pub(crate) async fn respond_to_server_request(
         .await
 }
 
-fn build_commit_message_prompt(diff: &str) -> String {
-    format!(
-        "Generate a concise git commit message for the following changes. \
-Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
-Keep the summary line under 72 characters. \
-Only output the commit message, nothing else.\n\n\
-Changes:\n{diff}"
-    )
-}
-
 /// Gets the diff content for commit message generation
 #[tauri::command]
 pub(crate) async fn get_commit_message_prompt(
@@ -881,7 +2950,7 @@ pub(crate) async fn get_commit_message_prompt(
         return Err("No changes to generate commit message for".to_string());
     }
 
-    let prompt = build_commit_message_prompt(&diff);
+    let prompt = commit_message_core::build_commit_message_prompt(&diff);
 
     Ok(prompt)
 }
@@ -914,180 +2983,60 @@ pub(crate) async fn get_config_model(
     codex_core::get_config_model_core(&state.workspaces, workspace_id).await
 }
 
-/// Generates a commit message in the background without showing in the main chat
 #[tauri::command]
-pub(crate) async fn generate_commit_message(
+pub(crate) async fn config_effective(
     workspace_id: String,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<String, String> {
-    // Get the diff from git
-    let diff = crate::git::get_workspace_diff(&workspace_id, &state).await?;
-
-    if diff.trim().is_empty() {
-        return Err("No changes to generate commit message for".to_string());
-    }
-
-    let prompt = build_commit_message_prompt(&diff);
-
-    // Get the session
-    let session = {
-        let sessions = state.sessions.lock().await;
-        sessions
-            .get(&workspace_id)
-            .ok_or("workspace not connected")?
-            .clone()
-    };
-
-    // Create a background thread
-    let thread_params = json!({
-        "cwd": session.entry.path,
-        "approvalPolicy": "never"  // Never ask for approval in background
-    });
-    let thread_result = session.send_request("thread/start", thread_params).await?;
-
-    // Handle error response
-    if let Some(error) = thread_result.get("error") {
-        let error_msg = error
-            .get("message")
-            .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting thread");
-        return Err(error_msg.to_string());
-    }
-
-    // Extract threadId - try multiple paths since response format may vary
-    let thread_id = thread_result
-        .get("result")
-        .and_then(|r| r.get("threadId"))
-        .or_else(|| thread_result.get("result").and_then(|r| r.get("thread")).and_then(|t| t.get("id")))
-        .or_else(|| thread_result.get("threadId"))
-        .or_else(|| thread_result.get("thread").and_then(|t| t.get("id")))
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| format!("Failed to get threadId from thread/start response: {:?}", thread_result))?
-        .to_string();
-
-    // Hide background helper threads from the sidebar, even if a thread/started event leaked.
-    let _ = app.emit(
-        "app-server-event",
-        AppServerEvent {
-            workspace_id: workspace_id.clone(),
-            message: json!({
-                "method": "codex/backgroundThread",
-                "params": {
-                    "threadId": thread_id,
-                    "action": "hide"
-                }
-            }),
-        },
-    );
-
-    // Create channel for receiving events
-    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
-
-    // Register callback for this thread
-    {
-        let mut callbacks = session.background_thread_callbacks.lock().await;
-        callbacks.insert(thread_id.clone(), tx);
-    }
-
-    // Start a turn with the commit message prompt
-    let turn_params = json!({
-        "threadId": thread_id,
-        "input": [{ "type": "text", "text": prompt }],
-        "cwd": session.entry.path,
-        "approvalPolicy": "never",
-        "sandboxPolicy": { "type": "readOnly" },
-    });
-    let turn_result = session.send_request("turn/start", turn_params).await;
-    let turn_result = match turn_result {
-        Ok(result) => result,
-        Err(error) => {
-            // Clean up if turn fails to start
-            {
-                let mut callbacks = session.background_thread_callbacks.lock().await;
-                callbacks.remove(&thread_id);
-            }
-            let archive_params = json!({ "threadId": thread_id.as_str() });
-            let _ = session.send_request("thread/archive", archive_params).await;
-            return Err(error);
-        }
-    };
-
-    if let Some(error) = turn_result.get("error") {
-        let error_msg = error
-            .get("message")
-            .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting turn");
-        {
-            let mut callbacks = session.background_thread_callbacks.lock().await;
-            callbacks.remove(&thread_id);
-        }
-        let archive_params = json!({ "threadId": thread_id.as_str() });
-        let _ = session.send_request("thread/archive", archive_params).await;
-        return Err(error_msg.to_string());
-    }
-
-    // Collect assistant text from events
-    let mut commit_message = String::new();
-    let timeout_duration = Duration::from_secs(60);
-    let collect_result = timeout(timeout_duration, async {
-        while let Some(event) = rx.recv().await {
-            let method = event.get("method").and_then(|m| m.as_str()).unwrap_or("");
-
-            match method {
-                "item/agentMessage/delta" => {
-                    // Extract text delta from agent messages
-                    if let Some(params) = event.get("params") {
-                        if let Some(delta) = params.get("delta").and_then(|d| d.as_str()) {
-                            commit_message.push_str(delta);
-                        }
-                    }
-                }
-                "turn/completed" => {
-                    // Turn completed, we can stop listening
-                    break;
-                }
-                "turn/error" => {
-                    // Error occurred
-                    let error_msg = event
-                        .get("params")
-                        .and_then(|p| p.get("error"))
-                        .and_then(|e| e.as_str())
-                        .unwrap_or("Unknown error during commit message generation");
-                    return Err(error_msg.to_string());
-                }
-                _ => {
-                    // Ignore other events (turn/started, item/started, item/completed, reasoning events, etc.)
-                }
-            }
-        }
-        Ok(())
-    })
-    .await;
-
-    // Unregister callback
-    {
-        let mut callbacks = session.background_thread_callbacks.lock().await;
-        callbacks.remove(&thread_id);
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "config_effective",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await;
     }
 
-    // Archive the thread to clean up
-    let archive_params = json!({ "threadId": thread_id });
-    let _ = session.send_request("thread/archive", archive_params).await;
+    codex_core::effective_config_core(&state.workspaces, &state.app_settings, workspace_id).await
+}
 
-    // Handle timeout or collection error
-    match collect_result {
-        Ok(Ok(())) => {}
-        Ok(Err(e)) => return Err(e),
-        Err(_) => return Err("Timeout waiting for commit message generation".to_string()),
+/// Drafts a commit message for the staged changes with the workspace's provider, and commits
+/// them with it when `commit` is set.
+#[tauri::command]
+pub(crate) async fn generate_commit_message(
+    workspace_id: String,
+    commit: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    let commit = commit.unwrap_or(false);
+    if commit {
+        settings_core::ensure_not_read_only(&state.app_settings, "Committing").await?;
     }
-
-    let trimmed = commit_message.trim().to_string();
-    if trimmed.is_empty() {
-        return Err("No commit message was generated".to_string());
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "generate_commit_message",
+            json!({ "workspaceId": workspace_id, "commit": commit }),
+        )
+        .await;
     }
 
-    Ok(trimmed)
+    commit_message_core::generate_commit_message_core(
+        &state.workspaces,
+        &state.app_settings,
+        &state.sessions,
+        &state.claude_threads,
+        &state.claude_threads_path,
+        workspace_id,
+        commit,
+        crate::git_utils::resolve_git_root,
+        TauriEventSink::new(app),
+    )
+    .await
 }
 
 #[tauri::command]
@@ -1165,8 +3114,8 @@ Task:\n{cleaned_prompt}"
         .to_string();
 
     // Hide background helper threads from the sidebar, even if a thread/started event leaked.
-    let _ = app.emit(
-        "app-server-event",
+    emit_app_server_event(
+        &app,
         AppServerEvent {
             workspace_id: workspace_id.clone(),
             message: json!({
@@ -903,82 +903,20 @@ pub(crate) async fn dictation_stop(
         }
 
         let state_handle = app_handle.state::<AppState>();
-        let cached_context = {
-            let dictation = state_handle.dictation.lock().await;
-            dictation
-                .cached_context
-                .as_ref()
-                .filter(|cached| cached.model_id == model_id)
-                .map(|cached| Arc::clone(&cached.context))
-        };
-
-        let context = if let Some(context) = cached_context {
-            context
-        } else {
-            let model_path = match model_path(&app_handle, &model_id) {
-                Ok(path) => path,
-                Err(error) => {
-                    emit_event(&app_handle, DictationEvent::Error { message: error });
-                    let mut dictation = state_handle.dictation.lock().await;
-                    dictation.session_state = DictationSessionState::Idle;
-                    emit_event(
-                        &app_handle,
-                        DictationEvent::State {
-                            state: DictationSessionState::Idle,
-                        },
-                    );
-                    return;
-                }
-            };
-            let path = model_path.to_string_lossy().into_owned();
-            let created = tokio::task::spawn_blocking(move || {
-                WhisperContext::new_with_params(&path, WhisperContextParameters::default())
-            })
-            .await;
-            let context = match created {
-                Ok(Ok(context)) => context,
-                Ok(Err(error)) => {
-                    emit_event(
-                        &app_handle,
-                        DictationEvent::Error {
-                            message: format!("Failed to load Whisper model: {error}"),
-                        },
-                    );
-                    let mut dictation = state_handle.dictation.lock().await;
-                    dictation.session_state = DictationSessionState::Idle;
-                    emit_event(
-                        &app_handle,
-                        DictationEvent::State {
-                            state: DictationSessionState::Idle,
-                        },
-                    );
-                    return;
-                }
-                Err(error) => {
-                    emit_event(
-                        &app_handle,
-                        DictationEvent::Error {
-                            message: format!("Failed to load Whisper model: {error}"),
-                        },
-                    );
-                    let mut dictation = state_handle.dictation.lock().await;
-                    dictation.session_state = DictationSessionState::Idle;
-                    emit_event(
-                        &app_handle,
-                        DictationEvent::State {
-                            state: DictationSessionState::Idle,
-                        },
-                    );
-                    return;
-                }
-            };
-            let context = Arc::new(context);
-            let mut dictation = state_handle.dictation.lock().await;
-            dictation.cached_context = Some(CachedWhisperContext {
-                model_id: model_id.clone(),
-                context: Arc::clone(&context),
-            });
-            context
+        let context = match load_whisper_context(&app_handle, &model_id).await {
+            Ok(context) => context,
+            Err(message) => {
+                emit_event(&app_handle, DictationEvent::Error { message });
+                let mut dictation = state_handle.dictation.lock().await;
+                dictation.session_state = DictationSessionState::Idle;
+                emit_event(
+                    &app_handle,
+                    DictationEvent::State {
+                        state: DictationSessionState::Idle,
+                    },
+                );
+                return;
+            }
         };
 
         let preferred = preferred_language.clone();
@@ -1095,6 +1033,68 @@ pub(crate) async fn dictation_cancel(
     Ok(DictationSessionState::Idle)
 }
 
+async fn load_whisper_context(
+    app: &AppHandle,
+    model_id: &str,
+) -> Result<Arc<WhisperContext>, String> {
+    let state_handle = app.state::<AppState>();
+    let cached_context = {
+        let dictation = state_handle.dictation.lock().await;
+        dictation
+            .cached_context
+            .as_ref()
+            .filter(|cached| cached.model_id == model_id)
+            .map(|cached| Arc::clone(&cached.context))
+    };
+    if let Some(context) = cached_context {
+        return Ok(context);
+    }
+    let path = model_path(app, model_id)?.to_string_lossy().into_owned();
+    let context = tokio::task::spawn_blocking(move || {
+        WhisperContext::new_with_params(&path, WhisperContextParameters::default())
+    })
+    .await
+    .map_err(|error| format!("Failed to load Whisper model: {error}"))?
+    .map_err(|error| format!("Failed to load Whisper model: {error}"))?;
+    let context = Arc::new(context);
+    let mut dictation = state_handle.dictation.lock().await;
+    dictation.cached_context = Some(CachedWhisperContext {
+        model_id: model_id.to_string(),
+        context: Arc::clone(&context),
+    });
+    Ok(context)
+}
+
+/// Transcribes a recorded WAV clip with the local Whisper model, outside of a live session.
+pub(crate) async fn dictation_transcribe_wav(
+    app: &AppHandle,
+    bytes: &[u8],
+) -> Result<String, String> {
+    let decoded = decode_wav(bytes)?;
+    let state = app.state::<AppState>();
+    let model_id = resolve_model_id(&state, None).await;
+    if refresh_status(app, &state, &model_id).await.state != DictationModelState::Ready {
+        return Err("Dictation model is not downloaded yet.".to_string());
+    }
+    let preferred_language = state
+        .app_settings
+        .lock()
+        .await
+        .dictation_preferred_language
+        .clone();
+    let context = load_whisper_context(app, &model_id).await?;
+    tokio::task::spawn_blocking(move || {
+        transcribe_audio(
+            decoded.samples,
+            decoded.sample_rate,
+            &context,
+            preferred_language,
+        )
+    })
+    .await
+    .map_err(|error| format!("Transcription task failed: {error}"))?
+}
+
 fn start_capture_thread(
     app: AppHandle,
     audio: Arc<Mutex<Vec<f32>>>,
@@ -1426,6 +1426,78 @@ fn transcribe_audio(
     Ok(cleaned)
 }
 
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|slice| u16::from_le_bytes([slice[0], slice[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|slice| u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DecodedAudio {
+    samples: Vec<f32>,
+    sample_rate: u32,
+}
+
+/// Decodes a RIFF/WAVE file (16-bit PCM or 32-bit float) into mono samples.
+fn decode_wav(bytes: &[u8]) -> Result<DecodedAudio, String> {
+    if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        return Err("Only WAV audio is supported for local transcription.".to_string());
+    }
+    let mut offset = 12;
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(bytes, offset + 4).unwrap_or(0) as usize;
+        let body_start = offset + 8;
+        let body_end = (body_start + size).min(bytes.len());
+        if id == b"fmt " {
+            format = Some((
+                read_u16(bytes, body_start).ok_or("Malformed WAV header.")?,
+                read_u16(bytes, body_start + 2).ok_or("Malformed WAV header.")?,
+                read_u32(bytes, body_start + 4).ok_or("Malformed WAV header.")?,
+                read_u16(bytes, body_start + 14).ok_or("Malformed WAV header.")?,
+            ));
+        } else if id == b"data" {
+            let (audio_format, channels, sample_rate, bits) =
+                format.ok_or("WAV data chunk appears before its format chunk.")?;
+            let channels = channels.max(1) as usize;
+            let data = &bytes[body_start..body_end];
+            let interleaved = match (audio_format, bits) {
+                (1, 16) => data
+                    .chunks_exact(2)
+                    .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+                    .collect::<Vec<_>>(),
+                (3, 32) => data
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect::<Vec<_>>(),
+                _ => {
+                    return Err(format!(
+                        "Unsupported WAV encoding (format {audio_format}, {bits}-bit)."
+                    ))
+                }
+            };
+            let samples = interleaved
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+                .collect();
+            return Ok(DecodedAudio {
+                samples,
+                sample_rate,
+            });
+        }
+        // Chunks are padded to an even number of bytes.
+        offset = body_start + size + (size % 2);
+    }
+    Err("WAV file has no audio data.".to_string())
+}
+
 fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     if from_rate == to_rate || samples.is_empty() {
         return samples.to_vec();
@@ -1443,3 +1515,38 @@ fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decode_wav;
+
+    fn wav_pcm16(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend_from_slice(&(channels * 2).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_wav_downmixes_stereo_pcm() {
+        let bytes = wav_pcm16(2, 16_000, &[16384, 0, -16384, -16384]);
+        let decoded = decode_wav(&bytes).expect("decode");
+        assert_eq!(decoded.sample_rate, 16_000);
+        assert_eq!(decoded.samples, vec![0.25, -0.5]);
+        assert!(decode_wav(b"not audio").is_err());
+    }
+}
@@ -89,6 +89,13 @@ fn windows_unsupported_status(model_id: Option<String>) -> DictationModelStatus
     }
 }
 
+pub(crate) async fn dictation_transcribe_wav(
+    _app: &AppHandle,
+    _bytes: &[u8],
+) -> Result<String, String> {
+    Err(UNSUPPORTED_MESSAGE.to_string())
+}
+
 #[tauri::command]
 pub(crate) async fn dictation_model_status(
     app: AppHandle,
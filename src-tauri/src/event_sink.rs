@@ -1,6 +1,10 @@
-use tauri::{AppHandle, Emitter};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, Window};
 
 use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use crate::shared::event_filter_core::{accepts_event, subscribe_events_core, EventFilter};
+use crate::shared::event_mirror_core::is_turn_event;
+use crate::state::AppState;
 
 #[derive(Clone)]
 pub(crate) struct TauriEventSink {
@@ -13,9 +17,37 @@ impl TauriEventSink {
     }
 }
 
+/// Emits `app-server-event` to the windows whose filter accepts it. Only listeners registered
+/// for a window label are filtered; ones listening on any target still get every event.
+pub(crate) fn emit_app_server_event(app: &AppHandle, event: AppServerEvent) {
+    let Some(state) = app.try_state::<AppState>() else {
+        let _ = app.emit("app-server-event", event);
+        return;
+    };
+    let _ = app.emit_filter("app-server-event", &event, |target| match target {
+        EventTarget::AnyLabel { label }
+        | EventTarget::Window { label }
+        | EventTarget::Webview { label }
+        | EventTarget::WebviewWindow { label } => {
+            accepts_event(&state.event_filters, label, &event)
+        }
+        _ => true,
+    });
+}
+
 impl EventSink for TauriEventSink {
     fn emit_app_server_event(&self, event: AppServerEvent) {
-        let _ = self.app.emit("app-server-event", event);
+        crate::notifications::notify_app_server_event(
+            &self.app,
+            &event.workspace_id,
+            &event.message,
+        );
+        if let Some(state) = self.app.try_state::<AppState>() {
+            if is_turn_event(&event.message) {
+                let _ = state.event_mirror.send(event.clone());
+            }
+        }
+        emit_app_server_event(&self.app, event);
     }
 
     fn emit_terminal_output(&self, event: TerminalOutput) {
@@ -26,3 +58,13 @@ impl EventSink for TauriEventSink {
         let _ = self.app.emit("terminal-exit", event);
     }
 }
+
+/// Limits the calling window's app-server events to `filter`, or restores all of them.
+#[tauri::command]
+pub(crate) fn subscribe_events(
+    filter: Option<EventFilter>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<Value, String> {
+    subscribe_events_core(&state.event_filters, window.label().to_string(), filter)
+}
@@ -1,6 +1,4 @@
-use tauri::Manager;
-#[cfg(target_os = "macos")]
-use tauri::{RunEvent, WindowEvent};
+use tauri::{Manager, RunEvent, WindowEvent};
 
 mod backend;
 mod codex;
@@ -42,6 +40,15 @@ pub fn run() {
         .menu(menu::build_menu)
         .on_menu_event(menu::handle_menu_event)
         .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                if let Some(state) = window.try_state::<state::AppState>() {
+                    let _ = shared::event_filter_core::subscribe_events_core(
+                        &state.event_filters,
+                        window.label().to_string(),
+                        None,
+                    );
+                }
+            }
             if window.label() != "main" {
                 return;
             }
@@ -52,8 +59,96 @@ pub fn run() {
             }
         })
         .setup(|app| {
-            let state = state::AppState::load(&app.handle());
+            let (event_mirror, mut mirrored_events) = tokio::sync::mpsc::unbounded_channel();
+            let state = state::AppState::load(&app.handle(), event_mirror);
             app.manage(state);
+            let retention_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let state = retention_handle.state::<state::AppState>();
+                    if let Err(error) = shared::retention_core::apply_retention_core(
+                        &state.workspaces,
+                        &state.app_settings,
+                        &state.claude_threads,
+                        &state.claude_threads_path,
+                        event_sink::TauriEventSink::new(retention_handle.clone()),
+                    )
+                    .await
+                    {
+                        eprintln!("retention pass failed: {error}");
+                    }
+                    tokio::time::sleep(shared::retention_core::RETENTION_INTERVAL).await;
+                }
+            });
+            let summary_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(shared::idle_summary_core::IDLE_SUMMARY_INTERVAL).await;
+                    let state = summary_handle.state::<state::AppState>();
+                    if let Err(error) = shared::idle_summary_core::summarize_idle_threads_core(
+                        &state.workspaces,
+                        &state.app_settings,
+                        &state.claude_threads,
+                        &state.claude_threads_path,
+                        event_sink::TauriEventSink::new(summary_handle.clone()),
+                    )
+                    .await
+                    {
+                        eprintln!("idle summary pass failed: {error}");
+                    }
+                }
+            });
+            let sync_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let state = sync_handle.state::<state::AppState>();
+                    let redaction = shared::redaction_core::RedactionPipeline::compile(
+                        &state.app_settings.lock().await.redaction_rules,
+                    );
+                    if let Err(error) = shared::thread_sync_core::sync_all_workspaces_core(
+                        &state.app_settings,
+                        &state.workspaces,
+                        &state.claude_threads,
+                        &state.claude_threads_path,
+                        &redaction,
+                        event_sink::TauriEventSink::new(sync_handle.clone()),
+                    )
+                    .await
+                    {
+                        eprintln!("history sync pass failed: {error}");
+                    }
+                    shared::thread_sync_core::wait_for_next_pass(&state.app_settings).await;
+                }
+            });
+            let settings_handle = app.handle().clone();
+            let mut settings_changes = shared::settings_core::subscribe_settings_changes();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match settings_changes.recv().await {
+                        Ok(change) => {
+                            workspaces::apply_settings_change(settings_handle.clone(), change).await
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+            let mirror_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut mirror = shared::event_mirror_core::EventMirror::default();
+                while let Some(event) = mirrored_events.recv().await {
+                    let state = mirror_handle.state::<state::AppState>();
+                    mirror.mirror(&state.workspaces, &event).await;
+                    shared::status_summary_core::track_status_event(
+                        &state.status,
+                        &event,
+                        state.turn_slots.queued(),
+                        shared::claude_core::now_ms(),
+                        &event_sink::TauriEventSink::new(mirror_handle.clone()),
+                    )
+                    .await;
+                }
+            });
             #[cfg(desktop)]
             {
                 app.handle()
@@ -78,15 +173,22 @@ pub fn run() {
             files::file_read,
             files::file_write,
             codex::get_config_model,
+            codex::config_effective,
             menu::menu_set_accelerators,
+            event_sink::subscribe_events,
             codex::codex_doctor,
+            codex::providers_detect,
             workspaces::list_workspaces,
             workspaces::is_workspace_path_dir,
+            workspaces::scan_workspaces,
+            workspaces::setup_probe,
             workspaces::add_workspace,
             workspaces::add_clone,
             workspaces::add_worktree,
             workspaces::worktree_setup_status,
             workspaces::worktree_setup_mark_ran,
+            workspaces::secrets_set,
+            workspaces::secrets_delete,
             workspaces::remove_workspace,
             workspaces::remove_worktree,
             workspaces::rename_worktree,
@@ -96,8 +198,18 @@ pub fn run() {
             workspaces::update_workspace_codex_bin,
             codex::start_thread,
             codex::send_user_message,
+            codex::send_with_context,
+            codex::prompts_history,
+            codex::prompts_history_resend,
+            codex::reply_latest,
+            codex::execute_plan,
+            codex::edit_message,
+            codex::undo_edit_message,
+            codex::send_audio_message,
             codex::turn_interrupt,
             codex::start_review,
+            codex::follow_claude_session,
+            codex::unfollow_claude_session,
             codex::respond_to_server_request,
             codex::remember_approval_rule,
             codex::get_commit_message_prompt,
@@ -106,10 +218,68 @@ pub fn run() {
             codex::resume_thread,
             codex::fork_thread,
             codex::list_threads,
+            codex::list_worktree_threads,
+            codex::threads_sync,
+            codex::threads_quick_find,
+            codex::inbox_list,
+            codex::lan_sync_start,
+            codex::lan_sync_stop,
+            codex::lan_sync_discover,
+            codex::lan_sync_with_peer,
             codex::list_mcp_server_status,
             codex::archive_thread,
+            codex::bulk_thread_op,
+            codex::list_trash,
+            codex::restore_trash,
+            codex::actions_list,
+            codex::provider_rate_limit_state,
+            codex::status_summary,
+            codex::orphans_list,
+            codex::orphans_purge,
+            codex::import_codex_sessions,
+            codex::import_external,
+            codex::export_threads,
+            codex::import_threads,
+            codex::thread_export_schema,
+            codex::audit_query,
+            codex::storage_usage,
+            codex::storage_migrate,
+            codex::profile_list,
+            codex::profile_switch,
+            codex::memory_read,
+            codex::memory_write,
+            codex::memory_append,
+            codex::memory_clear,
+            codex::claude_hooks_read,
+            codex::claude_hooks_write,
+            codex::save_draft,
+            codex::get_draft,
+            codex::prompt_estimate,
+            codex::list_playbooks,
+            codex::save_playbook,
+            codex::delete_playbook,
+            codex::run_playbook,
+            codex::respond_playbook_step,
+            codex::cancel_playbook,
+            codex::respond_to_approval,
+            codex::turn_patches,
+            codex::turn_artifacts,
+            codex::turn_details,
+            codex::apply_patch,
             codex::compact_thread,
             codex::set_thread_name,
+            codex::mark_thread_read,
+            codex::set_item_pinned,
+            codex::thread_pinned_items,
+            codex::diff_items,
+            codex::set_thread_cwd,
+            codex::set_thread_generation,
+            codex::merge_thread_worktree,
+            codex::revert_turn_changes,
+            codex::duplicate_thread,
+            codex::move_thread,
+            codex::rebind_thread_workspace,
+            codex::copy_thread,
             codex::collaboration_mode_list,
             workspaces::connect_workspace,
             git::get_git_status,
@@ -180,5 +350,20 @@ pub fn run() {
                 let _ = window.set_focus();
             }
         }
+        if let RunEvent::Exit = event {
+            let state = app_handle.state::<state::AppState>();
+            tauri::async_runtime::block_on(async {
+                terminal::close_all_terminals(&state).await;
+                shared::shutdown_core::shutdown_core(
+                    &state.data_dir,
+                    &state.claude_threads,
+                    &state.claude_threads_path,
+                    &state.claude_turn_cancels,
+                    &state.claude_processes,
+                    &state.sessions,
+                )
+                .await;
+            });
+        }
     });
 }
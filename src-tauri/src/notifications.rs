@@ -1,5 +1,220 @@
+use std::collections::HashMap;
 #[cfg(all(target_os = "macos", debug_assertions))]
 use std::process::Command;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, Timelike};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::state::AppState;
+use crate::types::AppSettings;
+
+/// Short turns finish while the user is still watching; only notify for longer ones.
+const MIN_TURN_DURATION: Duration = Duration::from_secs(60);
+const MAX_BODY_CHARS: usize = 200;
+const DEFAULT_COMPLETED_BODY: &str = "Your agent has finished its task.";
+
+#[derive(Default)]
+struct TurnNotifierState {
+    started_at: HashMap<String, Instant>,
+    last_message: HashMap<String, String>,
+}
+
+/// Watches app-server events and raises OS notifications for finished or failed turns.
+#[derive(Default)]
+pub(crate) struct TurnNotifier {
+    state: StdMutex<TurnNotifierState>,
+}
+
+struct PendingNotification {
+    workspace_id: String,
+    thread_id: String,
+    fallback_title: &'static str,
+    body: String,
+}
+
+fn truncate_body(text: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.chars().count() <= MAX_BODY_CHARS {
+        return trimmed.to_string();
+    }
+    let mut truncated = trimmed.chars().take(MAX_BODY_CHARS - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+fn parse_clock_minutes(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours = hours.parse::<u32>().ok().filter(|hours| *hours < 24)?;
+    let minutes = minutes
+        .parse::<u32>()
+        .ok()
+        .filter(|minutes| *minutes < 60)?;
+    Some(hours * 60 + minutes)
+}
+
+/// Quiet hours may wrap past midnight (e.g. 22:00–07:00).
+fn in_quiet_hours(now_minutes: u32, start: u32, end: u32) -> bool {
+    match start.cmp(&end) {
+        std::cmp::Ordering::Less => now_minutes >= start && now_minutes < end,
+        std::cmp::Ordering::Greater => now_minutes >= start || now_minutes < end,
+        std::cmp::Ordering::Equal => false,
+    }
+}
+
+fn quiet_hours_active(settings: &AppSettings) -> bool {
+    let start = settings
+        .notification_quiet_hours_start
+        .as_deref()
+        .and_then(parse_clock_minutes);
+    let end = settings
+        .notification_quiet_hours_end
+        .as_deref()
+        .and_then(parse_clock_minutes);
+    let (Some(start), Some(end)) = (start, end) else {
+        return false;
+    };
+    let now = Local::now();
+    in_quiet_hours(now.hour() * 60 + now.minute(), start, end)
+}
+
+impl TurnNotifier {
+    fn observe(&self, workspace_id: &str, message: &Value) -> Option<PendingNotification> {
+        let method = message.get("method").and_then(Value::as_str)?;
+        let params = message.get("params")?;
+        let thread_id = params
+            .get("threadId")
+            .or_else(|| params.get("turn").and_then(|turn| turn.get("threadId")))
+            .and_then(Value::as_str)?;
+        let key = format!("{workspace_id}:{thread_id}");
+        let mut state = self.state.lock().ok()?;
+        let (fallback_title, body) = match method {
+            "turn/started" => {
+                state.started_at.insert(key.clone(), Instant::now());
+                state.last_message.remove(&key);
+                return None;
+            }
+            "item/completed" => {
+                let item = params.get("item")?;
+                if item.get("type").and_then(Value::as_str) == Some("agentMessage") {
+                    if let Some(text) = item.get("text").and_then(Value::as_str) {
+                        state.last_message.insert(key, text.to_string());
+                    }
+                }
+                return None;
+            }
+            "turn/completed" => {
+                let body = state
+                    .last_message
+                    .remove(&key)
+                    .filter(|text| !text.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_COMPLETED_BODY.to_string());
+                ("Agent Complete", body)
+            }
+            "error" => {
+                if params.get("willRetry").and_then(Value::as_bool) == Some(true) {
+                    return None;
+                }
+                let body = params
+                    .get("error")
+                    .and_then(|error| error.get("message"))
+                    .and_then(Value::as_str)
+                    .filter(|text| !text.trim().is_empty())
+                    .unwrap_or("An error occurred.")
+                    .to_string();
+                ("Agent Error", body)
+            }
+            _ => return None,
+        };
+        // Failed turns emit `error` then `turn/completed`; only the first one finds a start time.
+        let started_at = state.started_at.remove(&key)?;
+        if started_at.elapsed() < MIN_TURN_DURATION {
+            return None;
+        }
+        Some(PendingNotification {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            fallback_title,
+            body: truncate_body(&body),
+        })
+    }
+}
+
+/// Called for every app-server event forwarded to the frontend, local or remote.
+pub(crate) fn notify_app_server_event(app: &AppHandle, workspace_id: &str, message: &Value) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Some(pending) = state.turn_notifier.observe(workspace_id, message) else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        {
+            let settings = state.app_settings.lock().await;
+            if !settings.system_notifications_enabled || quiet_hours_active(&settings) {
+                return;
+            }
+        }
+        let (title, enabled) = {
+            let workspaces = state.workspaces.lock().await;
+            let Some(entry) = workspaces.get(&pending.workspace_id) else {
+                return;
+            };
+            let parent_setting = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .and_then(|parent| parent.settings.turn_notifications);
+            let enabled = entry
+                .settings
+                .turn_notifications
+                .or(parent_setting)
+                .unwrap_or(true);
+            (entry.name.clone(), enabled)
+        };
+        if !enabled {
+            return;
+        }
+        let focused = app
+            .get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false);
+        if focused {
+            return;
+        }
+        let title = if title.trim().is_empty() {
+            pending.fallback_title.to_string()
+        } else {
+            title
+        };
+        let sent = if cfg!(all(target_os = "macos", debug_assertions)) {
+            send_notification_fallback(title, pending.body)
+                .await
+                .is_ok()
+        } else {
+            app.notification()
+                .builder()
+                .title(title)
+                .body(pending.body)
+                .show()
+                .is_ok()
+        };
+        if sent {
+            let _ = app.emit(
+                "thread-notification-sent",
+                json!({
+                    "workspaceId": pending.workspace_id,
+                    "threadId": pending.thread_id,
+                }),
+            );
+        }
+    });
+}
 
 #[tauri::command]
 pub(crate) async fn is_macos_debug_build() -> bool {
@@ -45,3 +260,30 @@ pub(crate) async fn send_notification_fallback(title: String, body: String) -> R
         Err("Notification fallback is only available on macOS debug builds.".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{in_quiet_hours, parse_clock_minutes, truncate_body, TurnNotifier};
+    use serde_json::json;
+
+    #[test]
+    fn quiet_hours_wrap_past_midnight() {
+        let start = parse_clock_minutes("22:00").expect("start");
+        let end = parse_clock_minutes("07:30").expect("end");
+        assert!(in_quiet_hours(23 * 60, start, end));
+        assert!(in_quiet_hours(7 * 60, start, end));
+        assert!(!in_quiet_hours(12 * 60, start, end));
+        assert!(in_quiet_hours(10 * 60, 9 * 60, 17 * 60));
+        assert!(parse_clock_minutes("25:00").is_none());
+    }
+
+    #[test]
+    fn short_turns_do_not_notify() {
+        let notifier = TurnNotifier::default();
+        let started = json!({ "method": "turn/started", "params": { "threadId": "t1" } });
+        let completed = json!({ "method": "turn/completed", "params": { "threadId": "t1" } });
+        assert!(notifier.observe("ws", &started).is_none());
+        assert!(notifier.observe("ws", &completed).is_none());
+        assert_eq!(truncate_body(&"a".repeat(300)).chars().count(), 200);
+    }
+}
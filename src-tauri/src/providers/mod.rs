@@ -1,8 +1,12 @@
+use std::borrow::Cow;
 use std::path::PathBuf;
 
-use crate::codex::args::resolve_workspace_codex_args;
+use crate::codex::args::{codex_arg_layers, resolve_workspace_codex_args, CodexArgLayer};
 use crate::codex::home::resolve_workspace_codex_home;
-use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+use crate::types::{
+    AppSettings, ClaudeOptions, CodexOptions, ProviderKind, ProviderProfile, WorkspaceEntry,
+    WorkspaceSettings,
+};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
@@ -32,7 +36,7 @@ pub(crate) fn capabilities(provider: &ProviderKind) -> ProviderCapabilities {
             list_threads: false,
             resume_thread: false,
             interrupt_turn: false,
-            model_list: false,
+            model_list: true,
         },
     }
 }
@@ -60,6 +64,9 @@ pub(crate) fn resolve_runtime_config(
     Option<PathBuf>,
 ) {
     let provider = resolve_workspace_provider(entry, app_settings);
+    let profile = resolve_provider_profile(entry, parent_entry, app_settings, &provider);
+    let settings = settings_with_profile(app_settings, profile);
+    let app_settings = settings.as_deref();
     match provider {
         ProviderKind::Codex => {
             let default_bin = resolve_codex_bin(entry, parent_entry, app_settings);
@@ -82,17 +89,227 @@ pub(crate) fn resolve_runtime_config(
     }
 }
 
+/// Claude's bin, args and profile environment for one turn.
 pub(crate) fn resolve_claude_runtime_config(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
     app_settings: Option<&AppSettings>,
-) -> (Option<String>, Option<String>) {
+) -> (Option<String>, Option<String>, Vec<(String, String)>) {
+    let profile =
+        resolve_provider_profile(entry, parent_entry, app_settings, &ProviderKind::Claude);
+    let settings = settings_with_profile(app_settings, profile);
     (
-        resolve_claude_bin(entry, parent_entry, app_settings),
-        resolve_claude_args(entry, parent_entry, app_settings),
+        resolve_claude_bin(entry, parent_entry, settings.as_deref()),
+        resolve_claude_args(entry, parent_entry, settings.as_deref()),
+        resolve_provider_env(profile),
     )
 }
 
+/// A Claude provider profile to retry a turn with, resolved like the workspace's own config.
+pub(crate) struct ClaudeFallback {
+    pub(crate) profile_id: String,
+    pub(crate) profile_name: String,
+    pub(crate) bin: Option<String>,
+    pub(crate) args: Option<String>,
+    pub(crate) env: Vec<(String, String)>,
+}
+
+/// The workspace's `providerFallbacks`, in order; worktrees without their own list use the
+/// parent's. A fallback's bin and args win over the workspace's, since a broken workspace
+/// bin is one of the failures it stands in for. Ids that name no Claude profile are skipped.
+pub(crate) fn resolve_claude_fallbacks(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
+) -> Vec<ClaudeFallback> {
+    let own = &entry.settings.provider_fallbacks;
+    let fallbacks = match parent_entry {
+        Some(parent) if entry.kind.is_worktree() && own.is_empty() => {
+            &parent.settings.provider_fallbacks
+        }
+        _ => own,
+    };
+    let Some(app_settings) = app_settings else {
+        return Vec::new();
+    };
+    fallbacks
+        .iter()
+        .filter_map(|id| {
+            app_settings
+                .provider_profiles
+                .iter()
+                .find(|profile| profile.id == id.trim() && profile.provider == ProviderKind::Claude)
+        })
+        .map(|profile| {
+            let settings = settings_with_profile(Some(app_settings), Some(profile));
+            let settings = settings.as_deref();
+            let args = match normalize_optional(profile.args.as_deref()) {
+                Some(args) => join_claude_args(settings, Some(args)),
+                None => resolve_claude_args(entry, parent_entry, settings),
+            };
+            ClaudeFallback {
+                profile_id: profile.id.clone(),
+                profile_name: profile.name.clone(),
+                bin: normalize_optional(profile.bin.as_deref())
+                    .or_else(|| resolve_claude_bin(entry, parent_entry, settings)),
+                args,
+                env: resolve_provider_env(Some(profile)),
+            }
+        })
+        .collect()
+}
+
+/// The profile the workspace selects (worktrees inherit their parent's), if it exists and is
+/// a profile for `provider`.
+pub(crate) fn resolve_provider_profile<'a>(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&'a AppSettings>,
+    provider: &ProviderKind,
+) -> Option<&'a ProviderProfile> {
+    let profile_id =
+        normalize_optional(entry.settings.provider_profile_id.as_deref()).or_else(|| {
+            if entry.kind.is_worktree() {
+                parent_entry.and_then(|parent| {
+                    normalize_optional(parent.settings.provider_profile_id.as_deref())
+                })
+            } else {
+                None
+            }
+        })?;
+    app_settings?
+        .provider_profiles
+        .iter()
+        .find(|profile| profile.id == profile_id && &profile.provider == provider)
+}
+
+/// The Codex args layers behind [`resolve_runtime_config`]; a provider profile's args replace
+/// the app layer.
+pub(crate) fn resolve_codex_arg_layers(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
+) -> Result<Vec<CodexArgLayer>, String> {
+    let profile = resolve_provider_profile(entry, parent_entry, app_settings, &ProviderKind::Codex);
+    let settings = settings_with_profile(app_settings, profile);
+    codex_arg_layers(entry, parent_entry, settings.as_deref())
+}
+
+/// Global settings with the profile's bin and args layered on top: workspace-level values
+/// still win, and anything the profile leaves empty falls back to the global value.
+fn settings_with_profile<'a>(
+    app_settings: Option<&'a AppSettings>,
+    profile: Option<&ProviderProfile>,
+) -> Option<Cow<'a, AppSettings>> {
+    let Some(profile) = profile else {
+        return app_settings.map(Cow::Borrowed);
+    };
+    let mut settings = app_settings.cloned().unwrap_or_default();
+    let bin = normalize_optional(profile.bin.as_deref());
+    let args = normalize_optional(profile.args.as_deref());
+    let (bin_slot, args_slot) = match profile.provider {
+        ProviderKind::Codex => (&mut settings.codex_bin, &mut settings.codex_args),
+        ProviderKind::Claude => (&mut settings.claude_bin, &mut settings.claude_args),
+        ProviderKind::Gemini => (&mut settings.gemini_bin, &mut settings.gemini_args),
+    };
+    if bin.is_some() {
+        *bin_slot = bin;
+    }
+    if args.is_some() {
+        *args_slot = args;
+    }
+    Some(Cow::Owned(settings))
+}
+
+pub(crate) fn validate_provider_profiles(profiles: &[ProviderProfile]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for profile in profiles {
+        let id = profile.id.trim();
+        if id.is_empty() {
+            return Err("Provider profile id is required.".to_string());
+        }
+        if profile.name.trim().is_empty() {
+            return Err(format!("Provider profile `{id}` needs a name."));
+        }
+        if !seen.insert(id) {
+            return Err(format!("Duplicate provider profile id `{id}`."));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects typed provider options the CLIs would refuse or misread.
+pub(crate) fn validate_provider_options(
+    claude: &ClaudeOptions,
+    codex: &CodexOptions,
+) -> Result<(), String> {
+    if claude.max_turns == Some(0) {
+        return Err("Claude max turns must be at least 1.".to_string());
+    }
+    if claude
+        .allowed_tools
+        .iter()
+        .any(|tool| tool.trim().is_empty())
+    {
+        return Err("Claude allowed tools can't be blank.".to_string());
+    }
+    if codex
+        .model
+        .as_deref()
+        .is_some_and(|model| model.trim().contains(char::is_whitespace))
+    {
+        return Err("Codex model can't contain spaces.".to_string());
+    }
+    Ok(())
+}
+
+/// Args for the typed Claude options, placed before `claudeArgs` so hand-written args win.
+fn render_claude_options(options: &ClaudeOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(mode) = options.permission_mode {
+        args.extend(["--permission-mode".to_string(), mode.as_str().to_string()]);
+    }
+    if let Some(max_turns) = options.max_turns {
+        args.extend(["--max-turns".to_string(), max_turns.to_string()]);
+    }
+    let tools = options
+        .allowed_tools
+        .iter()
+        .map(|tool| tool.trim())
+        .filter(|tool| !tool.is_empty())
+        .collect::<Vec<_>>();
+    if !tools.is_empty() {
+        args.extend(["--allowedTools".to_string(), tools.join(",")]);
+    }
+    args
+}
+
+fn api_key_env_name(provider: &ProviderKind) -> &'static str {
+    match provider {
+        ProviderKind::Codex => "OPENAI_API_KEY",
+        ProviderKind::Claude => "ANTHROPIC_API_KEY",
+        ProviderKind::Gemini => "GEMINI_API_KEY",
+    }
+}
+
+/// The profile's environment plus its API key, sorted so process keys stay stable.
+pub(crate) fn resolve_provider_env(profile: Option<&ProviderProfile>) -> Vec<(String, String)> {
+    let Some(profile) = profile else {
+        return Vec::new();
+    };
+    let mut env = profile
+        .env
+        .iter()
+        .map(|(name, value)| (name.trim().to_string(), value.clone()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect::<Vec<_>>();
+    env.sort();
+    if let Some(api_key) = normalize_optional(profile.api_key.as_deref()) {
+        env.push((api_key_env_name(&profile.provider).to_string(), api_key));
+    }
+    env
+}
+
 pub(crate) fn ensure_provider_spawn_supported(provider: &ProviderKind) -> Result<(), String> {
     match provider {
         ProviderKind::Codex | ProviderKind::Claude => Ok(()),
@@ -151,6 +368,30 @@ fn resolve_claude_args(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
     app_settings: Option<&AppSettings>,
+) -> Option<String> {
+    let args = resolve_claude_free_form_args(entry, parent_entry, app_settings);
+    join_claude_args(app_settings, args)
+}
+
+/// Typed Claude options rendered ahead of free-form `args`.
+fn join_claude_args(app_settings: Option<&AppSettings>, args: Option<String>) -> Option<String> {
+    let options = app_settings
+        .map(|settings| render_claude_options(&settings.claude_options))
+        .unwrap_or_default();
+    if options.is_empty() {
+        return args;
+    }
+    let options = shell_words::join(options);
+    Some(match args {
+        Some(args) => format!("{options} {args}"),
+        None => options,
+    })
+}
+
+fn resolve_claude_free_form_args(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
 ) -> Option<String> {
     normalize_optional(entry.settings.claude_args.as_deref())
         .or_else(|| {
@@ -166,6 +407,35 @@ fn resolve_claude_args(
         })
 }
 
+/// Workspace instructions for every thread, followed by a line pinning the response language
+/// when one is set. Worktrees inherit whichever of the two they don't set from the parent.
+pub(crate) fn resolve_workspace_instructions(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> Option<String> {
+    let inherited = |value: fn(&WorkspaceSettings) -> Option<&str>| {
+        normalize_optional(value(&entry.settings)).or_else(|| {
+            if entry.kind.is_worktree() {
+                parent_entry.and_then(|parent| normalize_optional(value(&parent.settings)))
+            } else {
+                None
+            }
+        })
+    };
+    let instructions = inherited(|settings| settings.instructions.as_deref());
+    let language = inherited(|settings| settings.response_language.as_deref()).map(|language| {
+        format!(
+            "Always respond in {language}, whatever language the request is written in. \
+             Keep code, identifiers and commands unchanged."
+        )
+    });
+    let parts = [instructions, language]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
 fn resolve_gemini_bin(
     entry: &WorkspaceEntry,
     parent_entry: Option<&WorkspaceEntry>,
@@ -203,3 +473,204 @@ fn resolve_gemini_args(
             app_settings.and_then(|settings| normalize_optional(settings.gemini_args.as_deref()))
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        resolve_claude_fallbacks, resolve_claude_runtime_config, resolve_runtime_config,
+        resolve_workspace_instructions, validate_provider_options,
+    };
+    use crate::types::{
+        AppSettings, ClaudeOptions, ClaudePermissionMode, CodexOptions, CodexSandboxMode,
+        ProviderKind, ProviderProfile, WorkspaceEntry, WorkspaceKind, WorkspaceSettings,
+    };
+    use std::collections::HashMap;
+
+    fn workspace(profile_id: Option<&str>, claude_bin: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: "ws".to_string(),
+            name: "ws".to_string(),
+            path: "/repo".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                provider: Some(ProviderKind::Claude),
+                provider_profile_id: profile_id.map(str::to_string),
+                claude_bin: claude_bin.map(str::to_string),
+                ..WorkspaceSettings::default()
+            },
+        }
+    }
+
+    #[test]
+    fn workspace_profile_overrides_global_claude_settings() {
+        let settings = AppSettings {
+            claude_bin: Some("claude".to_string()),
+            claude_args: Some("--verbose".to_string()),
+            provider_profiles: vec![ProviderProfile {
+                id: "work".to_string(),
+                name: "Work Claude".to_string(),
+                provider: ProviderKind::Claude,
+                bin: Some("/opt/work/claude".to_string()),
+                args: None,
+                env: HashMap::from([("CLAUDE_CONFIG_DIR".to_string(), "/work".to_string())]),
+                api_key: Some("sk-work".to_string()),
+            }],
+            ..AppSettings::default()
+        };
+
+        let (bin, args, env) =
+            resolve_claude_runtime_config(&workspace(Some("work"), None), None, Some(&settings));
+        assert_eq!(bin.as_deref(), Some("/opt/work/claude"));
+        assert_eq!(args.as_deref(), Some("--verbose"));
+        assert_eq!(
+            env,
+            vec![
+                ("CLAUDE_CONFIG_DIR".to_string(), "/work".to_string()),
+                ("ANTHROPIC_API_KEY".to_string(), "sk-work".to_string()),
+            ]
+        );
+
+        let (_, bin, _, _) = resolve_runtime_config(
+            &workspace(Some("work"), Some("/local/claude")),
+            None,
+            Some(&settings),
+        );
+        assert_eq!(bin.as_deref(), Some("/local/claude"));
+        let (bin, _, env) =
+            resolve_claude_runtime_config(&workspace(Some("missing"), None), None, Some(&settings));
+        assert_eq!(bin.as_deref(), Some("claude"));
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn fallbacks_resolve_claude_profiles_in_order() {
+        let profile = |id: &str, provider: ProviderKind, bin: Option<&str>| ProviderProfile {
+            id: id.to_string(),
+            name: id.to_string(),
+            provider,
+            bin: bin.map(str::to_string),
+            args: None,
+            env: HashMap::new(),
+            api_key: None,
+        };
+        let mut ollama = profile("ollama", ProviderKind::Claude, None);
+        ollama.env = HashMap::from([(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "http://localhost:11434".to_string(),
+        )]);
+        let settings = AppSettings {
+            claude_bin: Some("claude".to_string()),
+            provider_profiles: vec![
+                profile("backup", ProviderKind::Claude, Some("/opt/claude")),
+                profile("codex", ProviderKind::Codex, Some("codex")),
+                ollama,
+            ],
+            ..AppSettings::default()
+        };
+        let mut parent = workspace(None, Some("/broken/claude"));
+        parent.settings.provider_fallbacks = ["codex", "backup", "missing", "ollama"]
+            .map(str::to_string)
+            .to_vec();
+        let mut worktree = workspace(None, None);
+        worktree.kind = WorkspaceKind::Worktree;
+
+        let fallbacks = resolve_claude_fallbacks(&worktree, Some(&parent), Some(&settings));
+        let resolved = fallbacks
+            .iter()
+            .map(|fallback| (fallback.profile_id.as_str(), fallback.bin.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            resolved,
+            vec![
+                ("backup", Some("/opt/claude")),
+                ("ollama", Some("/broken/claude"))
+            ]
+        );
+        assert_eq!(fallbacks[1].env[0].0, "ANTHROPIC_BASE_URL");
+        assert!(resolve_claude_fallbacks(&worktree, None, Some(&settings)).is_empty());
+    }
+
+    #[test]
+    fn worktrees_inherit_parent_instructions() {
+        let mut parent = workspace(None, None);
+        parent.settings.instructions = Some("  Use pnpm.\n".to_string());
+        let mut worktree = workspace(None, None);
+        worktree.kind = WorkspaceKind::Worktree;
+        worktree.parent_id = Some(parent.id.clone());
+
+        assert_eq!(
+            resolve_workspace_instructions(&worktree, Some(&parent)).as_deref(),
+            Some("Use pnpm.")
+        );
+        worktree.settings.instructions = Some("Use npm.".to_string());
+        assert_eq!(
+            resolve_workspace_instructions(&worktree, Some(&parent)).as_deref(),
+            Some("Use npm.")
+        );
+        assert_eq!(
+            resolve_workspace_instructions(&workspace(None, None), Some(&parent)),
+            None
+        );
+
+        parent.settings.response_language = Some("German".to_string());
+        let resolved = resolve_workspace_instructions(&worktree, Some(&parent)).expect("resolved");
+        assert!(resolved.starts_with("Use npm.\n\nAlways respond in German,"));
+        worktree.settings.instructions = None;
+        worktree.settings.response_language = Some(" ".to_string());
+        let resolved = resolve_workspace_instructions(&worktree, Some(&parent)).expect("resolved");
+        assert!(resolved.starts_with("Use pnpm.\n\nAlways respond in German,"));
+    }
+
+    #[test]
+    fn renders_typed_options_before_free_form_args() {
+        let settings = AppSettings {
+            claude_args: Some("--verbose".to_string()),
+            claude_options: ClaudeOptions {
+                permission_mode: Some(ClaudePermissionMode::AcceptEdits),
+                max_turns: Some(8),
+                allowed_tools: vec!["Read".to_string(), " Bash(git diff:*) ".to_string()],
+            },
+            codex_args: Some("--model gpt-5".to_string()),
+            codex_options: CodexOptions {
+                sandbox: Some(CodexSandboxMode::WorkspaceWrite),
+                model: Some("o3".to_string()),
+            },
+            ..AppSettings::default()
+        };
+
+        let (_, args, _) =
+            resolve_claude_runtime_config(&workspace(None, None), None, Some(&settings));
+        assert_eq!(
+            args.as_deref(),
+            Some(
+                "--permission-mode acceptEdits --max-turns 8 \
+                 --allowedTools 'Read,Bash(git diff:*)' --verbose"
+            )
+        );
+
+        let mut codex = workspace(None, None);
+        codex.settings.provider = Some(ProviderKind::Codex);
+        let (_, _, args, _) = resolve_runtime_config(&codex, None, Some(&settings));
+        assert_eq!(
+            args.as_deref(),
+            Some("--sandbox workspace-write --model gpt-5")
+        );
+
+        assert!(
+            validate_provider_options(&settings.claude_options, &settings.codex_options).is_ok()
+        );
+        let zero_turns = ClaudeOptions {
+            max_turns: Some(0),
+            ..ClaudeOptions::default()
+        };
+        assert!(validate_provider_options(&zero_turns, &CodexOptions::default()).is_err());
+        let spaced_model = CodexOptions {
+            model: Some("gpt 5".to_string()),
+            ..CodexOptions::default()
+        };
+        assert!(validate_provider_options(&ClaudeOptions::default(), &spaced_model).is_err());
+    }
+}
@@ -8,6 +8,8 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
+use crate::backend::events::AppServerEvent;
+use crate::event_sink::emit_app_server_event;
 use crate::state::AppState;
 use crate::types::BackendMode;
 
@@ -246,7 +248,19 @@ async fn read_loop(
         let params = message.get("params").cloned().unwrap_or(Value::Null);
         match method {
             "app-server-event" => {
-                let _ = app.emit("app-server-event", params);
+                if let (Some(workspace_id), Some(message)) = (
+                    params.get("workspace_id").and_then(Value::as_str),
+                    params.get("message"),
+                ) {
+                    crate::notifications::notify_app_server_event(&app, workspace_id, message);
+                    let event = AppServerEvent {
+                        workspace_id: workspace_id.to_string(),
+                        message: message.clone(),
+                    };
+                    emit_app_server_event(&app, event);
+                } else {
+                    let _ = app.emit("app-server-event", params);
+                }
             }
             "terminal-output" => {
                 let _ = app.emit("terminal-output", params);
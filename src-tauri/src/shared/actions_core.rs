@@ -0,0 +1,703 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// One argument of an action, named as the command expects it in its JSON params.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ActionParam {
+    pub(crate) name: &'static str,
+    /// `string`, `string[]`, `number`, `boolean` or `object`.
+    #[serde(rename = "type")]
+    pub(crate) kind: &'static str,
+    pub(crate) required: bool,
+    pub(crate) description: &'static str,
+}
+
+/// A backend operation the frontend can offer in its command palette. `id` is the command
+/// name, so invoking an action is a plain command call with `params`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ActionSpec {
+    pub(crate) id: &'static str,
+    pub(crate) title: &'static str,
+    pub(crate) category: &'static str,
+    pub(crate) description: &'static str,
+    pub(crate) params: &'static [ActionParam],
+    /// Changes threads, workspaces or files, so it is refused in read-only mode.
+    pub(crate) mutating: bool,
+}
+
+const fn required(
+    name: &'static str,
+    kind: &'static str,
+    description: &'static str,
+) -> ActionParam {
+    ActionParam {
+        name,
+        kind,
+        required: true,
+        description,
+    }
+}
+
+const fn optional(
+    name: &'static str,
+    kind: &'static str,
+    description: &'static str,
+) -> ActionParam {
+    ActionParam {
+        name,
+        kind,
+        required: false,
+        description,
+    }
+}
+
+const WORKSPACE: ActionParam = required("workspaceId", "string", "Workspace to act on.");
+const THREAD: ActionParam = required("threadId", "string", "Thread to act on.");
+const TARGET_WORKSPACE: ActionParam = required(
+    "targetWorkspaceId",
+    "string",
+    "Workspace to put the thread in.",
+);
+
+const ACTIONS: &[ActionSpec] = &[
+    ActionSpec {
+        id: "add_workspace",
+        title: "Add workspace",
+        category: "Workspaces",
+        description: "Add a local folder as a workspace.",
+        params: &[
+            required("path", "string", "Folder to add."),
+            optional(
+                "codex_bin",
+                "string",
+                "Codex binary to use instead of the default.",
+            ),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "add_worktree",
+        title: "New worktree",
+        category: "Workspaces",
+        description: "Create a git worktree on a new branch and add it as a workspace.",
+        params: &[
+            required(
+                "parentId",
+                "string",
+                "Workspace the worktree branches from.",
+            ),
+            required("branch", "string", "Branch to create."),
+            optional("name", "string", "Display name of the new workspace."),
+            optional(
+                "copyAgentsMd",
+                "boolean",
+                "Copy AGENTS.md into the worktree.",
+            ),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "connect_workspace",
+        title: "Connect workspace",
+        category: "Workspaces",
+        description: "Start the workspace's provider session.",
+        params: &[required("id", "string", "Workspace to connect.")],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "remove_workspace",
+        title: "Remove workspace",
+        category: "Workspaces",
+        description: "Remove a workspace from the list; its folder is left on disk.",
+        params: &[required("id", "string", "Workspace to remove.")],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "start_thread",
+        title: "New thread",
+        category: "Threads",
+        description: "Start a new agent thread in a workspace.",
+        params: &[WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "send_user_message",
+        title: "Send message",
+        category: "Threads",
+        description: "Send a prompt to a thread and start a turn.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("text", "string", "Prompt to send."),
+            optional("model", "string", "Model for this turn."),
+            optional("effort", "string", "Reasoning effort for this turn."),
+            optional(
+                "accessMode",
+                "string",
+                "`read-only`, `current` or `full-access`.",
+            ),
+            optional("images", "string[]", "Paths of images to attach."),
+            optional("plan", "boolean", "Ask for a plan instead of changes."),
+            optional(
+                "dryRun",
+                "boolean",
+                "Return the provider command without running it.",
+            ),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "prompts_history",
+        title: "Prompt history",
+        category: "Threads",
+        description: "List prompts sent in a workspace, newest first or fuzzy-matched.",
+        params: &[
+            WORKSPACE,
+            optional("query", "string", "Characters to match in order."),
+            optional("limit", "number", "Most prompts to return."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "prompts_history_resend",
+        title: "Resend prompt",
+        category: "Threads",
+        description: "Send a prompt from the workspace's history to a thread.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("promptId", "string", "History entry to send."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "reply_latest",
+        title: "Reply to latest thread",
+        category: "Threads",
+        description: "Send a message to the workspace's most recently updated thread.",
+        params: &[WORKSPACE, required("text", "string", "Message to send.")],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "turn_interrupt",
+        title: "Stop turn",
+        category: "Threads",
+        description: "Interrupt a running turn.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("turnId", "string", "Turn to interrupt."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "resume_thread",
+        title: "Open thread",
+        category: "Threads",
+        description: "Load a thread with its turns, optionally one page at a time.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            optional("cursor", "string", "`nextCursor` of the newer page."),
+            optional("limit", "number", "Newest turns to return; all by default."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "mark_thread_read",
+        title: "Mark thread as read",
+        category: "Threads",
+        description: "Clear the thread's unread turns.",
+        params: &[WORKSPACE, THREAD],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "set_item_pinned",
+        title: "Pin message",
+        category: "Threads",
+        description: "Pin or unpin a message so it is kept when a session is reseeded.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("itemId", "string", "Message to pin."),
+            optional("pinned", "boolean", "False to unpin; defaults to true."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "edit_message",
+        title: "Edit message",
+        category: "Threads",
+        description: "Replace a past message, drop the turns after it and run it again.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("itemId", "string", "User message to edit."),
+            optional("text", "string", "New text; omit to resend it as is."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "undo_edit_message",
+        title: "Undo message edit",
+        category: "Threads",
+        description: "Restore a thread as it was before its latest message edit.",
+        params: &[WORKSPACE, THREAD],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "thread_pinned_items",
+        title: "Pinned messages",
+        category: "Threads",
+        description: "List a thread's pinned messages in order.",
+        params: &[WORKSPACE, THREAD],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "diff_items",
+        title: "Compare answers",
+        category: "Threads",
+        description: "Word-level diff of two assistant messages, such as two models' answers.",
+        params: &[
+            WORKSPACE,
+            required("leftThreadId", "string", "Thread of the first answer."),
+            required("leftItemId", "string", "First answer."),
+            required("rightThreadId", "string", "Thread of the second answer."),
+            required("rightItemId", "string", "Second answer."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "set_thread_name",
+        title: "Rename thread",
+        category: "Threads",
+        description: "Rename a thread; an empty name clears it.",
+        params: &[WORKSPACE, THREAD, required("name", "string", "New name.")],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "fork_thread",
+        title: "Fork thread",
+        category: "Threads",
+        description: "Branch a new thread off an existing one.",
+        params: &[WORKSPACE, THREAD],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "duplicate_thread",
+        title: "Duplicate thread",
+        category: "Threads",
+        description: "Copy a thread with its transcript into a new session.",
+        params: &[WORKSPACE, THREAD],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "move_thread",
+        title: "Move thread to workspace",
+        category: "Threads",
+        description: "Move a thread and its transcript to another workspace.",
+        params: &[WORKSPACE, THREAD, TARGET_WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "rebind_thread_workspace",
+        title: "Rebind thread to workspace",
+        category: "Threads",
+        description: "Point a thread whose folder is gone at a workspace that exists.",
+        params: &[WORKSPACE, THREAD, TARGET_WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "copy_thread",
+        title: "Copy thread to workspace",
+        category: "Threads",
+        description: "Copy a thread with its transcript into another workspace.",
+        params: &[WORKSPACE, THREAD, TARGET_WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "compact_thread",
+        title: "Compact thread",
+        category: "Threads",
+        description: "Summarize the thread's history to free up context.",
+        params: &[WORKSPACE, THREAD],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "archive_thread",
+        title: "Archive thread",
+        category: "Threads",
+        description: "Move a thread to the trash.",
+        params: &[WORKSPACE, THREAD],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "bulk_thread_op",
+        title: "Change many threads",
+        category: "Threads",
+        description: "Archive, delete, tag or export several threads at once.",
+        params: &[
+            WORKSPACE,
+            required("threadIds", "string[]", "Threads to change."),
+            required("op", "string", "`archive`, `delete`, `tag` or `export`."),
+            optional("tags", "string[]", "Tags to add for `tag`."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "list_worktree_threads",
+        title: "Threads across worktrees",
+        category: "Threads",
+        description: "List a worktree's threads together with its parent repo's.",
+        params: &[WORKSPACE],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "threads_sync",
+        title: "Sync thread history",
+        category: "Threads",
+        description: "Import the workspace's Claude CLI history and drop placeholder threads.",
+        params: &[WORKSPACE],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "threads_quick_find",
+        title: "Find thread",
+        category: "Threads",
+        description: "Search thread titles and previews as you type.",
+        params: &[
+            optional("workspaceId", "string", "Only search this workspace."),
+            required("query", "string", "Words or word prefixes to match."),
+            optional("limit", "number", "Most threads to return."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "inbox_list",
+        title: "Inbox",
+        category: "Threads",
+        description: "Recent turns across all workspaces.",
+        params: &[optional("limit", "number", "Most entries to return.")],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "start_review",
+        title: "Start review",
+        category: "Threads",
+        description: "Ask the agent to review uncommitted changes, a branch or a commit.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("target", "object", "What to review."),
+            optional(
+                "delivery",
+                "string",
+                "Where the review runs, as the provider names it.",
+            ),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "prompt_estimate",
+        title: "Estimate prompt size",
+        category: "Threads",
+        description: "Count the tokens a prompt would send, context files included.",
+        params: &[
+            WORKSPACE,
+            required("text", "string", "Prompt to estimate."),
+            optional("images", "string[]", "Paths of images to attach."),
+            optional("model", "string", "Model to estimate for."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "list_playbooks",
+        title: "Playbooks",
+        category: "Playbooks",
+        description: "List saved playbooks.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "run_playbook",
+        title: "Run playbook",
+        category: "Playbooks",
+        description: "Run a playbook's steps in a thread.",
+        params: &[
+            WORKSPACE,
+            THREAD,
+            required("playbookId", "string", "Playbook to run."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "memory_read",
+        title: "Show workspace memory",
+        category: "Memory",
+        description: "Read the notes attached to new threads in a workspace.",
+        params: &[WORKSPACE],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "memory_clear",
+        title: "Clear workspace memory",
+        category: "Memory",
+        description: "Delete the workspace's memory notes.",
+        params: &[WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "generate_commit_message",
+        title: "Generate commit message",
+        category: "Git",
+        description: "Draft a commit message for the staged changes, and optionally commit them.",
+        params: &[
+            WORKSPACE,
+            optional("commit", "boolean", "Also commit the staged changes."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "list_trash",
+        title: "Trash",
+        category: "Data",
+        description: "List archived and deleted threads.",
+        params: &[optional(
+            "workspaceId",
+            "string",
+            "Only this workspace's trash.",
+        )],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "restore_trash",
+        title: "Restore from trash",
+        category: "Data",
+        description: "Bring trashed threads back.",
+        params: &[
+            WORKSPACE,
+            required("threadIds", "string[]", "Threads to restore."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "orphans_list",
+        title: "Find orphaned data",
+        category: "Data",
+        description: "List data left behind by removed workspaces.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "orphans_purge",
+        title: "Purge orphaned data",
+        category: "Data",
+        description: "Delete the data of removed workspaces.",
+        params: &[required(
+            "workspaceIds",
+            "string[]",
+            "Removed workspaces to purge.",
+        )],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "storage_usage",
+        title: "Storage usage",
+        category: "Data",
+        description: "Show how much disk the app data uses.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "profile_list",
+        title: "Profiles",
+        category: "Data",
+        description: "List the app profiles and which one is active.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "profile_switch",
+        title: "Switch profile",
+        category: "Data",
+        description: "Restart into another profile, creating it the first time.",
+        params: &[required("name", "string", "Profile to switch to.")],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "import_codex_sessions",
+        title: "Import Codex sessions",
+        category: "Data",
+        description: "Import the workspace's Codex CLI sessions as threads.",
+        params: &[WORKSPACE],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "export_threads",
+        title: "Export threads",
+        category: "Data",
+        description: "Export a workspace's threads as JSON in the published PolyCode schema.",
+        params: &[
+            WORKSPACE,
+            optional(
+                "threadIds",
+                "string[]",
+                "Threads to export; all when omitted.",
+            ),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "import_threads",
+        title: "Import PolyCode export",
+        category: "Data",
+        description: "Import threads from a PolyCode JSON export.",
+        params: &[WORKSPACE, required("path", "string", "File to import.")],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "import_external",
+        title: "Import conversation",
+        category: "Data",
+        description: "Import a conversation exported from another tool.",
+        params: &[
+            WORKSPACE,
+            required("format", "string", "Format of the export."),
+            required("path", "string", "File to import."),
+        ],
+        mutating: true,
+    },
+    ActionSpec {
+        id: "lan_sync_discover",
+        title: "Find devices to sync",
+        category: "Sync",
+        description: "Look for other PolyCode instances on the local network.",
+        params: &[optional("timeoutMs", "number", "How long to listen.")],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "setup_probe",
+        title: "Probe setup",
+        category: "Diagnostics",
+        description: "Find agent CLIs, their session history and folders to add as workspaces.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "providers_detect",
+        title: "Detect providers",
+        category: "Diagnostics",
+        description: "Find installed agent CLIs and their versions.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "codex_doctor",
+        title: "Codex doctor",
+        category: "Diagnostics",
+        description: "Check that the Codex CLI can start.",
+        params: &[
+            optional("codexBin", "string", "Codex binary to check."),
+            optional("codexArgs", "string", "Extra arguments to check with."),
+        ],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "config_effective",
+        title: "Effective Codex args",
+        category: "Diagnostics",
+        description: "Show the Codex args a workspace starts with and which settings conflict.",
+        params: &[WORKSPACE],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "status_summary",
+        title: "Status summary",
+        category: "Diagnostics",
+        description: "Count running and queued turns and recent errors across workspaces.",
+        params: &[],
+        mutating: false,
+    },
+    ActionSpec {
+        id: "audit_query",
+        title: "Query audit log",
+        category: "Diagnostics",
+        description: "List the agent processes the app started, by date and workspace.",
+        params: &[
+            optional("since", "number", "Earliest start, in ms since the epoch."),
+            optional("until", "number", "Latest start, in ms since the epoch."),
+            optional("workspaceId", "string", "Only this workspace's processes."),
+            optional("limit", "number", "Entries to return; 500 by default."),
+        ],
+        mutating: false,
+    },
+];
+
+/// Every term of the query must appear in the action's id, title, category or description.
+fn matches_query(action: &ActionSpec, terms: &[String]) -> bool {
+    let haystack = format!(
+        "{} {} {} {}",
+        action.id, action.title, action.category, action.description
+    )
+    .to_lowercase();
+    terms.iter().all(|term| haystack.contains(term.as_str()))
+}
+
+/// Actions matching `query`, in registry order. Mutating actions are listed as disabled while
+/// the app is in read-only mode.
+pub(crate) fn actions_list_core(query: Option<String>, read_only: bool) -> Value {
+    let terms = query
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    let actions = ACTIONS
+        .iter()
+        .filter(|action| matches_query(action, &terms))
+        .map(|action| {
+            let mut value = json!(action);
+            value["enabled"] = json!(!(read_only && action.mutating));
+            value
+        })
+        .collect::<Vec<_>>();
+    json!({ "result": { "actions": actions } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{actions_list_core, ACTIONS};
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_action_is_a_registered_command() {
+        let commands = include_str!("../lib.rs");
+        let mut ids = HashSet::new();
+        for action in ACTIONS {
+            assert!(ids.insert(action.id), "duplicate action {}", action.id);
+            assert!(
+                commands.contains(&format!("::{},", action.id)),
+                "{} is not a registered command",
+                action.id
+            );
+        }
+    }
+
+    #[test]
+    fn filters_by_every_query_term_and_disables_mutations_when_read_only() {
+        let listed = actions_list_core(Some("  THREAD archive ".to_string()), true);
+        let actions = listed["result"]["actions"].as_array().expect("actions");
+        let ids = actions
+            .iter()
+            .map(|action| action["id"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["archive_thread", "bulk_thread_op", "list_trash"]);
+        assert_eq!(actions[0]["enabled"], false);
+        assert_eq!(actions[0]["params"][1]["type"], "string");
+
+        let all = actions_list_core(None, false);
+        assert_eq!(
+            all["result"]["actions"].as_array().map(Vec::len),
+            Some(ACTIONS.len())
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::shared::claude_core::ClaudeThreadsStore;
+
+/// Above this many LCS cells (changed words on the left times changed words on the right) the
+/// changed middle is reported as one removal and one addition instead of being aligned.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum HunkKind {
+    Equal,
+    Removed,
+    Added,
+}
+
+/// A run of text that both answers share, or that only one of them has. Concatenating the
+/// `equal` and `removed` hunks gives the left answer; `equal` and `added` give the right one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct DiffHunk {
+    pub(crate) kind: HunkKind,
+    pub(crate) text: String,
+}
+
+/// Words, whitespace runs and single punctuation marks, so hunks keep the original spacing.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let same_run = |next: char| {
+            (ch.is_alphanumeric() || ch == '_') && (next.is_alphanumeric() || next == '_')
+                || ch.is_whitespace() && next.is_whitespace()
+        };
+        if chars.peek().is_none_or(|(_, next)| !same_run(*next)) {
+            let end = index + ch.len_utf8();
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
+
+fn is_word(token: &str) -> bool {
+    !token.trim().is_empty()
+}
+
+fn push_hunk(hunks: &mut Vec<DiffHunk>, kind: HunkKind, token: &str) {
+    match hunks.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(token),
+        _ => hunks.push(DiffHunk {
+            kind,
+            text: token.to_string(),
+        }),
+    }
+}
+
+/// Aligns the changed middle of two token lists with a longest-common-subsequence table.
+fn diff_middle(left: &[&str], right: &[&str], hunks: &mut Vec<DiffHunk>) {
+    if left.len().saturating_mul(right.len()) > MAX_DIFF_CELLS {
+        for token in left {
+            push_hunk(hunks, HunkKind::Removed, token);
+        }
+        for token in right {
+            push_hunk(hunks, HunkKind::Added, token);
+        }
+        return;
+    }
+    let width = right.len() + 1;
+    let mut lcs = vec![0u32; (left.len() + 1) * width];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i * width + j] = if left[i] == right[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            push_hunk(hunks, HunkKind::Equal, left[i]);
+            i += 1;
+            j += 1;
+        } else if j == right.len()
+            || (i < left.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            push_hunk(hunks, HunkKind::Removed, left[i]);
+            i += 1;
+        } else {
+            push_hunk(hunks, HunkKind::Added, right[j]);
+            j += 1;
+        }
+    }
+}
+
+/// Word-level diff of two texts, as hunks in reading order.
+pub(crate) fn diff_words(left: &str, right: &str) -> Vec<DiffHunk> {
+    let left = tokenize(left);
+    let right = tokenize(right);
+    let prefix = left
+        .iter()
+        .zip(&right)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = left[prefix..]
+        .iter()
+        .rev()
+        .zip(right[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let mut hunks = Vec::new();
+    for token in &left[..prefix] {
+        push_hunk(&mut hunks, HunkKind::Equal, token);
+    }
+    diff_middle(
+        &left[prefix..left.len() - suffix],
+        &right[prefix..right.len() - suffix],
+        &mut hunks,
+    );
+    for token in &left[left.len() - suffix..] {
+        push_hunk(&mut hunks, HunkKind::Equal, token);
+    }
+    hunks
+}
+
+fn count_words(hunks: &[DiffHunk], kind: HunkKind) -> usize {
+    hunks
+        .iter()
+        .filter(|hunk| hunk.kind == kind)
+        .flat_map(|hunk| tokenize(&hunk.text))
+        .filter(|token| is_word(token))
+        .count()
+}
+
+/// Turn and text of an assistant message.
+async fn assistant_item(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    item_id: &str,
+) -> Result<(String, String), String> {
+    let store = claude_threads.lock().await;
+    let thread = store
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .ok_or_else(|| format!("thread not found: {thread_id}"))?;
+    thread
+        .turns
+        .iter()
+        .find_map(|turn| {
+            turn.items
+                .iter()
+                .find(|item| item.id == item_id && item.role == "assistant")
+                .map(|item| (turn.id.clone(), item.text.clone()))
+        })
+        .ok_or_else(|| format!("assistant message not found: {item_id}"))
+}
+
+/// Compares two stored assistant answers, usually the same prompt run on different models.
+/// The threads may differ but must belong to the same workspace.
+pub(crate) async fn diff_items_core(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    left_thread_id: String,
+    left_item_id: String,
+    right_thread_id: String,
+    right_item_id: String,
+) -> Result<Value, String> {
+    let (left_turn_id, left_text) = assistant_item(
+        claude_threads,
+        &workspace_id,
+        &left_thread_id,
+        &left_item_id,
+    )
+    .await?;
+    let (right_turn_id, right_text) = assistant_item(
+        claude_threads,
+        &workspace_id,
+        &right_thread_id,
+        &right_item_id,
+    )
+    .await?;
+    let hunks = diff_words(&left_text, &right_text);
+    let unchanged = count_words(&hunks, HunkKind::Equal);
+    let removed = count_words(&hunks, HunkKind::Removed);
+    let added = count_words(&hunks, HunkKind::Added);
+    let total = unchanged * 2 + removed + added;
+    let similarity = if total == 0 {
+        1.0
+    } else {
+        (unchanged * 2) as f64 / total as f64
+    };
+    Ok(json!({
+        "result": {
+            "left": {
+                "threadId": left_thread_id,
+                "turnId": left_turn_id,
+                "itemId": left_item_id,
+            },
+            "right": {
+                "threadId": right_thread_id,
+                "turnId": right_turn_id,
+                "itemId": right_item_id,
+            },
+            "hunks": hunks,
+            "stats": {
+                "unchangedWords": unchanged,
+                "removedWords": removed,
+                "addedWords": added,
+                "similarity": similarity,
+            },
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_words, HunkKind};
+
+    #[test]
+    fn diffs_words_and_keeps_both_texts_recoverable() {
+        let left = "Run `cargo test`, then fix the parser.\nDone.";
+        let right = "Run `cargo test --all`, then fix the lexer.\nDone.";
+        let hunks = diff_words(left, right);
+        let side = |skip: HunkKind| {
+            hunks
+                .iter()
+                .filter(|hunk| hunk.kind != skip)
+                .map(|hunk| hunk.text.as_str())
+                .collect::<String>()
+        };
+        assert_eq!(side(HunkKind::Added), left);
+        assert_eq!(side(HunkKind::Removed), right);
+        let changes = hunks
+            .iter()
+            .filter(|hunk| hunk.kind != HunkKind::Equal)
+            .map(|hunk| (hunk.kind, hunk.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                (HunkKind::Added, " --all"),
+                (HunkKind::Removed, "parser"),
+                (HunkKind::Added, "lexer"),
+            ]
+        );
+
+        let same = diff_words("same text", "same text");
+        assert_eq!(same.len(), 1);
+        assert_eq!(same[0].kind, HunkKind::Equal);
+        assert!(diff_words("", "").is_empty());
+    }
+}
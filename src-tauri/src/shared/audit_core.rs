@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::shared::turn_details_core::TurnRequest;
+use crate::types::ProviderKind;
+
+const AUDIT_LOG_FILE_NAME: &str = "audit.jsonl";
+const DEFAULT_QUERY_LIMIT: usize = 500;
+
+/// Appends from different turns go through one lock so their lines never interleave.
+static AUDIT_LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// One agent process the app started. Entries only describe the process, never the prompt or
+/// output, so the log can be kept and shared apart from thread content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditEntry {
+    pub(crate) workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) thread_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) provider: ProviderKind,
+    /// What the user did to cause the spawn, such as `sendMessage`.
+    pub(crate) action: String,
+    /// The resolved program.
+    pub(crate) bin: String,
+    /// SHA-256 of the program and its arguments, NUL-separated, with an argv prompt already
+    /// replaced by its size.
+    pub(crate) argv_hash: String,
+    pub(crate) attempts: u32,
+    /// Milliseconds since the epoch.
+    pub(crate) started_at: i64,
+    pub(crate) finished_at: i64,
+    /// `None` when the process failed to start, was canceled or was killed by a signal.
+    #[serde(default)]
+    pub(crate) exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) fn audit_log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(AUDIT_LOG_FILE_NAME)
+}
+
+pub(crate) fn argv_hash(bin: &str, args: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bin.as_bytes());
+    for arg in args {
+        hasher.update([0]);
+        hasher.update(arg.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+impl AuditEntry {
+    /// Describes a turn's provider process from the request recorded for it.
+    pub(crate) fn for_turn(
+        workspace_id: &str,
+        thread_id: &str,
+        turn_id: &str,
+        action: &str,
+        request: &TurnRequest,
+        started_at: i64,
+        finished_at: i64,
+    ) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            thread_id: Some(thread_id.to_string()),
+            turn_id: Some(turn_id.to_string()),
+            provider: ProviderKind::Claude,
+            action: action.to_string(),
+            bin: request.bin.clone(),
+            argv_hash: argv_hash(&request.bin, &request.args),
+            attempts: request.attempts,
+            started_at,
+            finished_at,
+            exit_code: request.exit_status,
+            error: None,
+        }
+    }
+}
+
+/// Adds an entry to the end of the log. The file is only ever opened for appending; nothing
+/// in the app rewrites, trims or deletes it.
+pub(crate) fn append_audit_entry(data_dir: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let mut line = serde_json::to_string(entry).map_err(|error| error.to_string())?;
+    line.push('\n');
+    let _guard = AUDIT_LOG_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    std::fs::create_dir_all(data_dir).map_err(|error| error.to_string())?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log_path(data_dir))
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|error| format!("Failed to write the audit log: {error}"))
+}
+
+/// Entries started within `[since, until]` (ms since the epoch), optionally for one workspace,
+/// newest first. Lines that don't parse are counted rather than failing the query.
+pub(crate) fn audit_query_core(
+    data_dir: &Path,
+    since: Option<i64>,
+    until: Option<i64>,
+    workspace_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Value, String> {
+    let path = audit_log_path(data_dir);
+    if !path.exists() {
+        return Ok(json!({ "result": { "entries": [], "total": 0, "unreadable": 0 } }));
+    }
+    let file = std::fs::File::open(&path)
+        .map_err(|error| format!("Failed to read the audit log: {error}"))?;
+    let mut entries = Vec::new();
+    let mut unreadable = 0usize;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|error| format!("Failed to read the audit log: {error}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) else {
+            unreadable += 1;
+            continue;
+        };
+        let in_range = since.is_none_or(|since| entry.started_at >= since)
+            && until.is_none_or(|until| entry.started_at <= until);
+        let in_workspace = workspace_id
+            .as_deref()
+            .is_none_or(|workspace_id| entry.workspace_id == workspace_id);
+        if in_range && in_workspace {
+            entries.push(entry);
+        }
+    }
+    let total = entries.len();
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+    Ok(json!({
+        "result": {
+            "entries": entries,
+            "total": total,
+            "unreadable": unreadable,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append_audit_entry, argv_hash, audit_log_path, audit_query_core, AuditEntry};
+    use crate::types::ProviderKind;
+    use std::io::Write;
+
+    fn entry(workspace_id: &str, started_at: i64) -> AuditEntry {
+        AuditEntry {
+            workspace_id: workspace_id.to_string(),
+            thread_id: Some("t-1".to_string()),
+            turn_id: Some(format!("turn-{started_at}")),
+            provider: ProviderKind::Claude,
+            action: "sendMessage".to_string(),
+            bin: "/usr/bin/claude".to_string(),
+            argv_hash: argv_hash("/usr/bin/claude", &["-p".to_string()]),
+            attempts: 1,
+            started_at,
+            finished_at: started_at + 5,
+            exit_code: Some(0),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn appends_entries_and_filters_by_date_and_workspace() {
+        let data_dir = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        append_audit_entry(&data_dir, &entry("ws-1", 100)).expect("append");
+        append_audit_entry(&data_dir, &entry("ws-2", 200)).expect("append");
+        append_audit_entry(&data_dir, &entry("ws-1", 300)).expect("append");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(audit_log_path(&data_dir))
+            .and_then(|mut file| file.write_all(b"not json\n"))
+            .expect("garbage line");
+
+        let all = audit_query_core(&data_dir, None, None, None, None).expect("query");
+        assert_eq!(all["result"]["total"], 3);
+        assert_eq!(all["result"]["unreadable"], 1);
+        assert_eq!(all["result"]["entries"][0]["startedAt"], 300);
+
+        let ws1 = audit_query_core(&data_dir, Some(150), None, Some("ws-1".to_string()), None)
+            .expect("query");
+        let entries = ws1["result"]["entries"].as_array().expect("entries");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["turnId"], "turn-300");
+        assert_eq!(entries[0]["argvHash"].as_str().map(str::len), Some(64));
+
+        let limited = audit_query_core(&data_dir, None, Some(250), None, Some(1)).expect("query");
+        assert_eq!(limited["result"]["total"], 2);
+        assert_eq!(limited["result"]["entries"][0]["workspaceId"], "ws-2");
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
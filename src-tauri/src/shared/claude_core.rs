@@ -5,26 +5,72 @@ use std::fs::File;
 use std::io::{BufRead, BufReader as StdBufReader};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use chrono::DateTime;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncWriteExt, BufReader};
 use tokio::sync::oneshot::error::TryRecvError;
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{broadcast, oneshot, Mutex};
 use tokio::time::{timeout, Duration};
 use uuid::Uuid;
 
 use crate::backend::events::{AppServerEvent, EventSink};
 use crate::providers;
-use crate::shared::process_core::tokio_command;
-use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+use crate::shared::audit_core::{self, AuditEntry};
+use crate::shared::claude_pool_core::{
+    self, ApprovalRequest, ClaudeApprovalsStore, ClaudeProcessPool, PooledTurn, PooledTurnRequest,
+    StreamLine,
+};
+use crate::shared::context_files_core::{
+    format_context_blocks, load_context_files, resolve_context_paths, ContextFileRecord,
+    LoadedContextFile,
+};
+use crate::shared::file_access_core::{self, FileAccessGuard};
+use crate::shared::file_references_core;
+use crate::shared::idle_summary_core::IdleSummary;
+use crate::shared::memory_core;
+use crate::shared::model_downgrade_core::{self, ModelDowngrade};
+use crate::shared::output_cap_core::TurnOutputCap;
+use crate::shared::output_decode_core::lossy_lines;
+use crate::shared::output_filters_core::OutputFilterPipeline;
+use crate::shared::output_sanitize_core;
+use crate::shared::process_core::through_shell;
+use crate::shared::provider_versions_core;
+use crate::shared::rate_limit_core::{parse_rate_limit_hint, rate_limit_state, RateLimitsStore};
+use crate::shared::redaction_core::{self, RedactionPipeline};
+use crate::shared::render_cache_core::{resume_page_range, RenderCacheStore};
+use crate::shared::resource_guard_core;
+use crate::shared::sandbox_core;
+use crate::shared::secrets_core::{KeyringSecrets, ResolvedSecrets};
+use crate::shared::settings_core::{self, SettingsChange};
+use crate::shared::thread_locks_core::ThreadLocksStore;
+use crate::shared::thread_quick_find_core;
+use crate::shared::thread_schema_core;
+use crate::shared::thread_stats_core::{self, ThreadStats};
+use crate::shared::thread_worktree_core::{self, ThreadWorktree};
+use crate::shared::trash_core::{trash_threads, TrashReason};
+use crate::shared::turn_artifacts_core::{self, ArtifactCollection};
+use crate::shared::turn_checklist_core::{
+    checklist_event, checklist_update, record_turn_checklist, ChecklistStep,
+};
+use crate::shared::turn_details_core::{record_turn_request, TurnRequest};
+use crate::shared::turn_hooks_core::{self, TurnHookRun};
+use crate::shared::turn_slots_core::{acquire_turn_slot, TurnSlotsStore};
+use crate::shared::turn_snapshot_core::{self, TurnSnapshot};
+use crate::shared::turn_suggestions_core::{self, SuggestionRequest};
+use crate::types::{AppSettings, ProviderKind, SandboxSettings, WorkspaceEntry};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct ClaudeMessageRecord {
     pub(crate) id: String,
     pub(crate) role: String,
     pub(crate) text: String,
+    /// Pinned messages are carried into the seed of a replacement session even when they are
+    /// too old for its replayed transcript.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) pinned: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +81,75 @@ pub(crate) struct ClaudeTurnRecord {
     #[serde(rename = "completedAt")]
     pub(crate) completed_at: Option<i64>,
     pub(crate) items: Vec<ClaudeMessageRecord>,
+    #[serde(
+        default,
+        rename = "contextFiles",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(crate) context_files: Vec<ContextFileRecord>,
+    /// `--version` output of the CLI that ran the turn.
+    #[serde(
+        default,
+        rename = "cliVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) cli_version: Option<String>,
+    /// Wall time from spawning the CLI to the end of the turn, including retries.
+    #[serde(
+        default,
+        rename = "durationMs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) duration_ms: Option<u64>,
+    /// Git snapshot taken before the turn when the workspace has turn snapshots on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snapshot: Option<TurnSnapshot>,
+    /// Set when the turn ran in plan mode; its answer is a plan for `execute_plan`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) plan: bool,
+    /// Follow-up prompts suggested after the turn when follow-up suggestions are on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) suggestions: Vec<String>,
+    /// How the provider process was started, for `turn_details`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) request: Option<TurnRequest>,
+    /// Latest to-do list Claude kept with `TodoWrite` during the turn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) checklist: Vec<ChecklistStep>,
+    /// Provider profile from `providerFallbacks` that ran the turn after the workspace's own
+    /// Claude config failed to start; unset when no fallback was needed.
+    #[serde(
+        default,
+        rename = "answeredBy",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) answered_by: Option<String>,
+    /// Cheaper model the downgrade policy ran the turn on, and why; unset when the turn used
+    /// the thread's own model.
+    #[serde(
+        default,
+        rename = "modelDowngrade",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) model_downgrade: Option<ModelDowngrade>,
+}
+
+/// Sampling settings pinned to a thread and applied to each of its turns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) model: Option<String>,
+    /// Stored for providers that accept it; the Claude CLI has no temperature flag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) temperature: Option<f64>,
+    #[serde(default, rename = "maxTokens", skip_serializing_if = "Option::is_none")]
+    pub(crate) max_tokens: Option<u32>,
+}
+
+impl GenerationParams {
+    pub(crate) fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,16 +164,124 @@ pub(crate) struct ClaudeThreadRecord {
     #[serde(default)]
     pub(crate) name: Option<String>,
     #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    /// Replacement Claude session when the original one became unresumable.
+    #[serde(default, rename = "sessionId", skip_serializing_if = "Option::is_none")]
+    pub(crate) session_id: Option<String>,
+    #[serde(
+        default,
+        rename = "sessionNeedsSeed",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub(crate) session_needs_seed: bool,
+    /// Session the Claude CLI reported for the latest turn; the next turn resumes it.
+    #[serde(
+        default,
+        rename = "providerSessionId",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) provider_session_id: Option<String>,
+    /// Directory (inside the workspace) the agent runs in instead of the workspace root.
+    #[serde(
+        default,
+        rename = "cwdOverride",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) cwd_override: Option<String>,
+    #[serde(default, skip_serializing_if = "GenerationParams::is_empty")]
+    pub(crate) generation: GenerationParams,
+    /// Set when the thread runs in its own git worktree; turns use it instead of the workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) worktree: Option<ThreadWorktree>,
+    /// Written by the idle summary task; stale once a newer turn lands.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) summary: Option<IdleSummary>,
+    /// Counted from the stored turns when a snapshot from before stats existed is migrated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stats: Option<ThreadStats>,
+    /// When the user last opened the thread; turns finished after it are unread.
+    #[serde(
+        default,
+        rename = "lastReadAt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) last_read_at: Option<i64>,
+    #[serde(default)]
     pub(crate) turns: Vec<ClaudeTurnRecord>,
 }
 
+impl ClaudeThreadRecord {
+    /// Whether the latest turn is still in flight (or was cut off before it completed).
+    pub(crate) fn has_open_turn(&self) -> bool {
+        self.turns
+            .last()
+            .is_some_and(|turn| turn.completed_at.is_none())
+    }
+}
+
 pub(crate) type ClaudeThreadsStore = Arc<Mutex<HashMap<String, Vec<ClaudeThreadRecord>>>>;
-pub(crate) type ClaudeTurnCancelsStore = Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>;
+/// A running turn's cancel handle; the store is keyed by turn id.
+pub(crate) struct TurnCancel {
+    workspace_id: String,
+    thread_id: String,
+    cancel: oneshot::Sender<()>,
+}
+
+pub(crate) type ClaudeTurnCancelsStore = Arc<Mutex<HashMap<String, TurnCancel>>>;
 
 const CLAUDE_THREADS_FILE_NAME: &str = "claude_threads.json";
 const CLAUDE_ARCHIVED_THREADS_FILE_NAME: &str = "claude_archived_threads.json";
-const CLAUDE_HISTORY_ROOT: &str = ".claude/projects";
+const CLAUDE_CONFIG_DIR_NAME: &str = ".claude";
+const CLAUDE_PROJECTS_DIR_NAME: &str = "projects";
 const MAX_IMPORTED_TURNS_PER_THREAD: usize = 200;
+const TURN_PROGRESS_INTERVAL_MS: u128 = 1000;
+const TURN_STALL_THRESHOLD_MS: u128 = 30_000;
+const SESSION_SEED_MAX_CHARS: usize = 32_000;
+const PINNED_SEED_MAX_CHARS: usize = 8_000;
+const RETRY_MAX_DELAY_MS: u64 = 60_000;
+/// Flags for a warm process that takes user messages on stdin for the lifetime of a session.
+const WARM_PROCESS_ARGS: &[&str] = &[
+    "-p",
+    "--input-format",
+    "stream-json",
+    "--output-format",
+    "stream-json",
+    "--verbose",
+    "--include-partial-messages",
+];
+/// Plan mode: Claude explores read-only and answers with a plan instead of editing anything.
+const PLAN_MODE_ARGS: &[&str] = &["--permission-mode", "plan"];
+const GUARDED_PROCESS_UNAVAILABLE: &str =
+    "Claude could not be started with permission prompts, so the turn was not run.";
+/// A thread runs one turn at a time; messages sent while it runs are refused, not queued.
+const THREAD_BUSY: &str =
+    "Busy: the thread is still running a turn. Wait for it to finish or interrupt it.";
+/// Status codes only count in the CLI's `API Error: <code>` form, so numbers in ordinary output
+/// never trigger a retry.
+const TRANSIENT_FAILURE_MARKERS: &[&str] = &[
+    "rate limit",
+    "rate_limit",
+    "too many requests",
+    "overloaded",
+    "api error: 429",
+    "api error: 529",
+    "api error: 503",
+    "temporarily unavailable",
+];
+/// Output of a CLI that could not be found through the launch shell, or that was refused its
+/// credentials; either moves a turn on to the workspace's next provider fallback.
+const FALLBACK_FAILURE_MARKERS: &[&str] = &[
+    "command not found",
+    "is not recognized as an internal or external command",
+    "invalid api key",
+    "authentication_error",
+    "authentication failed",
+    "oauth token has expired",
+    "please run /login",
+    "not logged in",
+];
+/// Shells exit with 127 when the program they were asked to run doesn't exist.
+const COMMAND_NOT_FOUND_STATUS: i32 = 127;
 
 pub(crate) fn claude_threads_path(data_dir: &Path) -> PathBuf {
     data_dir.join(CLAUDE_THREADS_FILE_NAME)
@@ -71,17 +294,37 @@ pub(crate) fn read_threads_snapshot(
         return Ok(HashMap::new());
     }
     let data = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
-    serde_json::from_str(&data).map_err(|error| error.to_string())
+    thread_schema_core::parse_threads_snapshot(&data)
+}
+
+/// Threads saved before sessions were tracked named theirs `claude-thread-<session>`. Moves
+/// that id into the session fields so turns never have to derive it from the thread id.
+pub(crate) fn migrate_legacy_session(thread: &mut ClaudeThreadRecord) {
+    if thread.session_id.is_some() || thread.provider_session_id.is_some() {
+        return;
+    }
+    let Some(session_id) = legacy_prefixed_session_id(&thread.id) else {
+        return;
+    };
+    if thread.turns.is_empty() {
+        // Never ran, so the session does not exist yet; the first turn creates it.
+        thread.session_id = Some(session_id);
+        thread.session_needs_seed = true;
+    } else {
+        thread.provider_session_id = Some(session_id);
+    }
 }
 
 fn write_threads_snapshot(
     path: &Path,
     threads: &HashMap<String, Vec<ClaudeThreadRecord>>,
 ) -> Result<(), String> {
+    thread_quick_find_core::index_snapshot(threads);
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
     }
-    let data = serde_json::to_string_pretty(threads).map_err(|error| error.to_string())?;
+    let data = serde_json::to_string_pretty(&thread_schema_core::versioned_snapshot(threads))
+        .map_err(|error| error.to_string())?;
     std::fs::write(path, data).map_err(|error| error.to_string())
 }
 
@@ -116,19 +359,16 @@ fn archived_id_variants(thread_id: &str) -> Vec<String> {
     if let Some(suffix) = trimmed.strip_prefix("claude-thread-") {
         return vec![trimmed.to_string(), suffix.to_string()];
     }
-    vec![
-        trimmed.to_string(),
-        format!("claude-thread-{trimmed}"),
-    ]
+    vec![trimmed.to_string(), format!("claude-thread-{trimmed}")]
 }
 
-fn is_archived_thread_id(archived_ids: &HashSet<String>, thread_id: &str) -> bool {
+pub(crate) fn is_archived_thread_id(archived_ids: &HashSet<String>, thread_id: &str) -> bool {
     archived_id_variants(thread_id)
         .into_iter()
         .any(|id| archived_ids.contains(&id))
 }
 
-fn read_archived_thread_ids_for_workspace(
+pub(crate) fn read_archived_thread_ids_for_workspace(
     claude_threads_path: &Path,
     workspace_id: &str,
 ) -> HashSet<String> {
@@ -142,17 +382,19 @@ fn read_archived_thread_ids_for_workspace(
         .collect()
 }
 
-fn persist_archived_thread_id_for_workspace(
+fn persist_archived_thread_ids_for_workspace(
     claude_threads_path: &Path,
     workspace_id: &str,
-    thread_id: &str,
+    thread_ids: &[String],
 ) -> Result<(), String> {
     let archived_path = claude_archived_threads_path(claude_threads_path);
     let mut snapshot = read_archived_threads_snapshot(&archived_path)?;
     let entry = snapshot.entry(workspace_id.to_string()).or_default();
     let mut merged: HashSet<String> = entry.iter().cloned().collect();
-    for id in archived_id_variants(thread_id) {
-        merged.insert(id);
+    for thread_id in thread_ids {
+        for id in archived_id_variants(thread_id) {
+            merged.insert(id);
+        }
     }
     let mut values = merged.into_iter().collect::<Vec<_>>();
     values.sort();
@@ -160,7 +402,50 @@ fn persist_archived_thread_id_for_workspace(
     write_archived_threads_snapshot(&archived_path, &snapshot)
 }
 
-async fn persist_threads_store(
+/// How many archived thread ids are recorded per workspace.
+pub(crate) fn archived_thread_id_counts(claude_threads_path: &Path) -> HashMap<String, usize> {
+    read_archived_threads_snapshot(&claude_archived_threads_path(claude_threads_path))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(workspace_id, ids)| (workspace_id, ids.len()))
+        .collect()
+}
+
+/// Drops the archived thread ids recorded for the given workspaces.
+pub(crate) fn forget_archived_workspaces(
+    claude_threads_path: &Path,
+    workspace_ids: &HashSet<String>,
+) -> Result<(), String> {
+    let archived_path = claude_archived_threads_path(claude_threads_path);
+    let mut snapshot = read_archived_threads_snapshot(&archived_path)?;
+    let before = snapshot.len();
+    snapshot.retain(|workspace_id, _| !workspace_ids.contains(workspace_id));
+    if snapshot.len() == before {
+        return Ok(());
+    }
+    write_archived_threads_snapshot(&archived_path, &snapshot)
+}
+
+/// Reverses [`persist_archived_thread_ids_for_workspace`] for threads restored from the trash.
+pub(crate) fn unarchive_thread_ids_for_workspace(
+    claude_threads_path: &Path,
+    workspace_id: &str,
+    thread_ids: &[String],
+) -> Result<(), String> {
+    let archived_path = claude_archived_threads_path(claude_threads_path);
+    let mut snapshot = read_archived_threads_snapshot(&archived_path)?;
+    let Some(entry) = snapshot.get_mut(workspace_id) else {
+        return Ok(());
+    };
+    let restored = thread_ids
+        .iter()
+        .flat_map(|thread_id| archived_id_variants(thread_id))
+        .collect::<HashSet<_>>();
+    entry.retain(|id| !restored.contains(id));
+    write_archived_threads_snapshot(&archived_path, &snapshot)
+}
+
+pub(crate) async fn persist_threads_store(
     claude_threads: &ClaudeThreadsStore,
     path: &Path,
 ) -> Result<(), String> {
@@ -168,14 +453,14 @@ async fn persist_threads_store(
     write_threads_snapshot(path, &snapshot)
 }
 
-fn now_ms() -> i64 {
+pub(crate) fn now_ms() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|duration| duration.as_millis() as i64)
         .unwrap_or(0)
 }
 
-fn cancel_key(workspace_id: &str, thread_id: &str) -> String {
+pub(crate) fn cancel_key(workspace_id: &str, thread_id: &str) -> String {
     format!("{workspace_id}:{thread_id}")
 }
 
@@ -248,7 +533,7 @@ fn is_debug_jsonrpc_line(line: &str) -> bool {
     is_jsonrpc_payload(&value)
 }
 
-fn is_debug_jsonrpc_message(message: &str) -> bool {
+pub(crate) fn is_debug_jsonrpc_message(message: &str) -> bool {
     if is_debug_jsonrpc_line(message) {
         return true;
     }
@@ -280,7 +565,7 @@ fn is_debug_jsonrpc_message(message: &str) -> bool {
     is_jsonrpc_payload(&value)
 }
 
-fn legacy_prefixed_session_id(thread_id: &str) -> Option<String> {
+pub(crate) fn legacy_prefixed_session_id(thread_id: &str) -> Option<String> {
     let suffix = thread_id.strip_prefix("claude-thread-")?;
     if Uuid::parse_str(suffix).is_ok() {
         Some(suffix.to_string())
@@ -289,13 +574,13 @@ fn legacy_prefixed_session_id(thread_id: &str) -> Option<String> {
     }
 }
 
-fn parse_rfc3339_ms(value: &str) -> Option<i64> {
+pub(crate) fn parse_rfc3339_ms(value: &str) -> Option<i64> {
     DateTime::parse_from_rfc3339(value)
         .ok()
         .map(|parsed| parsed.timestamp_millis())
 }
 
-fn preview_from_text(text: &str) -> String {
+pub(crate) fn preview_from_text(text: &str) -> String {
     let single_line = text.trim().replace('\n', " ");
     if single_line.len() <= 120 {
         return single_line;
@@ -303,6 +588,22 @@ fn preview_from_text(text: &str) -> String {
     format!("{}...", &single_line[..117])
 }
 
+/// Turns finished since the thread was last read. Threads that were never opened since read
+/// tracking existed (older and imported ones) count as read.
+fn unread_turn_count(thread: &ClaudeThreadRecord) -> usize {
+    let Some(last_read_at) = thread.last_read_at else {
+        return 0;
+    };
+    thread
+        .turns
+        .iter()
+        .filter(|turn| {
+            turn.completed_at
+                .is_some_and(|completed| completed > last_read_at)
+        })
+        .count()
+}
+
 fn thread_summary(thread: &ClaudeThreadRecord) -> Value {
     json!({
         "id": thread.id,
@@ -311,41 +612,59 @@ fn thread_summary(thread: &ClaudeThreadRecord) -> Value {
         "createdAt": thread.created_at,
         "updatedAt": thread.updated_at,
         "name": thread.name,
+        "tags": thread.tags,
+        "cwdOverride": thread.cwd_override,
+        "generation": thread.generation,
+        "worktree": thread.worktree,
+        "summary": thread.summary.as_ref().map(|summary| &summary.text),
+        "stats": thread_stats_core::listing_stats(thread),
+        "lastReadAt": thread.last_read_at,
+        "unreadTurns": unread_turn_count(thread),
     })
 }
 
-fn thread_resume_payload(thread: &ClaudeThreadRecord) -> Value {
-    let turns = thread
-        .turns
+pub(crate) fn render_turn(turn: &ClaudeTurnRecord) -> Value {
+    let items = turn
+        .items
         .iter()
-        .map(|turn| {
-            let items = turn
-                .items
-                .iter()
-                .map(|item| {
-                    if item.role == "user" {
-                        json!({
-                            "id": item.id,
-                            "type": "userMessage",
-                            "content": [{ "type": "text", "text": item.text }],
-                        })
-                    } else {
-                        json!({
-                            "id": item.id,
-                            "type": "agentMessage",
-                            "text": item.text,
-                        })
-                    }
+        .map(|item| {
+            if item.role == "user" {
+                json!({
+                    "id": item.id,
+                    "type": "userMessage",
+                    "content": [{ "type": "text", "text": item.text }],
+                    "pinned": item.pinned,
                 })
-                .collect::<Vec<_>>();
-            json!({
-                "id": turn.id,
-                "startedAt": turn.started_at,
-                "completedAt": turn.completed_at,
-                "items": items,
-            })
+            } else {
+                json!({
+                    "id": item.id,
+                    "type": "agentMessage",
+                    "text": item.text,
+                    "pinned": item.pinned,
+                })
+            }
         })
         .collect::<Vec<_>>();
+    json!({
+        "id": turn.id,
+        "startedAt": turn.started_at,
+        "completedAt": turn.completed_at,
+        "durationMs": turn.duration_ms,
+        "items": items,
+        "contextFiles": turn.context_files,
+        "plan": turn.plan,
+        "checklist": turn.checklist,
+        "answeredBy": turn.answered_by,
+        "modelDowngrade": turn.model_downgrade,
+    })
+}
+
+fn thread_resume_payload(thread: &ClaudeThreadRecord) -> Value {
+    let turns = thread.turns.iter().map(render_turn).collect::<Vec<_>>();
+    thread_resume_header(thread, turns)
+}
+
+fn thread_resume_header(thread: &ClaudeThreadRecord, turns: Vec<Value>) -> Value {
     json!({
         "id": thread.id,
         "cwd": thread.cwd,
@@ -353,11 +672,15 @@ fn thread_resume_payload(thread: &ClaudeThreadRecord) -> Value {
         "createdAt": thread.created_at,
         "updatedAt": thread.updated_at,
         "name": thread.name,
+        "tags": thread.tags,
+        "cwdOverride": thread.cwd_override,
+        "generation": thread.generation,
+        "worktree": thread.worktree,
         "turns": turns,
     })
 }
 
-fn emit<E: EventSink>(event_sink: &E, workspace_id: &str, method: &str, params: Value) {
+pub(crate) fn emit<E: EventSink>(event_sink: &E, workspace_id: &str, method: &str, params: Value) {
     event_sink.emit_app_server_event(AppServerEvent {
         workspace_id: workspace_id.to_string(),
         message: json!({
@@ -367,7 +690,71 @@ fn emit<E: EventSink>(event_sink: &E, workspace_id: &str, method: &str, params:
     });
 }
 
+/// Sends only the thread summaries a mutation touched, so sidebars can patch their list in
+/// place instead of re-fetching `list_threads`.
+pub(crate) async fn emit_thread_list_updated<E: EventSink>(
+    event_sink: &E,
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    changed_ids: &[String],
+    removed_ids: &[String],
+) {
+    let threads = {
+        let store = claude_threads.lock().await;
+        store
+            .get(workspace_id)
+            .map(|threads| {
+                threads
+                    .iter()
+                    .filter(|thread| changed_ids.contains(&thread.id))
+                    .map(thread_summary)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    if threads.is_empty() && removed_ids.is_empty() {
+        return;
+    }
+    emit(
+        event_sink,
+        workspace_id,
+        "thread/listUpdated",
+        json!({
+            "threads": threads,
+            "removedThreadIds": removed_ids,
+        }),
+    );
+}
+
+/// Drops the verbatim prefix `canonicalize` adds on Windows (`\\?\C:\...`, `\\?\UNC\...`),
+/// which Claude never sees in its own cwd.
+fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return format!(r"\\{rest}");
+    }
+    path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+}
+
+/// Matches Claude's project directory naming: every character other than an ASCII letter or
+/// digit becomes `-`, so `/Users/me/app` maps to `-Users-me-app` and `C:\Users\me\app` to
+/// `C--Users-me-app`.
 fn encode_workspace_for_claude_projects(workspace_path: &str) -> Option<String> {
+    let normalized = strip_verbatim_prefix(workspace_path.trim());
+    let trimmed = normalized.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(
+        trimmed
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '-' })
+            .collect(),
+    )
+}
+
+/// Earlier naming that collapsed runs of separators; still checked so existing lookups keep
+/// finding directories that happen to match it.
+fn legacy_encode_workspace_for_claude_projects(workspace_path: &str) -> Option<String> {
     let mut encoded = String::new();
     let mut last_dash = false;
     for ch in workspace_path.chars() {
@@ -387,10 +774,46 @@ fn encode_workspace_for_claude_projects(workspace_path: &str) -> Option<String>
     }
 }
 
-fn claude_project_dir_for_workspace(workspace_path: &str) -> Option<PathBuf> {
-    let home = std::env::var_os("HOME")?;
+/// `$CLAUDE_CONFIG_DIR/projects`, else `~/.claude/projects`. Claude resolves `~` from
+/// `USERPROFILE` on Windows, where `HOME` may point at an MSYS-style path.
+fn claude_projects_root_from(
+    lookup: impl Fn(&str) -> Option<std::ffi::OsString>,
+    windows: bool,
+) -> Option<PathBuf> {
+    let non_empty = |name: &str| lookup(name).filter(|value| !value.is_empty());
+    if let Some(config_dir) = non_empty("CLAUDE_CONFIG_DIR") {
+        return Some(PathBuf::from(config_dir).join(CLAUDE_PROJECTS_DIR_NAME));
+    }
+    let home = if windows {
+        non_empty("USERPROFILE").or_else(|| non_empty("HOME"))
+    } else {
+        non_empty("HOME").or_else(|| non_empty("USERPROFILE"))
+    }?;
+    Some(
+        PathBuf::from(home)
+            .join(CLAUDE_CONFIG_DIR_NAME)
+            .join(CLAUDE_PROJECTS_DIR_NAME),
+    )
+}
+
+pub(crate) fn claude_projects_root() -> Option<PathBuf> {
+    claude_projects_root_from(|name| std::env::var_os(name), cfg!(windows))
+}
+
+pub(crate) fn claude_project_dir_for_workspace(workspace_path: &str) -> Option<PathBuf> {
+    let root = claude_projects_root()?;
     let encoded = encode_workspace_for_claude_projects(workspace_path)?;
-    Some(PathBuf::from(home).join(CLAUDE_HISTORY_ROOT).join(encoded))
+    let project_dir = root.join(&encoded);
+    if !project_dir.exists() {
+        let legacy_dir = legacy_encode_workspace_for_claude_projects(workspace_path)
+            .filter(|legacy| *legacy != encoded)
+            .map(|legacy| root.join(legacy))
+            .filter(|legacy_dir| legacy_dir.exists());
+        if let Some(legacy_dir) = legacy_dir {
+            return Some(legacy_dir);
+        }
+    }
+    Some(project_dir)
 }
 
 fn extract_text_from_content(content: &Value) -> Option<String> {
@@ -422,17 +845,17 @@ fn extract_text_from_content(content: &Value) -> Option<String> {
     }
 }
 
-fn extract_message_text(record: &Value) -> Option<String> {
+pub(crate) fn extract_message_text(record: &Value) -> Option<String> {
     let message = record.get("message")?;
     let content = message.get("content")?;
     extract_text_from_content(content)
 }
 
 #[derive(Debug, Clone)]
-struct HistoryMessage {
-    role: String,
-    text: String,
-    timestamp_ms: i64,
+pub(crate) struct HistoryMessage {
+    pub(crate) role: String,
+    pub(crate) text: String,
+    pub(crate) timestamp_ms: i64,
 }
 
 fn flush_history_turn(
@@ -461,6 +884,7 @@ fn flush_history_turn(
             id: format!("claude-history-user-{thread_id}-{turn_index}"),
             role: "user".to_string(),
             text,
+            pinned: false,
         });
     }
     if let Some((text, _)) = pending_assistant {
@@ -468,6 +892,7 @@ fn flush_history_turn(
             id: format!("claude-history-assistant-{thread_id}-{turn_index}"),
             role: "assistant".to_string(),
             text,
+            pinned: false,
         });
     }
     if items.is_empty() {
@@ -479,10 +904,20 @@ fn flush_history_turn(
         started_at,
         completed_at,
         items,
+        context_files: Vec::new(),
+        cli_version: None,
+        duration_ms: None,
+        snapshot: None,
+        plan: false,
+        suggestions: Vec::new(),
+        checklist: Vec::new(),
+        answered_by: None,
+        model_downgrade: None,
+        request: None,
     });
 }
 
-fn build_turns_from_history_messages(
+pub(crate) fn build_turns_from_history_messages(
     thread_id: &str,
     history_messages: &[HistoryMessage],
 ) -> Vec<ClaudeTurnRecord> {
@@ -654,11 +1089,24 @@ fn parse_claude_history_thread_file(
         created_at,
         updated_at,
         name: None,
+        tags: Vec::new(),
+        session_id: None,
+        session_needs_seed: false,
+        provider_session_id: None,
+        cwd_override: None,
+        generation: GenerationParams::default(),
+        worktree: None,
+        summary: None,
+        stats: Some(ThreadStats::from_turns(&turns, ProviderKind::Claude)),
+        last_read_at: None,
         turns,
     })
 }
 
-fn scan_claude_history_threads(workspace_path: &str) -> Vec<ClaudeThreadRecord> {
+fn scan_claude_history_threads(
+    workspace_path: &str,
+    redaction: &RedactionPipeline,
+) -> Vec<ClaudeThreadRecord> {
     let project_dir = match claude_project_dir_for_workspace(workspace_path) {
         Some(path) => path,
         None => return Vec::new(),
@@ -681,9 +1129,10 @@ fn scan_claude_history_threads(workspace_path: &str) -> Vec<ClaudeThreadRecord>
         ) {
             continue;
         }
-        let Some(thread) = parse_claude_history_thread_file(&path, workspace_path) else {
+        let Some(mut thread) = parse_claude_history_thread_file(&path, workspace_path) else {
             continue;
         };
+        redact_thread_in_place(&mut thread, redaction);
         let should_replace = by_id
             .get(&thread.id)
             .map(|existing| existing.updated_at < thread.updated_at)
@@ -698,19 +1147,22 @@ fn scan_claude_history_threads(workspace_path: &str) -> Vec<ClaudeThreadRecord>
     threads
 }
 
-async fn import_history_threads_for_workspace(
+pub(crate) async fn import_history_threads_for_workspace(
     claude_threads: &ClaudeThreadsStore,
     claude_threads_path: &Path,
     workspace_id: &str,
     workspace_path: &str,
+    redaction: &RedactionPipeline,
 ) -> Result<bool, String> {
     let archived_ids = read_archived_thread_ids_for_workspace(claude_threads_path, workspace_id);
     let workspace_path = workspace_path.to_string();
     let workspace_path_for_scan = workspace_path.clone();
-    let imported =
-        tokio::task::spawn_blocking(move || scan_claude_history_threads(&workspace_path_for_scan))
-            .await
-            .map_err(|error| format!("failed to scan Claude history: {error}"))?;
+    let redaction = redaction.clone();
+    let imported = tokio::task::spawn_blocking(move || {
+        scan_claude_history_threads(&workspace_path_for_scan, &redaction)
+    })
+    .await
+    .map_err(|error| format!("failed to scan Claude history: {error}"))?;
     if imported.is_empty() {
         return Ok(false);
     }
@@ -723,6 +1175,13 @@ async fn import_history_threads_for_workspace(
             if is_archived_thread_id(&archived_ids, &imported_thread.id) {
                 continue;
             }
+            if threads
+                .iter()
+                .any(|thread| thread.session_id.as_deref() == Some(imported_thread.id.as_str()))
+            {
+                // Recovered sessions belong to the thread that replaced its original session.
+                continue;
+            }
             imported_thread.cwd = workspace_path.clone();
             let legacy_id = format!("claude-thread-{}", imported_thread.id);
             if let Some(existing) = threads
@@ -741,6 +1200,7 @@ async fn import_history_threads_for_workspace(
                     }
                     if !imported_thread.turns.is_empty() {
                         existing.turns = imported_thread.turns.clone();
+                        existing.stats = imported_thread.stats.clone();
                     }
                     updated = true;
                 }
@@ -750,6 +1210,7 @@ async fn import_history_threads_for_workspace(
                 }
                 if existing.turns.is_empty() && !imported_thread.turns.is_empty() {
                     existing.turns = imported_thread.turns.clone();
+                    existing.stats = imported_thread.stats.clone();
                     updated = true;
                 }
                 if updated {
@@ -771,7 +1232,7 @@ async fn import_history_threads_for_workspace(
     Ok(changed)
 }
 
-async fn prune_placeholder_threads_for_workspace(
+pub(crate) async fn prune_placeholder_threads_for_workspace(
     claude_threads: &ClaudeThreadsStore,
     claude_threads_path: &Path,
     workspace_id: &str,
@@ -879,14 +1340,43 @@ pub(crate) async fn start_thread_core<E: EventSink>(
 ) -> Result<Value, String> {
     let (entry, _parent_entry, _settings) =
         ensure_workspace_provider_is_claude(workspaces, app_settings, &workspace_id).await?;
+    let thread_id = Uuid::new_v4().to_string();
+    let worktree = match (
+        entry.settings.thread_worktrees,
+        claude_threads_path.parent(),
+    ) {
+        (true, Some(data_dir)) => Some(
+            thread_worktree_core::create_thread_worktree(
+                &entry.path,
+                data_dir,
+                &workspace_id,
+                &thread_id,
+            )
+            .await?,
+        ),
+        _ => None,
+    };
     let timestamp = now_ms();
     let thread = ClaudeThreadRecord {
-        id: Uuid::new_v4().to_string(),
-        cwd: entry.path.clone(),
+        id: thread_id,
+        cwd: worktree
+            .as_ref()
+            .map(|worktree| worktree.path.clone())
+            .unwrap_or_else(|| entry.path.clone()),
         preview: String::new(),
         created_at: timestamp,
         updated_at: timestamp,
         name: None,
+        tags: Vec::new(),
+        session_id: None,
+        session_needs_seed: false,
+        provider_session_id: None,
+        cwd_override: None,
+        generation: GenerationParams::default(),
+        worktree,
+        summary: None,
+        stats: Some(ThreadStats::default()),
+        last_read_at: Some(timestamp),
         turns: Vec::new(),
     };
     {
@@ -903,6 +1393,14 @@ pub(crate) async fn start_thread_core<E: EventSink>(
             "thread": thread_summary(&thread),
         }),
     );
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread.id),
+        &[],
+    )
+    .await;
     Ok(json!({
         "result": {
             "thread": thread_summary(&thread),
@@ -910,10 +1408,15 @@ pub(crate) async fn start_thread_core<E: EventSink>(
     }))
 }
 
+/// A thread with one page of its turns, oldest first. Without `limit` every turn is sent;
+/// with it the newest `limit` turns come first and `nextCursor` loads the ones before them.
 pub(crate) async fn resume_thread_core(
     claude_threads: &ClaudeThreadsStore,
+    render_cache: &RenderCacheStore,
     workspace_id: String,
     thread_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
 ) -> Result<Value, String> {
     let store = claude_threads.lock().await;
     let threads = store
@@ -923,32 +1426,30 @@ pub(crate) async fn resume_thread_core(
         .iter()
         .find(|thread| thread.id == thread_id)
         .ok_or_else(|| "thread not found".to_string())?;
+    let (start, end) = resume_page_range(thread.turns.len(), cursor.as_deref(), limit);
+    let turns = render_cache.lock().await.render_page(
+        &workspace_id,
+        &thread_id,
+        start,
+        &thread.turns[start..end],
+    );
+    let next_cursor = (start > 0).then(|| start.to_string());
     Ok(json!({
         "result": {
-            "thread": thread_resume_payload(thread),
+            "thread": thread_resume_header(thread, turns),
+            "turnCount": thread.turns.len(),
+            "nextCursor": next_cursor,
         }
     }))
 }
 
+/// Reads the store only; CLI history is brought in by `thread_sync_core`.
 pub(crate) async fn list_threads_core(
     claude_threads: &ClaudeThreadsStore,
-    claude_threads_path: &Path,
     workspace_id: String,
-    workspace_path: String,
     cursor: Option<String>,
     limit: Option<u32>,
 ) -> Result<Value, String> {
-    let _ = import_history_threads_for_workspace(
-        claude_threads,
-        claude_threads_path,
-        &workspace_id,
-        &workspace_path,
-    )
-    .await;
-    let _ =
-        prune_placeholder_threads_for_workspace(claude_threads, claude_threads_path, &workspace_id)
-            .await;
-
     let offset = cursor
         .as_deref()
         .and_then(|value| value.parse::<usize>().ok())
@@ -960,6 +1461,10 @@ pub(crate) async fn list_threads_core(
         store.get(&workspace_id).cloned().unwrap_or_default()
     };
     threads.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    let unread_count = threads
+        .iter()
+        .filter(|thread| unread_turn_count(thread) > 0)
+        .count();
 
     let data = threads
         .iter()
@@ -977,12 +1482,32 @@ pub(crate) async fn list_threads_core(
         "result": {
             "data": data,
             "nextCursor": next_cursor,
+            "unreadCount": unread_count,
         }
     }))
 }
 
-fn build_prompt(text: &str, images: Option<Vec<String>>) -> String {
-    let mut prompt = text.trim().to_string();
+/// Whether the next turn opens the thread, so workspace memory should be attached.
+async fn thread_is_new(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+) -> bool {
+    claude_threads
+        .lock()
+        .await
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .is_some_and(|thread| thread.turns.is_empty())
+}
+
+fn build_prompt(
+    text: &str,
+    images: Option<Vec<String>>,
+    context_files: &[LoadedContextFile],
+) -> String {
+    let mut prompt = format_context_blocks(context_files);
+    prompt.push_str(text.trim());
     let image_lines = images
         .unwrap_or_default()
         .into_iter()
@@ -1003,577 +1528,3495 @@ fn build_prompt(text: &str, images: Option<Vec<String>>) -> String {
     prompt
 }
 
-fn prepare_command(bin: Option<String>, args: Option<String>, cwd: &PathBuf) -> Result<tokio::process::Command, String> {
-    let executable = bin
-        .filter(|value| !value.trim().is_empty())
-        .unwrap_or_else(|| "claude".to_string());
-    let mut command = tokio_command(executable);
-    command.current_dir(cwd);
-    let parsed = parse_cli_args(args.as_deref())?;
-    if !parsed.is_empty() {
-        command.args(parsed);
+/// Checks that a session transcript ends cleanly: every line must be a complete JSON record
+/// and at least one conversation message must be present for `--resume` to pick up.
+fn session_content_is_resumable(content: &str) -> bool {
+    if content.trim().is_empty() || !content.ends_with('\n') {
+        return false;
     }
-    Ok(command)
+    let mut has_message = false;
+    for line in content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            return false;
+        };
+        if matches!(
+            record.get("type").and_then(Value::as_str),
+            Some("user") | Some("assistant")
+        ) {
+            has_message = true;
+        }
+    }
+    has_message
 }
 
-async fn finalize_turn(
-    claude_threads: &ClaudeThreadsStore,
+fn claude_session_file_exists(workspace_path: &str, session_id: &str) -> bool {
+    claude_project_dir_for_workspace(workspace_path)
+        .map(|dir| dir.join(format!("{session_id}.jsonl")).is_file())
+        .unwrap_or(false)
+}
+
+fn is_transient_failure(stderr: &str) -> bool {
+    let lowered = stderr.to_ascii_lowercase();
+    TRANSIENT_FAILURE_MARKERS
+        .iter()
+        .any(|marker| lowered.contains(marker))
+}
+
+fn is_fallback_failure(exit_status: Option<i32>, output: &str) -> bool {
+    let lowered = output.to_ascii_lowercase();
+    exit_status == Some(COMMAND_NOT_FOUND_STATUS)
+        || FALLBACK_FAILURE_MARKERS
+            .iter()
+            .any(|marker| lowered.contains(marker))
+}
+
+/// Moves a failed turn on to the next provider fallback, announcing it with `turn/fallback`.
+fn next_fallback<E: EventSink>(
+    fallbacks: &mut impl Iterator<Item = providers::ClaudeFallback>,
+    event_sink: &E,
     workspace_id: &str,
     thread_id: &str,
     turn_id: &str,
-    assistant_item_id: &str,
-    assistant_text: &str,
+    reason: &str,
+) -> Option<providers::ClaudeFallback> {
+    let fallback = fallbacks.next()?;
+    emit(
+        event_sink,
+        workspace_id,
+        "turn/fallback",
+        json!({
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "profileId": fallback.profile_id,
+            "profileName": fallback.profile_name,
+            "reason": reason.trim(),
+        }),
+    );
+    Some(fallback)
+}
+
+/// Starts the Claude cooldown when a failed turn's output reports a rate limit or overload, so
+/// later turns wait it out instead of failing the same way.
+fn note_rate_limit<E: EventSink>(
+    rate_limits: &RateLimitsStore,
+    event_sink: &E,
+    workspace_id: &str,
+    output: &str,
 ) {
-    let mut store = claude_threads.lock().await;
-    let threads = match store.get_mut(workspace_id) {
-        Some(threads) => threads,
-        None => return,
-    };
-    let thread = match threads.iter_mut().find(|thread| thread.id == thread_id) {
-        Some(thread) => thread,
-        None => return,
+    let Some(hint) = parse_rate_limit_hint(output) else {
+        return;
     };
-    let updated_at = now_ms();
-    thread.updated_at = updated_at;
-    if !assistant_text.trim().is_empty() {
-        thread.preview = preview_from_text(assistant_text);
+    let now = now_ms();
+    let cooldown = rate_limits.note(&ProviderKind::Claude, &hint, now);
+    emit(
+        event_sink,
+        workspace_id,
+        "provider/rateLimitState",
+        rate_limit_state(&ProviderKind::Claude, Some(&cooldown), now),
+    );
+}
+
+/// Exponential backoff: the base delay doubles per attempt, capped at one minute.
+fn retry_delay(base_delay_ms: u64, attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis(base_delay_ms.saturating_mul(factor).min(RETRY_MAX_DELAY_MS))
+}
+
+/// Reports the failed attempt and waits out its backoff; false when the turn was canceled
+/// meanwhile.
+async fn wait_before_retry<E: EventSink>(
+    event_sink: &E,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    error: &str,
+    attempt: u32,
+    max_attempts: u32,
+    base_delay_ms: u64,
+    cancel_rx: &mut oneshot::Receiver<()>,
+) -> bool {
+    let delay = retry_delay(base_delay_ms, attempt);
+    emit(
+        event_sink,
+        workspace_id,
+        "error",
+        json!({
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "error": { "message": error.trim() },
+            "willRetry": true,
+            "attempt": attempt,
+            "maxAttempts": max_attempts,
+            "retryInMs": delay.as_millis() as u64,
+        }),
+    );
+    timeout(delay, cancel_rx).await.is_err()
+}
+
+/// Idle timeout of the control-protocol process an attempt runs on, or `None` to run it
+/// one-shot. Retries of ordinary turns run one-shot; turns that need permission prompts never
+/// do, since a one-shot CLI can't ask and would run denied tools.
+fn attempt_idle_timeout(
+    warm_idle_timeout: Option<Duration>,
+    permission_prompt: bool,
+    attempt: u32,
+) -> Option<Duration> {
+    warm_idle_timeout.filter(|_| attempt == 0 || permission_prompt)
+}
+
+/// Flags for a control-protocol process beyond the configured CLI args.
+fn pooled_process_args(permission_prompt: bool, file_rules: bool, plan: bool) -> Vec<String> {
+    let mut args = WARM_PROCESS_ARGS
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<_>>();
+    if permission_prompt {
+        args.extend(claude_pool_core::PERMISSION_PROMPT_ARGS.map(str::to_string));
     }
-    if let Some(turn) = thread.turns.iter_mut().find(|turn| turn.id == turn_id) {
-        turn.completed_at = Some(updated_at);
-        if let Some(item) = turn
-            .items
-            .iter_mut()
-            .find(|item| item.id == assistant_item_id && item.role == "assistant")
-        {
-            item.text = assistant_text.to_string();
-        }
+    if file_rules {
+        args.extend(file_access_core::ask_for_file_tools_args());
     }
+    if plan {
+        args.extend(PLAN_MODE_ARGS.iter().map(|arg| arg.to_string()));
+    }
+    args
 }
 
-pub(crate) async fn send_user_message_core<E: EventSink>(
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    app_settings: &Mutex<AppSettings>,
-    claude_threads: &ClaudeThreadsStore,
-    claude_turn_cancels: &ClaudeTurnCancelsStore,
-    claude_threads_path: &Path,
-    workspace_id: String,
-    thread_id: String,
-    text: String,
-    images: Option<Vec<String>>,
-    event_sink: E,
-) -> Result<Value, String> {
-    if text.trim().is_empty() && images.as_ref().map(|items| items.is_empty()).unwrap_or(true) {
-        return Err("empty user message".to_string());
+/// How an attempt launches the CLI. Real turns and dry runs both add the turn's flags through
+/// [`TurnLaunch::turn_command`], so a preview shows what a send would start.
+#[derive(Debug, Clone, Copy)]
+struct TurnLaunch {
+    /// The attempt runs on a control-protocol process and writes the prompt to its stdin.
+    pooled: bool,
+    permission_prompt: bool,
+    file_rules: bool,
+    plan: bool,
+}
+
+impl TurnLaunch {
+    /// Adds the turn's flags to a command from [`prepare_command`].
+    fn turn_command(
+        self,
+        mut command: tokio::process::Command,
+        prompt_arg: Option<&str>,
+        explicit_session_id: Option<&str>,
+        resume_session_id: Option<&str>,
+        session_created: bool,
+    ) -> tokio::process::Command {
+        if self.pooled {
+            command.args(pooled_process_args(
+                self.permission_prompt,
+                self.file_rules,
+                self.plan,
+            ));
+            command.args(session_args(
+                explicit_session_id,
+                resume_session_id,
+                session_created,
+            ));
+        } else {
+            command.args(turn_args(
+                prompt_arg,
+                explicit_session_id,
+                resume_session_id,
+                session_created,
+            ));
+            if self.plan {
+                command.args(PLAN_MODE_ARGS);
+            }
+        }
+        command
     }
+}
 
-    let (entry, parent_entry, settings) =
-        resolve_workspace_context(workspaces, app_settings, &workspace_id).await?;
-    let (claude_bin, claude_args) =
-        providers::resolve_claude_runtime_config(&entry, parent_entry.as_ref(), Some(&settings));
-    let prompt = build_prompt(&text, images);
+fn session_file_is_resumable(workspace_path: &str, session_id: &str) -> bool {
+    let Some(project_dir) = claude_project_dir_for_workspace(workspace_path) else {
+        return true;
+    };
+    match std::fs::read_to_string(project_dir.join(format!("{session_id}.jsonl"))) {
+        Ok(content) => session_content_is_resumable(&content),
+        Err(_) => false,
+    }
+}
 
-    let turn_id = format!("claude-turn-{}", Uuid::new_v4());
-    let user_item_id = format!("claude-user-{}", Uuid::new_v4());
-    let assistant_item_id = format!("claude-assistant-{}", Uuid::new_v4());
-    let started_at = now_ms();
-    let thread_has_turns = {
-        let mut store = claude_threads.lock().await;
-        let threads = store
-            .get_mut(&workspace_id)
-            .ok_or_else(|| "thread not found".to_string())?;
-        let thread = threads
-            .iter_mut()
-            .find(|thread| thread.id == thread_id)
-            .ok_or_else(|| "thread not found".to_string())?;
-        let had_turns = !thread.turns.is_empty();
-        thread.updated_at = started_at;
-        thread.turns.push(ClaudeTurnRecord {
-            id: turn_id.clone(),
-            started_at,
-            completed_at: None,
-            items: vec![
-                ClaudeMessageRecord {
-                    id: user_item_id.clone(),
-                    role: "user".to_string(),
-                    text: text.clone(),
-                },
-                ClaudeMessageRecord {
-                    id: assistant_item_id.clone(),
-                    role: "assistant".to_string(),
-                    text: String::new(),
-                },
-            ],
-        });
-        had_turns
+fn transcript_entry(item: &ClaudeMessageRecord) -> Option<String> {
+    let text = item.text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let label = if item.role == "user" {
+        "User"
+    } else {
+        "Assistant"
     };
-    persist_threads_store(claude_threads, claude_threads_path).await?;
+    Some(format!("{label}: {text}"))
+}
 
+/// The latest messages of a thread as `User:`/`Assistant:` lines, at most `max_chars` long.
+pub(crate) fn recent_transcript(turns: &[ClaudeTurnRecord], max_chars: usize) -> String {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    let items = turns.iter().rev().flat_map(|turn| turn.items.iter().rev());
+    for entry in items.filter_map(transcript_entry) {
+        if total + entry.len() > max_chars {
+            break;
+        }
+        total += entry.len();
+        entries.push(entry);
+    }
+    entries.reverse();
+    entries.join("\n\n")
+}
+
+/// Replays stored turns as plain text so a fresh session starts with the prior conversation.
+/// Pinned messages too old for the replayed window are listed ahead of it, oldest first.
+fn build_session_seed(turns: &[ClaudeTurnRecord]) -> String {
+    let transcript = recent_transcript(turns, SESSION_SEED_MAX_CHARS);
+    if transcript.is_empty() {
+        return String::new();
+    }
+    let mut pinned = Vec::new();
+    let mut total = 0;
+    let pinned_items = turns
+        .iter()
+        .flat_map(|turn| &turn.items)
+        .filter(|item| item.pinned);
+    for entry in pinned_items.filter_map(transcript_entry) {
+        if transcript.contains(&entry) {
+            continue;
+        }
+        if total + entry.len() > PINNED_SEED_MAX_CHARS {
+            break;
+        }
+        total += entry.len();
+        pinned.push(entry);
+    }
+    let pinned = if pinned.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "Messages pinned from earlier in the conversation:\n\n{}\n\n",
+            pinned.join("\n\n")
+        )
+    };
+    format!(
+        "The previous session was interrupted. {pinned}Conversation so far:\n\n{transcript}\n\n\
+         Continue from here.\n\n"
+    )
+}
+
+async fn recover_session_after_cancel<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    event_sink: &E,
+    workspace_id: &str,
+    workspace_path: &str,
+    thread_id: &str,
+    session_id: &str,
+) {
+    if session_file_is_resumable(workspace_path, session_id) {
+        return;
+    }
+    let replacement = Uuid::new_v4().to_string();
+    {
+        let mut store = claude_threads.lock().await;
+        let Some(thread) = store
+            .get_mut(workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        else {
+            return;
+        };
+        thread.session_id = Some(replacement.clone());
+        thread.session_needs_seed = true;
+    }
+    let _ = persist_threads_store(claude_threads, claude_threads_path).await;
     emit(
-        &event_sink,
-        &workspace_id,
-        "turn/started",
-        json!({
-            "threadId": thread_id,
-            "turn": { "id": turn_id, "threadId": thread_id },
-        }),
-    );
-    emit(
-        &event_sink,
-        &workspace_id,
-        "item/started",
-        json!({
-            "threadId": thread_id,
-            "item": {
-                "id": user_item_id,
-                "type": "userMessage",
-                "content": [{ "type": "text", "text": text }],
-            },
-        }),
-    );
-    emit(
-        &event_sink,
-        &workspace_id,
-        "item/completed",
+        event_sink,
+        workspace_id,
+        "thread/sessionRecovered",
         json!({
             "threadId": thread_id,
-            "item": {
-                "id": user_item_id,
-                "type": "userMessage",
-                "content": [{ "type": "text", "text": text }],
-            },
+            "previousSessionId": session_id,
+            "sessionId": replacement,
         }),
     );
-    emit(
-        &event_sink,
-        &workspace_id,
-        "item/started",
+}
+
+/// Tracks streaming throughput so periodic `turn/progress` events can report rate and stalls.
+struct StreamProgress {
+    started_at: Instant,
+    last_emit_at: Instant,
+    last_delta_at: Instant,
+    deltas: u64,
+    chars: u64,
+}
+
+impl StreamProgress {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started_at: now,
+            last_emit_at: now,
+            last_delta_at: now,
+            deltas: 0,
+            chars: 0,
+        }
+    }
+
+    fn record(&mut self, delta: &str) {
+        self.deltas += 1;
+        self.chars += delta.chars().count() as u64;
+        self.last_delta_at = Instant::now();
+    }
+
+    fn is_due(&self) -> bool {
+        self.last_emit_at.elapsed().as_millis() >= TURN_PROGRESS_INTERVAL_MS
+    }
+
+    fn snapshot(&mut self, thread_id: &str, turn_id: &str) -> Value {
+        self.last_emit_at = Instant::now();
+        let elapsed_ms = self.started_at.elapsed().as_millis();
+        let idle_ms = self.last_delta_at.elapsed().as_millis();
+        let elapsed_secs = (elapsed_ms as f64 / 1000.0).max(0.001);
         json!({
             "threadId": thread_id,
-            "item": {
-                "id": assistant_item_id,
-                "type": "agentMessage",
-                "text": "",
-            },
-        }),
-    );
+            "turnId": turn_id,
+            "deltas": self.deltas,
+            "chars": self.chars,
+            "elapsedMs": elapsed_ms as u64,
+            "idleMs": idle_ms as u64,
+            "deltasPerSecond": self.deltas as f64 / elapsed_secs,
+            "charsPerSecond": self.chars as f64 / elapsed_secs,
+            "stalled": idle_ms >= TURN_STALL_THRESHOLD_MS,
+        })
+    }
+}
 
-    let key = cancel_key(&workspace_id, &thread_id);
-    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
-    {
-        let mut cancels = claude_turn_cancels.lock().await;
-        if let Some(existing) = cancels.remove(&key) {
-            let _ = existing.send(());
+/// Emits `turn/heartbeat` on a fixed interval while a turn runs, whether or not output arrives.
+/// The timer stops when the heartbeat is finished or dropped.
+struct TurnHeartbeat {
+    started_at: Instant,
+    bytes_received: Arc<AtomicU64>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl TurnHeartbeat {
+    fn start<E: EventSink>(
+        interval: Option<Duration>,
+        event_sink: E,
+        workspace_id: String,
+        thread_id: String,
+        turn_id: String,
+    ) -> Self {
+        let started_at = Instant::now();
+        let bytes_received = Arc::new(AtomicU64::new(0));
+        let task = interval.map(|interval| {
+            let bytes_received = Arc::clone(&bytes_received);
+            tokio::spawn(async move {
+                let mut ticks =
+                    tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+                loop {
+                    ticks.tick().await;
+                    emit(
+                        &event_sink,
+                        &workspace_id,
+                        "turn/heartbeat",
+                        heartbeat_payload(
+                            &thread_id,
+                            &turn_id,
+                            started_at.elapsed().as_millis() as u64,
+                            bytes_received.load(Ordering::Relaxed),
+                        ),
+                    );
+                }
+            })
+        });
+        Self {
+            started_at,
+            bytes_received,
+            task,
         }
-        cancels.insert(key.clone(), cancel_tx);
     }
 
-    let workspace_id_for_task = workspace_id.clone();
-    let thread_id_for_task = thread_id.clone();
-    let turn_id_for_task = turn_id.clone();
-    let assistant_item_id_for_task = assistant_item_id.clone();
-    let explicit_session_id = if let Some(legacy) = legacy_prefixed_session_id(&thread_id) {
-        Some(legacy)
-    } else if !thread_has_turns && Uuid::parse_str(&thread_id).is_ok() {
-        Some(thread_id.clone())
+    fn record_bytes(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Stops the timer and returns the turn's duration in milliseconds.
+    fn finish(mut self) -> u64 {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.started_at.elapsed().as_millis() as u64
+    }
+}
+
+impl Drop for TurnHeartbeat {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+fn heartbeat_payload(
+    thread_id: &str,
+    turn_id: &str,
+    elapsed_ms: u64,
+    bytes_received: u64,
+) -> Value {
+    json!({
+        "threadId": thread_id,
+        "turnId": turn_id,
+        "elapsedMs": elapsed_ms,
+        "bytesReceived": bytes_received,
+    })
+}
+
+/// Returns `(explicit, resume)` session ids: an explicit id is passed with `--session-id` to
+/// create the session, a resume id with `--resume` to continue an existing one.
+fn resolve_session_ids(
+    thread_id: &str,
+    thread_has_turns: bool,
+    session_override: Option<&str>,
+    seeded: bool,
+) -> (Option<String>, Option<String>) {
+    let explicit = if seeded {
+        session_override.map(str::to_string)
+    } else if !thread_has_turns && Uuid::parse_str(thread_id).is_ok() {
+        Some(thread_id.to_string())
     } else {
         None
     };
-    let resume_session_id = if explicit_session_id.is_none() && !thread_id.trim().is_empty() {
-        Some(thread_id.clone())
+    let resume = if explicit.is_some() {
+        None
+    } else if let Some(session_id) = session_override {
+        Some(session_id.to_string())
+    } else if !thread_id.trim().is_empty() {
+        Some(thread_id.to_string())
     } else {
         None
     };
-    let cwd = PathBuf::from(entry.path.clone());
-    let claude_threads_clone = Arc::clone(claude_threads);
-    let claude_turn_cancels_clone = Arc::clone(claude_turn_cancels);
-    let claude_threads_path = claude_threads_path.to_path_buf();
-    let event_sink_clone = event_sink.clone();
-
-    tokio::spawn(async move {
-        let mut aggregated = String::new();
-        let mut command = match prepare_command(claude_bin, claude_args, &cwd) {
-            Ok(command) => command,
-            Err(error) => {
-                emit(
-                    &event_sink_clone,
-                    &workspace_id_for_task,
-                    "error",
-                    json!({
-                        "threadId": thread_id_for_task,
-                        "turnId": turn_id_for_task,
-                        "error": { "message": error },
-                        "willRetry": false,
-                    }),
-                );
-                let mut cancels = claude_turn_cancels_clone.lock().await;
-                cancels.remove(&key);
-                return;
-            }
-        };
-        command.arg("-p").arg(prompt);
-        // Force plain text output so UI rendering doesn't ingest structured/debug streams.
-        command.arg("--output-format").arg("text");
-        if let Some(session_id) = &explicit_session_id {
-            command.arg("--session-id").arg(session_id);
-        } else if let Some(session_id) = &resume_session_id {
-            command.arg("--resume").arg(session_id);
-        }
-        command.stdin(Stdio::null());
-        command.stdout(Stdio::piped());
-        command.stderr(Stdio::piped());
-
-        let mut child = match command.spawn() {
-            Ok(child) => child,
-            Err(error) => {
-                let message = format!("Failed to start Claude CLI: {error}");
-                emit(
-                    &event_sink_clone,
-                    &workspace_id_for_task,
-                    "error",
-                    json!({
-                        "threadId": thread_id_for_task,
-                        "turnId": turn_id_for_task,
-                        "error": { "message": message },
-                        "willRetry": false,
-                    }),
-                );
-                let mut cancels = claude_turn_cancels_clone.lock().await;
-                cancels.remove(&key);
-                return;
-            }
-        };
+    (explicit, resume)
+}
 
-        let stdout = match child.stdout.take() {
-            Some(stdout) => stdout,
-            None => {
-                let message = "Claude CLI missing stdout".to_string();
-                emit(
-                    &event_sink_clone,
-                    &workspace_id_for_task,
-                    "error",
-                    json!({
-                        "threadId": thread_id_for_task,
-                        "turnId": turn_id_for_task,
-                        "error": { "message": message },
-                        "willRetry": false,
-                    }),
-                );
-                let mut cancels = claude_turn_cancels_clone.lock().await;
-                cancels.remove(&key);
-                return;
-            }
-        };
-        let stderr = child.stderr.take();
-        let stderr_handle = tokio::spawn(async move {
-            let mut output = String::new();
-            if let Some(stderr) = stderr {
-                let mut lines = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    if !output.is_empty() {
-                        output.push('\n');
-                    }
-                    output.push_str(&line);
-                }
-            }
-            output
-        });
+/// A thread worktree replaces the workspace root; a cwd override only applies without one.
+pub(crate) fn thread_cwd_override(thread: &ClaudeThreadRecord) -> Option<String> {
+    thread
+        .worktree
+        .as_ref()
+        .map(|worktree| worktree.path.clone())
+        .or_else(|| thread.cwd_override.clone())
+}
 
-        let mut lines = BufReader::new(stdout).lines();
-        let mut pending_server_token: Option<String> = None;
-        let mut canceled = false;
-        let mut read_error: Option<String> = None;
-        loop {
-            match cancel_rx.try_recv() {
-                Ok(_) | Err(TryRecvError::Closed) => {
-                    canceled = true;
-                    let _ = child.kill().await;
-                    break;
-                }
-                Err(TryRecvError::Empty) => {}
-            }
+fn resolve_turn_cwd(cwd_override: Option<String>, workspace_path: &str) -> PathBuf {
+    cwd_override
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .unwrap_or_else(|| PathBuf::from(workspace_path))
+}
 
-            match timeout(Duration::from_millis(120), lines.next_line()).await {
-                Ok(Ok(Some(line))) => {
-                    let normalized_line = strip_ansi_sequences(&line).trim().to_string();
-                    if normalized_line.is_empty() {
-                        continue;
-                    }
+fn session_args(
+    explicit_session_id: Option<&str>,
+    resume_session_id: Option<&str>,
+    explicit_session_created: bool,
+) -> Vec<String> {
+    match (explicit_session_id, resume_session_id) {
+        (Some(session_id), _) if explicit_session_created => {
+            vec!["--resume".to_string(), session_id.to_string()]
+        }
+        (Some(session_id), _) => vec!["--session-id".to_string(), session_id.to_string()],
+        (None, Some(session_id)) => vec!["--resume".to_string(), session_id.to_string()],
+        (None, None) => Vec::new(),
+    }
+}
 
-                    if let Some(server_token) = pending_server_token.take() {
-                        let candidate = format!("{server_token}\n{normalized_line}");
-                        if !is_debug_jsonrpc_message(&candidate) {
-                            let pending_delta = if aggregated.is_empty() {
-                                server_token
-                            } else {
-                                format!("\n{server_token}")
-                            };
-                            aggregated.push_str(&pending_delta);
-                            emit(
-                                &event_sink_clone,
-                                &workspace_id_for_task,
-                                "item/agentMessage/delta",
-                                json!({
-                                    "threadId": thread_id_for_task,
-                                    "itemId": assistant_item_id_for_task,
-                                    "delta": pending_delta,
-                                }),
-                            );
-                        } else {
-                            continue;
-                        }
-                    }
+/// Prompts at least this large are piped through stdin instead of argv, which has OS size
+/// limits and is visible to anyone listing processes.
+const STDIN_PROMPT_THRESHOLD_BYTES: usize = 32 * 1024;
 
-                    if is_server_token(&normalized_line) {
-                        pending_server_token = Some(normalized_line);
-                        continue;
-                    }
+/// `Some(prompt)` when the prompt fits on the command line, `None` when it goes through stdin.
+fn prompt_arg(prompt: &str) -> Option<&str> {
+    (prompt.len() < STDIN_PROMPT_THRESHOLD_BYTES).then_some(prompt)
+}
 
-                    if is_debug_jsonrpc_line(&normalized_line) {
-                        continue;
-                    }
+/// Arguments appended after the configured CLI args for a single turn. Without a prompt
+/// argument, `claude -p` reads the prompt from stdin.
+fn turn_args(
+    prompt: Option<&str>,
+    explicit_session_id: Option<&str>,
+    resume_session_id: Option<&str>,
+    explicit_session_created: bool,
+) -> Vec<String> {
+    let mut args = vec!["-p".to_string()];
+    args.extend(prompt.map(str::to_string));
+    // Structured output reports the session the CLI actually used; only its text deltas reach
+    // the thread.
+    args.extend(
+        [
+            "--output-format",
+            "stream-json",
+            "--verbose",
+            "--include-partial-messages",
+        ]
+        .map(str::to_string),
+    );
+    args.extend(session_args(
+        explicit_session_id,
+        resume_session_id,
+        explicit_session_created,
+    ));
+    args
+}
 
-                    let delta = if aggregated.is_empty() {
-                        normalized_line
-                    } else {
-                        format!("\n{normalized_line}")
-                    };
-                    aggregated.push_str(&delta);
-                    emit(
-                        &event_sink_clone,
-                        &workspace_id_for_task,
-                        "item/agentMessage/delta",
-                        json!({
-                            "threadId": thread_id_for_task,
-                            "itemId": assistant_item_id_for_task,
-                            "delta": delta,
-                        }),
-                    );
-                }
-                Ok(Ok(None)) => break,
-                Ok(Err(error)) => {
-                    read_error = Some(format!("Failed reading Claude output: {error}"));
-                    break;
-                }
-                Err(_) => continue,
+const DRY_RUN_ENV_NAMES: &[&str] = &["PATH", "HOME", "SHELL"];
+const DRY_RUN_ENV_PREFIXES: &[&str] = &["ANTHROPIC_", "CLAUDE_"];
+const DRY_RUN_SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+
+/// Picks the environment variables that influence the Claude CLI, masking credentials.
+fn dry_run_env(vars: impl Iterator<Item = (String, String)>) -> serde_json::Map<String, Value> {
+    let mut env = serde_json::Map::new();
+    for (name, value) in vars {
+        let relevant = DRY_RUN_ENV_NAMES.contains(&name.as_str())
+            || DRY_RUN_ENV_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+        if !relevant {
+            continue;
+        }
+        let value = if is_secret_env_name(&name) {
+            "<redacted>".to_string()
+        } else {
+            value
+        };
+        env.insert(name, Value::String(value));
+    }
+    env
+}
+
+fn is_secret_env_name(name: &str) -> bool {
+    DRY_RUN_SECRET_MARKERS
+        .iter()
+        .any(|marker| name.to_ascii_uppercase().contains(marker))
+}
+
+/// Describes the command a send would run, without recording a turn or spawning the CLI.
+fn describe_dry_run(
+    claude_bin: Option<String>,
+    claude_args: Option<String>,
+    claude_env: &[(String, String)],
+    workspace_path: &str,
+    thread_id: &str,
+    prompt: &str,
+    snapshot: (
+        bool,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        GenerationParams,
+    ),
+    context_files: &[LoadedContextFile],
+    sandbox: &SandboxSettings,
+    instructions: Option<&str>,
+    launch: TurnLaunch,
+    launch_shell: Option<&str>,
+) -> Result<Value, String> {
+    let (thread_has_turns, session_override, session_seed, cwd_override, generation) = snapshot;
+    let mut prompt = prompt.to_string();
+    if let Some(seed) = &session_seed {
+        prompt.insert_str(0, seed);
+    }
+    let (explicit_session_id, resume_session_id) = resolve_session_ids(
+        thread_id,
+        thread_has_turns,
+        session_override.as_deref(),
+        session_seed.is_some(),
+    );
+    let cwd = resolve_turn_cwd(cwd_override, workspace_path);
+    let prompt_via_argv = prompt_arg(&prompt).filter(|_| !launch.pooled);
+    let prompt_delivery = if prompt_via_argv.is_some() {
+        "argv"
+    } else {
+        "stdin"
+    };
+    let command = launch.turn_command(
+        prepare_command(
+            claude_bin,
+            claude_args,
+            claude_env,
+            &cwd,
+            &generation,
+            sandbox,
+            instructions,
+        )?,
+        prompt_via_argv,
+        explicit_session_id.as_deref(),
+        resume_session_id.as_deref(),
+        false,
+    );
+    let command = through_shell(command, launch_shell, sandbox.restrict_env);
+    let std_command = command.as_std();
+    let args = std_command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    let mut env = if sandbox.restrict_env {
+        serde_json::Map::new()
+    } else {
+        dry_run_env(std::env::vars())
+    };
+    for (name, value) in std_command.get_envs() {
+        let name = name.to_string_lossy().to_string();
+        match value {
+            Some(_) if is_secret_env_name(&name) => {
+                env.insert(name, Value::String("<redacted>".to_string()));
+            }
+            Some(value) => {
+                env.insert(name, Value::String(value.to_string_lossy().to_string()));
+            }
+            None => {
+                env.remove(&name);
             }
         }
-        if let Some(server_token) = pending_server_token.take() {
-            let delta = if aggregated.is_empty() {
-                server_token
-            } else {
-                format!("\n{server_token}")
-            };
-            aggregated.push_str(&delta);
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "item/agentMessage/delta",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "itemId": assistant_item_id_for_task,
-                    "delta": delta,
-                }),
-            );
+    }
+    Ok(json!({
+        "result": {
+            "dryRun": true,
+            "threadId": thread_id,
+            "program": std_command.get_program().to_string_lossy(),
+            "args": args,
+            "cwd": cwd.to_string_lossy(),
+            "env": env,
+            "promptDelivery": prompt_delivery,
+            "pooled": launch.pooled,
+            "prompt": prompt,
+            "sessionFlags": session_args(
+                explicit_session_id.as_deref(),
+                resume_session_id.as_deref(),
+                false,
+            ),
+            "sessionSeeded": session_seed.is_some(),
+            "generation": generation,
+            "unsupportedGeneration": unsupported_generation_params(&generation),
+            "contextFiles": context_files
+                .iter()
+                .map(|file| file.record.clone())
+                .collect::<Vec<_>>(),
         }
+    }))
+}
 
-        let status = child.wait().await.ok();
-        let stderr_output = stderr_handle.await.unwrap_or_default();
-        finalize_turn(
-            &claude_threads_clone,
-            &workspace_id_for_task,
-            &thread_id_for_task,
-            &turn_id_for_task,
-            &assistant_item_id_for_task,
-            &aggregated,
-        )
-        .await;
-        let _ = persist_threads_store(&claude_threads_clone, &claude_threads_path).await;
+fn claude_executable(bin: Option<&String>) -> String {
+    bin.filter(|value| !value.trim().is_empty())
+        .cloned()
+        .unwrap_or_else(|| "claude".to_string())
+}
 
-        if canceled {
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "item/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "item": {
-                        "id": assistant_item_id_for_task,
-                        "type": "agentMessage",
-                        "text": aggregated,
-                    },
-                }),
-            );
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "turn/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turn": { "id": turn_id_for_task, "threadId": thread_id_for_task },
-                }),
-            );
-            let mut cancels = claude_turn_cancels_clone.lock().await;
-            cancels.remove(&key);
-            return;
+/// Environment variable the Claude CLI reads as its output token limit.
+const CLAUDE_MAX_OUTPUT_TOKENS_ENV: &str = "CLAUDE_CODE_MAX_OUTPUT_TOKENS";
+
+/// CLI flags for the thread's generation settings, placed after the configured args so the
+/// thread's model wins over a `--model` in `claudeArgs`.
+fn generation_args(generation: &GenerationParams) -> Vec<String> {
+    generation
+        .model
+        .as_deref()
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+        .map(|model| vec!["--model".to_string(), model.to_string()])
+        .unwrap_or_default()
+}
+
+/// Settings the Claude CLI cannot apply; they stay on the thread but do not affect turns.
+fn unsupported_generation_params(generation: &GenerationParams) -> Vec<&'static str> {
+    let mut unsupported = Vec::new();
+    if generation.temperature.is_some() {
+        unsupported.push("temperature");
+    }
+    unsupported
+}
+
+fn prepare_command(
+    bin: Option<String>,
+    args: Option<String>,
+    env: &[(String, String)],
+    cwd: &PathBuf,
+    generation: &GenerationParams,
+    sandbox: &SandboxSettings,
+    instructions: Option<&str>,
+) -> Result<tokio::process::Command, String> {
+    let mut command = sandbox_core::sandboxed_command(claude_executable(bin.as_ref()), sandbox)?;
+    command.current_dir(cwd);
+    sandbox_core::restrict_env(&mut command, sandbox);
+    command.envs(env.iter().map(|(name, value)| (name, value)));
+    let parsed = parse_cli_args(args.as_deref())?;
+    if !parsed.is_empty() {
+        command.args(parsed);
+    }
+    command.args(sandbox_core::claude_sandbox_args(sandbox));
+    command.args(generation_args(generation));
+    if let Some(max_tokens) = generation.max_tokens {
+        command.env(CLAUDE_MAX_OUTPUT_TOKENS_ENV, max_tokens.to_string());
+    }
+    // The CLI does not keep the system prompt with the session, so it goes on every turn.
+    if let Some(instructions) = instructions {
+        command.args(["--append-system-prompt", instructions]);
+    }
+    Ok(command)
+}
+
+/// Runs a one-off prompt through the workspace's Claude CLI and returns the reply. The CLI
+/// runs outside the workspace so its throwaway session never shows up as an imported thread.
+pub(crate) async fn run_claude_prompt(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    settings: &AppSettings,
+    prompt: &str,
+    model: Option<&str>,
+    limit: Duration,
+) -> Result<String, String> {
+    let (claude_bin, claude_args, claude_env) =
+        providers::resolve_claude_runtime_config(entry, parent_entry, Some(settings));
+    let sandbox = sandbox_core::resolve_sandbox(entry, parent_entry);
+    let mut command = prepare_command(
+        claude_bin,
+        claude_args,
+        &claude_env,
+        &std::env::temp_dir(),
+        &GenerationParams {
+            model: model.map(str::to_string),
+            ..GenerationParams::default()
+        },
+        &sandbox,
+        None,
+    )?;
+    command.args(["-p", "--output-format", "text"]);
+    let mut command = through_shell(
+        command,
+        settings.launch_shell.as_deref(),
+        sandbox.restrict_env,
+    );
+    command.stdin(Stdio::piped());
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+    command.kill_on_drop(true);
+    let mut child = command
+        .spawn()
+        .map_err(|error| format!("Failed to start Claude: {error}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(prompt.as_bytes())
+            .await
+            .map_err(|error| format!("Failed to send the prompt to Claude: {error}"))?;
+    }
+    let output = timeout(limit, child.wait_with_output())
+        .await
+        .map_err(|_| "Claude did not answer in time.".to_string())?
+        .map_err(|error| format!("Failed to read Claude output: {error}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Claude exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Streams part of the assistant reply, up to the turn's output cap. The delta that reaches
+/// the cap emits `turn/outputTruncated`; later ones are only kept for the raw log.
+fn emit_capped_delta<E: EventSink>(
+    event_sink: &E,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    item_id: &str,
+    output_cap: &mut TurnOutputCap,
+    delta: &str,
+) {
+    let (delta, reached_cap) = output_cap.admit(delta);
+    if !delta.is_empty() {
+        emit(
+            event_sink,
+            workspace_id,
+            "item/agentMessage/delta",
+            json!({ "threadId": thread_id, "itemId": item_id, "delta": delta }),
+        );
+    }
+    if reached_cap {
+        emit(
+            event_sink,
+            workspace_id,
+            "turn/outputTruncated",
+            json!({
+                "threadId": thread_id,
+                "turnId": turn_id,
+                "itemId": item_id,
+                "limitBytes": output_cap.limit(),
+            }),
+        );
+    }
+}
+
+/// Keeps the session id the CLI reported so the next turn resumes exactly that session.
+async fn record_turn_answered_by(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    profile_id: String,
+) {
+    let mut store = claude_threads.lock().await;
+    let turn = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        .and_then(|thread| thread.turns.iter_mut().find(|turn| turn.id == turn_id));
+    if let Some(turn) = turn {
+        turn.answered_by = Some(profile_id);
+    }
+}
+
+async fn record_provider_session(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    session_id: &str,
+) {
+    let mut store = claude_threads.lock().await;
+    let thread = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id));
+    if let Some(thread) = thread {
+        thread.provider_session_id = Some(session_id.to_string());
+    }
+}
+
+async fn finalize_turn(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    assistant_item_id: &str,
+    assistant_text: &str,
+    duration_ms: u64,
+    failed: bool,
+) {
+    let mut store = claude_threads.lock().await;
+    let threads = match store.get_mut(workspace_id) {
+        Some(threads) => threads,
+        None => return,
+    };
+    let thread = match threads.iter_mut().find(|thread| thread.id == thread_id) {
+        Some(thread) => thread,
+        None => return,
+    };
+    let updated_at = now_ms();
+    thread.updated_at = updated_at;
+    if !assistant_text.trim().is_empty() {
+        thread.preview = preview_from_text(assistant_text);
+    }
+    thread_stats_core::record_finished_turn(thread, assistant_text, failed);
+    if let Some(turn) = thread.turns.iter_mut().find(|turn| turn.id == turn_id) {
+        turn.completed_at = Some(updated_at);
+        turn.duration_ms = Some(duration_ms);
+        if let Some(item) = turn
+            .items
+            .iter_mut()
+            .find(|item| item.id == assistant_item_id && item.role == "assistant")
+        {
+            item.text = assistant_text.to_string();
         }
+    }
+}
 
-        if let Some(error) = read_error {
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "error",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turnId": turn_id_for_task,
-                    "error": { "message": error },
-                    "willRetry": false,
-                }),
-            );
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "turn/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turn": { "id": turn_id_for_task, "threadId": thread_id_for_task },
-                }),
+/// JSON snapshot of one finished turn, handed to `onTurnCompleted` hooks.
+async fn turn_transcript(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    status: &str,
+) -> Value {
+    let store = claude_threads.lock().await;
+    let thread = store
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id));
+    let turn = thread.and_then(|thread| thread.turns.iter().find(|turn| turn.id == turn_id));
+    json!({
+        "workspaceId": workspace_id,
+        "threadId": thread_id,
+        "threadName": thread.and_then(|thread| thread.name.clone()),
+        "turnId": turn_id,
+        "status": status,
+        "startedAt": turn.map(|turn| turn.started_at),
+        "completedAt": turn.and_then(|turn| turn.completed_at),
+        "items": turn.map(|turn| turn.items.clone()).unwrap_or_default(),
+    })
+}
+
+/// Appends the turn's provider process to the audit log. A failed write is logged rather than
+/// failing a turn that already ran.
+fn audit_turn_spawn(
+    claude_threads_path: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    plan: bool,
+    request: &TurnRequest,
+    started_at: i64,
+    error: Option<String>,
+) {
+    let Some(data_dir) = claude_threads_path.parent() else {
+        return;
+    };
+    let action = if plan { "planMessage" } else { "sendMessage" };
+    let entry = AuditEntry {
+        error,
+        ..AuditEntry::for_turn(
+            workspace_id,
+            thread_id,
+            turn_id,
+            action,
+            request,
+            started_at,
+            now_ms(),
+        )
+    };
+    if let Err(error) = audit_core::append_audit_entry(data_dir, &entry) {
+        eprintln!("{error}");
+    }
+}
+
+/// The CLI a turn runs, and the fallback profiles left to try when it fails.
+struct ClaudeRuntime {
+    bin: Option<String>,
+    args: Option<String>,
+    env: Vec<(String, String)>,
+    fallbacks: std::vec::IntoIter<providers::ClaudeFallback>,
+    /// The fallback profile that took over the turn, if any.
+    answered_by: Option<String>,
+}
+
+/// Output the attempts of a turn have streamed so far.
+struct TurnOutput {
+    aggregated: String,
+    output_cap: TurnOutputCap,
+    provider_session_id: Option<String>,
+    checklist: Option<Vec<ChecklistStep>>,
+}
+
+/// How a turn's attempts ended, and what the audit log and thread record keep of them.
+#[derive(Default)]
+struct TurnAttempts {
+    success: bool,
+    canceled: bool,
+    stderr_output: String,
+    read_error: Option<String>,
+    /// Stays `None` for turns run on a pooled process, which does not exit with the turn.
+    exit_status: Option<i32>,
+    request: Option<TurnRequest>,
+    /// Set when the CLI could not be started; the audit entry records it.
+    spawn_error: Option<String>,
+}
+
+/// What the attempt loop does after a failed attempt.
+enum Recovery {
+    /// Another attempt runs, on a fallback profile or after the retry backoff.
+    Retry,
+    Canceled,
+    GiveUp,
+}
+
+/// How reading a one-shot CLI's stdout ended.
+enum OutputEnd {
+    Eof,
+    Canceled,
+    ReadError(String),
+}
+
+/// A recorded turn, run in the background once `send_user_message_core` has returned.
+struct ClaudeTurnTask<E: EventSink> {
+    event_sink: E,
+    claude_threads: ClaudeThreadsStore,
+    claude_threads_path: PathBuf,
+    claude_turn_cancels: ClaudeTurnCancelsStore,
+    claude_processes: ClaudeProcessPool,
+    claude_approvals: ClaudeApprovalsStore,
+    turn_slots: TurnSlotsStore,
+    rate_limits: RateLimitsStore,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    assistant_item_id: String,
+    started_at: i64,
+    cwd: PathBuf,
+    cwd_string: String,
+    prompt: String,
+    generation: GenerationParams,
+    sandbox: SandboxSettings,
+    instructions: Option<String>,
+    file_access: Option<FileAccessGuard>,
+    output_filters: OutputFilterPipeline,
+    launch_shell: Option<String>,
+    explicit_session_id: Option<String>,
+    resume_session_id: Option<String>,
+    plan: bool,
+    permission_prompt: bool,
+    command_approvals: bool,
+    sanitize_output: bool,
+    warm_idle_timeout: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    max_concurrent_turns: usize,
+    max_turn_output_bytes: u64,
+    retry_max_attempts: u32,
+    retry_base_delay_ms: u64,
+    secrets: ResolvedSecrets,
+    redaction: RedactionPipeline,
+    redaction_raw_log: bool,
+    turn_hook: Option<String>,
+    artifact_paths: Vec<String>,
+    suggestion_context: Option<(WorkspaceEntry, Option<WorkspaceEntry>, AppSettings)>,
+    settings_changes: broadcast::Receiver<SettingsChange>,
+    runtime_entries: (WorkspaceEntry, Option<WorkspaceEntry>),
+}
+
+impl<E: EventSink> ClaudeTurnTask<E> {
+    async fn run(mut self, mut runtime: ClaudeRuntime, mut cancel_rx: oneshot::Receiver<()>) {
+        let slot = acquire_turn_slot(
+            &self.turn_slots,
+            self.max_concurrent_turns,
+            &mut cancel_rx,
+            |position| {
+                emit(
+                    &self.event_sink,
+                    &self.workspace_id,
+                    "turn/queued",
+                    json!({
+                        "threadId": self.thread_id,
+                        "turnId": self.turn_id,
+                        "position": position,
+                    }),
+                );
+            },
+        )
+        .await;
+        // A turn canceled while queued never started a process, so there is no session to check.
+        let started = slot.is_some();
+        let heartbeat = TurnHeartbeat::start(
+            self.heartbeat_interval,
+            self.event_sink.clone(),
+            self.workspace_id.clone(),
+            self.thread_id.clone(),
+            self.turn_id.clone(),
+        );
+        if let Some(settings) =
+            settings_core::latest_settings(&mut self.settings_changes).as_deref()
+        {
+            let (entry, parent_entry) = &self.runtime_entries;
+            (runtime.bin, runtime.args, runtime.env) = providers::resolve_claude_runtime_config(
+                entry,
+                parent_entry.as_ref(),
+                Some(settings),
             );
-            let mut cancels = claude_turn_cancels_clone.lock().await;
-            cancels.remove(&key);
-            return;
+            runtime.fallbacks =
+                providers::resolve_claude_fallbacks(entry, parent_entry.as_ref(), Some(settings))
+                    .into_iter();
+        }
+        let mut output = TurnOutput {
+            aggregated: String::new(),
+            output_cap: TurnOutputCap::new(self.max_turn_output_bytes),
+            provider_session_id: None,
+            checklist: None,
+        };
+        let attempts = if started {
+            self.run_attempts(&mut runtime, &mut output, &heartbeat, &mut cancel_rx)
+                .await
+        } else {
+            TurnAttempts {
+                canceled: true,
+                ..TurnAttempts::default()
+            }
+        };
+        drop(slot);
+        let duration_ms = heartbeat.finish();
+        self.finish(attempts, output, runtime.answered_by, duration_ms, started)
+            .await;
+    }
+
+    fn launch(&self, pooled: bool) -> TurnLaunch {
+        TurnLaunch {
+            pooled,
+            permission_prompt: self.permission_prompt,
+            file_rules: self.file_access.is_some(),
+            plan: self.plan,
         }
+    }
 
-        let success = status.map(|value| value.success()).unwrap_or(false);
-        if success {
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "item/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "item": {
-                        "id": assistant_item_id_for_task,
-                        "type": "agentMessage",
-                        "text": aggregated,
-                    },
-                }),
+    /// Streams a piece of the reply to the UI and keeps it for the stored turn.
+    fn stream_delta(&self, output: &mut TurnOutput, progress: &mut StreamProgress, delta: &str) {
+        output.aggregated.push_str(delta);
+        progress.record(delta);
+        emit_capped_delta(
+            &self.event_sink,
+            &self.workspace_id,
+            &self.thread_id,
+            &self.turn_id,
+            &self.assistant_item_id,
+            &mut output.output_cap,
+            delta,
+        );
+    }
+
+    fn emit_progress(&self, progress: &mut StreamProgress) {
+        emit(
+            &self.event_sink,
+            &self.workspace_id,
+            "turn/progress",
+            progress.snapshot(&self.thread_id, &self.turn_id),
+        );
+    }
+
+    fn emit_checklist(&self, steps: &[ChecklistStep]) {
+        emit(
+            &self.event_sink,
+            &self.workspace_id,
+            "item/plan",
+            checklist_event(&self.thread_id, &self.turn_id, steps),
+        );
+    }
+
+    /// Runs attempts until one ends the turn, moving to fallbacks and retrying as configured.
+    /// Every exit returns here so the turn is always finalized.
+    async fn run_attempts(
+        &self,
+        runtime: &mut ClaudeRuntime,
+        output: &mut TurnOutput,
+        heartbeat: &TurnHeartbeat,
+        cancel_rx: &mut oneshot::Receiver<()>,
+    ) -> TurnAttempts {
+        let mut attempts = TurnAttempts::default();
+        let mut attempt: u32 = 0;
+        loop {
+            if let Some(cooldown) = self.rate_limits.active(&ProviderKind::Claude, now_ms()) {
+                let now = now_ms();
+                emit(
+                    &self.event_sink,
+                    &self.workspace_id,
+                    "provider/rateLimitState",
+                    rate_limit_state(&ProviderKind::Claude, Some(&cooldown), now),
+                );
+                let wait = Duration::from_millis((cooldown.until - now).max(0) as u64);
+                if timeout(wait, &mut *cancel_rx).await.is_ok() {
+                    attempts.canceled = true;
+                    return attempts;
+                }
+                emit(
+                    &self.event_sink,
+                    &self.workspace_id,
+                    "provider/rateLimitState",
+                    rate_limit_state(&ProviderKind::Claude, None, now_ms()),
+                );
+            }
+            if let Some(idle_timeout) =
+                attempt_idle_timeout(self.warm_idle_timeout, self.permission_prompt, attempt)
+            {
+                let pooled = self
+                    .run_pooled_attempt(
+                        runtime,
+                        idle_timeout,
+                        output,
+                        heartbeat,
+                        cancel_rx,
+                        &mut attempts.request,
+                    )
+                    .await;
+                match pooled {
+                    PooledTurn::Completed {
+                        success,
+                        error,
+                        session_id,
+                    } => {
+                        if let (false, Some(error)) = (success, error.as_deref()) {
+                            note_rate_limit(
+                                &self.rate_limits,
+                                &self.event_sink,
+                                &self.workspace_id,
+                                error,
+                            );
+                            let silent = output.aggregated.is_empty();
+                            let fallback = silent && is_fallback_failure(None, error);
+                            let recovery = self
+                                .recover_failed_attempt(
+                                    runtime,
+                                    &mut attempt,
+                                    error,
+                                    fallback,
+                                    silent,
+                                    cancel_rx,
+                                )
+                                .await;
+                            match recovery {
+                                Recovery::Retry => continue,
+                                Recovery::Canceled => {
+                                    attempts.canceled = true;
+                                    attempts.stderr_output = error.to_string();
+                                    return attempts;
+                                }
+                                Recovery::GiveUp => {}
+                            }
+                        }
+                        output.provider_session_id = session_id;
+                        attempts.success = success;
+                        attempts.stderr_output = error.unwrap_or_default();
+                        return attempts;
+                    }
+                    PooledTurn::Canceled => {
+                        attempts.canceled = true;
+                        return attempts;
+                    }
+                    // A one-shot CLI can't ask for approval, so it would run denied tools.
+                    PooledTurn::Unavailable(reason) if self.permission_prompt => {
+                        attempts.stderr_output = format!("{GUARDED_PROCESS_UNAVAILABLE} {reason}");
+                        return attempts;
+                    }
+                    PooledTurn::Unavailable(reason) => {
+                        eprintln!("Running Claude one-shot instead of pooled: {reason}");
+                    }
+                }
+            }
+            let command = match prepare_command(
+                runtime.bin.clone(),
+                runtime.args.clone(),
+                &runtime.env,
+                &self.cwd,
+                &self.generation,
+                &self.sandbox,
+                self.instructions.as_deref(),
+            ) {
+                Ok(command) => command,
+                Err(error) => {
+                    attempts.stderr_output = error;
+                    return attempts;
+                }
+            };
+            // A failed first attempt may already have created the session; resume it instead.
+            let explicit_session_created = attempt > 0
+                && self
+                    .explicit_session_id
+                    .as_deref()
+                    .is_some_and(|session_id| {
+                        claude_session_file_exists(&self.cwd_string, session_id)
+                    });
+            let prompt_via_argv = prompt_arg(&self.prompt);
+            let command = self.launch(false).turn_command(
+                command,
+                prompt_via_argv,
+                self.explicit_session_id.as_deref(),
+                self.resume_session_id.as_deref(),
+                explicit_session_created,
             );
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "turn/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turn": { "id": turn_id_for_task, "threadId": thread_id_for_task },
-                }),
+            attempts.request = Some(TurnRequest {
+                attempts: attempt + 1,
+                shell: self.launch_shell.clone(),
+                ..TurnRequest::describe(&command, prompt_via_argv, self.sandbox.restrict_env, false)
+            });
+            let mut command = through_shell(
+                command,
+                self.launch_shell.as_deref(),
+                self.sandbox.restrict_env,
             );
+            command.stdin(if prompt_via_argv.is_some() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            });
+            command.stdout(Stdio::piped());
+            command.stderr(Stdio::piped());
+
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(error) => {
+                    let message = format!("Failed to start Claude CLI: {error}");
+                    let recovery = self
+                        .recover_failed_attempt(
+                            runtime,
+                            &mut attempt,
+                            &message,
+                            true,
+                            false,
+                            cancel_rx,
+                        )
+                        .await;
+                    if let Recovery::Retry = recovery {
+                        continue;
+                    }
+                    attempts.spawn_error = Some(message.clone());
+                    attempts.stderr_output = message;
+                    return attempts;
+                }
+            };
+
+            let stdout = match child.stdout.take() {
+                Some(stdout) => stdout,
+                None => {
+                    let _ = child.start_kill();
+                    attempts.stderr_output = "Claude CLI missing stdout".to_string();
+                    return attempts;
+                }
+            };
+            if let Some(mut stdin) = child.stdin.take() {
+                // Written from a task so a full stdout pipe can't deadlock the write; dropping
+                // stdin afterwards signals the end of the prompt.
+                let prompt = self.prompt.clone();
+                tokio::spawn(async move {
+                    let _ = stdin.write_all(prompt.as_bytes()).await;
+                    let _ = stdin.shutdown().await;
+                });
+            }
+            let stderr = child.stderr.take();
+            let stderr_handle = tokio::spawn(async move {
+                let mut output = String::new();
+                if let Some(stderr) = stderr {
+                    let mut lines = lossy_lines(BufReader::new(stderr));
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if !output.is_empty() {
+                            output.push('\n');
+                        }
+                        output.push_str(&line);
+                    }
+                }
+                output
+            });
+
+            let end = self
+                .stream_one_shot_output(&mut child, stdout, output, heartbeat, cancel_rx)
+                .await;
+            let canceled = matches!(end, OutputEnd::Canceled);
+            let read_error = match end {
+                OutputEnd::ReadError(error) => Some(error),
+                OutputEnd::Eof | OutputEnd::Canceled => None,
+            };
+            let status = child.wait().await.ok();
+            attempts.exit_status = status.and_then(|value| value.code());
+            let stderr_output = stderr_handle.await.unwrap_or_default();
+            let success = status.map(|value| value.success()).unwrap_or(false);
+            if !success && !canceled {
+                note_rate_limit(
+                    &self.rate_limits,
+                    &self.event_sink,
+                    &self.workspace_id,
+                    &stderr_output,
+                );
+            }
+            let failed_silently =
+                !canceled && !success && read_error.is_none() && output.aggregated.is_empty();
+            let fallback =
+                failed_silently && is_fallback_failure(attempts.exit_status, &stderr_output);
+            let recovery = self
+                .recover_failed_attempt(
+                    runtime,
+                    &mut attempt,
+                    &stderr_output,
+                    fallback,
+                    failed_silently,
+                    cancel_rx,
+                )
+                .await;
+            if let Recovery::Retry = recovery {
+                continue;
+            }
+            attempts.canceled = canceled || matches!(recovery, Recovery::Canceled);
+            attempts.success = success;
+            attempts.stderr_output = stderr_output;
+            attempts.read_error = read_error;
+            return attempts;
+        }
+    }
+
+    /// After an attempt failed with `error`, moves the turn to the next fallback profile when
+    /// `fallback` is set and one is left, or else, when `retry` is set, waits out the backoff
+    /// before retrying a transient failure.
+    async fn recover_failed_attempt(
+        &self,
+        runtime: &mut ClaudeRuntime,
+        attempt: &mut u32,
+        error: &str,
+        fallback: bool,
+        retry: bool,
+        cancel_rx: &mut oneshot::Receiver<()>,
+    ) -> Recovery {
+        if fallback {
+            if let Some(next) = next_fallback(
+                &mut runtime.fallbacks,
+                &self.event_sink,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                error,
+            ) {
+                runtime.answered_by = Some(next.profile_id);
+                (runtime.bin, runtime.args, runtime.env) = (next.bin, next.args, next.env);
+                return Recovery::Retry;
+            }
+        }
+        if !retry || *attempt >= self.retry_max_attempts || !is_transient_failure(error) {
+            return Recovery::GiveUp;
+        }
+        *attempt += 1;
+        let waited = wait_before_retry(
+            &self.event_sink,
+            &self.workspace_id,
+            &self.thread_id,
+            &self.turn_id,
+            error,
+            *attempt,
+            self.retry_max_attempts,
+            self.retry_base_delay_ms,
+            cancel_rx,
+        )
+        .await;
+        if waited {
+            Recovery::Retry
         } else {
-            let message = if !stderr_output.trim().is_empty() {
-                stderr_output
+            Recovery::Canceled
+        }
+    }
+
+    /// The command a pooled attempt starts its control-protocol process with.
+    fn warm_command(&self, runtime: &ClaudeRuntime) -> Result<tokio::process::Command, String> {
+        let session_created = self
+            .explicit_session_id
+            .as_deref()
+            .is_some_and(|session_id| claude_session_file_exists(&self.cwd_string, session_id));
+        let command = prepare_command(
+            runtime.bin.clone(),
+            runtime.args.clone(),
+            &runtime.env,
+            &self.cwd,
+            &self.generation,
+            &self.sandbox,
+            self.instructions.as_deref(),
+        )?;
+        Ok(self.launch(true).turn_command(
+            command,
+            None,
+            self.explicit_session_id.as_deref(),
+            self.resume_session_id.as_deref(),
+            session_created,
+        ))
+    }
+
+    /// Runs the attempt on a warm process from the pool, starting one if none matches.
+    async fn run_pooled_attempt(
+        &self,
+        runtime: &ClaudeRuntime,
+        idle_timeout: Duration,
+        output: &mut TurnOutput,
+        heartbeat: &TurnHeartbeat,
+        cancel_rx: &mut oneshot::Receiver<()>,
+        request: &mut Option<TurnRequest>,
+    ) -> PooledTurn {
+        let session_id = self
+            .explicit_session_id
+            .as_deref()
+            .or(self.resume_session_id.as_deref())
+            .unwrap_or(&self.thread_id);
+        if let Ok(command) = self.warm_command(runtime) {
+            *request = Some(TurnRequest {
+                shell: self.launch_shell.clone(),
+                ..TurnRequest::describe(&command, None, self.sandbox.restrict_env, true)
+            });
+        }
+        let mut progress = StreamProgress::new();
+        let mut checklist = None;
+        let pooled_request = PooledTurnRequest {
+            workspace_id: &self.workspace_id,
+            key: claude_pool_core::process_key(
+                session_id,
+                &self.cwd_string,
+                runtime.bin.as_deref(),
+                runtime.args.as_deref(),
+                &json!([
+                    self.generation,
+                    runtime.env,
+                    self.sandbox,
+                    self.instructions,
+                    self.launch_shell,
+                    self.plan
+                ]),
+                self.permission_prompt,
+            ),
+            prompt: &self.prompt,
+            idle_timeout,
+            approvals: self.permission_prompt.then_some(&self.claude_approvals),
+            command_approvals: self.command_approvals,
+            file_access: self.file_access.as_ref(),
+            output_filters: &self.output_filters,
+        };
+        let pooled = claude_pool_core::run_pooled_turn(
+            &self.claude_processes,
+            pooled_request,
+            || {
+                self.warm_command(runtime).map(|command| {
+                    through_shell(
+                        command,
+                        self.launch_shell.as_deref(),
+                        self.sandbox.restrict_env,
+                    )
+                })
+            },
+            cancel_rx,
+            |delta| {
+                heartbeat.record_bytes(delta.len());
+                let sanitized;
+                let delta = if self.sanitize_output {
+                    sanitized = output_sanitize_core::sanitize_output(delta);
+                    sanitized.as_str()
+                } else {
+                    delta
+                };
+                if delta.is_empty() {
+                    return;
+                }
+                self.stream_delta(output, &mut progress, delta);
+                if progress.is_due() {
+                    self.emit_progress(&mut progress);
+                }
+            },
+            |approval_id, approval| {
+                let (method, mut params) = match approval {
+                    ApprovalRequest::Command {
+                        command,
+                        description,
+                    } => (
+                        "approval/commandRequested",
+                        json!({ "command": command, "description": description }),
+                    ),
+                    ApprovalRequest::FileAccess(violation) => (
+                        "approval/fileAccessRequested",
+                        json!({
+                            "tool": violation.tool,
+                            "path": violation.path,
+                            "access": violation.access,
+                            "reason": violation.reason,
+                        }),
+                    ),
+                };
+                params["threadId"] = json!(self.thread_id);
+                params["turnId"] = json!(self.turn_id);
+                params["approvalId"] = json!(approval_id);
+                params["cwd"] = json!(self.cwd_string);
+                emit(&self.event_sink, &self.workspace_id, method, params);
+            },
+            |steps| {
+                self.emit_checklist(&steps);
+                checklist = Some(steps);
+            },
+        )
+        .await;
+        if checklist.is_some() {
+            output.checklist = checklist;
+        }
+        pooled
+    }
+
+    /// Streams a one-shot CLI's stdout into the reply until it closes or the turn is canceled.
+    async fn stream_one_shot_output(
+        &self,
+        child: &mut tokio::process::Child,
+        stdout: tokio::process::ChildStdout,
+        output: &mut TurnOutput,
+        heartbeat: &TurnHeartbeat,
+        cancel_rx: &mut oneshot::Receiver<()>,
+    ) -> OutputEnd {
+        let mut lines = lossy_lines(BufReader::new(stdout));
+        let mut pending_server_token: Option<String> = None;
+        let mut progress = StreamProgress::new();
+        let mut streamed = false;
+        let end = loop {
+            if progress.is_due() {
+                self.emit_progress(&mut progress);
+            }
+            match cancel_rx.try_recv() {
+                Ok(_) | Err(TryRecvError::Closed) => {
+                    let _ = child.kill().await;
+                    break OutputEnd::Canceled;
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let line = match timeout(Duration::from_millis(120), lines.next_line()).await {
+                Ok(Ok(Some(line))) => line,
+                Ok(Ok(None)) => break OutputEnd::Eof,
+                Ok(Err(error)) => {
+                    break OutputEnd::ReadError(format!("Failed reading Claude output: {error}"));
+                }
+                Err(_) => continue,
+            };
+            heartbeat.record_bytes(line.len() + 1);
+            let cleaned_line = if self.sanitize_output {
+                output_sanitize_core::sanitize_output(&line)
             } else {
-                "Claude CLI failed.".to_string()
+                strip_ansi_sequences(&line)
             };
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "error",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turnId": turn_id_for_task,
-                    "error": { "message": message },
-                    "willRetry": false,
-                }),
+            let normalized_line = cleaned_line.trim().to_string();
+            if normalized_line.is_empty() {
+                continue;
+            }
+
+            let record = serde_json::from_str::<Value>(&normalized_line)
+                .ok()
+                .filter(|record| record.get("type").is_some_and(Value::is_string));
+            if let Some(record) = record {
+                if let Some(steps) = checklist_update(&record) {
+                    self.emit_checklist(&steps);
+                    output.checklist = Some(steps);
+                }
+                let parsed = claude_pool_core::parse_stream_record(&record);
+                if claude_pool_core::drops_record(&self.output_filters, &normalized_line, &parsed) {
+                    streamed |= matches!(parsed, StreamLine::Delta(_));
+                    continue;
+                }
+                let text = match parsed {
+                    StreamLine::Delta(text) => {
+                        streamed = true;
+                        text
+                    }
+                    // Whole messages repeat the streamed deltas.
+                    StreamLine::Message(text) if !streamed => text,
+                    StreamLine::Session(session_id) => {
+                        output.provider_session_id = Some(session_id);
+                        continue;
+                    }
+                    StreamLine::Result { session_id, .. } => {
+                        output.provider_session_id =
+                            session_id.or(output.provider_session_id.take());
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let delta = if self.sanitize_output {
+                    output_sanitize_core::sanitize_output(&text)
+                } else {
+                    text
+                };
+                if delta.is_empty() {
+                    continue;
+                }
+                self.stream_delta(output, &mut progress, &delta);
+                continue;
+            }
+
+            if let Some(server_token) = pending_server_token.take() {
+                let candidate = format!("{server_token}\n{normalized_line}");
+                if is_debug_jsonrpc_message(&candidate) {
+                    continue;
+                }
+                let pending_delta = if output.aggregated.is_empty() {
+                    server_token
+                } else {
+                    format!("\n{server_token}")
+                };
+                self.stream_delta(output, &mut progress, &pending_delta);
+            }
+
+            if is_server_token(&normalized_line) {
+                pending_server_token = Some(normalized_line);
+                continue;
+            }
+
+            if is_debug_jsonrpc_line(&normalized_line)
+                || self.output_filters.should_drop(&normalized_line)
+            {
+                continue;
+            }
+
+            let delta = if output.aggregated.is_empty() {
+                normalized_line
+            } else {
+                format!("\n{normalized_line}")
+            };
+            self.stream_delta(output, &mut progress, &delta);
+        };
+        if let Some(server_token) = pending_server_token {
+            let delta = if output.aggregated.is_empty() {
+                server_token
+            } else {
+                format!("\n{server_token}")
+            };
+            self.stream_delta(output, &mut progress, &delta);
+        }
+        end
+    }
+
+    fn emit_assistant_completed(&self, text: &str, references: &Value) {
+        emit(
+            &self.event_sink,
+            &self.workspace_id,
+            "item/completed",
+            json!({
+                "threadId": self.thread_id,
+                "item": {
+                    "id": self.assistant_item_id,
+                    "type": "agentMessage",
+                    "text": text,
+                    "references": references,
+                },
+            }),
+        );
+    }
+
+    fn emit_turn_error(&self, message: &str) {
+        emit(
+            &self.event_sink,
+            &self.workspace_id,
+            "error",
+            json!({
+                "threadId": self.thread_id,
+                "turnId": self.turn_id,
+                "error": { "message": message },
+                "willRetry": false,
+            }),
+        );
+    }
+
+    async fn emit_turn_completed(&self, duration_ms: u64) {
+        // The turn stops counting as running before anyone hears it completed, so a message
+        // sent in reply to `turn/completed` is never turned away as busy.
+        self.claude_turn_cancels.lock().await.remove(&self.turn_id);
+        emit(
+            &self.event_sink,
+            &self.workspace_id,
+            "turn/completed",
+            json!({
+                "threadId": self.thread_id,
+                "turn": {
+                    "id": self.turn_id,
+                    "threadId": self.thread_id,
+                    "durationMs": duration_ms,
+                },
+            }),
+        );
+    }
+
+    /// Stores the finished turn, runs its hook and artifact collection, and reports how it
+    /// ended.
+    async fn finish(
+        self,
+        attempts: TurnAttempts,
+        output: TurnOutput,
+        answered_by: Option<String>,
+        duration_ms: u64,
+        started: bool,
+    ) {
+        let TurnAttempts {
+            success,
+            canceled,
+            stderr_output,
+            read_error,
+            exit_status,
+            request,
+            spawn_error,
+        } = attempts;
+        let TurnOutput {
+            mut aggregated,
+            output_cap,
+            provider_session_id,
+            checklist,
+        } = output;
+        let failed = !(canceled || success && read_error.is_none());
+        aggregated = self.secrets.mask(&aggregated);
+        // Only the redacted reply is stored; the raw one goes to the encrypted log if enabled.
+        let stored_text = self.redaction.redact(&aggregated);
+        // A reply over the output cap is stored cut short, so the raw log keeps all of it,
+        // still redacted unless raw logging is on.
+        let truncated_text = output_cap.truncate(stored_text.as_deref().unwrap_or(&aggregated));
+        let raw_log_text = match (
+            self.redaction_raw_log && stored_text.is_some(),
+            &truncated_text,
+        ) {
+            (true, _) => Some(aggregated.as_str()),
+            (false, Some(_)) => Some(stored_text.as_deref().unwrap_or(&aggregated)),
+            (false, None) => None,
+        };
+        if let (Some(text), Some(data_dir)) = (raw_log_text, self.claude_threads_path.parent()) {
+            if let Err(error) = redaction_core::append_raw_log(
+                data_dir,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                text,
+            ) {
+                eprintln!("Failed to write raw turn log: {error}");
+            }
+        }
+        if let Some(session_id) = provider_session_id.as_deref() {
+            record_provider_session(
+                &self.claude_threads,
+                &self.workspace_id,
+                &self.thread_id,
+                session_id,
+            )
+            .await;
+        }
+        if let Some(request) = request {
+            let request = TurnRequest {
+                exit_status,
+                ..request
+            };
+            audit_turn_spawn(
+                &self.claude_threads_path,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                self.plan,
+                &request,
+                self.started_at,
+                spawn_error,
+            );
+            record_turn_request(
+                &self.claude_threads,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                request,
+            )
+            .await;
+        }
+        if let Some(profile_id) = answered_by {
+            record_turn_answered_by(
+                &self.claude_threads,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                profile_id,
+            )
+            .await;
+        }
+        if let Some(steps) = checklist {
+            record_turn_checklist(
+                &self.claude_threads,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                steps,
+            )
+            .await;
+        }
+        finalize_turn(
+            &self.claude_threads,
+            &self.workspace_id,
+            &self.thread_id,
+            &self.turn_id,
+            &self.assistant_item_id,
+            truncated_text
+                .as_deref()
+                .or(stored_text.as_deref())
+                .unwrap_or(&aggregated),
+            duration_ms,
+            failed,
+        )
+        .await;
+        let _ = persist_threads_store(&self.claude_threads, &self.claude_threads_path).await;
+        emit_thread_list_updated(
+            &self.event_sink,
+            &self.claude_threads,
+            &self.workspace_id,
+            std::slice::from_ref(&self.thread_id),
+            &[],
+        )
+        .await;
+
+        if let Some(hook) = self.turn_hook.clone() {
+            let status = if canceled {
+                "canceled"
+            } else if success && read_error.is_none() {
+                "completed"
+            } else {
+                "failed"
+            };
+            let transcript = turn_transcript(
+                &self.claude_threads,
+                &self.workspace_id,
+                &self.thread_id,
+                &self.turn_id,
+                status,
+            )
+            .await;
+            let exit_status = exit_status.filter(|_| !canceled);
+            let event_sink = self.event_sink.clone();
+            let workspace_id = self.workspace_id.clone();
+            let thread_id = self.thread_id.clone();
+            let turn_id = self.turn_id.clone();
+            let cwd = self.cwd_string.clone();
+            let launch_shell = self.launch_shell.clone();
+            // Hooks may run tests or formatters; don't hold up the turn's completion events.
+            tokio::spawn(async move {
+                let payload = turn_hooks_core::run_turn_hook(TurnHookRun {
+                    command: &hook,
+                    cwd: &cwd,
+                    workspace_id: &workspace_id,
+                    thread_id: &thread_id,
+                    turn_id: &turn_id,
+                    status,
+                    exit_status,
+                    transcript,
+                    shell: launch_shell.as_deref(),
+                })
+                .await;
+                emit(&event_sink, &workspace_id, "turn/hookCompleted", payload);
+            });
+        }
+
+        let references = file_references_core::extract_references(&aggregated, &self.cwd);
+        if let Some(shown) = output_cap.truncate(&aggregated) {
+            aggregated = shown;
+        }
+        if let (false, Some(data_dir)) = (canceled, self.claude_threads_path.parent()) {
+            let collected = turn_artifacts_core::collect_turn_artifacts(ArtifactCollection {
+                data_dir,
+                workspace_id: &self.workspace_id,
+                thread_id: &self.thread_id,
+                turn_id: &self.turn_id,
+                cwd: &self.cwd,
+                references: &references,
+                declared: &self.artifact_paths,
+                since_ms: self.started_at,
+            });
+            match collected {
+                Ok(Some(manifest)) => emit(
+                    &self.event_sink,
+                    &self.workspace_id,
+                    "turn/artifacts",
+                    json!({
+                        "threadId": self.thread_id,
+                        "turnId": self.turn_id,
+                        "artifacts": manifest.artifacts,
+                    }),
+                ),
+                Ok(None) => {}
+                Err(error) => eprintln!("Failed to collect turn artifacts: {error}"),
+            }
+        }
+        let references = json!(references);
+        if canceled {
+            let session_id = provider_session_id
+                .as_ref()
+                .or(self.explicit_session_id.as_ref())
+                .or(self.resume_session_id.as_ref())
+                .filter(|_| started);
+            if let Some(session_id) = session_id {
+                recover_session_after_cancel(
+                    &self.claude_threads,
+                    &self.claude_threads_path,
+                    &self.event_sink,
+                    &self.workspace_id,
+                    &self.cwd_string,
+                    &self.thread_id,
+                    session_id,
+                )
+                .await;
+            }
+            self.emit_assistant_completed(&aggregated, &references);
+            self.emit_turn_completed(duration_ms).await;
+            return;
+        }
+
+        if let Some(error) = read_error {
+            self.emit_turn_error(&error);
+            self.emit_turn_completed(duration_ms).await;
+            return;
+        }
+
+        if !success {
+            let message = if !stderr_output.trim().is_empty() {
+                stderr_output
+            } else {
+                "Claude CLI failed.".to_string()
+            };
+            self.emit_turn_error(&message);
+            self.emit_turn_completed(duration_ms).await;
+            return;
+        }
+
+        self.emit_assistant_completed(&aggregated, &references);
+        self.emit_turn_completed(duration_ms).await;
+        if let Some((entry, parent_entry, settings)) = self.suggestion_context {
+            let request = SuggestionRequest {
+                entry,
+                parent_entry,
+                settings,
+                workspace_id: self.workspace_id,
+                thread_id: self.thread_id,
+                turn_id: self.turn_id,
+            };
+            let claude_threads = self.claude_threads;
+            let claude_threads_path = self.claude_threads_path;
+            let event_sink = self.event_sink;
+            tokio::spawn(async move {
+                if let Err(error) = turn_suggestions_core::suggest_follow_ups(
+                    &claude_threads,
+                    &claude_threads_path,
+                    request,
+                    &event_sink,
+                )
+                .await
+                {
+                    eprintln!("Failed to suggest follow-ups: {error}");
+                }
+            });
+        }
+    }
+}
+
+pub(crate) async fn send_user_message_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_processes: &ClaudeProcessPool,
+    claude_approvals: &ClaudeApprovalsStore,
+    turn_slots: &TurnSlotsStore,
+    thread_locks: &ThreadLocksStore,
+    rate_limits: &RateLimitsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+    dry_run: bool,
+    plan: bool,
+    event_sink: E,
+) -> Result<Value, String> {
+    if text.trim().is_empty()
+        && images
+            .as_ref()
+            .map(|items| items.is_empty())
+            .unwrap_or(true)
+    {
+        return Err("empty user message".to_string());
+    }
+
+    let (entry, parent_entry, settings) =
+        resolve_workspace_context(workspaces, app_settings, &workspace_id).await?;
+    let (claude_bin, claude_args, claude_env) =
+        providers::resolve_claude_runtime_config(&entry, parent_entry.as_ref(), Some(&settings));
+    let claude_fallbacks =
+        providers::resolve_claude_fallbacks(&entry, parent_entry.as_ref(), Some(&settings));
+    let mut context_paths = resolve_context_paths(&entry, parent_entry.as_ref());
+    if thread_is_new(claude_threads, &workspace_id, &thread_id).await {
+        context_paths.push(memory_core::MEMORY_FILE_PATH.to_string());
+    }
+    let context_files = load_context_files(&entry.path, &context_paths);
+    let turn_hook = turn_hooks_core::resolve_turn_hook(&entry, parent_entry.as_ref());
+    let artifact_paths = turn_artifacts_core::resolve_artifact_paths(&entry, parent_entry.as_ref());
+    let sandbox = sandbox_core::resolve_sandbox(&entry, parent_entry.as_ref());
+    let file_access = FileAccessGuard::new(&sandbox)?;
+    let instructions = providers::resolve_workspace_instructions(&entry, parent_entry.as_ref());
+    let mut prompt = build_prompt(&text, images, &context_files);
+    let secrets = ResolvedSecrets::resolve(&prompt, &entry, &KeyringSecrets)?;
+    let output_filters =
+        OutputFilterPipeline::compile(&settings.output_filters, &ProviderKind::Claude);
+    let sanitize_output = settings.sanitize_output;
+    let redaction = RedactionPipeline::compile(&settings.redaction_rules);
+    let redaction_raw_log = settings.redaction_raw_log;
+    let retry_max_attempts = settings.retry_max_attempts;
+    let retry_base_delay_ms = settings.retry_base_delay_ms;
+    let max_turn_output_bytes = settings.max_turn_output_bytes;
+    let heartbeat_interval = (settings.turn_heartbeat_interval_secs > 0)
+        .then(|| Duration::from_secs(settings.turn_heartbeat_interval_secs));
+    let command_approvals = settings.claude_command_approvals;
+    let permission_prompt = command_approvals || file_access.is_some();
+    let launch_shell = settings.launch_shell.clone();
+    let max_concurrent_turns = settings.max_concurrent_turns as usize;
+    // Settings saved while the turn waits for a slot change the bin and args it starts with.
+    let settings_changes = settings_core::subscribe_settings_changes();
+    let runtime_entries = (entry.clone(), parent_entry.clone());
+    // Plan turns are followed by executing the plan, so they get no suggestions.
+    let suggestion_context = (settings.follow_up_suggestions && !plan)
+        .then(|| (entry.clone(), parent_entry.clone(), settings.clone()));
+    // Approvals need the stream-json control protocol, so they take the pooled path even with
+    // warm processes off, plan turns included; such a process is shut down as soon as the turn
+    // ends. Other plan turns run one-shot, since a warm process keeps the permission mode it
+    // was started with.
+    let warm_idle_timeout = settings
+        .claude_warm_process
+        .then(|| Duration::from_secs(settings.claude_warm_idle_timeout_secs.max(1)))
+        .filter(|_| !plan)
+        .or_else(|| permission_prompt.then_some(Duration::ZERO));
+
+    let cli_version = if dry_run {
+        None
+    } else {
+        provider_versions_core::probe_cli_version(&claude_executable(claude_bin.as_ref())).await
+    };
+    // Warm and one-shot turns alike read the CLI's stream-json output.
+    let version_mismatch = cli_version.as_deref().and_then(|installed| {
+        provider_versions_core::version_mismatch(
+            &ProviderKind::Claude,
+            installed,
+            provider_versions_core::configured_min_version(&settings, &ProviderKind::Claude),
+            &[&provider_versions_core::CLAUDE_STREAM_JSON],
+        )
+    });
+
+    let memory = (!dry_run
+        && resource_guard_core::uses_local_models(claude_args.as_deref(), &claude_env))
+    .then(resource_guard_core::MemorySnapshot::current);
+    let mut resource_warning = None;
+    let model_downgrade;
+
+    // Held until the turn is recorded and has registered its cancel handle; a second message
+    // sent meanwhile is turned away instead of interleaving with this one.
+    let _thread_lock = if dry_run {
+        None
+    } else {
+        let lock = thread_locks.try_lock(&workspace_id, &thread_id)?;
+        if thread_has_running_turn(claude_turn_cancels, &workspace_id, &thread_id).await {
+            return Err(THREAD_BUSY.to_string());
+        }
+        Some(lock)
+    };
+    let turn_id = format!("claude-turn-{}", Uuid::new_v4());
+    let user_item_id = format!("claude-user-{}", Uuid::new_v4());
+    let assistant_item_id = format!("claude-assistant-{}", Uuid::new_v4());
+    let started_at = now_ms();
+    let (thread_has_turns, session_override, session_seed, cwd_override, generation) = {
+        let mut store = claude_threads.lock().await;
+        let turns_used = model_downgrade_core::turns_in_budget_window(&store, started_at);
+        let threads = store
+            .get_mut(&workspace_id)
+            .ok_or_else(|| "thread not found".to_string())?;
+        let thread = threads
+            .iter_mut()
+            .find(|thread| thread.id == thread_id)
+            .ok_or_else(|| "thread not found".to_string())?;
+        let had_turns = !thread.turns.is_empty();
+        model_downgrade = model_downgrade_core::choose_model(
+            &settings,
+            &ProviderKind::Claude,
+            &thread.tags,
+            thread.generation.model.as_deref(),
+            turns_used,
+        );
+        let mut generation = thread.generation.clone();
+        if let Some(downgrade) = &model_downgrade {
+            generation.model = Some(downgrade.model.clone());
+        }
+        let session_seed = thread
+            .session_needs_seed
+            .then(|| build_session_seed(&thread.turns));
+        // A pending seed starts the replacement session; otherwise resume what the CLI reported.
+        let session = thread
+            .provider_session_id
+            .clone()
+            .filter(|_| !thread.session_needs_seed)
+            .or_else(|| thread.session_id.clone());
+        let snapshot = (
+            had_turns,
+            session,
+            session_seed,
+            thread_cwd_override(thread),
+            generation.clone(),
+        );
+        // A dry run only inspects the thread; the seed stays pending for the real send.
+        if dry_run {
+            return describe_dry_run(
+                claude_bin,
+                claude_args,
+                &claude_env,
+                &entry.path,
+                &thread_id,
+                &prompt,
+                snapshot,
+                &context_files,
+                &sandbox,
+                instructions.as_deref(),
+                TurnLaunch {
+                    pooled: attempt_idle_timeout(warm_idle_timeout, permission_prompt, 0).is_some(),
+                    permission_prompt,
+                    file_rules: file_access.is_some(),
+                    plan,
+                },
+                launch_shell.as_deref(),
+            );
+        }
+        // A local model that can't fit is refused before the turn is recorded.
+        if let (Some(memory), Some(model)) = (memory, generation.model.as_deref()) {
+            resource_warning = resource_guard_core::check_model_fit(model, memory)?;
+        }
+        if thread.session_needs_seed {
+            thread.provider_session_id = None;
+        }
+        thread.session_needs_seed = false;
+        thread.updated_at = started_at;
+        thread.turns.push(ClaudeTurnRecord {
+            id: turn_id.clone(),
+            started_at,
+            completed_at: None,
+            items: vec![
+                ClaudeMessageRecord {
+                    id: user_item_id.clone(),
+                    role: "user".to_string(),
+                    text: text.clone(),
+                    pinned: false,
+                },
+                ClaudeMessageRecord {
+                    id: assistant_item_id.clone(),
+                    role: "assistant".to_string(),
+                    text: String::new(),
+                    pinned: false,
+                },
+            ],
+            context_files: context_files
+                .iter()
+                .map(|file| file.record.clone())
+                .collect(),
+            cli_version: cli_version.clone(),
+            duration_ms: None,
+            snapshot: None,
+            plan,
+            suggestions: Vec::new(),
+            checklist: Vec::new(),
+            answered_by: None,
+            model_downgrade: model_downgrade.clone(),
+            request: None,
+        });
+        snapshot
+    };
+    // Placeholders are filled only now, so dry runs and stored turns never see the values.
+    prompt = secrets.fill(&prompt);
+    if let Some(seed) = &session_seed {
+        prompt.insert_str(0, seed);
+    }
+    let cwd = resolve_turn_cwd(cwd_override, &entry.path);
+    let file_access = file_access.map(|guard| guard.for_turn(Path::new(&entry.path), &cwd));
+    let mut snapshot_error = None;
+    if entry.settings.turn_snapshots {
+        match turn_snapshot_core::create_turn_snapshot(&cwd, &turn_id).await {
+            Ok(snapshot) => {
+                let mut store = claude_threads.lock().await;
+                if let Some(turn) = store
+                    .get_mut(&workspace_id)
+                    .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+                    .and_then(|thread| thread.turns.iter_mut().find(|turn| turn.id == turn_id))
+                {
+                    turn.snapshot = Some(snapshot);
+                }
+            }
+            Err(error) => snapshot_error = Some(error),
+        }
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+
+    emit(
+        &event_sink,
+        &workspace_id,
+        "turn/started",
+        json!({
+            "threadId": thread_id,
+            "turn": {
+                "id": turn_id,
+                "threadId": thread_id,
+                "plan": plan,
+                "modelDowngrade": model_downgrade,
+            },
+        }),
+    );
+    if let Some(mut mismatch) = version_mismatch {
+        mismatch["threadId"] = json!(thread_id);
+        mismatch["turnId"] = json!(turn_id);
+        emit(
+            &event_sink,
+            &workspace_id,
+            "provider/versionMismatch",
+            mismatch,
+        );
+    }
+    if let Some(mut warning) = resource_warning {
+        warning["threadId"] = json!(thread_id);
+        warning["turnId"] = json!(turn_id);
+        emit(
+            &event_sink,
+            &workspace_id,
+            "provider/resourceWarning",
+            warning,
+        );
+    }
+    if let Some(error) = snapshot_error {
+        emit(
+            &event_sink,
+            &workspace_id,
+            "turn/snapshotFailed",
+            json!({ "threadId": thread_id, "turnId": turn_id, "error": error }),
+        );
+    }
+    emit(
+        &event_sink,
+        &workspace_id,
+        "item/started",
+        json!({
+            "threadId": thread_id,
+            "item": {
+                "id": user_item_id,
+                "type": "userMessage",
+                "content": [{ "type": "text", "text": text }],
+            },
+        }),
+    );
+    emit(
+        &event_sink,
+        &workspace_id,
+        "item/completed",
+        json!({
+            "threadId": thread_id,
+            "item": {
+                "id": user_item_id,
+                "type": "userMessage",
+                "content": [{ "type": "text", "text": text }],
+            },
+        }),
+    );
+    emit(
+        &event_sink,
+        &workspace_id,
+        "item/started",
+        json!({
+            "threadId": thread_id,
+            "item": {
+                "id": assistant_item_id,
+                "type": "agentMessage",
+                "text": "",
+            },
+        }),
+    );
+
+    let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    {
+        let mut cancels = claude_turn_cancels.lock().await;
+        cancels.insert(
+            turn_id.clone(),
+            TurnCancel {
+                workspace_id: workspace_id.clone(),
+                thread_id: thread_id.clone(),
+                cancel: cancel_tx,
+            },
+        );
+    }
+
+    let (explicit_session_id, resume_session_id) = resolve_session_ids(
+        &thread_id,
+        thread_has_turns,
+        session_override.as_deref(),
+        session_seed.is_some(),
+    );
+    let runtime = ClaudeRuntime {
+        bin: claude_bin,
+        args: claude_args,
+        env: claude_env,
+        fallbacks: claude_fallbacks.into_iter(),
+        answered_by: None,
+    };
+    let task = ClaudeTurnTask {
+        event_sink: event_sink.clone(),
+        claude_threads: Arc::clone(claude_threads),
+        claude_threads_path: claude_threads_path.to_path_buf(),
+        claude_turn_cancels: Arc::clone(claude_turn_cancels),
+        claude_processes: Arc::clone(claude_processes),
+        claude_approvals: Arc::clone(claude_approvals),
+        turn_slots: Arc::clone(turn_slots),
+        rate_limits: Arc::clone(rate_limits),
+        workspace_id: workspace_id.clone(),
+        thread_id: thread_id.clone(),
+        turn_id: turn_id.clone(),
+        assistant_item_id,
+        started_at,
+        cwd_string: cwd.to_string_lossy().to_string(),
+        cwd,
+        prompt,
+        generation,
+        sandbox,
+        instructions,
+        file_access,
+        output_filters,
+        launch_shell,
+        explicit_session_id,
+        resume_session_id,
+        plan,
+        permission_prompt,
+        command_approvals,
+        sanitize_output,
+        warm_idle_timeout,
+        heartbeat_interval,
+        max_concurrent_turns,
+        max_turn_output_bytes,
+        retry_max_attempts,
+        retry_base_delay_ms,
+        secrets,
+        redaction,
+        redaction_raw_log,
+        turn_hook,
+        artifact_paths,
+        suggestion_context,
+        settings_changes,
+        runtime_entries,
+    };
+    tokio::spawn(task.run(runtime, cancel_rx));
+
+    Ok(json!({
+        "result": {
+            "turn": { "id": turn_id, "threadId": thread_id }
+        }
+    }))
+}
+
+const EXECUTE_PLAN_PROMPT: &str = "The plan below was accepted. Carry it out now.\n\n";
+
+/// Text of the plan produced by `turn_id`, or by the thread's latest turn when none is given.
+fn accepted_plan(thread: &ClaudeThreadRecord, turn_id: Option<&str>) -> Result<String, String> {
+    let turn = match turn_id {
+        Some(turn_id) => thread.turns.iter().find(|turn| turn.id == turn_id),
+        None => thread.turns.last(),
+    }
+    .ok_or_else(|| "turn not found".to_string())?;
+    if !turn.plan {
+        return Err("The turn is not a plan.".to_string());
+    }
+    if turn.completed_at.is_none() {
+        return Err("The plan is still being written.".to_string());
+    }
+    let plan = turn
+        .items
+        .iter()
+        .filter(|item| item.role == "assistant")
+        .map(|item| item.text.trim())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if plan.is_empty() {
+        return Err("The plan is empty.".to_string());
+    }
+    Ok(plan)
+}
+
+/// Sends an accepted plan back to the same thread as a regular turn, so Claude carries it out
+/// in the session that wrote it.
+pub(crate) async fn execute_plan_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_processes: &ClaudeProcessPool,
+    claude_approvals: &ClaudeApprovalsStore,
+    turn_slots: &TurnSlotsStore,
+    thread_locks: &ThreadLocksStore,
+    rate_limits: &RateLimitsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: Option<String>,
+    event_sink: E,
+) -> Result<Value, String> {
+    if thread_has_running_turn(claude_turn_cancels, &workspace_id, &thread_id).await {
+        return Err("Wait for the running turn to finish before executing the plan.".to_string());
+    }
+    let plan = {
+        let store = claude_threads.lock().await;
+        let thread = store
+            .get(&workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        accepted_plan(thread, turn_id.as_deref())?
+    };
+    send_user_message_core(
+        workspaces,
+        app_settings,
+        claude_threads,
+        claude_turn_cancels,
+        claude_processes,
+        claude_approvals,
+        turn_slots,
+        thread_locks,
+        rate_limits,
+        claude_threads_path,
+        workspace_id,
+        thread_id,
+        format!("{EXECUTE_PLAN_PROMPT}{plan}"),
+        None,
+        false,
+        false,
+        event_sink,
+    )
+    .await
+}
+
+pub(crate) async fn thread_has_running_turn(
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    workspace_id: &str,
+    thread_id: &str,
+) -> bool {
+    claude_turn_cancels
+        .lock()
+        .await
+        .values()
+        .any(|entry| entry.workspace_id == workspace_id && entry.thread_id == thread_id)
+}
+
+fn take_thread_cancels(
+    cancels: &mut HashMap<String, TurnCancel>,
+    workspace_id: &str,
+    thread_id: &str,
+) -> Vec<oneshot::Sender<()>> {
+    let turn_ids = cancels
+        .iter()
+        .filter(|(_, entry)| entry.workspace_id == workspace_id && entry.thread_id == thread_id)
+        .map(|(turn_id, _)| turn_id.clone())
+        .collect::<Vec<_>>();
+    turn_ids
+        .iter()
+        .filter_map(|turn_id| cancels.remove(turn_id))
+        .map(|entry| entry.cancel)
+        .collect()
+}
+
+/// Interrupts whatever turn the thread is running, if any.
+pub(crate) async fn interrupt_thread_turns(
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    workspace_id: &str,
+    thread_id: &str,
+) -> bool {
+    let pending = {
+        let mut cancels = claude_turn_cancels.lock().await;
+        take_thread_cancels(&mut cancels, workspace_id, thread_id)
+    };
+    let interrupted = !pending.is_empty();
+    for cancel in pending {
+        let _ = cancel.send(());
+    }
+    interrupted
+}
+
+/// Interrupts every running turn and returns their ids.
+pub(crate) async fn interrupt_all_turns(
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+) -> Vec<String> {
+    let pending = std::mem::take(&mut *claude_turn_cancels.lock().await);
+    pending
+        .into_iter()
+        .map(|(turn_id, entry)| {
+            let _ = entry.cancel.send(());
+            turn_id
+        })
+        .collect()
+}
+
+fn turn_interrupt_error(code: &str, turn_id: &str, message: String) -> String {
+    json!({ "code": code, "turnId": turn_id, "message": message }).to_string()
+}
+
+/// Interrupts one turn by id. An empty id or the frontend's `pending` placeholder (sent before
+/// `turn/started` arrives) falls back to the thread's current turn. Errors are JSON objects
+/// with a `code` of `turnAlreadyFinished` or `turnNotFound`.
+pub(crate) async fn turn_interrupt_core(
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: Option<String>,
+) -> Result<Value, String> {
+    let turn_id = turn_id
+        .map(|turn_id| turn_id.trim().to_string())
+        .filter(|turn_id| !turn_id.is_empty() && turn_id != "pending");
+    let Some(turn_id) = turn_id else {
+        let interrupted =
+            interrupt_thread_turns(claude_turn_cancels, &workspace_id, &thread_id).await;
+        return Ok(json!({ "result": { "ok": true, "interrupted": interrupted } }));
+    };
+
+    let cancel = {
+        let mut cancels = claude_turn_cancels.lock().await;
+        let belongs_to_thread = cancels.get(&turn_id).is_some_and(|entry| {
+            entry.workspace_id == workspace_id && entry.thread_id == thread_id
+        });
+        if belongs_to_thread {
+            cancels.remove(&turn_id)
+        } else {
+            None
+        }
+    };
+    if let Some(entry) = cancel {
+        let _ = entry.cancel.send(());
+        return Ok(json!({ "result": { "ok": true, "interrupted": true, "turnId": turn_id } }));
+    }
+
+    let completed_at = {
+        let store = claude_threads.lock().await;
+        store
+            .get(&workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+            .and_then(|thread| thread.turns.iter().find(|turn| turn.id == turn_id))
+            .map(|turn| turn.completed_at)
+    };
+    match completed_at {
+        Some(Some(_)) => Err(turn_interrupt_error(
+            "turnAlreadyFinished",
+            &turn_id,
+            format!("Turn `{turn_id}` has already finished."),
+        )),
+        // Recorded but no longer running (e.g. the app restarted mid-turn).
+        Some(None) => Ok(json!({
+            "result": { "ok": true, "interrupted": false, "turnId": turn_id }
+        })),
+        None => Err(turn_interrupt_error(
+            "turnNotFound",
+            &turn_id,
+            format!("Turn `{turn_id}` was not found in this thread."),
+        )),
+    }
+}
+
+pub(crate) async fn archive_thread_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    persist_archived_thread_ids_for_workspace(
+        claude_threads_path,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+    )?;
+    let mut store = claude_threads.lock().await;
+    let mut removed = Vec::new();
+    if let Some(threads) = store.get_mut(&workspace_id) {
+        if let Some(index) = threads.iter().position(|thread| thread.id == thread_id) {
+            removed.push(threads.remove(index));
+        }
+    }
+    drop(store);
+    trash_threads(
+        claude_threads_path,
+        &workspace_id,
+        removed,
+        TrashReason::Archive,
+    )?;
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        &[],
+        std::slice::from_ref(&thread_id),
+    )
+    .await;
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BulkThreadOp {
+    Archive,
+    Delete,
+    Tag(Vec<String>),
+    Export,
+}
+
+impl BulkThreadOp {
+    pub(crate) fn parse(op: &str, tags: Option<Vec<String>>) -> Result<Self, String> {
+        let tags = normalize_tags(tags.unwrap_or_default());
+        let op = match op.trim() {
+            "" => return Err("bulk thread op is required".to_string()),
+            "tag" if tags.is_empty() => {
+                return Err("bulk tag requires at least one tag".to_string())
+            }
+            "tag" => return Ok(BulkThreadOp::Tag(tags)),
+            "archive" => BulkThreadOp::Archive,
+            "delete" => BulkThreadOp::Delete,
+            "export" => BulkThreadOp::Export,
+            other => return Err(format!("unknown bulk thread op `{other}`")),
+        };
+        if !tags.is_empty() {
+            return Err(format!(
+                "tags only apply to the `tag` op, not `{}`",
+                op.as_str()
+            ));
+        }
+        Ok(op)
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            BulkThreadOp::Archive => "archive",
+            BulkThreadOp::Delete => "delete",
+            BulkThreadOp::Tag(_) => "tag",
+            BulkThreadOp::Export => "export",
+        }
+    }
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.clone()))
+        .collect()
+}
+
+/// Copy of `thread` with its assistant replies passed through the redaction rules, so
+/// exports never carry text recorded before a rule was added.
+pub(crate) fn redact_thread(
+    thread: &ClaudeThreadRecord,
+    redaction: &RedactionPipeline,
+) -> ClaudeThreadRecord {
+    let mut thread = thread.clone();
+    redact_thread_in_place(&mut thread, redaction);
+    thread
+}
+
+/// Passes the assistant replies of a thread read from outside the store through the
+/// redaction rules, as live turns are before they are stored.
+pub(crate) fn redact_thread_in_place(
+    thread: &mut ClaudeThreadRecord,
+    redaction: &RedactionPipeline,
+) {
+    for item in thread
+        .turns
+        .iter_mut()
+        .flat_map(|turn| turn.items.iter_mut())
+        .filter(|item| item.role == "assistant")
+    {
+        if let Some(redacted) = redaction.redact(&item.text) {
+            item.text = redacted;
+        }
+    }
+}
+
+/// Trimmed, de-duplicated ids for a bulk operation; blank ids are dropped and nothing left is
+/// an error.
+fn requested_thread_ids(thread_ids: Vec<String>) -> Result<Vec<String>, String> {
+    let mut requested = Vec::new();
+    let mut seen = HashSet::new();
+    for thread_id in thread_ids {
+        let trimmed = thread_id.trim().to_string();
+        if !trimmed.is_empty() && seen.insert(trimmed.clone()) {
+            requested.push(trimmed);
+        }
+    }
+    if requested.is_empty() {
+        return Err("no thread ids provided".to_string());
+    }
+    Ok(requested)
+}
+
+/// Applies one operation to many threads while touching the snapshot file at most once.
+/// Deleted threads are recorded like archived ones so history import does not bring them back;
+/// both land in the trash until it is purged.
+pub(crate) async fn bulk_thread_op_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_ids: Vec<String>,
+    op: BulkThreadOp,
+    redaction: &RedactionPipeline,
+    event_sink: E,
+) -> Result<Value, String> {
+    let requested = requested_thread_ids(thread_ids)?;
+
+    let (processed, missing, exported, removed) = {
+        let mut store = claude_threads.lock().await;
+        let threads = store.entry(workspace_id.clone()).or_default();
+        let known: HashSet<String> = threads.iter().map(|thread| thread.id.clone()).collect();
+        let (processed, missing): (Vec<String>, Vec<String>) =
+            requested.into_iter().partition(|id| known.contains(id));
+        let targets: HashSet<&str> = processed.iter().map(String::as_str).collect();
+        let mut exported = Vec::new();
+        let mut removed = Vec::new();
+        match &op {
+            BulkThreadOp::Archive | BulkThreadOp::Delete => {
+                let (dropped, kept) = std::mem::take(threads)
+                    .into_iter()
+                    .partition(|thread| targets.contains(thread.id.as_str()));
+                *threads = kept;
+                removed = dropped;
+            }
+            BulkThreadOp::Tag(tags) => {
+                for thread in threads
+                    .iter_mut()
+                    .filter(|thread| targets.contains(thread.id.as_str()))
+                {
+                    let mut merged = std::mem::take(&mut thread.tags);
+                    merged.extend(tags.iter().cloned());
+                    thread.tags = normalize_tags(merged);
+                }
+            }
+            BulkThreadOp::Export => {
+                exported = threads
+                    .iter()
+                    .filter(|thread| targets.contains(thread.id.as_str()))
+                    .map(|thread| thread_resume_payload(&redact_thread(thread, redaction)))
+                    .collect::<Vec<_>>();
+            }
+        }
+        (processed, missing, exported, removed)
+    };
+
+    if !processed.is_empty() {
+        match &op {
+            BulkThreadOp::Archive | BulkThreadOp::Delete => {
+                persist_archived_thread_ids_for_workspace(
+                    claude_threads_path,
+                    &workspace_id,
+                    &processed,
+                )?;
+                let reason = if matches!(op, BulkThreadOp::Delete) {
+                    TrashReason::Delete
+                } else {
+                    TrashReason::Archive
+                };
+                trash_threads(claude_threads_path, &workspace_id, removed, reason)?;
+                persist_threads_store(claude_threads, claude_threads_path).await?;
+                emit_thread_list_updated(
+                    &event_sink,
+                    claude_threads,
+                    &workspace_id,
+                    &[],
+                    &processed,
+                )
+                .await;
+            }
+            BulkThreadOp::Tag(_) => {
+                persist_threads_store(claude_threads, claude_threads_path).await?;
+                emit_thread_list_updated(
+                    &event_sink,
+                    claude_threads,
+                    &workspace_id,
+                    &processed,
+                    &[],
+                )
+                .await;
+            }
+            BulkThreadOp::Export => {}
+        }
+    }
+
+    let mut result = json!({
+        "op": op.as_str(),
+        "processed": processed,
+        "missing": missing,
+    });
+    if matches!(op, BulkThreadOp::Export) {
+        result["threads"] = Value::Array(exported);
+    }
+    Ok(json!({ "result": result }))
+}
+
+pub(crate) async fn set_thread_name_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    name: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let mut store = claude_threads.lock().await;
+    let threads = store
+        .get_mut(&workspace_id)
+        .ok_or_else(|| "thread not found".to_string())?;
+    let thread = threads
+        .iter_mut()
+        .find(|thread| thread.id == thread_id)
+        .ok_or_else(|| "thread not found".to_string())?;
+    let trimmed = name.trim().to_string();
+    thread.name = if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.clone())
+    };
+    thread.updated_at = now_ms();
+    let thread_name = thread.name.clone();
+    drop(store);
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "threadName": thread_name,
+        }
+    }))
+}
+
+/// Pins or unpins one message. Pinned messages are kept in the seed of any replacement session.
+pub(crate) async fn set_item_pinned_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    pinned: bool,
+    event_sink: E,
+) -> Result<Value, String> {
+    {
+        let mut store = claude_threads.lock().await;
+        let thread = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        let item = thread
+            .turns
+            .iter_mut()
+            .flat_map(|turn| turn.items.iter_mut())
+            .find(|item| item.id == item_id)
+            .ok_or_else(|| "item not found".to_string())?;
+        item.pinned = pinned;
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    let payload = json!({ "threadId": thread_id, "itemId": item_id, "pinned": pinned });
+    emit(&event_sink, &workspace_id, "item/pinned", payload.clone());
+    Ok(json!({ "result": payload }))
+}
+
+/// A thread's pinned messages in conversation order.
+pub(crate) async fn pinned_items_core(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let store = claude_threads.lock().await;
+    let thread = store
+        .get(&workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .ok_or_else(|| "thread not found".to_string())?;
+    let items = thread
+        .turns
+        .iter()
+        .flat_map(|turn| turn.items.iter().map(move |item| (turn, item)))
+        .filter(|(_, item)| item.pinned)
+        .map(|(turn, item)| {
+            json!({
+                "turnId": turn.id,
+                "itemId": item.id,
+                "role": item.role,
+                "text": item.text,
+                "startedAt": turn.started_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({ "result": { "threadId": thread_id, "items": items } }))
+}
+
+/// Marks every turn finished so far as read. `updatedAt` is left alone so reading a thread
+/// does not move it up the list.
+pub(crate) async fn mark_read_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let last_read_at = now_ms();
+    {
+        let mut store = claude_threads.lock().await;
+        let thread = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        thread.last_read_at = Some(last_read_at);
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "lastReadAt": last_read_at,
+        }
+    }))
+}
+
+const COPY_NAME_FALLBACK: &str = "New Agent";
+const COPY_NAME_PREVIEW_CHARS: usize = 40;
+
+fn copy_name(thread: &ClaudeThreadRecord) -> String {
+    let base = thread
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            let preview = thread.preview.lines().next().unwrap_or("").trim();
+            (!preview.is_empty()).then(|| preview.chars().take(COPY_NAME_PREVIEW_CHARS).collect())
+        })
+        .unwrap_or_else(|| COPY_NAME_FALLBACK.to_string());
+    format!("{base} (copy)")
+}
+
+/// Deep-copies a thread under fresh thread, turn and item IDs. The copy starts a new Claude
+/// session seeded from the transcript, so it never resumes (or mutates) the original session.
+fn duplicate_thread_record(thread: &ClaudeThreadRecord, timestamp: i64) -> ClaudeThreadRecord {
+    let turns = thread
+        .turns
+        .iter()
+        .map(|turn| ClaudeTurnRecord {
+            id: format!("claude-turn-{}", Uuid::new_v4()),
+            items: turn
+                .items
+                .iter()
+                .map(|item| ClaudeMessageRecord {
+                    id: if item.role == "user" {
+                        format!("claude-user-{}", Uuid::new_v4())
+                    } else {
+                        format!("claude-assistant-{}", Uuid::new_v4())
+                    },
+                    ..item.clone()
+                })
+                .collect(),
+            ..turn.clone()
+        })
+        .collect::<Vec<_>>();
+    let has_turns = !turns.is_empty();
+    ClaudeThreadRecord {
+        id: Uuid::new_v4().to_string(),
+        created_at: timestamp,
+        updated_at: timestamp,
+        name: Some(copy_name(thread)),
+        session_id: has_turns.then(|| Uuid::new_v4().to_string()),
+        session_needs_seed: has_turns,
+        provider_session_id: None,
+        turns,
+        ..thread.clone()
+    }
+}
+
+pub(crate) async fn duplicate_thread_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let copy = {
+        let mut store = claude_threads.lock().await;
+        let threads = store
+            .get_mut(&workspace_id)
+            .ok_or_else(|| "thread not found".to_string())?;
+        let original = threads
+            .iter()
+            .find(|thread| thread.id == thread_id)
+            .ok_or_else(|| "thread not found".to_string())?;
+        if original
+            .turns
+            .last()
+            .is_some_and(|turn| turn.completed_at.is_none())
+        {
+            return Err("Wait for the running turn to finish before duplicating.".to_string());
+        }
+        let copy = duplicate_thread_record(original, now_ms());
+        threads.insert(0, copy.clone());
+        copy
+    };
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit(
+        &event_sink,
+        &workspace_id,
+        "thread/started",
+        json!({
+            "thread": thread_summary(&copy),
+        }),
+    );
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&copy.id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "sourceThreadId": thread_id,
+            "thread": thread_summary(&copy),
+        }
+    }))
+}
+
+/// The thread as it will live in the workspace at `target_path`. A copy gets fresh IDs like a
+/// duplicate but keeps its name; a move keeps its IDs. Either way the cwd override is carried
+/// over when the same directory exists in the target, and a thread with turns starts a new
+/// session seeded from the transcript, since Claude keys sessions by cwd.
+pub(crate) fn relocate_thread_record(
+    thread: &ClaudeThreadRecord,
+    target_path: &str,
+    copy: bool,
+    timestamp: i64,
+) -> ClaudeThreadRecord {
+    let mut relocated = if copy {
+        ClaudeThreadRecord {
+            name: thread.name.clone(),
+            ..duplicate_thread_record(thread, timestamp)
+        }
+    } else {
+        thread.clone()
+    };
+    relocated.cwd_override = thread.cwd_override.as_deref().and_then(|cwd_override| {
+        let relative = Path::new(cwd_override).strip_prefix(&thread.cwd).ok()?;
+        let rebased = Path::new(target_path).join(relative);
+        rebased
+            .is_dir()
+            .then(|| rebased.to_string_lossy().to_string())
+    });
+    relocated.cwd = target_path.to_string();
+    if !relocated.turns.is_empty() {
+        relocated.session_id = Some(Uuid::new_v4().to_string());
+        relocated.session_needs_seed = true;
+        relocated.provider_session_id = None;
+    }
+    relocated.updated_at = timestamp;
+    relocated
+}
+
+async fn relocate_thread_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_from: String,
+    workspace_to: String,
+    thread_id: String,
+    copy: bool,
+    event_sink: E,
+) -> Result<Value, String> {
+    if workspace_from == workspace_to {
+        return Err("The thread is already in this workspace.".to_string());
+    }
+    let (target, _parent_entry, _settings) =
+        ensure_workspace_provider_is_claude(workspaces, app_settings, &workspace_to).await?;
+    let relocated = {
+        let mut store = claude_threads.lock().await;
+        let threads = store
+            .get_mut(&workspace_from)
+            .ok_or_else(|| "thread not found".to_string())?;
+        let index = threads
+            .iter()
+            .position(|thread| thread.id == thread_id)
+            .ok_or_else(|| "thread not found".to_string())?;
+        let original = &threads[index];
+        if original
+            .turns
+            .last()
+            .is_some_and(|turn| turn.completed_at.is_none())
+        {
+            return Err(
+                "Wait for the running turn to finish before moving the thread.".to_string(),
             );
-            emit(
-                &event_sink_clone,
-                &workspace_id_for_task,
-                "turn/completed",
-                json!({
-                    "threadId": thread_id_for_task,
-                    "turn": { "id": turn_id_for_task, "threadId": thread_id_for_task },
-                }),
+        }
+        if original.worktree.is_some() {
+            return Err(
+                "Merge or discard the thread's worktree before moving the thread.".to_string(),
             );
         }
-
-        let mut cancels = claude_turn_cancels_clone.lock().await;
-        cancels.remove(&key);
-    });
-
+        let relocated = relocate_thread_record(original, &target.path, copy, now_ms());
+        if !copy {
+            threads.remove(index);
+        }
+        store
+            .entry(workspace_to.clone())
+            .or_default()
+            .insert(0, relocated.clone());
+        relocated
+    };
+    if !copy {
+        // Keeps history import from bringing the thread back into the source workspace.
+        persist_archived_thread_ids_for_workspace(
+            claude_threads_path,
+            &workspace_from,
+            std::slice::from_ref(&thread_id),
+        )?;
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    if !copy {
+        emit_thread_list_updated(
+            &event_sink,
+            claude_threads,
+            &workspace_from,
+            &[],
+            std::slice::from_ref(&thread_id),
+        )
+        .await;
+    }
+    emit(
+        &event_sink,
+        &workspace_to,
+        "thread/started",
+        json!({
+            "thread": thread_summary(&relocated),
+        }),
+    );
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_to,
+        std::slice::from_ref(&relocated.id),
+        &[],
+    )
+    .await;
     Ok(json!({
         "result": {
-            "turn": { "id": turn_id, "threadId": thread_id }
+            "sourceWorkspaceId": workspace_from,
+            "sourceThreadId": thread_id,
+            "workspaceId": workspace_to,
+            "thread": thread_summary(&relocated),
         }
     }))
 }
 
-pub(crate) async fn turn_interrupt_core(
-    claude_turn_cancels: &ClaudeTurnCancelsStore,
-    workspace_id: String,
+/// Moves a thread to another workspace, e.g. one started in the wrong worktree.
+pub(crate) async fn move_thread_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_from: String,
+    workspace_to: String,
     thread_id: String,
+    event_sink: E,
 ) -> Result<Value, String> {
-    let key = cancel_key(&workspace_id, &thread_id);
-    let cancel = {
-        let mut cancels = claude_turn_cancels.lock().await;
-        cancels.remove(&key)
+    relocate_thread_core(
+        workspaces,
+        app_settings,
+        claude_threads,
+        claude_threads_path,
+        workspace_from,
+        workspace_to,
+        thread_id,
+        false,
+        event_sink,
+    )
+    .await
+}
+
+/// Copies a thread into another workspace, e.g. to continue it in a new branch's worktree.
+pub(crate) async fn copy_thread_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_from: String,
+    workspace_to: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    relocate_thread_core(
+        workspaces,
+        app_settings,
+        claude_threads,
+        claude_threads_path,
+        workspace_from,
+        workspace_to,
+        thread_id,
+        true,
+        event_sink,
+    )
+    .await
+}
+
+/// Resolves a requested thread cwd (absolute or workspace-relative) and keeps it inside the
+/// workspace. `None` and the workspace root itself clear the override; a blank path is rejected.
+fn resolve_thread_cwd(
+    workspace_path: &str,
+    requested: Option<&str>,
+) -> Result<Option<String>, String> {
+    let Some(requested) = requested else {
+        return Ok(None);
     };
-    if let Some(cancel) = cancel {
-        let _ = cancel.send(());
+    let requested = requested.trim();
+    if requested.is_empty() {
+        return Err("thread cwd is empty; pass null to clear it".to_string());
     }
-    Ok(json!({ "result": { "ok": true } }))
+    let workspace_root = Path::new(workspace_path)
+        .canonicalize()
+        .map_err(|error| format!("workspace path is unavailable: {error}"))?;
+    let requested_path = Path::new(requested);
+    let candidate = if requested_path.is_absolute() {
+        requested_path.to_path_buf()
+    } else {
+        workspace_root.join(requested_path)
+    };
+    let resolved = candidate
+        .canonicalize()
+        .map_err(|_| format!("directory not found: {}", candidate.display()))?;
+    if !resolved.is_dir() {
+        return Err(format!("not a directory: {}", resolved.display()));
+    }
+    if !resolved.starts_with(&workspace_root) {
+        return Err("thread cwd must be inside the workspace".to_string());
+    }
+    Ok((resolved != workspace_root).then(|| resolved.to_string_lossy().to_string()))
 }
 
-pub(crate) async fn archive_thread_core(
+/// Claude keys sessions by cwd, so moving a thread that already has turns starts a fresh
+/// session seeded from the stored transcript.
+pub(crate) async fn set_thread_cwd_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     claude_threads: &ClaudeThreadsStore,
     claude_threads_path: &Path,
     workspace_id: String,
     thread_id: String,
+    cwd: Option<String>,
+    event_sink: E,
 ) -> Result<Value, String> {
-    persist_archived_thread_id_for_workspace(claude_threads_path, &workspace_id, &thread_id)?;
+    let workspace_path = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .map(|entry| entry.path.clone())
+            .ok_or_else(|| "workspace not found".to_string())?
+    };
+    let cwd_override = resolve_thread_cwd(&workspace_path, cwd.as_deref())?;
     let mut store = claude_threads.lock().await;
-    if let Some(threads) = store.get_mut(&workspace_id) {
-        threads.retain(|thread| thread.id != thread_id);
+    let thread = store
+        .get_mut(&workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        .ok_or_else(|| "thread not found".to_string())?;
+    if thread.worktree.is_some() && cwd_override.is_some() {
+        return Err("thread runs in its own worktree, so its cwd cannot be overridden".to_string());
+    }
+    if thread.cwd_override != cwd_override {
+        if !thread.turns.is_empty() {
+            thread.session_id = Some(Uuid::new_v4().to_string());
+            thread.session_needs_seed = true;
+        }
+        thread.cwd_override = cwd_override.clone();
+        thread.updated_at = now_ms();
     }
     drop(store);
     persist_threads_store(claude_threads, claude_threads_path).await?;
-    Ok(json!({ "result": { "ok": true } }))
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "cwd": cwd_override.clone().unwrap_or(workspace_path),
+            "cwdOverride": cwd_override,
+        }
+    }))
 }
 
-pub(crate) async fn set_thread_name_core(
+/// Replaces the thread's generation settings; every later turn, including after a restart,
+/// runs with them. Settings the CLI cannot apply are kept and listed as `unsupported`.
+pub(crate) async fn set_thread_generation_core<E: EventSink>(
     claude_threads: &ClaudeThreadsStore,
     claude_threads_path: &Path,
     workspace_id: String,
     thread_id: String,
-    name: String,
+    model: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    event_sink: E,
 ) -> Result<Value, String> {
+    if temperature.is_some_and(|value| !(0.0..=2.0).contains(&value)) {
+        return Err("temperature must be between 0 and 2".to_string());
+    }
+    if max_tokens == Some(0) {
+        return Err("maxTokens must be greater than 0".to_string());
+    }
+    let generation = GenerationParams {
+        model: model
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty()),
+        temperature,
+        max_tokens,
+    };
     let mut store = claude_threads.lock().await;
-    let threads = store
+    let thread = store
         .get_mut(&workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
         .ok_or_else(|| "thread not found".to_string())?;
-    let thread = threads
-        .iter_mut()
-        .find(|thread| thread.id == thread_id)
-        .ok_or_else(|| "thread not found".to_string())?;
-    let trimmed = name.trim().to_string();
-    thread.name = if trimmed.is_empty() {
-        None
-    } else {
-        Some(trimmed.clone())
-    };
-    thread.updated_at = now_ms();
-    let thread_name = thread.name.clone();
+    if thread.generation != generation {
+        thread.generation = generation.clone();
+        thread.updated_at = now_ms();
+    }
     drop(store);
     persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
     Ok(json!({
         "result": {
             "threadId": thread_id,
-            "threadName": thread_name,
+            "generation": generation,
+            "unsupported": unsupported_generation_params(&generation),
         }
     }))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{is_debug_jsonrpc_line, is_debug_jsonrpc_message};
+    use super::{
+        accepted_plan, attempt_idle_timeout, build_session_seed, claude_projects_root_from,
+        dry_run_env, duplicate_thread_record, encode_workspace_for_claude_projects,
+        generation_args, heartbeat_payload, is_debug_jsonrpc_line, is_debug_jsonrpc_message,
+        is_fallback_failure, is_transient_failure, migrate_legacy_session, pooled_process_args,
+        prompt_arg, relocate_thread_record, requested_thread_ids, resolve_session_ids,
+        resolve_thread_cwd, retry_delay, send_user_message_core, session_content_is_resumable,
+        take_thread_cancels, turn_args, unread_turn_count, BulkThreadOp, ClaudeMessageRecord,
+        ClaudeThreadRecord, ClaudeTurnRecord, GenerationParams, StreamProgress, TurnCancel,
+        SESSION_SEED_MAX_CHARS, STDIN_PROMPT_THRESHOLD_BYTES,
+    };
+    use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+    use crate::types::{AppSettings, WorkspaceEntry};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::{oneshot, Mutex};
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(Arc<std::sync::Mutex<Vec<Value>>>);
+
+    impl EventSink for RecordingSink {
+        fn emit_app_server_event(&self, event: AppServerEvent) {
+            self.0.lock().expect("events").push(event.message);
+        }
+        fn emit_terminal_output(&self, _event: TerminalOutput) {}
+        fn emit_terminal_exit(&self, _event: TerminalExit) {}
+    }
 
     #[test]
     fn detects_prefixed_jsonrpc_debug_line() {
@@ -1595,7 +5038,782 @@ mod tests {
 
     #[test]
     fn detects_multiline_jsonrpc_debug_message() {
-        let message = "app-server\n{\"id\":1,\"method\":\"initialize\",\"params\":{\"foo\":\"bar\"}}";
+        let message =
+            "app-server\n{\"id\":1,\"method\":\"initialize\",\"params\":{\"foo\":\"bar\"}}";
         assert!(is_debug_jsonrpc_message(message));
     }
+
+    #[test]
+    fn transient_failures_back_off_exponentially() {
+        assert!(is_transient_failure("API Error: 529 Overloaded"));
+        assert!(is_transient_failure("API Error: 503 Service Unavailable"));
+        assert!(is_transient_failure("Rate limit reached, try later"));
+        assert!(!is_transient_failure("Invalid API key"));
+        assert!(!is_transient_failure(
+            "Renamed 429 files; the build broke at line 503"
+        ));
+        assert_eq!(retry_delay(1000, 1).as_millis(), 1000);
+        assert_eq!(retry_delay(1000, 3).as_millis(), 4000);
+        assert_eq!(retry_delay(1000, 12).as_millis(), 60_000);
+    }
+
+    #[test]
+    fn guarded_retries_stay_on_the_permission_prompt_process() {
+        let warm = Some(std::time::Duration::ZERO);
+        assert_eq!(attempt_idle_timeout(warm, false, 0), warm);
+        assert_eq!(attempt_idle_timeout(warm, false, 1), None);
+        assert_eq!(attempt_idle_timeout(warm, true, 2), warm);
+
+        let args = pooled_process_args(true, true, true);
+        let has = |needle: &[&str]| args.windows(needle.len()).any(|window| window == needle);
+        assert!(has(&["--permission-prompt-tool", "stdio"]));
+        assert!(has(&["--permission-mode", "plan"]));
+        assert!(args.iter().any(|arg| arg.contains("\"ask\"")));
+        assert!(!pooled_process_args(false, false, false)
+            .iter()
+            .any(|arg| arg == "--permission-prompt-tool"));
+    }
+
+    #[test]
+    fn missing_clis_and_rejected_credentials_fall_back() {
+        let falls_back = |output| is_fallback_failure(Some(1), output);
+        assert!(is_fallback_failure(Some(127), ""));
+        assert!(falls_back("zsh:1: command not found: claude"));
+        assert!(falls_back("Invalid API key · Please run /login"));
+        assert!(!falls_back("API Error: 529 Overloaded"));
+        assert!(!is_fallback_failure(None, "Claude exited."));
+    }
+
+    #[test]
+    fn resolve_thread_cwd_stays_inside_workspace() {
+        let root = std::env::temp_dir().join(format!("thread-cwd-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("packages/api")).expect("create dirs");
+        let workspace = root.to_string_lossy().to_string();
+        let resolved = resolve_thread_cwd(&workspace, Some("packages/api"))
+            .expect("resolve")
+            .expect("override");
+        assert!(resolved.ends_with("packages/api"));
+        assert_eq!(resolve_thread_cwd(&workspace, Some(".")), Ok(None));
+        assert_eq!(resolve_thread_cwd(&workspace, None), Ok(None));
+        assert!(resolve_thread_cwd(&workspace, Some("packages/missing")).is_err());
+        assert!(resolve_thread_cwd(&workspace, Some("..")).is_err());
+        assert!(resolve_thread_cwd(&workspace, Some("   ")).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn session_content_requires_complete_records() {
+        let complete = "{\"type\":\"user\",\"message\":{\"content\":\"hi\"}}\n";
+        assert!(session_content_is_resumable(complete));
+        assert!(!session_content_is_resumable(""));
+        assert!(!session_content_is_resumable(
+            &complete[..complete.len() - 1]
+        ));
+        assert!(!session_content_is_resumable(&format!(
+            "{complete}{{\"type\":\"assis\n"
+        )));
+        assert!(!session_content_is_resumable("{\"type\":\"summary\"}\n"));
+    }
+
+    #[test]
+    fn session_seed_replays_turns_in_order() {
+        let turn = ClaudeTurnRecord {
+            id: "turn-1".to_string(),
+            started_at: 0,
+            completed_at: Some(1),
+            items: vec![
+                ClaudeMessageRecord {
+                    id: "u".to_string(),
+                    role: "user".to_string(),
+                    text: "Question".to_string(),
+                    pinned: false,
+                },
+                ClaudeMessageRecord {
+                    id: "a".to_string(),
+                    role: "assistant".to_string(),
+                    text: "Answer".to_string(),
+                    pinned: false,
+                },
+            ],
+            context_files: Vec::new(),
+            cli_version: None,
+            duration_ms: None,
+            snapshot: None,
+            plan: false,
+            suggestions: Vec::new(),
+            checklist: Vec::new(),
+            answered_by: None,
+            model_downgrade: None,
+            request: None,
+        };
+        let seed = build_session_seed(std::slice::from_ref(&turn));
+        assert!(seed.contains("User: Question\n\nAssistant: Answer"));
+        assert!(!seed.contains("pinned"));
+        assert!(build_session_seed(&[]).is_empty());
+
+        let mut early = turn.clone();
+        early.items[0].text = "Keep the public API stable".to_string();
+        early.items[0].pinned = true;
+        let mut long = turn;
+        long.items[1].text = "x".repeat(SESSION_SEED_MAX_CHARS - 20);
+        let seed = build_session_seed(&[early, long]);
+        let pinned_at = seed.find("User: Keep the public API").expect("pinned");
+        assert!(pinned_at < seed.find("Conversation so far").expect("transcript"));
+    }
+
+    #[test]
+    fn accepted_plan_needs_a_finished_plan_turn() {
+        let mut thread: super::ClaudeThreadRecord = serde_json::from_value(serde_json::json!({
+            "id": "thread-1",
+            "cwd": "/repo",
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 2,
+            "turns": [
+                {
+                    "id": "turn-1",
+                    "startedAt": 1,
+                    "completedAt": 2,
+                    "plan": true,
+                    "items": [
+                        { "id": "u1", "role": "user", "text": "Add caching" },
+                        { "id": "a1", "role": "assistant", "text": " 1. Add a cache\n" },
+                    ],
+                },
+                {
+                    "id": "turn-2",
+                    "startedAt": 3,
+                    "completedAt": 4,
+                    "items": [{ "id": "u2", "role": "user", "text": "Looks good" }],
+                },
+            ],
+        }))
+        .expect("thread");
+        assert_eq!(
+            accepted_plan(&thread, Some("turn-1")),
+            Ok("1. Add a cache".to_string())
+        );
+        assert!(accepted_plan(&thread, None).is_err());
+        assert!(accepted_plan(&thread, Some("missing")).is_err());
+
+        thread.turns[0].completed_at = None;
+        assert!(accepted_plan(&thread, Some("turn-1")).is_err());
+        let regular_turn = serde_json::to_value(&thread.turns[1]).expect("turn");
+        assert!(regular_turn.get("plan").is_none());
+    }
+
+    #[test]
+    fn stream_progress_snapshot_reports_totals() {
+        let mut progress = StreamProgress::new();
+        progress.record("hello");
+        progress.record("\nwörld");
+        let snapshot = progress.snapshot("thread-1", "turn-1");
+        assert_eq!(snapshot["deltas"], 2);
+        assert_eq!(snapshot["chars"], 11);
+        assert_eq!(snapshot["turnId"], "turn-1");
+        assert_eq!(snapshot["stalled"], false);
+        assert!(!progress.is_due());
+    }
+
+    #[test]
+    fn turn_duration_is_optional_for_stored_turns() {
+        let turn: ClaudeTurnRecord = serde_json::from_value(serde_json::json!({
+            "id": "claude-turn-1",
+            "startedAt": 1,
+            "completedAt": 2,
+            "items": [],
+        }))
+        .expect("legacy turn");
+        assert!(turn.duration_ms.is_none());
+        let payload = heartbeat_payload("thread-1", "turn-1", 5000, 42);
+        assert_eq!(payload["elapsedMs"], 5000);
+        assert_eq!(payload["bytesReceived"], 42);
+    }
+
+    #[test]
+    fn unread_turns_are_those_finished_after_the_last_read() {
+        let mut thread: super::ClaudeThreadRecord = serde_json::from_value(serde_json::json!({
+            "id": "thread-1",
+            "cwd": "/repo",
+            "preview": "Fix the build",
+            "createdAt": 1,
+            "updatedAt": 30,
+            "turns": [
+                { "id": "turn-1", "startedAt": 1, "completedAt": 10, "items": [] },
+                { "id": "turn-2", "startedAt": 15, "completedAt": 20, "items": [] },
+                { "id": "turn-3", "startedAt": 25, "completedAt": null, "items": [] },
+            ],
+        }))
+        .expect("thread");
+        assert_eq!(unread_turn_count(&thread), 0);
+        thread.last_read_at = Some(12);
+        assert_eq!(unread_turn_count(&thread), 1);
+        thread.last_read_at = Some(20);
+        assert_eq!(unread_turn_count(&thread), 0);
+    }
+
+    #[test]
+    fn bulk_thread_op_parses_and_normalizes_tags() {
+        assert_eq!(
+            BulkThreadOp::parse("archive", None),
+            Ok(BulkThreadOp::Archive)
+        );
+        assert_eq!(
+            BulkThreadOp::parse(
+                "tag",
+                Some(vec![" a ".into(), "a".into(), "".into(), "b".into()])
+            ),
+            Ok(BulkThreadOp::Tag(vec!["a".to_string(), "b".to_string()]))
+        );
+        assert!(BulkThreadOp::parse("tag", None).is_err());
+        assert!(BulkThreadOp::parse("explode", None).is_err());
+        assert!(BulkThreadOp::parse("  ", None).is_err());
+        assert!(BulkThreadOp::parse("archive", Some(vec!["a".into()])).is_err());
+        assert_eq!(
+            BulkThreadOp::parse("export", Some(Vec::new())),
+            Ok(BulkThreadOp::Export)
+        );
+    }
+
+    #[test]
+    fn bulk_thread_op_rejects_empty_thread_ids() {
+        assert!(requested_thread_ids(Vec::new()).is_err());
+        assert!(requested_thread_ids(vec![" ".into(), String::new()]).is_err());
+        assert_eq!(
+            requested_thread_ids(vec![" t1 ".into(), "t1".into(), "t2".into()]),
+            Ok(vec!["t1".to_string(), "t2".to_string()])
+        );
+    }
+
+    #[test]
+    fn turn_args_switch_to_resume_once_session_exists() {
+        assert_eq!(
+            turn_args(Some("hi"), Some("s1"), None, false),
+            vec![
+                "-p",
+                "hi",
+                "--output-format",
+                "stream-json",
+                "--verbose",
+                "--include-partial-messages",
+                "--session-id",
+                "s1",
+            ]
+        );
+        assert_eq!(
+            turn_args(Some("hi"), Some("s1"), None, true)[6..],
+            ["--resume", "s1"]
+        );
+        assert_eq!(
+            turn_args(Some("hi"), None, Some("t1"), false)[6..],
+            ["--resume", "t1"]
+        );
+        assert_eq!(turn_args(Some("hi"), None, None, false).len(), 6);
+    }
+
+    #[test]
+    fn legacy_thread_ids_become_recorded_sessions() {
+        let session = "3f1c2b9e-8d4a-4f5e-9b6c-1a2b3c4d5e6f";
+        let thread = |turns: serde_json::Value| {
+            serde_json::from_value::<super::ClaudeThreadRecord>(serde_json::json!({
+                "id": format!("claude-thread-{session}"),
+                "cwd": "/repo",
+                "preview": "",
+                "createdAt": 1,
+                "updatedAt": 1,
+                "turns": turns,
+            }))
+            .expect("thread")
+        };
+        let mut ran = thread(serde_json::json!([
+            { "id": "t1", "startedAt": 1, "completedAt": 2, "items": [] }
+        ]));
+        migrate_legacy_session(&mut ran);
+        assert_eq!(ran.provider_session_id.as_deref(), Some(session));
+        assert_eq!(
+            resolve_session_ids(&ran.id, true, ran.provider_session_id.as_deref(), false),
+            (None, Some(session.to_string()))
+        );
+
+        let mut fresh = thread(serde_json::json!([]));
+        migrate_legacy_session(&mut fresh);
+        assert_eq!(fresh.provider_session_id, None);
+        assert!(fresh.session_needs_seed);
+        assert_eq!(
+            resolve_session_ids(&fresh.id, false, fresh.session_id.as_deref(), true),
+            (Some(session.to_string()), None)
+        );
+    }
+
+    #[test]
+    fn large_prompts_move_from_argv_to_stdin() {
+        let large = "x".repeat(STDIN_PROMPT_THRESHOLD_BYTES);
+        assert_eq!(prompt_arg("hi"), Some("hi"));
+        assert_eq!(prompt_arg(&large), None);
+        assert_eq!(
+            turn_args(None, None, None, false),
+            vec![
+                "-p",
+                "--output-format",
+                "stream-json",
+                "--verbose",
+                "--include-partial-messages",
+            ]
+        );
+    }
+
+    #[test]
+    fn generation_params_round_trip_and_map_to_cli_flags() {
+        let generation = GenerationParams {
+            model: Some(" sonnet ".to_string()),
+            temperature: Some(0.2),
+            max_tokens: Some(4096),
+        };
+        assert_eq!(generation_args(&generation), vec!["--model", "sonnet"]);
+        assert!(generation_args(&GenerationParams::default()).is_empty());
+        let stored = serde_json::to_value(&generation).expect("serialize");
+        assert_eq!(stored["maxTokens"], 4096);
+        let restored: GenerationParams = serde_json::from_value(stored).expect("deserialize");
+        assert_eq!(restored, generation);
+        assert!(GenerationParams::default().is_empty());
+    }
+
+    #[test]
+    fn dry_run_env_masks_credentials() {
+        let env = dry_run_env(
+            [
+                ("PATH", "/usr/bin"),
+                ("ANTHROPIC_API_KEY", "sk-secret"),
+                ("ANTHROPIC_MODEL", "opus"),
+                ("EDITOR", "vim"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        assert_eq!(env["PATH"], "/usr/bin");
+        assert_eq!(env["ANTHROPIC_API_KEY"], "<redacted>");
+        assert_eq!(env["ANTHROPIC_MODEL"], "opus");
+        assert!(!env.contains_key("EDITOR"));
+    }
+
+    #[test]
+    fn duplicate_thread_record_uses_fresh_ids_and_seeds_a_new_session() {
+        let original = super::ClaudeThreadRecord {
+            id: "thread-1".to_string(),
+            cwd: "/repo".to_string(),
+            preview: "Fix the build".to_string(),
+            created_at: 1,
+            updated_at: 2,
+            name: None,
+            tags: vec!["bug".to_string()],
+            session_id: None,
+            session_needs_seed: false,
+            provider_session_id: None,
+            cwd_override: None,
+            generation: super::GenerationParams::default(),
+            worktree: None,
+            summary: None,
+            stats: None,
+            last_read_at: None,
+            turns: vec![ClaudeTurnRecord {
+                id: "claude-turn-1".to_string(),
+                started_at: 1,
+                completed_at: Some(2),
+                items: vec![
+                    ClaudeMessageRecord {
+                        id: "claude-user-1".to_string(),
+                        role: "user".to_string(),
+                        text: "Fix the build".to_string(),
+                        pinned: false,
+                    },
+                    ClaudeMessageRecord {
+                        id: "claude-assistant-1".to_string(),
+                        role: "assistant".to_string(),
+                        text: "Done".to_string(),
+                        pinned: false,
+                    },
+                ],
+                context_files: Vec::new(),
+                cli_version: None,
+                duration_ms: None,
+                snapshot: None,
+                plan: false,
+                suggestions: Vec::new(),
+                checklist: Vec::new(),
+                answered_by: None,
+                model_downgrade: None,
+                request: None,
+            }],
+        };
+        let copy = duplicate_thread_record(&original, 10);
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.name.as_deref(), Some("Fix the build (copy)"));
+        assert_eq!(copy.tags, original.tags);
+        assert!(copy.session_needs_seed);
+        assert!(copy.session_id.is_some());
+        assert_ne!(copy.turns[0].id, original.turns[0].id);
+        assert!(copy.turns[0].items[0].id.starts_with("claude-user-"));
+        assert!(copy.turns[0].items[1].id.starts_with("claude-assistant-"));
+        assert_eq!(copy.turns[0].items[1].text, "Done");
+        assert_eq!(copy.created_at, 10);
+    }
+
+    #[test]
+    fn relocate_thread_record_rebases_cwd_and_starts_a_new_session() {
+        let source = std::env::temp_dir().join(format!("relocate-src-{}", uuid::Uuid::new_v4()));
+        let target = std::env::temp_dir().join(format!("relocate-dst-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(source.join("app")).expect("source dir");
+        std::fs::create_dir_all(target.join("app")).expect("target dir");
+        let source_path = source.to_string_lossy().to_string();
+        let target_path = target.to_string_lossy().to_string();
+        let original = super::ClaudeThreadRecord {
+            id: "thread-1".to_string(),
+            cwd: source_path.clone(),
+            preview: "Fix the build".to_string(),
+            created_at: 1,
+            updated_at: 2,
+            name: Some("Build".to_string()),
+            tags: Vec::new(),
+            session_id: None,
+            session_needs_seed: false,
+            provider_session_id: Some("session-1".to_string()),
+            cwd_override: Some(source.join("app").to_string_lossy().to_string()),
+            generation: super::GenerationParams::default(),
+            worktree: None,
+            summary: None,
+            stats: None,
+            last_read_at: None,
+            turns: vec![ClaudeTurnRecord {
+                id: "claude-turn-1".to_string(),
+                started_at: 1,
+                completed_at: Some(2),
+                items: Vec::new(),
+                context_files: Vec::new(),
+                cli_version: None,
+                duration_ms: None,
+                snapshot: None,
+                plan: false,
+                suggestions: Vec::new(),
+                checklist: Vec::new(),
+                answered_by: None,
+                model_downgrade: None,
+                request: None,
+            }],
+        };
+
+        let moved = relocate_thread_record(&original, &target_path, false, 10);
+        assert_eq!(moved.id, "thread-1");
+        assert_eq!(moved.cwd, target_path);
+        let expected_override = target.join("app").to_string_lossy().to_string();
+        assert_eq!(moved.cwd_override, Some(expected_override));
+        assert!(moved.session_needs_seed && moved.session_id.is_some());
+        assert_eq!(moved.provider_session_id, None);
+        assert_eq!(moved.turns[0].id, "claude-turn-1");
+
+        let copied = relocate_thread_record(&original, &target_path, true, 10);
+        assert_ne!(copied.id, original.id);
+        assert_ne!(copied.turns[0].id, original.turns[0].id);
+        assert_eq!(copied.name.as_deref(), Some("Build"));
+
+        std::fs::remove_dir_all(target.join("app")).expect("remove target dir");
+        let moved = relocate_thread_record(&original, &target_path, false, 10);
+        assert_eq!(moved.cwd_override, None);
+        let _ = std::fs::remove_dir_all(&source);
+        let _ = std::fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn encodes_windows_and_unix_paths_like_claude() {
+        let cases = [
+            (r"C:\Users\dev\my-app", "C--Users-dev-my-app"),
+            (r"\\?\C:\Users\dev\app", "C--Users-dev-app"),
+            (r"D:\work\repo\", "D--work-repo"),
+            (r"\\?\UNC\server\share\repo", "--server-share-repo"),
+            (r"\\server\share\repo", "--server-share-repo"),
+            ("/Users/dev/app.v2", "-Users-dev-app-v2"),
+            ("/home/dev/.config/tool", "-home-dev--config-tool"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                encode_workspace_for_claude_projects(path).as_deref(),
+                Some(expected),
+                "{path}"
+            );
+        }
+        assert!(encode_workspace_for_claude_projects("/").is_none());
+    }
+
+    #[test]
+    fn claude_projects_root_prefers_userprofile_on_windows() {
+        use std::ffi::OsString;
+        use std::path::PathBuf;
+        let env = |name: &str| match name {
+            "HOME" => Some(OsString::from("/c/Users/dev")),
+            "USERPROFILE" => Some(OsString::from(r"C:\Users\dev")),
+            _ => None,
+        };
+        assert_eq!(
+            claude_projects_root_from(env, true),
+            Some(
+                PathBuf::from(r"C:\Users\dev")
+                    .join(".claude")
+                    .join("projects")
+            )
+        );
+        assert_eq!(
+            claude_projects_root_from(env, false),
+            Some(PathBuf::from("/c/Users/dev/.claude/projects"))
+        );
+        let configured =
+            |name: &str| (name == "CLAUDE_CONFIG_DIR").then(|| OsString::from("/opt/claude"));
+        assert_eq!(
+            claude_projects_root_from(configured, false),
+            Some(PathBuf::from("/opt/claude/projects"))
+        );
+    }
+
+    #[test]
+    fn take_thread_cancels_only_removes_that_threads_turns() {
+        let mut cancels = HashMap::new();
+        for (turn_id, thread_id) in [("turn-a", "thread-1"), ("turn-b", "thread-2")] {
+            let (cancel, _rx) = oneshot::channel();
+            cancels.insert(
+                turn_id.to_string(),
+                TurnCancel {
+                    workspace_id: "ws".to_string(),
+                    thread_id: thread_id.to_string(),
+                    cancel,
+                },
+            );
+        }
+        let taken = take_thread_cancels(&mut cancels, "ws", "thread-1");
+        assert_eq!(taken.len(), 1);
+        assert!(cancels.contains_key("turn-b"));
+        assert!(!cancels.contains_key("turn-a"));
+    }
+
+    #[test]
+    fn a_message_sent_while_a_turn_runs_is_refused_without_cancelling_it() {
+        let root = std::env::temp_dir().join(format!("busy-thread-{}", uuid::Uuid::new_v4()));
+        let workspace_path = root.to_string_lossy().to_string();
+        let entry: WorkspaceEntry = serde_json::from_value(json!({
+            "id": "ws",
+            "name": "ws",
+            "path": workspace_path,
+            "settings": {},
+        }))
+        .expect("workspace");
+        let thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": "thread-1",
+            "cwd": workspace_path,
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 1,
+            "turns": [],
+        }))
+        .expect("thread");
+        let workspaces = Mutex::new(HashMap::from([("ws".to_string(), entry)]));
+        let claude_threads = Arc::new(Mutex::new(HashMap::from([(
+            "ws".to_string(),
+            vec![thread],
+        )])));
+        let (cancel, mut running) = oneshot::channel();
+        let claude_turn_cancels = Arc::new(Mutex::new(HashMap::from([(
+            "turn-running".to_string(),
+            TurnCancel {
+                workspace_id: "ws".to_string(),
+                thread_id: "thread-1".to_string(),
+                cancel,
+            },
+        )])));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let error = runtime
+            .block_on(send_user_message_core(
+                &workspaces,
+                &Mutex::new(AppSettings::default()),
+                &claude_threads,
+                &claude_turn_cancels,
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &root.join("claude_threads.json"),
+                "ws".to_string(),
+                "thread-1".to_string(),
+                "hello".to_string(),
+                None,
+                false,
+                false,
+                RecordingSink::default(),
+            ))
+            .expect_err("busy");
+        assert!(error.starts_with("Busy:"));
+        assert!(running.try_recv().is_err());
+        assert!(claude_turn_cancels
+            .blocking_lock()
+            .contains_key("turn-running"));
+        assert!(claude_threads.blocking_lock()["ws"][0].turns.is_empty());
+    }
+
+    #[test]
+    fn a_turn_whose_cli_fails_to_start_is_still_finalized() {
+        let root = std::env::temp_dir().join(format!("spawn-failure-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let workspace_path = root.to_string_lossy().to_string();
+        let entry: WorkspaceEntry = serde_json::from_value(json!({
+            "id": "ws",
+            "name": "ws",
+            "path": workspace_path,
+            "settings": { "claudeBin": root.join("missing-claude").to_string_lossy() },
+        }))
+        .expect("workspace");
+        let thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "cwd": workspace_path,
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 1,
+            "turns": [],
+        }))
+        .expect("thread");
+        let thread_id = thread.id.clone();
+        let workspaces = Mutex::new(HashMap::from([("ws".to_string(), entry)]));
+        let app_settings = Mutex::new(AppSettings::default());
+        let claude_threads = Arc::new(Mutex::new(HashMap::from([(
+            "ws".to_string(),
+            vec![thread],
+        )])));
+        let claude_turn_cancels = Arc::new(Mutex::new(HashMap::new()));
+        let sink = RecordingSink::default();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            send_user_message_core(
+                &workspaces,
+                &app_settings,
+                &claude_threads,
+                &claude_turn_cancels,
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &root.join("claude_threads.json"),
+                "ws".to_string(),
+                thread_id.clone(),
+                "hello".to_string(),
+                None,
+                false,
+                false,
+                sink.clone(),
+            )
+            .await
+            .expect("send");
+            let completed = |events: &[Value]| {
+                events
+                    .iter()
+                    .any(|event| event["method"] == "turn/completed")
+            };
+            // The turn counts as running until its cancel handle is gone.
+            for _ in 0..500 {
+                let done = completed(&sink.0.lock().expect("events"));
+                if done && claude_turn_cancels.lock().await.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            let events = sink.0.lock().expect("events").clone();
+            assert!(completed(&events));
+            assert!(events.iter().any(|event| event["method"] == "error"));
+            assert!(claude_turn_cancels.lock().await.is_empty());
+            let store = claude_threads.lock().await;
+            let thread = &store["ws"][0];
+            assert_eq!(thread.turns.len(), 1);
+            assert!(!thread.has_open_turn());
+        });
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn dry_run_shows_the_shell_wrapped_pooled_command() {
+        let root = std::env::temp_dir().join(format!("dry-run-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let workspace_path = root.to_string_lossy().to_string();
+        let entry: WorkspaceEntry = serde_json::from_value(json!({
+            "id": "ws",
+            "name": "ws",
+            "path": workspace_path,
+            "settings": {},
+        }))
+        .expect("workspace");
+        let thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "cwd": workspace_path,
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 1,
+            "turns": [],
+        }))
+        .expect("thread");
+        let thread_id = thread.id.clone();
+        let workspaces = Mutex::new(HashMap::from([("ws".to_string(), entry)]));
+        let app_settings = Mutex::new(AppSettings {
+            launch_shell: Some("/bin/zsh".to_string()),
+            claude_command_approvals: true,
+            ..AppSettings::default()
+        });
+        let claude_threads = Arc::new(Mutex::new(HashMap::from([(
+            "ws".to_string(),
+            vec![thread],
+        )])));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let preview = runtime
+            .block_on(send_user_message_core(
+                &workspaces,
+                &app_settings,
+                &claude_threads,
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &root.join("claude_threads.json"),
+                "ws".to_string(),
+                thread_id,
+                "hello".to_string(),
+                None,
+                true,
+                false,
+                RecordingSink::default(),
+            ))
+            .expect("dry run");
+        let result = &preview["result"];
+        assert_eq!(result["program"], "/bin/zsh");
+        assert_eq!(result["pooled"], true);
+        assert_eq!(result["promptDelivery"], "stdin");
+        let script = result["args"]
+            .as_array()
+            .and_then(|args| args.last())
+            .and_then(Value::as_str)
+            .expect("shell script");
+        assert!(script.starts_with("exec "));
+        assert!(script.contains("--permission-prompt-tool stdio"));
+        assert!(script.contains("--input-format stream-json"));
+        assert!(!script.contains("hello"));
+        assert!(claude_threads.blocking_lock()["ws"][0].turns.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
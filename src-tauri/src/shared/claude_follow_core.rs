@@ -0,0 +1,580 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    cancel_key, claude_project_dir_for_workspace, emit, extract_message_text,
+    is_debug_jsonrpc_message, legacy_prefixed_session_id, now_ms, persist_threads_store,
+    thread_cwd_override, ClaudeMessageRecord, ClaudeThreadRecord, ClaudeThreadsStore,
+    ClaudeTurnRecord,
+};
+
+pub(crate) type ClaudeFollowsStore = Arc<Mutex<HashMap<String, FollowHandle>>>;
+
+/// A running follow loop: `id` tells it apart from a later follow of the same thread.
+#[derive(Debug)]
+pub(crate) struct FollowHandle {
+    id: Uuid,
+    stop: oneshot::Sender<()>,
+}
+
+const FOLLOW_POLL_INTERVAL_MS: u64 = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FollowedMessage {
+    pub(crate) id: String,
+    pub(crate) role: String,
+    pub(crate) text: String,
+}
+
+/// Splits freshly read bytes into complete lines, keeping any trailing partial line buffered.
+pub(crate) fn drain_complete_lines(buffer: &mut Vec<u8>, chunk: &[u8]) -> Vec<String> {
+    buffer.extend_from_slice(chunk);
+    let Some(last_newline) = buffer.iter().rposition(|byte| *byte == b'\n') else {
+        return Vec::new();
+    };
+    let complete = buffer.drain(..=last_newline).collect::<Vec<_>>();
+    String::from_utf8_lossy(&complete)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub(crate) fn parse_followed_line(line: &str, session_id: &str) -> Option<FollowedMessage> {
+    let record: Value = serde_json::from_str(line).ok()?;
+    if let Some(record_session) = record.get("sessionId").and_then(Value::as_str) {
+        if record_session.trim() != session_id {
+            return None;
+        }
+    }
+    let role = match record.get("type").and_then(Value::as_str) {
+        Some("user") => "user",
+        Some("assistant") => "assistant",
+        _ => return None,
+    };
+    let text = extract_message_text(&record)?;
+    if is_debug_jsonrpc_message(&text) {
+        return None;
+    }
+    let id = record
+        .get("uuid")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    Some(FollowedMessage {
+        id,
+        role: role.to_string(),
+        text,
+    })
+}
+
+/// The session file the CLI writes for a thread: the session it last reported, then a
+/// replacement session, then the thread id itself (or the session id in a legacy prefixed id).
+/// Falls back to the first candidate when none exists yet.
+fn session_file_path(
+    project_dir: &Path,
+    thread: Option<&ClaudeThreadRecord>,
+    thread_id: &str,
+) -> Option<PathBuf> {
+    let candidates = thread
+        .and_then(|thread| thread.provider_session_id.clone())
+        .into_iter()
+        .chain(thread.and_then(|thread| thread.session_id.clone()))
+        .chain(legacy_prefixed_session_id(thread_id))
+        .chain(Some(thread_id.to_string()))
+        .map(|session_id| session_id.trim().to_string())
+        .filter(|session_id| !session_id.is_empty())
+        .map(|session_id| project_dir.join(format!("{session_id}.jsonl")))
+        .collect::<Vec<_>>();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .or_else(|| candidates.first())
+        .cloned()
+}
+
+fn emit_turn_event<E: EventSink>(
+    event_sink: &E,
+    workspace_id: &str,
+    thread_id: &str,
+    method: &str,
+    turn_id: &str,
+) {
+    emit(
+        event_sink,
+        workspace_id,
+        method,
+        json!({
+            "threadId": thread_id,
+            "turn": { "id": turn_id, "threadId": thread_id },
+        }),
+    );
+}
+
+/// Emits a followed message, starting a turn for each user message (and for replies that
+/// arrive before any). Returns the turn the message ended, if it ended one.
+fn emit_followed_message<E: EventSink>(
+    event_sink: &E,
+    workspace_id: &str,
+    thread_id: &str,
+    current_turn: &mut Option<String>,
+    message: &FollowedMessage,
+) -> Option<String> {
+    let completed = if message.role == "user" {
+        current_turn.take()
+    } else {
+        None
+    };
+    if let Some(turn_id) = &completed {
+        emit_turn_event(
+            event_sink,
+            workspace_id,
+            thread_id,
+            "turn/completed",
+            turn_id,
+        );
+    }
+    if current_turn.is_none() {
+        let turn_id = format!("claude-follow-turn-{}", Uuid::new_v4());
+        emit_turn_event(
+            event_sink,
+            workspace_id,
+            thread_id,
+            "turn/started",
+            &turn_id,
+        );
+        *current_turn = Some(turn_id);
+    }
+    if message.role == "user" {
+        emit(
+            event_sink,
+            workspace_id,
+            "item/completed",
+            json!({
+                "threadId": thread_id,
+                "item": {
+                    "id": message.id,
+                    "type": "userMessage",
+                    "content": [{ "type": "text", "text": message.text }],
+                },
+            }),
+        );
+        return completed;
+    }
+    emit(
+        event_sink,
+        workspace_id,
+        "item/completed",
+        json!({
+            "threadId": thread_id,
+            "item": {
+                "id": message.id,
+                "type": "agentMessage",
+                "text": message.text,
+            },
+        }),
+    );
+    completed
+}
+
+/// Stores a followed message in `turn_id`, adding the turn when it is new, so followed turns
+/// survive a reload. A message already stored is skipped.
+fn record_followed_message(
+    thread: &mut ClaudeThreadRecord,
+    turn_id: &str,
+    message: &FollowedMessage,
+    now: i64,
+) {
+    if thread
+        .turns
+        .iter()
+        .any(|turn| turn.items.iter().any(|item| item.id == message.id))
+    {
+        return;
+    }
+    let index = match thread.turns.iter().position(|turn| turn.id == turn_id) {
+        Some(index) => index,
+        None => {
+            thread.turns.push(ClaudeTurnRecord {
+                id: turn_id.to_string(),
+                started_at: now,
+                completed_at: None,
+                items: Vec::new(),
+                context_files: Vec::new(),
+                cli_version: None,
+                duration_ms: None,
+                snapshot: None,
+                plan: false,
+                suggestions: Vec::new(),
+                checklist: Vec::new(),
+                answered_by: None,
+                model_downgrade: None,
+                request: None,
+            });
+            thread.turns.len() - 1
+        }
+    };
+    thread.turns[index].items.push(ClaudeMessageRecord {
+        id: message.id.clone(),
+        role: message.role.clone(),
+        text: message.text.clone(),
+        pinned: false,
+    });
+    if message.role == "user" && thread.preview.trim().is_empty() {
+        thread.preview = message.text.clone();
+    }
+    thread.updated_at = now;
+}
+
+fn complete_followed_turn(thread: &mut ClaudeThreadRecord, turn_id: &str, now: i64) {
+    if let Some(turn) = thread.turns.iter_mut().find(|turn| turn.id == turn_id) {
+        turn.completed_at.get_or_insert(now);
+    }
+}
+
+/// Runs `update` on the followed thread; `false` when the thread is no longer stored.
+async fn update_followed_thread(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    update: impl FnOnce(&mut ClaudeThreadRecord),
+) -> bool {
+    let mut store = claude_threads.lock().await;
+    let Some(thread) = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+    else {
+        return false;
+    };
+    update(thread);
+    true
+}
+
+/// Registers a follow for `key`, stopping any earlier one.
+fn register_follow(
+    follows: &mut HashMap<String, FollowHandle>,
+    key: &str,
+) -> (Uuid, oneshot::Receiver<()>) {
+    let (stop, stop_rx) = oneshot::channel::<()>();
+    let id = Uuid::new_v4();
+    if let Some(existing) = follows.insert(key.to_string(), FollowHandle { id, stop }) {
+        let _ = existing.stop.send(());
+    }
+    (id, stop_rx)
+}
+
+/// Drops the entry for `key` unless a newer follow has taken it over.
+fn unregister_follow(follows: &mut HashMap<String, FollowHandle>, key: &str, id: Uuid) {
+    if follows.get(key).is_some_and(|handle| handle.id == id) {
+        follows.remove(key);
+    }
+}
+
+/// Tails the Claude session file for a thread so turns run from a terminal `claude`
+/// show up live. Only records appended after the follow starts are streamed; they are also
+/// stored on the thread.
+pub(crate) async fn follow_session_start_core<E: EventSink>(
+    claude_follows: &ClaudeFollowsStore,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    workspace_path: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let thread = claude_threads
+        .lock()
+        .await
+        .get(&workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .cloned();
+    let cwd = thread
+        .as_ref()
+        .and_then(thread_cwd_override)
+        .unwrap_or(workspace_path);
+    let path = claude_project_dir_for_workspace(&cwd)
+        .and_then(|project_dir| session_file_path(&project_dir, thread.as_ref(), &thread_id))
+        .ok_or_else(|| "unable to resolve Claude project directory".to_string())?;
+    if !path.is_file() {
+        return Err(format!("Claude session file not found: {}", path.display()));
+    }
+    let session_id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|error| format!("Failed to open Claude session file: {error}"))?;
+    let mut offset = file
+        .seek(SeekFrom::End(0))
+        .await
+        .map_err(|error| format!("Failed to seek Claude session file: {error}"))?;
+
+    let key = cancel_key(&workspace_id, &thread_id);
+    let (follow_id, mut stop_rx) = register_follow(&mut *claude_follows.lock().await, &key);
+
+    emit(
+        &event_sink,
+        &workspace_id,
+        "thread/followStarted",
+        json!({ "threadId": thread_id, "path": path.to_string_lossy() }),
+    );
+
+    let claude_follows = Arc::clone(claude_follows);
+    let claude_threads = Arc::clone(claude_threads);
+    let claude_threads_path = claude_threads_path.to_path_buf();
+    tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        let mut current_turn: Option<String> = None;
+        let mut stop_reason = "stopped";
+        loop {
+            match stop_rx.try_recv() {
+                Ok(_) | Err(TryRecvError::Closed) => break,
+                Err(TryRecvError::Empty) => {}
+            }
+            let len = match tokio::fs::metadata(&path).await {
+                Ok(metadata) => metadata.len(),
+                Err(_) => {
+                    stop_reason = "removed";
+                    break;
+                }
+            };
+            if len < offset {
+                // The file was truncated or replaced; start over from the beginning.
+                offset = 0;
+                buffer.clear();
+            }
+            if len > offset {
+                let mut chunk = Vec::with_capacity((len - offset) as usize);
+                let read = async {
+                    file.seek(SeekFrom::Start(offset)).await?;
+                    (&mut file).take(len - offset).read_to_end(&mut chunk).await
+                }
+                .await;
+                match read {
+                    Ok(bytes) => offset += bytes as u64,
+                    Err(_) => {
+                        stop_reason = "readError";
+                        break;
+                    }
+                }
+                let mut stored = false;
+                for line in drain_complete_lines(&mut buffer, &chunk) {
+                    let Some(message) = parse_followed_line(&line, &session_id) else {
+                        continue;
+                    };
+                    let completed = emit_followed_message(
+                        &event_sink,
+                        &workspace_id,
+                        &thread_id,
+                        &mut current_turn,
+                        &message,
+                    );
+                    let turn_id = current_turn.clone().unwrap_or_default();
+                    stored |= update_followed_thread(
+                        &claude_threads,
+                        &workspace_id,
+                        &thread_id,
+                        |thread| {
+                            let now = now_ms();
+                            if let Some(completed) = &completed {
+                                complete_followed_turn(thread, completed, now);
+                            }
+                            record_followed_message(thread, &turn_id, &message, now);
+                        },
+                    )
+                    .await;
+                }
+                if stored {
+                    if let Err(error) =
+                        persist_threads_store(&claude_threads, &claude_threads_path).await
+                    {
+                        eprintln!("Failed to store followed Claude messages: {error}");
+                    }
+                }
+            }
+            sleep(Duration::from_millis(FOLLOW_POLL_INTERVAL_MS)).await;
+        }
+        if let Some(turn_id) = current_turn.take() {
+            emit_turn_event(
+                &event_sink,
+                &workspace_id,
+                &thread_id,
+                "turn/completed",
+                &turn_id,
+            );
+            let completed =
+                update_followed_thread(&claude_threads, &workspace_id, &thread_id, |thread| {
+                    complete_followed_turn(thread, &turn_id, now_ms())
+                })
+                .await;
+            if completed {
+                if let Err(error) =
+                    persist_threads_store(&claude_threads, &claude_threads_path).await
+                {
+                    eprintln!("Failed to store followed Claude messages: {error}");
+                }
+            }
+        }
+        emit(
+            &event_sink,
+            &workspace_id,
+            "thread/followStopped",
+            json!({ "threadId": thread_id, "reason": stop_reason }),
+        );
+        unregister_follow(&mut *claude_follows.lock().await, &key, follow_id);
+    });
+
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+pub(crate) async fn follow_session_stop_core(
+    claude_follows: &ClaudeFollowsStore,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let key = cancel_key(&workspace_id, &thread_id);
+    let stop = claude_follows.lock().await.remove(&key);
+    let was_following = stop.is_some();
+    if let Some(handle) = stop {
+        let _ = handle.stop.send(());
+    }
+    Ok(json!({ "result": { "ok": true, "wasFollowing": was_following } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        complete_followed_turn, drain_complete_lines, parse_followed_line, record_followed_message,
+        register_follow, session_file_path, unregister_follow, FollowedMessage,
+    };
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn thread(id: &str) -> ClaudeThreadRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "cwd": "/repo",
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 1,
+            "turns": [],
+        }))
+        .expect("thread")
+    }
+
+    fn message(id: &str, role: &str, text: &str) -> FollowedMessage {
+        FollowedMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn drain_complete_lines_keeps_partial_tail() {
+        let mut buffer = Vec::new();
+        assert!(drain_complete_lines(&mut buffer, b"{\"a\":1}").is_empty());
+        let lines = drain_complete_lines(&mut buffer, b"\n{\"b\":2}\n{\"c\"");
+        assert_eq!(
+            lines,
+            vec!["{\"a\":1}".to_string(), "{\"b\":2}".to_string()]
+        );
+        assert_eq!(buffer, b"{\"c\"".to_vec());
+    }
+
+    #[test]
+    fn parse_followed_line_filters_other_sessions() {
+        let line = r#"{"type":"user","sessionId":"s1","uuid":"u1","message":{"content":"hi"}}"#;
+        let parsed = parse_followed_line(line, "s1").expect("message");
+        assert_eq!(parsed.role, "user");
+        assert_eq!(parsed.text, "hi");
+        assert_eq!(parsed.id, "u1");
+        assert!(parse_followed_line(line, "s2").is_none());
+    }
+
+    #[test]
+    fn a_stopped_follow_leaves_its_replacement_registered() {
+        let mut follows = HashMap::new();
+        let (first, mut first_stop) = register_follow(&mut follows, "ws:thread");
+        let (second, _second_stop) = register_follow(&mut follows, "ws:thread");
+        assert!(first_stop.try_recv().is_ok());
+
+        unregister_follow(&mut follows, "ws:thread", first);
+        assert!(follows.contains_key("ws:thread"));
+        unregister_follow(&mut follows, "ws:thread", second);
+        assert!(follows.is_empty());
+    }
+
+    #[test]
+    fn session_file_path_prefers_the_session_the_cli_reported() {
+        let project_dir = std::env::temp_dir().join(format!("follow-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&project_dir).expect("create dir");
+        let legacy = Uuid::new_v4().to_string();
+        let thread_id = format!("claude-thread-{legacy}");
+        let mut record = thread(&thread_id);
+        record.provider_session_id = Some("reported".to_string());
+
+        assert_eq!(
+            session_file_path(&project_dir, Some(&record), &thread_id),
+            Some(project_dir.join("reported.jsonl"))
+        );
+        std::fs::write(project_dir.join(format!("{legacy}.jsonl")), "").expect("write");
+        assert_eq!(
+            session_file_path(&project_dir, Some(&record), &thread_id),
+            Some(project_dir.join(format!("{legacy}.jsonl")))
+        );
+        std::fs::write(project_dir.join("reported.jsonl"), "").expect("write");
+        assert_eq!(
+            session_file_path(&project_dir, Some(&record), &thread_id),
+            Some(project_dir.join("reported.jsonl"))
+        );
+        assert_eq!(
+            session_file_path(&project_dir, None, "plain-thread"),
+            Some(project_dir.join("plain-thread.jsonl"))
+        );
+        let _ = std::fs::remove_dir_all(&project_dir);
+    }
+
+    #[test]
+    fn followed_messages_are_stored_as_turns() {
+        let mut record = thread("t1");
+        record_followed_message(&mut record, "follow-1", &message("u1", "user", "hi"), 10);
+        record_followed_message(
+            &mut record,
+            "follow-1",
+            &message("a1", "assistant", "hello"),
+            11,
+        );
+        record_followed_message(
+            &mut record,
+            "follow-1",
+            &message("a1", "assistant", "hello"),
+            12,
+        );
+        complete_followed_turn(&mut record, "follow-1", 13);
+        record_followed_message(&mut record, "follow-2", &message("u2", "user", "again"), 14);
+
+        assert_eq!(record.turns.len(), 2);
+        assert_eq!(record.turns[0].items.len(), 2);
+        assert_eq!(record.turns[0].completed_at, Some(13));
+        assert_eq!(record.turns[1].items[0].text, "again");
+        assert_eq!(record.turns[1].completed_at, None);
+        assert_eq!(record.preview, "hi");
+        assert_eq!(record.updated_at, 14);
+    }
+}
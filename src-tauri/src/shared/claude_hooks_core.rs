@@ -0,0 +1,208 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+
+use crate::types::WorkspaceEntry;
+
+/// Workspace-relative Claude settings file whose `hooks` section is managed here.
+pub(crate) const CLAUDE_SETTINGS_PATH: &str = ".claude/settings.json";
+
+/// Hook events the Claude CLI understands.
+const HOOK_EVENTS: &[&str] = &[
+    "PreToolUse",
+    "PostToolUse",
+    "Notification",
+    "UserPromptSubmit",
+    "Stop",
+    "SubagentStop",
+    "PreCompact",
+    "SessionStart",
+    "SessionEnd",
+];
+/// Events whose `matcher` selects tools; the others run for every occurrence.
+const TOOL_HOOK_EVENTS: &[&str] = &["PreToolUse", "PostToolUse"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct HookCommand {
+    /// Always `command`; kept so unknown future kinds fail validation instead of being dropped.
+    #[serde(rename = "type")]
+    pub(crate) kind: String,
+    pub(crate) command: String,
+    /// Seconds before Claude kills the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct HookMatcher {
+    /// Tool name pattern such as `Edit|Write`; empty or missing matches every tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) matcher: Option<String>,
+    pub(crate) hooks: Vec<HookCommand>,
+}
+
+/// The `hooks` section, keyed by event name.
+pub(crate) type HooksConfig = BTreeMap<String, Vec<HookMatcher>>;
+
+pub(crate) fn validate_hooks(hooks: &HooksConfig) -> Result<(), String> {
+    for (event, matchers) in hooks {
+        if !HOOK_EVENTS.contains(&event.as_str()) {
+            return Err(format!("Unknown hook event `{event}`."));
+        }
+        for matcher in matchers {
+            if let Some(pattern) = matcher.matcher.as_deref().filter(|value| !value.is_empty()) {
+                if !TOOL_HOOK_EVENTS.contains(&event.as_str()) {
+                    return Err(format!("`{event}` hooks do not take a matcher."));
+                }
+                Regex::new(pattern)
+                    .map_err(|error| format!("Invalid `{event}` matcher `{pattern}`: {error}"))?;
+            }
+            if matcher.hooks.is_empty() {
+                return Err(format!("`{event}` entries need at least one hook."));
+            }
+            for hook in &matcher.hooks {
+                if hook.kind != "command" {
+                    return Err(format!("Unsupported hook type `{}`.", hook.kind));
+                }
+                if hook.command.trim().is_empty() {
+                    return Err(format!("`{event}` hook command is empty."));
+                }
+                if hook.timeout == Some(0) {
+                    return Err(format!("`{event}` hook timeout must be greater than 0."));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn settings_path(workspace_path: &str) -> PathBuf {
+    Path::new(workspace_path).join(CLAUDE_SETTINGS_PATH)
+}
+
+async fn workspace_path(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<String, String> {
+    workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(|| "workspace not found".to_string())
+}
+
+/// Reads the whole settings object; `None` when the file does not exist yet.
+fn read_settings_object(path: &Path) -> Result<Option<Map<String, Value>>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+    };
+    if content.trim().is_empty() {
+        return Ok(Some(Map::new()));
+    }
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(map)) => Ok(Some(map)),
+        Ok(_) => Err(format!("{} is not a JSON object.", path.display())),
+        Err(error) => Err(format!("Failed to parse {}: {error}", path.display())),
+    }
+}
+
+fn hooks_from_settings(settings: &Map<String, Value>) -> Result<HooksConfig, String> {
+    match settings.get("hooks") {
+        None | Some(Value::Null) => Ok(HooksConfig::new()),
+        Some(hooks) => serde_json::from_value(hooks.clone())
+            .map_err(|error| format!("Invalid `hooks` in {CLAUDE_SETTINGS_PATH}: {error}")),
+    }
+}
+
+fn hooks_response(path: &Path, exists: bool, hooks: &HooksConfig) -> Value {
+    json!({
+        "result": {
+            "path": CLAUDE_SETTINGS_PATH,
+            "absolutePath": path.to_string_lossy(),
+            "exists": exists,
+            "hooks": hooks,
+        }
+    })
+}
+
+pub(crate) async fn claude_hooks_read_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let path = settings_path(&workspace_path(workspaces, &workspace_id).await?);
+    let settings = read_settings_object(&path)?;
+    let hooks = settings
+        .as_ref()
+        .map(hooks_from_settings)
+        .transpose()?
+        .unwrap_or_default();
+    Ok(hooks_response(&path, settings.is_some(), &hooks))
+}
+
+/// Replaces the `hooks` section and leaves every other setting in the file untouched.
+/// An empty config removes the section.
+pub(crate) async fn claude_hooks_write_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    hooks: HooksConfig,
+) -> Result<Value, String> {
+    validate_hooks(&hooks)?;
+    let path = settings_path(&workspace_path(workspaces, &workspace_id).await?);
+    let mut settings = read_settings_object(&path)?.unwrap_or_default();
+    let hooks = hooks
+        .into_iter()
+        .filter(|(_, matchers)| !matchers.is_empty())
+        .collect::<HooksConfig>();
+    if hooks.is_empty() {
+        settings.remove("hooks");
+    } else {
+        let value = serde_json::to_value(&hooks).map_err(|error| error.to_string())?;
+        settings.insert("hooks".to_string(), value);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(&Value::Object(settings))
+        .map_err(|error| error.to_string())?;
+    std::fs::write(&path, format!("{data}\n"))
+        .map_err(|error| format!("Failed to write {}: {error}", path.display()))?;
+    Ok(hooks_response(&path, true, &hooks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hooks_from_settings, validate_hooks, HooksConfig};
+    use serde_json::json;
+
+    #[test]
+    fn parses_and_validates_claude_hooks() {
+        let settings = json!({
+            "permissions": { "allow": ["Bash(npm test)"] },
+            "hooks": {
+                "PreToolUse": [{
+                    "matcher": "Edit|Write",
+                    "hooks": [{ "type": "command", "command": "npm run lint", "timeout": 30 }],
+                }],
+                "Stop": [{ "hooks": [{ "type": "command", "command": "notify-send done" }] }],
+            },
+        });
+        let hooks = hooks_from_settings(settings.as_object().unwrap()).expect("parse");
+        assert_eq!(hooks["PreToolUse"][0].hooks[0].timeout, Some(30));
+        assert!(validate_hooks(&hooks).is_ok());
+        assert_eq!(json!(hooks), settings["hooks"]);
+
+        let mut invalid = hooks.clone();
+        invalid.insert("Stop".to_string(), hooks["PreToolUse"].clone());
+        assert!(validate_hooks(&invalid).is_err());
+        let unknown: HooksConfig =
+            serde_json::from_value(json!({ "BeforeEverything": [] })).unwrap();
+        assert!(validate_hooks(&unknown).is_err());
+    }
+}
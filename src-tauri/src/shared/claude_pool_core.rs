@@ -0,0 +1,637 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, Mutex};
+use uuid::Uuid;
+
+use crate::shared::file_access_core::{FileAccessGuard, FileAccessViolation};
+use crate::shared::output_decode_core::{lossy_lines, LossyLines};
+use crate::shared::output_filters_core::OutputFilterPipeline;
+use crate::shared::turn_checklist_core::{checklist_update, ChecklistStep};
+
+/// A long-lived `claude --input-format stream-json` process bound to one session.
+pub(crate) struct WarmClaudeProcess {
+    key: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: LossyLines<BufReader<ChildStdout>>,
+    checked_in_at: Instant,
+}
+
+/// At most one warm process per workspace, keyed by workspace id.
+pub(crate) type ClaudeProcessPool = Arc<Mutex<HashMap<String, WarmClaudeProcess>>>;
+
+/// Bash invocations and file accesses waiting on the user, keyed by approval id.
+pub(crate) type ClaudeApprovalsStore =
+    Arc<Mutex<HashMap<String, oneshot::Sender<PermissionDecision>>>>;
+
+/// Argument that routes Claude's permission prompts over the stream-json control protocol.
+pub(crate) const PERMISSION_PROMPT_ARGS: [&str; 2] = ["--permission-prompt-tool", "stdio"];
+
+pub(crate) enum PermissionDecision {
+    Allow,
+    Deny(String),
+}
+
+pub(crate) enum ApprovalRequest {
+    Command {
+        command: String,
+        description: Option<String>,
+    },
+    FileAccess(FileAccessViolation),
+}
+
+pub(crate) struct PooledTurnRequest<'a> {
+    pub(crate) workspace_id: &'a str,
+    /// See [`process_key`]; a pooled process is only reused for a matching key.
+    pub(crate) key: String,
+    pub(crate) prompt: &'a str,
+    pub(crate) idle_timeout: Duration,
+    /// Set when the process was started with [`PERMISSION_PROMPT_ARGS`]; requests that need
+    /// the user are then held here until answered, other tools are allowed.
+    pub(crate) approvals: Option<&'a ClaudeApprovalsStore>,
+    /// Every Bash command needs the user.
+    pub(crate) command_approvals: bool,
+    /// File tool calls outside the workspace's file rules need the user.
+    pub(crate) file_access: Option<&'a FileAccessGuard>,
+    pub(crate) output_filters: &'a OutputFilterPipeline,
+}
+
+pub(crate) enum PooledTurn {
+    Completed {
+        success: bool,
+        error: Option<String>,
+        /// Session the CLI reported, when it did.
+        session_id: Option<String>,
+    },
+    Canceled,
+    /// The warm process could not be used before any output was produced, for the given
+    /// reason; run a one-shot CLI.
+    Unavailable(String),
+}
+
+pub(crate) enum StreamLine {
+    Delta(String),
+    Message(String),
+    /// The `system` init record naming the session the CLI is writing to.
+    Session(String),
+    Result {
+        success: bool,
+        error: Option<String>,
+        session_id: Option<String>,
+    },
+    ToolPermission {
+        request_id: String,
+        tool_name: String,
+        input: Value,
+    },
+    Ignored,
+}
+
+/// Identifies which process a turn can reuse: same session, directory and CLI configuration.
+/// `overrides` covers per-thread generation settings and profile environment.
+pub(crate) fn process_key(
+    session_id: &str,
+    cwd: &str,
+    bin: Option<&str>,
+    args: Option<&str>,
+    overrides: &Value,
+    permission_prompt: bool,
+) -> String {
+    json!([session_id, cwd, bin, args, overrides, permission_prompt]).to_string()
+}
+
+fn user_message_line(prompt: &str) -> String {
+    let message = json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{ "type": "text", "text": prompt }],
+        },
+    });
+    format!("{message}\n")
+}
+
+fn assistant_message_text(message: &Value) -> Option<String> {
+    let text = message
+        .get("content")?
+        .as_array()?
+        .iter()
+        .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+        .filter_map(|block| block.get("text").and_then(Value::as_str))
+        .collect::<String>();
+    (!text.is_empty()).then_some(text)
+}
+
+fn record_session_id(record: &Value) -> Option<String> {
+    record
+        .get("session_id")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|session_id| !session_id.is_empty())
+        .map(str::to_string)
+}
+
+/// Whether an output filter drops the text record `line` was parsed into. Rules see the whole
+/// record, never a fragment of its text; records that drive the protocol are always kept.
+pub(crate) fn drops_record(
+    output_filters: &OutputFilterPipeline,
+    line: &str,
+    parsed: &StreamLine,
+) -> bool {
+    matches!(parsed, StreamLine::Delta(_) | StreamLine::Message(_))
+        && output_filters.should_drop(line.trim())
+}
+
+/// Classifies one `--output-format stream-json` record.
+pub(crate) fn parse_stream_record(record: &Value) -> StreamLine {
+    match record.get("type").and_then(Value::as_str) {
+        Some("system") if record.get("subtype").and_then(Value::as_str) == Some("init") => {
+            record_session_id(record)
+                .map(StreamLine::Session)
+                .unwrap_or(StreamLine::Ignored)
+        }
+        Some("stream_event") => {
+            let event = record.get("event");
+            let delta = event
+                .filter(|event| {
+                    event.get("type").and_then(Value::as_str) == Some("content_block_delta")
+                })
+                .and_then(|event| event.get("delta"))
+                .filter(|delta| delta.get("type").and_then(Value::as_str) == Some("text_delta"))
+                .and_then(|delta| delta.get("text"))
+                .and_then(Value::as_str);
+            match delta {
+                Some(text) if !text.is_empty() => StreamLine::Delta(text.to_string()),
+                _ => StreamLine::Ignored,
+            }
+        }
+        Some("assistant") => record
+            .get("message")
+            .and_then(assistant_message_text)
+            .map(StreamLine::Message)
+            .unwrap_or(StreamLine::Ignored),
+        Some("control_request") => {
+            let request = record.get("request");
+            let is_tool_permission = request
+                .and_then(|request| request.get("subtype"))
+                .and_then(Value::as_str)
+                == Some("can_use_tool");
+            let request_id = record.get("request_id").and_then(Value::as_str);
+            match (is_tool_permission, request_id, request) {
+                (true, Some(request_id), Some(request)) => StreamLine::ToolPermission {
+                    request_id: request_id.to_string(),
+                    tool_name: request
+                        .get("tool_name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    input: request.get("input").cloned().unwrap_or_else(|| json!({})),
+                },
+                _ => StreamLine::Ignored,
+            }
+        }
+        Some("result") => {
+            let success = record.get("subtype").and_then(Value::as_str) == Some("success")
+                && !record
+                    .get("is_error")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+            let error = (!success).then(|| {
+                record
+                    .get("result")
+                    .and_then(Value::as_str)
+                    .filter(|text| !text.trim().is_empty())
+                    .unwrap_or("Claude CLI failed.")
+                    .to_string()
+            });
+            StreamLine::Result {
+                success,
+                error,
+                session_id: record_session_id(record),
+            }
+        }
+        _ => StreamLine::Ignored,
+    }
+}
+
+fn control_response_line(request_id: &str, decision: &PermissionDecision, input: &Value) -> String {
+    let response = match decision {
+        PermissionDecision::Allow => json!({ "behavior": "allow", "updatedInput": input }),
+        PermissionDecision::Deny(message) => json!({ "behavior": "deny", "message": message }),
+    };
+    let line = json!({
+        "type": "control_response",
+        "response": {
+            "subtype": "success",
+            "request_id": request_id,
+            "response": response,
+        },
+    });
+    format!("{line}\n")
+}
+
+/// Waits for the user's answer; `None` means the turn was canceled first.
+async fn wait_for_decision(
+    decision_rx: oneshot::Receiver<PermissionDecision>,
+    cancel_rx: &mut oneshot::Receiver<()>,
+) -> Option<PermissionDecision> {
+    tokio::select! {
+        biased;
+        _ = cancel_rx => None,
+        decision = decision_rx => Some(decision.unwrap_or_else(|_| {
+            PermissionDecision::Deny("The approval request was withdrawn.".to_string())
+        })),
+    }
+}
+
+/// Answers a pending `approval/commandRequested` or `approval/fileAccessRequested` event.
+pub(crate) async fn respond_to_approval_core(
+    approvals: &ClaudeApprovalsStore,
+    approval_id: String,
+    allow: bool,
+    message: Option<String>,
+) -> Result<Value, String> {
+    let sender = approvals
+        .lock()
+        .await
+        .remove(&approval_id)
+        .ok_or_else(|| "approval request not found".to_string())?;
+    let decision = if allow {
+        PermissionDecision::Allow
+    } else {
+        PermissionDecision::Deny(
+            message
+                .map(|message| message.trim().to_string())
+                .filter(|message| !message.is_empty())
+                .unwrap_or_else(|| "The user denied this request.".to_string()),
+        )
+    };
+    let _ = sender.send(decision);
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+fn spawn_warm_process(key: String, mut command: Command) -> Result<WarmClaudeProcess, String> {
+    command.stdin(std::process::Stdio::piped());
+    command.stdout(std::process::Stdio::piped());
+    command.stderr(std::process::Stdio::null());
+    command.kill_on_drop(true);
+    let mut child = command
+        .spawn()
+        .map_err(|error| format!("Failed to start Claude CLI: {error}"))?;
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Claude CLI missing stdin".to_string())?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Claude CLI missing stdout".to_string())?;
+    Ok(WarmClaudeProcess {
+        key,
+        child,
+        stdin,
+        lines: lossy_lines(BufReader::new(stdout)),
+        checked_in_at: Instant::now(),
+    })
+}
+
+async fn checkout(
+    pool: &ClaudeProcessPool,
+    workspace_id: &str,
+    key: &str,
+    command: impl FnOnce() -> Result<Command, String>,
+) -> Result<WarmClaudeProcess, String> {
+    let existing = pool.lock().await.remove(workspace_id);
+    match existing {
+        Some(process) if process.key == key => Ok(process),
+        stale => {
+            if let Some(mut stale) = stale {
+                let _ = stale.child.start_kill();
+            }
+            spawn_warm_process(key.to_string(), command()?)
+        }
+    }
+}
+
+/// Returns the process to the pool and shuts it down if no turn reuses it before the timeout.
+async fn checkin(
+    pool: &ClaudeProcessPool,
+    workspace_id: &str,
+    mut process: WarmClaudeProcess,
+    idle_timeout: Duration,
+) {
+    let checked_in_at = Instant::now();
+    process.checked_in_at = checked_in_at;
+    pool.lock().await.insert(workspace_id.to_string(), process);
+    let pool = Arc::clone(pool);
+    let workspace_id = workspace_id.to_string();
+    tokio::spawn(async move {
+        tokio::time::sleep(idle_timeout).await;
+        let mut processes = pool.lock().await;
+        let idle = processes
+            .get(&workspace_id)
+            .is_some_and(|process| process.checked_in_at == checked_in_at);
+        if idle {
+            if let Some(mut process) = processes.remove(&workspace_id) {
+                let _ = process.child.start_kill();
+            }
+        }
+    });
+}
+
+/// Kills every idle warm process and returns how many there were. Checked-out processes belong
+/// to running turns and are killed when those are cancelled.
+pub(crate) async fn shutdown_pool(pool: &ClaudeProcessPool) -> usize {
+    let processes = std::mem::take(&mut *pool.lock().await);
+    let count = processes.len();
+    for (_, mut process) in processes {
+        let _ = process.child.kill().await;
+    }
+    count
+}
+
+/// Runs one turn over the workspace's warm process, spawning it with `command` when missing or
+/// bound to a different session. Cancelling kills the process rather than returning it.
+pub(crate) async fn run_pooled_turn(
+    pool: &ClaudeProcessPool,
+    request: PooledTurnRequest<'_>,
+    command: impl FnOnce() -> Result<Command, String>,
+    cancel_rx: &mut oneshot::Receiver<()>,
+    mut on_delta: impl FnMut(&str),
+    mut on_approval: impl FnMut(&str, ApprovalRequest),
+    mut on_checklist: impl FnMut(Vec<ChecklistStep>),
+) -> PooledTurn {
+    let PooledTurnRequest {
+        workspace_id,
+        key,
+        prompt,
+        idle_timeout,
+        approvals,
+        command_approvals,
+        file_access,
+        output_filters,
+    } = request;
+    let mut process = match checkout(pool, workspace_id, &key, command).await {
+        Ok(process) => process,
+        Err(error) => return PooledTurn::Unavailable(error),
+    };
+    let line = user_message_line(prompt);
+    let written = async {
+        process.stdin.write_all(line.as_bytes()).await?;
+        process.stdin.flush().await
+    }
+    .await;
+    if let Err(error) = written {
+        let _ = process.child.start_kill();
+        return PooledTurn::Unavailable(format!("Failed to send the prompt to Claude: {error}"));
+    }
+
+    let mut streamed = false;
+    let mut produced = false;
+    let mut session_id = None;
+    loop {
+        let next = tokio::select! {
+            biased;
+            _ = &mut *cancel_rx => {
+                let _ = process.child.kill().await;
+                return PooledTurn::Canceled;
+            }
+            next = process.lines.next_line() => next,
+        };
+        let line = match next {
+            Ok(Some(line)) => line,
+            Ok(None) | Err(_) => {
+                let _ = process.child.start_kill();
+                if produced {
+                    return PooledTurn::Completed {
+                        success: false,
+                        error: Some("Claude process exited unexpectedly.".to_string()),
+                        session_id,
+                    };
+                }
+                return PooledTurn::Unavailable(
+                    "The Claude process exited before answering.".to_string(),
+                );
+            }
+        };
+        let record = serde_json::from_str::<Value>(line.trim()).ok();
+        if let Some(steps) = record.as_ref().and_then(checklist_update) {
+            produced = true;
+            on_checklist(steps);
+        }
+        let parsed = record
+            .as_ref()
+            .map_or(StreamLine::Ignored, parse_stream_record);
+        if drops_record(output_filters, &line, &parsed) {
+            produced = true;
+            streamed |= matches!(parsed, StreamLine::Delta(_));
+            continue;
+        }
+        match parsed {
+            StreamLine::Delta(text) => {
+                streamed = true;
+                produced = true;
+                on_delta(&text);
+            }
+            // Whole messages repeat the streamed deltas; only use them when nothing streamed.
+            StreamLine::Message(text) if !streamed => {
+                produced = true;
+                on_delta(&text);
+            }
+            StreamLine::Session(id) => session_id = Some(id),
+            StreamLine::Result {
+                success,
+                error,
+                session_id: reported,
+            } => {
+                checkin(pool, workspace_id, process, idle_timeout).await;
+                return PooledTurn::Completed {
+                    success,
+                    error,
+                    session_id: reported.or(session_id),
+                };
+            }
+            StreamLine::ToolPermission {
+                request_id,
+                tool_name,
+                input,
+            } => {
+                let text_field =
+                    |name: &str| input.get(name).and_then(Value::as_str).map(str::to_string);
+                let approval = if tool_name == "Bash" {
+                    command_approvals.then(|| ApprovalRequest::Command {
+                        command: text_field("command").unwrap_or_default(),
+                        description: text_field("description"),
+                    })
+                } else {
+                    file_access
+                        .and_then(|guard| guard.violation(&tool_name, &input))
+                        .map(ApprovalRequest::FileAccess)
+                };
+                let decision = match approvals.zip(approval) {
+                    Some((approvals, approval)) => {
+                        let approval_id = format!("claude-approval-{}", Uuid::new_v4());
+                        let (decision_tx, decision_rx) = oneshot::channel();
+                        approvals
+                            .lock()
+                            .await
+                            .insert(approval_id.clone(), decision_tx);
+                        on_approval(&approval_id, approval);
+                        match wait_for_decision(decision_rx, cancel_rx).await {
+                            Some(decision) => decision,
+                            None => {
+                                approvals.lock().await.remove(&approval_id);
+                                let _ = process.child.kill().await;
+                                return PooledTurn::Canceled;
+                            }
+                        }
+                    }
+                    None => PermissionDecision::Allow,
+                };
+                let response = control_response_line(&request_id, &decision, &input);
+                let written = async {
+                    process.stdin.write_all(response.as_bytes()).await?;
+                    process.stdin.flush().await
+                }
+                .await;
+                if written.is_err() {
+                    let _ = process.child.start_kill();
+                    return PooledTurn::Completed {
+                        success: false,
+                        error: Some("Claude process exited unexpectedly.".to_string()),
+                        session_id,
+                    };
+                }
+                produced = true;
+            }
+            StreamLine::Message(_) | StreamLine::Ignored => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        control_response_line, drops_record, parse_stream_record, user_message_line,
+        PermissionDecision, StreamLine,
+    };
+    use crate::shared::output_filters_core::OutputFilterPipeline;
+    use crate::types::{OutputFilterKind, OutputFilterRule, ProviderKind};
+    use serde_json::Value;
+
+    fn parse_stream_line(line: &str) -> StreamLine {
+        match serde_json::from_str::<Value>(line) {
+            Ok(record) => parse_stream_record(&record),
+            Err(_) => StreamLine::Ignored,
+        }
+    }
+
+    #[test]
+    fn parse_stream_line_reads_deltas_and_results() {
+        let delta = r#"{"type":"stream_event","event":{"type":"content_block_delta","delta":{"type":"text_delta","text":"Hi"}}}"#;
+        assert!(matches!(parse_stream_line(delta), StreamLine::Delta(text) if text == "Hi"));
+
+        let assistant =
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}"#;
+        assert!(
+            matches!(parse_stream_line(assistant), StreamLine::Message(text) if text == "Done")
+        );
+
+        let failed = r#"{"type":"result","subtype":"error_during_execution","is_error":true,"result":"boom"}"#;
+        assert!(matches!(
+            parse_stream_line(failed),
+            StreamLine::Result { success: false, error: Some(message), session_id: None }
+                if message == "boom"
+        ));
+        let ok = r#"{"type":"result","subtype":"success","is_error":false,"result":"Done","session_id":"s-2"}"#;
+        assert!(matches!(
+            parse_stream_line(ok),
+            StreamLine::Result { success: true, error: None, session_id: Some(id) } if id == "s-2"
+        ));
+
+        let init = r#"{"type":"system","subtype":"init","session_id":"s-1","tools":[]}"#;
+        assert!(matches!(parse_stream_line(init), StreamLine::Session(id) if id == "s-1"));
+        assert!(matches!(
+            parse_stream_line("plain text"),
+            StreamLine::Ignored
+        ));
+    }
+
+    #[test]
+    fn user_message_line_is_single_json_line() {
+        let line = user_message_line("multi\nline");
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let value: serde_json::Value = serde_json::from_str(line.trim()).expect("json");
+        assert_eq!(value["message"]["content"][0]["text"], "multi\nline");
+    }
+
+    #[test]
+    fn tool_permission_requests_round_trip() {
+        let request = r#"{"type":"control_request","request_id":"req-1","request":{"subtype":"can_use_tool","tool_name":"Bash","input":{"command":"ls"}}}"#;
+        let StreamLine::ToolPermission {
+            request_id,
+            tool_name,
+            input,
+        } = parse_stream_line(request)
+        else {
+            panic!("expected a tool permission request");
+        };
+        assert_eq!((request_id.as_str(), tool_name.as_str()), ("req-1", "Bash"));
+
+        let denied = control_response_line(
+            &request_id,
+            &PermissionDecision::Deny("no".to_string()),
+            &input,
+        );
+        let value: serde_json::Value = serde_json::from_str(denied.trim()).expect("json");
+        assert_eq!(value["response"]["request_id"], "req-1");
+        assert_eq!(value["response"]["response"]["behavior"], "deny");
+        let allowed = control_response_line(&request_id, &PermissionDecision::Allow, &input);
+        let value: serde_json::Value = serde_json::from_str(allowed.trim()).expect("json");
+        assert_eq!(
+            value["response"]["response"]["updatedInput"]["command"],
+            "ls"
+        );
+    }
+
+    #[test]
+    fn output_filters_drop_whole_records_and_keep_the_answer() {
+        let filters = OutputFilterPipeline::compile(
+            &[OutputFilterRule {
+                id: "subagent".to_string(),
+                kind: OutputFilterKind::JsonPath,
+                pattern: "$.parent_tool_use_id=toolu_1".to_string(),
+                enabled: true,
+                provider: None,
+            }],
+            &ProviderKind::Claude,
+        );
+        let lines = [
+            r#"{"type":"stream_event","parent_tool_use_id":null,"event":{"type":"content_block_delta","delta":{"type":"text_delta","text":"The answer"}}}"#,
+            r#"{"type":"stream_event","parent_tool_use_id":"toolu_1","event":{"type":"content_block_delta","delta":{"type":"text_delta","text":"subagent noise"}}}"#,
+            r#"{"type":"stream_event","parent_tool_use_id":null,"event":{"type":"content_block_delta","delta":{"type":"text_delta","text":" is 42."}}}"#,
+            r#"{"type":"result","subtype":"success","is_error":false,"parent_tool_use_id":"toolu_1"}"#,
+        ];
+        let mut answer = String::new();
+        let mut results = 0;
+        for line in lines {
+            let parsed = parse_stream_line(line);
+            if drops_record(&filters, line, &parsed) {
+                continue;
+            }
+            match parsed {
+                StreamLine::Delta(text) => answer.push_str(&text),
+                StreamLine::Result { .. } => results += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(answer, "The answer is 42.");
+        assert_eq!(results, 1);
+    }
+}
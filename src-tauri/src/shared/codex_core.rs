@@ -5,17 +5,22 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
-use tokio::sync::{Mutex, oneshot};
+use tokio::sync::{mpsc, Mutex, oneshot};
 use tokio::sync::oneshot::error::TryRecvError;
 use tokio::time::timeout;
 use tokio::time::Instant;
 
 use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::EventSink;
+use crate::codex::args::{layer_codex_args, LayeredCodexArgs};
 use crate::codex::config as codex_config;
 use crate::codex::home::{resolve_default_codex_home, resolve_workspace_codex_home};
+use crate::providers;
 use crate::rules;
 use crate::shared::account::{build_account_response, read_auth_account};
-use crate::types::WorkspaceEntry;
+use crate::shared::claude_core::emit;
+use crate::shared::{resource_guard_core, sandbox_core};
+use crate::types::{AppSettings, WorkspaceEntry};
 
 const LOGIN_START_TIMEOUT: Duration = Duration::from_secs(30);
 
@@ -64,13 +69,20 @@ async fn resolve_codex_home_for_workspace_core(
 
 pub(crate) async fn start_thread_core(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: String,
 ) -> Result<Value, String> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
+    let mut params = json!({
         "cwd": session.entry.path,
         "approvalPolicy": "on-request"
     });
+    if let Some(instructions) =
+        providers::resolve_workspace_instructions(&entry, parent_entry.as_ref())
+    {
+        params["developerInstructions"] = json!(instructions);
+    }
     session.send_request("thread/start", params).await
 }
 
@@ -147,7 +159,7 @@ pub(crate) async fn set_thread_name_core(
     session.send_request("thread/name/set", params).await
 }
 
-pub(crate) async fn send_user_message_core(
+pub(crate) async fn send_user_message_core<E: EventSink>(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     workspace_id: String,
     thread_id: String,
@@ -157,16 +169,31 @@ pub(crate) async fn send_user_message_core(
     access_mode: Option<String>,
     images: Option<Vec<String>>,
     collaboration_mode: Option<Value>,
+    event_sink: E,
 ) -> Result<Value, String> {
     let session = get_session_clone(sessions, &workspace_id).await?;
-    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
+    if let Some(model) = model.as_deref().filter(|_| session.local_models) {
+        let memory = resource_guard_core::MemorySnapshot::current();
+        if let Some(mut warning) = resource_guard_core::check_model_fit(model, memory)? {
+            warning["threadId"] = json!(thread_id);
+            emit(
+                &event_sink,
+                &workspace_id,
+                "provider/resourceWarning",
+                warning,
+            );
+        }
+    }
+    let sandbox = &session.entry.settings.sandbox;
+    let access_mode = sandbox_core::codex_access_mode(sandbox, access_mode)
+        .unwrap_or_else(|| "current".to_string());
     let sandbox_policy = match access_mode.as_str() {
         "full-access" => json!({ "type": "dangerFullAccess" }),
         "read-only" => json!({ "type": "readOnly" }),
         _ => json!({
             "type": "workspaceWrite",
             "writableRoots": [session.entry.path],
-            "networkAccess": true
+            "networkAccess": !sandbox.no_network
         }),
     };
 
@@ -495,3 +522,145 @@ pub(crate) async fn get_config_model_core(
     let model = codex_config::read_config_model(Some(codex_home))?;
     Ok(json!({ "model": model }))
 }
+
+/// The argv Codex starts with for a workspace, the args each settings layer contributes, and
+/// the settings where a later layer overrode an earlier one.
+pub(crate) async fn effective_config_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
+    let settings = app_settings.lock().await.clone();
+    let layers =
+        providers::resolve_codex_arg_layers(&entry, parent_entry.as_ref(), Some(&settings))?;
+    let LayeredCodexArgs { argv, conflicts } = layer_codex_args(&layers);
+    Ok(json!({
+        "result": {
+            "workspaceId": workspace_id,
+            "argv": argv,
+            "layers": layers,
+            "conflicts": conflicts,
+        }
+    }))
+}
+
+async fn release_background_thread(session: &WorkspaceSession, thread_id: &str) {
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .remove(thread_id);
+    let archive_params = json!({ "threadId": thread_id });
+    let _ = session.send_request("thread/archive", archive_params).await;
+}
+
+/// Runs a one-off prompt on a hidden, read-only Codex thread and returns the agent's reply.
+/// The thread is archived afterwards so it never shows up in the workspace's history.
+pub(crate) async fn run_background_prompt<E: EventSink>(
+    session: &WorkspaceSession,
+    workspace_id: &str,
+    prompt: &str,
+    limit: Duration,
+    event_sink: &E,
+) -> Result<String, String> {
+    let thread_params = json!({
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+    });
+    let thread_result = session.send_request("thread/start", thread_params).await?;
+    if let Some(error) = thread_result.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error starting thread");
+        return Err(message.to_string());
+    }
+    let thread_id = thread_result
+        .get("result")
+        .and_then(|result| result.get("threadId"))
+        .or_else(|| {
+            thread_result
+                .get("result")
+                .and_then(|result| result.get("thread"))
+                .and_then(|thread| thread.get("id"))
+        })
+        .or_else(|| thread_result.get("threadId"))
+        .or_else(|| {
+            thread_result
+                .get("thread")
+                .and_then(|thread| thread.get("id"))
+        })
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            format!("Failed to get threadId from thread/start response: {thread_result:?}")
+        })?
+        .to_string();
+
+    // Hide the helper thread from the sidebar, even if a thread/started event leaked.
+    emit(
+        event_sink,
+        workspace_id,
+        "codex/backgroundThread",
+        json!({ "threadId": thread_id, "action": "hide" }),
+    );
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .insert(thread_id.clone(), tx);
+    let turn_params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": prompt }],
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "sandboxPolicy": { "type": "readOnly" },
+    });
+    let turn_result = match session.send_request("turn/start", turn_params).await {
+        Ok(result) => result,
+        Err(error) => {
+            release_background_thread(session, &thread_id).await;
+            return Err(error);
+        }
+    };
+    if let Some(error) = turn_result.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error starting turn")
+            .to_string();
+        release_background_thread(session, &thread_id).await;
+        return Err(message);
+    }
+
+    let mut reply = String::new();
+    let collected = timeout(limit, async {
+        while let Some(event) = rx.recv().await {
+            match event.get("method").and_then(Value::as_str).unwrap_or("") {
+                "item/agentMessage/delta" => {
+                    if let Some(delta) = event["params"]["delta"].as_str() {
+                        reply.push_str(delta);
+                    }
+                }
+                "turn/completed" => break,
+                "turn/error" => {
+                    return Err(event["params"]["error"]
+                        .as_str()
+                        .unwrap_or("Unknown error while running the prompt")
+                        .to_string());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })
+    .await;
+    release_background_thread(session, &thread_id).await;
+    match collected {
+        Ok(Ok(())) => Ok(reply.trim().to_string()),
+        Ok(Err(error)) => Err(error),
+        Err(_) => Err("Timed out waiting for Codex to reply.".to_string()),
+    }
+}
@@ -0,0 +1,312 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::codex::home::{resolve_default_codex_home, resolve_workspace_codex_home};
+use crate::shared::claude_core::{
+    build_turns_from_history_messages, emit_thread_list_updated, now_ms, parse_rfc3339_ms,
+    persist_threads_store, preview_from_text, redact_thread_in_place, ClaudeThreadRecord,
+    ClaudeThreadsStore, GenerationParams, HistoryMessage,
+};
+use crate::shared::redaction_core::RedactionPipeline;
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::types::{ProviderKind, WorkspaceEntry};
+
+const CODEX_IMPORT_TAG: &str = "codex";
+/// Codex injects these as user messages; they are context, not part of the conversation.
+const INJECTED_USER_PREFIXES: &[&str] = &[
+    "<environment_context>",
+    "<user_instructions>",
+    "# AGENTS.md instructions",
+];
+
+fn codex_content_text(content: &Value) -> Option<String> {
+    let text = match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let trimmed = text.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn is_injected_user_text(text: &str) -> bool {
+    INJECTED_USER_PREFIXES
+        .iter()
+        .any(|prefix| text.starts_with(prefix))
+}
+
+/// Converts a Codex rollout (`rollout-*.jsonl`) into the unified thread shape. Both the
+/// `session_meta`/`response_item` envelope and the older bare-item format are accepted.
+pub(crate) fn parse_codex_rollout(content: &str) -> Option<ClaudeThreadRecord> {
+    let mut session_id: Option<String> = None;
+    let mut cwd = String::new();
+    let mut created_at: Option<i64> = None;
+    let mut updated_at: Option<i64> = None;
+    let mut history_messages = Vec::new();
+
+    for line in content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let timestamp = record
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(parse_rfc3339_ms);
+        if let Some(timestamp) = timestamp {
+            created_at.get_or_insert(timestamp);
+            updated_at = Some(updated_at.map_or(timestamp, |value| value.max(timestamp)));
+        }
+        let (kind, payload) = match record.get("payload") {
+            Some(payload) => (record.get("type").and_then(Value::as_str), payload),
+            None => (Some("response_item"), &record),
+        };
+        match kind {
+            Some("session_meta") => {
+                session_id = payload
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                if let Some(meta_cwd) = payload.get("cwd").and_then(Value::as_str) {
+                    cwd = meta_cwd.to_string();
+                }
+            }
+            Some("response_item") => {
+                if session_id.is_none() && record.get("instructions").is_some() {
+                    session_id = record.get("id").and_then(Value::as_str).map(str::to_string);
+                    continue;
+                }
+                if payload.get("type").and_then(Value::as_str) != Some("message") {
+                    continue;
+                }
+                let role = match payload.get("role").and_then(Value::as_str) {
+                    Some(role @ ("user" | "assistant")) => role,
+                    _ => continue,
+                };
+                let Some(text) = payload.get("content").and_then(codex_content_text) else {
+                    continue;
+                };
+                if role == "user" && is_injected_user_text(&text) {
+                    continue;
+                }
+                history_messages.push(HistoryMessage {
+                    role: role.to_string(),
+                    text,
+                    timestamp_ms: timestamp.or(updated_at).unwrap_or_else(now_ms),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let thread_id = session_id?;
+    let turns = build_turns_from_history_messages(&thread_id, &history_messages);
+    if turns.is_empty() {
+        return None;
+    }
+    let preview_source = history_messages
+        .iter()
+        .find(|message| message.role == "user")
+        .map(|message| message.text.clone())
+        .unwrap_or_else(|| thread_id.clone());
+    let created_at = created_at.unwrap_or_else(now_ms);
+    Some(ClaudeThreadRecord {
+        id: thread_id,
+        cwd,
+        preview: preview_from_text(&preview_source),
+        created_at,
+        updated_at: updated_at.unwrap_or(created_at),
+        name: None,
+        tags: vec![CODEX_IMPORT_TAG.to_string()],
+        session_id: None,
+        session_needs_seed: false,
+        provider_session_id: None,
+        cwd_override: None,
+        generation: GenerationParams::default(),
+        worktree: None,
+        summary: None,
+        stats: Some(ThreadStats::from_turns(&turns, ProviderKind::Codex)),
+        last_read_at: None,
+        turns,
+    })
+}
+
+pub(crate) fn collect_rollout_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_rollout_files(&path, files);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("jsonl") {
+            files.push(path);
+        }
+    }
+}
+
+fn same_path(left: &str, right: &str) -> bool {
+    let normalize = |value: &str| {
+        Path::new(value)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(value.trim_end_matches('/')))
+    };
+    normalize(left) == normalize(right)
+}
+
+fn scan_codex_sessions(
+    sessions_dir: &Path,
+    workspace_path: &str,
+    redaction: &RedactionPipeline,
+) -> Vec<ClaudeThreadRecord> {
+    let mut files = Vec::new();
+    collect_rollout_files(sessions_dir, &mut files);
+    files
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|content| parse_codex_rollout(&content))
+        .filter(|thread| same_path(&thread.cwd, workspace_path))
+        .map(|mut thread| {
+            redact_thread_in_place(&mut thread, redaction);
+            thread
+        })
+        .collect()
+}
+
+/// Copies Codex sessions recorded for this workspace into the Claude thread store. Imported
+/// threads start a fresh Claude session seeded with their transcript on the next message.
+pub(crate) async fn import_codex_sessions_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    redaction: &RedactionPipeline,
+    event_sink: E,
+) -> Result<Value, String> {
+    let (entry, parent_entry) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(|| "workspace not found".to_string())?;
+        let parent_entry = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        (entry, parent_entry)
+    };
+    let codex_home = resolve_workspace_codex_home(&entry, parent_entry.as_ref())
+        .or_else(resolve_default_codex_home)
+        .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())?;
+    let sessions_dir = codex_home.join("sessions");
+    let workspace_path = entry.path.clone();
+    let redaction = redaction.clone();
+    let scanned = tokio::task::spawn_blocking(move || {
+        scan_codex_sessions(&sessions_dir, &workspace_path, &redaction)
+    })
+    .await
+    .map_err(|error| format!("failed to scan Codex sessions: {error}"))?;
+
+    let mut imported = Vec::new();
+    let mut skipped = 0usize;
+    {
+        let mut store = claude_threads.lock().await;
+        let threads = store.entry(workspace_id.clone()).or_default();
+        for mut thread in scanned {
+            if threads.iter().any(|existing| existing.id == thread.id) {
+                skipped += 1;
+                continue;
+            }
+            thread.cwd = entry.path.clone();
+            thread.session_id = Some(Uuid::new_v4().to_string());
+            thread.session_needs_seed = true;
+            imported.push(thread.id.clone());
+            threads.push(thread);
+        }
+    }
+    if !imported.is_empty() {
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(&event_sink, claude_threads, &workspace_id, &imported, &[]).await;
+    }
+    Ok(json!({
+        "result": {
+            "imported": imported,
+            "skipped": skipped,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_codex_rollout, scan_codex_sessions};
+    use crate::shared::redaction_core::RedactionPipeline;
+    use crate::types::AppSettings;
+    use uuid::Uuid;
+
+    #[test]
+    fn parse_codex_rollout_builds_turns_and_skips_injected_context() {
+        let content = [
+            r#"{"timestamp":"2025-01-01T00:00:00Z","type":"session_meta","payload":{"id":"abc","cwd":"/repo"}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:01Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"<environment_context>cwd</environment_context>"}]}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:02Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"Fix the bug"}]}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:03Z","type":"response_item","payload":{"type":"reasoning","summary":[]}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:04Z","type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Done."}]}}"#,
+        ]
+        .join("\n");
+        let thread = parse_codex_rollout(&content).expect("thread");
+        assert_eq!(thread.id, "abc");
+        assert_eq!(thread.cwd, "/repo");
+        assert_eq!(thread.preview, "Fix the bug");
+        assert_eq!(thread.turns.len(), 1);
+        assert_eq!(thread.turns[0].items[0].text, "Fix the bug");
+        assert_eq!(thread.turns[0].items[1].text, "Done.");
+    }
+
+    #[test]
+    fn parse_codex_rollout_accepts_legacy_format() {
+        let content = [
+            r#"{"id":"legacy","timestamp":"2025-01-01T00:00:00Z","instructions":null}"#,
+            r#"{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}"#,
+            r#"{"type":"message","role":"assistant","content":[{"type":"output_text","text":"hi"}]}"#,
+        ]
+        .join("\n");
+        let thread = parse_codex_rollout(&content).expect("thread");
+        assert_eq!(thread.id, "legacy");
+        assert_eq!(thread.turns.len(), 1);
+        assert!(parse_codex_rollout("").is_none());
+    }
+
+    #[test]
+    fn imported_sessions_are_redacted_before_they_are_stored() {
+        let sessions_dir =
+            std::env::temp_dir().join(format!("polycode-codex-import-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(sessions_dir.join("2025/01/01")).expect("create sessions");
+        let content = [
+            r#"{"timestamp":"2025-01-01T00:00:00Z","type":"session_meta","payload":{"id":"abc","cwd":"/repo"}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:01Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"Which key?"}]}}"#,
+            r#"{"timestamp":"2025-01-01T00:00:02Z","type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Use sk-ant-REDACTED"}]}}"#,
+        ]
+        .join("\n");
+        std::fs::write(sessions_dir.join("2025/01/01/rollout-abc.jsonl"), content)
+            .expect("write rollout");
+
+        let redaction = RedactionPipeline::compile(&AppSettings::default().redaction_rules);
+        let threads = scan_codex_sessions(&sessions_dir, "/repo", &redaction);
+        let _ = std::fs::remove_dir_all(&sessions_dir);
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].turns[0].items[1].text, "Use [REDACTED]");
+    }
+}
@@ -0,0 +1,259 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::EventSink;
+use crate::providers;
+use crate::shared::claude_core::{
+    build_turns_from_history_messages, emit_thread_list_updated, now_ms, persist_threads_store,
+    preview_from_text, run_claude_prompt, ClaudeThreadRecord, ClaudeThreadsStore, GenerationParams,
+    HistoryMessage,
+};
+use crate::shared::codex_core::run_background_prompt;
+use crate::shared::git_core::run_git_command;
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+/// Tag of the threads that record generated commit messages.
+pub(crate) const COMMIT_MESSAGE_TAG: &str = "commit-message";
+const COMMIT_MESSAGE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Enough of a diff to summarize; bigger ones are cut so the prompt stays cheap.
+const MAX_DIFF_CHARS: usize = 60_000;
+
+pub(crate) fn build_commit_message_prompt(diff: &str) -> String {
+    format!(
+        "Generate a concise git commit message for the following changes. \
+Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
+Keep the summary line under 72 characters. \
+Only output the commit message, nothing else.\n\n\
+Changes:\n{diff}"
+    )
+}
+
+fn truncate_diff(diff: &str) -> String {
+    if diff.len() <= MAX_DIFF_CHARS {
+        return diff.to_string();
+    }
+    let mut end = MAX_DIFF_CHARS;
+    while !diff.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[diff truncated]", &diff[..end])
+}
+
+/// The reply without the code fence models tend to add even when asked not to.
+fn clean_commit_message(reply: &str) -> String {
+    let trimmed = reply.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.split_once('\n').map_or(inner, |(_, body)| body))
+        .unwrap_or(trimmed);
+    unfenced.trim().to_string()
+}
+
+/// A finished two-message thread holding the request and the suggestion. The request lists the
+/// changed files instead of the whole diff to keep the store small; replying to the thread
+/// starts a Claude session seeded with it.
+fn commit_message_thread(
+    cwd: &str,
+    request: String,
+    message: &str,
+    now: i64,
+) -> ClaudeThreadRecord {
+    let id = Uuid::new_v4().to_string();
+    let messages = [
+        HistoryMessage {
+            role: "user".to_string(),
+            text: request,
+            timestamp_ms: now,
+        },
+        HistoryMessage {
+            role: "assistant".to_string(),
+            text: message.to_string(),
+            timestamp_ms: now,
+        },
+    ];
+    let turns = build_turns_from_history_messages(&id, &messages);
+    ClaudeThreadRecord {
+        id,
+        cwd: cwd.to_string(),
+        preview: preview_from_text(message),
+        created_at: now,
+        updated_at: now,
+        name: Some("Commit message".to_string()),
+        tags: vec![COMMIT_MESSAGE_TAG.to_string()],
+        session_id: Some(Uuid::new_v4().to_string()),
+        session_needs_seed: true,
+        provider_session_id: None,
+        cwd_override: None,
+        generation: GenerationParams::default(),
+        worktree: None,
+        summary: None,
+        stats: Some(ThreadStats::from_turns(&turns, ProviderKind::Claude)),
+        last_read_at: Some(now),
+        turns,
+    }
+}
+
+/// Drafts a commit message for the staged changes, or for every change to tracked files when
+/// nothing is staged, with the workspace's provider. With `commit` the staged changes are then
+/// committed with it; callers pass it only once the user has agreed to commit. Claude
+/// workspaces keep the exchange as a thread tagged `commit-message`; Codex runs it on a hidden
+/// thread that is archived afterwards.
+pub(crate) async fn generate_commit_message_core<E, FResolveGitRoot>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    commit: bool,
+    resolve_git_root: FResolveGitRoot,
+    event_sink: E,
+) -> Result<Value, String>
+where
+    E: EventSink,
+    FResolveGitRoot: Fn(&WorkspaceEntry) -> Result<PathBuf, String>,
+{
+    let (entry, parent_entry) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(|| "workspace not found".to_string())?;
+        let parent_entry = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        (entry, parent_entry)
+    };
+    let settings = app_settings.lock().await.clone();
+    let repo = resolve_git_root(&entry)?;
+
+    let staged_diff = run_git_command(&repo, &["diff", "--staged", "--no-color"]).await?;
+    let staged = !staged_diff.is_empty();
+    if commit && !staged {
+        return Err("Stage the changes to commit first.".to_string());
+    }
+    let (diff, stat) = if staged {
+        let stat = run_git_command(&repo, &["diff", "--staged", "--stat"]).await?;
+        (staged_diff, stat)
+    } else {
+        let diff = run_git_command(&repo, &["diff", "HEAD", "--no-color"]).await?;
+        let stat = run_git_command(&repo, &["diff", "HEAD", "--stat"]).await?;
+        (diff, stat)
+    };
+    if diff.is_empty() {
+        return Err("No changes to generate commit message for".to_string());
+    }
+    let prompt = build_commit_message_prompt(&truncate_diff(&diff));
+
+    let provider = providers::resolve_workspace_provider(&entry, Some(&settings));
+    let reply = match provider {
+        ProviderKind::Claude => {
+            run_claude_prompt(
+                &entry,
+                parent_entry.as_ref(),
+                &settings,
+                &prompt,
+                None,
+                COMMIT_MESSAGE_TIMEOUT,
+            )
+            .await?
+        }
+        ProviderKind::Codex => {
+            let session = sessions
+                .lock()
+                .await
+                .get(&workspace_id)
+                .cloned()
+                .ok_or_else(|| "workspace not connected".to_string())?;
+            run_background_prompt(
+                &session,
+                &workspace_id,
+                &prompt,
+                COMMIT_MESSAGE_TIMEOUT,
+                &event_sink,
+            )
+            .await?
+        }
+        ProviderKind::Gemini => {
+            return Err("Provider `gemini` is not implemented yet.".to_string());
+        }
+    };
+    let message = clean_commit_message(&reply);
+    if message.is_empty() {
+        return Err("No commit message was generated".to_string());
+    }
+
+    let thread_id = if provider == ProviderKind::Claude {
+        let scope = if staged { "staged" } else { "uncommitted" };
+        let request = format!("Write a commit message for the {scope} changes:\n{stat}");
+        let thread = commit_message_thread(&entry.path, request, &message, now_ms());
+        let thread_id = thread.id.clone();
+        claude_threads
+            .lock()
+            .await
+            .entry(workspace_id.clone())
+            .or_default()
+            .insert(0, thread);
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(
+            &event_sink,
+            claude_threads,
+            &workspace_id,
+            std::slice::from_ref(&thread_id),
+            &[],
+        )
+        .await;
+        Some(thread_id)
+    } else {
+        None
+    };
+
+    let commit_sha = if commit {
+        run_git_command(&repo, &["commit", "-m", &message]).await?;
+        Some(run_git_command(&repo, &["rev-parse", "HEAD"]).await?)
+    } else {
+        None
+    };
+    Ok(json!({
+        "result": {
+            "message": message,
+            "provider": provider.as_str(),
+            "staged": staged,
+            "threadId": thread_id,
+            "commit": commit_sha,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_commit_message, truncate_diff, MAX_DIFF_CHARS};
+
+    #[test]
+    fn cleans_fenced_replies_and_truncates_long_diffs() {
+        assert_eq!(
+            clean_commit_message("```text\nfix: handle empty diffs\n\nMore detail.\n```\n"),
+            "fix: handle empty diffs\n\nMore detail."
+        );
+        assert_eq!(clean_commit_message("```\nfeat: add x\n```"), "feat: add x");
+        assert_eq!(clean_commit_message("  docs: typo \n"), "docs: typo");
+
+        let short = "diff --git a/x b/x";
+        assert_eq!(truncate_diff(short), short);
+        let long = "é".repeat(MAX_DIFF_CHARS);
+        let truncated = truncate_diff(&long);
+        assert!(truncated.ends_with("\n[diff truncated]"));
+        assert!(truncated.len() <= MAX_DIFF_CHARS + "\n[diff truncated]".len());
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::types::WorkspaceEntry;
+
+const MAX_CONTEXT_FILE_BYTES: usize = 64 * 1024;
+const MAX_CONTEXT_TOTAL_BYTES: usize = 256 * 1024;
+
+/// What was injected into a turn, recorded so the turn can be audited later.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct ContextFileRecord {
+    pub(crate) path: String,
+    pub(crate) sha256: String,
+    pub(crate) bytes: usize,
+    #[serde(default)]
+    pub(crate) truncated: bool,
+}
+
+/// A snippet the user selected in the UI, attached to a message instead of pasted by hand.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct SelectionContextBlock {
+    #[serde(default)]
+    pub(crate) path: Option<String>,
+    #[serde(default)]
+    pub(crate) language: Option<String>,
+    #[serde(default, rename = "startLine")]
+    pub(crate) start_line: Option<u32>,
+    #[serde(default, rename = "endLine")]
+    pub(crate) end_line: Option<u32>,
+    pub(crate) content: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LoadedContextFile {
+    pub(crate) record: ContextFileRecord,
+    pub(crate) content: String,
+}
+
+fn resolve_context_path(workspace_path: &str, raw: &str) -> Option<PathBuf> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let candidate = Path::new(trimmed);
+    let joined = if candidate.is_absolute() {
+        candidate.to_path_buf()
+    } else {
+        Path::new(workspace_path).join(candidate)
+    };
+    joined.canonicalize().ok().filter(|path| path.is_file())
+}
+
+fn truncate_to_char_boundary(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// The workspace's configured context files; a worktree without its own uses its parent's.
+pub(crate) fn resolve_context_paths(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> Vec<String> {
+    if entry.settings.context_files.is_empty() {
+        parent_entry
+            .map(|parent| parent.settings.context_files.clone())
+            .unwrap_or_default()
+    } else {
+        entry.settings.context_files.clone()
+    }
+}
+
+/// Reads the configured context files, skipping missing paths and duplicates (by path or
+/// content). Each file and the combined total are capped so a large file can't blow up the prompt.
+pub(crate) fn load_context_files(workspace_path: &str, paths: &[String]) -> Vec<LoadedContextFile> {
+    let mut seen_paths = HashSet::new();
+    let mut seen_hashes = HashSet::new();
+    let mut remaining = MAX_CONTEXT_TOTAL_BYTES;
+    let mut loaded = Vec::new();
+    for raw in paths {
+        if remaining == 0 {
+            break;
+        }
+        let Some(path) = resolve_context_path(workspace_path, raw) else {
+            continue;
+        };
+        if !seen_paths.insert(path.clone()) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        if !seen_hashes.insert(sha256.clone()) {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&bytes);
+        let cap = remaining.min(MAX_CONTEXT_FILE_BYTES);
+        let content = truncate_to_char_boundary(&text, cap).to_string();
+        remaining -= content.len();
+        let display_path = path
+            .strip_prefix(workspace_path)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        loaded.push(LoadedContextFile {
+            record: ContextFileRecord {
+                path: display_path,
+                sha256,
+                bytes: bytes.len(),
+                truncated: content.len() < text.len(),
+            },
+            content,
+        });
+    }
+    loaded
+}
+
+pub(crate) fn format_context_blocks(files: &[LoadedContextFile]) -> String {
+    let mut output = String::new();
+    for file in files {
+        output.push_str(&format!("<context file=\"{}\">\n", file.record.path));
+        output.push_str(file.content.trim_end());
+        if file.record.truncated {
+            output.push_str("\n[truncated]");
+        }
+        output.push_str("\n</context>\n\n");
+    }
+    output
+}
+
+fn language_for_path(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" => "python",
+        "go" => "go",
+        "rb" => "ruby",
+        "java" => "java",
+        "kt" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "sh" | "bash" | "zsh" => "bash",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        "css" => "css",
+        "html" => "html",
+        "sql" => "sql",
+        _ => "",
+    }
+}
+
+fn format_selection_block(block: &SelectionContextBlock) -> String {
+    let path = block
+        .path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty());
+    let language = block
+        .language
+        .as_deref()
+        .map(str::trim)
+        .filter(|language| !language.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| path.map(language_for_path).unwrap_or_default().to_string());
+    let range = match (block.start_line, block.end_line) {
+        (Some(start), Some(end)) if end > start => format!(":{start}-{end}"),
+        (Some(start), _) => format!(":{start}"),
+        _ => String::new(),
+    };
+    // Use a fence longer than any backtick run inside the snippet so it can't close early.
+    let longest_run = block
+        .content
+        .split(|ch| ch != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let mut output = String::new();
+    if let Some(path) = path {
+        output.push_str(&format!("{path}{range}\n"));
+    }
+    output.push_str(&format!("{fence}{language}\n"));
+    output.push_str(block.content.trim_end_matches('\n'));
+    output.push_str(&format!("\n{fence}"));
+    output
+}
+
+/// Appends selection blocks to the message text in the order they were given.
+pub(crate) fn compose_message_with_context(
+    text: &str,
+    blocks: &[SelectionContextBlock],
+) -> Result<String, String> {
+    let formatted = blocks
+        .iter()
+        .filter(|block| !block.content.trim().is_empty())
+        .map(format_selection_block)
+        .collect::<Vec<_>>();
+    let text = text.trim();
+    if text.is_empty() && formatted.is_empty() {
+        return Err("empty user message".to_string());
+    }
+    let mut sections = Vec::new();
+    if !text.is_empty() {
+        sections.push(text.to_string());
+    }
+    sections.extend(formatted);
+    Ok(sections.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compose_message_with_context, format_context_blocks, load_context_files,
+        truncate_to_char_boundary, SelectionContextBlock,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn load_context_files_dedups_by_path_and_content() {
+        let dir = std::env::temp_dir().join(format!("context-files-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(dir.join("a.md"), "alpha").expect("write a");
+        std::fs::write(dir.join("b.md"), "alpha").expect("write b");
+        std::fs::write(dir.join("c.md"), "gamma").expect("write c");
+        let workspace = dir
+            .canonicalize()
+            .expect("canonical")
+            .to_string_lossy()
+            .to_string();
+
+        let loaded = load_context_files(
+            &workspace,
+            &[
+                "a.md".to_string(),
+                "./a.md".to_string(),
+                "b.md".to_string(),
+                "c.md".to_string(),
+                "missing.md".to_string(),
+            ],
+        );
+        let paths = loaded
+            .iter()
+            .map(|file| file.record.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a.md", "c.md"]);
+
+        let blocks = format_context_blocks(&loaded);
+        assert!(blocks.starts_with("<context file=\"a.md\">\nalpha\n</context>"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compose_message_formats_selection_blocks() {
+        let block = SelectionContextBlock {
+            path: Some("src/lib.rs".to_string()),
+            language: None,
+            start_line: Some(10),
+            end_line: Some(12),
+            content: "fn main() {}\n".to_string(),
+        };
+        let message = compose_message_with_context("Explain this", &[block]).expect("message");
+        assert_eq!(
+            message,
+            "Explain this\n\nsrc/lib.rs:10-12\n```rust\nfn main() {}\n```"
+        );
+
+        let fenced = SelectionContextBlock {
+            path: None,
+            language: Some("md".to_string()),
+            start_line: None,
+            end_line: None,
+            content: "```\ncode\n```".to_string(),
+        };
+        let message = compose_message_with_context("", &[fenced]).expect("message");
+        assert!(message.starts_with("````md\n"));
+        assert!(compose_message_with_context("  ", &[]).is_err());
+    }
+
+    #[test]
+    fn truncate_respects_char_boundaries() {
+        assert_eq!(truncate_to_char_boundary("héllo", 2), "h");
+        assert_eq!(truncate_to_char_boundary("abc", 10), "abc");
+    }
+}
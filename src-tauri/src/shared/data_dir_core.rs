@@ -0,0 +1,599 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+
+use crate::shared::settings_core::ensure_not_read_only;
+use crate::shared::thread_worktree_core::THREAD_WORKTREES_DIR_NAME;
+use crate::types::AppSettings;
+
+/// Kept in the default data dir and points at the one actually in use.
+const LOCATOR_FILE_NAME: &str = "data_location.json";
+/// A file with this name next to the executable switches on portable mode.
+const PORTABLE_MARKER_FILE_NAME: &str = "portable";
+const PORTABLE_DATA_DIR_NAME: &str = "data";
+/// Profiles other than the default one keep their data in `profiles/<name>` of the data dir.
+const PROFILES_DIR_NAME: &str = "profiles";
+const DEFAULT_PROFILE: &str = "default";
+const MAX_PROFILE_NAME_LEN: usize = 40;
+/// Left where they are on a move: git worktrees are registered by absolute path, and dictation
+/// models are large and always loaded from the default location.
+const STAY_BEHIND: &[&str] = &[
+    LOCATOR_FILE_NAME,
+    "worktrees",
+    THREAD_WORKTREES_DIR_NAME,
+    "models",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DataLocator {
+    #[serde(default, rename = "dataDir", skip_serializing_if = "Option::is_none")]
+    data_dir: Option<PathBuf>,
+    /// Set by `storage_migrate`; the data is moved out of here on the next start, before any
+    /// store is loaded.
+    #[serde(default, rename = "moveFrom", skip_serializing_if = "Option::is_none")]
+    move_from: Option<PathBuf>,
+    /// Active profile; unset for the default one. Set by `profile_switch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataDirLocation {
+    pub(crate) path: PathBuf,
+    pub(crate) portable: bool,
+}
+
+fn locator_path(default_dir: &Path) -> PathBuf {
+    default_dir.join(LOCATOR_FILE_NAME)
+}
+
+fn read_locator(default_dir: &Path) -> DataLocator {
+    fs::read_to_string(locator_path(default_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_locator(default_dir: &Path, locator: &DataLocator) -> Result<(), String> {
+    fs::create_dir_all(default_dir).map_err(|error| error.to_string())?;
+    let content = serde_json::to_string_pretty(locator).map_err(|error| error.to_string())?;
+    fs::write(locator_path(default_dir), content).map_err(|error| error.to_string())
+}
+
+fn portable_marker_path() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(PORTABLE_MARKER_FILE_NAME))
+}
+
+/// `data` next to the executable when the portable marker is present.
+fn portable_data_dir() -> Option<PathBuf> {
+    let marker = portable_marker_path().filter(|marker| marker.exists())?;
+    Some(marker.with_file_name(PORTABLE_DATA_DIR_NAME))
+}
+
+/// Where data lives: next to the executable in portable mode, else wherever the last
+/// `storage_migrate` pointed, else `default_dir`. Nothing on disk changes, so while a move is
+/// still pending this is where the data is being moved from. The active profile's folder inside
+/// it is used when a profile other than the default one is active.
+pub(crate) fn resolve_data_dir(default_dir: &Path) -> DataDirLocation {
+    with_profile(default_dir, resolve_with(default_dir, portable_data_dir()))
+}
+
+/// Finishes a move scheduled by `storage_migrate`, then returns the data dir as
+/// [`resolve_data_dir`] would. Only the app calls this, at startup before any store is loaded.
+#[allow(dead_code)]
+pub(crate) fn apply_pending_move(default_dir: &Path) -> DataDirLocation {
+    let portable_dir = portable_data_dir();
+    let location = finish_move(default_dir, portable_dir.clone());
+    if portable_dir.is_some() && !location.portable {
+        // The move into the portable dir failed; drop the marker so the next start does not
+        // come up empty.
+        if let Some(marker) = portable_marker_path() {
+            let _ = fs::remove_file(marker);
+        }
+    }
+    let profile_location = with_profile(default_dir, location.clone());
+    if let Err(error) = fs::create_dir_all(&profile_location.path) {
+        eprintln!(
+            "Failed to open profile {}: {error}",
+            profile_location.path.display()
+        );
+        return location;
+    }
+    profile_location
+}
+
+fn with_profile(default_dir: &Path, location: DataDirLocation) -> DataDirLocation {
+    let Some(profile) = read_locator(default_dir).profile else {
+        return location;
+    };
+    let path = profile_dir(&location.path, &profile);
+    DataDirLocation { path, ..location }
+}
+
+fn profile_dir(base_dir: &Path, profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        base_dir.to_path_buf()
+    } else {
+        base_dir.join(PROFILES_DIR_NAME).join(profile)
+    }
+}
+
+/// The data dir shared by all profiles and the profile `data_dir` belongs to.
+fn split_profile(data_dir: &Path) -> (PathBuf, String) {
+    let parent = data_dir.parent();
+    match (parent, data_dir.file_name()) {
+        (Some(parent), Some(name)) if parent.file_name() == Some(PROFILES_DIR_NAME.as_ref()) => (
+            parent.parent().unwrap_or(parent).to_path_buf(),
+            name.to_string_lossy().to_string(),
+        ),
+        _ => (data_dir.to_path_buf(), DEFAULT_PROFILE.to_string()),
+    }
+}
+
+fn validate_profile_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        return Ok(DEFAULT_PROFILE.to_string());
+    }
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PROFILE_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'));
+    if !valid {
+        return Err(format!(
+            "Profile names use up to {MAX_PROFILE_NAME_LEN} letters, digits, `-`, `_` or `.`."
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// The default profile followed by the ones created with `profile_switch`, by name.
+pub(crate) fn list_profiles_core(current_dir: &Path) -> Result<Value, String> {
+    let (base_dir, active) = split_profile(current_dir);
+    let mut names = match fs::read_dir(base_dir.join(PROFILES_DIR_NAME)) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| validate_profile_name(name).as_deref() == Ok(name.as_str()))
+            .filter(|name| name != DEFAULT_PROFILE)
+            .collect::<Vec<_>>(),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(error) => return Err(format!("Failed to read profiles: {error}")),
+    };
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    let profiles = names
+        .iter()
+        .map(|name| {
+            json!({
+                "name": name,
+                "dataDir": profile_dir(&base_dir, name).to_string_lossy(),
+                "active": *name == active,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "result": {
+            "active": active,
+            "profiles": profiles,
+        }
+    }))
+}
+
+/// Makes `name` the profile loaded on the next start, creating its empty data dir the first
+/// time. Settings, workspaces and threads are per profile, so nothing carries over. The stores
+/// in memory belong to the current profile; callers restart once this returns.
+pub(crate) async fn switch_profile_core(
+    app_settings: &Mutex<AppSettings>,
+    default_dir: &Path,
+    current_dir: &Path,
+    name: String,
+) -> Result<Value, String> {
+    ensure_not_read_only(app_settings, "Switching profiles").await?;
+    let name = validate_profile_name(&name)?;
+    let (base_dir, active) = split_profile(current_dir);
+    if name == active {
+        return Err(format!("Profile `{name}` is already active."));
+    }
+    let mut locator = read_locator(default_dir);
+    if locator.move_from.is_some() {
+        return Err("Restart to finish moving the data directory first.".to_string());
+    }
+    let target = profile_dir(&base_dir, &name);
+    let created = !target.exists();
+    ensure_writable(&target)?;
+    locator.profile = (name != DEFAULT_PROFILE).then(|| name.clone());
+    write_locator(default_dir, &locator)?;
+    Ok(json!({
+        "result": {
+            "profile": name,
+            "dataDir": target.to_string_lossy(),
+            "created": created,
+            "restartRequired": true,
+        }
+    }))
+}
+
+fn resolve_with(default_dir: &Path, portable_dir: Option<PathBuf>) -> DataDirLocation {
+    let locator = read_locator(default_dir);
+    let portable = portable_dir.is_some();
+    let target = portable_dir
+        .or(locator.data_dir)
+        .unwrap_or_else(|| default_dir.to_path_buf());
+    match locator.move_from {
+        Some(source) if source != target => DataDirLocation {
+            path: source,
+            portable: false,
+        },
+        _ => DataDirLocation {
+            path: target,
+            portable,
+        },
+    }
+}
+
+fn finish_move(default_dir: &Path, portable_dir: Option<PathBuf>) -> DataDirLocation {
+    let mut locator = read_locator(default_dir);
+    let portable = portable_dir.is_some();
+    let target = portable_dir
+        .or_else(|| locator.data_dir.clone())
+        .unwrap_or_else(|| default_dir.to_path_buf());
+    let Some(source) = locator.move_from.take() else {
+        return DataDirLocation {
+            path: target,
+            portable,
+        };
+    };
+    if source == target {
+        let _ = write_locator(default_dir, &locator);
+        return DataDirLocation {
+            path: target,
+            portable,
+        };
+    }
+    match move_data(&source, &target) {
+        Ok(()) => {
+            if let Err(error) = write_locator(default_dir, &locator) {
+                eprintln!("Failed to update the data location: {error}");
+            }
+            DataDirLocation {
+                path: target,
+                portable,
+            }
+        }
+        Err(error) => {
+            eprintln!(
+                "Failed to move data from {} to {}: {error}",
+                source.display(),
+                target.display()
+            );
+            locator.data_dir = (source != default_dir).then(|| source.clone());
+            let _ = write_locator(default_dir, &locator);
+            DataDirLocation {
+                path: source,
+                portable: false,
+            }
+        }
+    }
+}
+
+fn movable_entries(dir: &Path) -> Result<Vec<String>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("Failed to read {}: {error}", dir.display())),
+    };
+    Ok(entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !STAY_BEHIND.contains(&name.as_str()))
+        .collect())
+}
+
+fn copy_recursively(source: &Path, target: &Path) -> std::io::Result<()> {
+    if fs::metadata(source)?.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(source, target).map(|_| ())
+    }
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Copies every movable entry to `target` and only then removes the originals, so a failed
+/// copy leaves the data where it was.
+fn move_data(source: &Path, target: &Path) -> Result<(), String> {
+    let names = movable_entries(source)?;
+    if let Some(name) = names.iter().find(|name| target.join(name).exists()) {
+        return Err(format!("{} already exists", target.join(name).display()));
+    }
+    fs::create_dir_all(target).map_err(|error| error.to_string())?;
+    for (index, name) in names.iter().enumerate() {
+        if let Err(error) = copy_recursively(&source.join(name), &target.join(name)) {
+            for copied in &names[..=index] {
+                let _ = remove_path(&target.join(copied));
+            }
+            return Err(format!("Failed to copy {name}: {error}"));
+        }
+    }
+    for name in &names {
+        if let Err(error) = remove_path(&source.join(name)) {
+            eprintln!(
+                "Failed to remove {} after moving it: {error}",
+                source.join(name).display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn ensure_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|error| format!("Cannot create {}: {error}", dir.display()))?;
+    let probe = dir.join(".polycode-write-test");
+    fs::write(&probe, b"")
+        .map_err(|error| format!("Cannot write to {}: {error}", dir.display()))?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+/// Schedules moving the data dir to `target`, or next to the executable when `portable` is
+/// set. The move happens on the next start, so the app has to be restarted.
+pub(crate) async fn migrate_data_dir_core(
+    app_settings: &Mutex<AppSettings>,
+    default_dir: &Path,
+    current_dir: &Path,
+    target: Option<String>,
+    portable: bool,
+) -> Result<Value, String> {
+    ensure_not_read_only(app_settings, "Moving the data directory").await?;
+    let portable_marker = portable_marker_path();
+    let target = if portable {
+        portable_marker
+            .as_ref()
+            .map(|marker| marker.with_file_name(PORTABLE_DATA_DIR_NAME))
+            .ok_or_else(|| "Unable to locate the executable.".to_string())?
+    } else {
+        let target = target
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| "A target directory is required.".to_string())?;
+        PathBuf::from(target)
+    };
+    if !target.is_absolute() {
+        return Err("The target directory must be an absolute path.".to_string());
+    }
+    if read_locator(default_dir).profile.is_some() {
+        return Err("Switch to the default profile before moving the data directory.".to_string());
+    }
+    if target == current_dir {
+        return Err("Data is already stored there.".to_string());
+    }
+    if target.starts_with(current_dir) || current_dir.starts_with(&target) {
+        return Err(
+            "The target cannot be inside the current data directory or contain it.".to_string(),
+        );
+    }
+    let conflicts = movable_entries(current_dir)?
+        .into_iter()
+        .filter(|name| target.join(name).exists())
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+        return Err(format!(
+            "{} already holds {}.",
+            target.display(),
+            conflicts.join(", ")
+        ));
+    }
+    ensure_writable(&target)?;
+
+    if let Some(marker) = portable_marker.filter(|marker| portable || marker.exists()) {
+        let result = if portable {
+            fs::write(&marker, b"")
+        } else {
+            fs::remove_file(&marker)
+        };
+        result.map_err(|error| format!("Failed to update {}: {error}", marker.display()))?;
+    }
+    let locator = DataLocator {
+        data_dir: (!portable && target != default_dir).then(|| target.clone()),
+        move_from: Some(current_dir.to_path_buf()),
+        profile: None,
+    };
+    write_locator(default_dir, &locator)?;
+    Ok(json!({
+        "result": {
+            "dataDir": target.to_string_lossy(),
+            "portable": portable,
+            "restartRequired": true,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        finish_move, list_profiles_core, migrate_data_dir_core, resolve_with, switch_profile_core,
+        with_profile, DataDirLocation,
+    };
+    use crate::types::AppSettings;
+    use std::fs;
+    use std::future::Future;
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime")
+            .block_on(future)
+    }
+
+    #[test]
+    fn pending_move_runs_only_when_finished_and_leaves_worktrees_behind() {
+        let root = std::env::temp_dir().join(format!("polycode-data-dir-test-{}", Uuid::new_v4()));
+        let default_dir = root.join("default");
+        let synced_dir = root.join("synced");
+        let settings = Mutex::new(AppSettings::default());
+        fs::create_dir_all(default_dir.join("worktrees/ws-1")).expect("create worktrees");
+        fs::create_dir_all(default_dir.join("raw-logs")).expect("create logs");
+        fs::write(default_dir.join("claude_threads.json"), "{}").expect("write threads");
+        fs::write(default_dir.join("raw-logs/ws-1.log"), "log").expect("write log");
+        let read_only = Mutex::new(AppSettings {
+            read_only: true,
+            ..AppSettings::default()
+        });
+        let denied = block_on(migrate_data_dir_core(
+            &read_only,
+            &default_dir,
+            &default_dir,
+            Some(synced_dir.to_string_lossy().to_string()),
+            false,
+        ))
+        .expect_err("read-only");
+        assert!(denied.starts_with("PermissionDenied:"));
+        assert!(block_on(switch_profile_core(
+            &read_only,
+            &default_dir,
+            &default_dir,
+            "work".to_string()
+        ))
+        .is_err());
+        assert_eq!(resolve_with(&default_dir, None).path, default_dir);
+
+        let result = block_on(migrate_data_dir_core(
+            &settings,
+            &default_dir,
+            &default_dir,
+            Some(synced_dir.to_string_lossy().to_string()),
+            false,
+        ))
+        .expect("migrate");
+        assert_eq!(result["result"]["restartRequired"], true);
+        assert!(default_dir.join("claude_threads.json").exists());
+        assert_eq!(resolve_with(&default_dir, None).path, default_dir);
+        assert!(!synced_dir.join("claude_threads.json").exists());
+
+        let location = finish_move(&default_dir, None);
+        assert_eq!(
+            location,
+            DataDirLocation {
+                path: synced_dir.clone(),
+                portable: false,
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(synced_dir.join("raw-logs/ws-1.log")).expect("moved log"),
+            "log"
+        );
+        assert!(synced_dir.join("claude_threads.json").exists());
+        assert!(!default_dir.join("claude_threads.json").exists());
+        assert!(default_dir.join("worktrees/ws-1").exists());
+        assert_eq!(resolve_with(&default_dir, None).path, synced_dir);
+
+        assert!(block_on(migrate_data_dir_core(
+            &settings,
+            &default_dir,
+            &synced_dir,
+            Some(synced_dir.join("nested").to_string_lossy().to_string()),
+            false,
+        ))
+        .is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn switching_profiles_points_the_next_start_at_their_own_data_dir() {
+        let root = std::env::temp_dir().join(format!("polycode-profiles-test-{}", Uuid::new_v4()));
+        let default_dir = root.join("default");
+        let settings = Mutex::new(AppSettings::default());
+        let resolve = || with_profile(&default_dir, resolve_with(&default_dir, None)).path;
+        let names = |dir: &std::path::Path| {
+            let listing = list_profiles_core(dir).expect("list");
+            let names = listing["result"]["profiles"]
+                .as_array()
+                .expect("profiles")
+                .iter()
+                .map(|profile| profile["name"].as_str().unwrap_or_default().to_string())
+                .collect::<Vec<_>>();
+            (
+                listing["result"]["active"].as_str().map(str::to_string),
+                names,
+            )
+        };
+        assert_eq!(
+            names(&default_dir),
+            (Some("default".to_string()), vec!["default".to_string()])
+        );
+
+        for invalid in ["", "../work", ".hidden", "a/b"] {
+            assert!(block_on(switch_profile_core(
+                &settings,
+                &default_dir,
+                &default_dir,
+                invalid.to_string()
+            ))
+            .is_err());
+        }
+        let switched = block_on(switch_profile_core(
+            &settings,
+            &default_dir,
+            &default_dir,
+            "work".to_string(),
+        ))
+        .expect("switch");
+        assert_eq!(switched["result"]["created"], true);
+        let work_dir = default_dir.join("profiles/work");
+        assert_eq!(resolve(), work_dir);
+        assert_eq!(
+            names(&work_dir),
+            (
+                Some("work".to_string()),
+                vec!["default".to_string(), "work".to_string()]
+            )
+        );
+        assert!(block_on(switch_profile_core(
+            &settings,
+            &default_dir,
+            &work_dir,
+            "work".to_string()
+        ))
+        .is_err());
+        assert!(block_on(migrate_data_dir_core(
+            &settings,
+            &default_dir,
+            &work_dir,
+            Some(root.join("elsewhere").to_string_lossy().to_string()),
+            false,
+        ))
+        .is_err());
+
+        block_on(switch_profile_core(
+            &settings,
+            &default_dir,
+            &work_dir,
+            "Default".to_string(),
+        ))
+        .expect("back");
+        assert_eq!(resolve(), default_dir);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::shared::claude_core::now_ms;
+use crate::shared::settings_core::ensure_not_read_only;
+use crate::types::AppSettings;
+
+const DRAFTS_FILE_NAME: &str = "drafts.json";
+
+/// An unsent composer message; `images` holds attachment paths as sent with a turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ThreadDraft {
+    pub(crate) text: String,
+    #[serde(default)]
+    pub(crate) images: Vec<String>,
+    #[serde(rename = "updatedAt")]
+    pub(crate) updated_at: i64,
+}
+
+/// Drafts keyed by workspace id, then thread id.
+pub(crate) type DraftsStore = Arc<Mutex<HashMap<String, HashMap<String, ThreadDraft>>>>;
+
+pub(crate) fn drafts_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(DRAFTS_FILE_NAME)
+}
+
+pub(crate) fn read_drafts(
+    path: &Path,
+) -> Result<HashMap<String, HashMap<String, ThreadDraft>>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&data).map_err(|error| error.to_string())
+}
+
+fn write_drafts(
+    path: &Path,
+    drafts: &HashMap<String, HashMap<String, ThreadDraft>>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(drafts).map_err(|error| error.to_string())?;
+    std::fs::write(path, data).map_err(|error| error.to_string())
+}
+
+/// Returns `None` when there is nothing worth keeping, which clears the thread's draft.
+fn normalize_draft(
+    text: String,
+    images: Option<Vec<String>>,
+    updated_at: i64,
+) -> Option<ThreadDraft> {
+    let images = images
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| !path.trim().is_empty())
+        .collect::<Vec<_>>();
+    if text.trim().is_empty() && images.is_empty() {
+        return None;
+    }
+    Some(ThreadDraft {
+        text,
+        images,
+        updated_at,
+    })
+}
+
+/// Stores the thread's draft, or removes it when both text and attachments are empty.
+pub(crate) async fn save_draft_core(
+    app_settings: &Mutex<AppSettings>,
+    drafts: &DraftsStore,
+    drafts_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+) -> Result<Value, String> {
+    ensure_not_read_only(app_settings, "Saving drafts").await?;
+    let draft = normalize_draft(text, images, now_ms());
+    // Hold the lock while writing so rapid saves land on disk in order.
+    let mut store = drafts.lock().await;
+    match draft.clone() {
+        Some(draft) => {
+            store
+                .entry(workspace_id)
+                .or_default()
+                .insert(thread_id, draft);
+        }
+        None => {
+            if let Some(threads) = store.get_mut(&workspace_id) {
+                threads.remove(&thread_id);
+                if threads.is_empty() {
+                    store.remove(&workspace_id);
+                }
+            }
+        }
+    }
+    write_drafts(drafts_path, &store)?;
+    Ok(json!({ "result": { "draft": draft } }))
+}
+
+/// Drops every draft kept for the given workspaces.
+pub(crate) async fn remove_workspace_drafts(
+    drafts: &DraftsStore,
+    drafts_path: &Path,
+    workspace_ids: &HashSet<String>,
+) -> Result<(), String> {
+    let mut store = drafts.lock().await;
+    let before = store.len();
+    store.retain(|workspace_id, _| !workspace_ids.contains(workspace_id));
+    if store.len() == before {
+        return Ok(());
+    }
+    write_drafts(drafts_path, &store)
+}
+
+pub(crate) async fn get_draft_core(
+    drafts: &DraftsStore,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let draft = drafts
+        .lock()
+        .await
+        .get(&workspace_id)
+        .and_then(|threads| threads.get(&thread_id))
+        .cloned();
+    Ok(json!({ "result": { "draft": draft } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_draft, save_draft_core};
+    use crate::types::AppSettings;
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    #[test]
+    fn normalize_draft_drops_empty_drafts() {
+        assert!(normalize_draft("  \n".to_string(), Some(vec![" ".to_string()]), 1).is_none());
+        let draft = normalize_draft(
+            String::new(),
+            Some(vec!["/tmp/shot.png".to_string(), String::new()]),
+            1,
+        )
+        .expect("draft with an attachment");
+        assert_eq!(draft.images, vec!["/tmp/shot.png".to_string()]);
+        let draft = normalize_draft("  half a prompt".to_string(), None, 1).expect("draft");
+        assert_eq!(draft.text, "  half a prompt");
+    }
+
+    #[test]
+    fn read_only_mode_keeps_drafts_off_disk() {
+        let drafts_path =
+            std::env::temp_dir().join(format!("polycode-drafts-{}.json", Uuid::new_v4()));
+        let settings = Mutex::new(AppSettings {
+            read_only: true,
+            ..AppSettings::default()
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let error = runtime
+            .block_on(save_draft_core(
+                &settings,
+                &Default::default(),
+                &drafts_path,
+                "ws".to_string(),
+                "thread".to_string(),
+                "draft".to_string(),
+                None,
+            ))
+            .expect_err("read-only");
+        assert!(error.starts_with("PermissionDenied:"));
+        assert!(!drafts_path.exists());
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::backend::events::AppServerEvent;
+
+/// Which app-server events a subscriber receives. An empty list does not restrict.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EventFilter {
+    #[serde(default)]
+    pub(crate) workspace_ids: Vec<String>,
+    /// Exact methods such as `error`, or whole families ending in `/` such as `turn/`.
+    #[serde(default)]
+    pub(crate) methods: Vec<String>,
+}
+
+impl EventFilter {
+    pub(crate) fn matches(&self, event: &AppServerEvent) -> bool {
+        let method = event
+            .message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let in_workspace =
+            self.workspace_ids.is_empty() || self.workspace_ids.contains(&event.workspace_id);
+        let wanted_method = self.methods.is_empty()
+            || self.methods.iter().any(|wanted| {
+                if wanted.ends_with('/') {
+                    method.starts_with(wanted.as_str())
+                } else {
+                    method == wanted
+                }
+            });
+        in_workspace && wanted_method
+    }
+}
+
+/// Filters by subscriber: a window label in the app, a connection in the daemon. Read on every
+/// emitted event, so it is a plain lock rather than an async one.
+pub(crate) type EventFiltersStore = Arc<RwLock<HashMap<String, EventFilter>>>;
+
+/// Whether `subscriber` wants the event; subscribers that never set a filter get everything.
+pub(crate) fn accepts_event(
+    filters: &EventFiltersStore,
+    subscriber: &str,
+    event: &AppServerEvent,
+) -> bool {
+    filters
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(subscriber)
+        .is_none_or(|filter| filter.matches(event))
+}
+
+/// Replaces a subscriber's filter, or clears it with `None` so every event is delivered again.
+pub(crate) fn subscribe_events_core(
+    filters: &EventFiltersStore,
+    subscriber: String,
+    filter: Option<EventFilter>,
+) -> Result<Value, String> {
+    let mut store = filters.write().unwrap_or_else(PoisonError::into_inner);
+    match &filter {
+        Some(filter) => {
+            store.insert(subscriber.clone(), filter.clone());
+        }
+        None => {
+            store.remove(&subscriber);
+        }
+    }
+    Ok(json!({
+        "result": {
+            "subscriber": subscriber,
+            "filter": filter,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts_event, subscribe_events_core, EventFilter, EventFiltersStore};
+    use crate::backend::events::AppServerEvent;
+    use serde_json::json;
+
+    fn event(workspace_id: &str, method: &str) -> AppServerEvent {
+        AppServerEvent {
+            workspace_id: workspace_id.to_string(),
+            message: json!({ "method": method, "params": {} }),
+        }
+    }
+
+    #[test]
+    fn filters_by_workspace_and_method_family_per_subscriber() {
+        let filters = EventFiltersStore::default();
+        let delta = event("ws-1", "item/agentMessage/delta");
+        assert!(accepts_event(&filters, "main", &delta));
+
+        let filter = serde_json::from_value::<EventFilter>(json!({
+            "workspaceIds": ["ws-1"],
+            "methods": ["error", "turn/"],
+        }))
+        .expect("filter");
+        subscribe_events_core(&filters, "panel".to_string(), Some(filter)).expect("subscribe");
+        assert!(!accepts_event(&filters, "panel", &delta));
+        assert!(accepts_event(&filters, "panel", &event("ws-1", "error")));
+        assert!(accepts_event(
+            &filters,
+            "panel",
+            &event("ws-1", "turn/completed")
+        ));
+        assert!(!accepts_event(&filters, "panel", &event("ws-2", "error")));
+        assert!(!accepts_event(&filters, "panel", &event("ws-1", "errors")));
+        assert!(accepts_event(&filters, "main", &delta));
+
+        let workspace_only = EventFilter {
+            workspace_ids: vec!["ws-2".to_string()],
+            methods: Vec::new(),
+        };
+        subscribe_events_core(&filters, "panel".to_string(), Some(workspace_only))
+            .expect("replace");
+        assert!(accepts_event(
+            &filters,
+            "panel",
+            &event("ws-2", "item/agentMessage/delta")
+        ));
+        assert!(!accepts_event(&filters, "panel", &delta));
+
+        subscribe_events_core(&filters, "panel".to_string(), None).expect("clear");
+        assert!(accepts_event(&filters, "panel", &delta));
+    }
+}
@@ -0,0 +1,185 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::AppServerEvent;
+use crate::shared::claude_core::now_ms;
+use crate::types::WorkspaceEntry;
+
+/// Methods mirrored besides the `turn/*` and `item/*` families.
+const EXTRA_MIRRORED_METHODS: &[&str] = &["error"];
+
+/// Whether an app-server message belongs to a turn's progress.
+pub(crate) fn is_turn_event(message: &Value) -> bool {
+    message
+        .get("method")
+        .and_then(Value::as_str)
+        .is_some_and(|method| {
+            method.starts_with("turn/")
+                || method.starts_with("item/")
+                || EXTRA_MIRRORED_METHODS.contains(&method)
+        })
+}
+
+/// One NDJSON line for a mirrored event.
+fn mirror_line(event: &AppServerEvent, at: i64) -> String {
+    let line = json!({
+        "workspaceId": event.workspace_id,
+        "at": at,
+        "method": event.message.get("method").cloned().unwrap_or(Value::Null),
+        "params": event.message.get("params").cloned().unwrap_or(Value::Null),
+    });
+    format!("{line}\n")
+}
+
+/// Opens a mirror target without blocking: a unix socket is connected to, a named pipe is
+/// opened only when something is reading it, and an existing regular file is appended to.
+/// Missing paths are not created, so a tool can bind its socket there later.
+#[cfg(unix)]
+fn open_target(path: &Path) -> io::Result<Box<dyn Write + Send>> {
+    use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+    use std::os::unix::net::UnixStream;
+
+    let file_type = std::fs::metadata(path)?.file_type();
+    if file_type.is_socket() {
+        let stream = UnixStream::connect(path)?;
+        stream.set_nonblocking(true)?;
+        return Ok(Box::new(stream));
+    }
+    let file = if file_type.is_fifo() {
+        OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)?
+    } else if file_type.is_file() {
+        OpenOptions::new().append(true).open(path)?
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a pipe, socket or file",
+        ));
+    };
+    Ok(Box::new(file))
+}
+
+/// Named pipes (`\\.\pipe\name`) open like files on Windows.
+#[cfg(not(unix))]
+fn open_target(path: &Path) -> io::Result<Box<dyn Write + Send>> {
+    let file = if path.is_file() {
+        OpenOptions::new().append(true).open(path)?
+    } else {
+        OpenOptions::new().write(true).open(path)?
+    };
+    Ok(Box::new(file))
+}
+
+struct MirrorTarget {
+    path: String,
+    writer: Box<dyn Write + Send>,
+}
+
+/// Open mirror targets by workspace. A target that can't be opened or stops taking writes is
+/// dropped and reopened on the next event, so readers can come and go; events sent while
+/// nothing is reading are lost.
+#[derive(Default)]
+pub(crate) struct EventMirror {
+    targets: HashMap<String, MirrorTarget>,
+}
+
+impl EventMirror {
+    /// Writes a turn event to the `eventMirrorPath` of its workspace, if one is set.
+    pub(crate) async fn mirror(
+        &mut self,
+        workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+        event: &AppServerEvent,
+    ) {
+        if !is_turn_event(&event.message) {
+            return;
+        }
+        let path = workspaces
+            .lock()
+            .await
+            .get(&event.workspace_id)
+            .and_then(|entry| entry.settings.event_mirror_path.clone());
+        self.mirror_to(path.as_deref(), event);
+    }
+
+    fn mirror_to(&mut self, path: Option<&str>, event: &AppServerEvent) {
+        let Some(path) = path.map(str::trim).filter(|path| !path.is_empty()) else {
+            self.targets.remove(&event.workspace_id);
+            return;
+        };
+        let target = match self.targets.remove(&event.workspace_id) {
+            Some(target) if target.path == path => target,
+            _ => match open_target(Path::new(path)) {
+                Ok(writer) => MirrorTarget {
+                    path: path.to_string(),
+                    writer,
+                },
+                Err(_) => return,
+            },
+        };
+        let MirrorTarget { path, mut writer } = target;
+        if writer
+            .write_all(mirror_line(event, now_ms()).as_bytes())
+            .is_ok()
+        {
+            self.targets
+                .insert(event.workspace_id.clone(), MirrorTarget { path, writer });
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{is_turn_event, EventMirror};
+    use crate::backend::events::AppServerEvent;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader};
+    use uuid::Uuid;
+
+    fn event(method: &str) -> AppServerEvent {
+        AppServerEvent {
+            workspace_id: "ws-1".to_string(),
+            message: json!({ "method": method, "params": { "threadId": "t-1" } }),
+        }
+    }
+
+    #[test]
+    fn mirrors_turn_events_to_a_unix_socket() {
+        use std::os::unix::net::UnixListener;
+
+        assert!(is_turn_event(&event("item/agentMessage/delta").message));
+        assert!(is_turn_event(&event("error").message));
+        assert!(!is_turn_event(&event("account/updated").message));
+
+        let path = std::env::temp_dir().join(format!("codex-monitor-mirror-{}", Uuid::new_v4()));
+        let path_text = path.to_string_lossy().to_string();
+        let mut mirror = EventMirror::default();
+        // Nothing is listening yet: the event is dropped without creating the path.
+        mirror.mirror_to(Some(&path_text), &event("turn/started"));
+        assert!(!path.exists());
+
+        let listener = UnixListener::bind(&path).expect("bind socket");
+        mirror.mirror_to(Some(&path_text), &event("turn/started"));
+        mirror.mirror_to(Some(&path_text), &event("turn/completed"));
+        let (stream, _) = listener.accept().expect("accept");
+        let lines = BufReader::new(stream)
+            .lines()
+            .take(2)
+            .map(|line| serde_json::from_str::<Value>(&line.expect("line")).expect("json"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines[0]["method"], "turn/started");
+        assert_eq!(lines[1]["method"], "turn/completed");
+        assert_eq!(lines[1]["workspaceId"], "ws-1");
+        assert_eq!(lines[1]["params"]["threadId"], "t-1");
+
+        mirror.mirror_to(None, &event("turn/started"));
+        assert!(mirror.targets.is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,532 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    build_turns_from_history_messages, emit_thread_list_updated, now_ms, parse_rfc3339_ms,
+    persist_threads_store, preview_from_text, redact_thread_in_place, ClaudeThreadRecord,
+    ClaudeThreadsStore, GenerationParams, HistoryMessage,
+};
+use crate::shared::redaction_core::RedactionPipeline;
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::types::{ProviderKind, WorkspaceEntry};
+
+/// Exports bigger than this are almost certainly not chat history.
+const MAX_IMPORT_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// History formats of other agent UIs that can be imported as threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExternalFormat {
+    /// Cursor chat data: `{ "tabs": [{ "bubbles": [...] }] }` or composer objects with a
+    /// `conversation` array.
+    Cursor,
+    /// Continue.dev session files from `~/.continue/sessions`.
+    Continue,
+    /// `conversations.json` from a ChatGPT data export.
+    ChatGpt,
+}
+
+impl ExternalFormat {
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "cursor" => Ok(Self::Cursor),
+            "continue" => Ok(Self::Continue),
+            "chatgpt" => Ok(Self::ChatGpt),
+            other => Err(format!(
+                "Unknown import format `{other}`; expected cursor, continue or chatgpt."
+            )),
+        }
+    }
+
+    /// Tag put on imported threads and prefix of their ids.
+    fn tag(self) -> &'static str {
+        match self {
+            Self::Cursor => "cursor",
+            Self::Continue => "continue",
+            Self::ChatGpt => "chatgpt",
+        }
+    }
+
+    fn conversations(self, document: &Value) -> Vec<ExternalConversation> {
+        match self {
+            Self::Cursor => cursor_conversations(document),
+            Self::Continue => continue_conversations(document),
+            Self::ChatGpt => chatgpt_conversations(document),
+        }
+    }
+}
+
+/// What every adapter produces; turned into a thread record by [`into_thread`].
+#[derive(Debug)]
+struct ExternalConversation {
+    id: String,
+    title: Option<String>,
+    created_at: Option<i64>,
+    updated_at: Option<i64>,
+    /// `(role, text, timestamp)`; roles other than `user` and `assistant` are dropped.
+    messages: Vec<(String, String, Option<i64>)>,
+}
+
+/// Accepts epoch seconds, epoch milliseconds (as numbers or strings) and RFC 3339.
+fn timestamp_ms(value: Option<&Value>) -> Option<i64> {
+    let seconds_or_ms = |number: f64| {
+        if number < 1e12 {
+            (number * 1000.0) as i64
+        } else {
+            number as i64
+        }
+    };
+    match value? {
+        Value::Number(number) => number.as_f64().map(seconds_or_ms),
+        Value::String(text) => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(seconds_or_ms)
+            .or_else(|| parse_rfc3339_ms(text.trim())),
+        _ => None,
+    }
+}
+
+/// Plain text of a message body: a string, or the text parts of an array.
+fn content_text(content: &Value) -> Option<String> {
+    let text = match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.as_str().or_else(|| part.get("text")?.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let trimmed = text.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn string_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| value.get(*key)?.as_str())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn cursor_conversations(document: &Value) -> Vec<ExternalConversation> {
+    let items = match document {
+        Value::Array(items) => items.iter().collect::<Vec<_>>(),
+        Value::Object(_) => match document
+            .get("tabs")
+            .or_else(|| document.get("allComposers"))
+        {
+            Some(Value::Array(items)) => items.iter().collect(),
+            _ => vec![document],
+        },
+        _ => Vec::new(),
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let id = string_field(item, &["tabId", "composerId"])?;
+            let entries = item
+                .get("bubbles")
+                .or_else(|| item.get("conversation"))?
+                .as_array()?;
+            let messages = entries
+                .iter()
+                .filter_map(|entry| {
+                    // Chat tabs use "user"/"ai", composers use 1/2.
+                    let role = match entry.get("type")? {
+                        Value::String(kind) if kind == "user" => "user",
+                        Value::String(kind) if kind == "ai" => "assistant",
+                        Value::Number(kind) if kind.as_u64() == Some(1) => "user",
+                        Value::Number(kind) if kind.as_u64() == Some(2) => "assistant",
+                        _ => return None,
+                    };
+                    let text = string_field(entry, &["text", "rawText"])?;
+                    Some((role.to_string(), text, None))
+                })
+                .collect();
+            let updated_at = timestamp_ms(
+                item.get("lastSendTime")
+                    .or_else(|| item.get("lastUpdatedAt")),
+            );
+            Some(ExternalConversation {
+                id,
+                title: string_field(item, &["chatTitle", "name"]),
+                created_at: timestamp_ms(item.get("createdAt")).or(updated_at),
+                updated_at,
+                messages,
+            })
+        })
+        .collect()
+}
+
+fn continue_conversations(document: &Value) -> Vec<ExternalConversation> {
+    let Some(id) = string_field(document, &["sessionId"]) else {
+        return Vec::new();
+    };
+    let Some(history) = document.get("history").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let messages = history
+        .iter()
+        .filter_map(|item| {
+            // Newer sessions wrap each message as `{ "message": {...}, "contextItems": [...] }`.
+            let message = item.get("message").unwrap_or(item);
+            let role = message.get("role")?.as_str()?.to_string();
+            let text = content_text(message.get("content")?)?;
+            Some((role, text, None))
+        })
+        .collect();
+    let created_at = timestamp_ms(document.get("dateCreated"));
+    vec![ExternalConversation {
+        id,
+        title: string_field(document, &["title"]),
+        created_at,
+        updated_at: created_at,
+        messages,
+    }]
+}
+
+/// A ChatGPT conversation is a tree of edits and regenerations; the branch ending at
+/// `current_node` is what the user last saw.
+fn chatgpt_branch(conversation: &Value) -> Vec<&Value> {
+    let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut branch = Vec::new();
+    let mut next = conversation.get("current_node").and_then(Value::as_str);
+    while let Some(node) = next.and_then(|node_id| mapping.get(node_id)) {
+        if branch.len() > mapping.len() {
+            break;
+        }
+        branch.push(node);
+        next = node.get("parent").and_then(Value::as_str);
+    }
+    if branch.is_empty() {
+        let mut nodes = mapping.values().collect::<Vec<_>>();
+        nodes.sort_by_key(|node| {
+            timestamp_ms(
+                node.get("message")
+                    .and_then(|message| message.get("create_time")),
+            )
+        });
+        return nodes;
+    }
+    branch.reverse();
+    branch
+}
+
+fn chatgpt_conversations(document: &Value) -> Vec<ExternalConversation> {
+    let conversations = match document {
+        Value::Array(items) => items.iter().collect::<Vec<_>>(),
+        Value::Object(_) => vec![document],
+        _ => Vec::new(),
+    };
+    conversations
+        .into_iter()
+        .filter_map(|conversation| {
+            let id = string_field(conversation, &["conversation_id", "id"])?;
+            let messages = chatgpt_branch(conversation)
+                .into_iter()
+                .filter_map(|node| {
+                    let message = node.get("message")?;
+                    let role = message.get("author")?.get("role")?.as_str()?.to_string();
+                    let text = content_text(message.get("content")?.get("parts")?)?;
+                    Some((role, text, timestamp_ms(message.get("create_time"))))
+                })
+                .collect();
+            Some(ExternalConversation {
+                id,
+                title: string_field(conversation, &["title"]),
+                created_at: timestamp_ms(conversation.get("create_time")),
+                updated_at: timestamp_ms(conversation.get("update_time")),
+                messages,
+            })
+        })
+        .collect()
+}
+
+/// Builds a thread from an adapter's output; `None` when nothing conversational is left.
+fn into_thread(
+    format: ExternalFormat,
+    conversation: ExternalConversation,
+    cwd: &str,
+) -> Option<ClaudeThreadRecord> {
+    let thread_id = format!("{}-{}", format.tag(), conversation.id);
+    let fallback_time = conversation
+        .updated_at
+        .or(conversation.created_at)
+        .unwrap_or_else(now_ms);
+    let history_messages = conversation
+        .messages
+        .into_iter()
+        .filter(|(role, _, _)| role == "user" || role == "assistant")
+        .map(|(role, text, timestamp)| HistoryMessage {
+            role,
+            text,
+            timestamp_ms: timestamp.unwrap_or(fallback_time),
+        })
+        .collect::<Vec<_>>();
+    let turns = build_turns_from_history_messages(&thread_id, &history_messages);
+    if turns.is_empty() {
+        return None;
+    }
+    let preview_source = history_messages
+        .iter()
+        .find(|message| message.role == "user")
+        .map(|message| message.text.clone())
+        .unwrap_or_else(|| thread_id.clone());
+    let created_at = conversation
+        .created_at
+        .or_else(|| history_messages.first().map(|message| message.timestamp_ms))
+        .unwrap_or(fallback_time);
+    let updated_at = history_messages
+        .last()
+        .map(|message| message.timestamp_ms)
+        .into_iter()
+        .chain(conversation.updated_at)
+        .max()
+        .unwrap_or(created_at);
+    // The turns were not run by any provider this app knows about.
+    let stats = ThreadStats {
+        last_provider: None,
+        ..ThreadStats::from_turns(&turns, ProviderKind::Claude)
+    };
+    Some(ClaudeThreadRecord {
+        id: thread_id,
+        cwd: cwd.to_string(),
+        preview: preview_from_text(&preview_source),
+        created_at,
+        updated_at,
+        name: conversation.title,
+        tags: vec![format.tag().to_string()],
+        session_id: Some(Uuid::new_v4().to_string()),
+        session_needs_seed: true,
+        provider_session_id: None,
+        cwd_override: None,
+        generation: GenerationParams::default(),
+        worktree: None,
+        summary: None,
+        stats: Some(stats),
+        last_read_at: None,
+        turns,
+    })
+}
+
+/// The export itself, or every `.json` file directly inside a directory of exports.
+fn export_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries = std::fs::read_dir(path)
+        .map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+    let mut files = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("json")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn read_export(path: &Path) -> Result<Value, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|error| format!("Failed to read {}: {error}", path.display()))?
+        .len();
+    if size > MAX_IMPORT_FILE_BYTES {
+        return Err(format!("{} is too large to import.", path.display()));
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|error| format!("{} is not valid JSON: {error}", path.display()))
+}
+
+/// Parses every export under `path`; unreadable files are reported instead of failing the
+/// whole import.
+fn scan_exports(
+    format: ExternalFormat,
+    path: &Path,
+    cwd: &str,
+    redaction: &RedactionPipeline,
+) -> Result<(Vec<ClaudeThreadRecord>, Vec<String>), String> {
+    let mut threads = Vec::new();
+    let mut failed = Vec::new();
+    for file in export_files(path)? {
+        match read_export(&file) {
+            Ok(document) => threads.extend(
+                format
+                    .conversations(&document)
+                    .into_iter()
+                    .filter_map(|conversation| into_thread(format, conversation, cwd))
+                    .map(|mut thread| {
+                        redact_thread_in_place(&mut thread, redaction);
+                        thread
+                    }),
+            ),
+            Err(error) => failed.push(error),
+        }
+    }
+    Ok((threads, failed))
+}
+
+/// Imports another tool's chat history into a workspace. Imported threads start a fresh Claude
+/// session seeded with their transcript on the next message; ones imported before are skipped.
+pub(crate) async fn import_external_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    format: String,
+    path: String,
+    redaction: &RedactionPipeline,
+    event_sink: E,
+) -> Result<Value, String> {
+    let format = ExternalFormat::parse(&format)?;
+    let path = PathBuf::from(path.trim());
+    if !path.exists() {
+        return Err(format!("{} does not exist.", path.display()));
+    }
+    let workspace_path = workspaces
+        .lock()
+        .await
+        .get(&workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(|| "workspace not found".to_string())?;
+    let redaction = redaction.clone();
+    let (scanned, failed) = tokio::task::spawn_blocking(move || {
+        scan_exports(format, &path, &workspace_path, &redaction)
+    })
+    .await
+    .map_err(|error| format!("failed to read the export: {error}"))??;
+
+    let mut imported = Vec::new();
+    let mut skipped = 0usize;
+    {
+        let mut store = claude_threads.lock().await;
+        let threads = store.entry(workspace_id.clone()).or_default();
+        for thread in scanned {
+            if threads.iter().any(|existing| existing.id == thread.id) {
+                skipped += 1;
+                continue;
+            }
+            imported.push(thread.id.clone());
+            threads.push(thread);
+        }
+    }
+    if !imported.is_empty() {
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(&event_sink, claude_threads, &workspace_id, &imported, &[]).await;
+    }
+    Ok(json!({
+        "result": {
+            "imported": imported,
+            "skipped": skipped,
+            "failed": failed,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{into_thread, ExternalFormat};
+    use serde_json::json;
+
+    fn threads(format: ExternalFormat, document: serde_json::Value) -> Vec<(String, Vec<String>)> {
+        format
+            .conversations(&document)
+            .into_iter()
+            .filter_map(|conversation| into_thread(format, conversation, "/repo"))
+            .map(|thread| {
+                let texts = thread
+                    .turns
+                    .iter()
+                    .flat_map(|turn| turn.items.iter().map(|item| item.text.clone()))
+                    .collect();
+                (thread.id, texts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adapters_convert_each_format_into_turns() {
+        let cursor = json!({
+            "tabs": [{
+                "tabId": "tab-1",
+                "chatTitle": "Build",
+                "lastSendTime": 1_700_000_000_000_i64,
+                "bubbles": [
+                    { "type": "user", "text": "Fix the build" },
+                    { "type": "ai", "rawText": "Fixed." },
+                ],
+            }],
+        });
+        assert_eq!(
+            threads(ExternalFormat::Cursor, cursor),
+            vec![(
+                "cursor-tab-1".to_string(),
+                vec!["Fix the build".to_string(), "Fixed.".to_string()]
+            )]
+        );
+
+        let continue_session = json!({
+            "sessionId": "s-1",
+            "title": "Tests",
+            "dateCreated": "1700000000000",
+            "history": [
+                { "message": { "role": "user", "content": [{ "type": "text", "text": "Run tests" }] } },
+                { "message": { "role": "assistant", "content": "All green." } },
+            ],
+        });
+        assert_eq!(
+            threads(ExternalFormat::Continue, continue_session)[0].1,
+            vec!["Run tests".to_string(), "All green.".to_string()]
+        );
+
+        // Only the branch ending at `current_node` survives; the regenerated answer is dropped.
+        let chatgpt = json!([{
+            "id": "c-1",
+            "title": "Regex",
+            "create_time": 1_700_000_000.5,
+            "current_node": "a2",
+            "mapping": {
+                "root": { "id": "root", "message": null, "parent": null },
+                "sys": {
+                    "id": "sys",
+                    "parent": "root",
+                    "message": { "author": { "role": "system" }, "content": { "parts": ["x"] } },
+                },
+                "u1": {
+                    "id": "u1",
+                    "parent": "sys",
+                    "message": { "author": { "role": "user" }, "content": { "parts": ["Match dates"] } },
+                },
+                "a1": {
+                    "id": "a1",
+                    "parent": "u1",
+                    "message": { "author": { "role": "assistant" }, "content": { "parts": ["Old"] } },
+                },
+                "a2": {
+                    "id": "a2",
+                    "parent": "u1",
+                    "message": { "author": { "role": "assistant" }, "content": { "parts": ["\\d{4}"] } },
+                },
+            },
+        }]);
+        assert_eq!(
+            threads(ExternalFormat::ChatGpt, chatgpt),
+            vec![(
+                "chatgpt-c-1".to_string(),
+                vec!["Match dates".to_string(), "\\d{4}".to_string()]
+            )]
+        );
+        assert!(ExternalFormat::parse("windsurf").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+
+use crate::types::SandboxSettings;
+
+/// Claude tools that touch files: the input field holding the path, and whether they write.
+/// Searches are checked by the directory they start in.
+const FILE_TOOLS: &[(&str, &str, bool)] = &[
+    ("Read", "file_path", false),
+    ("Glob", "path", false),
+    ("Grep", "path", false),
+    ("Write", "file_path", true),
+    ("Edit", "file_path", true),
+    ("MultiEdit", "file_path", true),
+    ("NotebookEdit", "notebook_path", true),
+];
+
+/// `--settings` that make Claude ask before every file tool. Reads inside the project are
+/// otherwise allowed without a permission prompt, so they would never reach the rules.
+pub(crate) fn ask_for_file_tools_args() -> Vec<String> {
+    let tools = FILE_TOOLS
+        .iter()
+        .map(|(tool, _, _)| *tool)
+        .collect::<Vec<_>>();
+    vec![
+        "--settings".to_string(),
+        json!({ "permissions": { "ask": tools } }).to_string(),
+    ]
+}
+
+/// A file tool call the workspace's rules don't let through without the user.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FileAccessViolation {
+    pub(crate) tool: String,
+    pub(crate) path: String,
+    /// `read` or `write`.
+    pub(crate) access: &'static str,
+    pub(crate) reason: String,
+}
+
+/// Rules are matched against paths already made relative to the workspace, so they compile
+/// without a root.
+fn compile_rules(patterns: &[String]) -> Result<Option<Gitignore>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in patterns {
+        if pattern.trim().is_empty() {
+            return Err("File access rules can't be blank.".to_string());
+        }
+        builder
+            .add_line(None, pattern.trim())
+            .map_err(|error| format!("Invalid file access rule `{pattern}`: {error}"))?;
+    }
+    builder.build().map(Some).map_err(|error| error.to_string())
+}
+
+/// Resolves `.` and `..` without touching the disk, since the file may not exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// The compiled `fileAllow` and `fileDeny` globs of a workspace. Globs use gitignore syntax
+/// relative to the workspace; a deny match wins over an allow match, and once either list is
+/// set, paths outside the workspace always need the user.
+pub(crate) struct FileAccessGuard {
+    root: PathBuf,
+    cwd: PathBuf,
+    allow: Option<Gitignore>,
+    deny: Option<Gitignore>,
+}
+
+impl FileAccessGuard {
+    /// `None` when the sandbox sets no file rules.
+    pub(crate) fn new(sandbox: &SandboxSettings) -> Result<Option<Self>, String> {
+        let allow = compile_rules(&sandbox.file_allow)?;
+        let deny = compile_rules(&sandbox.file_deny)?;
+        if allow.is_none() && deny.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            root: PathBuf::new(),
+            cwd: PathBuf::new(),
+            allow,
+            deny,
+        }))
+    }
+
+    /// Binds the rules to a turn's directories; relative tool paths resolve against `cwd`. A
+    /// thread working outside the workspace, in its own worktree, has the rules applied to
+    /// that directory instead.
+    pub(crate) fn for_turn(self, workspace_path: &Path, cwd: &Path) -> Self {
+        let cwd = normalize(cwd);
+        let workspace_path = normalize(workspace_path);
+        Self {
+            root: if cwd.starts_with(&workspace_path) {
+                workspace_path
+            } else {
+                cwd.clone()
+            },
+            cwd,
+            ..self
+        }
+    }
+
+    /// Why a file tool call needs confirmation, or `None` when the rules let it through or the
+    /// tool doesn't touch files.
+    pub(crate) fn violation(&self, tool_name: &str, input: &Value) -> Option<FileAccessViolation> {
+        let (tool, field, write) = FILE_TOOLS.iter().find(|(tool, _, _)| *tool == tool_name)?;
+        let requested = input.get(*field).and_then(Value::as_str).unwrap_or(".");
+        let path = normalize(&self.cwd.join(requested));
+        let is_dir = *field == "path";
+        let reason = match path.strip_prefix(&self.root) {
+            Err(_) => Some("outside the workspace".to_string()),
+            Ok(relative) => {
+                let matched = |rules: &Option<Gitignore>| match rules {
+                    Some(rules) => match rules.matched_path_or_any_parents(relative, is_dir) {
+                        Match::Ignore(glob) => Some(Some(glob.original().to_string())),
+                        _ => Some(None),
+                    },
+                    None => None,
+                };
+                match (matched(&self.deny), matched(&self.allow)) {
+                    (Some(Some(pattern)), _) => Some(format!("matches the deny rule `{pattern}`")),
+                    (_, Some(None)) => Some("not covered by an allow rule".to_string()),
+                    _ => None,
+                }
+            }
+        }?;
+        Some(FileAccessViolation {
+            tool: tool.to_string(),
+            path: path.to_string_lossy().to_string(),
+            access: if *write { "write" } else { "read" },
+            reason,
+        })
+    }
+}
+
+pub(crate) fn validate_file_rules(sandbox: &SandboxSettings) -> Result<(), String> {
+    FileAccessGuard::new(sandbox)?;
+    if sandbox.has_file_rules() && sandbox.claude_skip_permissions {
+        return Err(
+            "File access rules need Claude's permission prompts; turn off skip permissions."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_file_rules, FileAccessGuard};
+    use crate::types::SandboxSettings;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn deny_rules_win_and_allowlists_cover_the_rest() {
+        let rules = |allow: &[&str], deny: &[&str]| SandboxSettings {
+            file_allow: allow.iter().map(|rule| rule.to_string()).collect(),
+            file_deny: deny.iter().map(|rule| rule.to_string()).collect(),
+            ..SandboxSettings::default()
+        };
+        let root = Path::new("/repo");
+        let guard = |sandbox: &SandboxSettings| {
+            FileAccessGuard::new(sandbox)
+                .expect("valid rules")
+                .expect("guard")
+                .for_turn(root, &root.join("app"))
+        };
+        assert!(FileAccessGuard::new(&SandboxSettings::default())
+            .expect("no rules")
+            .is_none());
+
+        let deny_only = guard(&rules(&[], &[".env", "secrets/"]));
+        let read = |path: &str| json!({ "file_path": path });
+        assert_eq!(deny_only.violation("Read", &read("src/main.rs")), None);
+        let violation = deny_only
+            .violation("Read", &read("/repo/secrets/keys/prod.pem"))
+            .expect("denied");
+        assert_eq!(violation.path, "/repo/secrets/keys/prod.pem");
+        assert_eq!(violation.access, "read");
+        assert_eq!(violation.reason, "matches the deny rule `secrets/`");
+        let write = deny_only
+            .violation("Write", &read(".env"))
+            .expect("relative to cwd");
+        assert_eq!(
+            (write.path.as_str(), write.access),
+            ("/repo/app/.env", "write")
+        );
+        let outside = deny_only
+            .violation("Edit", &read("../../etc/hosts"))
+            .expect("outside");
+        assert_eq!(outside.reason, "outside the workspace");
+        assert_eq!(
+            deny_only.violation("Bash", &json!({ "command": "ls" })),
+            None
+        );
+
+        let allowlist = guard(&rules(&["app/src/**", "*.md"], &["app/src/generated/"]));
+        assert_eq!(allowlist.violation("Read", &read("src/lib.rs")), None);
+        assert_eq!(
+            allowlist.violation("Read", &read("/repo/docs/README.md")),
+            None
+        );
+        assert_eq!(
+            allowlist
+                .violation("Read", &read("/repo/Cargo.toml"))
+                .expect("not allowed")
+                .reason,
+            "not covered by an allow rule"
+        );
+        assert!(allowlist
+            .violation("Edit", &read("src/generated/api.rs"))
+            .is_some());
+        assert_eq!(
+            allowlist.violation("Grep", &json!({ "path": "src/ui" })),
+            None
+        );
+        assert!(allowlist
+            .violation("Glob", &json!({ "pattern": "**" }))
+            .is_some());
+
+        let worktree = guard(&rules(&[], &["secrets/"])).for_turn(root, Path::new("/tmp/wt"));
+        assert!(worktree.violation("Read", &read("secrets/a")).is_some());
+        assert_eq!(worktree.violation("Read", &read("/tmp/wt/src/a.rs")), None);
+
+        assert!(validate_file_rules(&rules(&["src/**"], &[])).is_ok());
+        assert!(validate_file_rules(&rules(&[" "], &[])).is_err());
+        let mut skipping = rules(&[], &["secrets/"]);
+        skipping.claude_skip_permissions = true;
+        assert!(validate_file_rules(&skipping).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Keeps long answers from turning every completed item into a wall of links.
+const MAX_REFERENCES: usize = 50;
+/// Each distinct candidate costs a filesystem lookup.
+const MAX_CANDIDATES: usize = 500;
+
+static REFERENCE_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// A file mentioned in assistant output that exists in the workspace.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct FileReference {
+    /// The mention as written, including any line suffix, so the UI can find it in the text.
+    pub(crate) text: String,
+    /// Relative to the directory the turn ran in, with `/` separators.
+    pub(crate) path: String,
+    #[serde(rename = "absolutePath")]
+    pub(crate) absolute_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) line: Option<u32>,
+    #[serde(rename = "endLine", skip_serializing_if = "Option::is_none")]
+    pub(crate) end_line: Option<u32>,
+}
+
+/// Paths with an optional `:12`, `:12-20`, `:12:5` or `#L12-L20` suffix.
+fn reference_pattern() -> &'static Regex {
+    REFERENCE_PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?P<path>(?:\.{1,2}/|/)?(?:[\w@-][\w.@-]*/)*[\w@-][\w.@-]*)",
+            r"(?::(?P<line>\d+)(?:(?P<sep>[-:])(?P<end>\d+))?",
+            r"|#L(?P<hline>\d+)(?:-L?(?P<hend>\d+))?)?",
+        ))
+        .expect("valid reference pattern")
+    })
+}
+
+/// The canonical file `candidate` points at, if it is a file inside `root`.
+fn resolve_in_root(root: &Path, candidate: &str) -> Option<PathBuf> {
+    let path = Path::new(candidate);
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+    let resolved = joined.canonicalize().ok()?;
+    (resolved.starts_with(root) && resolved.is_file()).then_some(resolved)
+}
+
+fn parse_line(value: Option<regex::Match<'_>>) -> Option<u32> {
+    value?.as_str().parse().ok().filter(|line| *line > 0)
+}
+
+/// Finds file paths (and line references) in `text` that exist under `root`. Mentions that
+/// resolve outside of `root`, directories and missing files are dropped.
+pub(crate) fn extract_references(text: &str, root: &Path) -> Vec<FileReference> {
+    let Ok(root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    let mut references = Vec::new();
+    let mut seen = HashSet::new();
+    let mut checked = HashSet::new();
+    for captures in reference_pattern().captures_iter(text) {
+        if references.len() >= MAX_REFERENCES || checked.len() >= MAX_CANDIDATES {
+            break;
+        }
+        let (Some(whole), Some(path)) = (captures.get(0), captures.name("path")) else {
+            continue;
+        };
+        // Sentence punctuation is not part of the path.
+        let candidate = path.as_str().trim_end_matches('.');
+        let has_suffix = whole.end() > path.end();
+        if !candidate.contains('/') && !candidate.trim_start_matches('.').contains('.') {
+            continue;
+        }
+        checked.insert(candidate.to_string());
+        let Some(resolved) = resolve_in_root(&root, candidate) else {
+            continue;
+        };
+        let line = parse_line(captures.name("line").or_else(|| captures.name("hline")));
+        let end_line = match captures.name("sep").map(|sep| sep.as_str()) {
+            Some("-") => parse_line(captures.name("end")),
+            Some(_) => None,
+            None => parse_line(captures.name("hend")),
+        }
+        .filter(|end| line.is_some_and(|line| *end > line));
+        let relative = resolved
+            .strip_prefix(&root)
+            .map(|relative| {
+                relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+        if !seen.insert((relative.clone(), line, end_line)) {
+            continue;
+        }
+        let text = if has_suffix {
+            whole.as_str()
+        } else {
+            candidate
+        };
+        references.push(FileReference {
+            text: text.to_string(),
+            path: relative,
+            absolute_path: resolved.to_string_lossy().to_string(),
+            line,
+            end_line,
+        });
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_references;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn extracts_existing_files_with_line_ranges() {
+        let temp =
+            std::env::temp_dir().join(format!("polycode-references-test-{}", Uuid::new_v4()));
+        let root = temp.join("repo");
+        fs::create_dir_all(root.join("src/shared")).expect("create dirs");
+        fs::write(root.join("src/shared/claude_core.rs"), "fn main() {}\n").expect("write");
+        fs::write(root.join("README.md"), "# Readme\n").expect("write");
+        fs::write(temp.join("outside.rs"), "").expect("write");
+
+        let text = "Changed `src/shared/claude_core.rs:12-20` and ./README.md#L3. \
+            See also src/shared/claude_core.rs:40:7, src/missing.rs, src/shared, e.g. \
+            ../outside.rs and README.md.";
+        let references = extract_references(text, &root);
+
+        let summary = references
+            .iter()
+            .map(|reference| {
+                (
+                    reference.text.as_str(),
+                    reference.path.as_str(),
+                    reference.line,
+                    reference.end_line,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "src/shared/claude_core.rs:12-20",
+                    "src/shared/claude_core.rs",
+                    Some(12),
+                    Some(20)
+                ),
+                ("./README.md#L3", "README.md", Some(3), None),
+                (
+                    "src/shared/claude_core.rs:40:7",
+                    "src/shared/claude_core.rs",
+                    Some(40),
+                    None
+                ),
+                ("README.md", "README.md", None, None),
+            ]
+        );
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
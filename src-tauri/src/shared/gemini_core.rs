@@ -0,0 +1,168 @@
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+const GEMINI_MODELS_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const MODEL_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_GEMINI_MODEL: &str = "gemini-2.5-pro";
+/// Used when no API key is configured or the API is unreachable.
+const BUILTIN_GEMINI_MODELS: &[(&str, &str)] = &[
+    ("gemini-2.5-pro", "Gemini 2.5 Pro"),
+    ("gemini-2.5-flash", "Gemini 2.5 Flash"),
+    ("gemini-2.5-flash-lite", "Gemini 2.5 Flash-Lite"),
+];
+
+pub(crate) struct CachedGeminiModels {
+    fetched_at: Instant,
+    data: Vec<Value>,
+}
+
+pub(crate) type GeminiModelsCache = Arc<Mutex<Option<CachedGeminiModels>>>;
+
+fn model_entry(id: &str, display_name: &str, description: &str) -> Value {
+    json!({
+        "id": id,
+        "model": id,
+        "displayName": display_name,
+        "description": description,
+        "supportedReasoningEfforts": [],
+        "defaultReasoningEffort": null,
+        "isDefault": id == DEFAULT_GEMINI_MODEL,
+    })
+}
+
+fn builtin_models() -> Vec<Value> {
+    BUILTIN_GEMINI_MODELS
+        .iter()
+        .map(|(id, name)| model_entry(id, name, ""))
+        .collect()
+}
+
+fn gemini_api_key() -> Option<String> {
+    ["GEMINI_API_KEY", "GOOGLE_API_KEY"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+/// Keeps Gemini models that can generate content, mapped to the shape `model/list` returns.
+fn parse_models_response(body: &Value) -> Vec<Value> {
+    body.get("models")
+        .and_then(Value::as_array)
+        .map(|models| {
+            models
+                .iter()
+                .filter(|model| {
+                    model
+                        .get("supportedGenerationMethods")
+                        .and_then(Value::as_array)
+                        .is_some_and(|methods| {
+                            methods.iter().any(|method| method == "generateContent")
+                        })
+                })
+                .filter_map(|model| {
+                    let name = model.get("name").and_then(Value::as_str)?;
+                    let id = name.strip_prefix("models/").unwrap_or(name);
+                    if !id.starts_with("gemini") {
+                        return None;
+                    }
+                    let display_name = model
+                        .get("displayName")
+                        .and_then(Value::as_str)
+                        .unwrap_or(id);
+                    let description = model
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    Some(model_entry(id, display_name, description))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn fetch_models(api_key: &str) -> Result<Vec<Value>, String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|error| format!("Failed to configure Gemini client: {error}"))?;
+    let response = client
+        .get(GEMINI_MODELS_URL)
+        .query(&[("pageSize", "1000")])
+        .header("x-goog-api-key", api_key)
+        .send()
+        .await
+        .map_err(|error| format!("Failed to list Gemini models: {error}"))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|error| format!("Failed to read Gemini models: {error}"))?;
+    if !status.is_success() {
+        return Err(format!("Gemini model listing returned {status}"));
+    }
+    let value: Value = serde_json::from_str(&body)
+        .map_err(|error| format!("Invalid Gemini model listing: {error}"))?;
+    Ok(parse_models_response(&value))
+}
+
+/// Lists Gemini models from the API when a key is available, cached for a few minutes.
+pub(crate) async fn model_list_core(cache: &GeminiModelsCache) -> Result<Value, String> {
+    {
+        let cached = cache.lock().await;
+        if let Some(cached) = cached.as_ref() {
+            if cached.fetched_at.elapsed() < MODEL_CACHE_TTL {
+                return Ok(json!({ "result": { "data": cached.data, "source": "api" } }));
+            }
+        }
+    }
+    let fetched = match gemini_api_key() {
+        Some(api_key) => fetch_models(&api_key).await.ok(),
+        None => None,
+    };
+    match fetched {
+        Some(data) if !data.is_empty() => {
+            *cache.lock().await = Some(CachedGeminiModels {
+                fetched_at: Instant::now(),
+                data: data.clone(),
+            });
+            Ok(json!({ "result": { "data": data, "source": "api" } }))
+        }
+        _ => Ok(json!({ "result": { "data": builtin_models(), "source": "builtin" } })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_models_response;
+    use serde_json::json;
+
+    #[test]
+    fn parse_models_keeps_generative_gemini_models() {
+        let body = json!({
+            "models": [
+                {
+                    "name": "models/gemini-2.5-pro",
+                    "displayName": "Gemini 2.5 Pro",
+                    "supportedGenerationMethods": ["generateContent", "countTokens"],
+                },
+                {
+                    "name": "models/text-embedding-004",
+                    "supportedGenerationMethods": ["embedContent"],
+                },
+                {
+                    "name": "models/gemma-3-27b-it",
+                    "supportedGenerationMethods": ["generateContent"],
+                },
+            ]
+        });
+        let models = parse_models_response(&body);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0]["id"], "gemini-2.5-pro");
+        assert_eq!(models[0]["isDefault"], true);
+    }
+}
@@ -0,0 +1,330 @@
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+
+/// Finish reasons Gemini gives when it withholds a response.
+const BLOCKED_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GeminiStreamLine {
+    /// The `init` record naming the session the CLI is writing to.
+    Session(String),
+    Delta(String),
+    /// Sources the model cited for the text so far.
+    Citations(Vec<Value>),
+    ToolCall {
+        call_id: String,
+        tool: String,
+        arguments: Value,
+    },
+    ToolResult {
+        call_id: String,
+        success: bool,
+        output: String,
+    },
+    /// A response withheld by a safety filter, with the reason Gemini gave.
+    Blocked(String),
+    Result {
+        success: bool,
+        error: Option<String>,
+    },
+    Ignored,
+}
+
+fn text_field<'a>(record: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| record.get(*key).and_then(Value::as_str))
+}
+
+fn error_message(record: &Value) -> Option<String> {
+    let error = record.get("error")?;
+    error
+        .get("message")
+        .and_then(Value::as_str)
+        .or_else(|| error.as_str())
+        .map(str::to_string)
+}
+
+fn blocked_reason(record: &Value) -> Option<String> {
+    let reason = text_field(record, &["finish_reason", "finishReason"])?;
+    BLOCKED_FINISH_REASONS
+        .contains(&reason)
+        .then(|| reason.to_string())
+}
+
+/// Classifies one `gemini --output-format stream-json` record.
+pub(crate) fn parse_gemini_record(record: &Value) -> GeminiStreamLine {
+    if let Some(reason) = blocked_reason(record) {
+        return GeminiStreamLine::Blocked(reason);
+    }
+    match record.get("type").and_then(Value::as_str) {
+        Some("init") => text_field(record, &["session_id"])
+            .filter(|session_id| !session_id.trim().is_empty())
+            .map(|session_id| GeminiStreamLine::Session(session_id.to_string()))
+            .unwrap_or(GeminiStreamLine::Ignored),
+        Some("message") if record.get("role").and_then(Value::as_str) == Some("assistant") => {
+            match record.get("citations").and_then(Value::as_array) {
+                Some(citations) if !citations.is_empty() => {
+                    GeminiStreamLine::Citations(citations.clone())
+                }
+                _ => match text_field(record, &["content"]) {
+                    Some(text) if !text.is_empty() => GeminiStreamLine::Delta(text.to_string()),
+                    _ => GeminiStreamLine::Ignored,
+                },
+            }
+        }
+        Some("tool_use") => GeminiStreamLine::ToolCall {
+            call_id: text_field(record, &["tool_id"])
+                .unwrap_or_default()
+                .to_string(),
+            tool: text_field(record, &["tool_name"])
+                .unwrap_or_default()
+                .to_string(),
+            arguments: record
+                .get("parameters")
+                .cloned()
+                .unwrap_or_else(|| json!({})),
+        },
+        Some("tool_result") => {
+            let success = record.get("status").and_then(Value::as_str) != Some("error");
+            GeminiStreamLine::ToolResult {
+                call_id: text_field(record, &["tool_id"])
+                    .unwrap_or_default()
+                    .to_string(),
+                success,
+                output: if success {
+                    text_field(record, &["output"])
+                        .unwrap_or_default()
+                        .to_string()
+                } else {
+                    error_message(record).unwrap_or_default()
+                },
+            }
+        }
+        Some("result") => {
+            let success = record.get("status").and_then(Value::as_str) == Some("success");
+            GeminiStreamLine::Result {
+                success,
+                error: (!success)
+                    .then(|| error_message(record).unwrap_or_else(|| "Gemini CLI failed.".into())),
+            }
+        }
+        _ => GeminiStreamLine::Ignored,
+    }
+}
+
+/// Builds a Gemini turn's items in the shapes Claude and Codex turns use: text becomes
+/// `agentMessage` items streamed with `item/agentMessage/delta`, tool calls become
+/// `mcpToolCall` items, and citations ride on the message they belong to. The finished items
+/// are what the turn record keeps.
+pub(crate) struct GeminiTurnItems {
+    turn_id: String,
+    items: Vec<Value>,
+    /// Index of the agent message still receiving text.
+    open_message: Option<usize>,
+    error: Option<String>,
+}
+
+impl GeminiTurnItems {
+    pub(crate) fn new(turn_id: &str) -> Self {
+        Self {
+            turn_id: turn_id.to_string(),
+            items: Vec::new(),
+            open_message: None,
+            error: None,
+        }
+    }
+
+    fn next_id(&self, kind: &str) -> String {
+        format!("{}-{kind}-{}", self.turn_id, self.items.len())
+    }
+
+    fn close_message(&mut self, events: &mut Vec<(&'static str, Value)>) {
+        if let Some(index) = self.open_message.take() {
+            events.push(("item/completed", json!({ "item": self.items[index] })));
+        }
+    }
+
+    /// Folds one line into the turn and returns the events it produces as `(method, params)`;
+    /// the caller adds `threadId` and `turnId`.
+    pub(crate) fn apply(&mut self, line: GeminiStreamLine) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        match line {
+            GeminiStreamLine::Delta(text) => {
+                let index = match self.open_message {
+                    Some(index) => index,
+                    None => {
+                        let item = json!({
+                            "id": self.next_id("assistant"),
+                            "type": "agentMessage",
+                            "text": "",
+                        });
+                        events.push(("item/started", json!({ "item": item })));
+                        self.items.push(item);
+                        self.open_message = Some(self.items.len() - 1);
+                        self.items.len() - 1
+                    }
+                };
+                let message = &mut self.items[index];
+                let mut joined = message["text"].as_str().unwrap_or_default().to_string();
+                joined.push_str(&text);
+                message["text"] = json!(joined);
+                events.push((
+                    "item/agentMessage/delta",
+                    json!({ "itemId": message["id"], "delta": text }),
+                ));
+            }
+            GeminiStreamLine::Citations(citations) => {
+                let message = self
+                    .items
+                    .iter_mut()
+                    .rev()
+                    .find(|item| item["type"] == "agentMessage");
+                if let Some(message) = message {
+                    let cited = message["citations"].as_array_mut();
+                    match cited {
+                        Some(cited) => cited.extend(citations),
+                        None => message["citations"] = json!(citations),
+                    }
+                }
+            }
+            GeminiStreamLine::ToolCall {
+                call_id,
+                tool,
+                arguments,
+            } => {
+                self.close_message(&mut events);
+                let item = json!({
+                    "id": self.next_id("tool"),
+                    "type": "mcpToolCall",
+                    "callId": call_id,
+                    "server": "gemini",
+                    "tool": tool,
+                    "arguments": arguments,
+                    "status": "inProgress",
+                });
+                events.push(("item/started", json!({ "item": item })));
+                self.items.push(item);
+            }
+            GeminiStreamLine::ToolResult {
+                call_id,
+                success,
+                output,
+            } => {
+                let call = self.items.iter_mut().rev().find(|item| {
+                    item["type"] == "mcpToolCall" && item["callId"] == call_id.as_str()
+                });
+                if let Some(call) = call {
+                    call["status"] = json!(if success { "completed" } else { "failed" });
+                    call[if success { "result" } else { "error" }] = json!(output);
+                    events.push(("item/completed", json!({ "item": call })));
+                }
+            }
+            GeminiStreamLine::Blocked(reason) => {
+                self.error = Some(format!("Gemini withheld the response ({reason})."));
+            }
+            GeminiStreamLine::Result { success, error } => {
+                self.close_message(&mut events);
+                if !success && self.error.is_none() {
+                    self.error = error;
+                }
+            }
+            GeminiStreamLine::Session(_) | GeminiStreamLine::Ignored => {}
+        }
+        events
+    }
+
+    /// The turn's items and, when it failed or was blocked, why.
+    pub(crate) fn finish(self) -> (Vec<Value>, Option<String>) {
+        (self.items, self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_gemini_record, GeminiStreamLine, GeminiTurnItems};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn builds_message_tool_and_citation_items() {
+        let lines = [
+            r#"{"type":"init","session_id":"s-1","model":"gemini-2.5-pro"}"#,
+            r#"{"type":"message","role":"user","content":"Check the docs"}"#,
+            r#"{"type":"message","role":"assistant","content":"Looking","delta":true}"#,
+            r#"{"type":"message","role":"assistant","content":" now.","delta":true}"#,
+            r#"{"type":"tool_use","tool_name":"read_file","tool_id":"c-1","parameters":{}}"#,
+            r#"{"type":"tool_result","tool_id":"c-1","status":"success","output":"Docs"}"#,
+            r#"{"type":"message","role":"assistant","content":"Done.","delta":true}"#,
+            r#"{"type":"message","role":"assistant","citations":[{"uri":"https://a.dev"}]}"#,
+            r#"{"type":"result","status":"success","stats":{"total_tokens":42}}"#,
+        ];
+        let parsed = lines
+            .iter()
+            .map(|line| parse_gemini_record(&serde_json::from_str::<Value>(line).expect("json")))
+            .collect::<Vec<_>>();
+        assert_eq!(parsed[0], GeminiStreamLine::Session("s-1".to_string()));
+        assert_eq!(parsed[1], GeminiStreamLine::Ignored);
+
+        let mut turn = GeminiTurnItems::new("gemini-turn-1");
+        let methods = parsed
+            .into_iter()
+            .flat_map(|line| turn.apply(line))
+            .map(|(method, _)| method)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            [
+                "item/started",
+                "item/agentMessage/delta",
+                "item/agentMessage/delta",
+                "item/completed",
+                "item/started",
+                "item/completed",
+                "item/started",
+                "item/agentMessage/delta",
+                "item/completed",
+            ]
+        );
+        let (items, error) = turn.finish();
+        assert_eq!(error, None);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["text"], "Looking now.");
+        assert_eq!(items[1]["type"], "mcpToolCall");
+        assert_eq!(
+            (&items[1]["status"], &items[1]["result"]),
+            (&json!("completed"), &json!("Docs"))
+        );
+        assert_eq!(items[2]["citations"][0]["uri"], "https://a.dev");
+    }
+
+    #[test]
+    fn safety_blocks_and_failed_results_fail_the_turn() {
+        let mut turn = GeminiTurnItems::new("gemini-turn-2");
+        let blocked = json!({ "type": "result", "status": "error", "finish_reason": "SAFETY" });
+        turn.apply(parse_gemini_record(&blocked));
+        let failed =
+            json!({ "type": "result", "status": "error", "error": { "message": "quota" } });
+        turn.apply(parse_gemini_record(&failed));
+        assert_eq!(
+            turn.finish().1.as_deref(),
+            Some("Gemini withheld the response (SAFETY).")
+        );
+
+        let mut turn = GeminiTurnItems::new("gemini-turn-3");
+        let tool_error = json!({
+            "type": "tool_result",
+            "tool_id": "c-9",
+            "status": "error",
+            "error": { "message": "denied" },
+        });
+        assert!(turn.apply(parse_gemini_record(&tool_error)).is_empty());
+        turn.apply(parse_gemini_record(&failed));
+        assert_eq!(turn.finish().1.as_deref(), Some("quota"));
+    }
+}
@@ -21,11 +21,21 @@ fn format_git_error(stdout: &[u8], stderr: &[u8]) -> String {
 }
 
 pub(crate) async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
+    run_git_command_with_env(repo_path, args, &[]).await
+}
+
+/// Runs git with extra environment variables, e.g. `GIT_INDEX_FILE` for a scratch index.
+pub(crate) async fn run_git_command_with_env(
+    repo_path: &PathBuf,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<String, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
     let output = tokio_command(git_bin)
         .args(args)
         .current_dir(repo_path)
         .env("PATH", git_env_path())
+        .envs(envs.iter().copied())
         .output()
         .await
         .map_err(|err| format!("Failed to run git: {err}"))?;
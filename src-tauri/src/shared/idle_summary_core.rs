@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::EventSink;
+use crate::providers;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, now_ms, persist_threads_store, recent_transcript, run_claude_prompt,
+    ClaudeThreadRecord, ClaudeThreadsStore,
+};
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+pub(crate) const IDLE_SUMMARY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Every summary is a provider call, so a pass handles a few threads and leaves the rest for
+/// the next one.
+const MAX_SUMMARIES_PER_PASS: usize = 5;
+const SUMMARY_TRANSCRIPT_MAX_CHARS: usize = 24_000;
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(120);
+const MINUTE_MS: i64 = 60 * 1000;
+const SUMMARY_PROMPT: &str = "Summarize this conversation between a user and a coding agent \
+in one short paragraph: what was asked, what was done and what is still open. Reply with the \
+paragraph only.\n\n";
+
+/// One-paragraph recap of a thread, shown in listings so long histories are scannable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct IdleSummary {
+    pub(crate) text: String,
+    /// Last turn the summary covers.
+    #[serde(rename = "turnId")]
+    pub(crate) turn_id: String,
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: i64,
+}
+
+/// The last turn's id when the thread has sat idle for `idle_ms` and has no summary of that
+/// turn yet. Threads with a turn in flight are skipped.
+fn turn_to_summarize(thread: &ClaudeThreadRecord, now: i64, idle_ms: i64) -> Option<&str> {
+    let last = thread.turns.last()?;
+    if last.completed_at.is_none() || now - thread.updated_at < idle_ms {
+        return None;
+    }
+    if thread
+        .summary
+        .as_ref()
+        .is_some_and(|summary| summary.turn_id == last.id)
+    {
+        return None;
+    }
+    Some(&last.id)
+}
+
+struct SummaryJob {
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    transcript: String,
+}
+
+/// Most recently idle threads first, since those are the ones likely to be looked at next.
+fn select_jobs(
+    store: &HashMap<String, Vec<ClaudeThreadRecord>>,
+    workspace_ids: &[String],
+    now: i64,
+    idle_ms: i64,
+) -> Vec<SummaryJob> {
+    let mut candidates = store
+        .iter()
+        .filter(|(workspace_id, _)| workspace_ids.contains(workspace_id))
+        .flat_map(|(workspace_id, threads)| {
+            threads.iter().filter_map(move |thread| {
+                let turn_id = turn_to_summarize(thread, now, idle_ms)?;
+                Some((workspace_id, thread, turn_id))
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(_, thread, _)| std::cmp::Reverse(thread.updated_at));
+    candidates
+        .into_iter()
+        .filter_map(|(workspace_id, thread, turn_id)| {
+            let transcript = recent_transcript(&thread.turns, SUMMARY_TRANSCRIPT_MAX_CHARS);
+            (!transcript.is_empty()).then(|| SummaryJob {
+                workspace_id: workspace_id.clone(),
+                thread_id: thread.id.clone(),
+                turn_id: turn_id.to_string(),
+                transcript,
+            })
+        })
+        .take(MAX_SUMMARIES_PER_PASS)
+        .collect()
+}
+
+/// Stores the summary unless a turn landed while it was being written.
+async fn store_summary(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    summary: IdleSummary,
+) -> bool {
+    let mut store = claude_threads.lock().await;
+    let Some(thread) = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+    else {
+        return false;
+    };
+    if thread.turns.last().map(|turn| turn.id.as_str()) != Some(summary.turn_id.as_str()) {
+        return false;
+    }
+    thread.summary = Some(summary);
+    true
+}
+
+/// Asks Claude to summarize threads that have been idle past `idleSummaryAfterMinutes`.
+/// Summaries leave `updatedAt` alone so listings keep their order.
+pub(crate) async fn summarize_idle_threads_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    event_sink: E,
+) -> Result<Value, String> {
+    let settings = app_settings.lock().await.clone();
+    let idle_minutes = settings
+        .idle_summary_after_minutes
+        .filter(|minutes| *minutes > 0 && !settings.read_only);
+    let Some(idle_minutes) = idle_minutes else {
+        return Ok(json!({ "result": { "summarized": 0, "errors": [] } }));
+    };
+    let workspaces = workspaces.lock().await.clone();
+    let claude_workspace_ids = workspaces
+        .values()
+        .filter(|entry| {
+            matches!(
+                providers::resolve_workspace_provider(entry, Some(&settings)),
+                ProviderKind::Claude
+            )
+        })
+        .map(|entry| entry.id.clone())
+        .collect::<Vec<_>>();
+    let jobs = {
+        let store = claude_threads.lock().await;
+        select_jobs(
+            &store,
+            &claude_workspace_ids,
+            now_ms(),
+            i64::from(idle_minutes) * MINUTE_MS,
+        )
+    };
+
+    let mut summarized = 0;
+    let mut errors = Vec::new();
+    for job in jobs {
+        let Some(entry) = workspaces.get(&job.workspace_id) else {
+            continue;
+        };
+        let parent_entry = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id));
+        let prompt = format!("{SUMMARY_PROMPT}{}", job.transcript);
+        let text = match run_claude_prompt(
+            entry,
+            parent_entry,
+            &settings,
+            &prompt,
+            None,
+            SUMMARY_TIMEOUT,
+        )
+        .await
+        {
+            Ok(text) if !text.is_empty() => text,
+            Ok(_) => continue,
+            Err(error) => {
+                errors.push(format!("{}: {error}", job.thread_id));
+                continue;
+            }
+        };
+        let summary = IdleSummary {
+            text,
+            turn_id: job.turn_id,
+            created_at: now_ms(),
+        };
+        if !store_summary(claude_threads, &job.workspace_id, &job.thread_id, summary).await {
+            continue;
+        }
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(
+            &event_sink,
+            claude_threads,
+            &job.workspace_id,
+            &[job.thread_id],
+            &[],
+        )
+        .await;
+        summarized += 1;
+    }
+    Ok(json!({ "result": { "summarized": summarized, "errors": errors } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_jobs, IdleSummary, MINUTE_MS};
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn thread(id: &str, updated_at: i64, running: bool) -> ClaudeThreadRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "cwd": "/repo",
+            "preview": id,
+            "createdAt": 0,
+            "updatedAt": updated_at,
+            "turns": [{
+                "id": format!("{id}-turn"),
+                "startedAt": updated_at,
+                "completedAt": (!running).then_some(updated_at),
+                "items": [
+                    { "id": "u", "role": "user", "text": "Fix the build" },
+                    { "id": "a", "role": "assistant", "text": "Done." },
+                ],
+            }],
+        }))
+        .expect("thread")
+    }
+
+    #[test]
+    fn selects_idle_finished_threads_without_a_current_summary() {
+        let now = 100 * MINUTE_MS;
+        let mut summarized = thread("summarized", 10 * MINUTE_MS, false);
+        summarized.summary = Some(IdleSummary {
+            text: "Fixed the build.".to_string(),
+            turn_id: "summarized-turn".to_string(),
+            created_at: 20 * MINUTE_MS,
+        });
+        let mut stale = thread("stale", 30 * MINUTE_MS, false);
+        stale.summary = Some(IdleSummary {
+            turn_id: "older-turn".to_string(),
+            ..summarized.summary.clone().expect("summary")
+        });
+        let store = HashMap::from([
+            (
+                "ws".to_string(),
+                vec![
+                    thread("idle", 20 * MINUTE_MS, false),
+                    thread("recent", 95 * MINUTE_MS, false),
+                    thread("running", 10 * MINUTE_MS, true),
+                    summarized,
+                    stale,
+                ],
+            ),
+            ("codex-ws".to_string(), vec![thread("other", 0, false)]),
+        ]);
+
+        let jobs = select_jobs(&store, &["ws".to_string()], now, 30 * MINUTE_MS);
+
+        let ids = jobs
+            .iter()
+            .map(|job| job.thread_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["stale", "idle"]);
+        assert_eq!(jobs[1].turn_id, "idle-turn");
+        assert_eq!(
+            jobs[1].transcript,
+            "User: Fix the build\n\nAssistant: Done."
+        );
+    }
+}
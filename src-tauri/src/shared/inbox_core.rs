@@ -0,0 +1,134 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::shared::claude_core::{self, ClaudeThreadsStore};
+use crate::shared::codex_core;
+use crate::types::{ProviderKind, WorkspaceEntry};
+
+const DEFAULT_INBOX_LIMIT: u32 = 50;
+const MAX_INBOX_LIMIT: u32 = 500;
+/// Per-workspace fetch size; only the newest threads of each workspace can make the cut.
+const PER_WORKSPACE_FETCH_LIMIT: u32 = 100;
+/// Codex reports seconds and Claude milliseconds; anything below this is taken as seconds.
+const SECONDS_TIMESTAMP_CUTOFF: i64 = 100_000_000_000;
+
+fn timestamp_ms(thread: &Value) -> i64 {
+    let value = ["updatedAt", "updated_at", "createdAt", "created_at"]
+        .iter()
+        .filter_map(|key| thread.get(*key))
+        .find_map(|value| {
+            value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|raw| raw.parse::<i64>().ok()))
+        })
+        .unwrap_or(0);
+    if value < SECONDS_TIMESTAMP_CUTOFF {
+        value.saturating_mul(1000)
+    } else {
+        value
+    }
+}
+
+fn labeled_entries(
+    response: Value,
+    provider: &ProviderKind,
+    workspace: &WorkspaceEntry,
+) -> Vec<Value> {
+    let mut entries = response
+        .get("result")
+        .and_then(|result| result.get("data"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for entry in entries.iter_mut() {
+        let updated_at_ms = timestamp_ms(entry);
+        if let Some(object) = entry.as_object_mut() {
+            object.insert("provider".to_string(), json!(provider.as_str()));
+            object.insert("workspaceId".to_string(), json!(workspace.id));
+            object.insert("workspaceName".to_string(), json!(workspace.name));
+            object.insert("updatedAtMs".to_string(), json!(updated_at_ms));
+        }
+    }
+    entries
+}
+
+/// Newest first, keeping at most `limit` threads.
+fn merge_inbox(mut entries: Vec<Value>, limit: usize) -> Vec<Value> {
+    entries.sort_by_key(|entry| std::cmp::Reverse(timestamp_ms(entry)));
+    entries.truncate(limit);
+    entries
+}
+
+/// Recent threads across every workspace, newest first, labeled with provider and workspace.
+/// Workspaces whose Codex session is not connected only contribute their Claude threads.
+pub(crate) async fn inbox_list_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    claude_threads: &ClaudeThreadsStore,
+    limit: Option<u32>,
+) -> Result<Value, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_INBOX_LIMIT)
+        .clamp(1, MAX_INBOX_LIMIT) as usize;
+    let workspaces = workspaces
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut entries = Vec::new();
+    for workspace in &workspaces {
+        if let Ok(response) = codex_core::list_threads_core(
+            sessions,
+            workspace.id.clone(),
+            None,
+            Some(PER_WORKSPACE_FETCH_LIMIT),
+        )
+        .await
+        {
+            entries.extend(labeled_entries(response, &ProviderKind::Codex, workspace));
+        }
+        if let Ok(response) = claude_core::list_threads_core(
+            claude_threads,
+            workspace.id.clone(),
+            None,
+            Some(PER_WORKSPACE_FETCH_LIMIT),
+        )
+        .await
+        {
+            entries.extend(labeled_entries(response, &ProviderKind::Claude, workspace));
+        }
+    }
+
+    Ok(json!({
+        "result": {
+            "data": merge_inbox(entries, limit),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_inbox;
+    use serde_json::json;
+
+    #[test]
+    fn merges_second_and_millisecond_timestamps() {
+        let entries = vec![
+            json!({ "id": "codex-old", "updatedAt": 1_700_000_000 }),
+            json!({ "id": "claude-new", "updatedAt": 1_700_000_500_000i64 }),
+            json!({ "id": "codex-new", "createdAt": 1_700_001_000 }),
+        ];
+        let merged = merge_inbox(entries, 2);
+        let ids = merged
+            .iter()
+            .map(|entry| entry["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["codex-new", "claude-new"]);
+    }
+}
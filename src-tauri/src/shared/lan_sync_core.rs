@@ -0,0 +1,1174 @@
+use base64::Engine;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, hmac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, is_archived_thread_id, persist_threads_store,
+    read_archived_thread_ids_for_workspace, ClaudeThreadRecord, ClaudeThreadsStore,
+};
+use crate::shared::settings_core::{
+    ensure_not_read_only, latest_settings, subscribe_settings_changes,
+};
+use crate::shared::trash_core::trashed_thread_ids;
+use crate::types::{AppSettings, WorkspaceEntry};
+
+const SERVICE_TYPE: &str = "_polycode-sync._tcp.local.";
+/// Domain separation for the handshake MACs; the role keeps a proof from being reflected.
+const AUTH_CONTEXT: &str = "polycode-lan-sync/v1";
+const MIN_SECRET_LEN: usize = 12;
+const NONCE_LEN: usize = 32;
+const MAX_HANDSHAKE_BYTES: u64 = 4 * 1024;
+const MAX_SNAPSHOT_BYTES: u64 = 256 * 1024 * 1024;
+/// A sealed snapshot line: base64 of the JSON plus the AEAD tag.
+const MAX_SEALED_BYTES: u64 = MAX_SNAPSHOT_BYTES / 3 * 4 + 64;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const SESSION_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_DISCOVERY_MS: u64 = 3_000;
+const MAX_DISCOVERY_MS: u64 = 15_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncWorkspace {
+    id: String,
+    name: String,
+    path: String,
+}
+
+/// Everything one device sends: its workspaces, so the peer can map them onto its own, and
+/// the Claude threads of each.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncSnapshot {
+    workspaces: Vec<SyncWorkspace>,
+    threads: HashMap<String, Vec<ClaudeThreadRecord>>,
+}
+
+/// Newline-delimited JSON messages. The listener greets with a nonce, the connecting side
+/// answers with a MAC over it plus its own nonce, and the listener proves itself in return
+/// before either side sends a snapshot. Everything after the handshake is sealed with
+/// [`SessionCipher`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SyncMessage {
+    #[serde(rename_all = "camelCase")]
+    Hello {
+        device_id: String,
+        device_name: String,
+        nonce: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Auth {
+        device_id: String,
+        device_name: String,
+        nonce: String,
+        mac: String,
+    },
+    AuthOk {
+        mac: String,
+    },
+    Sync {
+        snapshot: SyncSnapshot,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// The running listener and its mDNS registration; dropping it stops both.
+pub(crate) struct LanSyncServer {
+    device_id: String,
+    device_name: String,
+    port: u16,
+    mdns: ServiceDaemon,
+    task: JoinHandle<()>,
+}
+
+impl Drop for LanSyncServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = self.mdns.shutdown();
+    }
+}
+
+pub(crate) type LanSyncState = Mutex<Option<LanSyncServer>>;
+
+/// What a sync session needs from this device.
+#[derive(Clone)]
+struct SyncContext<E: EventSink> {
+    secret: String,
+    /// The listener's copy is refreshed from saved settings before each connection.
+    app_settings: Arc<Mutex<AppSettings>>,
+    device_id: String,
+    device_name: String,
+    workspaces: Vec<WorkspaceEntry>,
+    claude_threads: ClaudeThreadsStore,
+    claude_threads_path: PathBuf,
+    event_sink: E,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct MergeOutcome {
+    /// Local workspace id to the ids of threads added or replaced in it.
+    changed: BTreeMap<String, Vec<String>>,
+    /// Peer workspaces with threads that match no local workspace.
+    unmatched_workspaces: Vec<String>,
+}
+
+/// Local thread ids a sync must leave alone, by local workspace id: archived and trashed
+/// threads, which a peer's copy would otherwise bring back.
+type HeldThreads = HashMap<String, HashSet<String>>;
+
+fn sync_secret(settings: &AppSettings) -> Result<String, String> {
+    settings
+        .lan_sync_secret
+        .as_deref()
+        .map(str::trim)
+        .filter(|secret| secret.chars().count() >= MIN_SECRET_LEN)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            format!(
+                "Set a LAN sync secret of at least {MIN_SECRET_LEN} characters on both devices."
+            )
+        })
+}
+
+fn bind_address(settings: &AppSettings) -> Result<SocketAddr, String> {
+    let ip = match settings
+        .lan_sync_bind_address
+        .as_deref()
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+    {
+        Some(address) => address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid LAN sync bind address `{address}`."))?,
+        None => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    };
+    Ok(SocketAddr::new(ip, settings.lan_sync_port))
+}
+
+fn device_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "PolyCode".to_string())
+}
+
+fn new_nonce() -> Result<String, String> {
+    let mut bytes = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Failed to generate a sync nonce.".to_string())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+/// The handshake key is derived from the secret rather than being the secret itself, so it
+/// stays independent of the session keys [`SessionCipher`] derives from the same secret.
+fn handshake_key(secret: &str) -> hmac::Key {
+    let prk =
+        hkdf::Salt::new(hkdf::HKDF_SHA256, AUTH_CONTEXT.as_bytes()).extract(secret.as_bytes());
+    let info = format!("{AUTH_CONTEXT}:handshake");
+    let info = [info.as_bytes()];
+    let okm = prk
+        .expand(&info, hmac::HMAC_SHA256)
+        .expect("HKDF-SHA256 can produce one HMAC-SHA256 key");
+    hmac::Key::from(okm)
+}
+
+fn handshake_mac(secret: &str, role: &str, nonce: &str) -> String {
+    let tag = hmac::sign(
+        &handshake_key(secret),
+        format!("{AUTH_CONTEXT}:{role}:{nonce}").as_bytes(),
+    );
+    base64::engine::general_purpose::STANDARD.encode(tag.as_ref())
+}
+
+/// Constant-time check that `mac` was produced by [`handshake_mac`] with the same secret.
+fn verify_handshake_mac(secret: &str, role: &str, nonce: &str, mac: &str) -> bool {
+    let Ok(tag) = base64::engine::general_purpose::STANDARD.decode(mac) else {
+        return false;
+    };
+    hmac::verify(
+        &handshake_key(secret),
+        format!("{AUTH_CONTEXT}:{role}:{nonce}").as_bytes(),
+        &tag,
+    )
+    .is_ok()
+}
+
+/// ChaCha20-Poly1305 keys for one connection, one per direction, derived with HKDF from the
+/// secret and both handshake nonces. Frames carry a per-direction counter as nonce, so a
+/// replayed, reordered or dropped frame fails to open.
+struct SessionCipher {
+    sealing: LessSafeKey,
+    opening: LessSafeKey,
+    sent: u64,
+    received: u64,
+}
+
+impl SessionCipher {
+    /// `role` is this side's: `server` for the listener, `client` for the connecting side.
+    fn new(
+        secret: &str,
+        server_nonce: &str,
+        client_nonce: &str,
+        role: &str,
+    ) -> Result<Self, String> {
+        let salt = hkdf::Salt::new(
+            hkdf::HKDF_SHA256,
+            format!("{server_nonce}:{client_nonce}").as_bytes(),
+        );
+        let prk = salt.extract(secret.as_bytes());
+        let key = |sender: &str| -> Result<LessSafeKey, String> {
+            let info = format!("{AUTH_CONTEXT}:session:{sender}");
+            let info = [info.as_bytes()];
+            let okm = prk
+                .expand(&info, &CHACHA20_POLY1305)
+                .map_err(|_| "Failed to derive the sync session key.".to_string())?;
+            Ok(LessSafeKey::new(UnboundKey::from(okm)))
+        };
+        let peer = if role == "server" { "client" } else { "server" };
+        Ok(Self {
+            sealing: key(role)?,
+            opening: key(peer)?,
+            sent: 0,
+            received: 0,
+        })
+    }
+
+    fn nonce(counter: u64) -> Nonce {
+        let mut bytes = [0u8; aead::NONCE_LEN];
+        bytes[aead::NONCE_LEN - 8..].copy_from_slice(&counter.to_be_bytes());
+        Nonce::assume_unique_for_key(bytes)
+    }
+
+    fn seal(&mut self, message: &SyncMessage) -> Result<String, String> {
+        let mut data = serde_json::to_vec(message).map_err(|error| error.to_string())?;
+        self.sealing
+            .seal_in_place_append_tag(
+                Self::nonce(self.sent),
+                Aad::from(AUTH_CONTEXT.as_bytes()),
+                &mut data,
+            )
+            .map_err(|_| "Failed to encrypt the sync message.".to_string())?;
+        self.sent += 1;
+        Ok(base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    fn open(&mut self, line: &str) -> Result<SyncMessage, String> {
+        let mut data = base64::engine::general_purpose::STANDARD
+            .decode(line.trim())
+            .map_err(|_| "The peer sent a malformed sealed message.".to_string())?;
+        let plain = self
+            .opening
+            .open_in_place(
+                Self::nonce(self.received),
+                Aad::from(AUTH_CONTEXT.as_bytes()),
+                &mut data,
+            )
+            .map_err(|_| "A sync message failed its integrity check.".to_string())?;
+        self.received += 1;
+        serde_json::from_slice(plain).map_err(|error| format!("Invalid message from peer: {error}"))
+    }
+}
+
+async fn write_line<W: AsyncWrite + Unpin>(writer: &mut W, line: &[u8]) -> Result<(), String> {
+    writer
+        .write_all(line)
+        .await
+        .map_err(|error| format!("Failed to send to peer: {error}"))?;
+    writer
+        .flush()
+        .await
+        .map_err(|error| format!("Failed to send to peer: {error}"))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &SyncMessage,
+) -> Result<(), String> {
+    let mut line = serde_json::to_vec(message).map_err(|error| error.to_string())?;
+    line.push(b'\n');
+    write_line(writer, &line).await
+}
+
+async fn write_sealed<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    cipher: &mut SessionCipher,
+    message: &SyncMessage,
+) -> Result<(), String> {
+    let mut line = cipher.seal(message)?;
+    line.push('\n');
+    write_line(writer, line.as_bytes()).await
+}
+
+/// Reads one line, refusing lines over `max_bytes` so an unauthenticated peer cannot make us
+/// buffer without bound.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: u64,
+) -> Result<String, String> {
+    let mut line = String::new();
+    let read = (&mut *reader)
+        .take(max_bytes)
+        .read_line(&mut line)
+        .await
+        .map_err(|error| format!("Failed to read from peer: {error}"))?;
+    if read == 0 {
+        return Err("The peer closed the connection.".to_string());
+    }
+    if !line.ends_with('\n') {
+        return Err("The peer sent a message that is too large.".to_string());
+    }
+    Ok(line)
+}
+
+async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: u64,
+) -> Result<SyncMessage, String> {
+    let line = read_line(reader, max_bytes).await?;
+    serde_json::from_str(&line).map_err(|error| format!("Invalid message from peer: {error}"))
+}
+
+async fn read_sealed<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    cipher: &mut SessionCipher,
+) -> Result<SyncMessage, String> {
+    let line = read_line(reader, MAX_SEALED_BYTES).await?;
+    cipher.open(&line)
+}
+
+fn unexpected_message(message: SyncMessage, expected: &str) -> String {
+    match message {
+        SyncMessage::Error { message } => format!("The peer refused to sync: {message}"),
+        _ => format!("Expected a `{expected}` message from the peer."),
+    }
+}
+
+fn local_snapshot(
+    workspaces: &[WorkspaceEntry],
+    store: &HashMap<String, Vec<ClaudeThreadRecord>>,
+) -> SyncSnapshot {
+    let threads = workspaces
+        .iter()
+        .filter_map(|workspace| {
+            store
+                .get(&workspace.id)
+                .filter(|threads| !threads.is_empty())
+                .map(|threads| (workspace.id.clone(), threads.clone()))
+        })
+        .collect();
+    SyncSnapshot {
+        workspaces: workspaces
+            .iter()
+            .map(|workspace| SyncWorkspace {
+                id: workspace.id.clone(),
+                name: workspace.name.clone(),
+                path: workspace.path.clone(),
+            })
+            .collect(),
+        threads,
+    }
+}
+
+/// Pairs a peer workspace with a local one: same id first (copied workspace lists), then the
+/// same path, then a name that is unique on this device.
+fn match_workspace<'a>(
+    remote: &SyncWorkspace,
+    local: &'a [WorkspaceEntry],
+) -> Option<&'a WorkspaceEntry> {
+    if let Some(entry) = local.iter().find(|entry| entry.id == remote.id) {
+        return Some(entry);
+    }
+    if let Some(entry) = local.iter().find(|entry| entry.path == remote.path) {
+        return Some(entry);
+    }
+    let mut named = local.iter().filter(|entry| entry.name == remote.name);
+    match (named.next(), named.next()) {
+        (Some(entry), None) => Some(entry),
+        _ => None,
+    }
+}
+
+/// `path` moved from the peer's workspace root to ours; `None` when it lies outside the
+/// peer's root, since a foreign absolute path means nothing on this device.
+fn rebase_path(path: &str, remote_root: &str, local_root: &str) -> Option<String> {
+    let relative = Path::new(path).strip_prefix(remote_root).ok()?;
+    if relative
+        .components()
+        .any(|component| !matches!(component, std::path::Component::Normal(_)))
+    {
+        return None;
+    }
+    if relative.as_os_str().is_empty() {
+        return Some(local_root.to_string());
+    }
+    Some(
+        Path::new(local_root)
+            .join(relative)
+            .to_string_lossy()
+            .to_string(),
+    )
+}
+
+/// Adapts a peer's thread to this device. Claude sessions live on the machine that ran them,
+/// so a thread with history continues in a fresh session seeded from its transcript. A
+/// directory outside the peer's workspace falls back to the local workspace root.
+fn localize_thread(
+    mut thread: ClaudeThreadRecord,
+    remote: &SyncWorkspace,
+    local: &WorkspaceEntry,
+    existing: Option<&ClaudeThreadRecord>,
+) -> ClaudeThreadRecord {
+    thread.cwd =
+        rebase_path(&thread.cwd, &remote.path, &local.path).unwrap_or_else(|| local.path.clone());
+    thread.cwd_override = thread
+        .cwd_override
+        .and_then(|path| rebase_path(&path, &remote.path, &local.path));
+    // Worktrees and turn snapshots only exist on the device that created them.
+    thread.worktree = existing.and_then(|existing| existing.worktree.clone());
+    for turn in &mut thread.turns {
+        turn.snapshot = existing
+            .and_then(|existing| existing.turns.iter().find(|local| local.id == turn.id))
+            .and_then(|local| local.snapshot.clone());
+    }
+    if !thread.turns.is_empty() {
+        thread.session_id = Some(Uuid::new_v4().to_string());
+        thread.session_needs_seed = true;
+    }
+    thread
+}
+
+/// Merges a peer snapshot into the local store. Each thread is matched by id and the copy with
+/// the newer `updatedAt` wins; ties keep the local copy. Threads only the peer has are added,
+/// and nothing is ever deleted. Held threads and threads with a turn in flight on either side
+/// are skipped.
+fn merge_snapshot(
+    store: &mut HashMap<String, Vec<ClaudeThreadRecord>>,
+    local_workspaces: &[WorkspaceEntry],
+    held: &HeldThreads,
+    incoming: SyncSnapshot,
+) -> MergeOutcome {
+    let mut outcome = MergeOutcome::default();
+    let mut incoming_threads = incoming.threads;
+    for remote in &incoming.workspaces {
+        let Some(threads) = incoming_threads.remove(&remote.id) else {
+            continue;
+        };
+        if threads.is_empty() {
+            continue;
+        }
+        let Some(local) = match_workspace(remote, local_workspaces) else {
+            outcome.unmatched_workspaces.push(remote.name.clone());
+            continue;
+        };
+        let held_ids = held.get(&local.id);
+        let local_threads = store.entry(local.id.clone()).or_default();
+        for thread in threads {
+            let thread_id = thread.id.clone();
+            if thread.has_open_turn()
+                || held_ids.is_some_and(|ids| is_archived_thread_id(ids, &thread_id))
+            {
+                continue;
+            }
+            let position = local_threads
+                .iter()
+                .position(|existing| existing.id == thread_id);
+            match position {
+                Some(index)
+                    if local_threads[index].has_open_turn()
+                        || local_threads[index].updated_at >= thread.updated_at =>
+                {
+                    continue
+                }
+                Some(index) => {
+                    local_threads[index] =
+                        localize_thread(thread, remote, local, Some(&local_threads[index]));
+                }
+                None => local_threads.push(localize_thread(thread, remote, local, None)),
+            }
+            outcome
+                .changed
+                .entry(local.id.clone())
+                .or_default()
+                .push(thread_id);
+        }
+    }
+    outcome
+}
+
+async fn apply_snapshot<E: EventSink>(
+    context: &SyncContext<E>,
+    snapshot: SyncSnapshot,
+) -> Result<MergeOutcome, String> {
+    ensure_not_read_only(&context.app_settings, "LAN sync").await?;
+    let mut held = trashed_thread_ids(&context.claude_threads_path)?;
+    for workspace in &context.workspaces {
+        held.entry(workspace.id.clone()).or_default().extend(
+            read_archived_thread_ids_for_workspace(&context.claude_threads_path, &workspace.id),
+        );
+    }
+    let outcome = {
+        let mut store = context.claude_threads.lock().await;
+        merge_snapshot(&mut store, &context.workspaces, &held, snapshot)
+    };
+    if outcome.changed.is_empty() {
+        return Ok(outcome);
+    }
+    persist_threads_store(&context.claude_threads, &context.claude_threads_path).await?;
+    for (workspace_id, thread_ids) in &outcome.changed {
+        emit_thread_list_updated(
+            &context.event_sink,
+            &context.claude_threads,
+            workspace_id,
+            thread_ids,
+            &[],
+        )
+        .await;
+    }
+    Ok(outcome)
+}
+
+async fn current_snapshot<E: EventSink>(context: &SyncContext<E>) -> SyncSnapshot {
+    let store = context.claude_threads.lock().await;
+    local_snapshot(&context.workspaces, &store)
+}
+
+/// Listener side of one connection.
+async fn serve_connection<E: EventSink>(
+    stream: TcpStream,
+    context: &SyncContext<E>,
+) -> Result<(), String> {
+    let (read_half, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    let nonce = new_nonce()?;
+    write_message(
+        &mut writer,
+        &SyncMessage::Hello {
+            device_id: context.device_id.clone(),
+            device_name: context.device_name.clone(),
+            nonce: nonce.clone(),
+        },
+    )
+    .await?;
+    let (peer_nonce, mac) = match read_message(&mut reader, MAX_HANDSHAKE_BYTES).await? {
+        SyncMessage::Auth { nonce, mac, .. } => (nonce, mac),
+        other => return Err(unexpected_message(other, "auth")),
+    };
+    if !verify_handshake_mac(&context.secret, "client", &nonce, &mac) {
+        let message = "Authentication failed; check that both devices use the same secret.";
+        let _ = write_message(
+            &mut writer,
+            &SyncMessage::Error {
+                message: message.to_string(),
+            },
+        )
+        .await;
+        return Err(message.to_string());
+    }
+    write_message(
+        &mut writer,
+        &SyncMessage::AuthOk {
+            mac: handshake_mac(&context.secret, "server", &peer_nonce),
+        },
+    )
+    .await?;
+    let mut cipher = SessionCipher::new(&context.secret, &nonce, &peer_nonce, "server")?;
+    let snapshot = match read_sealed(&mut reader, &mut cipher).await? {
+        SyncMessage::Sync { snapshot } => snapshot,
+        other => return Err(unexpected_message(other, "sync")),
+    };
+    if let Err(error) = apply_snapshot(context, snapshot).await {
+        let _ = write_sealed(
+            &mut writer,
+            &mut cipher,
+            &SyncMessage::Error {
+                message: error.clone(),
+            },
+        )
+        .await;
+        return Err(error);
+    }
+    let snapshot = current_snapshot(context).await;
+    write_sealed(&mut writer, &mut cipher, &SyncMessage::Sync { snapshot }).await
+}
+
+/// Connecting side: authenticates the listener, exchanges snapshots and merges the reply.
+async fn sync_with_peer<E: EventSink>(
+    address: &str,
+    context: &SyncContext<E>,
+) -> Result<Value, String> {
+    let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(|_| format!("Timed out connecting to {address}."))?
+        .map_err(|error| format!("Failed to connect to {address}: {error}"))?;
+    let (read_half, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    let (peer_id, peer_name, peer_nonce) =
+        match read_message(&mut reader, MAX_HANDSHAKE_BYTES).await? {
+            SyncMessage::Hello {
+                device_id,
+                device_name,
+                nonce,
+            } => (device_id, device_name, nonce),
+            other => return Err(unexpected_message(other, "hello")),
+        };
+    if peer_id == context.device_id {
+        return Err("That address is this device.".to_string());
+    }
+    let nonce = new_nonce()?;
+    write_message(
+        &mut writer,
+        &SyncMessage::Auth {
+            device_id: context.device_id.clone(),
+            device_name: context.device_name.clone(),
+            nonce: nonce.clone(),
+            mac: handshake_mac(&context.secret, "client", &peer_nonce),
+        },
+    )
+    .await?;
+    match read_message(&mut reader, MAX_HANDSHAKE_BYTES).await? {
+        SyncMessage::AuthOk { mac }
+            if verify_handshake_mac(&context.secret, "server", &nonce, &mac) => {}
+        SyncMessage::AuthOk { .. } => {
+            return Err("The peer could not prove it knows the sync secret.".to_string())
+        }
+        other => return Err(unexpected_message(other, "authOk")),
+    }
+    let mut cipher = SessionCipher::new(&context.secret, &peer_nonce, &nonce, "client")?;
+    let snapshot = current_snapshot(context).await;
+    write_sealed(&mut writer, &mut cipher, &SyncMessage::Sync { snapshot }).await?;
+    let snapshot = match read_sealed(&mut reader, &mut cipher).await? {
+        SyncMessage::Sync { snapshot } => snapshot,
+        other => return Err(unexpected_message(other, "sync")),
+    };
+    let outcome = apply_snapshot(context, snapshot).await?;
+    Ok(json!({
+        "result": {
+            "peer": {
+                "deviceId": peer_id,
+                "deviceName": peer_name,
+                "address": address,
+            },
+            "updatedThreads": outcome.changed.values().map(Vec::len).sum::<usize>(),
+            "unmatchedWorkspaces": outcome.unmatched_workspaces,
+        }
+    }))
+}
+
+fn status_response(server: Option<&LanSyncServer>) -> Value {
+    match server {
+        Some(server) => json!({
+            "result": {
+                "running": true,
+                "deviceId": server.device_id,
+                "deviceName": server.device_name,
+                "port": server.port,
+            }
+        }),
+        None => json!({ "result": { "running": false } }),
+    }
+}
+
+/// Starts (or restarts) the sync listener and advertises it over mDNS. The workspace list is
+/// captured here, so restart the listener after adding workspaces.
+pub(crate) async fn lan_sync_start_core<E: EventSink>(
+    lan_sync: &LanSyncState,
+    app_settings: &Mutex<AppSettings>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    event_sink: E,
+) -> Result<Value, String> {
+    let (secret, bind_address) = {
+        let settings = app_settings.lock().await;
+        (sync_secret(&settings)?, bind_address(&settings)?)
+    };
+    let mut current = lan_sync.lock().await;
+    // Release the port before binding it again.
+    let previous_id = current.take().map(|server| server.device_id.clone());
+    let listener = TcpListener::bind(bind_address)
+        .await
+        .map_err(|error| format!("Failed to listen on {bind_address}: {error}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|error| error.to_string())?
+        .port();
+    let context = SyncContext {
+        secret,
+        app_settings: Arc::new(Mutex::new(app_settings.lock().await.clone())),
+        device_id: previous_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+        device_name: device_name(),
+        workspaces: workspaces.lock().await.values().cloned().collect(),
+        claude_threads: claude_threads.clone(),
+        claude_threads_path: claude_threads_path.to_path_buf(),
+        event_sink,
+    };
+
+    let mdns = ServiceDaemon::new().map_err(|error| format!("Failed to start mDNS: {error}"))?;
+    let properties = HashMap::from([
+        ("id".to_string(), context.device_id.clone()),
+        ("name".to_string(), context.device_name.clone()),
+    ]);
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &context.device_id,
+        &format!("{}.local.", context.device_id),
+        "",
+        port,
+        properties,
+    )
+    .map_err(|error| format!("Failed to describe the sync service: {error}"))?
+    .enable_addr_auto();
+    mdns.register(service)
+        .map_err(|error| format!("Failed to advertise the sync service: {error}"))?;
+
+    let device_id = context.device_id.clone();
+    let device_name = context.device_name.clone();
+    let mut settings_changes = subscribe_settings_changes();
+    let task = tokio::spawn(async move {
+        while let Ok((stream, peer)) = listener.accept().await {
+            if let Some(settings) = latest_settings(&mut settings_changes) {
+                *context.app_settings.lock().await = (*settings).clone();
+            }
+            let context = context.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(SESSION_TIMEOUT, serve_connection(stream, &context))
+                    .await
+                {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => eprintln!("LAN sync with {peer} failed: {error}"),
+                    Err(_) => eprintln!("LAN sync with {peer} timed out."),
+                }
+            });
+        }
+    });
+    *current = Some(LanSyncServer {
+        device_id,
+        device_name,
+        port,
+        mdns,
+        task,
+    });
+    Ok(status_response(current.as_ref()))
+}
+
+pub(crate) async fn lan_sync_stop_core(lan_sync: &LanSyncState) -> Result<Value, String> {
+    lan_sync.lock().await.take();
+    Ok(status_response(None))
+}
+
+/// Browses mDNS for other PolyCode devices for up to `timeout_ms`.
+pub(crate) async fn lan_sync_discover_core(
+    lan_sync: &LanSyncState,
+    timeout_ms: Option<u64>,
+) -> Result<Value, String> {
+    let timeout = Duration::from_millis(
+        timeout_ms
+            .unwrap_or(DEFAULT_DISCOVERY_MS)
+            .clamp(1, MAX_DISCOVERY_MS),
+    );
+    let own_id = lan_sync
+        .lock()
+        .await
+        .as_ref()
+        .map(|server| server.device_id.clone());
+    let mdns = ServiceDaemon::new().map_err(|error| format!("Failed to start mDNS: {error}"))?;
+    let receiver = mdns
+        .browse(SERVICE_TYPE)
+        .map_err(|error| format!("Failed to browse for devices: {error}"))?;
+    let peers = tokio::task::spawn_blocking(move || {
+        let deadline = Instant::now() + timeout;
+        let mut peers = BTreeMap::new();
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let Ok(event) = receiver.recv_timeout(remaining) else {
+                break;
+            };
+            let ServiceEvent::ServiceResolved(info) = event else {
+                continue;
+            };
+            let device_id = info
+                .get_property_val_str("id")
+                .unwrap_or_else(|| info.get_fullname())
+                .to_string();
+            if own_id.as_deref() == Some(device_id.as_str()) {
+                continue;
+            }
+            let addresses = info.get_addresses();
+            let Some(ip) = addresses
+                .iter()
+                .find(|ip| ip.is_ipv4())
+                .or_else(|| addresses.iter().next())
+                .copied()
+            else {
+                continue;
+            };
+            let device_name = info.get_property_val_str("name").unwrap_or("PolyCode");
+            peers.insert(
+                device_id.clone(),
+                json!({
+                    "deviceId": device_id,
+                    "deviceName": device_name,
+                    "address": SocketAddr::new(ip, info.get_port()).to_string(),
+                }),
+            );
+        }
+        peers.into_values().collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|error| error.to_string());
+    let _ = mdns.shutdown();
+    let peers = peers?;
+    Ok(json!({ "result": { "peers": peers } }))
+}
+
+/// Syncs the thread store with the device listening at `address` (`host:port`).
+pub(crate) async fn lan_sync_with_peer_core<E: EventSink>(
+    lan_sync: &LanSyncState,
+    app_settings: &Mutex<AppSettings>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    address: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let address = address.trim().to_string();
+    if address.is_empty() {
+        return Err("Peer address is required.".to_string());
+    }
+    ensure_not_read_only(app_settings, "LAN sync").await?;
+    let secret = sync_secret(&*app_settings.lock().await)?;
+    let (device_id, device_name) = match lan_sync.lock().await.as_ref() {
+        Some(server) => (server.device_id.clone(), server.device_name.clone()),
+        None => (Uuid::new_v4().to_string(), device_name()),
+    };
+    let context = SyncContext {
+        secret,
+        app_settings: Arc::new(Mutex::new(app_settings.lock().await.clone())),
+        device_id,
+        device_name,
+        workspaces: workspaces.lock().await.values().cloned().collect(),
+        claude_threads: claude_threads.clone(),
+        claude_threads_path: claude_threads_path.to_path_buf(),
+        event_sink,
+    };
+    tokio::time::timeout(SESSION_TIMEOUT, sync_with_peer(&address, &context))
+        .await
+        .map_err(|_| format!("Sync with {address} timed out."))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_snapshot, handshake_key, handshake_mac, merge_snapshot, verify_handshake_mac,
+        HeldThreads, SessionCipher, SyncContext, SyncMessage, SyncSnapshot, SyncWorkspace,
+        AUTH_CONTEXT,
+    };
+    use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use crate::shared::trash_core::{trash_threads, TrashReason};
+    use crate::types::{AppSettings, WorkspaceEntry};
+    use ring::hmac;
+    use serde_json::json;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[derive(Clone)]
+    struct NoopSink;
+
+    impl EventSink for NoopSink {
+        fn emit_app_server_event(&self, _event: AppServerEvent) {}
+        fn emit_terminal_output(&self, _event: TerminalOutput) {}
+        fn emit_terminal_exit(&self, _event: TerminalExit) {}
+    }
+
+    fn thread(id: &str, cwd: &str, updated_at: i64) -> ClaudeThreadRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "cwd": cwd,
+            "preview": id,
+            "createdAt": 1,
+            "updatedAt": updated_at,
+            "turns": [],
+        }))
+        .expect("thread")
+    }
+
+    fn mid_turn(id: &str, cwd: &str, updated_at: i64) -> ClaudeThreadRecord {
+        let mut thread = thread(id, cwd, updated_at);
+        thread.turns = serde_json::from_value(json!([
+            { "id": "turn-1", "startedAt": updated_at, "completedAt": null, "items": [] },
+        ]))
+        .expect("turns");
+        thread
+    }
+
+    fn workspace(id: &str, path: &str) -> WorkspaceEntry {
+        serde_json::from_value(json!({ "id": id, "name": "polycode", "path": path }))
+            .expect("workspace")
+    }
+
+    fn snapshot_of(
+        workspace_id: &str,
+        path: &str,
+        threads: Vec<ClaudeThreadRecord>,
+    ) -> SyncSnapshot {
+        SyncSnapshot {
+            workspaces: vec![SyncWorkspace {
+                id: workspace_id.to_string(),
+                name: "polycode".to_string(),
+                path: path.to_string(),
+            }],
+            threads: HashMap::from([(workspace_id.to_string(), threads)]),
+        }
+    }
+
+    #[test]
+    fn newer_threads_win_and_paths_follow_the_local_workspace() {
+        let local_workspace: WorkspaceEntry = serde_json::from_value(json!({
+            "id": "ws-laptop",
+            "name": "polycode",
+            "path": "/Users/me/src/polycode",
+        }))
+        .expect("workspace");
+        let mut store = HashMap::from([(
+            "ws-laptop".to_string(),
+            vec![
+                thread("stale", "/Users/me/src/polycode", 10),
+                thread("fresh", "/Users/me/src/polycode", 50),
+            ],
+        )]);
+        let incoming = SyncSnapshot {
+            workspaces: vec![
+                SyncWorkspace {
+                    id: "ws-desktop".to_string(),
+                    name: "polycode".to_string(),
+                    path: "/home/me/polycode".to_string(),
+                },
+                SyncWorkspace {
+                    id: "ws-other".to_string(),
+                    name: "scratch".to_string(),
+                    path: "/home/me/scratch".to_string(),
+                },
+            ],
+            threads: HashMap::from([
+                (
+                    "ws-desktop".to_string(),
+                    vec![
+                        thread("stale", "/home/me/polycode", 20),
+                        thread("fresh", "/home/me/polycode", 40),
+                        thread("new", "/home/me/polycode/app", 30),
+                        thread("foreign", "/etc/secrets", 30),
+                        thread("escape", "/home/me/polycode/../.ssh", 30),
+                    ],
+                ),
+                (
+                    "ws-other".to_string(),
+                    vec![thread("lost", "/home/me/scratch", 5)],
+                ),
+            ]),
+        };
+
+        let outcome = merge_snapshot(
+            &mut store,
+            &[local_workspace],
+            &HeldThreads::new(),
+            incoming,
+        );
+        assert_eq!(
+            outcome.changed["ws-laptop"],
+            vec!["stale", "new", "foreign", "escape"]
+        );
+        assert_eq!(outcome.unmatched_workspaces, vec!["scratch"]);
+        let threads = &store["ws-laptop"];
+        assert_eq!(threads[0].updated_at, 20);
+        assert_eq!(threads[0].cwd, "/Users/me/src/polycode");
+        assert_eq!(threads[1].updated_at, 50);
+        assert_eq!(threads[2].cwd, "/Users/me/src/polycode/app");
+        assert_eq!(threads[3].cwd, "/Users/me/src/polycode");
+        assert_eq!(threads[4].cwd, "/Users/me/src/polycode");
+    }
+
+    #[test]
+    fn held_threads_and_threads_mid_turn_are_left_alone() {
+        let mut store = HashMap::from([("ws".to_string(), vec![mid_turn("busy", "/repo", 10)])]);
+        let held = HeldThreads::from([(
+            "ws".to_string(),
+            HashSet::from(["claude-thread-archived".to_string(), "trashed".to_string()]),
+        )]);
+        let incoming = snapshot_of(
+            "ws",
+            "/repo",
+            vec![
+                thread("busy", "/repo", 20),
+                mid_turn("running", "/repo", 20),
+                thread("archived", "/repo", 20),
+                thread("trashed", "/repo", 20),
+                thread("new", "/repo", 20),
+            ],
+        );
+
+        let outcome = merge_snapshot(&mut store, &[workspace("ws", "/repo")], &held, incoming);
+        assert_eq!(outcome.changed["ws"], vec!["new"]);
+        assert_eq!(store["ws"][0].updated_at, 10);
+        assert_eq!(store["ws"].len(), 2);
+    }
+
+    #[test]
+    fn applying_a_snapshot_respects_read_only_mode_and_the_local_trash_and_archive() {
+        let root = std::env::temp_dir().join(format!("lan-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let claude_threads_path = root.join("claude_threads.json");
+        trash_threads(
+            &claude_threads_path,
+            "ws",
+            vec![thread("trashed", "/repo", 1)],
+            TrashReason::Delete,
+        )
+        .expect("trash");
+        std::fs::write(
+            root.join("claude_archived_threads.json"),
+            json!({ "ws": ["claude-thread-archived"] }).to_string(),
+        )
+        .expect("archive");
+        let context = SyncContext {
+            secret: "correct horse battery".to_string(),
+            app_settings: Arc::new(Mutex::new(AppSettings {
+                read_only: true,
+                ..AppSettings::default()
+            })),
+            device_id: "device".to_string(),
+            device_name: "PolyCode".to_string(),
+            workspaces: vec![workspace("ws", "/repo")],
+            claude_threads: Arc::new(Mutex::new(HashMap::new())),
+            claude_threads_path: claude_threads_path.clone(),
+            event_sink: NoopSink,
+        };
+        let incoming = || {
+            snapshot_of(
+                "ws",
+                "/home/me/repo",
+                vec![
+                    thread("trashed", "/home/me/repo", 20),
+                    thread("archived", "/home/me/repo", 20),
+                    thread("new", "/home/me/repo", 20),
+                ],
+            )
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let error = apply_snapshot(&context, incoming())
+                .await
+                .expect_err("read-only");
+            assert!(error.starts_with("PermissionDenied:"));
+            assert!(context.claude_threads.lock().await.is_empty());
+            assert!(!claude_threads_path.exists());
+
+            context.app_settings.lock().await.read_only = false;
+            let outcome = apply_snapshot(&context, incoming()).await.expect("apply");
+            assert_eq!(outcome.changed["ws"], vec!["new"]);
+            let store = context.claude_threads.lock().await;
+            assert_eq!(store["ws"].len(), 1);
+            assert_eq!(store["ws"][0].cwd, "/repo");
+        });
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn sealed_frames_only_open_in_order_with_the_same_secret() {
+        let secret = "correct horse battery";
+        let mut client =
+            SessionCipher::new(secret, "server-nonce", "client-nonce", "client").expect("client");
+        let mut server =
+            SessionCipher::new(secret, "server-nonce", "client-nonce", "server").expect("server");
+        let message = || SyncMessage::Sync {
+            snapshot: SyncSnapshot::default(),
+        };
+
+        let first = client.seal(&message()).expect("seal");
+        assert!(!first.contains("snapshot"));
+        let second = client.seal(&message()).expect("seal");
+        assert!(server.open(&second).is_err());
+        assert!(matches!(server.open(&first), Ok(SyncMessage::Sync { .. })));
+        assert!(matches!(server.open(&second), Ok(SyncMessage::Sync { .. })));
+        assert!(server.open(&second).is_err());
+
+        let mut tampered = client.seal(&message()).expect("seal").into_bytes();
+        tampered[4] = if tampered[4] == b'A' { b'B' } else { b'A' };
+        assert!(server
+            .open(std::str::from_utf8(&tampered).expect("utf8"))
+            .is_err());
+
+        let reply = server.seal(&message()).expect("seal");
+        assert!(client.open(&reply).is_ok());
+        let mut intruder = SessionCipher::new(
+            "wrong secret value",
+            "server-nonce",
+            "client-nonce",
+            "client",
+        )
+        .expect("intruder");
+        assert!(intruder
+            .open(&server.seal(&message()).expect("seal"))
+            .is_err());
+    }
+
+    #[test]
+    fn handshake_macs_are_bound_to_secret_and_role() {
+        let mac = handshake_mac("correct horse battery", "client", "nonce-1");
+        assert!(verify_handshake_mac(
+            "correct horse battery",
+            "client",
+            "nonce-1",
+            &mac
+        ));
+        assert!(!verify_handshake_mac(
+            "wrong secret value",
+            "client",
+            "nonce-1",
+            &mac
+        ));
+        assert!(!verify_handshake_mac(
+            "correct horse battery",
+            "server",
+            "nonce-1",
+            &mac
+        ));
+        assert!(!verify_handshake_mac(
+            "correct horse battery",
+            "client",
+            "nonce-2",
+            &mac
+        ));
+        let raw_key = hmac::Key::new(hmac::HMAC_SHA256, b"correct horse battery");
+        let message = format!("{AUTH_CONTEXT}:client:nonce-1");
+        assert_ne!(
+            hmac::sign(&raw_key, message.as_bytes()).as_ref(),
+            hmac::sign(&handshake_key("correct horse battery"), message.as_bytes()).as_ref()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+
+use crate::shared::claude_core::ClaudeThreadsStore;
+use crate::types::WorkspaceEntry;
+
+/// Workspace-relative notes file that new threads receive as context.
+pub(crate) const MEMORY_FILE_PATH: &str = ".polycode/memory.md";
+
+fn memory_path(workspace_path: &str) -> PathBuf {
+    Path::new(workspace_path).join(MEMORY_FILE_PATH)
+}
+
+async fn workspace_path(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<String, String> {
+    workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(|| "workspace not found".to_string())
+}
+
+fn read_memory(path: &Path) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("Failed to read {}: {error}", path.display())),
+    }
+}
+
+fn write_memory(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    std::fs::write(path, content)
+        .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+}
+
+/// Adds `entry` as its own paragraph so appended notes never run into the previous line.
+fn append_entry(existing: &str, entry: &str) -> String {
+    let existing = existing.trim_end();
+    if existing.is_empty() {
+        format!("{}\n", entry.trim())
+    } else {
+        format!("{existing}\n\n{}\n", entry.trim())
+    }
+}
+
+fn memory_response(path: &Path, content: Option<String>) -> Value {
+    json!({
+        "result": {
+            "path": MEMORY_FILE_PATH,
+            "absolutePath": path.to_string_lossy(),
+            "exists": content.is_some(),
+            "content": content.unwrap_or_default(),
+        }
+    })
+}
+
+pub(crate) async fn memory_read_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let path = memory_path(&workspace_path(workspaces, &workspace_id).await?);
+    let content = read_memory(&path)?;
+    Ok(memory_response(&path, content))
+}
+
+/// Replaces the notes with `content`, as saved from the editor.
+pub(crate) async fn memory_write_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    content: String,
+) -> Result<Value, String> {
+    let path = memory_path(&workspace_path(workspaces, &workspace_id).await?);
+    write_memory(&path, &content)?;
+    Ok(memory_response(&path, Some(content)))
+}
+
+/// Appends `text`, or the final assistant reply of `turn_id` when no text is given.
+pub(crate) async fn memory_append_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    text: Option<String>,
+    thread_id: Option<String>,
+    turn_id: Option<String>,
+) -> Result<Value, String> {
+    let entry = match (
+        text.filter(|text| !text.trim().is_empty()),
+        thread_id,
+        turn_id,
+    ) {
+        (Some(text), _, _) => text,
+        (None, Some(thread_id), Some(turn_id)) => {
+            turn_memory_entry(claude_threads, &workspace_id, &thread_id, &turn_id).await?
+        }
+        _ => {
+            return Err("Nothing to append: pass `text` or a `threadId` and `turnId`.".to_string())
+        }
+    };
+    let path = memory_path(&workspace_path(workspaces, &workspace_id).await?);
+    let existing = read_memory(&path)?.unwrap_or_default();
+    let content = append_entry(&existing, &entry);
+    write_memory(&path, &content)?;
+    Ok(memory_response(&path, Some(content)))
+}
+
+async fn turn_memory_entry(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<String, String> {
+    let store = claude_threads.lock().await;
+    let thread = store
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .ok_or_else(|| "thread not found".to_string())?;
+    let turn = thread
+        .turns
+        .iter()
+        .find(|turn| turn.id == turn_id)
+        .ok_or_else(|| "turn not found".to_string())?;
+    if turn.completed_at.is_none() {
+        return Err("Only completed turns can be saved to memory.".to_string());
+    }
+    let reply = turn
+        .items
+        .iter()
+        .rev()
+        .find(|item| item.role == "assistant" && !item.text.trim().is_empty())
+        .map(|item| item.text.trim().to_string())
+        .ok_or_else(|| "The turn has no assistant reply to save.".to_string())?;
+    let title = thread
+        .name
+        .clone()
+        .unwrap_or_else(|| thread.preview.clone());
+    Ok(format!("### From \"{}\"\n\n{reply}", title.trim()))
+}
+
+pub(crate) async fn memory_clear_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let path = memory_path(&workspace_path(workspaces, &workspace_id).await?);
+    match std::fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => return Err(format!("Failed to remove {}: {error}", path.display())),
+    }
+    Ok(memory_response(&path, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::append_entry;
+
+    #[test]
+    fn append_entry_separates_paragraphs() {
+        assert_eq!(append_entry("", "  first note \n"), "first note\n");
+        assert_eq!(
+            append_entry("first note\n\n\n", "second"),
+            "first note\n\nsecond\n"
+        );
+    }
+}
@@ -1,9 +1,74 @@
 pub(crate) mod account;
+pub(crate) mod actions_core;
+pub(crate) mod answer_diff_core;
+pub(crate) mod audit_core;
 pub(crate) mod claude_core;
+pub(crate) mod claude_follow_core;
+pub(crate) mod claude_hooks_core;
+pub(crate) mod claude_pool_core;
 pub(crate) mod codex_core;
+pub(crate) mod codex_import_core;
+pub(crate) mod commit_message_core;
+pub(crate) mod context_files_core;
+pub(crate) mod data_dir_core;
+pub(crate) mod drafts_core;
+pub(crate) mod event_filter_core;
+pub(crate) mod event_mirror_core;
+pub(crate) mod external_import_core;
+pub(crate) mod file_access_core;
+pub(crate) mod file_references_core;
 pub(crate) mod files_core;
+pub(crate) mod gemini_core;
+pub(crate) mod gemini_stream_core;
 pub(crate) mod git_core;
+pub(crate) mod idle_summary_core;
+pub(crate) mod inbox_core;
+pub(crate) mod lan_sync_core;
+pub(crate) mod memory_core;
+pub(crate) mod model_downgrade_core;
+pub(crate) mod orphans_core;
+pub(crate) mod output_cap_core;
+pub(crate) mod output_decode_core;
+pub(crate) mod output_filters_core;
+pub(crate) mod output_sanitize_core;
+pub(crate) mod patches_core;
+pub(crate) mod playbooks_core;
 pub(crate) mod process_core;
+pub(crate) mod prompt_estimate_core;
+pub(crate) mod prompt_history_core;
+pub(crate) mod provider_detect_core;
+pub(crate) mod provider_versions_core;
+pub(crate) mod quick_reply_core;
+pub(crate) mod rate_limit_core;
+pub(crate) mod redaction_core;
+pub(crate) mod render_cache_core;
+pub(crate) mod resource_guard_core;
+pub(crate) mod retention_core;
+pub(crate) mod sandbox_core;
+pub(crate) mod secrets_core;
 pub(crate) mod settings_core;
-pub(crate) mod worktree_core;
+pub(crate) mod setup_core;
+pub(crate) mod shutdown_core;
+pub(crate) mod status_summary_core;
+pub(crate) mod thread_edit_core;
+pub(crate) mod thread_export_core;
+pub(crate) mod thread_inheritance_core;
+pub(crate) mod thread_locks_core;
+pub(crate) mod thread_quick_find_core;
+pub(crate) mod thread_rebind_core;
+pub(crate) mod thread_schema_core;
+pub(crate) mod thread_stats_core;
+pub(crate) mod thread_sync_core;
+pub(crate) mod thread_worktree_core;
+pub(crate) mod transcription_core;
+pub(crate) mod trash_core;
+pub(crate) mod turn_artifacts_core;
+pub(crate) mod turn_checklist_core;
+pub(crate) mod turn_details_core;
+pub(crate) mod turn_hooks_core;
+pub(crate) mod turn_slots_core;
+pub(crate) mod turn_snapshot_core;
+pub(crate) mod turn_suggestions_core;
+pub(crate) mod workspace_scan_core;
 pub(crate) mod workspaces_core;
+pub(crate) mod worktree_core;
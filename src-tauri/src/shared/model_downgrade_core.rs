@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::shared::claude_core::ClaudeThreadRecord;
+use crate::types::{AppSettings, ProviderKind};
+
+/// The daily turn budget counts turns started within this window.
+const BUDGET_WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DowngradeReason {
+    /// The thread carries one of `downgradeTags`.
+    SimpleThread,
+    /// The daily turn budget is close to used up.
+    NearBudget,
+}
+
+/// Kept on a turn that ran on a cheaper model than its thread asks for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ModelDowngrade {
+    /// The model the turn actually used.
+    pub(crate) model: String,
+    /// The thread's pinned model; unset when it follows the CLI default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) from: Option<String>,
+    pub(crate) reason: DowngradeReason,
+    /// The tag that matched or how much of the budget is used, for display.
+    pub(crate) detail: String,
+}
+
+/// Turns started across all workspaces within the budget window ending at `now`.
+pub(crate) fn turns_in_budget_window(
+    threads: &HashMap<String, Vec<ClaudeThreadRecord>>,
+    now: i64,
+) -> u32 {
+    let count = threads
+        .values()
+        .flatten()
+        .flat_map(|thread| &thread.turns)
+        .filter(|turn| now - turn.started_at < BUDGET_WINDOW_MS)
+        .count();
+    u32::try_from(count).unwrap_or(u32::MAX)
+}
+
+/// The cheaper model a turn should run on, if the policy applies. A thread tagged as simple
+/// always drops down; any other thread does once `turns_used` reaches the budget threshold.
+pub(crate) fn choose_model(
+    settings: &AppSettings,
+    provider: &ProviderKind,
+    tags: &[String],
+    pinned_model: Option<&str>,
+    turns_used: u32,
+) -> Option<ModelDowngrade> {
+    let model = settings
+        .cheaper_models
+        .get(provider.as_str())
+        .map(|model| model.trim())
+        .filter(|model| !model.is_empty())?;
+    if pinned_model == Some(model) {
+        return None;
+    }
+    let simple_tag = tags.iter().find(|tag| {
+        settings
+            .downgrade_tags
+            .iter()
+            .any(|simple| simple.trim().eq_ignore_ascii_case(tag.trim()))
+    });
+    let (reason, detail) = if let Some(tag) = simple_tag {
+        (
+            DowngradeReason::SimpleThread,
+            format!("Thread is tagged `{tag}`."),
+        )
+    } else {
+        let budget = settings.daily_turn_budget.filter(|budget| *budget > 0)?;
+        let threshold = u64::from(budget) * u64::from(settings.downgrade_budget_percent) / 100;
+        if u64::from(turns_used) < threshold {
+            return None;
+        }
+        (
+            DowngradeReason::NearBudget,
+            format!("{turns_used} of {budget} daily turns used."),
+        )
+    };
+    Some(ModelDowngrade {
+        model: model.to_string(),
+        from: pinned_model.map(str::to_string),
+        reason,
+        detail,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{choose_model, turns_in_budget_window, DowngradeReason, BUDGET_WINDOW_MS};
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use crate::types::{AppSettings, ProviderKind};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn downgrades_simple_threads_and_turns_near_the_budget() {
+        let mut settings = AppSettings::default();
+        let claude = ProviderKind::Claude;
+        let chat = vec!["Chat".to_string()];
+        assert_eq!(choose_model(&settings, &claude, &chat, None, 0), None);
+
+        settings
+            .cheaper_models
+            .insert("claude".to_string(), "haiku".to_string());
+        let simple = choose_model(&settings, &claude, &chat, Some("opus"), 0).expect("simple");
+        assert_eq!(simple.model, "haiku");
+        assert_eq!(simple.from.as_deref(), Some("opus"));
+        assert_eq!(simple.reason, DowngradeReason::SimpleThread);
+        assert_eq!(
+            choose_model(&settings, &claude, &chat, Some("haiku"), 0),
+            None
+        );
+
+        let untagged = Vec::new();
+        assert_eq!(choose_model(&settings, &claude, &untagged, None, 500), None);
+        settings.daily_turn_budget = Some(100);
+        assert_eq!(choose_model(&settings, &claude, &untagged, None, 79), None);
+        let near = choose_model(&settings, &claude, &untagged, None, 80).expect("near budget");
+        assert_eq!(near.reason, DowngradeReason::NearBudget);
+        assert_eq!(near.detail, "80 of 100 daily turns used.");
+        assert_eq!(
+            choose_model(&settings, &ProviderKind::Codex, &chat, None, 80),
+            None
+        );
+
+        let thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": "thread-1",
+            "cwd": "/repo",
+            "preview": "",
+            "createdAt": 0,
+            "updatedAt": 0,
+            "turns": [
+                { "id": "old", "startedAt": 0, "completedAt": 1, "items": [] },
+                { "id": "new", "startedAt": BUDGET_WINDOW_MS, "completedAt": null, "items": [] },
+            ],
+        }))
+        .expect("thread");
+        let threads = HashMap::from([("ws-1".to_string(), vec![thread])]);
+        assert_eq!(turns_in_budget_window(&threads, BUDGET_WINDOW_MS + 5), 1);
+    }
+}
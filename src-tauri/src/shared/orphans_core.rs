@@ -0,0 +1,280 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+
+use crate::shared::claude_core::{
+    archived_thread_id_counts, forget_archived_workspaces, persist_threads_store,
+    ClaudeThreadsStore,
+};
+use crate::shared::drafts_core::{remove_workspace_drafts, DraftsStore};
+use crate::shared::redaction_core::RAW_LOGS_DIR_NAME;
+use crate::shared::retention_core::path_size;
+use crate::shared::thread_worktree_core::THREAD_WORKTREES_DIR_NAME;
+use crate::shared::trash_core::{purge_workspace_trash, trashed_thread_counts};
+use crate::shared::turn_artifacts_core::ARTIFACTS_DIR_NAME;
+use crate::types::WorkspaceEntry;
+
+/// Data left behind by a workspace that is no longer in the workspace list.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub(crate) struct OrphanedWorkspace {
+    #[serde(rename = "workspaceId")]
+    pub(crate) workspace_id: String,
+    pub(crate) threads: usize,
+    #[serde(rename = "archivedIds")]
+    pub(crate) archived_ids: usize,
+    #[serde(rename = "trashedThreads")]
+    pub(crate) trashed_threads: usize,
+    pub(crate) drafts: usize,
+    /// Raw logs, artifacts and thread worktrees kept for the workspace in the data dir.
+    pub(crate) paths: Vec<String>,
+    /// Size of `paths` on disk.
+    pub(crate) bytes: u64,
+}
+
+/// What each store holds, keyed by workspace id.
+#[derive(Default)]
+struct WorkspaceData {
+    threads: HashMap<String, usize>,
+    archived_ids: HashMap<String, usize>,
+    trashed_threads: HashMap<String, usize>,
+    drafts: HashMap<String, usize>,
+    paths: HashMap<String, Vec<PathBuf>>,
+}
+
+type SetCount = fn(&mut OrphanedWorkspace, usize);
+
+/// Per-workspace files in the data dir, keyed by workspace id.
+fn workspace_paths(data_dir: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut paths: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for dir_name in [
+        RAW_LOGS_DIR_NAME,
+        ARTIFACTS_DIR_NAME,
+        THREAD_WORKTREES_DIR_NAME,
+    ] {
+        let Ok(entries) = fs::read_dir(data_dir.join(dir_name)) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            // Raw logs are `<workspace id>.log` files, the others one folder per workspace.
+            let workspace_id = if dir_name == RAW_LOGS_DIR_NAME {
+                path.extension()
+                    .filter(|extension| *extension == "log")
+                    .and(path.file_stem())
+            } else {
+                path.file_name()
+            };
+            if let Some(workspace_id) = workspace_id.and_then(|id| id.to_str()) {
+                paths
+                    .entry(workspace_id.to_string())
+                    .or_default()
+                    .push(path);
+            }
+        }
+    }
+    paths
+}
+
+fn orphan_entry<'a>(
+    orphans: &'a mut BTreeMap<String, OrphanedWorkspace>,
+    known: &HashSet<String>,
+    workspace_id: String,
+) -> Option<&'a mut OrphanedWorkspace> {
+    if known.contains(&workspace_id) {
+        return None;
+    }
+    Some(
+        orphans
+            .entry(workspace_id.clone())
+            .or_insert_with(|| OrphanedWorkspace {
+                workspace_id,
+                ..Default::default()
+            }),
+    )
+}
+
+/// The workspaces in `data` that are not in `known`, sorted by id.
+fn find_orphans(known: &HashSet<String>, data: WorkspaceData) -> Vec<OrphanedWorkspace> {
+    let mut orphans = BTreeMap::new();
+    let counts: [(_, SetCount); 4] = [
+        (data.threads, |orphan, count| orphan.threads = count),
+        (data.archived_ids, |orphan, count| {
+            orphan.archived_ids = count
+        }),
+        (data.trashed_threads, |orphan, count| {
+            orphan.trashed_threads = count
+        }),
+        (data.drafts, |orphan, count| orphan.drafts = count),
+    ];
+    for (counts, set) in counts {
+        for (workspace_id, count) in counts.into_iter().filter(|(_, count)| *count > 0) {
+            if let Some(orphan) = orphan_entry(&mut orphans, known, workspace_id) {
+                set(orphan, count);
+            }
+        }
+    }
+    for (workspace_id, mut paths) in data.paths {
+        let Some(orphan) = orphan_entry(&mut orphans, known, workspace_id) else {
+            continue;
+        };
+        paths.sort();
+        orphan.bytes = paths.iter().map(|path| path_size(path)).sum();
+        orphan.paths = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+    }
+    orphans.into_values().collect()
+}
+
+async fn list_orphans(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    drafts: &DraftsStore,
+) -> Result<Vec<OrphanedWorkspace>, String> {
+    let known = workspaces
+        .lock()
+        .await
+        .keys()
+        .cloned()
+        .collect::<HashSet<_>>();
+    let threads = claude_threads
+        .lock()
+        .await
+        .iter()
+        .map(|(workspace_id, threads)| (workspace_id.clone(), threads.len()))
+        .collect();
+    let drafts = drafts
+        .lock()
+        .await
+        .iter()
+        .map(|(workspace_id, drafts)| (workspace_id.clone(), drafts.len()))
+        .collect();
+    let claude_threads_path = claude_threads_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let data = WorkspaceData {
+            threads,
+            archived_ids: archived_thread_id_counts(&claude_threads_path),
+            trashed_threads: trashed_thread_counts(&claude_threads_path)?,
+            drafts,
+            paths: claude_threads_path
+                .parent()
+                .map(workspace_paths)
+                .unwrap_or_default(),
+        };
+        Ok(find_orphans(&known, data))
+    })
+    .await
+    .map_err(|error| format!("failed to scan for orphaned data: {error}"))?
+}
+
+/// Thread data whose workspace was removed: threads, archived ids, trashed threads, drafts,
+/// raw logs, artifacts and thread worktrees.
+pub(crate) async fn list_orphans_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    drafts: &DraftsStore,
+) -> Result<Value, String> {
+    let orphans = list_orphans(workspaces, claude_threads, claude_threads_path, drafts).await?;
+    Ok(json!({ "result": { "orphans": orphans } }))
+}
+
+/// Deletes the data of the given orphaned workspaces. Ids of workspaces that still exist are
+/// ignored, so a stale list can't remove live threads.
+pub(crate) async fn purge_orphans_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    drafts: &DraftsStore,
+    drafts_path: &Path,
+    workspace_ids: Vec<String>,
+) -> Result<Value, String> {
+    let requested = workspace_ids.into_iter().collect::<HashSet<_>>();
+    let purged = list_orphans(workspaces, claude_threads, claude_threads_path, drafts)
+        .await?
+        .into_iter()
+        .filter(|orphan| requested.contains(&orphan.workspace_id))
+        .collect::<Vec<_>>();
+    if purged.is_empty() {
+        return Ok(json!({ "result": { "purged": purged } }));
+    }
+    let ids = purged
+        .iter()
+        .map(|orphan| orphan.workspace_id.clone())
+        .collect::<HashSet<_>>();
+
+    let removed_threads = {
+        let mut store = claude_threads.lock().await;
+        let before = store.len();
+        store.retain(|workspace_id, _| !ids.contains(workspace_id));
+        store.len() != before
+    };
+    if removed_threads {
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+    }
+    remove_workspace_drafts(drafts, drafts_path, &ids).await?;
+    forget_archived_workspaces(claude_threads_path, &ids)?;
+    purge_workspace_trash(claude_threads_path, &ids)?;
+    for path in purged.iter().flat_map(|orphan| &orphan.paths) {
+        let path = Path::new(path);
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        removed.map_err(|error| format!("Failed to remove {}: {error}", path.display()))?;
+    }
+    Ok(json!({ "result": { "purged": purged } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_orphans, workspace_paths, WorkspaceData};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn reports_data_of_unknown_workspaces_only() {
+        let data_dir =
+            std::env::temp_dir().join(format!("polycode-orphans-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(data_dir.join("raw_logs")).expect("create raw logs");
+        fs::create_dir_all(data_dir.join("artifacts/ws-gone/thread-1")).expect("create artifacts");
+        fs::write(data_dir.join("raw_logs/ws-gone.log"), "sealed").expect("write log");
+        fs::write(data_dir.join("raw_logs/ws-live.log"), "sealed").expect("write log");
+        fs::write(data_dir.join("raw_logs/notes.txt"), "").expect("write other");
+
+        let counts = |entries: &[(&str, usize)]| {
+            entries
+                .iter()
+                .map(|(id, count)| (id.to_string(), *count))
+                .collect::<HashMap<_, _>>()
+        };
+        let data = WorkspaceData {
+            threads: counts(&[("ws-live", 2), ("ws-gone", 1), ("ws-empty", 0)]),
+            archived_ids: counts(&[("ws-archived", 4)]),
+            trashed_threads: counts(&[("ws-gone", 3)]),
+            drafts: counts(&[("ws-live", 1)]),
+            paths: workspace_paths(&data_dir),
+        };
+        let known = HashSet::from(["ws-live".to_string()]);
+        let orphans = find_orphans(&known, data);
+
+        let ids = orphans
+            .iter()
+            .map(|orphan| orphan.workspace_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["ws-archived", "ws-gone"]);
+        assert_eq!(orphans[0].archived_ids, 4);
+        let gone = &orphans[1];
+        assert_eq!((gone.threads, gone.trashed_threads, gone.drafts), (1, 3, 0));
+        assert_eq!(gone.paths.len(), 2);
+        assert_eq!(gone.bytes, 6);
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,84 @@
+/// Caps how much assistant output a turn streams, so an agent stuck in a loop can't flood the
+/// UI. The full output is still collected for the raw log.
+pub(crate) struct TurnOutputCap {
+    /// `0` means no cap.
+    limit: usize,
+    streamed: usize,
+    truncated: bool,
+}
+
+impl TurnOutputCap {
+    pub(crate) fn new(limit_bytes: u64) -> Self {
+        Self {
+            limit: usize::try_from(limit_bytes).unwrap_or(usize::MAX),
+            streamed: 0,
+            truncated: false,
+        }
+    }
+
+    /// The part of `delta` that still fits, and whether this delta is the one that hit the cap.
+    pub(crate) fn admit<'a>(&mut self, delta: &'a str) -> (&'a str, bool) {
+        if self.limit == 0 {
+            return (delta, false);
+        }
+        if self.truncated {
+            return ("", false);
+        }
+        let room = self.limit - self.streamed;
+        if delta.len() <= room {
+            self.streamed += delta.len();
+            return (delta, false);
+        }
+        let cut = char_floor(delta, room);
+        self.streamed += cut;
+        self.truncated = true;
+        (&delta[..cut], true)
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// `text` cut to the cap with a marker, or `None` when it fits.
+    pub(crate) fn truncate(&self, text: &str) -> Option<String> {
+        if self.limit == 0 || text.len() <= self.limit {
+            return None;
+        }
+        let kept = &text[..char_floor(text, self.limit)];
+        Some(format!(
+            "{kept}\n\n[Output truncated after {} bytes; the full output is in the raw log.]",
+            self.limit
+        ))
+    }
+}
+
+/// The largest char boundary in `text` at or below `index`.
+fn char_floor(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TurnOutputCap;
+
+    #[test]
+    fn streams_up_to_the_cap_and_marks_the_stored_text() {
+        let mut cap = TurnOutputCap::new(8);
+        assert_eq!(cap.admit("abc"), ("abc", false));
+        // `é` is two bytes and would straddle the cap, so the cut falls before it.
+        assert_eq!(cap.admit("defgé!"), ("defg", true));
+        assert_eq!(cap.admit("more"), ("", false));
+
+        assert_eq!(cap.truncate("short"), None);
+        let stored = cap.truncate("abcdefgé and the rest").expect("truncated");
+        assert!(stored.starts_with("abcdefg\n\n[Output truncated after 8 bytes"));
+
+        let mut uncapped = TurnOutputCap::new(0);
+        assert!(!uncapped.admit(&"x".repeat(64)).1);
+        assert_eq!(uncapped.truncate(&"x".repeat(64)), None);
+    }
+}
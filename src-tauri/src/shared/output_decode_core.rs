@@ -0,0 +1,80 @@
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Line reader for CLI output that never fails on encoding. Tokio's `Lines` rejects a line
+/// that is not valid UTF-8 with an error, which used to end the read loop and fail the turn;
+/// here the whole line is read as bytes and decoded with replacement characters instead.
+pub(crate) struct LossyLines<R> {
+    reader: R,
+    /// Bytes of the line being read, kept across calls so `next_line` stays cancel safe.
+    pending: Vec<u8>,
+}
+
+pub(crate) fn lossy_lines<R: AsyncBufRead + Unpin>(reader: R) -> LossyLines<R> {
+    LossyLines {
+        reader,
+        pending: Vec::new(),
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> LossyLines<R> {
+    /// The next line without its `\n` or `\r\n`, or `None` at the end of the stream. Only I/O
+    /// errors are returned. Like `Lines::next_line` it can be raced against a timeout: a line
+    /// cut short by cancellation is finished by the next call.
+    pub(crate) async fn next_line(&mut self) -> io::Result<Option<String>> {
+        let read = self.reader.read_until(b'\n', &mut self.pending).await?;
+        if read == 0 && self.pending.is_empty() {
+            return Ok(None);
+        }
+        let mut line = self.pending.as_slice();
+        if let Some(rest) = line.strip_suffix(b"\n") {
+            line = rest.strip_suffix(b"\r").unwrap_or(rest);
+        }
+        let decoded = String::from_utf8_lossy(line).into_owned();
+        self.pending.clear();
+        Ok(Some(decoded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lossy_lines;
+    use serde_json::Value;
+    use tokio::io::BufReader;
+
+    const FIXTURE: &[u8] = include_bytes!("fixtures/invalid_utf8_output.bin");
+
+    #[test]
+    fn decodes_invalid_utf8_lines_with_replacement_characters() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        // A tiny buffer splits multi-byte characters across reads.
+        for capacity in [3, 8 * 1024] {
+            let lines = runtime.block_on(async {
+                let mut reader = lossy_lines(BufReader::with_capacity(capacity, FIXTURE));
+                let mut lines = Vec::new();
+                while let Some(line) = reader.next_line().await.expect("no read error") {
+                    lines.push(line);
+                }
+                lines
+            });
+            assert_eq!(lines.len(), 6, "capacity {capacity}");
+            let text = |line: &str| {
+                serde_json::from_str::<Value>(line).expect("json")["event"]["delta"]["text"]
+                    .as_str()
+                    .map(str::to_string)
+            };
+            assert_eq!(text(&lines[0]).as_deref(), Some("café ok"));
+            assert_eq!(
+                text(&lines[1]).as_deref(),
+                Some("bad \u{fffd}\u{fffd} bytes")
+            );
+            assert_eq!(lines[2], "\u{0}\u{1}\u{fffd}PNG");
+            assert_eq!(lines[3], "\u{1a}");
+            assert_eq!(lines[4], "\u{fffd} cut short");
+            assert_eq!(lines[5], "last line without newline \u{fffd}");
+        }
+    }
+}
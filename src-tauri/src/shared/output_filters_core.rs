@@ -0,0 +1,191 @@
+use regex::Regex;
+use serde_json::Value;
+
+use crate::types::{OutputFilterKind, OutputFilterRule, ProviderKind};
+
+#[derive(Debug, Clone)]
+struct JsonPathMatcher {
+    segments: Vec<String>,
+    expected: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum CompiledFilter {
+    Regex(Regex),
+    JsonPath(JsonPathMatcher),
+}
+
+/// Output matching any enabled rule is dropped before it reaches the thread. Rules see one whole
+/// output line: a stream-json record that carries text, or a plain line. They never see a
+/// fragment of a streamed answer. Only the Claude adapter runs the pipeline.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OutputFilterPipeline {
+    filters: Vec<CompiledFilter>,
+}
+
+/// Parses `$.a.b` or `$.a.b=value`; a leading `$.` is optional.
+fn parse_json_path(pattern: &str) -> Result<JsonPathMatcher, String> {
+    let trimmed = pattern.trim();
+    let (path, expected) = match trimmed.split_once('=') {
+        Some((path, expected)) => (path.trim(), Some(expected.trim().to_string())),
+        None => (trimmed, None),
+    };
+    let path = path
+        .strip_prefix("$.")
+        .or_else(|| path.strip_prefix('$'))
+        .unwrap_or(path);
+    let segments = path
+        .split('.')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        return Err(format!("Invalid JSON path filter `{pattern}`"));
+    }
+    Ok(JsonPathMatcher { segments, expected })
+}
+
+fn compile_rule(rule: &OutputFilterRule) -> Result<CompiledFilter, String> {
+    match rule.kind {
+        OutputFilterKind::Regex => Regex::new(&rule.pattern)
+            .map(CompiledFilter::Regex)
+            .map_err(|error| format!("Invalid output filter `{}`: {error}", rule.id)),
+        OutputFilterKind::JsonPath => parse_json_path(&rule.pattern).map(CompiledFilter::JsonPath),
+    }
+}
+
+fn lookup_json_path<'a>(value: &'a Value, segments: &[String]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            Value::Object(map) => map.get(segment),
+            _ => None,
+        })
+}
+
+impl JsonPathMatcher {
+    fn matches(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+            return false;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(trimmed) else {
+            return false;
+        };
+        let Some(found) = lookup_json_path(&value, &self.segments) else {
+            return false;
+        };
+        match &self.expected {
+            None => true,
+            Some(expected) => match found {
+                Value::String(text) => text == expected,
+                other => serde_json::from_str::<Value>(expected).ok().as_ref() == Some(other),
+            },
+        }
+    }
+}
+
+impl OutputFilterPipeline {
+    /// Compiles enabled rules for a provider, skipping rules that fail to compile.
+    pub(crate) fn compile(rules: &[OutputFilterRule], provider: &ProviderKind) -> Self {
+        let filters = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter(|rule| !matches!(&rule.provider, Some(value) if value != provider))
+            .filter_map(|rule| compile_rule(rule).ok())
+            .collect();
+        Self { filters }
+    }
+
+    pub(crate) fn should_drop(&self, line: &str) -> bool {
+        self.filters.iter().any(|filter| match filter {
+            CompiledFilter::Regex(regex) => regex.is_match(line),
+            CompiledFilter::JsonPath(matcher) => matcher.matches(line),
+        })
+    }
+}
+
+pub(crate) fn validate_output_filters(rules: &[OutputFilterRule]) -> Result<(), String> {
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err("Output filter id is required.".to_string());
+        }
+        if matches!(&rule.provider, Some(provider) if *provider != ProviderKind::Claude) {
+            return Err(format!(
+                "Output filter `{}` can only target Claude; other providers' output is not filtered.",
+                rule.id
+            ));
+        }
+        compile_rule(rule)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_output_filters, OutputFilterPipeline};
+    use crate::types::{OutputFilterKind, OutputFilterRule, ProviderKind};
+
+    fn rule(kind: OutputFilterKind, pattern: &str) -> OutputFilterRule {
+        OutputFilterRule {
+            id: "r1".to_string(),
+            kind,
+            pattern: pattern.to_string(),
+            enabled: true,
+            provider: None,
+        }
+    }
+
+    #[test]
+    fn regex_rules_drop_matching_lines() {
+        let pipeline = OutputFilterPipeline::compile(
+            &[rule(OutputFilterKind::Regex, r"^[⠋⠙⠹⠸] ")],
+            &ProviderKind::Claude,
+        );
+        assert!(pipeline.should_drop("⠙ Thinking"));
+        assert!(!pipeline.should_drop("Done."));
+    }
+
+    #[test]
+    fn json_path_rules_match_existence_and_value() {
+        let pipeline = OutputFilterPipeline::compile(
+            &[
+                rule(OutputFilterKind::JsonPath, "$.telemetry"),
+                rule(OutputFilterKind::JsonPath, "$.event.kind=banner"),
+            ],
+            &ProviderKind::Claude,
+        );
+        assert!(pipeline.should_drop(r#"{"telemetry":{"id":1}}"#));
+        assert!(pipeline.should_drop(r#"{"event":{"kind":"banner"}}"#));
+        assert!(!pipeline.should_drop(r#"{"event":{"kind":"text"}}"#));
+        assert!(!pipeline.should_drop("plain text"));
+    }
+
+    #[test]
+    fn provider_scoped_rules_are_skipped_for_other_providers() {
+        let mut scoped = rule(OutputFilterKind::Regex, "noise");
+        scoped.provider = Some(ProviderKind::Gemini);
+        let pipeline = OutputFilterPipeline::compile(&[scoped], &ProviderKind::Claude);
+        assert!(!pipeline.should_drop("noise"));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_regex() {
+        assert!(validate_output_filters(&[rule(OutputFilterKind::Regex, "(")]).is_err());
+        assert!(validate_output_filters(&[rule(OutputFilterKind::JsonPath, "$.")]).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_rules_for_providers_without_filtering() {
+        let mut scoped = rule(OutputFilterKind::Regex, "noise");
+        scoped.provider = Some(ProviderKind::Codex);
+        assert!(validate_output_filters(std::slice::from_ref(&scoped)).is_err());
+        scoped.provider = Some(ProviderKind::Claude);
+        assert!(validate_output_filters(&[scoped]).is_ok());
+    }
+}
@@ -0,0 +1,91 @@
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+/// 8-bit forms of CSI and of the string introducers (DCS, SOS, OSC, PM, APC).
+const C1_CSI: char = '\u{9b}';
+const C1_STRING_INTRODUCERS: &[char] = &['\u{90}', '\u{98}', '\u{9d}', '\u{9e}', '\u{9f}'];
+const C1_ST: char = '\u{9c}';
+
+/// Skips a CSI sequence's parameter and intermediate bytes up to and including its final byte.
+fn skip_csi(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    for ch in chars.by_ref() {
+        if ('@'..='~').contains(&ch) {
+            break;
+        }
+    }
+}
+
+/// Skips a control string (OSC, DCS, SOS, PM, APC) up to BEL or the string terminator.
+/// An unterminated string swallows the rest of the text rather than leaking its payload.
+fn skip_control_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while let Some(ch) = chars.next() {
+        match ch {
+            BEL | C1_ST => break,
+            ESC if chars.peek() == Some(&'\\') => {
+                chars.next();
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Makes agent output safe to show and store: removes every terminal escape sequence (CSI,
+/// OSC such as title changes and hyperlinks, DCS/SOS/PM/APC, and two-byte escapes), drops C0
+/// and C1 control characters other than tab and newline, and normalizes CRLF and lone CR to LF.
+/// A CR at the very end is dropped so a CRLF split across stream chunks yields one newline.
+pub(crate) fn sanitize_output(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ESC => match chars.next() {
+                Some('[') => skip_csi(&mut chars),
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_control_string(&mut chars),
+                // Intermediate bytes (e.g. `ESC ( B`) are followed by one final byte.
+                Some(' '..='/') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            C1_CSI => skip_csi(&mut chars),
+            ch if C1_STRING_INTRODUCERS.contains(&ch) => skip_control_string(&mut chars),
+            '\r' => match chars.peek() {
+                Some('\n') | None => {}
+                Some(_) => output.push('\n'),
+            },
+            '\n' | '\t' => output.push(ch),
+            ch if ch.is_control() => {}
+            ch => output.push(ch),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_output;
+
+    #[test]
+    fn strips_escape_injection_from_model_output() {
+        // Colors, a window title change, a disguised hyperlink and a screen clear.
+        let text = "\u{1b}[31mred\u{1b}[0m \u{1b}]0;pwned\u{7}title \
+                    \u{1b}]8;;https://evil.example\u{1b}\\link\u{1b}]8;;\u{1b}\\ \u{1b}c\u{1b}[2Jdone";
+        assert_eq!(sanitize_output(text), "red title link done");
+        // 8-bit CSI/OSC, a DCS payload, charset switches and stray control bytes.
+        let text = "a\u{9b}1;31mb\u{9d}2;x\u{9c}c\u{1b}P+q\u{1b}\\d\u{1b}(Be\u{8}\u{7f}\u{0}f";
+        assert_eq!(sanitize_output(text), "abcdef");
+        // An unterminated OSC must not leak its payload.
+        assert_eq!(sanitize_output("ok\u{1b}]52;c;c2VjcmV0"), "ok");
+    }
+
+    #[test]
+    fn normalizes_line_endings() {
+        assert_eq!(
+            sanitize_output("one\r\ntwo\rthree\tend"),
+            "one\ntwo\nthree\tend"
+        );
+        let split = format!("{}{}", sanitize_output("line\r"), sanitize_output("\nnext"));
+        assert_eq!(split, "line\nnext");
+        assert_eq!(sanitize_output("naïve 日本語 ✓"), "naïve 日本語 ✓");
+    }
+}
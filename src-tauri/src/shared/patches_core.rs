@@ -0,0 +1,398 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::shared::claude_core::ClaudeThreadsStore;
+use crate::shared::git_core::run_git_command;
+use crate::shared::turn_snapshot_core::keep_snapshot;
+use crate::types::WorkspaceEntry;
+
+const DIFF_LANGUAGES: &[&str] = &["diff", "patch", "udiff"];
+const DIFF_LINE_PREFIXES: &[&str] = &[
+    "diff --git ",
+    "index ",
+    "--- ",
+    "+++ ",
+    "@@",
+    "new file mode",
+    "deleted file mode",
+    "old mode",
+    "new mode",
+    "similarity index",
+    "rename from",
+    "rename to",
+    "\\ No newline",
+];
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PatchKind {
+    /// A unified diff, applied with `git apply`.
+    Diff,
+    /// A code block labelled with a file path, written as the file's full contents.
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub(crate) struct ExtractedPatch {
+    pub(crate) id: String,
+    pub(crate) kind: PatchKind,
+    pub(crate) files: Vec<String>,
+    pub(crate) content: String,
+}
+
+struct Fence<'a> {
+    info: &'a str,
+    header: Option<&'a str>,
+    body: String,
+}
+
+fn diff_files(diff: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    for line in diff.lines() {
+        let Some(path) = line.strip_prefix("+++ ") else {
+            continue;
+        };
+        let path = path.split('\t').next().unwrap_or(path).trim();
+        if path == "/dev/null" {
+            continue;
+        }
+        let path = path.strip_prefix("b/").unwrap_or(path).to_string();
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn looks_like_diff(body: &str) -> bool {
+    let has_old = body.lines().any(|line| line.starts_with("--- "));
+    let has_new = body.lines().any(|line| line.starts_with("+++ "));
+    let has_hunk = body.lines().any(|line| line.starts_with("@@"));
+    has_old && has_new && has_hunk
+}
+
+fn is_diff_line(line: &str) -> bool {
+    line.starts_with([' ', '+', '-'])
+        || line.is_empty()
+        || DIFF_LINE_PREFIXES
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+}
+
+/// Accepts labels such as `src/main.rs`, **src/main.rs**, `File: src/main.rs` or `src/main.rs:`.
+fn file_label(line: &str) -> Option<&str> {
+    let mut label = line.trim();
+    for prefix in ["File:", "file:", "Path:", "path:"] {
+        if let Some(rest) = label.strip_prefix(prefix) {
+            label = rest.trim();
+        }
+    }
+    let label = label
+        .trim_matches(|ch| matches!(ch, '`' | '*' | '#' | ':'))
+        .trim()
+        .trim_matches('`');
+    let plausible = !label.is_empty()
+        && !label.contains(char::is_whitespace)
+        && Path::new(label).extension().is_some()
+        && !label.starts_with("http");
+    plausible.then_some(label)
+}
+
+fn parse_fences(text: &str) -> (Vec<Fence<'_>>, Vec<&str>) {
+    let mut fences = Vec::new();
+    let mut outside = Vec::new();
+    let mut previous: Option<&str> = None;
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with("```") {
+            outside.push(line);
+            if !line.trim().is_empty() {
+                previous = Some(line);
+            }
+            continue;
+        }
+        let ticks = trimmed.chars().take_while(|ch| *ch == '`').count();
+        let fence = &trimmed[..ticks];
+        let info = trimmed[ticks..].trim();
+        let mut body = Vec::new();
+        for inner in lines.by_ref() {
+            if inner.trim() == fence {
+                break;
+            }
+            body.push(inner);
+        }
+        fences.push(Fence {
+            info,
+            header: previous.take(),
+            body: body.join("\n"),
+        });
+        outside.push("");
+    }
+    (fences, outside)
+}
+
+/// Diffs written straight into the message, outside any code fence.
+fn bare_diffs(lines: &[&str]) -> Vec<String> {
+    let mut diffs = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let starts_diff = line.starts_with("diff --git ")
+            || (line.starts_with("--- ")
+                && lines
+                    .get(index + 1)
+                    .is_some_and(|next| next.starts_with("+++ ")));
+        if !starts_diff {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < lines.len() && is_diff_line(lines[index]) {
+            index += 1;
+        }
+        let diff = lines[start..index].join("\n");
+        if looks_like_diff(&diff) {
+            diffs.push(diff);
+        }
+        index = index.max(start + 1);
+    }
+    diffs
+}
+
+/// Finds unified diffs and file-labelled code blocks in assistant output, in message order.
+pub(crate) fn extract_patches(text: &str) -> Vec<ExtractedPatch> {
+    let (fences, outside) = parse_fences(text);
+    let mut patches = Vec::new();
+    let mut push = |kind: PatchKind, files: Vec<String>, content: String| {
+        let id = format!("patch-{}", patches.len() + 1);
+        patches.push(ExtractedPatch {
+            id,
+            kind,
+            files,
+            content,
+        });
+    };
+    for fence in fences {
+        let mut info = fence.info.split_whitespace();
+        let language = info.next().unwrap_or_default();
+        let (language, info_path) = match language.split_once(':') {
+            Some((language, path)) => (language, Some(path)),
+            None => (language, None),
+        };
+        if DIFF_LANGUAGES.contains(&language) || looks_like_diff(&fence.body) {
+            if looks_like_diff(&fence.body) {
+                let mut content = fence.body.clone();
+                content.push('\n');
+                push(PatchKind::Diff, diff_files(&content), content);
+            }
+            continue;
+        }
+        let path = info_path
+            .or_else(|| info.next())
+            .and_then(file_label)
+            .or_else(|| file_label(language).filter(|label| label.contains('/')))
+            .or_else(|| fence.header.and_then(file_label));
+        if let Some(path) = path {
+            let mut content = fence.body.clone();
+            content.push('\n');
+            push(PatchKind::File, vec![path.to_string()], content);
+        }
+    }
+    for diff in bare_diffs(&outside) {
+        let mut content = diff;
+        content.push('\n');
+        push(PatchKind::Diff, diff_files(&content), content);
+    }
+    patches
+}
+
+/// Resolves a patch path inside the workspace, refusing absolute paths and `..` segments.
+fn workspace_file_path(workspace_path: &str, relative: &str) -> Result<PathBuf, String> {
+    let candidate = Path::new(relative);
+    let escapes = candidate.components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if escapes {
+        return Err(format!("Patch path `{relative}` is outside the workspace."));
+    }
+    Ok(Path::new(workspace_path).join(candidate))
+}
+
+async fn turn_assistant_text(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<String, String> {
+    let store = claude_threads.lock().await;
+    let thread = store
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .ok_or_else(|| "thread not found".to_string())?;
+    let turn = thread
+        .turns
+        .iter()
+        .find(|turn| turn.id == turn_id)
+        .ok_or_else(|| "turn not found".to_string())?;
+    Ok(turn
+        .items
+        .iter()
+        .filter(|item| item.role == "assistant")
+        .map(|item| item.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+pub(crate) async fn turn_patches_core(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+) -> Result<Value, String> {
+    let text = turn_assistant_text(claude_threads, &workspace_id, &thread_id, &turn_id).await?;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "patches": extract_patches(&text),
+        }
+    }))
+}
+
+/// Records the working tree as a stash-shaped commit kept under a private snapshot ref, so a
+/// bad patch can be undone with `git stash apply <commit>` without touching the user's stash
+/// list. Returns `None` when there is nothing to snapshot.
+async fn snapshot_working_tree(
+    repo_path: &PathBuf,
+    turn_id: &str,
+    patch_id: &str,
+) -> Result<Option<String>, String> {
+    let commit = run_git_command(repo_path, &["stash", "create"]).await?;
+    if commit.is_empty() {
+        return Ok(None);
+    }
+    keep_snapshot(repo_path, &format!("{turn_id}-{patch_id}"), &commit).await?;
+    Ok(Some(commit))
+}
+
+async fn apply_diff(repo_path: &PathBuf, content: &str) -> Result<(), String> {
+    let patch_path = std::env::temp_dir().join(format!("polycode-patch-{}.diff", Uuid::new_v4()));
+    std::fs::write(&patch_path, content)
+        .map_err(|error| format!("Failed to write patch: {error}"))?;
+    let patch_arg = patch_path.to_string_lossy().to_string();
+    let result = run_git_command(
+        repo_path,
+        &["apply", "--recount", "--whitespace=nowarn", &patch_arg],
+    )
+    .await;
+    let _ = std::fs::remove_file(&patch_path);
+    result.map(|_| ())
+}
+
+/// Applies one extracted patch to the workspace after snapshotting uncommitted changes.
+pub(crate) async fn apply_patch_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    patch_id: String,
+) -> Result<Value, String> {
+    let workspace_path = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .map(|entry| entry.path.clone())
+            .ok_or_else(|| "workspace not found".to_string())?
+    };
+    let text = turn_assistant_text(claude_threads, &workspace_id, &thread_id, &turn_id).await?;
+    let patch = extract_patches(&text)
+        .into_iter()
+        .find(|patch| patch.id == patch_id)
+        .ok_or_else(|| format!("patch `{patch_id}` not found"))?;
+
+    let repo_path = PathBuf::from(&workspace_path);
+    let snapshot = snapshot_working_tree(&repo_path, &turn_id, &patch_id).await?;
+    match patch.kind {
+        PatchKind::Diff => apply_diff(&repo_path, &patch.content).await?,
+        PatchKind::File => {
+            for file in &patch.files {
+                let target = workspace_file_path(&workspace_path, file)?;
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                std::fs::write(&target, &patch.content)
+                    .map_err(|error| format!("Failed to write {file}: {error}"))?;
+            }
+        }
+    }
+    Ok(json!({
+        "result": {
+            "patchId": patch.id,
+            "files": patch.files,
+            "snapshot": snapshot,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_patches, workspace_file_path, PatchKind};
+
+    #[test]
+    fn extract_patches_finds_fenced_and_bare_diffs() {
+        let text = [
+            "Here is the fix:",
+            "```diff",
+            "--- a/src/lib.rs",
+            "+++ b/src/lib.rs",
+            "@@ -1 +1 @@",
+            "-old",
+            "+new",
+            "```",
+            "And another:",
+            "diff --git a/README.md b/README.md",
+            "--- a/README.md",
+            "+++ b/README.md",
+            "@@ -1 +1 @@",
+            "-a",
+            "+b",
+            "That's all.",
+        ]
+        .join("\n");
+        let patches = extract_patches(&text);
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].kind, PatchKind::Diff);
+        assert_eq!(patches[0].files, vec!["src/lib.rs"]);
+        assert_eq!(patches[1].files, vec!["README.md"]);
+        assert!(!patches[1].content.contains("That's all."));
+    }
+
+    #[test]
+    fn extract_patches_reads_file_labelled_blocks() {
+        let text = "`src/util.ts`\n```ts\nexport const x = 1;\n```\n\n```rust\nfn main() {}\n```\n\n```rust:src/main.rs\nfn main() {}\n```";
+        let patches = extract_patches(text);
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].kind, PatchKind::File);
+        assert_eq!(patches[0].files, vec!["src/util.ts"]);
+        assert_eq!(patches[0].content, "export const x = 1;\n");
+        assert_eq!(patches[1].files, vec!["src/main.rs"]);
+        assert_eq!(patches[1].id, "patch-2");
+    }
+
+    #[test]
+    fn workspace_file_path_rejects_escapes() {
+        assert!(workspace_file_path("/repo", "../etc/passwd").is_err());
+        assert!(workspace_file_path("/repo", "/etc/passwd").is_err());
+        assert!(workspace_file_path("/repo", "src/lib.rs").is_ok());
+    }
+}
@@ -0,0 +1,446 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot, Mutex};
+use uuid::Uuid;
+
+use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use crate::shared::claude_core::{self, emit, now_ms, ClaudeThreadsStore, ClaudeTurnCancelsStore};
+use crate::shared::claude_pool_core::{ClaudeApprovalsStore, ClaudeProcessPool};
+use crate::shared::rate_limit_core::RateLimitsStore;
+use crate::shared::thread_locks_core::ThreadLocksStore;
+use crate::shared::turn_slots_core::TurnSlotsStore;
+use crate::types::{AppSettings, WorkspaceEntry};
+
+const PLAYBOOKS_FILE_NAME: &str = "playbooks.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PlaybookStep {
+    pub(crate) prompt: String,
+    /// Pause before this step until the user confirms it.
+    #[serde(default, rename = "requireConfirmation")]
+    pub(crate) require_confirmation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Playbook {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) steps: Vec<PlaybookStep>,
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub(crate) updated_at: i64,
+}
+
+pub(crate) struct PlaybookRunHandle {
+    workspace_id: String,
+    thread_id: String,
+    confirm: Option<oneshot::Sender<bool>>,
+    canceled: bool,
+}
+
+pub(crate) type PlaybookRunsStore = Arc<Mutex<HashMap<String, PlaybookRunHandle>>>;
+
+/// The stores a playbook run needs to send turns into a Claude thread.
+pub(crate) struct PlaybookTurnContext<'a> {
+    pub(crate) workspaces: &'a Mutex<HashMap<String, WorkspaceEntry>>,
+    pub(crate) app_settings: &'a Mutex<AppSettings>,
+    pub(crate) claude_threads: &'a ClaudeThreadsStore,
+    pub(crate) claude_turn_cancels: &'a ClaudeTurnCancelsStore,
+    pub(crate) claude_processes: &'a ClaudeProcessPool,
+    pub(crate) claude_approvals: &'a ClaudeApprovalsStore,
+    pub(crate) turn_slots: &'a TurnSlotsStore,
+    pub(crate) thread_locks: &'a ThreadLocksStore,
+    pub(crate) rate_limits: &'a RateLimitsStore,
+    pub(crate) claude_threads_path: &'a Path,
+    pub(crate) playbook_runs: &'a PlaybookRunsStore,
+}
+
+/// A registered run, returned by [`start_playbook_run_core`] and consumed by [`run_playbook_core`].
+pub(crate) struct PlaybookRun {
+    run_id: String,
+    workspace_id: String,
+    thread_id: String,
+    playbook: Playbook,
+}
+
+impl PlaybookRun {
+    pub(crate) fn summary(&self) -> Value {
+        json!({
+            "result": {
+                "runId": self.run_id,
+                "playbookId": self.playbook.id,
+                "threadId": self.thread_id,
+                "totalSteps": self.playbook.steps.len(),
+            }
+        })
+    }
+}
+
+pub(crate) fn playbooks_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(PLAYBOOKS_FILE_NAME)
+}
+
+fn read_playbooks(path: &Path) -> Result<Vec<Playbook>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| e.to_string())
+}
+
+fn write_playbooks(path: &Path, playbooks: &[Playbook]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(playbooks).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| e.to_string())
+}
+
+fn normalize_steps(steps: Vec<PlaybookStep>) -> Result<Vec<PlaybookStep>, String> {
+    let steps = steps
+        .into_iter()
+        .filter(|step| !step.prompt.trim().is_empty())
+        .collect::<Vec<_>>();
+    if steps.is_empty() {
+        return Err("A playbook needs at least one prompt.".to_string());
+    }
+    Ok(steps)
+}
+
+pub(crate) async fn list_playbooks_core(playbooks_path: &Path) -> Result<Value, String> {
+    let playbooks = read_playbooks(playbooks_path)?;
+    Ok(json!({ "result": { "playbooks": playbooks } }))
+}
+
+/// Creates a playbook, or replaces the name and steps of an existing one when `id` is set.
+pub(crate) async fn save_playbook_core(
+    playbooks_path: &Path,
+    id: Option<String>,
+    name: String,
+    steps: Vec<PlaybookStep>,
+) -> Result<Value, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Playbook name is required.".to_string());
+    }
+    let steps = normalize_steps(steps)?;
+    let mut playbooks = read_playbooks(playbooks_path)?;
+    let timestamp = now_ms();
+    let saved = match id.and_then(|id| playbooks.iter_mut().find(|playbook| playbook.id == id)) {
+        Some(existing) => {
+            existing.name = name;
+            existing.steps = steps;
+            existing.updated_at = timestamp;
+            existing.clone()
+        }
+        None => {
+            let playbook = Playbook {
+                id: Uuid::new_v4().to_string(),
+                name,
+                steps,
+                created_at: timestamp,
+                updated_at: timestamp,
+            };
+            playbooks.push(playbook.clone());
+            playbook
+        }
+    };
+    write_playbooks(playbooks_path, &playbooks)?;
+    Ok(json!({ "result": { "playbook": saved } }))
+}
+
+pub(crate) async fn delete_playbook_core(
+    playbooks_path: &Path,
+    id: String,
+) -> Result<Value, String> {
+    let mut playbooks = read_playbooks(playbooks_path)?;
+    let before = playbooks.len();
+    playbooks.retain(|playbook| playbook.id != id);
+    if playbooks.len() == before {
+        return Err("playbook not found".to_string());
+    }
+    write_playbooks(playbooks_path, &playbooks)?;
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+/// Registers a run so it can be confirmed or canceled; one run per thread at a time.
+pub(crate) async fn start_playbook_run_core(
+    playbooks_path: &Path,
+    playbook_runs: &PlaybookRunsStore,
+    workspace_id: String,
+    thread_id: String,
+    playbook_id: String,
+) -> Result<PlaybookRun, String> {
+    let playbook = read_playbooks(playbooks_path)?
+        .into_iter()
+        .find(|playbook| playbook.id == playbook_id)
+        .ok_or_else(|| "playbook not found".to_string())?;
+    let mut runs = playbook_runs.lock().await;
+    if runs
+        .values()
+        .any(|run| run.workspace_id == workspace_id && run.thread_id == thread_id)
+    {
+        return Err("A playbook is already running in this thread.".to_string());
+    }
+    let run_id = Uuid::new_v4().to_string();
+    runs.insert(
+        run_id.clone(),
+        PlaybookRunHandle {
+            workspace_id: workspace_id.clone(),
+            thread_id: thread_id.clone(),
+            confirm: None,
+            canceled: false,
+        },
+    );
+    Ok(PlaybookRun {
+        run_id,
+        workspace_id,
+        thread_id,
+        playbook,
+    })
+}
+
+/// Answers a step waiting on `requireConfirmation`; declining stops the run.
+pub(crate) async fn respond_playbook_step_core(
+    playbook_runs: &PlaybookRunsStore,
+    run_id: String,
+    approved: bool,
+) -> Result<Value, String> {
+    let confirm = {
+        let mut runs = playbook_runs.lock().await;
+        let run = runs
+            .get_mut(&run_id)
+            .ok_or_else(|| "playbook run not found".to_string())?;
+        run.confirm
+            .take()
+            .ok_or_else(|| "playbook run is not waiting for confirmation".to_string())?
+    };
+    let _ = confirm.send(approved);
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+/// Stops a run after interrupting the turn it is currently waiting on.
+pub(crate) async fn cancel_playbook_core(
+    playbook_runs: &PlaybookRunsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    run_id: String,
+) -> Result<Value, String> {
+    let (workspace_id, thread_id, confirm) = {
+        let mut runs = playbook_runs.lock().await;
+        let run = runs
+            .get_mut(&run_id)
+            .ok_or_else(|| "playbook run not found".to_string())?;
+        run.canceled = true;
+        (
+            run.workspace_id.clone(),
+            run.thread_id.clone(),
+            run.confirm.take(),
+        )
+    };
+    match confirm {
+        Some(confirm) => {
+            let _ = confirm.send(false);
+        }
+        None => {
+            claude_core::interrupt_thread_turns(claude_turn_cancels, &workspace_id, &thread_id)
+                .await;
+        }
+    }
+    Ok(json!({ "result": { "ok": true } }))
+}
+
+enum TurnOutcome {
+    Failed(String),
+    Completed,
+}
+
+/// Forwards every event unchanged while reporting when the watched thread's turn ends.
+#[derive(Clone)]
+struct TurnWatchSink<E: EventSink> {
+    inner: E,
+    thread_id: String,
+    outcomes: mpsc::UnboundedSender<TurnOutcome>,
+}
+
+impl<E: EventSink> EventSink for TurnWatchSink<E> {
+    fn emit_app_server_event(&self, event: AppServerEvent) {
+        let params = event.message.get("params");
+        let thread_id = params
+            .and_then(|params| params.get("threadId"))
+            .and_then(Value::as_str);
+        if thread_id == Some(self.thread_id.as_str()) {
+            match event.message.get("method").and_then(Value::as_str) {
+                Some("turn/completed") => {
+                    let _ = self.outcomes.send(TurnOutcome::Completed);
+                }
+                Some("error")
+                    if params
+                        .and_then(|params| params.get("willRetry"))
+                        .and_then(Value::as_bool)
+                        != Some(true) =>
+                {
+                    let message = params
+                        .and_then(|params| params.get("error"))
+                        .and_then(|error| error.get("message"))
+                        .and_then(Value::as_str)
+                        .unwrap_or("Turn failed.")
+                        .to_string();
+                    let _ = self.outcomes.send(TurnOutcome::Failed(message));
+                }
+                _ => {}
+            }
+        }
+        self.inner.emit_app_server_event(event);
+    }
+
+    fn emit_terminal_output(&self, event: TerminalOutput) {
+        self.inner.emit_terminal_output(event);
+    }
+
+    fn emit_terminal_exit(&self, event: TerminalExit) {
+        self.inner.emit_terminal_exit(event);
+    }
+}
+
+fn emit_progress<E: EventSink>(
+    event_sink: &E,
+    run: &PlaybookRun,
+    step_index: usize,
+    status: &str,
+    error: Option<&str>,
+) {
+    emit(
+        event_sink,
+        &run.workspace_id,
+        "playbook/progress",
+        json!({
+            "runId": run.run_id,
+            "playbookId": run.playbook.id,
+            "playbookName": run.playbook.name,
+            "threadId": run.thread_id,
+            "stepIndex": step_index,
+            "totalSteps": run.playbook.steps.len(),
+            "status": status,
+            "error": error,
+        }),
+    );
+}
+
+async fn is_canceled(playbook_runs: &PlaybookRunsStore, run_id: &str) -> bool {
+    !matches!(playbook_runs.lock().await.get(run_id), Some(run) if !run.canceled)
+}
+
+/// Sends each step as a turn and waits for it to finish before moving on. Progress is emitted
+/// as `playbook/progress` with status `awaitingConfirmation`, `running`, `completed`, `failed`
+/// or `canceled`.
+pub(crate) async fn run_playbook_core<E: EventSink>(
+    ctx: PlaybookTurnContext<'_>,
+    run: PlaybookRun,
+    event_sink: E,
+) {
+    let mut final_status = ("completed", None::<String>);
+    let mut last_index = 0;
+    for (index, step) in run.playbook.steps.iter().enumerate() {
+        last_index = index;
+        if is_canceled(ctx.playbook_runs, &run.run_id).await {
+            final_status = ("canceled", None);
+            break;
+        }
+        if step.require_confirmation {
+            let (confirm_tx, confirm_rx) = oneshot::channel();
+            if let Some(handle) = ctx.playbook_runs.lock().await.get_mut(&run.run_id) {
+                handle.confirm = Some(confirm_tx);
+            }
+            emit_progress(&event_sink, &run, index, "awaitingConfirmation", None);
+            if confirm_rx.await != Ok(true) {
+                final_status = ("canceled", None);
+                break;
+            }
+        }
+        emit_progress(&event_sink, &run, index, "running", None);
+        let (outcomes_tx, mut outcomes_rx) = mpsc::unbounded_channel();
+        let sink = TurnWatchSink {
+            inner: event_sink.clone(),
+            thread_id: run.thread_id.clone(),
+            outcomes: outcomes_tx,
+        };
+        if let Err(error) = claude_core::send_user_message_core(
+            ctx.workspaces,
+            ctx.app_settings,
+            ctx.claude_threads,
+            ctx.claude_turn_cancels,
+            ctx.claude_processes,
+            ctx.claude_approvals,
+            ctx.turn_slots,
+            ctx.thread_locks,
+            ctx.rate_limits,
+            ctx.claude_threads_path,
+            run.workspace_id.clone(),
+            run.thread_id.clone(),
+            step.prompt.clone(),
+            None,
+            false,
+            false,
+            sink,
+        )
+        .await
+        {
+            final_status = ("failed", Some(error));
+            break;
+        }
+        let mut failure = None;
+        loop {
+            match outcomes_rx.recv().await {
+                Some(TurnOutcome::Failed(message)) => failure = Some(message),
+                Some(TurnOutcome::Completed) => break,
+                None => {
+                    failure.get_or_insert_with(|| "Turn ended unexpectedly.".to_string());
+                    break;
+                }
+            }
+        }
+        if let Some(message) = failure {
+            final_status = ("failed", Some(message));
+            break;
+        }
+    }
+    // A cancel that interrupted the last turn still counts as canceled.
+    if final_status.0 == "completed" && is_canceled(ctx.playbook_runs, &run.run_id).await {
+        final_status = ("canceled", None);
+    }
+    ctx.playbook_runs.lock().await.remove(&run.run_id);
+    emit_progress(
+        &event_sink,
+        &run,
+        last_index,
+        final_status.0,
+        final_status.1.as_deref(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_steps, PlaybookStep};
+
+    #[test]
+    fn normalize_steps_drops_blank_prompts() {
+        let steps = vec![
+            PlaybookStep {
+                prompt: "Run the tests".to_string(),
+                require_confirmation: false,
+            },
+            PlaybookStep {
+                prompt: "   ".to_string(),
+                require_confirmation: true,
+            },
+        ];
+        let normalized = normalize_steps(steps).expect("steps");
+        assert_eq!(normalized.len(), 1);
+        assert!(normalize_steps(Vec::new()).is_err());
+    }
+}
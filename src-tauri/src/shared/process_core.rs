@@ -17,4 +17,161 @@ pub(crate) fn tokio_command(program: impl AsRef<OsStr>) -> Command {
     let mut command = Command::new(program);
     hide_console_on_windows(command.as_std_mut());
     command
-}
\ No newline at end of file
+}
+
+/// Shell families that differ in how scripts are passed and words are quoted. Anything not
+/// recognized by name is treated as a POSIX shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShellKind {
+    Posix,
+    Fish,
+    PowerShell,
+    Cmd,
+}
+
+impl ShellKind {
+    pub(crate) fn of(shell: &str) -> Self {
+        // `Path` only splits on the host separator; settings may name a shell by either style.
+        let file = shell.trim().rsplit(['/', '\\']).next().unwrap_or_default();
+        let name = file.to_ascii_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::PowerShell,
+            "cmd" => ShellKind::Cmd,
+            _ => ShellKind::Posix,
+        }
+    }
+}
+
+fn is_bare_word(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '/' | ':' | '='))
+}
+
+/// Quotes one word for `kind`. Plain words stay unquoted so a program name can still resolve
+/// to a shell alias.
+pub(crate) fn quote_for_shell(kind: ShellKind, value: &str) -> String {
+    match kind {
+        ShellKind::Posix => shell_words::quote(value).into_owned(),
+        _ if is_bare_word(value) => value.to_string(),
+        ShellKind::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        ShellKind::PowerShell => format!("'{}'", value.replace('\'', "''")),
+        ShellKind::Cmd => format!("\"{}\"", value.replace('"', "\"\"")),
+    }
+}
+
+/// Runs `script` with `shell`, or with `sh` (`cmd` on Windows) when none is configured. A
+/// configured POSIX shell runs interactively so the functions and aliases from its rc files
+/// are defined; fish and PowerShell load their profiles either way.
+pub(crate) fn shell_script_command(shell: Option<&str>, script: &str) -> Command {
+    let Some(shell) = shell.map(str::trim).filter(|shell| !shell.is_empty()) else {
+        #[cfg(windows)]
+        {
+            let mut command = tokio_command("cmd");
+            command.args(["/C", script]);
+            return command;
+        }
+        #[cfg(not(windows))]
+        {
+            let mut command = tokio_command("sh");
+            command.args(["-c", script]);
+            return command;
+        }
+    };
+    let mut command = tokio_command(shell);
+    match ShellKind::of(shell) {
+        ShellKind::Posix => command.args(["-i", "-c", script]),
+        ShellKind::Fish => command.args(["-c", script]),
+        ShellKind::PowerShell => command.args(["-NoLogo", "-NonInteractive", "-Command", script]),
+        ShellKind::Cmd => command.args(["/C", script]),
+    };
+    command
+}
+
+/// Re-creates `command` as a script run by the user's `shell`, for CLIs installed as shell
+/// functions or aliases. Call once args, env and cwd are set and before stdio is configured;
+/// `env_cleared` must say whether `command` started from an empty environment. Without a
+/// shell, `command` is returned as is.
+pub(crate) fn through_shell(command: Command, shell: Option<&str>, env_cleared: bool) -> Command {
+    let Some(shell) = shell.map(str::trim).filter(|shell| !shell.is_empty()) else {
+        return command;
+    };
+    let original = command.as_std();
+    let kind = ShellKind::of(shell);
+    let words = std::iter::once(original.get_program())
+        .chain(original.get_args())
+        .map(|word| quote_for_shell(kind, &word.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // `exec` keeps the shell from sitting between the app and the CLI, so killing the child
+    // stops the CLI itself.
+    let script = match kind {
+        ShellKind::Posix | ShellKind::Fish => format!("exec {words}"),
+        ShellKind::PowerShell => format!("& {words}; exit $LASTEXITCODE"),
+        ShellKind::Cmd => words,
+    };
+    let mut wrapped = shell_script_command(Some(shell), &script);
+    if env_cleared {
+        wrapped.env_clear();
+    }
+    for (name, value) in original.get_envs() {
+        match value {
+            Some(value) => wrapped.env(name, value),
+            None => wrapped.env_remove(name),
+        };
+    }
+    if let Some(dir) = original.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote_for_shell, through_shell, tokio_command, ShellKind};
+
+    #[test]
+    fn runs_commands_through_the_configured_shell() {
+        assert_eq!(ShellKind::of("/bin/zsh"), ShellKind::Posix);
+        assert_eq!(ShellKind::of("/opt/homebrew/bin/fish"), ShellKind::Fish);
+        assert_eq!(
+            ShellKind::of(r"C:\Program Files\PowerShell\7\pwsh.exe"),
+            ShellKind::PowerShell
+        );
+        assert_eq!(
+            quote_for_shell(ShellKind::Fish, "it's a \\"),
+            r"'it\'s a \\'"
+        );
+        assert_eq!(quote_for_shell(ShellKind::PowerShell, "it's"), "'it''s'");
+        assert_eq!(quote_for_shell(ShellKind::PowerShell, "claude"), "claude");
+
+        let mut command = tokio_command("claude");
+        command
+            .args(["-p", "Fix the build's tests"])
+            .current_dir("/repo")
+            .env("CLAUDE_CODE_MAX_OUTPUT_TOKENS", "4096");
+        let wrapped = through_shell(command, Some("/bin/zsh"), false);
+        let wrapped = wrapped.as_std();
+        assert_eq!(wrapped.get_program(), "/bin/zsh");
+        let args = wrapped
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            vec!["-i", "-c", r"exec claude -p 'Fix the build'\''s tests'"]
+        );
+        assert_eq!(
+            wrapped
+                .get_current_dir()
+                .map(|dir| dir.to_string_lossy().to_string()),
+            Some("/repo".to_string())
+        );
+        assert_eq!(wrapped.get_envs().count(), 1);
+
+        let direct = through_shell(tokio_command("claude"), Some("  "), false);
+        assert_eq!(direct.as_std().get_program(), "claude");
+    }
+}
@@ -0,0 +1,147 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use tokio::sync::Mutex;
+
+use crate::providers;
+use crate::shared::context_files_core::{
+    format_context_blocks, load_context_files, resolve_context_paths,
+};
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+/// Rough cost of one attached image; Claude caps a downscaled image near this size.
+const IMAGE_TOKEN_ESTIMATE: u64 = 1_600;
+
+/// Approximate input prices in USD per million tokens, matched by model name substring.
+/// More specific names come first.
+const INPUT_PRICES_PER_MILLION: &[(&str, f64)] = &[
+    ("opus-4-5", 5.0),
+    ("opus-4.5", 5.0),
+    ("opus", 15.0),
+    ("sonnet", 3.0),
+    ("haiku", 1.0),
+    ("gpt-5-mini", 0.25),
+    ("gpt-5", 1.25),
+    ("codex", 1.25),
+    ("gemini-2.5-pro", 1.25),
+    ("gemini-3-pro", 2.0),
+    ("flash", 0.3),
+];
+
+fn chars_per_token(provider: &ProviderKind) -> f64 {
+    match provider {
+        ProviderKind::Claude => 3.5,
+        ProviderKind::Codex | ProviderKind::Gemini => 4.0,
+    }
+}
+
+/// Heuristic token count: ASCII text at the provider's chars-per-token ratio, and one token
+/// per non-ASCII character, which keeps CJK text from being badly undercounted.
+fn estimate_text_tokens(text: &str, chars_per_token: f64) -> u64 {
+    let (ascii, other) = text.chars().fold((0u64, 0u64), |(ascii, other), ch| {
+        if ch.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    (ascii as f64 / chars_per_token).ceil() as u64 + other
+}
+
+fn input_price_per_million(model: &str) -> Option<f64> {
+    let model = model.to_ascii_lowercase();
+    INPUT_PRICES_PER_MILLION
+        .iter()
+        .find(|(name, _)| model.contains(name))
+        .map(|(_, price)| *price)
+}
+
+/// Estimates the input tokens a message would cost with the workspace's context files attached.
+pub(crate) async fn estimate_prompt_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    workspace_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+    model: Option<String>,
+) -> Result<Value, String> {
+    let (entry, parent_entry) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or_else(|| "workspace not found".to_string())?;
+        let parent_entry = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        (entry, parent_entry)
+    };
+    let settings = app_settings.lock().await.clone();
+    let provider = providers::resolve_workspace_provider(&entry, Some(&settings));
+    let ratio = chars_per_token(&provider);
+
+    let context_paths = resolve_context_paths(&entry, parent_entry.as_ref());
+    let context_files = load_context_files(&entry.path, &context_paths);
+    let context_breakdown = context_files
+        .iter()
+        .map(|file| {
+            let tokens =
+                estimate_text_tokens(&format_context_blocks(std::slice::from_ref(file)), ratio);
+            json!({ "path": file.record.path, "tokens": tokens })
+        })
+        .collect::<Vec<_>>();
+    let context_tokens = estimate_text_tokens(&format_context_blocks(&context_files), ratio);
+    let prompt_tokens = estimate_text_tokens(text.trim(), ratio);
+    let image_count = images
+        .unwrap_or_default()
+        .iter()
+        .filter(|path| !path.trim().is_empty())
+        .count() as u64;
+    let image_tokens = image_count * IMAGE_TOKEN_ESTIMATE;
+    let total = prompt_tokens + context_tokens + image_tokens;
+
+    let model = model
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty());
+    let price = model.as_deref().and_then(input_price_per_million);
+    let cost = price.map(|price| total as f64 * price / 1_000_000.0);
+
+    Ok(json!({
+        "result": {
+            "provider": provider.as_str(),
+            "model": model,
+            "method": "heuristic",
+            "tokens": {
+                "prompt": prompt_tokens,
+                "context": context_tokens,
+                "images": image_tokens,
+                "total": total,
+            },
+            "contextFiles": context_breakdown,
+            "inputPricePerMillion": price,
+            "estimatedCostUsd": cost,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_text_tokens, input_price_per_million};
+
+    #[test]
+    fn estimates_text_and_prices() {
+        assert_eq!(estimate_text_tokens("", 4.0), 0);
+        assert_eq!(estimate_text_tokens("abcdefgh", 4.0), 2);
+        assert_eq!(estimate_text_tokens("abcde", 4.0), 2);
+        assert_eq!(estimate_text_tokens("你好", 4.0), 2);
+        assert_eq!(
+            input_price_per_million("claude-opus-4-5-20251101"),
+            Some(5.0)
+        );
+        assert_eq!(input_price_per_million("claude-opus-4-1"), Some(15.0));
+        assert_eq!(input_price_per_million("Claude-Sonnet-4-5"), Some(3.0));
+        assert_eq!(input_price_per_million("mystery-model"), None);
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::shared::claude_core::now_ms;
+
+const PROMPT_HISTORY_FILE_NAME: &str = "prompt_history.json";
+/// Oldest prompts fall off once a workspace has this many.
+const MAX_PROMPTS_PER_WORKSPACE: usize = 500;
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// A prompt sent to any thread of a workspace. Sending the same text again moves it to the
+/// front instead of adding a copy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PromptHistoryEntry {
+    pub(crate) id: String,
+    pub(crate) text: String,
+    /// Thread the prompt was last sent to.
+    #[serde(rename = "threadId")]
+    pub(crate) thread_id: String,
+    #[serde(rename = "lastUsedAt")]
+    pub(crate) last_used_at: i64,
+    #[serde(default = "default_use_count", rename = "useCount")]
+    pub(crate) use_count: u32,
+}
+
+fn default_use_count() -> u32 {
+    1
+}
+
+/// Prompts keyed by workspace id, most recently sent first.
+pub(crate) type PromptHistoryStore = Arc<Mutex<HashMap<String, Vec<PromptHistoryEntry>>>>;
+
+pub(crate) fn prompt_history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(PROMPT_HISTORY_FILE_NAME)
+}
+
+pub(crate) fn read_prompt_history(
+    path: &Path,
+) -> Result<HashMap<String, Vec<PromptHistoryEntry>>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&data).map_err(|error| error.to_string())
+}
+
+fn write_prompt_history(
+    path: &Path,
+    history: &HashMap<String, Vec<PromptHistoryEntry>>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(history).map_err(|error| error.to_string())?;
+    std::fs::write(path, data).map_err(|error| error.to_string())
+}
+
+/// Prompts that differ only in whitespace count as the same prompt.
+fn dedup_key(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn push_prompt(entries: &mut Vec<PromptHistoryEntry>, thread_id: &str, text: &str, now: i64) {
+    let key = dedup_key(text);
+    let entry = match entries
+        .iter()
+        .position(|entry| dedup_key(&entry.text) == key)
+    {
+        Some(index) => {
+            let previous = entries.remove(index);
+            PromptHistoryEntry {
+                use_count: previous.use_count.saturating_add(1),
+                ..previous
+            }
+        }
+        None => PromptHistoryEntry {
+            id: Uuid::new_v4().to_string(),
+            text: text.trim().to_string(),
+            thread_id: String::new(),
+            last_used_at: 0,
+            use_count: 1,
+        },
+    };
+    entries.insert(
+        0,
+        PromptHistoryEntry {
+            thread_id: thread_id.to_string(),
+            last_used_at: now,
+            ..entry
+        },
+    );
+    entries.truncate(MAX_PROMPTS_PER_WORKSPACE);
+}
+
+/// Adds a sent message to the workspace's history. Blank messages (image-only sends) are
+/// skipped.
+pub(crate) async fn record_prompt(
+    history: &PromptHistoryStore,
+    history_path: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    text: &str,
+) -> Result<(), String> {
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let mut store = history.lock().await;
+    push_prompt(
+        store.entry(workspace_id.to_string()).or_default(),
+        thread_id,
+        text,
+        now_ms(),
+    );
+    write_prompt_history(history_path, &store)
+}
+
+/// Scores `text` against `query` when the query's characters appear in it in order, ignoring
+/// case. Runs of adjacent characters and matches at word starts score higher.
+fn fuzzy_score(text: &str, query: &str) -> Option<u32> {
+    let mut query = query
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for ch in text.chars().flat_map(char::to_lowercase) {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        let matched = ch == wanted;
+        if matched {
+            query.next();
+            score += 1;
+            if previous_matched {
+                score += 2;
+            }
+            if previous.is_none_or(|previous| !previous.is_alphanumeric()) {
+                score += 3;
+            }
+        }
+        previous_matched = matched;
+        previous = Some(ch);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// The workspace's prompts, newest first, or the best fuzzy matches for `query`.
+pub(crate) async fn prompt_history_core(
+    history: &PromptHistoryStore,
+    workspace_id: String,
+    query: Option<String>,
+    limit: Option<u32>,
+) -> Result<Value, String> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+    let query = query.unwrap_or_default();
+    let store = history.lock().await;
+    let entries = store
+        .get(&workspace_id)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let data = if query.trim().is_empty() {
+        entries.iter().take(limit).collect::<Vec<_>>()
+    } else {
+        let mut matches = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                fuzzy_score(&entry.text, &query).map(|score| (score, index, entry))
+            })
+            .collect::<Vec<_>>();
+        // Entries are newest first, so the index breaks ties in favor of recent prompts.
+        matches.sort_by(|(a_score, a_index, _), (b_score, b_index, _)| {
+            b_score.cmp(a_score).then(a_index.cmp(b_index))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, entry)| entry)
+            .collect()
+    };
+    Ok(json!({ "result": { "data": data } }))
+}
+
+/// Text of a history entry, for sending it again.
+pub(crate) async fn prompt_history_text(
+    history: &PromptHistoryStore,
+    workspace_id: &str,
+    prompt_id: &str,
+) -> Result<String, String> {
+    history
+        .lock()
+        .await
+        .get(workspace_id)
+        .and_then(|entries| entries.iter().find(|entry| entry.id == prompt_id))
+        .map(|entry| entry.text.clone())
+        .ok_or_else(|| "prompt not found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, push_prompt, MAX_PROMPTS_PER_WORKSPACE};
+
+    #[test]
+    fn keeps_one_entry_per_prompt_and_matches_fuzzily() {
+        let mut entries = Vec::new();
+        push_prompt(&mut entries, "t-1", "Run the tests", 1);
+        push_prompt(&mut entries, "t-1", "Fix the login bug", 2);
+        let first_id = entries[1].id.clone();
+        push_prompt(&mut entries, "t-2", "  Run  the tests\n", 3);
+        assert_eq!(entries[0].id, first_id);
+        let summary = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.text.as_str(),
+                    entry.thread_id.as_str(),
+                    entry.use_count,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![("Run the tests", "t-2", 2), ("Fix the login bug", "t-1", 1)]
+        );
+
+        for index in 0..MAX_PROMPTS_PER_WORKSPACE {
+            push_prompt(&mut entries, "t-3", &format!("prompt {index}"), 10);
+        }
+        assert_eq!(entries.len(), MAX_PROMPTS_PER_WORKSPACE);
+        assert!(entries.iter().all(|entry| entry.text.starts_with("prompt")));
+
+        assert!(fuzzy_score("Fix the login bug", "flb").is_some());
+        assert!(fuzzy_score("Fix the login bug", "LOGIN").is_some());
+        assert!(fuzzy_score("Fix the login bug", "bugfix").is_none());
+        assert!(fuzzy_score("Run the tests", "tests") > fuzzy_score("Run the tests", "rts"));
+    }
+}
@@ -0,0 +1,245 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::shared::provider_versions_core::{parse_version, probe_cli_version};
+use crate::types::ProviderKind;
+
+const PROVIDERS: [ProviderKind; 3] = [
+    ProviderKind::Codex,
+    ProviderKind::Claude,
+    ProviderKind::Gemini,
+];
+
+/// A directory to look in and where it came from, shown next to each suggestion.
+type SearchDir = (PathBuf, &'static str);
+
+/// An executable found for a provider.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct CliCandidate {
+    pub(crate) path: String,
+    /// `path`, `local`, `npm`, `nvm`, `homebrew`, `bun` or `system`.
+    pub(crate) source: &'static str,
+    /// First line of `--version`; `None` when the candidate did not answer.
+    pub(crate) version: Option<String>,
+}
+
+/// Executable names for a provider, with the wrappers npm installs on Windows.
+fn executable_names(provider: &ProviderKind, windows: bool) -> Vec<String> {
+    let name = provider.as_str();
+    if windows {
+        ["exe", "cmd"]
+            .iter()
+            .map(|extension| format!("{name}.{extension}"))
+            .collect()
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+/// PATH first, then the places installers commonly put these CLIs even when the app was
+/// launched without the shell's PATH.
+fn search_dirs(
+    path_var: Option<OsString>,
+    home: Option<PathBuf>,
+    npm_prefix: Option<PathBuf>,
+    windows: bool,
+) -> Vec<SearchDir> {
+    let mut dirs = path_var
+        .map(|value| {
+            std::env::split_paths(&value)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .map(|dir| (dir, "path"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if let Some(prefix) = npm_prefix {
+        dirs.push(if windows {
+            (prefix, "npm")
+        } else {
+            (prefix.join("bin"), "npm")
+        });
+    }
+    if let Some(home) = home {
+        if windows {
+            dirs.push((home.join("AppData").join("Roaming").join("npm"), "npm"));
+        } else {
+            dirs.push((home.join(".local").join("bin"), "local"));
+            dirs.push((home.join(".claude").join("local"), "local"));
+            dirs.push((home.join(".npm-global").join("bin"), "npm"));
+            dirs.push((home.join(".bun").join("bin"), "bun"));
+            let nvm_root = home.join(".nvm").join("versions").join("node");
+            let mut nvm_bins = std::fs::read_dir(nvm_root)
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .map(|entry| entry.path().join("bin"))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            // Newest Node release first.
+            nvm_bins.sort_by(|left, right| right.cmp(left));
+            dirs.extend(nvm_bins.into_iter().map(|dir| (dir, "nvm")));
+        }
+    }
+    if !windows {
+        dirs.push((PathBuf::from("/opt/homebrew/bin"), "homebrew"));
+        dirs.push((PathBuf::from("/usr/local/bin"), "system"));
+        dirs.push((PathBuf::from("/usr/bin"), "system"));
+    }
+    dirs
+}
+
+/// Existing executables for `provider`, skipping paths that resolve to one already found.
+fn find_candidates(provider: &ProviderKind, dirs: &[SearchDir], windows: bool) -> Vec<SearchDir> {
+    let names = executable_names(provider, windows);
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for (dir, source) in dirs {
+        for name in &names {
+            let candidate = dir.join(name);
+            if !candidate.is_file() {
+                continue;
+            }
+            let resolved = candidate
+                .canonicalize()
+                .unwrap_or_else(|_| candidate.clone());
+            if seen.insert(resolved) {
+                found.push((candidate, *source));
+            }
+        }
+    }
+    found
+}
+
+/// Working candidates first, newest version first among them; otherwise search order wins.
+fn rank_candidates(candidates: &mut [CliCandidate]) {
+    candidates.sort_by(|left, right| {
+        let key = |candidate: &CliCandidate| {
+            candidate
+                .version
+                .as_deref()
+                .map(|version| parse_version(version).unwrap_or_default())
+        };
+        key(right).cmp(&key(left))
+    });
+}
+
+fn npm_prefix() -> Option<PathBuf> {
+    std::env::var_os("NPM_CONFIG_PREFIX")
+        .or_else(|| std::env::var_os("npm_config_prefix"))
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .find(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The executables found for one provider.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ProviderDetection {
+    pub(crate) provider: ProviderKind,
+    /// The best guess for the provider's bin setting: the top candidate, if it answered.
+    pub(crate) suggested: Option<String>,
+    pub(crate) candidates: Vec<CliCandidate>,
+}
+
+/// Looks for the Codex, Claude and Gemini CLIs and version-checks each one found.
+pub(crate) async fn detect_providers() -> Vec<ProviderDetection> {
+    let windows = cfg!(windows);
+    let dirs = search_dirs(std::env::var_os("PATH"), home_dir(), npm_prefix(), windows);
+    let mut providers = Vec::new();
+    for provider in PROVIDERS {
+        let found = find_candidates(&provider, &dirs, windows);
+        let mut candidates = Vec::with_capacity(found.len());
+        for (path, source) in found {
+            let path = path.to_string_lossy().to_string();
+            candidates.push(CliCandidate {
+                version: probe_cli_version(&path).await,
+                path,
+                source,
+            });
+        }
+        rank_candidates(&mut candidates);
+        let suggested = candidates
+            .first()
+            .filter(|candidate| candidate.version.is_some())
+            .map(|candidate| candidate.path.clone());
+        providers.push(ProviderDetection {
+            provider,
+            suggested,
+            candidates,
+        });
+    }
+    providers
+}
+
+pub(crate) async fn detect_providers_core() -> Result<Value, String> {
+    Ok(json!({ "result": { "providers": detect_providers().await } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_candidates, rank_candidates, search_dirs, CliCandidate};
+    use crate::types::ProviderKind;
+    use std::fs;
+    use uuid::Uuid;
+
+    fn candidate(path: &str, version: Option<&str>) -> CliCandidate {
+        CliCandidate {
+            path: path.to_string(),
+            source: "path",
+            version: version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn finds_installs_outside_path_and_ranks_newest_working_first() {
+        let home = std::env::temp_dir().join(format!("polycode-detect-test-{}", Uuid::new_v4()));
+        let on_path = home.join("bin");
+        fs::create_dir_all(&on_path).expect("create bin");
+        fs::create_dir_all(home.join(".local/bin")).expect("create local bin");
+        fs::write(on_path.join("claude"), "").expect("write claude");
+        fs::write(home.join(".local/bin/claude"), "").expect("write local claude");
+        fs::write(home.join(".local/bin/codex"), "").expect("write codex");
+
+        let path_var = std::env::join_paths([&on_path]).expect("join paths");
+        let dirs = search_dirs(Some(path_var), Some(home.clone()), None, false);
+        assert_eq!(dirs[0], (on_path.clone(), "path"));
+        // Leave out system dirs so CLIs installed on this machine don't leak in.
+        let dirs = dirs
+            .into_iter()
+            .filter(|(dir, _)| dir.starts_with(&home))
+            .collect::<Vec<_>>();
+
+        let found = find_candidates(&ProviderKind::Claude, &dirs, false);
+        assert_eq!(
+            found,
+            vec![
+                (on_path.join("claude"), "path"),
+                (home.join(".local/bin/claude"), "local"),
+            ]
+        );
+        assert!(find_candidates(&ProviderKind::Gemini, &dirs, false).is_empty());
+
+        let mut candidates = vec![
+            candidate("/broken/claude", None),
+            candidate("/old/claude", Some("1.0.9 (Claude Code)")),
+            candidate("/new/claude", Some("1.0.33 (Claude Code)")),
+        ];
+        rank_candidates(&mut candidates);
+        let paths = candidates
+            .iter()
+            .map(|candidate| candidate.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/new/claude", "/old/claude", "/broken/claude"]);
+        let _ = fs::remove_dir_all(&home);
+    }
+}
@@ -0,0 +1,168 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::time::timeout;
+
+use crate::shared::process_core::tokio_command;
+use crate::types::{AppSettings, ProviderKind};
+
+const VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Long enough to skip a probe per turn, short enough to notice an upgrade.
+const VERSION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A CLI feature the app relies on and the first release that ships it.
+pub(crate) struct FeatureRequirement {
+    pub(crate) feature: &'static str,
+    pub(crate) min_version: &'static str,
+}
+
+pub(crate) const CLAUDE_STREAM_JSON: FeatureRequirement = FeatureRequirement {
+    feature: "--output-format stream-json",
+    min_version: "1.0.0",
+};
+
+/// When each executable was probed, and what it reported.
+type VersionCache = HashMap<String, (Instant, Option<String>)>;
+
+static VERSION_CACHE: OnceLock<Mutex<VersionCache>> = OnceLock::new();
+
+/// Runs `<bin> --version`, remembering the answer per executable for a few minutes.
+/// `None` means the CLI did not report a version; the turn itself surfaces spawn failures.
+pub(crate) async fn probe_cli_version(executable: &str) -> Option<String> {
+    let cache = VERSION_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(cache) = cache.lock() {
+        if let Some((probed_at, version)) = cache.get(executable) {
+            if probed_at.elapsed() < VERSION_CACHE_TTL {
+                return version.clone();
+            }
+        }
+    }
+
+    let mut command = tokio_command(executable);
+    command.arg("--version");
+    command.stdin(Stdio::null());
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::null());
+    let version = match timeout(VERSION_PROBE_TIMEOUT, command.output()).await {
+        Ok(Ok(output)) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+        }
+        _ => None,
+    };
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(executable.to_string(), (Instant::now(), version.clone()));
+    }
+    version
+}
+
+/// Pulls the first dotted number out of output like `1.0.33 (Claude Code)` or `v0.4.1`.
+pub(crate) fn parse_version(raw: &str) -> Option<Vec<u64>> {
+    raw.split(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .map(|token| token.trim_matches('.'))
+        .find(|token| token.contains('.') && !token.is_empty())
+        .or_else(|| {
+            raw.split(|ch: char| !ch.is_ascii_digit())
+                .find(|token| !token.is_empty())
+        })
+        .and_then(|token| {
+            token
+                .split('.')
+                .map(|part| part.parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()
+        })
+}
+
+fn is_older(installed: &[u64], required: &[u64]) -> bool {
+    let len = installed.len().max(required.len());
+    let part = |parts: &[u64], index: usize| parts.get(index).copied().unwrap_or(0);
+    (0..len)
+        .map(|index| part(installed, index).cmp(&part(required, index)))
+        .find(|ordering| ordering.is_ne())
+        .is_some_and(|ordering| ordering.is_lt())
+}
+
+pub(crate) fn configured_min_version<'a>(
+    settings: &'a AppSettings,
+    provider: &ProviderKind,
+) -> Option<&'a str> {
+    settings
+        .provider_min_versions
+        .get(provider.as_str())
+        .map(|version| version.trim())
+        .filter(|version| !version.is_empty())
+}
+
+/// Builds the `provider/versionMismatch` payload when the installed CLI is older than the
+/// configured minimum or than any feature the turn needs. Unparseable versions never warn.
+pub(crate) fn version_mismatch(
+    provider: &ProviderKind,
+    installed: &str,
+    configured_min: Option<&str>,
+    features: &[&FeatureRequirement],
+) -> Option<Value> {
+    let installed_parts = parse_version(installed)?;
+    let mut reasons = Vec::new();
+    if let Some(min) = configured_min {
+        if parse_version(min).is_some_and(|required| is_older(&installed_parts, &required)) {
+            reasons.push(json!({ "source": "settings", "minVersion": min }));
+        }
+    }
+    for requirement in features {
+        if parse_version(requirement.min_version)
+            .is_some_and(|required| is_older(&installed_parts, &required))
+        {
+            reasons.push(json!({
+                "source": "feature",
+                "feature": requirement.feature,
+                "minVersion": requirement.min_version,
+            }));
+        }
+    }
+    if reasons.is_empty() {
+        return None;
+    }
+    Some(json!({
+        "provider": provider.as_str(),
+        "installedVersion": installed,
+        "reasons": reasons,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_version, version_mismatch, FeatureRequirement};
+    use crate::types::ProviderKind;
+
+    #[test]
+    fn parses_versions_from_cli_banners() {
+        assert_eq!(parse_version("1.0.33 (Claude Code)"), Some(vec![1, 0, 33]));
+        assert_eq!(parse_version("gemini v0.4.1"), Some(vec![0, 4, 1]));
+        assert_eq!(parse_version("codex-cli 2"), Some(vec![2]));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn warns_only_when_older_than_a_requirement() {
+        let feature = FeatureRequirement {
+            feature: "--output-format stream-json",
+            min_version: "1.0.0",
+        };
+        let claude = ProviderKind::Claude;
+        assert!(version_mismatch(&claude, "1.0.33", Some("1.0"), &[&feature]).is_none());
+        let mismatch = version_mismatch(&claude, "0.2.9", Some("1.0.40"), &[&feature])
+            .expect("older CLI warns");
+        assert_eq!(mismatch["reasons"].as_array().map(Vec::len), Some(2));
+        let mismatch = version_mismatch(&claude, "1.0.33", Some("1.0.40"), &[&feature])
+            .expect("settings minimum warns");
+        assert_eq!(mismatch["reasons"][0]["source"], "settings");
+        assert!(version_mismatch(&claude, "dev build", Some("9.0.0"), &[]).is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use serde_json::{json, Value};
+use std::future::Future;
+
+/// The first thread of a `list_threads` response, which lists Codex and Claude threads
+/// newest first.
+fn latest_thread_id(threads: &Value) -> Option<String> {
+    threads["result"]["data"]
+        .as_array()?
+        .iter()
+        .find_map(|thread| thread.get("id").and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+/// Sends `text` to the workspace's most recently updated thread, so a hotkey or menu bar
+/// reply doesn't need a thread id. `send` is the regular send path for `(thread_id, text)`;
+/// the reply names the thread it went to.
+pub(crate) async fn reply_latest_core<FutSend>(
+    threads: Value,
+    text: String,
+    send: impl FnOnce(String, String) -> FutSend,
+) -> Result<Value, String>
+where
+    FutSend: Future<Output = Result<Value, String>>,
+{
+    let thread_id = latest_thread_id(&threads)
+        .ok_or_else(|| "The workspace has no threads yet.".to_string())?;
+    let response = send(thread_id.clone(), text).await?;
+    Ok(json!({ "result": { "threadId": thread_id, "response": response } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::latest_thread_id;
+    use serde_json::json;
+
+    #[test]
+    fn picks_the_first_listed_thread() {
+        let threads = json!({
+            "result": {
+                "data": [
+                    { "preview": "no id" },
+                    { "id": "claude-2", "provider": "claude", "updatedAt": 30 },
+                    { "id": "codex-1", "provider": "codex", "updatedAt": 20 },
+                ],
+            },
+        });
+        assert_eq!(latest_thread_id(&threads).as_deref(), Some("claude-2"));
+        assert_eq!(latest_thread_id(&json!({ "result": { "data": [] } })), None);
+        assert_eq!(latest_thread_id(&json!({ "error": "offline" })), None);
+    }
+}
@@ -0,0 +1,226 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::types::ProviderKind;
+
+/// Used when the provider says it is rate limiting without saying for how long.
+const DEFAULT_RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+/// Overload errors clear up faster than quota limits.
+const DEFAULT_OVERLOADED_COOLDOWN: Duration = Duration::from_secs(15);
+/// A garbled hint must not park every turn for hours.
+const MAX_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+const RATE_LIMIT_MARKERS: &[&str] = &["rate limit", "rate_limit", "too many requests"];
+const OVERLOADED_MARKERS: &[&str] = &["overloaded", "temporarily unavailable"];
+
+static STATUS_PATTERN: OnceLock<Regex> = OnceLock::new();
+static RETRY_AFTER_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// Turns of a provider wait until its cooldown ends. Shared by all workspaces, since limits
+/// apply to the account rather than to one workspace.
+pub(crate) type RateLimitsStore = Arc<RateLimits>;
+
+#[derive(Default)]
+pub(crate) struct RateLimits {
+    cooldowns: Mutex<HashMap<String, Cooldown>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cooldown {
+    /// Ms since the epoch.
+    pub(crate) until: i64,
+    /// `rateLimited` or `overloaded`.
+    pub(crate) reason: &'static str,
+}
+
+/// What a failed turn's output says about the provider's limits.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RateLimitHint {
+    pub(crate) reason: &'static str,
+    pub(crate) retry_after: Duration,
+}
+
+fn status_pattern() -> &'static Regex {
+    STATUS_PATTERN.get_or_init(|| Regex::new(r"\b(429|503|529)\b").expect("valid status pattern"))
+}
+
+/// `retry-after: 30`, `Retry after 2.5 seconds`, `try again in 5m`, `resets in 90s`, ...
+fn retry_after_pattern() -> &'static Regex {
+    RETRY_AFTER_PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r#"(?i)(?:retry[- _]?after|try again in|retry in|resets? in)[\s:="']*"#,
+            r"(?P<amount>\d+(?:\.\d+)?)\s*",
+            r"(?P<unit>ms|milliseconds?|s|secs?|seconds?|m|mins?|minutes?|h|hours?)?\b",
+        ))
+        .expect("valid retry-after pattern")
+    })
+}
+
+fn parse_retry_after(output: &str) -> Option<Duration> {
+    let captures = retry_after_pattern().captures(output)?;
+    let amount = captures["amount"].parse::<f64>().ok()?;
+    let unit = captures
+        .name("unit")
+        .map_or("s".to_string(), |unit| unit.as_str().to_ascii_lowercase());
+    let seconds = match unit.chars().next() {
+        Some('m') if unit.len() == 2 || unit.starts_with("milli") => amount / 1000.0,
+        Some('m') => amount * 60.0,
+        Some('h') => amount * 3600.0,
+        _ => amount,
+    };
+    Some(Duration::from_secs_f64(seconds).min(MAX_COOLDOWN))
+}
+
+/// Recognizes rate-limit and overload errors in provider output, with how long to back off.
+/// `None` for every other failure.
+pub(crate) fn parse_rate_limit_hint(output: &str) -> Option<RateLimitHint> {
+    let lowered = output.to_ascii_lowercase();
+    let status = status_pattern()
+        .captures(&lowered)
+        .map(|captures| captures[1].to_string());
+    let (reason, default_cooldown) = if status.as_deref() == Some("429")
+        || RATE_LIMIT_MARKERS
+            .iter()
+            .any(|marker| lowered.contains(marker))
+    {
+        ("rateLimited", DEFAULT_RATE_LIMIT_COOLDOWN)
+    } else if status.is_some()
+        || OVERLOADED_MARKERS
+            .iter()
+            .any(|marker| lowered.contains(marker))
+    {
+        ("overloaded", DEFAULT_OVERLOADED_COOLDOWN)
+    } else {
+        return None;
+    };
+    Some(RateLimitHint {
+        reason,
+        retry_after: parse_retry_after(output).unwrap_or(default_cooldown),
+    })
+}
+
+impl RateLimits {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Cooldown>> {
+        self.cooldowns
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Starts or extends the provider's cooldown; a shorter hint never cuts one short.
+    pub(crate) fn note(
+        &self,
+        provider: &ProviderKind,
+        hint: &RateLimitHint,
+        now_ms: i64,
+    ) -> Cooldown {
+        let until = now_ms + hint.retry_after.as_millis() as i64;
+        let mut cooldowns = self.lock();
+        let cooldown = cooldowns
+            .entry(provider.as_str().to_string())
+            .or_insert(Cooldown {
+                until,
+                reason: hint.reason,
+            });
+        if until > cooldown.until {
+            *cooldown = Cooldown {
+                until,
+                reason: hint.reason,
+            };
+        }
+        cooldown.clone()
+    }
+
+    /// The provider's cooldown while it is still running.
+    pub(crate) fn active(&self, provider: &ProviderKind, now_ms: i64) -> Option<Cooldown> {
+        let mut cooldowns = self.lock();
+        let key = provider.as_str();
+        match cooldowns.get(key) {
+            Some(cooldown) if cooldown.until > now_ms => Some(cooldown.clone()),
+            Some(_) => {
+                cooldowns.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+/// Params of `provider/rateLimitState`; `cooldown: None` reports the provider as available.
+pub(crate) fn rate_limit_state(
+    provider: &ProviderKind,
+    cooldown: Option<&Cooldown>,
+    now_ms: i64,
+) -> Value {
+    json!({
+        "provider": provider.as_str(),
+        "coolingDown": cooldown.is_some(),
+        "until": cooldown.map(|cooldown| cooldown.until),
+        "retryInMs": cooldown.map_or(0, |cooldown| (cooldown.until - now_ms).max(0)),
+        "reason": cooldown.map(|cooldown| cooldown.reason),
+    })
+}
+
+/// Current state of every provider, for clients that missed the `provider/rateLimitState`
+/// events.
+pub(crate) fn rate_limit_state_core(rate_limits: &RateLimits, now_ms: i64) -> Value {
+    let data = [
+        ProviderKind::Codex,
+        ProviderKind::Claude,
+        ProviderKind::Gemini,
+    ]
+    .iter()
+    .map(|provider| {
+        rate_limit_state(
+            provider,
+            rate_limits.active(provider, now_ms).as_ref(),
+            now_ms,
+        )
+    })
+    .collect::<Vec<_>>();
+    json!({ "result": { "data": data } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_rate_limit_hint, RateLimits};
+    use crate::types::ProviderKind;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_hints_and_keeps_the_longest_cooldown() {
+        let hint = parse_rate_limit_hint("API Error: 429 rate_limit_error. Retry-After: 42")
+            .expect("rate limited");
+        assert_eq!(hint.reason, "rateLimited");
+        assert_eq!(hint.retry_after, Duration::from_secs(42));
+        let overloaded =
+            parse_rate_limit_hint("API Error: 529 {\"type\":\"overloaded_error\"}").expect("529");
+        assert_eq!(overloaded.reason, "overloaded");
+        assert_eq!(overloaded.retry_after, Duration::from_secs(15));
+        assert_eq!(
+            parse_rate_limit_hint("Too many requests, try again in 2m")
+                .map(|hint| hint.retry_after),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_rate_limit_hint("rate limited; retry after 1500ms").map(|hint| hint.retry_after),
+            Some(Duration::from_millis(1500))
+        );
+        assert!(parse_rate_limit_hint("error: src/main.rs:4290 mismatched types").is_none());
+
+        let limits = RateLimits::default();
+        let claude = ProviderKind::Claude;
+        assert_eq!(limits.note(&claude, &hint, 1_000).until, 43_000);
+        assert_eq!(limits.note(&claude, &overloaded, 2_000).until, 43_000);
+        assert!(limits.active(&ProviderKind::Codex, 2_000).is_none());
+        assert_eq!(
+            limits
+                .active(&claude, 42_999)
+                .map(|cooldown| cooldown.reason),
+            Some("rateLimited")
+        );
+        assert!(limits.active(&claude, 43_000).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use base64::Engine;
+use regex::Regex;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::json;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::shared::claude_core::now_ms;
+use crate::types::RedactionRule;
+
+pub(crate) const RAW_LOGS_DIR_NAME: &str = "raw_logs";
+const RAW_LOG_KEY_FILE_NAME: &str = "raw_logs.key";
+const RAW_LOG_KEY_LEN: usize = 32;
+
+/// Enabled redaction rules, compiled once per turn or export.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RedactionPipeline {
+    rules: Vec<(Regex, String)>,
+}
+
+impl RedactionPipeline {
+    /// Compiles enabled rules, skipping any that fail to compile.
+    pub(crate) fn compile(rules: &[RedactionRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| {
+                Regex::new(&rule.pattern)
+                    .ok()
+                    .map(|regex| (regex, rule.replacement.clone()))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Returns `None` when no rule matched, so callers can skip the raw log.
+    pub(crate) fn redact(&self, text: &str) -> Option<String> {
+        let mut redacted = text.to_string();
+        let mut changed = false;
+        for (regex, replacement) in &self.rules {
+            if regex.is_match(&redacted) {
+                redacted = regex
+                    .replace_all(&redacted, regex::NoExpand(replacement))
+                    .into_owned();
+                changed = true;
+            }
+        }
+        changed.then_some(redacted)
+    }
+}
+
+pub(crate) fn validate_redaction_rules(rules: &[RedactionRule]) -> Result<(), String> {
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err("Redaction rule id is required.".to_string());
+        }
+        Regex::new(&rule.pattern)
+            .map_err(|error| format!("Invalid redaction rule `{}`: {error}", rule.id))?;
+    }
+    Ok(())
+}
+
+fn load_or_create_key(data_dir: &Path) -> Result<LessSafeKey, String> {
+    let key_path = data_dir.join(RAW_LOG_KEY_FILE_NAME);
+    let key_bytes = match std::fs::read(&key_path) {
+        Ok(bytes) if bytes.len() == RAW_LOG_KEY_LEN => bytes,
+        Ok(_) => return Err(format!("Raw log key {} is corrupt.", key_path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            let mut bytes = vec![0u8; RAW_LOG_KEY_LEN];
+            SystemRandom::new()
+                .fill(&mut bytes)
+                .map_err(|_| "Failed to generate raw log key.".to_string())?;
+            std::fs::create_dir_all(data_dir).map_err(|error| error.to_string())?;
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options
+                .open(&key_path)
+                .map_err(|error| format!("Failed to create raw log key: {error}"))?;
+            file.write_all(&bytes).map_err(|error| error.to_string())?;
+            bytes
+        }
+        Err(error) => return Err(format!("Failed to read raw log key: {error}")),
+    };
+    let key = UnboundKey::new(&AES_256_GCM, &key_bytes)
+        .map_err(|_| "Invalid raw log key.".to_string())?;
+    Ok(LessSafeKey::new(key))
+}
+
+pub(crate) fn raw_log_path(data_dir: &Path, workspace_id: &str) -> PathBuf {
+    data_dir
+        .join(RAW_LOGS_DIR_NAME)
+        .join(format!("{workspace_id}.log"))
+}
+
+/// Appends one AES-256-GCM sealed JSON record per line: base64 of `nonce || ciphertext`.
+/// The key lives next to the logs in the app data dir, readable only by the current user.
+pub(crate) fn append_raw_log(
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    text: &str,
+) -> Result<(), String> {
+    let key = load_or_create_key(data_dir)?;
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce_bytes)
+        .map_err(|_| "Failed to generate raw log nonce.".to_string())?;
+    let record = json!({
+        "threadId": thread_id,
+        "turnId": turn_id,
+        "loggedAt": now_ms(),
+        "text": text,
+    });
+    let mut sealed = record.to_string().into_bytes();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce_bytes),
+        Aad::empty(),
+        &mut sealed,
+    )
+    .map_err(|_| "Failed to encrypt raw log record.".to_string())?;
+    let mut line = nonce_bytes.to_vec();
+    line.extend_from_slice(&sealed);
+
+    let path = raw_log_path(data_dir, workspace_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|error| format!("Failed to open raw log: {error}"))?;
+    writeln!(
+        file,
+        "{}",
+        base64::engine::general_purpose::STANDARD.encode(line)
+    )
+    .map_err(|error| format!("Failed to write raw log: {error}"))
+}
+
+fn open_raw_log_line(key: &LessSafeKey, line: &str) -> Result<serde_json::Value, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(line)
+        .map_err(|error| error.to_string())?;
+    if bytes.len() < NONCE_LEN {
+        return Err("Truncated raw log record.".to_string());
+    }
+    let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "bad nonce")?;
+    let mut sealed = sealed.to_vec();
+    let plain = key
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| "decrypt failed")?;
+    serde_json::from_slice(plain).map_err(|error| error.to_string())
+}
+
+/// Drops the records of the given threads from a workspace's raw log, and the log itself
+/// once nothing is left in it. Records that cannot be decrypted are kept.
+pub(crate) fn remove_raw_log_threads(
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_ids: &HashSet<String>,
+) -> Result<(), String> {
+    let path = raw_log_path(data_dir, workspace_id);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(format!("Failed to read raw log: {error}")),
+    };
+    let key = load_or_create_key(data_dir)?;
+    let lines = content.lines().collect::<Vec<_>>();
+    let kept = lines
+        .iter()
+        .filter(|line| {
+            let thread_id = open_raw_log_line(&key, line)
+                .ok()
+                .and_then(|record| record["threadId"].as_str().map(str::to_string));
+            !thread_id.is_some_and(|thread_id| thread_ids.contains(&thread_id))
+        })
+        .collect::<Vec<_>>();
+    if kept.len() == lines.len() {
+        return Ok(());
+    }
+    if kept.is_empty() {
+        return std::fs::remove_file(&path)
+            .map_err(|error| format!("Failed to remove raw log: {error}"));
+    }
+    let content = kept
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect::<String>();
+    std::fs::write(&path, content).map_err(|error| format!("Failed to write raw log: {error}"))
+}
+
+#[cfg(test)]
+fn read_raw_log(data_dir: &Path, workspace_id: &str) -> Result<Vec<serde_json::Value>, String> {
+    let key = load_or_create_key(data_dir)?;
+    let content = std::fs::read_to_string(raw_log_path(data_dir, workspace_id))
+        .map_err(|error| error.to_string())?;
+    content
+        .lines()
+        .map(|line| open_raw_log_line(&key, line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        append_raw_log, read_raw_log, remove_raw_log_threads, validate_redaction_rules,
+        RedactionPipeline,
+    };
+    use crate::types::{AppSettings, RedactionRule};
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    #[test]
+    fn default_rules_redact_keys_but_not_emails() {
+        let pipeline = RedactionPipeline::compile(&AppSettings::default().redaction_rules);
+        let text = "Use sk-ant-REDACTED and mail dev@example.com";
+        assert_eq!(
+            pipeline.redact(text).as_deref(),
+            Some("Use [REDACTED] and mail dev@example.com")
+        );
+        assert!(pipeline.redact("nothing secret here").is_none());
+    }
+
+    #[test]
+    fn replacement_is_literal() {
+        let rule = RedactionRule {
+            id: "ids".to_string(),
+            pattern: r"id-\d+".to_string(),
+            replacement: "$0-hidden".to_string(),
+            enabled: true,
+        };
+        let pipeline = RedactionPipeline::compile(std::slice::from_ref(&rule));
+        assert_eq!(
+            pipeline.redact("see id-42").as_deref(),
+            Some("see $0-hidden")
+        );
+        assert!(validate_redaction_rules(&[RedactionRule {
+            pattern: "(".to_string(),
+            ..rule
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn raw_log_round_trips_through_encryption() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-raw-{}", Uuid::new_v4()));
+        append_raw_log(&dir, "ws", "thread", "turn", "secret sk-123").expect("append");
+        append_raw_log(&dir, "ws", "thread", "turn-2", "second").expect("append");
+        let raw = std::fs::read_to_string(super::raw_log_path(&dir, "ws")).expect("log");
+        assert!(!raw.contains("secret"));
+        let records = read_raw_log(&dir, "ws").expect("decrypt");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["text"], "secret sk-123");
+        assert_eq!(records[1]["turnId"], "turn-2");
+
+        append_raw_log(&dir, "ws", "other", "turn", "kept").expect("append");
+        let purged = HashSet::from(["thread".to_string()]);
+        remove_raw_log_threads(&dir, "ws", &purged).expect("remove");
+        let records = read_raw_log(&dir, "ws").expect("decrypt");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["text"], "kept");
+        remove_raw_log_threads(&dir, "ws", &HashSet::from(["other".to_string()])).expect("remove");
+        assert!(!super::raw_log_path(&dir, "ws").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,201 @@
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::shared::claude_core::{render_turn, ClaudeTurnRecord};
+
+/// Rendered turns kept across every thread before the least recently opened pages go.
+const RENDER_CACHE_TURNS: usize = 2_000;
+/// Turns per page when `resume_thread` is given a `limit` above it.
+pub(crate) const MAX_RESUME_PAGE: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PageKey {
+    workspace_id: String,
+    thread_id: String,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug)]
+struct CachedPage {
+    fingerprint: u64,
+    turns: Vec<Value>,
+    last_used: u64,
+}
+
+/// Turns already rendered for `resume_thread`, by page. Only pages of finished turns are kept:
+/// those only change when messages are pinned or the thread is rewritten, and both show up in
+/// the page's fingerprint.
+#[derive(Debug, Default)]
+pub(crate) struct RenderCache {
+    pages: HashMap<PageKey, CachedPage>,
+    clock: u64,
+    cached_turns: usize,
+}
+
+pub(crate) type RenderCacheStore = Arc<Mutex<RenderCache>>;
+
+/// Cheap stand-in for the rendered page: what can change on a finished turn, without hashing
+/// message text.
+fn page_fingerprint(turns: &[ClaudeTurnRecord]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for turn in turns {
+        turn.id.hash(&mut hasher);
+        turn.completed_at.hash(&mut hasher);
+        turn.checklist.len().hash(&mut hasher);
+        turn.answered_by.hash(&mut hasher);
+        for item in &turn.items {
+            item.id.hash(&mut hasher);
+            item.text.len().hash(&mut hasher);
+            item.pinned.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+impl RenderCache {
+    /// `turns` rendered for the client, where `start` is the index of the first one in the
+    /// thread. Pages holding a running turn are rendered every time.
+    pub(crate) fn render_page(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        start: usize,
+        turns: &[ClaudeTurnRecord],
+    ) -> Vec<Value> {
+        if turns.iter().any(|turn| turn.completed_at.is_none()) {
+            return turns.iter().map(render_turn).collect();
+        }
+        self.clock += 1;
+        let key = PageKey {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            start,
+            end: start + turns.len(),
+        };
+        let fingerprint = page_fingerprint(turns);
+        if let Some(page) = self.pages.get_mut(&key) {
+            if page.fingerprint == fingerprint {
+                page.last_used = self.clock;
+                return page.turns.clone();
+            }
+        }
+        let rendered = turns.iter().map(render_turn).collect::<Vec<_>>();
+        if let Some(stale) = self.pages.remove(&key) {
+            self.cached_turns -= stale.turns.len();
+        }
+        self.cached_turns += rendered.len();
+        self.pages.insert(
+            key,
+            CachedPage {
+                fingerprint,
+                turns: rendered.clone(),
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+        rendered
+    }
+
+    fn evict(&mut self) {
+        while self.cached_turns > RENDER_CACHE_TURNS && self.pages.len() > 1 {
+            let Some(oldest) = self
+                .pages
+                .iter()
+                .min_by_key(|(_, page)| page.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(page) = self.pages.remove(&oldest) {
+                self.cached_turns -= page.turns.len();
+            }
+        }
+    }
+}
+
+/// The `[start, end)` range of turns for a resume page. Without `limit` the whole thread is
+/// one page; otherwise pages are counted back from the newest turn, and `cursor` is the index
+/// the previous page started at, as returned in `nextCursor`.
+pub(crate) fn resume_page_range(
+    turn_count: usize,
+    cursor: Option<&str>,
+    limit: Option<u32>,
+) -> (usize, usize) {
+    let Some(limit) = limit else {
+        return (0, turn_count);
+    };
+    let end = cursor
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(turn_count)
+        .min(turn_count);
+    let size = (limit as usize).clamp(1, MAX_RESUME_PAGE);
+    (end.saturating_sub(size), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resume_page_range, RenderCache, RENDER_CACHE_TURNS};
+    use crate::shared::claude_core::{ClaudeMessageRecord, ClaudeTurnRecord};
+
+    fn turn(index: usize, completed: bool) -> ClaudeTurnRecord {
+        ClaudeTurnRecord {
+            id: format!("turn-{index}"),
+            started_at: index as i64,
+            completed_at: completed.then_some(index as i64),
+            items: vec![ClaudeMessageRecord {
+                id: format!("user-{index}"),
+                role: "user".to_string(),
+                text: format!("question {index}"),
+                pinned: false,
+            }],
+            context_files: Vec::new(),
+            cli_version: None,
+            duration_ms: None,
+            snapshot: None,
+            plan: false,
+            suggestions: Vec::new(),
+            request: None,
+            checklist: Vec::new(),
+            answered_by: None,
+            model_downgrade: None,
+        }
+    }
+
+    #[test]
+    fn pages_count_back_from_the_newest_turn_and_cache_until_changed() {
+        assert_eq!(resume_page_range(500, None, None), (0, 500));
+        assert_eq!(resume_page_range(500, None, Some(50)), (450, 500));
+        assert_eq!(resume_page_range(500, Some("450"), Some(50)), (400, 450));
+        assert_eq!(resume_page_range(500, Some("30"), Some(50)), (0, 30));
+        assert_eq!(resume_page_range(10, Some("99"), Some(0)), (9, 10));
+
+        let mut cache = RenderCache::default();
+        let mut turns = (0..3).map(|index| turn(index, true)).collect::<Vec<_>>();
+        let first = cache.render_page("ws", "thread", 0, &turns);
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[1]["items"][0]["pinned"], false);
+        assert_eq!(cache.cached_turns, 3);
+
+        turns[1].items[0].pinned = true;
+        let pinned = cache.render_page("ws", "thread", 0, &turns);
+        assert_eq!(pinned[1]["items"][0]["pinned"], true);
+        assert_eq!(cache.cached_turns, 3);
+
+        turns.push(turn(3, false));
+        cache.render_page("ws", "thread", 1, &turns[1..]);
+        assert_eq!(cache.pages.len(), 1);
+
+        let big = (0..RENDER_CACHE_TURNS)
+            .map(|index| turn(index, true))
+            .collect::<Vec<_>>();
+        cache.render_page("ws", "other", 0, &big);
+        assert_eq!(cache.pages.len(), 1);
+        assert_eq!(cache.cached_turns, RENDER_CACHE_TURNS);
+    }
+}
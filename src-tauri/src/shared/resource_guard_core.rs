@@ -0,0 +1,190 @@
+use serde_json::{json, Value};
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+
+/// Codex runs against a local Ollama server with `--oss`.
+const OSS_FLAG: &str = "--oss";
+/// Variables that point a provider CLI at a model server.
+const BASE_URL_VARS: &[&str] = &["ANTHROPIC_BASE_URL", "CODEX_OSS_BASE_URL", "OLLAMA_HOST"];
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "0.0.0.0", "[::1]"];
+/// Bytes per parameter for the weights plus runtime buffers. Ollama serves 4-bit weights
+/// unless the tag names another quantization.
+const DEFAULT_BYTES_PER_PARAM: f64 = 0.65;
+const QUANTIZATION_BYTES_PER_PARAM: &[(&str, f64)] = &[
+    ("fp16", 2.1),
+    ("bf16", 2.1),
+    ("f16", 2.1),
+    ("q8", 1.1),
+    ("q6", 0.85),
+    ("q5", 0.75),
+    ("q3", 0.5),
+    ("q2", 0.4),
+];
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// System memory when a turn starts. `sysinfo` reports no GPU memory, but Ollama moves the
+/// layers that don't fit in VRAM to system memory, so system memory bounds what can load.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MemorySnapshot {
+    pub(crate) total_bytes: u64,
+    pub(crate) available_bytes: u64,
+}
+
+impl MemorySnapshot {
+    pub(crate) fn current() -> Self {
+        let system = System::new_with_specifics(
+            RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+        );
+        Self {
+            total_bytes: system.total_memory(),
+            available_bytes: system.available_memory(),
+        }
+    }
+}
+
+fn is_loopback_url(url: &str) -> bool {
+    let rest = url
+        .trim()
+        .split_once("://")
+        .map_or(url.trim(), |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.chars().all(|ch| ch.is_ascii_digit()) => {
+            host
+        }
+        _ => authority,
+    };
+    LOOPBACK_HOSTS.contains(&host.to_ascii_lowercase().as_str())
+}
+
+/// Whether a provider's CLI is set up to run models on this machine: Codex with `--oss`, or
+/// any CLI whose base URL points at localhost.
+pub(crate) fn uses_local_models(args: Option<&str>, env: &[(String, String)]) -> bool {
+    let oss = args
+        .and_then(|args| shell_words::split(args).ok())
+        .is_some_and(|args| args.iter().any(|arg| arg == OSS_FLAG));
+    oss || env
+        .iter()
+        .any(|(name, value)| BASE_URL_VARS.contains(&name.as_str()) && is_loopback_url(value))
+}
+
+/// Billions of parameters from a size tag such as `70b`, `1.5b`, `8x7b` or `500m`.
+fn parameter_count(tag: &str) -> Option<f64> {
+    let (experts, size) = match tag.split_once('x') {
+        Some((experts, size)) => (experts.parse::<f64>().ok()?, size),
+        None => (1.0, tag),
+    };
+    let billions = if let Some(count) = size.strip_suffix('b') {
+        count.parse::<f64>().ok()?
+    } else {
+        size.strip_suffix('m')?.parse::<f64>().ok()? / 1000.0
+    };
+    Some(experts * billions)
+}
+
+/// Rough memory a model needs, read from its name (`qwen2.5-coder:32b`,
+/// `llama3.1:70b-instruct-q8_0`). `None` when the name carries no size.
+pub(crate) fn estimate_model_bytes(model: &str) -> Option<u64> {
+    let model = model.to_ascii_lowercase();
+    let tags = model
+        .split([':', '-', '_', '/'])
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    let billions = tags.iter().find_map(|tag| parameter_count(tag))?;
+    let bytes_per_param = tags
+        .iter()
+        .find_map(|tag| {
+            QUANTIZATION_BYTES_PER_PARAM
+                .iter()
+                .find(|(prefix, _)| tag.starts_with(prefix))
+                .map(|(_, bytes)| *bytes)
+        })
+        .unwrap_or(DEFAULT_BYTES_PER_PARAM);
+    Some((billions * 1e9 * bytes_per_param).round() as u64)
+}
+
+fn gib(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / GIB)
+}
+
+/// Checks a local model against system memory before its turn starts. A model larger than
+/// all memory is refused with a JSON error whose `code` is `modelTooLarge`; one larger than
+/// the memory free right now returns the params for a `provider/resourceWarning` event.
+pub(crate) fn check_model_fit(
+    model: &str,
+    memory: MemorySnapshot,
+) -> Result<Option<Value>, String> {
+    let Some(required_bytes) = estimate_model_bytes(model) else {
+        return Ok(None);
+    };
+    let details = |message: String| {
+        json!({
+            "model": model,
+            "requiredBytes": required_bytes,
+            "availableBytes": memory.available_bytes,
+            "totalBytes": memory.total_bytes,
+            "message": message,
+        })
+    };
+    if memory.total_bytes > 0 && required_bytes > memory.total_bytes {
+        let mut error = details(format!(
+            "{model} needs about {} of memory, but this machine has {}.",
+            gib(required_bytes),
+            gib(memory.total_bytes)
+        ));
+        error["code"] = json!("modelTooLarge");
+        return Err(error.to_string());
+    }
+    if required_bytes > memory.available_bytes {
+        return Ok(Some(details(format!(
+            "{model} needs about {} of memory and only {} is free; it may load slowly or fail.",
+            gib(required_bytes),
+            gib(memory.available_bytes)
+        ))));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_model_fit, estimate_model_bytes, uses_local_models, MemorySnapshot};
+    use serde_json::Value;
+
+    const GB: u64 = 1_000_000_000;
+
+    #[test]
+    fn refuses_local_models_that_cannot_fit() {
+        let env = |url: &str| vec![("ANTHROPIC_BASE_URL".to_string(), url.to_string())];
+        assert!(uses_local_models(Some("--oss -m gpt-oss:20b"), &[]));
+        assert!(uses_local_models(None, &env("http://localhost:11434")));
+        assert!(uses_local_models(None, &env("http://127.0.0.1:11434/v1")));
+        assert!(!uses_local_models(None, &env("https://api.anthropic.com")));
+        assert!(!uses_local_models(Some("--ossify"), &[]));
+
+        assert_eq!(estimate_model_bytes("llama3.1:70b"), Some(45_500_000_000));
+        assert_eq!(
+            estimate_model_bytes("llama3.1:8b-instruct-q8_0"),
+            Some(8_800_000_000)
+        );
+        assert_eq!(estimate_model_bytes("mixtral:8x7b"), Some(36_400_000_000));
+        assert_eq!(
+            estimate_model_bytes("qwen2.5-coder:500m"),
+            Some(325_000_000)
+        );
+        assert_eq!(estimate_model_bytes("claude-sonnet-4-5"), None);
+
+        let memory = MemorySnapshot {
+            total_bytes: 32 * GB,
+            available_bytes: 12 * GB,
+        };
+        assert_eq!(check_model_fit("qwen2.5-coder:7b", memory), Ok(None));
+        let warning = check_model_fit("qwen2.5-coder:32b", memory)
+            .expect("fits")
+            .expect("warning");
+        assert_eq!(warning["requiredBytes"], 20_800_000_000u64);
+        assert!(warning.get("code").is_none());
+        let error = check_model_fit("llama3.1:70b", memory).expect_err("too large");
+        let error: Value = serde_json::from_str(&error).expect("json error");
+        assert_eq!(error["code"], "modelTooLarge");
+        assert_eq!(error["model"], "llama3.1:70b");
+    }
+}
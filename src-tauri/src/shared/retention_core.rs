@@ -0,0 +1,358 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    bulk_thread_op_core, claude_project_dir_for_workspace, legacy_prefixed_session_id, now_ms,
+    BulkThreadOp, ClaudeThreadRecord, ClaudeThreadsStore,
+};
+use crate::shared::redaction_core::RedactionPipeline;
+use crate::shared::trash_core::{purge_expired_trash, TrashReason, TrashedThread};
+use crate::types::{AppSettings, RetentionAction, WorkspaceEntry};
+
+pub(crate) const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RetentionPolicy {
+    max_age_days: Option<u32>,
+    max_threads: Option<u32>,
+    action: RetentionAction,
+}
+
+impl RetentionPolicy {
+    fn from_settings(settings: &AppSettings) -> Option<Self> {
+        if settings.read_only {
+            return None;
+        }
+        let policy = Self {
+            max_age_days: settings
+                .retention_max_thread_age_days
+                .filter(|days| *days > 0),
+            max_threads: settings
+                .retention_max_threads_per_workspace
+                .filter(|count| *count > 0),
+            action: settings.retention_action,
+        };
+        (policy.max_age_days.is_some() || policy.max_threads.is_some()).then_some(policy)
+    }
+}
+
+/// Threads past the age limit, plus the least recently updated ones beyond the count limit.
+/// Threads with a turn still in flight are never selected.
+fn select_expired_threads(
+    threads: &[ClaudeThreadRecord],
+    now: i64,
+    policy: &RetentionPolicy,
+) -> Vec<String> {
+    let mut candidates = threads
+        .iter()
+        .filter(|thread| !thread.has_open_turn())
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|thread| std::cmp::Reverse(thread.updated_at));
+    let cutoff = policy
+        .max_age_days
+        .map(|days| now - i64::from(days) * DAY_MS);
+    let keep = policy
+        .max_threads
+        .map(|count| count as usize)
+        .unwrap_or(usize::MAX);
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter(|(index, thread)| {
+            *index >= keep || cutoff.is_some_and(|cutoff| thread.updated_at < cutoff)
+        })
+        .map(|(_, thread)| thread.id.clone())
+        .collect()
+}
+
+/// Removes the Claude CLI transcripts (and their sidecar directories) backing a thread.
+fn remove_session_logs(project_dir: &Path, thread: &ClaudeThreadRecord) {
+    let mut session_ids = vec![thread.id.clone()];
+    session_ids.extend(legacy_prefixed_session_id(&thread.id));
+    session_ids.extend(thread.session_id.clone());
+    session_ids.extend(thread.provider_session_id.clone());
+    for session_id in session_ids {
+        let _ = std::fs::remove_file(project_dir.join(format!("{session_id}.jsonl")));
+        let _ = std::fs::remove_dir_all(project_dir.join(&session_id));
+    }
+}
+
+/// Removes the CLI session logs of deleted threads once they are purged from the trash.
+fn remove_purged_session_logs(workspace_paths: &HashMap<String, String>, purged: &[TrashedThread]) {
+    for entry in purged {
+        if entry.reason != TrashReason::Delete {
+            continue;
+        }
+        let Some(project_dir) = workspace_paths
+            .get(&entry.workspace_id)
+            .and_then(|path| claude_project_dir_for_workspace(path))
+        else {
+            continue;
+        };
+        remove_session_logs(&project_dir, &entry.thread);
+    }
+}
+
+/// Applies the configured retention policy to every workspace's Claude threads and purges
+/// expired trash entries.
+pub(crate) async fn apply_retention_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    event_sink: E,
+) -> Result<Value, String> {
+    let (policy, trash_retention_days, delete_session_logs) = {
+        let settings = app_settings.lock().await;
+        let trash_retention_days = (!settings.read_only).then_some(settings.trash_retention_days);
+        (
+            RetentionPolicy::from_settings(&settings),
+            trash_retention_days,
+            settings.retention_delete_session_logs,
+        )
+    };
+    let purged = match trash_retention_days {
+        Some(days) => purge_expired_trash(claude_threads_path, days)?,
+        None => Vec::new(),
+    };
+    if delete_session_logs && !purged.is_empty() {
+        let workspace_paths = {
+            let workspaces = workspaces.lock().await;
+            workspaces
+                .iter()
+                .map(|(id, entry)| (id.clone(), entry.path.clone()))
+                .collect::<HashMap<_, _>>()
+        };
+        remove_purged_session_logs(&workspace_paths, &purged);
+    }
+    let purged = purged.len();
+    let Some(policy) = policy else {
+        return Ok(json!({ "result": { "processed": 0, "purged": purged } }));
+    };
+    let now = now_ms();
+    let expired = {
+        let store = claude_threads.lock().await;
+        store
+            .iter()
+            .filter_map(|(workspace_id, threads)| {
+                let ids = select_expired_threads(threads, now, &policy);
+                if ids.is_empty() {
+                    return None;
+                }
+                let records = threads
+                    .iter()
+                    .filter(|thread| ids.contains(&thread.id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                Some((workspace_id.clone(), records))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut processed = 0usize;
+    for (workspace_id, records) in expired {
+        let op = match policy.action {
+            RetentionAction::Archive => BulkThreadOp::Archive,
+            RetentionAction::Delete => BulkThreadOp::Delete,
+        };
+        let thread_ids = records.iter().map(|thread| thread.id.clone()).collect();
+        bulk_thread_op_core(
+            claude_threads,
+            claude_threads_path,
+            workspace_id.clone(),
+            thread_ids,
+            op,
+            &RedactionPipeline::default(),
+            event_sink.clone(),
+        )
+        .await?;
+        processed += records.len();
+    }
+    Ok(json!({ "result": { "processed": processed, "purged": purged } }))
+}
+
+pub(crate) fn path_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| path_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Reports how much disk each workspace's threads and Claude session logs take up.
+pub(crate) async fn storage_usage_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+) -> Result<Value, String> {
+    let entries = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .values()
+            .map(|entry| (entry.id.clone(), entry.name.clone(), entry.path.clone()))
+            .collect::<Vec<_>>()
+    };
+    let thread_usage = {
+        let store = claude_threads.lock().await;
+        store
+            .iter()
+            .map(|(workspace_id, threads)| {
+                let bytes = serde_json::to_vec(threads)
+                    .map(|data| data.len() as u64)
+                    .unwrap_or(0);
+                (workspace_id.clone(), (threads.len(), bytes))
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let data_dir = claude_threads_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let (log_sizes, data_dir_bytes) = tokio::task::spawn_blocking({
+        let data_dir = data_dir.clone();
+        let paths = entries
+            .iter()
+            .map(|(id, _, path)| (id.clone(), path.clone()))
+            .collect::<Vec<_>>();
+        move || {
+            let log_sizes = paths
+                .into_iter()
+                .map(|(id, path)| {
+                    let size = claude_project_dir_for_workspace(&path)
+                        .map(|dir| path_size(&dir))
+                        .unwrap_or(0);
+                    (id, size)
+                })
+                .collect::<HashMap<_, _>>();
+            (log_sizes, path_size(&data_dir))
+        }
+    })
+    .await
+    .map_err(|error| format!("failed to measure storage: {error}"))?;
+
+    let mut total_bytes = 0u64;
+    let mut usage = entries
+        .into_iter()
+        .map(|(id, name, _)| {
+            let (threads, thread_bytes) = thread_usage.get(&id).copied().unwrap_or((0, 0));
+            let session_log_bytes = log_sizes.get(&id).copied().unwrap_or(0);
+            total_bytes += thread_bytes + session_log_bytes;
+            json!({
+                "workspaceId": id,
+                "name": name,
+                "threads": threads,
+                "threadBytes": thread_bytes,
+                "sessionLogBytes": session_log_bytes,
+                "totalBytes": thread_bytes + session_log_bytes,
+            })
+        })
+        .collect::<Vec<_>>();
+    usage.sort_by_key(|entry| std::cmp::Reverse(entry["totalBytes"].as_u64().unwrap_or(0)));
+    Ok(json!({
+        "result": {
+            "workspaces": usage,
+            "dataDir": data_dir.to_string_lossy(),
+            "dataDirBytes": data_dir_bytes,
+            "totalBytes": total_bytes,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_expired_threads, RetentionPolicy, DAY_MS};
+    use crate::shared::claude_core::{ClaudeThreadRecord, ClaudeTurnRecord, GenerationParams};
+    use crate::types::{AppSettings, RetentionAction};
+
+    fn thread(id: &str, updated_at: i64, running: bool) -> ClaudeThreadRecord {
+        ClaudeThreadRecord {
+            id: id.to_string(),
+            cwd: "/repo".to_string(),
+            preview: String::new(),
+            created_at: updated_at,
+            updated_at,
+            name: None,
+            tags: Vec::new(),
+            session_id: None,
+            session_needs_seed: false,
+            provider_session_id: None,
+            cwd_override: None,
+            generation: GenerationParams::default(),
+            worktree: None,
+            summary: None,
+            stats: None,
+            last_read_at: None,
+            turns: vec![ClaudeTurnRecord {
+                id: format!("{id}-turn"),
+                started_at: updated_at,
+                completed_at: (!running).then_some(updated_at),
+                items: Vec::new(),
+                context_files: Vec::new(),
+                cli_version: None,
+                duration_ms: None,
+                snapshot: None,
+                plan: false,
+                suggestions: Vec::new(),
+                checklist: Vec::new(),
+                answered_by: None,
+                model_downgrade: None,
+                request: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn select_expired_threads_applies_age_and_count_limits() {
+        let now = 100 * DAY_MS;
+        let threads = vec![
+            thread("fresh", now, false),
+            thread("recent", now - DAY_MS, false),
+            thread("stale", now - 40 * DAY_MS, false),
+            thread("stale-running", now - 50 * DAY_MS, true),
+        ];
+        let by_age = RetentionPolicy {
+            max_age_days: Some(30),
+            max_threads: None,
+            action: RetentionAction::Archive,
+        };
+        assert_eq!(
+            select_expired_threads(&threads, now, &by_age),
+            vec!["stale"]
+        );
+
+        let by_count = RetentionPolicy {
+            max_age_days: None,
+            max_threads: Some(1),
+            action: RetentionAction::Delete,
+        };
+        assert_eq!(
+            select_expired_threads(&threads, now, &by_count),
+            vec!["recent", "stale"]
+        );
+
+        let mut settings = AppSettings {
+            retention_max_threads_per_workspace: Some(0),
+            retention_action: RetentionAction::Delete,
+            ..AppSettings::default()
+        };
+        assert_eq!(RetentionPolicy::from_settings(&settings), None);
+        settings.retention_max_thread_age_days = Some(30);
+        let policy = RetentionPolicy::from_settings(&settings).expect("age limit applies");
+        assert_eq!(policy.max_threads, None);
+    }
+}
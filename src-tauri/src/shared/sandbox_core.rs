@@ -0,0 +1,293 @@
+use std::ffi::OsStr;
+
+use tokio::process::Command;
+
+use crate::shared::file_access_core;
+use crate::shared::process_core::tokio_command;
+use crate::types::{SandboxSettings, WorkspaceEntry};
+
+const CODEX_MODES: &[&str] = &["read-only", "workspace-write", "full-access"];
+const CLAUDE_SKIP_PERMISSIONS_ARG: &str = "--dangerously-skip-permissions";
+/// Built-in Claude Code tools; MCP tools are named `mcp__<server>` or `mcp__<server>__<tool>`.
+const CLAUDE_TOOLS: &[&str] = &[
+    "Agent",
+    "Bash",
+    "BashOutput",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "KillShell",
+    "LS",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Read",
+    "SlashCommand",
+    "Task",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+const MCP_TOOL_PREFIX: &str = "mcp__";
+/// Kept with `restrictEnv`: enough for the CLI to find its tools, config and credentials.
+const BASELINE_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TERM",
+    "XDG_CONFIG_HOME",
+    "SystemRoot",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+/// Fresh user and network namespaces: the child only sees a loopback interface that is down.
+/// `--map-current-user` needs util-linux 2.38 or newer.
+#[cfg(target_os = "linux")]
+const NO_NETWORK_WRAPPER: (&str, &[&str]) =
+    ("unshare", &["--user", "--map-current-user", "--net", "--"]);
+/// Denies network access except loopback, so a local API proxy keeps working.
+#[cfg(target_os = "macos")]
+const NO_NETWORK_WRAPPER: (&str, &[&str]) = (
+    "sandbox-exec",
+    &[
+        "-p",
+        "(version 1)(allow default)(deny network*)(allow network* (remote ip \"localhost:*\"))",
+    ],
+);
+
+/// The workspace's sandbox; worktrees without their own inherit the parent's. A worktree
+/// with its own sandbox still takes the parent's Claude tool lists when it leaves them empty.
+pub(crate) fn resolve_sandbox(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> SandboxSettings {
+    let Some(parent) = parent_entry.filter(|_| entry.kind.is_worktree()) else {
+        return entry.settings.sandbox.clone();
+    };
+    if entry.settings.sandbox.is_default() {
+        return parent.settings.sandbox.clone();
+    }
+    let mut sandbox = entry.settings.sandbox.clone();
+    if sandbox.claude_allowed_tools.is_empty() {
+        sandbox.claude_allowed_tools = parent.settings.sandbox.claude_allowed_tools.clone();
+    }
+    if sandbox.claude_disallowed_tools.is_empty() {
+        sandbox.claude_disallowed_tools = parent.settings.sandbox.claude_disallowed_tools.clone();
+    }
+    sandbox
+}
+
+/// Checks a rule like `Read`, `Bash(git diff:*)` or `mcp__github__create_issue`. Rules are
+/// passed comma-separated, so they can't contain commas.
+fn validate_claude_tool_rule(rule: &str) -> Result<(), String> {
+    let rule = rule.trim();
+    if rule.is_empty() {
+        return Err("Claude tool rules can't be blank.".to_string());
+    }
+    if rule.contains(',') {
+        return Err(format!("Claude tool rule `{rule}` can't contain commas."));
+    }
+    let name = match rule.split_once('(') {
+        Some((name, pattern)) => {
+            let pattern = pattern
+                .strip_suffix(')')
+                .ok_or_else(|| format!("Claude tool rule `{rule}` is missing a closing `)`."))?;
+            if pattern.trim().is_empty() {
+                return Err(format!("Claude tool rule `{rule}` has an empty pattern."));
+            }
+            name
+        }
+        None => rule,
+    };
+    let known = CLAUDE_TOOLS.contains(&name)
+        || name
+            .strip_prefix(MCP_TOOL_PREFIX)
+            .is_some_and(|tool| !tool.is_empty() && !tool.contains(char::is_whitespace));
+    if !known {
+        return Err(format!("Unknown Claude tool `{name}`."));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_sandbox(sandbox: &SandboxSettings) -> Result<(), String> {
+    if let Some(mode) = sandbox.codex_mode.as_deref() {
+        if !CODEX_MODES.contains(&mode) {
+            return Err(format!("Unknown Codex sandbox mode `{mode}`."));
+        }
+    }
+    if sandbox.no_network && !cfg!(any(target_os = "linux", target_os = "macos")) {
+        return Err("Blocking network access is only supported on Linux and macOS.".to_string());
+    }
+    if let Some(name) = sandbox
+        .allowed_env
+        .iter()
+        .find(|name| name.trim().is_empty() || name.contains('='))
+    {
+        return Err(format!("Invalid environment variable name `{name}`."));
+    }
+    for rule in sandbox
+        .claude_allowed_tools
+        .iter()
+        .chain(&sandbox.claude_disallowed_tools)
+    {
+        validate_claude_tool_rule(rule)?;
+    }
+    if let Some(rule) = sandbox.claude_allowed_tools.iter().find(|rule| {
+        sandbox
+            .claude_disallowed_tools
+            .iter()
+            .any(|denied| denied.trim() == rule.trim())
+    }) {
+        return Err(format!(
+            "Claude tool rule `{}` is both allowed and disallowed.",
+            rule.trim()
+        ));
+    }
+    file_access_core::validate_file_rules(sandbox)
+}
+
+/// Codex access mode for a turn; a mode forced by the workspace wins over the composer's.
+pub(crate) fn codex_access_mode(
+    sandbox: &SandboxSettings,
+    requested: Option<String>,
+) -> Option<String> {
+    sandbox.codex_mode.clone().or(requested)
+}
+
+pub(crate) fn claude_sandbox_args(sandbox: &SandboxSettings) -> Vec<String> {
+    let mut args = Vec::new();
+    if sandbox.claude_skip_permissions {
+        args.push(CLAUDE_SKIP_PERMISSIONS_ARG.to_string());
+    }
+    for (flag, rules) in [
+        ("--allowedTools", &sandbox.claude_allowed_tools),
+        ("--disallowedTools", &sandbox.claude_disallowed_tools),
+    ] {
+        let rules = rules
+            .iter()
+            .map(|rule| rule.trim())
+            .filter(|rule| !rule.is_empty())
+            .collect::<Vec<_>>();
+        if !rules.is_empty() {
+            args.extend([flag.to_string(), rules.join(",")]);
+        }
+    }
+    args
+}
+
+/// A command for `program`, run through the OS network sandbox when `noNetwork` is set.
+pub(crate) fn sandboxed_command(
+    program: impl AsRef<OsStr>,
+    sandbox: &SandboxSettings,
+) -> Result<Command, String> {
+    if !sandbox.no_network {
+        return Ok(tokio_command(program));
+    }
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let (wrapper, wrapper_args) = NO_NETWORK_WRAPPER;
+        let mut command = tokio_command(wrapper);
+        command.args(wrapper_args).arg(program);
+        Ok(command)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    Err("Blocking network access is only supported on Linux and macOS.".to_string())
+}
+
+/// With `restrictEnv`, drops the inherited environment down to the baseline and `allowedEnv`.
+/// Call before adding the provider's own variables so those still reach the agent.
+pub(crate) fn restrict_env(command: &mut Command, sandbox: &SandboxSettings) {
+    if !sandbox.restrict_env {
+        return;
+    }
+    command.env_clear();
+    let names = BASELINE_ENV
+        .iter()
+        .copied()
+        .chain(sandbox.allowed_env.iter().map(|name| name.trim()));
+    for name in names {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        claude_sandbox_args, codex_access_mode, restrict_env, validate_sandbox, BASELINE_ENV,
+    };
+    use crate::shared::process_core::tokio_command;
+    use crate::types::SandboxSettings;
+
+    #[test]
+    fn sandbox_settings_map_to_provider_flags_and_env() {
+        let mut sandbox = SandboxSettings::default();
+        assert!(claude_sandbox_args(&sandbox).is_empty());
+        assert_eq!(
+            codex_access_mode(&sandbox, Some("full-access".to_string())).as_deref(),
+            Some("full-access")
+        );
+
+        sandbox.codex_mode = Some("read-only".to_string());
+        sandbox.claude_skip_permissions = true;
+        sandbox.restrict_env = true;
+        sandbox.allowed_env = vec!["RUST_LOG".to_string()];
+        assert!(validate_sandbox(&sandbox).is_ok());
+        assert_eq!(
+            codex_access_mode(&sandbox, Some("full-access".to_string())).as_deref(),
+            Some("read-only")
+        );
+        assert_eq!(
+            claude_sandbox_args(&sandbox),
+            vec!["--dangerously-skip-permissions"]
+        );
+
+        let mut command = tokio_command("claude");
+        restrict_env(&mut command, &sandbox);
+        let names = command
+            .as_std()
+            .get_envs()
+            .map(|(name, _)| name.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"PATH".to_string()));
+        assert!(names
+            .iter()
+            .all(|name| BASELINE_ENV.contains(&name.as_str()) || name == "RUST_LOG"));
+
+        sandbox.claude_skip_permissions = false;
+        sandbox.claude_allowed_tools = vec!["Read".to_string(), " Bash(git diff:*) ".to_string()];
+        sandbox.claude_disallowed_tools = vec!["WebFetch".to_string(), "mcp__github".to_string()];
+        assert!(validate_sandbox(&sandbox).is_ok());
+        assert_eq!(
+            claude_sandbox_args(&sandbox),
+            vec![
+                "--allowedTools",
+                "Read,Bash(git diff:*)",
+                "--disallowedTools",
+                "WebFetch,mcp__github"
+            ]
+        );
+        for rule in ["Shell", "Bash(", "Bash()", "Read,Write", "mcp__", " "] {
+            sandbox.claude_disallowed_tools = vec![rule.to_string()];
+            assert!(validate_sandbox(&sandbox).is_err(), "{rule:?}");
+        }
+        sandbox.claude_disallowed_tools = vec!["Read".to_string()];
+        assert!(validate_sandbox(&sandbox).is_err());
+        sandbox.claude_disallowed_tools.clear();
+
+        sandbox.codex_mode = Some("yolo".to_string());
+        assert!(validate_sandbox(&sandbox).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use tokio::sync::Mutex;
+
+use crate::storage::write_workspaces;
+use crate::types::WorkspaceEntry;
+
+/// Keyring service secret values are filed under, as `<workspace id>/<name>`.
+const KEYRING_SERVICE: &str = "PolyCode";
+
+static PLACEHOLDER_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// `{{secret:NAME}}`.
+fn placeholder_pattern() -> &'static Regex {
+    PLACEHOLDER_PATTERN.get_or_init(|| {
+        Regex::new(r"\{\{secret:([A-Za-z0-9_-]+)\}\}").expect("valid placeholder pattern")
+    })
+}
+
+fn placeholder(name: &str) -> String {
+    format!("{{{{secret:{name}}}}}")
+}
+
+pub(crate) fn validate_secret_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Secret names use letters, digits, `_` and `-`; got `{name}`."
+        ))
+    }
+}
+
+/// Where secret values are read from: the OS keyring, or a map in tests.
+pub(crate) trait SecretStore {
+    fn get(&self, scope: &str, name: &str) -> Result<Option<String>, String>;
+}
+
+pub(crate) struct KeyringSecrets;
+
+fn keyring_entry(scope: &str, name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("{scope}/{name}"))
+        .map_err(|error| format!("Failed to open the keyring: {error}"))
+}
+
+impl SecretStore for KeyringSecrets {
+    fn get(&self, scope: &str, name: &str) -> Result<Option<String>, String> {
+        match keyring_entry(scope, name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(format!("Failed to read secret `{name}`: {error}")),
+        }
+    }
+}
+
+/// Secrets are shared by a workspace and its worktrees.
+fn secret_scope(entry: &WorkspaceEntry) -> &str {
+    entry.parent_id.as_deref().unwrap_or(&entry.id)
+}
+
+/// The values behind a prompt's placeholders. They fill the prompt only on its way to the
+/// agent; the turn stores the prompt as written and output with the values masked back.
+#[derive(Debug, Default)]
+pub(crate) struct ResolvedSecrets {
+    values: Vec<(String, String)>,
+}
+
+impl ResolvedSecrets {
+    pub(crate) fn resolve(
+        text: &str,
+        entry: &WorkspaceEntry,
+        store: &impl SecretStore,
+    ) -> Result<Self, String> {
+        let mut values: Vec<(String, String)> = Vec::new();
+        for captures in placeholder_pattern().captures_iter(text) {
+            let name = &captures[1];
+            if values.iter().any(|(known, _)| known == name) {
+                continue;
+            }
+            let value = store
+                .get(secret_scope(entry), name)?
+                .ok_or_else(|| format!("Secret `{name}` isn't set for this workspace."))?;
+            values.push((name.to_string(), value));
+        }
+        // Longest first, so a value containing another is masked whole.
+        values.sort_by_key(|(_, value)| Reverse(value.len()));
+        Ok(Self { values })
+    }
+
+    pub(crate) fn fill(&self, text: &str) -> String {
+        placeholder_pattern()
+            .replace_all(text, |captures: &regex::Captures| {
+                self.values
+                    .iter()
+                    .find(|(name, _)| name == &captures[1])
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_else(|| captures[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// Puts placeholders back wherever a value shows up, such as an agent echoing a token.
+    pub(crate) fn mask(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .fold(text.to_string(), |masked, (name, value)| {
+                masked.replace(value.as_str(), &placeholder(name))
+            })
+    }
+}
+
+/// Records which secret names a workspace has; the keyring can't list them.
+async fn update_secret_names(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    storage_path: &PathBuf,
+    scope: &str,
+    update: impl FnOnce(&mut Vec<String>),
+) -> Result<Vec<String>, String> {
+    let (names, list) = {
+        let mut workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get_mut(scope)
+            .ok_or_else(|| "workspace not found".to_string())?;
+        update(&mut entry.settings.secret_names);
+        entry.settings.secret_names.sort();
+        entry.settings.secret_names.dedup();
+        let names = entry.settings.secret_names.clone();
+        (names, workspaces.values().cloned().collect::<Vec<_>>())
+    };
+    write_workspaces(storage_path, &list)?;
+    Ok(names)
+}
+
+async fn workspace_scope(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<String, String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| "workspace not found".to_string())?;
+    Ok(secret_scope(entry).to_string())
+}
+
+/// Stores a secret for `{{secret:NAME}}` placeholders in the workspace's prompts. Only the
+/// name is saved with the workspace; the value goes to the OS keyring.
+pub(crate) async fn set_secret_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    storage_path: &PathBuf,
+    workspace_id: String,
+    name: String,
+    value: String,
+) -> Result<Value, String> {
+    validate_secret_name(&name)?;
+    if value.is_empty() {
+        return Err("Secret value can't be empty.".to_string());
+    }
+    let scope = workspace_scope(workspaces, &workspace_id).await?;
+    keyring_entry(&scope, &name)?
+        .set_password(&value)
+        .map_err(|error| format!("Failed to save secret `{name}`: {error}"))?;
+    let names = update_secret_names(workspaces, storage_path, &scope, |names| {
+        names.push(name);
+    })
+    .await?;
+    Ok(json!({ "result": { "names": names } }))
+}
+
+pub(crate) async fn delete_secret_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    storage_path: &PathBuf,
+    workspace_id: String,
+    name: String,
+) -> Result<Value, String> {
+    let scope = workspace_scope(workspaces, &workspace_id).await?;
+    match keyring_entry(&scope, &name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(error) => return Err(format!("Failed to delete secret `{name}`: {error}")),
+    }
+    let names = update_secret_names(workspaces, storage_path, &scope, |names| {
+        names.retain(|known| known != &name);
+    })
+    .await?;
+    Ok(json!({ "result": { "names": names } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_secret_name, ResolvedSecrets, SecretStore};
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use std::collections::HashMap;
+
+    struct MapSecrets(HashMap<(String, String), String>);
+
+    impl SecretStore for MapSecrets {
+        fn get(&self, scope: &str, name: &str) -> Result<Option<String>, String> {
+            Ok(self.0.get(&(scope.to_string(), name.to_string())).cloned())
+        }
+    }
+
+    #[test]
+    fn fills_placeholders_and_masks_values_back() {
+        let store = MapSecrets(HashMap::from([
+            (
+                ("ws-1".to_string(), "API_TOKEN".to_string()),
+                "tok-123".to_string(),
+            ),
+            (
+                ("ws-1".to_string(), "DB".to_string()),
+                "postgres://u:tok-123@db".to_string(),
+            ),
+        ]));
+        let worktree = WorkspaceEntry {
+            id: "wt-1".to_string(),
+            name: "feature".to_string(),
+            path: "/tmp/wt-1".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("ws-1".to_string()),
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+        };
+
+        let prompt = "Use {{secret:API_TOKEN}} against {{secret:DB}}, not {{secret:API_TOKEN}}.";
+        let secrets = ResolvedSecrets::resolve(prompt, &worktree, &store).expect("resolved");
+        let filled = secrets.fill(prompt);
+        assert_eq!(
+            filled,
+            "Use tok-123 against postgres://u:tok-123@db, not tok-123."
+        );
+        assert_eq!(secrets.mask(&filled), prompt);
+        assert_eq!(secrets.fill("{{secret:OTHER}}"), "{{secret:OTHER}}");
+
+        let missing = ResolvedSecrets::resolve("{{secret:NOPE}}", &worktree, &store);
+        assert_eq!(
+            missing.expect_err("missing").as_str(),
+            "Secret `NOPE` isn't set for this workspace."
+        );
+        assert!(
+            ResolvedSecrets::resolve("no placeholders", &worktree, &store)
+                .expect("plain")
+                .values
+                .is_empty()
+        );
+
+        assert!(validate_secret_name("GITHUB_TOKEN").is_ok());
+        assert!(validate_secret_name("bad name").is_err());
+        assert!(validate_secret_name("").is_err());
+    }
+}
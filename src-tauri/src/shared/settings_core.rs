@@ -1,11 +1,56 @@
 use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
+use tokio::sync::broadcast::{self, error::TryRecvError};
 use tokio::sync::Mutex;
 
 use crate::codex::config as codex_config;
+use crate::providers;
+use crate::shared::output_filters_core::validate_output_filters;
+use crate::shared::redaction_core::validate_redaction_rules;
 use crate::storage::write_settings;
 use crate::types::AppSettings;
 
+/// Changes a slow listener can fall behind by before it skips to newer ones.
+const SETTINGS_CHANGES_CAPACITY: usize = 16;
+
+static SETTINGS_CHANGES: OnceLock<broadcast::Sender<SettingsChange>> = OnceLock::new();
+
+/// One saved update of the app settings, sent to everything that caches values derived from
+/// them: app-server sessions and turns still waiting for a slot.
+#[derive(Debug, Clone)]
+pub(crate) struct SettingsChange {
+    pub(crate) previous: Arc<AppSettings>,
+    pub(crate) current: Arc<AppSettings>,
+}
+
+fn settings_changes() -> &'static broadcast::Sender<SettingsChange> {
+    SETTINGS_CHANGES.get_or_init(|| broadcast::channel(SETTINGS_CHANGES_CAPACITY).0)
+}
+
+pub(crate) fn subscribe_settings_changes() -> broadcast::Receiver<SettingsChange> {
+    settings_changes().subscribe()
+}
+
+fn publish_settings_change(change: SettingsChange) {
+    // Nobody listening is fine; the next subscriber reads the settings when it needs them.
+    let _ = settings_changes().send(change);
+}
+
+/// The newest settings sent since `changes` last looked, without waiting.
+pub(crate) fn latest_settings(
+    changes: &mut broadcast::Receiver<SettingsChange>,
+) -> Option<Arc<AppSettings>> {
+    let mut latest = None;
+    loop {
+        match changes.try_recv() {
+            Ok(change) => latest = Some(change.current),
+            Err(TryRecvError::Lagged(_)) => continue,
+            Err(TryRecvError::Empty | TryRecvError::Closed) => return latest,
+        }
+    }
+}
+
 fn normalize_personality(value: &str) -> Option<&'static str> {
     match value.trim() {
         "friendly" => Some("friendly"),
@@ -14,6 +59,22 @@ fn normalize_personality(value: &str) -> Option<&'static str> {
     }
 }
 
+/// Mutating operations fail with this while `readOnly` is on; the `PermissionDenied:` prefix
+/// lets clients tell it apart from ordinary errors.
+pub(crate) fn read_only_error(action: &str) -> String {
+    format!("PermissionDenied: {action} is disabled while PolyCode is in read-only mode.")
+}
+
+pub(crate) async fn ensure_not_read_only(
+    app_settings: &Mutex<AppSettings>,
+    action: &str,
+) -> Result<(), String> {
+    if app_settings.lock().await.read_only {
+        return Err(read_only_error(action));
+    }
+    Ok(())
+}
+
 pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) -> AppSettings {
     let mut settings = app_settings.lock().await.clone();
     if let Ok(Some(collab_enabled)) = codex_config::read_collab_enabled() {
@@ -43,10 +104,14 @@ pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) ->
 }
 
 pub(crate) async fn update_app_settings_core(
-    settings: AppSettings,
+    mut settings: AppSettings,
     app_settings: &Mutex<AppSettings>,
     settings_path: &PathBuf,
 ) -> Result<AppSettings, String> {
+    validate_output_filters(&settings.output_filters)?;
+    validate_redaction_rules(&settings.redaction_rules)?;
+    providers::validate_provider_profiles(&settings.provider_profiles)?;
+    providers::validate_provider_options(&settings.claude_options, &settings.codex_options)?;
     let _ = codex_config::write_collab_enabled(settings.experimental_collab_enabled);
     let _ = codex_config::write_collaboration_modes_enabled(
         settings.collaboration_modes_enabled,
@@ -55,9 +120,16 @@ pub(crate) async fn update_app_settings_core(
     let _ = codex_config::write_unified_exec_enabled(settings.unified_exec_enabled);
     let _ = codex_config::write_apps_enabled(settings.experimental_apps_enabled);
     let _ = codex_config::write_personality(settings.personality.as_str());
-    write_settings(settings_path, &settings)?;
     let mut current = app_settings.lock().await;
-    *current = settings.clone();
+    settings.data_dir = current.data_dir.clone();
+    settings.portable_mode = current.portable_mode;
+    write_settings(settings_path, &settings)?;
+    let previous = std::mem::replace(&mut *current, settings.clone());
+    drop(current);
+    publish_settings_change(SettingsChange {
+        previous: Arc::new(previous),
+        current: Arc::new(settings.clone()),
+    });
     Ok(settings)
 }
 
@@ -70,3 +142,48 @@ pub(crate) fn get_codex_config_path_core() -> Result<String, String> {
                 .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ensure_not_read_only, latest_settings, publish_settings_change, subscribe_settings_changes,
+        SettingsChange,
+    };
+    use crate::types::AppSettings;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn read_only_mode_denies_mutations() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let settings = Mutex::new(AppSettings::default());
+        runtime
+            .block_on(ensure_not_read_only(&settings, "Sending messages"))
+            .expect("writable by default");
+        runtime.block_on(async { settings.lock().await.read_only = true });
+        let error = runtime
+            .block_on(ensure_not_read_only(&settings, "Sending messages"))
+            .expect_err("read-only");
+        assert!(error.starts_with("PermissionDenied:"));
+    }
+
+    #[test]
+    fn latest_settings_skips_to_the_newest_change() {
+        let mut changes = subscribe_settings_changes();
+        assert!(latest_settings(&mut changes).is_none());
+        for max_turn_output_bytes in [1, 2] {
+            publish_settings_change(SettingsChange {
+                previous: Arc::new(AppSettings::default()),
+                current: Arc::new(AppSettings {
+                    max_turn_output_bytes,
+                    ..AppSettings::default()
+                }),
+            });
+        }
+        let latest = latest_settings(&mut changes).expect("latest");
+        assert_eq!(latest.max_turn_output_bytes, 2);
+        assert!(latest_settings(&mut changes).is_none());
+    }
+}
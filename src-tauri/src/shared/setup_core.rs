@@ -0,0 +1,404 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use tokio::sync::Mutex;
+
+use crate::codex::home::resolve_default_codex_home;
+use crate::shared::claude_core::claude_projects_root;
+use crate::shared::codex_import_core::collect_rollout_files;
+use crate::shared::provider_detect_core::{detect_providers, home_dir, ProviderDetection};
+use crate::shared::workspace_scan_core::{dir_name, normalize_path, scan_repositories};
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+/// Folders under the home directory that commonly hold checkouts.
+const COMMON_PROJECT_DIRS: &[&str] = &[
+    "Projects",
+    "projects",
+    "Code",
+    "code",
+    "dev",
+    "Developer",
+    "src",
+    "repos",
+    "git",
+    "workspace",
+    "Documents/GitHub",
+];
+const PROJECT_SCAN_DEPTH: u32 = 2;
+/// Sessions record their working directory near the top; later lines aren't read.
+const SESSION_HEAD_LINES: usize = 20;
+/// Only the newest sessions per provider are opened; older ones are just counted.
+const MAX_SESSIONS_INSPECTED: usize = 2000;
+
+/// What a provider's local history holds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistorySummary {
+    provider: ProviderKind,
+    /// Where the provider keeps sessions; unset when no home directory resolves.
+    root: Option<String>,
+    sessions: usize,
+    /// Distinct existing folders those sessions ran in.
+    folders: usize,
+}
+
+/// Sessions a provider recorded, as `(working directory, last modified ms)`.
+type SessionFolders = Vec<(String, Option<i64>)>;
+
+/// A folder worth adding as a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SuggestedWorkspace {
+    path: String,
+    name: String,
+    is_git_repo: bool,
+    /// Providers with sessions recorded in this folder.
+    history: Vec<ProviderKind>,
+    sessions: usize,
+    last_active_at: Option<i64>,
+}
+
+/// One step of the guided setup, in the order the frontend walks them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SetupStep {
+    id: &'static str,
+    done: bool,
+    detail: String,
+}
+
+fn modified_ms(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let millis = modified.duration_since(UNIX_EPOCH).ok()?.as_millis();
+    i64::try_from(millis).ok()
+}
+
+/// The working directory a Claude (`cwd`) or Codex (`session_meta` payload) session ran in.
+fn session_cwd(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .take(SESSION_HEAD_LINES)
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .find_map(|record| {
+            record
+                .get("cwd")
+                .or_else(|| record.get("payload").and_then(|payload| payload.get("cwd")))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+}
+
+/// Counts the sessions under `root` and reads where the newest of them ran.
+fn scan_history(root: &Path) -> (usize, SessionFolders) {
+    let mut files = Vec::new();
+    collect_rollout_files(root, &mut files);
+    let mut files = files
+        .into_iter()
+        .map(|path| (modified_ms(&path), path))
+        .collect::<Vec<_>>();
+    files.sort_by_key(|(modified, _)| Reverse(*modified));
+    let folders = files
+        .iter()
+        .take(MAX_SESSIONS_INSPECTED)
+        .filter_map(|(modified, path)| Some((session_cwd(path)?, *modified)))
+        .collect();
+    (files.len(), folders)
+}
+
+/// The suggestion for `path`, added on first sight. `None` for folders that are already
+/// workspaces (recorded in `already_added`) or no longer exist.
+fn suggestion_for<'a>(
+    by_path: &'a mut HashMap<PathBuf, SuggestedWorkspace>,
+    already_added: &mut HashSet<PathBuf>,
+    known: &HashSet<PathBuf>,
+    path: &Path,
+) -> Option<&'a mut SuggestedWorkspace> {
+    let path = normalize_path(path);
+    if known.contains(&path) {
+        already_added.insert(path);
+        return None;
+    }
+    if !path.is_dir() {
+        return None;
+    }
+    Some(
+        by_path
+            .entry(path.clone())
+            .or_insert_with(|| SuggestedWorkspace {
+                name: dir_name(&path),
+                is_git_repo: path.join(".git").exists(),
+                path: path.to_string_lossy().to_string(),
+                history: Vec::new(),
+                sessions: 0,
+                last_active_at: None,
+            }),
+    )
+}
+
+/// Merges folders from session history with repositories found on disk, most recently
+/// active first. Also returns how many of those folders are already workspaces.
+fn merge_suggestions(
+    history: &[(ProviderKind, SessionFolders)],
+    repos: Vec<PathBuf>,
+    known: &HashSet<PathBuf>,
+) -> (usize, Vec<SuggestedWorkspace>) {
+    let mut by_path = HashMap::new();
+    let mut already_added = HashSet::new();
+    for (provider, folders) in history {
+        for (cwd, modified) in folders {
+            let Some(suggestion) =
+                suggestion_for(&mut by_path, &mut already_added, known, Path::new(cwd))
+            else {
+                continue;
+            };
+            if !suggestion.history.contains(provider) {
+                suggestion.history.push(provider.clone());
+            }
+            suggestion.sessions += 1;
+            suggestion.last_active_at = suggestion.last_active_at.max(*modified);
+        }
+    }
+    for repo in repos {
+        suggestion_for(&mut by_path, &mut already_added, known, &repo);
+    }
+    let mut suggestions = by_path.into_values().collect::<Vec<_>>();
+    suggestions.sort_by(|left, right| {
+        Reverse(left.last_active_at)
+            .cmp(&Reverse(right.last_active_at))
+            .then_with(|| left.path.cmp(&right.path))
+    });
+    (already_added.len(), suggestions)
+}
+
+fn provider_names(providers: &[&ProviderKind]) -> String {
+    providers
+        .iter()
+        .map(|provider| provider.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn setup_steps(
+    providers: &[ProviderDetection],
+    default_provider: Option<&ProviderKind>,
+    workspace_count: usize,
+    suggestion_count: usize,
+) -> Vec<SetupStep> {
+    let found = providers
+        .iter()
+        .filter(|detection| detection.suggested.is_some())
+        .map(|detection| &detection.provider)
+        .collect::<Vec<_>>();
+    vec![
+        SetupStep {
+            id: "providers",
+            done: !found.is_empty(),
+            detail: if found.is_empty() {
+                "No agent CLI found; install one or set its path.".to_string()
+            } else {
+                format!("Found {}.", provider_names(&found))
+            },
+        },
+        SetupStep {
+            id: "defaultProvider",
+            done: default_provider.is_some(),
+            detail: match default_provider {
+                Some(provider) => format!("New workspaces use {}.", provider.as_str()),
+                None => "Pick the provider new workspaces use.".to_string(),
+            },
+        },
+        SetupStep {
+            id: "workspaces",
+            done: workspace_count > 0,
+            detail: format!(
+                "{workspace_count} workspaces added, {suggestion_count} folders suggested."
+            ),
+        },
+    ]
+}
+
+/// Everything first-run setup needs in one call: installed CLIs, the sessions each provider
+/// already recorded, and folders to add as workspaces, from that history and from git
+/// repositories in common project directories. Nothing is changed.
+pub(crate) async fn setup_probe_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+) -> Result<Value, String> {
+    let (scan_root, default_provider) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.workspace_scan_root.clone(),
+            settings.default_provider.clone(),
+        )
+    };
+    let known = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .values()
+            .map(|entry| normalize_path(Path::new(&entry.path)))
+            .collect::<HashSet<_>>()
+    };
+    let providers = detect_providers().await;
+
+    let mut scan_dirs = home_dir()
+        .map(|home| {
+            COMMON_PROJECT_DIRS
+                .iter()
+                .map(|dir| home.join(dir))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    scan_dirs.extend(scan_root.map(PathBuf::from));
+    let history_roots = [
+        (ProviderKind::Claude, claude_projects_root()),
+        (
+            ProviderKind::Codex,
+            resolve_default_codex_home().map(|home| home.join("sessions")),
+        ),
+    ];
+    let workspace_count = known.len();
+    let (scanned_dirs, histories, already_added, suggestions) =
+        tokio::task::spawn_blocking(move || {
+            let mut seen = HashSet::new();
+            let scanned_dirs = scan_dirs
+                .into_iter()
+                .filter(|dir| dir.is_dir() && seen.insert(normalize_path(dir)))
+                .collect::<Vec<_>>();
+            let repos = scanned_dirs
+                .iter()
+                .flat_map(|dir| scan_repositories(dir, PROJECT_SCAN_DEPTH))
+                .map(|repo| repo.path)
+                .collect::<Vec<_>>();
+            let mut history = Vec::new();
+            let mut summaries = Vec::new();
+            for (provider, root) in history_roots {
+                let (sessions, folders) = root.as_deref().map(scan_history).unwrap_or_default();
+                let distinct = folders
+                    .iter()
+                    .map(|(cwd, _)| normalize_path(Path::new(cwd)))
+                    .filter(|cwd| cwd.is_dir())
+                    .collect::<HashSet<_>>();
+                summaries.push(HistorySummary {
+                    provider: provider.clone(),
+                    root: root.map(|root| root.to_string_lossy().to_string()),
+                    sessions,
+                    folders: distinct.len(),
+                });
+                history.push((provider, folders));
+            }
+            let (already_added, suggestions) = merge_suggestions(&history, repos, &known);
+            (scanned_dirs, summaries, already_added, suggestions)
+        })
+        .await
+        .map_err(|error| format!("failed to probe setup: {error}"))?;
+
+    // The installed provider with the most history is the likeliest default.
+    let suggested_default_provider = providers
+        .iter()
+        .filter(|detection| detection.suggested.is_some())
+        .max_by_key(|detection| {
+            histories
+                .iter()
+                .find(|summary| summary.provider == detection.provider)
+                .map_or(0, |summary| summary.sessions)
+        })
+        .map(|detection| detection.provider.clone());
+    let steps = setup_steps(
+        &providers,
+        default_provider.as_ref(),
+        workspace_count,
+        suggestions.len(),
+    );
+    Ok(json!({
+        "result": {
+            "needsSetup": workspace_count == 0,
+            "steps": steps,
+            "providers": providers,
+            "suggestedDefaultProvider": suggested_default_provider,
+            "histories": histories,
+            "scannedDirs": scanned_dirs,
+            "suggestions": suggestions,
+            "alreadyAdded": already_added,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_suggestions, scan_history};
+    use crate::shared::workspace_scan_core::normalize_path;
+    use crate::types::ProviderKind;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    #[test]
+    fn suggests_history_folders_and_repos_newest_first() {
+        let root = std::env::temp_dir().join(format!("polycode-setup-{}", Uuid::new_v4()));
+        let api = root.join("api");
+        let web = root.join("web");
+        let added = root.join("added");
+        for dir in [&api, &web, &added] {
+            std::fs::create_dir_all(dir.join(".git")).expect("create repo");
+        }
+        let sessions = root.join("sessions").join("2025").join("01");
+        std::fs::create_dir_all(&sessions).expect("create sessions");
+        std::fs::write(
+            sessions.join("rollout-1.jsonl"),
+            format!(
+                "{}\n",
+                serde_json::json!({
+                    "type": "session_meta",
+                    "payload": { "id": "s-1", "cwd": api.to_string_lossy() },
+                })
+            ),
+        )
+        .expect("write rollout");
+        std::fs::write(sessions.join("notes.txt"), "not a session").expect("write notes");
+
+        let (count, folders) = scan_history(&root.join("sessions"));
+        assert_eq!(count, 1);
+        assert_eq!(folders[0].0, api.to_string_lossy());
+
+        let history = vec![
+            (
+                ProviderKind::Claude,
+                vec![
+                    (api.to_string_lossy().to_string(), Some(20)),
+                    (root.join("gone").to_string_lossy().to_string(), Some(30)),
+                ],
+            ),
+            (
+                ProviderKind::Codex,
+                vec![(api.to_string_lossy().to_string(), Some(10))],
+            ),
+        ];
+        let known = HashSet::from([normalize_path(&added)]);
+        let (already_added, suggestions) = merge_suggestions(
+            &history,
+            vec![web.clone(), added.clone(), api.clone()],
+            &known,
+        );
+        assert_eq!(already_added, 1);
+        let names = suggestions
+            .iter()
+            .map(|suggestion| suggestion.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["api", "web"]);
+        assert_eq!(
+            suggestions[0].history,
+            vec![ProviderKind::Claude, ProviderKind::Codex]
+        );
+        assert_eq!(suggestions[0].sessions, 2);
+        assert_eq!(suggestions[0].last_active_at, Some(20));
+        assert!(suggestions[1].is_git_repo);
+        assert!(suggestions[1].history.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::shared::claude_core::{
+    interrupt_all_turns, now_ms, persist_threads_store, ClaudeThreadRecord, ClaudeThreadsStore,
+    ClaudeTurnCancelsStore,
+};
+use crate::shared::claude_pool_core::{shutdown_pool, ClaudeProcessPool};
+
+/// Written as the last step of a graceful shutdown and removed on the next start.
+const CLEAN_SHUTDOWN_FILE_NAME: &str = "clean_shutdown.json";
+/// How long cancelled turns get to store their partial output before they are detached.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CleanShutdownMarker {
+    #[serde(default, rename = "shutdownAt")]
+    shutdown_at: i64,
+    /// Turns that had not finished when the drain timed out; closed before the final save.
+    #[serde(default, rename = "detachedTurns")]
+    detached_turns: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct ShutdownReport {
+    pub(crate) canceled_turns: usize,
+    pub(crate) detached_turns: usize,
+    pub(crate) killed_processes: usize,
+}
+
+fn marker_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(CLEAN_SHUTDOWN_FILE_NAME)
+}
+
+fn write_marker(data_dir: &Path, marker: &CleanShutdownMarker) -> Result<(), String> {
+    fs::create_dir_all(data_dir).map_err(|error| error.to_string())?;
+    let content = serde_json::to_string_pretty(marker).map_err(|error| error.to_string())?;
+    fs::write(marker_path(data_dir), content).map_err(|error| error.to_string())
+}
+
+/// Whether the previous run shut down cleanly. The marker is removed so that a crash during
+/// this run is noticed on the next start.
+fn take_clean_shutdown_marker(data_dir: &Path) -> bool {
+    let path = marker_path(data_dir);
+    let clean = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CleanShutdownMarker>(&content).ok())
+        .is_some();
+    let _ = fs::remove_file(path);
+    clean
+}
+
+/// Marks turns that never finished as completed at `now`, limited to `turn_ids` when given.
+fn close_running_turns(
+    threads: &mut HashMap<String, Vec<ClaudeThreadRecord>>,
+    turn_ids: Option<&HashSet<String>>,
+    now: i64,
+) -> usize {
+    let mut closed = 0;
+    for thread in threads.values_mut().flatten() {
+        for turn in &mut thread.turns {
+            let selected = turn_ids.is_none_or(|turn_ids| turn_ids.contains(&turn.id));
+            if selected && turn.completed_at.is_none() {
+                turn.completed_at = Some(now.max(turn.started_at));
+                closed += 1;
+            }
+        }
+    }
+    closed
+}
+
+fn has_running_turns(
+    threads: &HashMap<String, Vec<ClaudeThreadRecord>>,
+    turn_ids: &HashSet<String>,
+) -> bool {
+    threads.values().flatten().any(|thread| {
+        thread
+            .turns
+            .iter()
+            .any(|turn| turn.completed_at.is_none() && turn_ids.contains(&turn.id))
+    })
+}
+
+/// Runs once at startup, before the threads are served. Without a clean-shutdown marker the
+/// previous run crashed or was killed, and the turns it left running are closed so they don't
+/// stay in progress forever. Returns whether recovery ran.
+pub(crate) fn recover_after_unclean_shutdown(
+    data_dir: &Path,
+    threads: &mut HashMap<String, Vec<ClaudeThreadRecord>>,
+) -> bool {
+    if take_clean_shutdown_marker(data_dir) {
+        return false;
+    }
+    let closed = close_running_turns(threads, None, now_ms());
+    if closed > 0 {
+        eprintln!("Recovered {closed} turn(s) left running by an unclean shutdown.");
+    }
+    true
+}
+
+/// Turns running on Claude or on any Codex app-server; a shutdown would interrupt them.
+pub(crate) async fn running_turn_count(
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> usize {
+    let mut count = claude_turn_cancels.lock().await.len();
+    let sessions = sessions.lock().await.values().cloned().collect::<Vec<_>>();
+    for session in sessions {
+        count += session.active_turns.lock().await.len();
+    }
+    count
+}
+
+/// Cancels all running turns and gives them `DRAIN_TIMEOUT` to record their output, closes
+/// whatever is still running after that, kills the warm Claude processes and Codex app-server
+/// sessions, saves the threads and finally writes the clean-shutdown marker.
+pub(crate) async fn shutdown_core(
+    data_dir: &Path,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_processes: &ClaudeProcessPool,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+) -> ShutdownReport {
+    let turn_ids = interrupt_all_turns(claude_turn_cancels)
+        .await
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let started = Instant::now();
+    while started.elapsed() < DRAIN_TIMEOUT
+        && has_running_turns(&*claude_threads.lock().await, &turn_ids)
+    {
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+    let detached_turns =
+        close_running_turns(&mut *claude_threads.lock().await, Some(&turn_ids), now_ms());
+
+    let mut killed_processes = shutdown_pool(claude_processes).await;
+    let sessions = std::mem::take(&mut *sessions.lock().await);
+    for session in sessions.into_values() {
+        session.close().await;
+        killed_processes += 1;
+    }
+
+    match persist_threads_store(claude_threads, claude_threads_path).await {
+        Ok(()) => {
+            let marker = CleanShutdownMarker {
+                shutdown_at: now_ms(),
+                detached_turns,
+            };
+            if let Err(error) = write_marker(data_dir, &marker) {
+                eprintln!("Failed to write the clean-shutdown marker: {error}");
+            }
+        }
+        // Without the marker the next start closes the turns itself.
+        Err(error) => eprintln!("Failed to save threads on shutdown: {error}"),
+    }
+    ShutdownReport {
+        canceled_turns: turn_ids.len(),
+        detached_turns,
+        killed_processes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        close_running_turns, marker_path, recover_after_unclean_shutdown, write_marker,
+        CleanShutdownMarker,
+    };
+    use crate::shared::claude_core::{ClaudeThreadRecord, ClaudeTurnRecord};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use uuid::Uuid;
+
+    fn turn(id: &str, completed_at: Option<i64>) -> ClaudeTurnRecord {
+        ClaudeTurnRecord {
+            id: id.to_string(),
+            started_at: 10,
+            completed_at,
+            items: Vec::new(),
+            context_files: Vec::new(),
+            cli_version: None,
+            duration_ms: None,
+            snapshot: None,
+            plan: false,
+            suggestions: Vec::new(),
+            checklist: Vec::new(),
+            answered_by: None,
+            model_downgrade: None,
+            request: None,
+        }
+    }
+
+    fn threads() -> HashMap<String, Vec<ClaudeThreadRecord>> {
+        let thread = ClaudeThreadRecord {
+            id: "thread-1".to_string(),
+            cwd: "/repo".to_string(),
+            preview: String::new(),
+            created_at: 1,
+            updated_at: 1,
+            name: None,
+            tags: Vec::new(),
+            session_id: None,
+            session_needs_seed: false,
+            provider_session_id: None,
+            cwd_override: None,
+            generation: Default::default(),
+            worktree: None,
+            summary: None,
+            stats: None,
+            last_read_at: None,
+            turns: vec![
+                turn("done", Some(5)),
+                turn("running-a", None),
+                turn("running-b", None),
+            ],
+        };
+        HashMap::from([("ws-1".to_string(), vec![thread])])
+    }
+
+    fn completed(threads: &HashMap<String, Vec<ClaudeThreadRecord>>) -> Vec<Option<i64>> {
+        threads["ws-1"][0]
+            .turns
+            .iter()
+            .map(|turn| turn.completed_at)
+            .collect()
+    }
+
+    #[test]
+    fn closes_running_turns_only_after_an_unclean_shutdown() {
+        let data_dir =
+            std::env::temp_dir().join(format!("polycode-shutdown-test-{}", Uuid::new_v4()));
+
+        let mut detached = threads();
+        let turn_ids = HashSet::from(["running-a".to_string()]);
+        assert_eq!(close_running_turns(&mut detached, Some(&turn_ids), 3), 1);
+        assert_eq!(completed(&detached), vec![Some(5), Some(10), None]);
+
+        write_marker(&data_dir, &CleanShutdownMarker::default()).expect("write marker");
+        let mut clean = threads();
+        assert!(!recover_after_unclean_shutdown(&data_dir, &mut clean));
+        assert!(!marker_path(&data_dir).exists());
+        assert_eq!(completed(&clean), vec![Some(5), None, None]);
+
+        let mut crashed = threads();
+        assert!(recover_after_unclean_shutdown(&data_dir, &mut crashed));
+        assert!(completed(&crashed).iter().all(Option::is_some));
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::shared::claude_core::emit;
+use crate::shared::event_mirror_core::is_turn_event;
+
+/// Errors older than this no longer count as recent.
+const RECENT_ERROR_WINDOW_MS: i64 = 15 * 60 * 1000;
+const MAX_RECENT_ERRORS: usize = 20;
+
+pub(crate) type StatusTrackerStore = Arc<Mutex<StatusTracker>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecentError {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) message: String,
+    pub(crate) at: i64,
+}
+
+/// Counts across all workspaces, small enough for a tray icon to poll.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatusSummary {
+    pub(crate) running: usize,
+    pub(crate) queued: usize,
+    pub(crate) recent_errors: usize,
+    pub(crate) last_error: Option<RecentError>,
+}
+
+/// Turns in flight and recent errors, kept from the turn events every workspace emits. A
+/// started turn counts until it completes or fails for good.
+#[derive(Default)]
+pub(crate) struct StatusTracker {
+    active: HashSet<(String, String)>,
+    errors: VecDeque<RecentError>,
+    last_pushed: Option<StatusSummary>,
+}
+
+fn turn_id(params: &Value) -> Option<&str> {
+    params["turn"]["id"]
+        .as_str()
+        .or_else(|| params["turnId"].as_str())
+}
+
+impl StatusTracker {
+    pub(crate) fn observe(&mut self, event: &AppServerEvent, now: i64) {
+        let params = &event.message["params"];
+        let key = |turn_id: &str| (event.workspace_id.clone(), turn_id.to_string());
+        match event.message["method"].as_str() {
+            Some("turn/started") => {
+                if let Some(turn_id) = turn_id(params) {
+                    self.active.insert(key(turn_id));
+                }
+            }
+            Some("turn/completed") => {
+                if let Some(turn_id) = turn_id(params) {
+                    self.active.remove(&key(turn_id));
+                }
+            }
+            Some("error") if params["willRetry"].as_bool() != Some(true) => {
+                // Some failures end a turn without a `turn/completed`.
+                if let Some(turn_id) = turn_id(params) {
+                    self.active.remove(&key(turn_id));
+                }
+                let message = params["error"]["message"]
+                    .as_str()
+                    .or_else(|| params["error"].as_str())
+                    .unwrap_or("Unknown error");
+                self.errors.push_back(RecentError {
+                    workspace_id: event.workspace_id.clone(),
+                    thread_id: params["threadId"].as_str().map(str::to_string),
+                    message: message.to_string(),
+                    at: now,
+                });
+                if self.errors.len() > MAX_RECENT_ERRORS {
+                    self.errors.pop_front();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `queued` comes from the turn slots; queued turns have started but hold no slot yet.
+    pub(crate) fn summary(&mut self, queued: usize, now: i64) -> StatusSummary {
+        self.errors
+            .retain(|error| now - error.at < RECENT_ERROR_WINDOW_MS);
+        StatusSummary {
+            running: self.active.len().saturating_sub(queued),
+            queued,
+            recent_errors: self.errors.len(),
+            last_error: self.errors.back().cloned(),
+        }
+    }
+
+    /// The summary when it differs from the last one pushed.
+    fn changed_summary(&mut self, queued: usize, now: i64) -> Option<StatusSummary> {
+        let summary = self.summary(queued, now);
+        if self.last_pushed.as_ref() == Some(&summary) {
+            return None;
+        }
+        self.last_pushed = Some(summary.clone());
+        Some(summary)
+    }
+}
+
+/// Folds a turn event into the tracker and emits `status/summary` when the counts change.
+/// The event isn't tied to a workspace, so its workspace id is empty. Errors aging out of the
+/// window don't push an update; the next poll or event picks that up.
+pub(crate) async fn track_status_event<E: EventSink>(
+    tracker: &StatusTrackerStore,
+    event: &AppServerEvent,
+    queued: usize,
+    now: i64,
+    event_sink: &E,
+) {
+    if !is_turn_event(&event.message) {
+        return;
+    }
+    let summary = {
+        let mut tracker = tracker.lock().await;
+        tracker.observe(event, now);
+        tracker.changed_summary(queued, now)
+    };
+    if let Some(summary) = summary {
+        emit(event_sink, "", "status/summary", json!(summary));
+    }
+}
+
+pub(crate) async fn status_summary_core(
+    tracker: &StatusTrackerStore,
+    queued: usize,
+    now: i64,
+) -> Result<Value, String> {
+    let summary = tracker.lock().await.summary(queued, now);
+    Ok(json!({ "result": summary }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatusTracker, RECENT_ERROR_WINDOW_MS};
+    use crate::backend::events::AppServerEvent;
+    use serde_json::{json, Value};
+
+    fn event(workspace_id: &str, method: &str, params: Value) -> AppServerEvent {
+        AppServerEvent {
+            workspace_id: workspace_id.to_string(),
+            message: json!({ "method": method, "params": params }),
+        }
+    }
+
+    #[test]
+    fn counts_turns_and_recent_errors_across_workspaces() {
+        let mut tracker = StatusTracker::default();
+        let started = |turn_id: &str| json!({ "threadId": "t-1", "turn": { "id": turn_id } });
+        tracker.observe(&event("ws-1", "turn/started", started("a")), 0);
+        tracker.observe(&event("ws-1", "turn/started", started("b")), 0);
+        tracker.observe(&event("ws-2", "turn/started", started("a")), 0);
+        let summary = tracker.summary(1, 0);
+        assert_eq!(
+            (summary.running, summary.queued, summary.recent_errors),
+            (2, 1, 0)
+        );
+        assert_eq!(tracker.changed_summary(1, 0), Some(summary));
+        assert_eq!(tracker.changed_summary(1, 0), None);
+
+        tracker.observe(&event("ws-1", "turn/completed", started("a")), 10);
+        let retrying = json!({ "turnId": "b", "error": { "message": "503" }, "willRetry": true });
+        tracker.observe(&event("ws-1", "error", retrying), 20);
+        assert_eq!(tracker.summary(0, 20).recent_errors, 0);
+        let failed = json!({
+            "threadId": "t-2",
+            "turnId": "b",
+            "error": { "message": "claude: not found" },
+            "willRetry": false,
+        });
+        tracker.observe(&event("ws-1", "error", failed), 30);
+        let summary = tracker.summary(0, 30);
+        assert_eq!((summary.running, summary.recent_errors), (1, 1));
+        let last_error = summary.last_error.expect("last error");
+        assert_eq!(last_error.message, "claude: not found");
+        assert_eq!(last_error.thread_id.as_deref(), Some("t-2"));
+
+        let later = 30 + RECENT_ERROR_WINDOW_MS;
+        assert_eq!(tracker.summary(0, later).recent_errors, 0);
+        assert!(tracker.summary(0, later).last_error.is_none());
+    }
+}
@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, now_ms, persist_threads_store, preview_from_text,
+    send_user_message_core, thread_has_running_turn, ClaudeThreadRecord, ClaudeThreadsStore,
+    ClaudeTurnCancelsStore,
+};
+use crate::shared::claude_pool_core::{ClaudeApprovalsStore, ClaudeProcessPool};
+use crate::shared::rate_limit_core::RateLimitsStore;
+use crate::shared::thread_locks_core::ThreadLocksStore;
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::shared::turn_slots_core::TurnSlotsStore;
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+const CLAUDE_THREAD_EDITS_FILE_NAME: &str = "claude_thread_edits.json";
+
+/// A thread as it was before one of its messages was edited; `undo_edit_message` puts it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EditSnapshot {
+    #[serde(rename = "workspaceId")]
+    workspace_id: String,
+    #[serde(rename = "itemId")]
+    item_id: String,
+    #[serde(rename = "editedAt")]
+    edited_at: i64,
+    thread: ClaudeThreadRecord,
+}
+
+fn edits_path(claude_threads_path: &Path) -> PathBuf {
+    claude_threads_path.with_file_name(CLAUDE_THREAD_EDITS_FILE_NAME)
+}
+
+fn read_edits(path: &Path) -> Result<Vec<EditSnapshot>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&data).map_err(|error| error.to_string())
+}
+
+fn write_edits(path: &Path, entries: &[EditSnapshot]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(entries).map_err(|error| error.to_string())?;
+    std::fs::write(path, data).map_err(|error| error.to_string())
+}
+
+fn is_snapshot_of(entry: &EditSnapshot, workspace_id: &str, thread_id: &str) -> bool {
+    entry.workspace_id == workspace_id && entry.thread.id == thread_id
+}
+
+/// Drops the turn holding the user message `item_id` and every turn after it. The Claude
+/// session still remembers the dropped turns, so the thread forks into a new session seeded
+/// with what is left. Returns the message's text, whether its turn ran in plan mode and how
+/// many turns were dropped.
+fn truncate_at_message(
+    thread: &mut ClaudeThreadRecord,
+    item_id: &str,
+    timestamp: i64,
+) -> Result<(String, bool, usize), String> {
+    let index = thread
+        .turns
+        .iter()
+        .position(|turn| {
+            turn.items
+                .iter()
+                .any(|item| item.id == item_id && item.role == "user")
+        })
+        .ok_or_else(|| format!("user message not found: {item_id}"))?;
+    let turn = &thread.turns[index];
+    let text = turn
+        .items
+        .iter()
+        .find(|item| item.id == item_id)
+        .map(|item| item.text.clone())
+        .unwrap_or_default();
+    let plan = turn.plan;
+    let removed = thread.turns.split_off(index).len();
+    thread.preview = thread
+        .turns
+        .iter()
+        .flat_map(|turn| &turn.items)
+        .rev()
+        .find(|item| item.role == "assistant")
+        .map(|item| preview_from_text(&item.text))
+        .unwrap_or_default();
+    // Failures are not tracked per turn, so the error count restarts with the kept turns.
+    thread.stats = Some(ThreadStats::from_turns(&thread.turns, ProviderKind::Claude));
+    thread.session_id = Some(Uuid::new_v4().to_string());
+    thread.session_needs_seed = true;
+    thread.provider_session_id = None;
+    thread.summary = None;
+    thread.updated_at = timestamp;
+    Ok((text, plan, removed))
+}
+
+/// Replaces a past user message and runs the thread again from there. The turns from that
+/// message on are dropped, after the whole thread is saved so `undo_edit_message_core` can
+/// restore it; only the latest edit of a thread can be undone. Without `new_text` the message
+/// is resent as it was, which regenerates the answer.
+pub(crate) async fn edit_message_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_processes: &ClaudeProcessPool,
+    claude_approvals: &ClaudeApprovalsStore,
+    turn_slots: &TurnSlotsStore,
+    thread_locks: &ThreadLocksStore,
+    rate_limits: &RateLimitsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    item_id: String,
+    new_text: Option<String>,
+    event_sink: E,
+) -> Result<Value, String> {
+    if new_text
+        .as_deref()
+        .is_some_and(|text| text.trim().is_empty())
+    {
+        return Err("empty user message".to_string());
+    }
+    if thread_has_running_turn(claude_turn_cancels, &workspace_id, &thread_id).await {
+        return Err("Wait for the running turn to finish before editing a message.".to_string());
+    }
+    let (original_text, plan, removed_turns) = {
+        let mut store = claude_threads.lock().await;
+        let thread = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        let snapshot = EditSnapshot {
+            workspace_id: workspace_id.clone(),
+            item_id: item_id.clone(),
+            edited_at: now_ms(),
+            thread: thread.clone(),
+        };
+        let mut edited = thread.clone();
+        let truncated = truncate_at_message(&mut edited, &item_id, snapshot.edited_at)?;
+        let path = edits_path(claude_threads_path);
+        let mut entries = read_edits(&path)?;
+        entries.retain(|entry| !is_snapshot_of(entry, &workspace_id, &thread_id));
+        entries.push(snapshot);
+        write_edits(&path, &entries)?;
+        *thread = edited;
+        truncated
+    };
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+
+    let mut response = send_user_message_core(
+        workspaces,
+        app_settings,
+        claude_threads,
+        claude_turn_cancels,
+        claude_processes,
+        claude_approvals,
+        turn_slots,
+        thread_locks,
+        rate_limits,
+        claude_threads_path,
+        workspace_id,
+        thread_id,
+        new_text.unwrap_or(original_text),
+        None,
+        false,
+        plan,
+        event_sink,
+    )
+    .await?;
+    response["result"]["removedTurns"] = json!(removed_turns);
+    Ok(response)
+}
+
+/// Puts a thread back the way it was before its latest message edit, dropping whatever ran
+/// since. The original Claude session was never touched, so the thread resumes it.
+pub(crate) async fn undo_edit_message_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    if thread_has_running_turn(claude_turn_cancels, &workspace_id, &thread_id).await {
+        return Err("Wait for the running turn to finish before undoing the edit.".to_string());
+    }
+    let path = edits_path(claude_threads_path);
+    let mut entries = read_edits(&path)?;
+    let index = entries
+        .iter()
+        .position(|entry| is_snapshot_of(entry, &workspace_id, &thread_id))
+        .ok_or_else(|| "No edit to undo for this thread.".to_string())?;
+    let snapshot = entries.remove(index);
+    {
+        let mut store = claude_threads.lock().await;
+        let thread = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        *thread = snapshot.thread;
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    write_edits(&path, &entries)?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "workspaceId": workspace_id,
+            "threadId": thread_id,
+            "itemId": snapshot.item_id,
+            "editedAt": snapshot.edited_at,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::truncate_at_message;
+    use crate::shared::claude_core::{
+        ClaudeMessageRecord, ClaudeThreadRecord, ClaudeTurnRecord, GenerationParams,
+    };
+
+    fn turn(index: usize, plan: bool) -> ClaudeTurnRecord {
+        let message = |role: &str, text: String| ClaudeMessageRecord {
+            id: format!("{role}-{index}"),
+            role: role.to_string(),
+            text,
+            pinned: false,
+        };
+        ClaudeTurnRecord {
+            id: format!("turn-{index}"),
+            started_at: index as i64,
+            completed_at: Some(index as i64),
+            items: vec![
+                message("user", format!("question {index}")),
+                message("assistant", format!("answer {index}")),
+            ],
+            context_files: Vec::new(),
+            cli_version: None,
+            duration_ms: None,
+            snapshot: None,
+            plan,
+            suggestions: Vec::new(),
+            request: None,
+            checklist: Vec::new(),
+            answered_by: None,
+            model_downgrade: None,
+        }
+    }
+
+    #[test]
+    fn truncation_drops_the_edited_turn_and_forks_the_session() {
+        let mut thread = ClaudeThreadRecord {
+            id: "thread-1".to_string(),
+            cwd: "/repo".to_string(),
+            preview: "answer 2".to_string(),
+            created_at: 0,
+            updated_at: 2,
+            name: None,
+            tags: Vec::new(),
+            session_id: None,
+            session_needs_seed: false,
+            provider_session_id: Some("session-1".to_string()),
+            cwd_override: None,
+            generation: GenerationParams::default(),
+            worktree: None,
+            summary: None,
+            stats: None,
+            last_read_at: None,
+            turns: vec![turn(0, false), turn(1, true), turn(2, false)],
+        };
+
+        assert!(truncate_at_message(&mut thread, "assistant-1", 5).is_err());
+        assert!(truncate_at_message(&mut thread, "user-9", 5).is_err());
+        assert_eq!(thread.turns.len(), 3);
+
+        let (text, plan, removed) =
+            truncate_at_message(&mut thread, "user-1", 5).expect("truncate");
+        assert_eq!(text, "question 1");
+        assert!(plan);
+        assert_eq!(removed, 2);
+        assert_eq!(thread.turns.len(), 1);
+        assert_eq!(thread.preview, "answer 0");
+        assert_eq!(thread.stats.as_ref().map(|stats| stats.turn_count), Some(1));
+        assert_eq!(thread.provider_session_id, None);
+        assert!(thread.session_needs_seed && thread.session_id.is_some());
+        assert_eq!(thread.updated_at, 5);
+
+        truncate_at_message(&mut thread, "user-0", 6).expect("truncate first");
+        assert!(thread.turns.is_empty());
+        assert_eq!(thread.preview, "");
+    }
+}
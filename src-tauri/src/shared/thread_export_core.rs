@@ -0,0 +1,379 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, now_ms, persist_threads_store, redact_thread, ClaudeMessageRecord,
+    ClaudeThreadRecord, ClaudeThreadsStore, ClaudeTurnRecord, GenerationParams,
+};
+use crate::shared::redaction_core::RedactionPipeline;
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::types::{ProviderKind, WorkspaceEntry};
+
+/// `format` of every PolyCode thread export.
+pub(crate) const EXPORT_FORMAT: &str = "polycode.threads";
+/// Bump when a field is removed or changes meaning; new optional fields keep the version.
+/// `docs/thread-export.schema.json` is generated from the types below and must match them.
+pub(crate) const EXPORT_SCHEMA_VERSION: u32 = 1;
+/// Exports bigger than this are almost certainly not thread history.
+const MAX_IMPORT_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Threads exported from PolyCode for other tools, and importable back into a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(title = "PolyCode thread export")]
+pub(crate) struct ThreadExport {
+    /// Always `polycode.threads`.
+    pub(crate) format: String,
+    /// Version of this schema the export was written with.
+    pub(crate) schema_version: u32,
+    /// Milliseconds since the Unix epoch.
+    pub(crate) exported_at: i64,
+    pub(crate) threads: Vec<ExportedThread>,
+}
+
+/// One conversation with an agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedThread {
+    pub(crate) id: String,
+    /// Name the user gave the thread.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// Directory the agent ran in on the exporting machine.
+    pub(crate) cwd: String,
+    /// Short text shown in thread lists.
+    #[serde(default)]
+    pub(crate) preview: String,
+    /// Milliseconds since the Unix epoch.
+    pub(crate) created_at: i64,
+    /// Milliseconds since the Unix epoch.
+    pub(crate) updated_at: i64,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    /// Oldest first.
+    #[serde(default)]
+    pub(crate) turns: Vec<ExportedTurn>,
+}
+
+/// One prompt and the agent's reply to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedTurn {
+    pub(crate) id: String,
+    /// Milliseconds since the Unix epoch.
+    pub(crate) started_at: i64,
+    /// Milliseconds since the Unix epoch; unset for a turn still running at export time.
+    #[serde(default)]
+    pub(crate) completed_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duration_ms: Option<u64>,
+    /// The turn ran in plan mode, so its reply is a plan rather than changes.
+    #[serde(default)]
+    pub(crate) plan: bool,
+    pub(crate) items: Vec<ExportedItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ExportedRole {
+    User,
+    Assistant,
+}
+
+/// A message within a turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedItem {
+    pub(crate) id: String,
+    pub(crate) role: ExportedRole,
+    pub(crate) text: String,
+}
+
+impl ExportedThread {
+    fn from_record(thread: &ClaudeThreadRecord) -> Self {
+        Self {
+            id: thread.id.clone(),
+            name: thread.name.clone(),
+            cwd: thread.cwd.clone(),
+            preview: thread.preview.clone(),
+            created_at: thread.created_at,
+            updated_at: thread.updated_at,
+            tags: thread.tags.clone(),
+            turns: thread
+                .turns
+                .iter()
+                .map(|turn| ExportedTurn {
+                    id: turn.id.clone(),
+                    started_at: turn.started_at,
+                    completed_at: turn.completed_at,
+                    duration_ms: turn.duration_ms,
+                    plan: turn.plan,
+                    items: turn
+                        .items
+                        .iter()
+                        .map(|item| ExportedItem {
+                            id: item.id.clone(),
+                            role: if item.role == "user" {
+                                ExportedRole::User
+                            } else {
+                                ExportedRole::Assistant
+                            },
+                            text: item.text.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The thread as a record of the workspace at `cwd`. Its next message starts a new Claude
+    /// session seeded with the transcript, and a turn that was running at export time counts
+    /// as finished.
+    fn into_record(self, cwd: &str) -> ClaudeThreadRecord {
+        let turns = self
+            .turns
+            .into_iter()
+            .map(|turn| ClaudeTurnRecord {
+                id: turn.id,
+                started_at: turn.started_at,
+                completed_at: Some(turn.completed_at.unwrap_or(turn.started_at)),
+                items: turn
+                    .items
+                    .into_iter()
+                    .map(|item| ClaudeMessageRecord {
+                        id: item.id,
+                        role: match item.role {
+                            ExportedRole::User => "user",
+                            ExportedRole::Assistant => "assistant",
+                        }
+                        .to_string(),
+                        text: item.text,
+                        pinned: false,
+                    })
+                    .collect(),
+                context_files: Vec::new(),
+                cli_version: None,
+                duration_ms: turn.duration_ms,
+                snapshot: None,
+                plan: turn.plan,
+                suggestions: Vec::new(),
+                request: None,
+                checklist: Vec::new(),
+                answered_by: None,
+                model_downgrade: None,
+            })
+            .collect::<Vec<_>>();
+        let has_turns = !turns.is_empty();
+        ClaudeThreadRecord {
+            id: self.id,
+            cwd: cwd.to_string(),
+            preview: self.preview,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            name: self.name,
+            tags: self.tags,
+            session_id: has_turns.then(|| Uuid::new_v4().to_string()),
+            session_needs_seed: has_turns,
+            provider_session_id: None,
+            cwd_override: None,
+            generation: GenerationParams::default(),
+            worktree: None,
+            summary: None,
+            stats: Some(ThreadStats::from_turns(&turns, ProviderKind::Claude)),
+            last_read_at: None,
+            turns,
+        }
+    }
+}
+
+/// JSON Schema (draft 7) of [`ThreadExport`].
+pub(crate) fn export_schema() -> Value {
+    json!(schemars::schema_for!(ThreadExport))
+}
+
+fn build_export(threads: &[ClaudeThreadRecord], exported_at: i64) -> ThreadExport {
+    ThreadExport {
+        format: EXPORT_FORMAT.to_string(),
+        schema_version: EXPORT_SCHEMA_VERSION,
+        exported_at,
+        threads: threads.iter().map(ExportedThread::from_record).collect(),
+    }
+}
+
+/// Reads an export, refusing other formats and versions newer than this one.
+fn parse_export(data: &str) -> Result<ThreadExport, String> {
+    let value: Value =
+        serde_json::from_str(data).map_err(|error| format!("Export is not valid JSON: {error}"))?;
+    if value["format"].as_str() != Some(EXPORT_FORMAT) {
+        return Err(format!(
+            "Not a PolyCode thread export (`format` must be `{EXPORT_FORMAT}`)."
+        ));
+    }
+    let version = value["schemaVersion"]
+        .as_u64()
+        .ok_or_else(|| "Export has no `schemaVersion`.".to_string())?;
+    if version > u64::from(EXPORT_SCHEMA_VERSION) {
+        return Err(format!(
+            "Export schema version {version} is newer than this app reads \
+             ({EXPORT_SCHEMA_VERSION}); update PolyCode to import it."
+        ));
+    }
+    serde_json::from_value(value).map_err(|error| format!("Invalid thread export: {error}"))
+}
+
+pub(crate) fn thread_export_schema_core() -> Result<Value, String> {
+    Ok(json!({ "result": export_schema() }))
+}
+
+/// Exports the given threads of a workspace, or all of them, with assistant replies passed
+/// through the redaction rules.
+pub(crate) async fn export_threads_core(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: String,
+    thread_ids: Option<Vec<String>>,
+    redaction: &RedactionPipeline,
+) -> Result<Value, String> {
+    let threads = {
+        let store = claude_threads.lock().await;
+        store
+            .get(&workspace_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|thread| {
+                thread_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&thread.id))
+            })
+            .map(|thread| redact_thread(thread, redaction))
+            .collect::<Vec<_>>()
+    };
+    Ok(json!({ "result": build_export(&threads, now_ms()) }))
+}
+
+/// Imports a PolyCode export into a workspace. Threads whose id the workspace already has are
+/// skipped, so importing the same file twice is harmless.
+pub(crate) async fn import_threads_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    path: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let path = Path::new(path.trim());
+    let size = tokio::fs::metadata(path)
+        .await
+        .map_err(|error| format!("Failed to read {}: {error}", path.display()))?
+        .len();
+    if size > MAX_IMPORT_FILE_BYTES {
+        return Err(format!("{} is too large to import.", path.display()));
+    }
+    let data = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+    let export = parse_export(&data)?;
+    let workspace_path = workspaces
+        .lock()
+        .await
+        .get(&workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(|| "workspace not found".to_string())?;
+
+    let mut imported = Vec::new();
+    let mut skipped = 0usize;
+    {
+        let mut store = claude_threads.lock().await;
+        let threads = store.entry(workspace_id.clone()).or_default();
+        for thread in export.threads {
+            if threads.iter().any(|existing| existing.id == thread.id) {
+                skipped += 1;
+                continue;
+            }
+            imported.push(thread.id.clone());
+            threads.push(thread.into_record(&workspace_path));
+        }
+    }
+    if !imported.is_empty() {
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(&event_sink, claude_threads, &workspace_id, &imported, &[]).await;
+    }
+    Ok(json!({
+        "result": {
+            "imported": imported,
+            "skipped": skipped,
+            "schemaVersion": export.schema_version,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_export, export_schema, parse_export, ExportedThread};
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn exports_round_trip_and_match_the_published_schema() {
+        let thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": "thread-1",
+            "cwd": "/repo",
+            "preview": "Fix the build",
+            "createdAt": 1,
+            "updatedAt": 3,
+            "name": "Build",
+            "tags": ["bug"],
+            "providerSessionId": "session-1",
+            "turns": [{
+                "id": "claude-turn-1",
+                "startedAt": 1,
+                "completedAt": 2,
+                "plan": true,
+                "items": [
+                    { "id": "claude-user-1", "role": "user", "text": "Fix the build" },
+                    { "id": "claude-assistant-1", "role": "assistant", "text": "Done" },
+                ],
+            }],
+        }))
+        .expect("thread");
+        let export = build_export(std::slice::from_ref(&thread), 10);
+        let data = serde_json::to_string(&export).expect("serialized");
+        let parsed = parse_export(&data).expect("parsed");
+        assert_eq!(parsed, export);
+
+        let imported = parsed.threads[0].clone().into_record("/other");
+        assert_eq!(imported.cwd, "/other");
+        assert!(imported.session_needs_seed);
+        assert_eq!(imported.provider_session_id, None);
+        assert_eq!(
+            ExportedThread::from_record(&imported),
+            ExportedThread {
+                cwd: "/other".to_string(),
+                ..export.threads[0].clone()
+            }
+        );
+
+        let mut newer: Value = serde_json::from_str(&data).expect("json");
+        newer["schemaVersion"] = json!(2);
+        assert!(parse_export(&newer.to_string())
+            .expect_err("newer")
+            .contains("newer than this app reads"));
+        assert!(parse_export(r#"{"threads":[]}"#).is_err());
+
+        let published: Value =
+            serde_json::from_str(include_str!("../../../docs/thread-export.schema.json"))
+                .expect("published schema");
+        assert_eq!(
+            export_schema(),
+            published,
+            "regenerate docs/thread-export.schema.json"
+        );
+    }
+}
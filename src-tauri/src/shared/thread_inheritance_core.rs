@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use tokio::sync::Mutex;
+
+use crate::types::WorkspaceEntry;
+
+/// The repo a worktree workspace was created from. `None` for other workspaces and for
+/// worktrees whose parent has been removed.
+pub(crate) async fn worktree_parent_id(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<Option<String>, String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| "workspace not found".to_string())?;
+    if !entry.kind.is_worktree() {
+        return Ok(None);
+    }
+    Ok(entry
+        .parent_id
+        .clone()
+        .filter(|parent_id| workspaces.contains_key(parent_id)))
+}
+
+fn tagged_threads(list: &Value, workspace_id: &str, origin: &str) -> Vec<Value> {
+    let mut threads = list["result"]["data"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for thread in &mut threads {
+        if let Some(object) = thread.as_object_mut() {
+            object.insert("workspaceId".to_string(), json!(workspace_id));
+            object.insert("origin".to_string(), json!(origin));
+        }
+    }
+    threads
+}
+
+/// Combines a worktree's thread list with its parent repo's. Each thread carries the
+/// `workspaceId` it belongs to and an `origin` of `worktree` or `parent`; the worktree's own
+/// threads come first, both halves keep their list order.
+pub(crate) fn inherited_threads_core(
+    workspace_id: &str,
+    own: Value,
+    parent: Option<(String, Value)>,
+) -> Value {
+    let mut data = tagged_threads(&own, workspace_id, "worktree");
+    let parent_workspace_id = parent.map(|(parent_id, list)| {
+        data.extend(tagged_threads(&list, &parent_id, "parent"));
+        parent_id
+    });
+    json!({
+        "result": {
+            "workspaceId": workspace_id,
+            "parentWorkspaceId": parent_workspace_id,
+            "data": data,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inherited_threads_core, worktree_parent_id};
+    use crate::types::WorkspaceEntry;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tokio::sync::Mutex;
+
+    fn workspace(id: &str, kind: &str, parent_id: Option<&str>) -> (String, WorkspaceEntry) {
+        let entry = serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "path": format!("/repos/{id}"),
+            "codex_bin": null,
+            "kind": kind,
+            "parentId": parent_id,
+        }))
+        .expect("workspace");
+        (id.to_string(), entry)
+    }
+
+    #[test]
+    fn worktrees_list_their_own_threads_before_the_parents() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let workspaces = Mutex::new(HashMap::from([
+            workspace("repo", "main", None),
+            workspace("feature", "worktree", Some("repo")),
+            workspace("stale", "worktree", Some("removed")),
+        ]));
+        runtime.block_on(async {
+            assert_eq!(
+                worktree_parent_id(&workspaces, "feature").await,
+                Ok(Some("repo".to_string()))
+            );
+            assert_eq!(worktree_parent_id(&workspaces, "repo").await, Ok(None));
+            assert_eq!(worktree_parent_id(&workspaces, "stale").await, Ok(None));
+            assert!(worktree_parent_id(&workspaces, "missing").await.is_err());
+        });
+
+        let own = json!({ "result": { "data": [{ "id": "t-feature", "updatedAt": 1 }] } });
+        let parent = json!({ "result": { "data": [{ "id": "t-repo", "updatedAt": 2 }] } });
+        let listed = inherited_threads_core("feature", own, Some(("repo".to_string(), parent)));
+        assert_eq!(listed["result"]["parentWorkspaceId"], "repo");
+        let origins = listed["result"]["data"]
+            .as_array()
+            .expect("threads")
+            .iter()
+            .map(|thread| {
+                (
+                    thread["id"].as_str().unwrap_or_default(),
+                    thread["workspaceId"].as_str().unwrap_or_default(),
+                    thread["origin"].as_str().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            origins,
+            vec![
+                ("t-feature", "feature", "worktree"),
+                ("t-repo", "repo", "parent")
+            ]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Serializes turn submissions per thread, so checking that the thread is idle, recording the
+/// turn and registering its cancel handle happen as one step. A second message for the same
+/// thread sent meanwhile is refused as busy rather than cancelling the first.
+pub(crate) type ThreadLocksStore = Arc<ThreadLocks>;
+
+/// `(workspace_id, thread_id)`.
+type ThreadKey = (String, String);
+
+#[derive(Default)]
+pub(crate) struct ThreadLocks {
+    locks: StdMutex<HashMap<ThreadKey, Arc<Mutex<()>>>>,
+}
+
+/// Held while a turn is being submitted; the thread takes the next submission once it drops.
+pub(crate) struct ThreadLockGuard {
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl ThreadLocks {
+    pub(crate) fn try_lock(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+    ) -> Result<ThreadLockGuard, String> {
+        let lock = {
+            let mut locks = self
+                .locks
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            // Locks nobody holds are dropped, so the map only tracks busy threads.
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            Arc::clone(
+                locks
+                    .entry((workspace_id.to_string(), thread_id.to_string()))
+                    .or_default(),
+            )
+        };
+        let guard = lock
+            .try_lock_owned()
+            .map_err(|_| "Busy: a message for this thread is still being sent.".to_string())?;
+        Ok(ThreadLockGuard { _guard: guard })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadLocks;
+
+    #[test]
+    fn a_second_submission_to_a_busy_thread_is_refused() {
+        let locks = ThreadLocks::default();
+
+        let first = locks.try_lock("ws-1", "t-1").expect("idle thread");
+        let busy = locks.try_lock("ws-1", "t-1").err().expect("busy thread");
+        assert!(busy.starts_with("Busy:"));
+        // Other threads are not held up by a busy one.
+        let other = locks.try_lock("ws-1", "t-2").expect("other thread");
+        let same_id_elsewhere = locks.try_lock("ws-2", "t-1").expect("other workspace");
+
+        drop(first);
+        let second = locks.try_lock("ws-1", "t-1").expect("free again");
+        drop((second, other, same_id_elsewhere));
+
+        let _idle = locks.try_lock("ws-1", "t-3").expect("idle thread");
+        assert_eq!(locks.locks.lock().expect("locks").len(), 1);
+    }
+}
@@ -0,0 +1,233 @@
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use crate::shared::claude_core::ClaudeThreadRecord;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 50;
+
+/// Kept in step with every thread snapshot written to disk, so quick find never has to lock
+/// the store.
+static INDEX: OnceLock<Mutex<QuickFindIndex>> = OnceLock::new();
+
+/// `(workspace id, thread id)`.
+type ThreadKey = (String, String);
+
+#[derive(Debug, PartialEq)]
+struct IndexedThread {
+    name: Option<String>,
+    preview: String,
+    updated_at: i64,
+}
+
+/// Word-prefix index over thread names and previews.
+#[derive(Default)]
+pub(crate) struct QuickFindIndex {
+    threads: HashMap<ThreadKey, IndexedThread>,
+    tokens: BTreeMap<String, HashSet<ThreadKey>>,
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+fn thread_tokens(thread: &IndexedThread) -> HashSet<String> {
+    tokenize(thread.name.as_deref().unwrap_or_default())
+        .chain(tokenize(&thread.preview))
+        .collect()
+}
+
+impl QuickFindIndex {
+    fn insert(&mut self, key: ThreadKey, thread: IndexedThread) {
+        for token in thread_tokens(&thread) {
+            self.tokens.entry(token).or_default().insert(key.clone());
+        }
+        self.threads.insert(key, thread);
+    }
+
+    fn remove(&mut self, key: &ThreadKey) {
+        let Some(thread) = self.threads.remove(key) else {
+            return;
+        };
+        for token in thread_tokens(&thread) {
+            if let Some(keys) = self.tokens.get_mut(&token) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// Brings the index in line with a full store snapshot; only threads whose name, preview
+    /// or update time changed are re-tokenized.
+    pub(crate) fn sync(&mut self, snapshot: &HashMap<String, Vec<ClaudeThreadRecord>>) {
+        let mut seen = HashSet::new();
+        for (workspace_id, threads) in snapshot {
+            for thread in threads {
+                let key = (workspace_id.clone(), thread.id.clone());
+                let indexed = IndexedThread {
+                    name: thread.name.clone(),
+                    preview: thread.preview.clone(),
+                    updated_at: thread.updated_at,
+                };
+                if self.threads.get(&key) != Some(&indexed) {
+                    self.remove(&key);
+                    self.insert(key.clone(), indexed);
+                }
+                seen.insert(key);
+            }
+        }
+        let stale = self
+            .threads
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in stale {
+            self.remove(&key);
+        }
+    }
+
+    fn prefix_matches(&self, term: &str) -> HashSet<&ThreadKey> {
+        self.tokens
+            .range(term.to_string()..)
+            .take_while(|(token, _)| token.starts_with(term))
+            .flat_map(|(_, keys)| keys)
+            .collect()
+    }
+
+    /// Threads where every query word starts some word of the name or preview, most recently
+    /// updated first.
+    pub(crate) fn find(&self, workspace_id: Option<&str>, query: &str, limit: usize) -> Vec<Value> {
+        let mut matches: Option<HashSet<&ThreadKey>> = None;
+        for term in tokenize(query) {
+            let found = self.prefix_matches(&term);
+            matches = Some(match matches {
+                Some(previous) => previous.intersection(&found).copied().collect(),
+                None => found,
+            });
+        }
+        let mut matches = matches
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(workspace, _)| workspace_id.is_none_or(|id| id == workspace))
+            .filter_map(|key| self.threads.get(key).map(|thread| (key, thread)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a_key, a), (b_key, b)| {
+            b.updated_at
+                .cmp(&a.updated_at)
+                .then_with(|| a_key.cmp(b_key))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|((workspace_id, thread_id), thread)| {
+                json!({
+                    "id": thread_id,
+                    "workspaceId": workspace_id,
+                    "name": thread.name,
+                    "preview": thread.preview,
+                    "updatedAt": thread.updated_at,
+                })
+            })
+            .collect()
+    }
+}
+
+fn index() -> &'static Mutex<QuickFindIndex> {
+    INDEX.get_or_init(|| Mutex::new(QuickFindIndex::default()))
+}
+
+/// Called with every snapshot of the thread store that is loaded or written.
+pub(crate) fn index_snapshot(snapshot: &HashMap<String, Vec<ClaudeThreadRecord>>) {
+    if let Ok(mut index) = index().lock() {
+        index.sync(snapshot);
+    }
+}
+
+/// Search-as-you-type over thread titles and previews, across all workspaces unless one is
+/// given. An empty query matches nothing.
+pub(crate) fn quick_find_core(
+    workspace_id: Option<&str>,
+    query: &str,
+    limit: Option<u32>,
+) -> Result<Value, String> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+    let data = index()
+        .lock()
+        .map_err(|_| "thread index unavailable".to_string())?
+        .find(workspace_id, query, limit);
+    Ok(json!({ "result": { "data": data } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuickFindIndex;
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn thread(id: &str, name: Option<&str>, preview: &str, updated_at: i64) -> ClaudeThreadRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "cwd": "/repo",
+            "preview": preview,
+            "createdAt": 1,
+            "updatedAt": updated_at,
+            "name": name,
+        }))
+        .expect("thread")
+    }
+
+    fn ids(index: &QuickFindIndex, workspace_id: Option<&str>, query: &str) -> Vec<String> {
+        index
+            .find(workspace_id, query, 10)
+            .iter()
+            .map(|thread| thread["id"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_threads_by_word_prefixes_and_follows_the_store() {
+        let mut snapshot = HashMap::from([
+            (
+                "ws-1".to_string(),
+                vec![
+                    thread(
+                        "t-login",
+                        Some("Fix login redirect"),
+                        "The OAuth flow loops",
+                        10,
+                    ),
+                    thread("t-logs", None, "Rotate server logs nightly", 20),
+                ],
+            ),
+            (
+                "ws-2".to_string(),
+                vec![thread("t-other", Some("Login page copy"), "", 30)],
+            ),
+        ]);
+        let mut index = QuickFindIndex::default();
+        index.sync(&snapshot);
+
+        assert_eq!(
+            ids(&index, None, "log"),
+            vec!["t-other", "t-logs", "t-login"]
+        );
+        assert_eq!(ids(&index, Some("ws-1"), "LOG"), vec!["t-logs", "t-login"]);
+        assert_eq!(ids(&index, None, "login oau"), vec!["t-login"]);
+        assert!(ids(&index, None, "  ").is_empty());
+        assert!(ids(&index, None, "ogin").is_empty());
+
+        snapshot.get_mut("ws-1").expect("ws-1")[0].name = Some("Session expiry".to_string());
+        snapshot.remove("ws-2");
+        index.sync(&snapshot);
+        assert_eq!(ids(&index, None, "login"), Vec::<String>::new());
+        assert_eq!(ids(&index, None, "sess"), vec!["t-login"]);
+        assert!(!index.tokens.contains_key("page"));
+    }
+}
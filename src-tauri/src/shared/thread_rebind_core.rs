@@ -0,0 +1,244 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::EventSink;
+use crate::providers::resolve_workspace_provider;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, move_thread_core, now_ms, persist_threads_store,
+    relocate_thread_record, ClaudeThreadsStore,
+};
+use crate::shared::git_core::git_get_origin_url;
+use crate::types::{AppSettings, ProviderKind, WorkspaceEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum RebindMatch {
+    /// The workspace's `origin` is the same repository.
+    GitRemote,
+    /// Only the folder name matches; used when the repository could not be identified.
+    FolderName,
+}
+
+/// A workspace a thread whose directory is gone could be rebound to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RebindCandidate {
+    pub(crate) workspace_id: String,
+    pub(crate) name: String,
+    pub(crate) path: String,
+    pub(crate) matched_by: RebindMatch,
+}
+
+/// Host and path of a git remote, so `git@github.com:owner/repo.git`,
+/// `https://github.com/owner/repo` and `ssh://git@github.com:22/owner/repo` compare equal.
+pub(crate) fn remote_key(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp-like `user@host:path`; plain local paths have no colon and pass through.
+        None => url.replacen(':', "/", 1),
+    };
+    let (host, path) = rest.split_once('/').unwrap_or((rest.as_str(), ""));
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host);
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return None;
+    }
+    Some(format!("{host}/{path}").to_ascii_lowercase())
+}
+
+async fn origin_key(path: &str) -> Option<String> {
+    let url = git_get_origin_url(&PathBuf::from(path)).await?;
+    remote_key(&url)
+}
+
+/// Ranks Claude workspaces whose folder exists as new homes for a thread that ran in `cwd`.
+/// Remote matches come first, then the thread's own workspace, then by name.
+async fn rebind_candidates(
+    entries: &[WorkspaceEntry],
+    settings: &AppSettings,
+    workspace_id: &str,
+    cwd: &str,
+    remote: Option<&str>,
+) -> Vec<RebindCandidate> {
+    let folder_name = Path::new(cwd).file_name();
+    let mut candidates = Vec::new();
+    for entry in entries {
+        if resolve_workspace_provider(entry, Some(settings)) != ProviderKind::Claude
+            || !Path::new(&entry.path).is_dir()
+        {
+            continue;
+        }
+        let same_remote = match remote {
+            Some(remote) => origin_key(&entry.path).await.as_deref() == Some(remote),
+            None => false,
+        };
+        let matched_by = if same_remote {
+            RebindMatch::GitRemote
+        } else if folder_name.is_some() && Path::new(&entry.path).file_name() == folder_name {
+            RebindMatch::FolderName
+        } else {
+            continue;
+        };
+        candidates.push(RebindCandidate {
+            workspace_id: entry.id.clone(),
+            name: entry.name.clone(),
+            path: entry.path.clone(),
+            matched_by,
+        });
+    }
+    candidates.sort_by(|a, b| {
+        (a.matched_by != RebindMatch::GitRemote)
+            .cmp(&(b.matched_by != RebindMatch::GitRemote))
+            .then_with(|| (a.workspace_id != workspace_id).cmp(&(b.workspace_id != workspace_id)))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    candidates
+}
+
+/// Adds `cwdRepair` to a Claude resume response when the thread's directory no longer exists,
+/// e.g. because the repository was moved after its history was imported. The repository is
+/// identified by the `origin` of the thread's workspace folder when that still exists.
+pub(crate) async fn attach_cwd_repair(
+    response: &mut Value,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+) {
+    let cwd = {
+        let store = claude_threads.lock().await;
+        store
+            .get(workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+            .filter(|thread| thread.worktree.is_none())
+            .map(|thread| thread.cwd.clone())
+    };
+    let Some(cwd) = cwd.filter(|cwd| !Path::new(cwd).is_dir()) else {
+        return;
+    };
+    let entries = workspaces
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let settings = app_settings.lock().await.clone();
+    let remote = match entries.iter().find(|entry| entry.id == workspace_id) {
+        Some(entry) if Path::new(&entry.path).is_dir() => origin_key(&entry.path).await,
+        _ => None,
+    };
+    let candidates =
+        rebind_candidates(&entries, &settings, workspace_id, &cwd, remote.as_deref()).await;
+    response["result"]["thread"]["cwdRepair"] = json!({
+        "cwd": cwd,
+        "gitRemote": remote,
+        "candidates": candidates,
+    });
+}
+
+/// Points a thread at a workspace whose folder exists. Rebinding to its own workspace only
+/// updates the directory; any other workspace moves the thread there. Either way the next
+/// message starts a Claude session seeded from the transcript, since sessions are keyed by cwd.
+pub(crate) async fn rebind_thread_workspace_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    target_workspace_id: String,
+    event_sink: E,
+) -> Result<Value, String> {
+    let target_path = workspaces
+        .lock()
+        .await
+        .get(&target_workspace_id)
+        .map(|entry| entry.path.clone())
+        .ok_or_else(|| "workspace not found".to_string())?;
+    if !Path::new(&target_path).is_dir() {
+        return Err(format!("{target_path} does not exist."));
+    }
+    if target_workspace_id != workspace_id {
+        return move_thread_core(
+            workspaces,
+            app_settings,
+            claude_threads,
+            claude_threads_path,
+            workspace_id,
+            target_workspace_id,
+            thread_id,
+            event_sink,
+        )
+        .await;
+    }
+    {
+        let mut store = claude_threads.lock().await;
+        let thread = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        if thread
+            .turns
+            .last()
+            .is_some_and(|turn| turn.completed_at.is_none())
+        {
+            return Err(
+                "Wait for the running turn to finish before rebinding the thread.".to_string(),
+            );
+        }
+        if thread.cwd != target_path {
+            *thread = relocate_thread_record(thread, &target_path, false, now_ms());
+        }
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit_thread_list_updated(
+        &event_sink,
+        claude_threads,
+        &workspace_id,
+        std::slice::from_ref(&thread_id),
+        &[],
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "workspaceId": workspace_id,
+            "threadId": thread_id,
+            "cwd": target_path,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remote_key;
+
+    #[test]
+    fn remote_keys_ignore_transport_user_port_and_suffix() {
+        let expected = Some("github.com/owner/repo".to_string());
+        assert_eq!(remote_key("git@github.com:owner/repo.git"), expected);
+        assert_eq!(remote_key("https://github.com/Owner/Repo"), expected);
+        assert_eq!(
+            remote_key("https://token@github.com/owner/repo.git/"),
+            expected
+        );
+        assert_eq!(
+            remote_key("ssh://git@github.com:22/owner/repo.git"),
+            expected
+        );
+        assert_eq!(
+            remote_key("/srv/git/repo.git"),
+            Some("/srv/git/repo".to_string())
+        );
+        assert_ne!(remote_key("git@github.com:owner/other.git"), expected);
+        assert_eq!(remote_key("https://github.com/"), None);
+        assert_eq!(remote_key(""), None);
+    }
+}
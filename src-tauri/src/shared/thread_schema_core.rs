@@ -0,0 +1,121 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::shared::claude_core::{migrate_legacy_session, ClaudeThreadRecord};
+use crate::shared::thread_stats_core::ThreadStats;
+use crate::types::ProviderKind;
+
+/// Schema of `claude_threads.json`. Bump it together with a new entry in [`MIGRATIONS`] and a
+/// fixture of the version being replaced.
+pub(crate) const THREADS_SCHEMA_VERSION: u64 = 1;
+const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+const WORKSPACES_KEY: &str = "workspaces";
+
+/// `MIGRATIONS[n]` upgrades one thread record from schema `n` to `n + 1`. They work on the raw
+/// JSON so a migration can rename or reshape fields that serde would drop or reject.
+const MIGRATIONS: &[ThreadMigration] = &[migrate_thread_v0];
+
+type ThreadMigration = fn(&mut Value) -> Result<(), String>;
+type ThreadsSnapshot = HashMap<String, Vec<ClaudeThreadRecord>>;
+
+/// Schema 0 is the bare `{ workspaceId: [thread] }` map saved before snapshots had a version.
+/// Its threads may predate tags, stats, or session ids separate from the thread id.
+fn migrate_thread_v0(thread: &mut Value) -> Result<(), String> {
+    let mut record: ClaudeThreadRecord =
+        serde_json::from_value(thread.take()).map_err(|error| error.to_string())?;
+    migrate_legacy_session(&mut record);
+    if record.stats.is_none() {
+        record.stats = Some(ThreadStats::from_turns(&record.turns, ProviderKind::Claude));
+    }
+    *thread = serde_json::to_value(record).map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+/// Reads a snapshot of any schema version, running the migrations its threads are missing.
+/// Snapshots from a newer version load as far as this version understands them.
+pub(crate) fn parse_threads_snapshot(data: &str) -> Result<ThreadsSnapshot, String> {
+    let value: Value = serde_json::from_str(data).map_err(|error| error.to_string())?;
+    let (version, mut workspaces) = match value {
+        Value::Object(mut snapshot) if snapshot.contains_key(SCHEMA_VERSION_KEY) => {
+            let version = snapshot
+                .get(SCHEMA_VERSION_KEY)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| format!("invalid {SCHEMA_VERSION_KEY}"))?;
+            let workspaces = snapshot.remove(WORKSPACES_KEY).unwrap_or_else(|| json!({}));
+            (version, workspaces)
+        }
+        legacy => (0, legacy),
+    };
+    let pending = MIGRATIONS.get(version as usize..).unwrap_or_default();
+    if !pending.is_empty() {
+        let threads = workspaces
+            .as_object_mut()
+            .ok_or_else(|| "threads snapshot is not an object".to_string())?
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten();
+        for thread in threads {
+            for migrate in pending {
+                migrate(thread)?;
+            }
+        }
+    }
+    serde_json::from_value(workspaces).map_err(|error| error.to_string())
+}
+
+/// The snapshot as saved, tagged with the current schema version.
+pub(crate) fn versioned_snapshot(threads: &ThreadsSnapshot) -> Value {
+    json!({ "schemaVersion": THREADS_SCHEMA_VERSION, "workspaces": threads })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_threads_snapshot, versioned_snapshot, THREADS_SCHEMA_VERSION};
+
+    /// One fixture per schema version, each saving the same threads.
+    const FIXTURES: &[(u64, &str)] = &[
+        (0, include_str!("fixtures/claude_threads_v0.json")),
+        (1, include_str!("fixtures/claude_threads_v1.json")),
+    ];
+    const SESSION: &str = "0b9a5f8e-4c2d-4e31-9a57-6f1d2c3b4a59";
+    const FRESH_SESSION: &str = "7d3e1a2b-9c8f-4b6a-8e5d-1f2a3b4c5d6e";
+
+    #[test]
+    fn loads_snapshots_from_every_schema_version() {
+        assert_eq!(FIXTURES.len() as u64, THREADS_SCHEMA_VERSION + 1);
+        let mut loaded = Vec::new();
+        for (version, fixture) in FIXTURES {
+            let threads = parse_threads_snapshot(fixture)
+                .unwrap_or_else(|error| panic!("schema {version}: {error}"));
+            let threads = &threads["ws-1"];
+            assert_eq!(threads.len(), 3, "schema {version}");
+
+            let ran = &threads[0];
+            assert_eq!(ran.provider_session_id.as_deref(), Some(SESSION));
+            let stats = ran.stats.as_ref().expect("stats");
+            assert_eq!((stats.turn_count, stats.assistant_chars), (1, 5));
+            let fresh = &threads[1];
+            assert_eq!(fresh.session_id.as_deref(), Some(FRESH_SESSION));
+            assert!(fresh.session_needs_seed);
+            let tagged = &threads[2];
+            assert_eq!(tagged.tags, vec!["release"]);
+            assert_eq!(tagged.name.as_deref(), Some("Release notes"));
+
+            let saved = versioned_snapshot(&parse_threads_snapshot(fixture).expect("parse"));
+            assert_eq!(saved["schemaVersion"], THREADS_SCHEMA_VERSION);
+            let reloaded = parse_threads_snapshot(&saved.to_string()).expect("reload");
+            assert_eq!(
+                serde_json::to_value(&reloaded).expect("serialize"),
+                saved["workspaces"]
+            );
+            loaded.push(saved);
+        }
+        assert!(loaded.windows(2).all(|pair| pair[0] == pair[1]));
+
+        let newer = r#"{ "schemaVersion": 99, "workspaces": { "ws-1": [] }, "extra": true }"#;
+        assert!(parse_threads_snapshot(newer).expect("newer")["ws-1"].is_empty());
+        let invalid = r#"{ "schemaVersion": "one", "workspaces": {} }"#;
+        assert!(parse_threads_snapshot(invalid).is_err());
+        assert!(parse_threads_snapshot("{}").expect("empty").is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::claude_core::{ClaudeThreadRecord, ClaudeTurnRecord};
+use crate::types::ProviderKind;
+
+/// Per-thread counters for listings, updated as each turn finishes so a listing never has to
+/// walk every turn.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ThreadStats {
+    #[serde(default, rename = "turnCount")]
+    pub(crate) turn_count: u32,
+    #[serde(default, rename = "assistantChars")]
+    pub(crate) assistant_chars: u64,
+    #[serde(default, rename = "lastProvider")]
+    pub(crate) last_provider: Option<ProviderKind>,
+    #[serde(default, rename = "errorCount")]
+    pub(crate) error_count: u32,
+}
+
+impl ThreadStats {
+    /// Counts the finished turns of a thread that has no stats yet. Failures were not recorded
+    /// before stats existed, so the error count starts at zero.
+    pub(crate) fn from_turns(turns: &[ClaudeTurnRecord], provider: ProviderKind) -> Self {
+        let mut stats = Self::default();
+        for turn in turns.iter().filter(|turn| turn.completed_at.is_some()) {
+            let assistant_text = turn
+                .items
+                .iter()
+                .filter(|item| item.role == "assistant")
+                .map(|item| item.text.as_str())
+                .collect::<String>();
+            stats.record_turn(&assistant_text, provider.clone(), false);
+        }
+        stats
+    }
+
+    fn record_turn(&mut self, assistant_text: &str, provider: ProviderKind, failed: bool) {
+        self.turn_count += 1;
+        self.assistant_chars += assistant_text.chars().count() as u64;
+        self.last_provider = Some(provider);
+        if failed {
+            self.error_count += 1;
+        }
+    }
+}
+
+/// Stats for a listing; threads saved before stats existed get them counted on the fly.
+pub(crate) fn listing_stats(thread: &ClaudeThreadRecord) -> ThreadStats {
+    thread
+        .stats
+        .clone()
+        .unwrap_or_else(|| ThreadStats::from_turns(&thread.turns, ProviderKind::Claude))
+}
+
+/// Adds a finished turn to the thread's cached stats. Call before the turn is marked complete
+/// so a backfill from stored turns does not count it twice.
+pub(crate) fn record_finished_turn(
+    thread: &mut ClaudeThreadRecord,
+    assistant_text: &str,
+    failed: bool,
+) {
+    let turns = &thread.turns;
+    thread
+        .stats
+        .get_or_insert_with(|| ThreadStats::from_turns(turns, ProviderKind::Claude))
+        .record_turn(assistant_text, ProviderKind::Claude, failed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{listing_stats, record_finished_turn, ThreadStats};
+    use crate::shared::claude_core::ClaudeThreadRecord;
+    use crate::types::ProviderKind;
+    use serde_json::json;
+
+    #[test]
+    fn backfills_from_finished_turns_then_counts_incrementally() {
+        let mut thread: ClaudeThreadRecord = serde_json::from_value(json!({
+            "id": "thread-1",
+            "cwd": "/repo",
+            "preview": "",
+            "createdAt": 1,
+            "updatedAt": 2,
+            "turns": [
+                {
+                    "id": "turn-1",
+                    "startedAt": 1,
+                    "completedAt": 2,
+                    "items": [
+                        { "id": "u1", "role": "user", "text": "Fix the build" },
+                        { "id": "a1", "role": "assistant", "text": "Fixed ✓" },
+                    ],
+                },
+                {
+                    "id": "turn-2",
+                    "startedAt": 3,
+                    "completedAt": null,
+                    "items": [{ "id": "u2", "role": "user", "text": "Run the tests" }],
+                },
+            ],
+        }))
+        .expect("thread");
+        assert_eq!(listing_stats(&thread).turn_count, 1);
+        assert!(thread.stats.is_none());
+
+        record_finished_turn(&mut thread, "Tests fail", true);
+
+        assert_eq!(
+            thread.stats,
+            Some(ThreadStats {
+                turn_count: 2,
+                assistant_chars: 17,
+                last_provider: Some(ProviderKind::Claude),
+                error_count: 1,
+            })
+        );
+    }
+}
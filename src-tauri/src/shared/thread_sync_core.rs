@@ -0,0 +1,257 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{self, ClaudeThreadRecord, ClaudeThreadsStore};
+use crate::shared::redaction_core::RedactionPipeline;
+use crate::shared::settings_core::ensure_not_read_only;
+use crate::types::{AppSettings, WorkspaceEntry};
+
+/// How often the background job looks again while periodic syncing is off.
+const SYNC_SETTING_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a sync can change about a thread as far as listings are concerned.
+type ThreadVersion = (i64, i64, usize, String);
+
+fn thread_versions(
+    claude_threads: &HashMap<String, Vec<ClaudeThreadRecord>>,
+    workspace_id: &str,
+) -> HashMap<String, ThreadVersion> {
+    claude_threads
+        .get(workspace_id)
+        .into_iter()
+        .flatten()
+        .map(|thread| {
+            let version = (
+                thread.created_at,
+                thread.updated_at,
+                thread.turns.len(),
+                thread.cwd.clone(),
+            );
+            (thread.id.clone(), version)
+        })
+        .collect()
+}
+
+/// Threads added or updated, and threads removed, between two versions of a workspace.
+fn thread_changes(
+    before: &HashMap<String, ThreadVersion>,
+    after: &HashMap<String, ThreadVersion>,
+) -> (Vec<String>, Vec<String>) {
+    let mut changed = after
+        .iter()
+        .filter(|(id, version)| before.get(*id) != Some(*version))
+        .map(|(id, _)| id.clone())
+        .collect::<Vec<_>>();
+    let mut removed = before
+        .keys()
+        .filter(|id| !after.contains_key(*id))
+        .cloned()
+        .collect::<Vec<_>>();
+    changed.sort();
+    removed.sort();
+    (changed, removed)
+}
+
+/// Imports the workspace's Claude CLI history and drops placeholder and archived threads.
+/// Listing threads never does this, so it only happens here and in the background job.
+pub(crate) async fn sync_threads_core<E: EventSink>(
+    app_settings: &Mutex<AppSettings>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: &str,
+    workspace_path: &str,
+    redaction: &RedactionPipeline,
+    event_sink: &E,
+) -> Result<Value, String> {
+    ensure_not_read_only(app_settings, "Syncing threads").await?;
+    let before = thread_versions(&*claude_threads.lock().await, workspace_id);
+    claude_core::import_history_threads_for_workspace(
+        claude_threads,
+        claude_threads_path,
+        workspace_id,
+        workspace_path,
+        redaction,
+    )
+    .await?;
+    claude_core::prune_placeholder_threads_for_workspace(
+        claude_threads,
+        claude_threads_path,
+        workspace_id,
+    )
+    .await?;
+    let after = thread_versions(&*claude_threads.lock().await, workspace_id);
+
+    let (changed, removed) = thread_changes(&before, &after);
+    claude_core::emit_thread_list_updated(
+        event_sink,
+        claude_threads,
+        workspace_id,
+        &changed,
+        &removed,
+    )
+    .await;
+    Ok(json!({
+        "result": {
+            "workspaceId": workspace_id,
+            "changedThreadIds": changed,
+            "removedThreadIds": removed,
+        }
+    }))
+}
+
+/// One pass of the background job over every workspace. A failing workspace is reported and
+/// skipped; in read-only mode the pass does nothing.
+pub(crate) async fn sync_all_workspaces_core<E: EventSink>(
+    app_settings: &Mutex<AppSettings>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    redaction: &RedactionPipeline,
+    event_sink: E,
+) -> Result<(), String> {
+    if app_settings.lock().await.read_only {
+        return Ok(());
+    }
+    let workspaces = workspaces
+        .lock()
+        .await
+        .values()
+        .map(|entry| (entry.id.clone(), entry.path.clone()))
+        .collect::<Vec<_>>();
+    let mut failures = Vec::new();
+    for (workspace_id, workspace_path) in workspaces {
+        if let Err(error) = sync_threads_core(
+            app_settings,
+            claude_threads,
+            claude_threads_path,
+            &workspace_id,
+            &workspace_path,
+            redaction,
+            &event_sink,
+        )
+        .await
+        {
+            failures.push(format!("{workspace_id}: {error}"));
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+fn sync_interval(settings: &AppSettings) -> Option<Duration> {
+    settings
+        .history_sync_interval_minutes
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+}
+
+/// Sleeps until the next background pass is due. Periodic syncing is off until
+/// `historySyncIntervalMinutes` is set; turning it on takes effect within a minute.
+pub(crate) async fn wait_for_next_pass(app_settings: &Mutex<AppSettings>) {
+    loop {
+        let interval = sync_interval(&*app_settings.lock().await);
+        match interval {
+            Some(interval) => {
+                tokio::time::sleep(interval).await;
+                return;
+            }
+            None => tokio::time::sleep(SYNC_SETTING_CHECK_INTERVAL).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sync_all_workspaces_core, sync_interval, sync_threads_core, thread_changes};
+    use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+    use crate::shared::redaction_core::RedactionPipeline;
+    use crate::types::{AppSettings, WorkspaceEntry};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    #[derive(Clone)]
+    struct NoopSink;
+
+    impl EventSink for NoopSink {
+        fn emit_app_server_event(&self, _event: AppServerEvent) {}
+        fn emit_terminal_output(&self, _event: TerminalOutput) {}
+        fn emit_terminal_exit(&self, _event: TerminalExit) {}
+    }
+
+    #[test]
+    fn reports_changed_and_removed_threads() {
+        let before = HashMap::from([
+            ("kept".to_string(), (1, 10, 2, "/repo".to_string())),
+            ("updated".to_string(), (1, 10, 2, "/repo".to_string())),
+            ("pruned".to_string(), (1, 10, 0, "/repo".to_string())),
+        ]);
+        let after = HashMap::from([
+            ("kept".to_string(), (1, 10, 2, "/repo".to_string())),
+            ("updated".to_string(), (1, 20, 3, "/repo".to_string())),
+            ("imported".to_string(), (5, 5, 1, "/repo".to_string())),
+        ]);
+        let (changed, removed) = thread_changes(&before, &after);
+        assert_eq!(changed, vec!["imported", "updated"]);
+        assert_eq!(removed, vec!["pruned"]);
+
+        let mut settings = AppSettings::default();
+        assert_eq!(sync_interval(&settings), None);
+        settings.history_sync_interval_minutes = Some(0);
+        assert_eq!(sync_interval(&settings), None);
+        settings.history_sync_interval_minutes = Some(15);
+        assert_eq!(sync_interval(&settings), Some(Duration::from_secs(900)));
+    }
+
+    #[test]
+    fn read_only_mode_skips_syncing() {
+        let settings = Mutex::new(AppSettings {
+            read_only: true,
+            ..AppSettings::default()
+        });
+        let entry: WorkspaceEntry = serde_json::from_value(json!({
+            "id": "ws",
+            "name": "ws",
+            "path": "/repo",
+        }))
+        .expect("workspace");
+        let workspaces = Mutex::new(HashMap::from([("ws".to_string(), entry)]));
+        let claude_threads = Default::default();
+        let threads_path = std::env::temp_dir().join("polycode-read-only-sync/threads.json");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let error = runtime
+            .block_on(sync_threads_core(
+                &settings,
+                &claude_threads,
+                &threads_path,
+                "ws",
+                "/repo",
+                &RedactionPipeline::default(),
+                &NoopSink,
+            ))
+            .expect_err("read-only");
+        assert!(error.starts_with("PermissionDenied:"));
+        runtime
+            .block_on(sync_all_workspaces_core(
+                &settings,
+                &workspaces,
+                &claude_threads,
+                &threads_path,
+                &RedactionPipeline::default(),
+                NoopSink,
+            ))
+            .expect("the background pass is skipped");
+        assert!(!threads_path.exists());
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, now_ms, persist_threads_store, ClaudeThreadsStore,
+};
+use crate::shared::git_core::{git_branch_exists, run_git_command};
+use crate::shared::worktree_core::{sanitize_worktree_name, unique_worktree_path_strict};
+use crate::types::WorkspaceEntry;
+
+pub(crate) const THREAD_WORKTREES_DIR_NAME: &str = "thread-worktrees";
+const THREAD_BRANCH_PREFIX: &str = "polycode/thread-";
+
+/// A git worktree created for one thread so agent edits stay off the main checkout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ThreadWorktree {
+    pub(crate) path: String,
+    pub(crate) branch: String,
+    /// Branch the main checkout was on when the thread started; merges go back into it.
+    #[serde(rename = "baseBranch")]
+    pub(crate) base_branch: String,
+}
+
+/// `polycode/thread-<first 8 chars of the thread id>`, suffixed when the name is taken.
+fn thread_branch_name(thread_id: &str, attempt: u32) -> String {
+    let short_id = thread_id.chars().take(8).collect::<String>();
+    if attempt == 0 {
+        format!("{THREAD_BRANCH_PREFIX}{short_id}")
+    } else {
+        format!("{THREAD_BRANCH_PREFIX}{short_id}-{}", attempt + 1)
+    }
+}
+
+/// Creates a branch and worktree for a new thread, starting from the main checkout's HEAD.
+pub(crate) async fn create_thread_worktree(
+    repo_path: &str,
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+) -> Result<ThreadWorktree, String> {
+    let repo_path = PathBuf::from(repo_path);
+    let base_branch = run_git_command(&repo_path, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .await
+        .map_err(|error| format!("Thread worktrees need a git repository: {error}"))?;
+    if base_branch == "HEAD" {
+        return Err("Thread worktrees need the main checkout to be on a branch.".to_string());
+    }
+    let mut attempt = 0;
+    let branch = loop {
+        let candidate = thread_branch_name(thread_id, attempt);
+        if !git_branch_exists(&repo_path, &candidate).await? {
+            break candidate;
+        }
+        attempt += 1;
+        if attempt >= 100 {
+            return Err("Failed to find an available thread branch name.".to_string());
+        }
+    };
+    let worktree_root = data_dir.join(THREAD_WORKTREES_DIR_NAME).join(workspace_id);
+    std::fs::create_dir_all(&worktree_root)
+        .map_err(|error| format!("Failed to create worktree directory: {error}"))?;
+    let worktree_path =
+        unique_worktree_path_strict(&worktree_root, &sanitize_worktree_name(&branch))?;
+    let worktree_path = worktree_path.to_string_lossy().to_string();
+    run_git_command(
+        &repo_path,
+        &["worktree", "add", "-b", &branch, &worktree_path, "HEAD"],
+    )
+    .await?;
+    Ok(ThreadWorktree {
+        path: worktree_path,
+        branch,
+        base_branch,
+    })
+}
+
+/// Commits whatever the agent left uncommitted in the thread worktree.
+async fn commit_pending_changes(worktree: &ThreadWorktree, title: &str) -> Result<bool, String> {
+    let worktree_path = PathBuf::from(&worktree.path);
+    let status = run_git_command(&worktree_path, &["status", "--porcelain"]).await?;
+    if status.trim().is_empty() {
+        return Ok(false);
+    }
+    run_git_command(&worktree_path, &["add", "-A"]).await?;
+    let message = format!("PolyCode: {title}");
+    run_git_command(&worktree_path, &["commit", "-m", &message]).await?;
+    Ok(true)
+}
+
+/// Commits pending agent edits in the thread's worktree and merges its branch into the base
+/// branch of the main checkout. Conflicts abort the merge and leave both sides untouched.
+/// With `remove_worktree`, the worktree and branch are deleted and the thread moves back to
+/// the main checkout.
+pub(crate) async fn merge_thread_worktree_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_id: String,
+    remove_worktree: bool,
+    event_sink: E,
+) -> Result<Value, String> {
+    let repo_path = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .map(|entry| PathBuf::from(&entry.path))
+            .ok_or_else(|| "workspace not found".to_string())?
+    };
+    let (worktree, title) = {
+        let store = claude_threads.lock().await;
+        let thread = store
+            .get(&workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        let worktree = thread
+            .worktree
+            .clone()
+            .ok_or_else(|| "This thread does not run in its own worktree.".to_string())?;
+        let title = thread
+            .name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| worktree.branch.clone());
+        (worktree, title)
+    };
+
+    let committed = commit_pending_changes(&worktree, &title).await?;
+    let current_branch =
+        run_git_command(&repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+    if current_branch != worktree.base_branch {
+        return Err(format!(
+            "The main checkout is on `{current_branch}`; switch back to `{}` to merge.",
+            worktree.base_branch
+        ));
+    }
+    let message = format!("Merge {} ({title})", worktree.branch);
+    if let Err(error) = run_git_command(
+        &repo_path,
+        &["merge", "--no-ff", "-m", &message, &worktree.branch],
+    )
+    .await
+    {
+        let _ = run_git_command(&repo_path, &["merge", "--abort"]).await;
+        return Err(format!("Merge failed and was aborted: {error}"));
+    }
+    let merge_commit = run_git_command(&repo_path, &["rev-parse", "HEAD"]).await?;
+
+    if remove_worktree {
+        run_git_command(
+            &repo_path,
+            &["worktree", "remove", "--force", &worktree.path],
+        )
+        .await?;
+        run_git_command(&repo_path, &["branch", "-D", &worktree.branch]).await?;
+        let mut store = claude_threads.lock().await;
+        if let Some(thread) = store
+            .get_mut(&workspace_id)
+            .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        {
+            // Claude keys sessions by cwd, so the thread continues in a fresh, seeded session.
+            if !thread.turns.is_empty() {
+                thread.session_id = Some(Uuid::new_v4().to_string());
+                thread.session_needs_seed = true;
+            }
+            thread.worktree = None;
+            thread.cwd = repo_path.to_string_lossy().to_string();
+            thread.updated_at = now_ms();
+        }
+        drop(store);
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        emit_thread_list_updated(
+            &event_sink,
+            claude_threads,
+            &workspace_id,
+            std::slice::from_ref(&thread_id),
+            &[],
+        )
+        .await;
+    }
+
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "branch": worktree.branch,
+            "baseBranch": worktree.base_branch,
+            "committedPendingChanges": committed,
+            "mergeCommit": merge_commit,
+            "worktreeRemoved": remove_worktree,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::thread_branch_name;
+
+    #[test]
+    fn thread_branches_are_named_after_the_thread() {
+        let thread_id = "3f2a9c1e-7b44-4d0b-9a51-0c8e2f6d1a22";
+        assert_eq!(thread_branch_name(thread_id, 0), "polycode/thread-3f2a9c1e");
+        assert_eq!(
+            thread_branch_name(thread_id, 1),
+            "polycode/thread-3f2a9c1e-2"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use base64::Engine;
+use serde_json::Value;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::shared::settings_core::ensure_not_read_only;
+use crate::types::AppSettings;
+
+const MAX_AUDIO_BYTES: usize = 50 * 1024 * 1024;
+
+fn decode_audio_payload(audio: &str) -> Result<Vec<u8>, String> {
+    let payload = audio
+        .split_once("base64,")
+        .map(|(_, data)| data)
+        .unwrap_or(audio)
+        .trim();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload.as_bytes())
+        .map_err(|error| format!("Invalid audio payload: {error}"))?;
+    if bytes.is_empty() {
+        return Err("Audio payload is empty.".to_string());
+    }
+    if bytes.len() > MAX_AUDIO_BYTES {
+        return Err("Audio payload is too large.".to_string());
+    }
+    Ok(bytes)
+}
+
+/// Posts raw audio to a transcription endpoint that replies with `{ "text": "..." }`.
+async fn transcribe_via_endpoint(
+    endpoint: &str,
+    audio: Vec<u8>,
+    mime_type: &str,
+) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|error| format!("Failed to configure transcription client: {error}"))?;
+    let response = client
+        .post(endpoint)
+        .header("Content-Type", mime_type)
+        .body(audio)
+        .send()
+        .await
+        .map_err(|error| format!("Transcription request failed: {error}"))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|error| format!("Failed to read transcription response: {error}"))?;
+    if !status.is_success() {
+        return Err(format!(
+            "Transcription endpoint returned {status}: {}",
+            body.trim()
+        ));
+    }
+    parse_transcription_response(&body)
+}
+
+/// Transcribes an audio prompt before it is sent: through the configured dictation endpoint
+/// when there is one, otherwise with `local`, the host's own speech model.
+pub(crate) async fn transcribe_audio_message_core<F, Fut>(
+    app_settings: &Mutex<AppSettings>,
+    audio: &str,
+    mime_type: Option<String>,
+    local: F,
+) -> Result<String, String>
+where
+    F: FnOnce(Vec<u8>) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    ensure_not_read_only(app_settings, "Sending messages").await?;
+    let bytes = decode_audio_payload(audio)?;
+    let endpoint = app_settings
+        .lock()
+        .await
+        .dictation_endpoint
+        .clone()
+        .filter(|value| !value.trim().is_empty());
+    let transcript = match endpoint {
+        Some(endpoint) => {
+            let mime_type = mime_type.unwrap_or_else(|| "audio/wav".to_string());
+            transcribe_via_endpoint(&endpoint, bytes, &mime_type).await?
+        }
+        None => local(bytes).await?,
+    };
+    if transcript.trim().is_empty() {
+        return Err("No speech detected in audio.".to_string());
+    }
+    Ok(transcript)
+}
+
+fn parse_transcription_response(body: &str) -> Result<String, String> {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => value
+            .get("text")
+            .and_then(Value::as_str)
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| "Transcription response is missing `text`.".to_string()),
+        Err(_) => Ok(body.trim().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_audio_payload, parse_transcription_response, transcribe_audio_message_core,
+    };
+    use crate::types::AppSettings;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn decode_audio_payload_accepts_data_urls() {
+        assert_eq!(
+            decode_audio_payload("data:audio/wav;base64,AQID").expect("decode"),
+            vec![1, 2, 3]
+        );
+        assert!(decode_audio_payload("").is_err());
+    }
+
+    #[test]
+    fn transcription_response_reads_text_field() {
+        assert_eq!(
+            parse_transcription_response(r#"{"text":" hello "}"#).expect("text"),
+            "hello"
+        );
+        assert_eq!(
+            parse_transcription_response("plain").expect("text"),
+            "plain"
+        );
+        assert!(parse_transcription_response(r#"{"error":"x"}"#).is_err());
+    }
+
+    #[test]
+    fn audio_messages_fall_back_to_the_local_model_without_an_endpoint() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let settings = Mutex::new(AppSettings {
+            dictation_endpoint: Some("  ".to_string()),
+            ..AppSettings::default()
+        });
+        let transcript = runtime
+            .block_on(transcribe_audio_message_core(
+                &settings,
+                "AQID",
+                None,
+                |bytes| async move {
+                    assert_eq!(bytes, vec![1, 2, 3]);
+                    Ok(" hello ".to_string())
+                },
+            ))
+            .expect("transcript");
+        assert_eq!(transcript, " hello ");
+        let silent = runtime.block_on(transcribe_audio_message_core(
+            &settings,
+            "AQID",
+            None,
+            |_| async { Ok(String::new()) },
+        ));
+        assert_eq!(silent, Err("No speech detected in audio.".to_string()));
+
+        settings.blocking_lock().read_only = true;
+        let denied = runtime
+            .block_on(transcribe_audio_message_core(
+                &settings,
+                "AQID",
+                None,
+                |_| async { Ok("unused".to_string()) },
+            ))
+            .expect_err("read-only");
+        assert!(denied.starts_with("PermissionDenied:"));
+    }
+}
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit_thread_list_updated, now_ms, persist_threads_store, unarchive_thread_ids_for_workspace,
+    ClaudeThreadRecord, ClaudeThreadsStore,
+};
+use crate::shared::redaction_core::remove_raw_log_threads;
+use crate::shared::turn_artifacts_core::remove_thread_artifacts;
+
+const CLAUDE_THREADS_TRASH_FILE_NAME: &str = "claude_threads_trash.json";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TrashReason {
+    Archive,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TrashedThread {
+    #[serde(rename = "workspaceId")]
+    pub(crate) workspace_id: String,
+    #[serde(rename = "trashedAt")]
+    pub(crate) trashed_at: i64,
+    pub(crate) reason: TrashReason,
+    pub(crate) thread: ClaudeThreadRecord,
+}
+
+fn trash_path(claude_threads_path: &Path) -> PathBuf {
+    claude_threads_path.with_file_name(CLAUDE_THREADS_TRASH_FILE_NAME)
+}
+
+fn read_trash(path: &Path) -> Result<Vec<TrashedThread>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&data).map_err(|error| error.to_string())
+}
+
+fn write_trash(path: &Path, entries: &[TrashedThread]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(entries).map_err(|error| error.to_string())?;
+    std::fs::write(path, data).map_err(|error| error.to_string())
+}
+
+/// Keeps removed threads restorable; a thread trashed twice keeps only its latest copy.
+pub(crate) fn trash_threads(
+    claude_threads_path: &Path,
+    workspace_id: &str,
+    threads: Vec<ClaudeThreadRecord>,
+    reason: TrashReason,
+) -> Result<(), String> {
+    if threads.is_empty() {
+        return Ok(());
+    }
+    let path = trash_path(claude_threads_path);
+    let mut entries = read_trash(&path)?;
+    let ids = threads
+        .iter()
+        .map(|thread| thread.id.as_str())
+        .collect::<HashSet<_>>();
+    entries.retain(|entry| {
+        entry.workspace_id != workspace_id || !ids.contains(entry.thread.id.as_str())
+    });
+    let trashed_at = now_ms();
+    entries.extend(threads.into_iter().map(|thread| TrashedThread {
+        workspace_id: workspace_id.to_string(),
+        trashed_at,
+        reason,
+        thread,
+    }));
+    write_trash(&path, &entries)
+}
+
+/// How many trashed threads each workspace has.
+pub(crate) fn trashed_thread_counts(
+    claude_threads_path: &Path,
+) -> Result<HashMap<String, usize>, String> {
+    let mut counts = HashMap::new();
+    for entry in read_trash(&trash_path(claude_threads_path))? {
+        *counts.entry(entry.workspace_id).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+/// The ids of the trashed threads of each workspace.
+pub(crate) fn trashed_thread_ids(
+    claude_threads_path: &Path,
+) -> Result<HashMap<String, HashSet<String>>, String> {
+    let mut ids: HashMap<String, HashSet<String>> = HashMap::new();
+    for entry in read_trash(&trash_path(claude_threads_path))? {
+        ids.entry(entry.workspace_id)
+            .or_default()
+            .insert(entry.thread.id);
+    }
+    Ok(ids)
+}
+
+/// Permanently drops the trashed threads of the given workspaces. Their raw logs and
+/// artifacts go with the workspace's own data folders, which the caller removes.
+pub(crate) fn purge_workspace_trash(
+    claude_threads_path: &Path,
+    workspace_ids: &HashSet<String>,
+) -> Result<(), String> {
+    let path = trash_path(claude_threads_path);
+    let mut entries = read_trash(&path)?;
+    let before = entries.len();
+    entries.retain(|entry| !workspace_ids.contains(&entry.workspace_id));
+    if entries.len() == before {
+        return Ok(());
+    }
+    write_trash(&path, &entries)
+}
+
+fn split_expired(
+    entries: Vec<TrashedThread>,
+    now: i64,
+    retention_days: u32,
+) -> (Vec<TrashedThread>, Vec<TrashedThread>) {
+    if retention_days == 0 {
+        return (entries, Vec::new());
+    }
+    let cutoff = now - i64::from(retention_days) * DAY_MS;
+    entries
+        .into_iter()
+        .partition(|entry| entry.trashed_at >= cutoff)
+}
+
+/// Deletes what was kept alongside purged threads in the data dir: their raw log records
+/// and turn artifacts. Trashed threads keep both so a restore loses nothing.
+fn remove_purged_thread_data(
+    claude_threads_path: &Path,
+    purged: &[TrashedThread],
+) -> Result<(), String> {
+    let Some(data_dir) = claude_threads_path.parent() else {
+        return Ok(());
+    };
+    let mut by_workspace: HashMap<&str, HashSet<String>> = HashMap::new();
+    for entry in purged {
+        by_workspace
+            .entry(&entry.workspace_id)
+            .or_default()
+            .insert(entry.thread.id.clone());
+    }
+    for (workspace_id, thread_ids) in by_workspace {
+        remove_raw_log_threads(data_dir, workspace_id, &thread_ids)?;
+        for thread_id in &thread_ids {
+            remove_thread_artifacts(data_dir, workspace_id, thread_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Permanently drops trash entries older than the retention window, with their raw logs and
+/// artifacts; returns the purged entries.
+pub(crate) fn purge_expired_trash(
+    claude_threads_path: &Path,
+    retention_days: u32,
+) -> Result<Vec<TrashedThread>, String> {
+    let path = trash_path(claude_threads_path);
+    let (kept, purged) = split_expired(read_trash(&path)?, now_ms(), retention_days);
+    if !purged.is_empty() {
+        write_trash(&path, &kept)?;
+        remove_purged_thread_data(claude_threads_path, &purged)?;
+    }
+    Ok(purged)
+}
+
+pub(crate) async fn list_trash_core(
+    claude_threads_path: &Path,
+    workspace_id: Option<String>,
+) -> Result<Value, String> {
+    let mut entries = read_trash(&trash_path(claude_threads_path))?;
+    if let Some(workspace_id) = workspace_id {
+        entries.retain(|entry| entry.workspace_id == workspace_id);
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.trashed_at));
+    let threads = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "workspaceId": entry.workspace_id,
+                "threadId": entry.thread.id,
+                "name": entry.thread.name,
+                "preview": entry.thread.preview,
+                "turns": entry.thread.turns.len(),
+                "updatedAt": entry.thread.updated_at,
+                "trashedAt": entry.trashed_at,
+                "reason": entry.reason,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({ "result": { "threads": threads } }))
+}
+
+/// Moves threads back into the store. Threads that already exist in the workspace again
+/// (e.g. re-imported from history) are reported as conflicts and stay in the trash.
+pub(crate) async fn restore_trash_core<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    workspace_id: String,
+    thread_ids: Vec<String>,
+    event_sink: E,
+) -> Result<Value, String> {
+    let requested = thread_ids
+        .iter()
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty())
+        .collect::<HashSet<_>>();
+    if requested.is_empty() {
+        return Err("no thread ids provided".to_string());
+    }
+    let path = trash_path(claude_threads_path);
+    let entries = read_trash(&path)?;
+    let mut restored = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut remaining = Vec::new();
+    {
+        let mut store = claude_threads.lock().await;
+        let threads = store.entry(workspace_id.clone()).or_default();
+        for entry in entries {
+            let selected =
+                entry.workspace_id == workspace_id && requested.contains(entry.thread.id.as_str());
+            if !selected {
+                remaining.push(entry);
+                continue;
+            }
+            if threads.iter().any(|thread| thread.id == entry.thread.id) {
+                conflicts.push(entry.thread.id.clone());
+                remaining.push(entry);
+                continue;
+            }
+            let mut thread = entry.thread;
+            // Deleting may have removed the Claude session log; start a seeded session instead.
+            if entry.reason == TrashReason::Delete && !thread.turns.is_empty() {
+                thread.session_id = Some(Uuid::new_v4().to_string());
+                thread.session_needs_seed = true;
+            }
+            restored.push(thread.id.clone());
+            threads.push(thread);
+        }
+        threads.sort_by_key(|thread| std::cmp::Reverse(thread.updated_at));
+    }
+    let missing = requested
+        .into_iter()
+        .filter(|id| !restored.iter().any(|restored| restored == id))
+        .filter(|id| !conflicts.iter().any(|conflict| conflict == id))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if !restored.is_empty() {
+        unarchive_thread_ids_for_workspace(claude_threads_path, &workspace_id, &restored)?;
+        persist_threads_store(claude_threads, claude_threads_path).await?;
+        write_trash(&path, &remaining)?;
+        emit_thread_list_updated(&event_sink, claude_threads, &workspace_id, &restored, &[]).await;
+    }
+    Ok(json!({
+        "result": {
+            "restored": restored,
+            "conflicts": conflicts,
+            "missing": missing,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        purge_expired_trash, split_expired, trash_path, write_trash, TrashReason, TrashedThread,
+        DAY_MS,
+    };
+    use crate::shared::claude_core::{now_ms, ClaudeThreadRecord, GenerationParams};
+    use crate::shared::redaction_core::{append_raw_log, raw_log_path};
+    use crate::shared::turn_artifacts_core::ARTIFACTS_DIR_NAME;
+    use std::fs;
+    use uuid::Uuid;
+
+    fn entry(id: &str, trashed_at: i64) -> TrashedThread {
+        TrashedThread {
+            workspace_id: "ws".to_string(),
+            trashed_at,
+            reason: TrashReason::Archive,
+            thread: ClaudeThreadRecord {
+                id: id.to_string(),
+                cwd: "/repo".to_string(),
+                preview: String::new(),
+                created_at: 0,
+                updated_at: 0,
+                name: None,
+                tags: Vec::new(),
+                session_id: None,
+                session_needs_seed: false,
+                provider_session_id: None,
+                cwd_override: None,
+                generation: GenerationParams::default(),
+                worktree: None,
+                summary: None,
+                stats: None,
+                last_read_at: None,
+                turns: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn split_expired_honors_retention_window() {
+        let now = 100 * DAY_MS;
+        let entries = vec![entry("old", now - 31 * DAY_MS), entry("new", now - DAY_MS)];
+        let (kept, purged) = split_expired(entries.clone(), now, 30);
+        assert_eq!(purged.len(), 1);
+        assert_eq!(kept[0].thread.id, "new");
+        let (kept, purged) = split_expired(entries, now, 0);
+        assert_eq!((kept.len(), purged.len()), (2, 0));
+    }
+
+    #[test]
+    fn purging_removes_raw_logs_and_artifacts_only_of_purged_threads() {
+        let data_dir = std::env::temp_dir().join(format!("polycode-trash-{}", Uuid::new_v4()));
+        let threads_path = data_dir.join("claude_threads.json");
+        let artifacts = data_dir.join(ARTIFACTS_DIR_NAME).join("ws");
+        for id in ["old", "new"] {
+            append_raw_log(&data_dir, "ws", id, "turn", id).expect("raw log");
+            fs::create_dir_all(artifacts.join(id).join("turn")).expect("artifacts");
+        }
+        let now = now_ms();
+        write_trash(
+            &trash_path(&threads_path),
+            &[entry("old", now - 31 * DAY_MS), entry("new", now - DAY_MS)],
+        )
+        .expect("trash");
+
+        let purged = purge_expired_trash(&threads_path, 30).expect("purge");
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].thread.id, "old");
+        assert!(!artifacts.join("old").exists());
+        assert!(artifacts.join("new").exists());
+        let raw = fs::read_to_string(raw_log_path(&data_dir, "ws")).expect("raw log");
+        assert_eq!(raw.lines().count(), 1);
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::shared::claude_core::now_ms;
+use crate::shared::file_references_core::FileReference;
+use crate::types::WorkspaceEntry;
+
+pub(crate) const ARTIFACTS_DIR_NAME: &str = "artifacts";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const MAX_ARTIFACTS: usize = 100;
+const MAX_ARTIFACT_BYTES: u64 = 20 * 1024 * 1024;
+/// Declared directories are walked this deep at most.
+const MAX_DECLARED_DEPTH: usize = 4;
+/// Filesystems with coarse timestamps can date a write at the very start of a turn earlier.
+const MTIME_SLACK_MS: i64 = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TurnArtifact {
+    /// Relative to the directory the turn ran in, with `/` separators.
+    pub(crate) path: String,
+    /// `reply` for files the agent mentioned, `declared` for the workspace's artifact paths.
+    pub(crate) source: String,
+    /// The copy in the turn's artifacts folder.
+    #[serde(rename = "storedPath")]
+    pub(crate) stored_path: String,
+    pub(crate) size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ArtifactManifest {
+    #[serde(rename = "threadId")]
+    pub(crate) thread_id: String,
+    #[serde(rename = "turnId")]
+    pub(crate) turn_id: String,
+    #[serde(rename = "collectedAt")]
+    pub(crate) collected_at: i64,
+    pub(crate) artifacts: Vec<TurnArtifact>,
+}
+
+/// One finished turn whose outputs should be kept.
+pub(crate) struct ArtifactCollection<'a> {
+    pub(crate) data_dir: &'a Path,
+    pub(crate) workspace_id: &'a str,
+    pub(crate) thread_id: &'a str,
+    pub(crate) turn_id: &'a str,
+    /// Directory the turn ran in; artifacts must live under it.
+    pub(crate) cwd: &'a Path,
+    pub(crate) references: &'a [FileReference],
+    /// Workspace `artifactPaths`, relative to `cwd`.
+    pub(crate) declared: &'a [String],
+    /// Only files modified after this (ms since the epoch) belong to the turn.
+    pub(crate) since_ms: i64,
+}
+
+/// Worktrees fall back to their parent's artifact paths, like other workspace launch settings.
+pub(crate) fn resolve_artifact_paths(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> Vec<String> {
+    let normalize = |paths: &[String]| {
+        paths
+            .iter()
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+            .collect::<Vec<_>>()
+    };
+    let own = normalize(&entry.settings.artifact_paths);
+    match parent_entry {
+        Some(parent) if own.is_empty() => normalize(&parent.settings.artifact_paths),
+        _ => own,
+    }
+}
+
+/// Ids become folder names, so they must be a single plain path component.
+fn is_safe_component(value: &str) -> bool {
+    let mut components = Path::new(value).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+fn turn_artifacts_dir(
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<PathBuf, String> {
+    if ![workspace_id, thread_id, turn_id]
+        .iter()
+        .all(|id| is_safe_component(id))
+    {
+        return Err("Invalid turn id.".to_string());
+    }
+    Ok(data_dir
+        .join(ARTIFACTS_DIR_NAME)
+        .join(workspace_id)
+        .join(thread_id)
+        .join(turn_id))
+}
+
+fn modified_ms(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(modified.as_millis() as i64)
+}
+
+fn push_declared_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        if files.len() >= MAX_ARTIFACTS {
+            return;
+        }
+        if path.is_dir() {
+            if depth < MAX_DECLARED_DEPTH {
+                push_declared_files(&path, depth + 1, files);
+            }
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Files the turn produced: mentioned in the reply or under a declared path, inside `cwd`,
+/// written since the turn started and not too large to copy.
+fn artifact_candidates(
+    request: &ArtifactCollection<'_>,
+    root: &Path,
+) -> Vec<(PathBuf, &'static str)> {
+    let mut candidates = request
+        .references
+        .iter()
+        .map(|reference| (PathBuf::from(&reference.absolute_path), "reply"))
+        .collect::<Vec<_>>();
+    for declared in request.declared {
+        let mut files = Vec::new();
+        let path = root.join(declared);
+        if path.is_dir() {
+            push_declared_files(&path, 0, &mut files);
+        } else {
+            files.push(path);
+        }
+        candidates.extend(files.into_iter().map(|file| (file, "declared")));
+    }
+
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter_map(|(path, source)| {
+            let resolved = path.canonicalize().ok()?;
+            let metadata = fs::metadata(&resolved).ok()?;
+            let fresh = modified_ms(&metadata)
+                .is_some_and(|modified| modified + MTIME_SLACK_MS >= request.since_ms);
+            let keep = resolved.starts_with(root)
+                && metadata.is_file()
+                && metadata.len() <= MAX_ARTIFACT_BYTES
+                && fresh;
+            (keep && seen.insert(resolved.clone())).then_some((resolved, source))
+        })
+        .take(MAX_ARTIFACTS)
+        .collect()
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|relative| {
+            relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
+
+/// Copies the turn's artifacts into `artifacts/<workspace>/<thread>/<turn>` in the data dir
+/// and writes a manifest next to them. `None` when the turn left nothing to keep.
+pub(crate) fn collect_turn_artifacts(
+    request: ArtifactCollection<'_>,
+) -> Result<Option<ArtifactManifest>, String> {
+    let dir = turn_artifacts_dir(
+        request.data_dir,
+        request.workspace_id,
+        request.thread_id,
+        request.turn_id,
+    )?;
+    let root = request
+        .cwd
+        .canonicalize()
+        .map_err(|error| format!("Failed to resolve {}: {error}", request.cwd.display()))?;
+    let candidates = artifact_candidates(&request, &root);
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let mut artifacts = Vec::with_capacity(candidates.len());
+    for (path, source) in candidates {
+        let relative = relative_path(&root, &path);
+        let stored = dir.join(&relative);
+        if let Some(parent) = stored.parent() {
+            fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        let size = fs::copy(&path, &stored)
+            .map_err(|error| format!("Failed to copy {relative}: {error}"))?;
+        artifacts.push(TurnArtifact {
+            path: relative,
+            source: source.to_string(),
+            stored_path: stored.to_string_lossy().to_string(),
+            size,
+        });
+    }
+    let manifest = ArtifactManifest {
+        thread_id: request.thread_id.to_string(),
+        turn_id: request.turn_id.to_string(),
+        collected_at: now_ms(),
+        artifacts,
+    };
+    let content = serde_json::to_string_pretty(&manifest).map_err(|error| error.to_string())?;
+    fs::write(dir.join(MANIFEST_FILE_NAME), content).map_err(|error| error.to_string())?;
+    Ok(Some(manifest))
+}
+
+/// Deletes every artifact collected for a thread.
+pub(crate) fn remove_thread_artifacts(
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+) -> Result<(), String> {
+    // Nothing is ever collected under ids that are not plain folder names.
+    if !is_safe_component(workspace_id) || !is_safe_component(thread_id) {
+        return Ok(());
+    }
+    let dir = data_dir
+        .join(ARTIFACTS_DIR_NAME)
+        .join(workspace_id)
+        .join(thread_id);
+    match fs::remove_dir_all(&dir) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(format!("Failed to remove {}: {error}", dir.display())),
+    }
+}
+
+/// The manifest of a turn's artifacts; an empty list when none were collected.
+pub(crate) fn turn_artifacts_core(
+    data_dir: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<Value, String> {
+    let dir = turn_artifacts_dir(data_dir, workspace_id, thread_id, turn_id)?;
+    let manifest = match fs::read_to_string(dir.join(MANIFEST_FILE_NAME)) {
+        Ok(content) => serde_json::from_str(&content).map_err(|error| error.to_string())?,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => ArtifactManifest {
+            thread_id: thread_id.to_string(),
+            turn_id: turn_id.to_string(),
+            ..Default::default()
+        },
+        Err(error) => return Err(error.to_string()),
+    };
+    Ok(json!({ "result": manifest }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_turn_artifacts, turn_artifacts_core, ArtifactCollection};
+    use crate::shared::file_references_core::FileReference;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn collects_fresh_reply_and_declared_files_with_a_manifest() {
+        let temp = std::env::temp_dir().join(format!("polycode-artifacts-test-{}", Uuid::new_v4()));
+        let repo = temp.join("repo");
+        let data_dir = temp.join("data");
+        fs::create_dir_all(repo.join("reports/unit")).expect("create dirs");
+        fs::write(repo.join("notes.md"), "# Notes\n").expect("write notes");
+        fs::write(repo.join("reports/unit/junit.xml"), "<testsuite/>").expect("write report");
+        fs::write(temp.join("outside.txt"), "").expect("write outside");
+        let reference = |path: &str| FileReference {
+            text: path.to_string(),
+            path: path.to_string(),
+            absolute_path: path.to_string(),
+            line: None,
+            end_line: None,
+        };
+        let references = [
+            reference(&repo.join("notes.md").to_string_lossy()),
+            reference(&temp.join("outside.txt").to_string_lossy()),
+        ];
+        let declared = ["reports".to_string(), "missing.xml".to_string()];
+        let request = |since_ms| ArtifactCollection {
+            data_dir: &data_dir,
+            workspace_id: "ws-1",
+            thread_id: "thread-1",
+            turn_id: "turn-1",
+            cwd: &repo,
+            references: &references,
+            declared: &declared,
+            since_ms,
+        };
+
+        let future = super::now_ms() + 60_000;
+        assert!(collect_turn_artifacts(request(future))
+            .expect("stale")
+            .is_none());
+
+        let manifest = collect_turn_artifacts(request(0))
+            .expect("collect")
+            .expect("manifest");
+        let collected = manifest
+            .artifacts
+            .iter()
+            .map(|artifact| (artifact.path.as_str(), artifact.source.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            collected,
+            vec![
+                ("notes.md", "reply"),
+                ("reports/unit/junit.xml", "declared")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(&manifest.artifacts[1].stored_path).expect("stored copy"),
+            "<testsuite/>"
+        );
+
+        let listed = turn_artifacts_core(&data_dir, "ws-1", "thread-1", "turn-1").expect("list");
+        assert_eq!(
+            listed["result"]["artifacts"].as_array().map(Vec::len),
+            Some(2)
+        );
+        let empty = turn_artifacts_core(&data_dir, "ws-1", "thread-1", "turn-2").expect("empty");
+        assert_eq!(empty["result"]["artifacts"], serde_json::json!([]));
+        assert!(turn_artifacts_core(&data_dir, "ws-1", "..", "turn-1").is_err());
+        let _ = fs::remove_dir_all(&temp);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::shared::claude_core::ClaudeThreadsStore;
+
+const TODO_TOOL_NAME: &str = "TodoWrite";
+
+/// One entry of the checklist Claude keeps with its `TodoWrite` tool. Statuses use the same
+/// names as Codex plan steps: `pending`, `inProgress` and `completed`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ChecklistStep {
+    pub(crate) step: String,
+    pub(crate) status: String,
+    /// Present-tense wording Claude shows while the step is in progress.
+    #[serde(
+        default,
+        rename = "activeForm",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) active_form: Option<String>,
+}
+
+fn step_status(status: &str) -> &'static str {
+    match status {
+        "in_progress" | "inProgress" => "inProgress",
+        "completed" => "completed",
+        _ => "pending",
+    }
+}
+
+fn checklist_step(todo: &Value) -> Option<ChecklistStep> {
+    let text = |key: &str| {
+        todo.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    };
+    Some(ChecklistStep {
+        step: text("content")?.to_string(),
+        status: step_status(text("status").unwrap_or_default()).to_string(),
+        active_form: text("activeForm").map(str::to_string),
+    })
+}
+
+/// The checklist a `TodoWrite` call in an `assistant` stream-json record sets. Each call
+/// replaces the whole list, so only the last call of a message counts.
+pub(crate) fn checklist_update(record: &Value) -> Option<Vec<ChecklistStep>> {
+    if record.get("type").and_then(Value::as_str) != Some("assistant") {
+        return None;
+    }
+    let todos = record
+        .get("message")?
+        .get("content")?
+        .as_array()?
+        .iter()
+        .rev()
+        .find(|block| {
+            block.get("type").and_then(Value::as_str) == Some("tool_use")
+                && block.get("name").and_then(Value::as_str) == Some(TODO_TOOL_NAME)
+        })?
+        .get("input")?
+        .get("todos")?
+        .as_array()?;
+    Some(todos.iter().filter_map(checklist_step).collect())
+}
+
+/// Params of `item/plan`; `explanation` and `plan` match `turn/plan/updated`.
+pub(crate) fn checklist_event(thread_id: &str, turn_id: &str, steps: &[ChecklistStep]) -> Value {
+    json!({
+        "threadId": thread_id,
+        "turnId": turn_id,
+        "itemId": format!("claude-plan-{turn_id}"),
+        "explanation": Value::Null,
+        "plan": steps,
+    })
+}
+
+/// Keeps the turn's latest checklist; the caller persists the store.
+pub(crate) async fn record_turn_checklist(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    steps: Vec<ChecklistStep>,
+) {
+    let mut store = claude_threads.lock().await;
+    let turn = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        .and_then(|thread| thread.turns.iter_mut().find(|turn| turn.id == turn_id));
+    if let Some(turn) = turn {
+        turn.checklist = steps;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::checklist_update;
+    use serde_json::json;
+
+    #[test]
+    fn reads_the_last_todo_write_call_of_a_message() {
+        let record = json!({
+            "type": "assistant",
+            "message": {
+                "content": [
+                    { "type": "text", "text": "Let me plan this." },
+                    {
+                        "type": "tool_use",
+                        "name": "TodoWrite",
+                        "input": { "todos": [{ "content": "stale", "status": "pending" }] }
+                    },
+                    {
+                        "type": "tool_use",
+                        "name": "TodoWrite",
+                        "input": { "todos": [
+                            { "content": "Read the config", "status": "completed",
+                              "activeForm": "Reading the config" },
+                            { "content": "Fix the parser", "status": "in_progress",
+                              "activeForm": "Fixing the parser" },
+                            { "content": "Run the tests", "status": "pending" },
+                            { "content": "  ", "status": "pending" }
+                        ] }
+                    }
+                ]
+            }
+        });
+        let steps = checklist_update(&record).expect("checklist");
+        let summary = steps
+            .iter()
+            .map(|step| (step.step.as_str(), step.status.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("Read the config", "completed"),
+                ("Fix the parser", "inProgress"),
+                ("Run the tests", "pending"),
+            ]
+        );
+        assert_eq!(steps[1].active_form.as_deref(), Some("Fixing the parser"));
+        assert_eq!(
+            serde_json::to_value(&steps[2]).expect("serialize"),
+            json!({ "step": "Run the tests", "status": "pending" })
+        );
+
+        let bash = json!({
+            "type": "assistant",
+            "message": { "content": [{ "type": "tool_use", "name": "Bash", "input": {} }] }
+        });
+        assert!(checklist_update(&bash).is_none());
+        assert!(checklist_update(&json!({ "type": "result" })).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+
+use tokio::process::Command;
+
+use crate::shared::claude_core::ClaudeThreadsStore;
+
+const SESSION_FLAGS: &[&str] = &["--session-id", "--resume"];
+
+/// How a turn's provider process was started, kept on the turn so a run can be explained
+/// after the fact. Variable values are never stored, only their names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TurnRequest {
+    /// The program as found on the process's `PATH` when the turn started.
+    pub(crate) bin: String,
+    /// Arguments after the program; a prompt passed on the command line is replaced by a
+    /// placeholder since the turn already stores it.
+    pub(crate) args: Vec<String>,
+    pub(crate) cwd: String,
+    /// Variables set for the process on top of the app's environment.
+    #[serde(default, rename = "envNames")]
+    pub(crate) env_names: Vec<String>,
+    #[serde(default, rename = "envRemoved", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) env_removed: Vec<String>,
+    /// The app's environment was dropped (`restrictEnv`); only `envNames` reached the process.
+    #[serde(default, rename = "envCleared")]
+    pub(crate) env_cleared: bool,
+    /// `--session-id` / `--resume` and their session, as passed in `args`.
+    #[serde(default, rename = "sessionFlags")]
+    pub(crate) session_flags: Vec<String>,
+    /// Ran in a warm pooled process instead of a fresh one; its exit status is the turn's
+    /// result (`0` or `1`), as the process keeps running.
+    #[serde(default)]
+    pub(crate) pooled: bool,
+    #[serde(default)]
+    pub(crate) attempts: u32,
+    #[serde(default, rename = "exitStatus")]
+    pub(crate) exit_status: Option<i32>,
+    /// The `launchShell` the program was run through, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shell: Option<String>,
+}
+
+fn resolve_program(program: &OsStr, path_var: Option<OsString>) -> String {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.to_string_lossy().to_string();
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", "exe", "cmd", "bat"]
+    } else {
+        &[""]
+    };
+    path_var
+        .iter()
+        .flat_map(env::split_paths)
+        .flat_map(|dir| {
+            extensions.iter().map(move |extension| {
+                let mut name = program.to_os_string();
+                if !extension.is_empty() {
+                    name.push(".");
+                    name.push(extension);
+                }
+                dir.join(name)
+            })
+        })
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+impl TurnRequest {
+    /// Captures a command right before it is spawned. `prompt` is the prompt argument, if the
+    /// prompt went on the command line.
+    pub(crate) fn describe(
+        command: &Command,
+        prompt: Option<&str>,
+        env_cleared: bool,
+        pooled: bool,
+    ) -> Self {
+        let command = command.as_std();
+        let args = command
+            .get_args()
+            .map(|arg| match (arg.to_str(), prompt) {
+                (Some(arg), Some(prompt)) if arg == prompt => {
+                    format!("<prompt, {} bytes>", prompt.len())
+                }
+                _ => arg.to_string_lossy().to_string(),
+            })
+            .collect::<Vec<_>>();
+        let session_flags = args
+            .windows(2)
+            .filter(|pair| SESSION_FLAGS.contains(&pair[0].as_str()))
+            .flatten()
+            .cloned()
+            .collect();
+        let mut env_names = Vec::new();
+        let mut env_removed = Vec::new();
+        let mut path_var = (!env_cleared).then(|| env::var_os("PATH")).flatten();
+        for (name, value) in command.get_envs() {
+            if name == "PATH" {
+                path_var = value.map(OsStr::to_os_string);
+            }
+            let name = name.to_string_lossy().to_string();
+            match value {
+                Some(_) => env_names.push(name),
+                None => env_removed.push(name),
+            }
+        }
+        env_names.sort();
+        env_removed.sort();
+        Self {
+            bin: resolve_program(command.get_program(), path_var),
+            args,
+            cwd: command
+                .get_current_dir()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            env_names,
+            env_removed,
+            env_cleared,
+            session_flags,
+            pooled,
+            attempts: 1,
+            exit_status: None,
+            shell: None,
+        }
+    }
+}
+
+/// Stores how the turn's process was started; the caller persists the store.
+pub(crate) async fn record_turn_request(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+    request: TurnRequest,
+) {
+    let mut store = claude_threads.lock().await;
+    let turn = store
+        .get_mut(workspace_id)
+        .and_then(|threads| threads.iter_mut().find(|thread| thread.id == thread_id))
+        .and_then(|thread| thread.turns.iter_mut().find(|turn| turn.id == turn_id));
+    if let Some(turn) = turn {
+        turn.request = Some(request);
+    }
+}
+
+/// What is known about how a turn ran. `request` is null for turns from before it was
+/// recorded and for imported ones.
+pub(crate) async fn turn_details_core(
+    claude_threads: &ClaudeThreadsStore,
+    workspace_id: &str,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<Value, String> {
+    let store = claude_threads.lock().await;
+    let turn = store
+        .get(workspace_id)
+        .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+        .and_then(|thread| thread.turns.iter().find(|turn| turn.id == turn_id))
+        .ok_or_else(|| "turn not found".to_string())?;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "startedAt": turn.started_at,
+            "completedAt": turn.completed_at,
+            "durationMs": turn.duration_ms,
+            "cliVersion": turn.cli_version,
+            "plan": turn.plan,
+            "answeredBy": turn.answered_by,
+            "modelDowngrade": turn.model_downgrade,
+            "request": turn.request,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TurnRequest;
+    use tokio::process::Command;
+
+    #[test]
+    fn describes_a_command_without_env_values_or_the_prompt() {
+        let mut command = Command::new("/opt/claude/bin/claude");
+        command
+            .current_dir("/repo")
+            .env("ANTHROPIC_API_KEY", "sk-secret")
+            .env("CLAUDE_CODE_MAX_OUTPUT_TOKENS", "4096")
+            .env_remove("DEBUG")
+            .args(["-p", "Fix the build", "--resume", "session-1"]);
+
+        let request = TurnRequest::describe(&command, Some("Fix the build"), true, false);
+        assert_eq!(request.bin, "/opt/claude/bin/claude");
+        assert_eq!(
+            request.args,
+            vec!["-p", "<prompt, 13 bytes>", "--resume", "session-1"]
+        );
+        assert_eq!(request.cwd, "/repo");
+        assert_eq!(
+            request.env_names,
+            vec!["ANTHROPIC_API_KEY", "CLAUDE_CODE_MAX_OUTPUT_TOKENS"]
+        );
+        assert_eq!(request.env_removed, vec!["DEBUG"]);
+        assert_eq!(request.session_flags, vec!["--resume", "session-1"]);
+        assert!(!serde_json::to_string(&request)
+            .expect("serialize")
+            .contains("sk-secret"));
+
+        let unresolved = TurnRequest::describe(&Command::new("no-such-cli"), None, true, true);
+        assert_eq!(unresolved.bin, "no-such-cli");
+        assert_eq!(unresolved.exit_status, None);
+    }
+}
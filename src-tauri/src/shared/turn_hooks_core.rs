@@ -0,0 +1,204 @@
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::time::timeout;
+use uuid::Uuid;
+
+use crate::shared::process_core::{quote_for_shell, shell_script_command, ShellKind};
+use crate::types::WorkspaceEntry;
+
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_HOOK_OUTPUT_CHARS: usize = 4000;
+
+/// Everything an `onTurnCompleted` hook is told about the turn that just finished.
+pub(crate) struct TurnHookRun<'a> {
+    pub(crate) command: &'a str,
+    pub(crate) cwd: &'a str,
+    pub(crate) workspace_id: &'a str,
+    pub(crate) thread_id: &'a str,
+    pub(crate) turn_id: &'a str,
+    /// `completed`, `failed` or `canceled`.
+    pub(crate) status: &'a str,
+    /// Exit status of the agent CLI; `None` when the turn was canceled.
+    pub(crate) exit_status: Option<i32>,
+    pub(crate) transcript: Value,
+    /// The `launchShell` setting; the platform shell runs the hook when unset.
+    pub(crate) shell: Option<&'a str>,
+}
+
+/// Worktrees fall back to their parent's hook, like other workspace launch settings.
+pub(crate) fn resolve_turn_hook(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> Option<String> {
+    let normalize = |value: Option<&String>| {
+        value
+            .map(|script| script.trim().to_string())
+            .filter(|script| !script.is_empty())
+    };
+    normalize(entry.settings.on_turn_completed.as_ref()).or_else(|| {
+        parent_entry.and_then(|parent| normalize(parent.settings.on_turn_completed.as_ref()))
+    })
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Substitutes `{transcriptPath}`, `{exitStatus}`, `{status}`, `{threadId}` and `{turnId}`.
+/// Values are quoted for the shell that runs the hook so paths with spaces survive.
+fn render_hook_command(template: &str, vars: &[(&str, String)], shell: Option<&str>) -> String {
+    let quote = |value: &str| match shell.map(str::trim).filter(|shell| !shell.is_empty()) {
+        Some(shell) => quote_for_shell(ShellKind::of(shell), value),
+        None => shell_quote(value),
+    };
+    vars.iter()
+        .fold(template.to_string(), |command, (name, value)| {
+            command.replace(&format!("{{{name}}}"), &quote(value))
+        })
+}
+
+/// A fresh name per run, so an existing file in the shared temp dir is never reused.
+fn transcript_path(turn_id: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("polycode-turn-{turn_id}-{}.json", Uuid::new_v4()))
+}
+
+/// Creates the transcript readable only by the current user.
+fn write_transcript(path: &Path, transcript: &Value) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(transcript).map_err(|error| error.to_string())?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(&data))
+        .map_err(|error| format!("failed to write transcript: {error}"))
+}
+
+fn output_tail(stdout: &[u8], stderr: &[u8]) -> String {
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(stdout),
+        String::from_utf8_lossy(stderr)
+    );
+    let count = combined.chars().count();
+    combined
+        .chars()
+        .skip(count.saturating_sub(MAX_HOOK_OUTPUT_CHARS))
+        .collect()
+}
+
+/// Writes the turn transcript to a temp file, runs the hook in the turn's directory and removes
+/// the file once the hook exits. Returns the payload for the `turn/hookCompleted` event.
+pub(crate) async fn run_turn_hook(run: TurnHookRun<'_>) -> Value {
+    let path = transcript_path(run.turn_id);
+    let exit_status = run
+        .exit_status
+        .map(|code| code.to_string())
+        .unwrap_or_default();
+    let vars = [
+        ("transcriptPath", path.to_string_lossy().to_string()),
+        ("exitStatus", exit_status.clone()),
+        ("status", run.status.to_string()),
+        ("threadId", run.thread_id.to_string()),
+        ("turnId", run.turn_id.to_string()),
+    ];
+    let rendered = render_hook_command(run.command, &vars, run.shell);
+    let outcome = async {
+        write_transcript(&path, &run.transcript)?;
+        let mut command = shell_script_command(run.shell, &rendered);
+        command
+            .current_dir(run.cwd)
+            .env("POLYCODE_WORKSPACE_ID", run.workspace_id)
+            .env("POLYCODE_THREAD_ID", run.thread_id)
+            .env("POLYCODE_TURN_ID", run.turn_id)
+            .env("POLYCODE_TURN_STATUS", run.status)
+            .env("POLYCODE_EXIT_STATUS", &exit_status)
+            .env("POLYCODE_TRANSCRIPT_PATH", &path)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        let output = timeout(HOOK_TIMEOUT, command.output())
+            .await
+            .map_err(|_| "hook timed out".to_string())?
+            .map_err(|error| format!("failed to run hook: {error}"))?;
+        Ok::<_, String>((
+            output.status.code(),
+            output_tail(&output.stdout, &output.stderr),
+        ))
+    }
+    .await;
+    let _ = std::fs::remove_file(&path);
+    let (exit_code, output, error) = match outcome {
+        Ok((code, output)) => (code, output, None),
+        Err(error) => (None, String::new(), Some(error)),
+    };
+    json!({
+        "threadId": run.thread_id,
+        "turnId": run.turn_id,
+        "command": rendered,
+        "exitCode": exit_code,
+        "output": output,
+        "error": error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_hook_command, shell_quote, transcript_path, write_transcript};
+    use serde_json::json;
+
+    #[test]
+    fn render_hook_command_quotes_substituted_values() {
+        let vars = [
+            ("transcriptPath", "/tmp/my turns/t1.json".to_string()),
+            ("exitStatus", "0".to_string()),
+        ];
+        let template = "./check {transcriptPath} {exitStatus} {other}";
+        let rendered = render_hook_command(template, &vars, None);
+        assert_eq!(
+            rendered,
+            format!(
+                "./check {} {} {{other}}",
+                shell_quote("/tmp/my turns/t1.json"),
+                shell_quote("0")
+            )
+        );
+        #[cfg(not(windows))]
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(
+            render_hook_command(template, &vars, Some("pwsh")),
+            "./check '/tmp/my turns/t1.json' 0 {other}"
+        );
+    }
+
+    #[test]
+    fn transcripts_are_private_and_never_overwrite_an_existing_file() {
+        let path = transcript_path("turn-1");
+        assert_ne!(path, transcript_path("turn-1"));
+        write_transcript(&path, &json!({ "turnId": "turn-1" })).expect("write");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)
+                .expect("metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(write_transcript(&path, &json!({})).is_err());
+        std::fs::remove_file(&path).expect("remove");
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Poll;
+use tokio::sync::{oneshot, Notify};
+
+/// Limits how many turns run a provider process at once, across all workspaces. Turns over the
+/// limit wait in arrival order.
+pub(crate) type TurnSlotsStore = Arc<TurnSlots>;
+
+#[derive(Default)]
+pub(crate) struct TurnSlots {
+    state: Mutex<SlotsState>,
+    /// Wakes queued turns when a slot frees up or the queue moves.
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct SlotsState {
+    running: usize,
+    next_ticket: u64,
+    queue: VecDeque<u64>,
+}
+
+/// Held while a turn runs; dropping it lets the next queued turn start.
+pub(crate) struct TurnSlot {
+    slots: TurnSlotsStore,
+}
+
+impl Drop for TurnSlot {
+    fn drop(&mut self) {
+        let mut state = self.slots.lock();
+        state.running = state.running.saturating_sub(1);
+        drop(state);
+        self.slots.changed.notify_waiters();
+    }
+}
+
+/// A place in the queue, given up when the wait ends either way.
+struct QueueTicket<'a> {
+    slots: &'a TurnSlotsStore,
+    ticket: u64,
+}
+
+impl QueueTicket<'_> {
+    /// The slot if this ticket is first in line and one is free, else its 1-based position.
+    fn try_take(&self, limit: usize) -> Result<TurnSlot, usize> {
+        let mut state = self.slots.lock();
+        let position = state
+            .queue
+            .iter()
+            .position(|ticket| *ticket == self.ticket)
+            .unwrap_or_default();
+        if position > 0 || (limit > 0 && state.running >= limit) {
+            return Err(position + 1);
+        }
+        state.queue.pop_front();
+        state.running += 1;
+        let moved_up = !state.queue.is_empty();
+        drop(state);
+        if moved_up {
+            self.slots.changed.notify_waiters();
+        }
+        Ok(TurnSlot {
+            slots: Arc::clone(self.slots),
+        })
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        let removed = {
+            let mut state = self.slots.lock();
+            let before = state.queue.len();
+            state.queue.retain(|ticket| *ticket != self.ticket);
+            state.queue.len() != before
+        };
+        if removed {
+            self.slots.changed.notify_waiters();
+        }
+    }
+}
+
+impl TurnSlots {
+    fn lock(&self) -> MutexGuard<'_, SlotsState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Turns waiting for a slot.
+    pub(crate) fn queued(&self) -> usize {
+        self.lock().queue.len()
+    }
+}
+
+fn enqueue(slots: &TurnSlotsStore) -> QueueTicket<'_> {
+    let mut state = slots.lock();
+    let ticket = state.next_ticket;
+    state.next_ticket += 1;
+    state.queue.push_back(ticket);
+    QueueTicket { slots, ticket }
+}
+
+/// Waits for a free slot under `limit` (`0` means unlimited). `on_queued` is called with the
+/// turn's 1-based queue position whenever it has to wait or moves up. `None` when the turn is
+/// canceled while queued.
+pub(crate) async fn acquire_turn_slot(
+    slots: &TurnSlotsStore,
+    limit: usize,
+    cancel_rx: &mut oneshot::Receiver<()>,
+    mut on_queued: impl FnMut(usize),
+) -> Option<TurnSlot> {
+    let ticket = enqueue(slots);
+    let mut reported = None;
+    loop {
+        // Registered before checking, so a slot freed in between still wakes this turn.
+        let mut changed = pin!(slots.changed.notified());
+        changed.as_mut().enable();
+        match ticket.try_take(limit) {
+            Ok(slot) => return Some(slot),
+            Err(position) if reported != Some(position) => {
+                reported = Some(position);
+                on_queued(position);
+            }
+            Err(_) => {}
+        }
+        let canceled = poll_fn(|cx| {
+            if Pin::new(&mut *cancel_rx).poll(cx).is_ready() {
+                return Poll::Ready(true);
+            }
+            changed.as_mut().poll(cx).map(|_| false)
+        })
+        .await;
+        if canceled {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acquire_turn_slot, TurnSlotsStore};
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn queues_turns_over_the_limit_in_arrival_order() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let slots = TurnSlotsStore::default();
+            let mut context = Context::from_waker(Waker::noop());
+            let (_first_tx, mut first_rx) = oneshot::channel();
+            let (_second_tx, mut second_rx) = oneshot::channel();
+            let (third_tx, mut third_rx) = oneshot::channel();
+            let positions = RefCell::new(Vec::new());
+
+            let first = acquire_turn_slot(&slots, 1, &mut first_rx, |_| unreachable!())
+                .await
+                .expect("free slot");
+            let mut second = pin!(acquire_turn_slot(&slots, 1, &mut second_rx, |position| {
+                positions.borrow_mut().push(("second", position))
+            }));
+            let mut third = pin!(acquire_turn_slot(&slots, 1, &mut third_rx, |position| {
+                positions.borrow_mut().push(("third", position))
+            }));
+            assert!(second.as_mut().poll(&mut context).is_pending());
+            assert!(third.as_mut().poll(&mut context).is_pending());
+            assert_eq!(*positions.borrow(), vec![("second", 1), ("third", 2)]);
+
+            drop(first);
+            assert!(third.as_mut().poll(&mut context).is_pending());
+            let Poll::Ready(Some(second)) = second.as_mut().poll(&mut context) else {
+                panic!("the first queued turn should start");
+            };
+            assert!(third.as_mut().poll(&mut context).is_pending());
+            assert_eq!(positions.borrow().last(), Some(&("third", 1)));
+
+            let _ = third_tx.send(());
+            assert!(matches!(
+                third.as_mut().poll(&mut context),
+                Poll::Ready(None)
+            ));
+            drop(second);
+
+            let (_tx, mut rx) = oneshot::channel();
+            let unlimited = [
+                acquire_turn_slot(&slots, 0, &mut rx, |_| unreachable!()).await,
+                acquire_turn_slot(&slots, 0, &mut rx, |_| unreachable!()).await,
+            ];
+            assert!(unlimited.iter().all(Option::is_some));
+        });
+    }
+}
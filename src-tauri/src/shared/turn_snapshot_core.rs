@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::shared::claude_core::{
+    thread_has_running_turn, ClaudeThreadsStore, ClaudeTurnCancelsStore,
+};
+use crate::shared::git_core::{run_git_command, run_git_command_with_env};
+
+/// Snapshot commits are kept reachable under this ref namespace, one ref per turn or patch.
+const SNAPSHOT_REF_PREFIX: &str = "refs/polycode/snapshots/";
+/// Snapshot refs kept per repository; the oldest go once a new one would exceed this.
+const MAX_SNAPSHOT_REFS: usize = 100;
+/// Snapshot commits never land on a branch, so they use a fixed identity instead of
+/// requiring the user's git config.
+const SNAPSHOT_IDENTITY: &[(&str, &str)] = &[
+    ("GIT_AUTHOR_NAME", "PolyCode"),
+    ("GIT_AUTHOR_EMAIL", "polycode@localhost"),
+    ("GIT_COMMITTER_NAME", "PolyCode"),
+    ("GIT_COMMITTER_EMAIL", "polycode@localhost"),
+];
+
+/// The working tree and index as they were before a turn ran, laid out like a `git stash`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TurnSnapshot {
+    /// Commit whose tree is the working tree, untracked files included. Its parents are `head`
+    /// and `index`.
+    pub(crate) commit: String,
+    pub(crate) head: String,
+    /// Commit holding what was staged, so reverting keeps the user's staging area.
+    pub(crate) index: String,
+    /// Repository root the snapshot was taken in.
+    pub(crate) root: String,
+}
+
+/// A throwaway index so snapshots never disturb what the user has staged.
+struct ScratchIndex {
+    path: PathBuf,
+}
+
+impl ScratchIndex {
+    fn new() -> Self {
+        Self {
+            path: std::env::temp_dir().join(format!("polycode-snapshot-{}.index", Uuid::new_v4())),
+        }
+    }
+
+    async fn git(&self, root: &PathBuf, args: &[&str]) -> Result<String, String> {
+        let index = self.path.to_string_lossy();
+        run_git_command_with_env(root, args, &[("GIT_INDEX_FILE", index.as_ref())]).await
+    }
+}
+
+impl Drop for ScratchIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Writes the working tree as `git add -A` would stage it and returns the tree id. Starts from
+/// a copy of the real index so unchanged files are not rehashed.
+async fn write_working_tree(root: &PathBuf) -> Result<String, String> {
+    let index = ScratchIndex::new();
+    let real_index = run_git_command(root, &["rev-parse", "--git-path", "index"]).await?;
+    if std::fs::copy(root.join(real_index), &index.path).is_err() {
+        index.git(root, &["read-tree", "HEAD"]).await?;
+    }
+    index.git(root, &["add", "-A"]).await?;
+    index.git(root, &["write-tree"]).await
+}
+
+/// Keeps `commit` reachable under a private ref named `name`, out of the user's branches and
+/// stash list, then prunes the oldest snapshot refs past [`MAX_SNAPSHOT_REFS`].
+pub(crate) async fn keep_snapshot(root: &PathBuf, name: &str, commit: &str) -> Result<(), String> {
+    let reference = format!("{SNAPSHOT_REF_PREFIX}{name}");
+    run_git_command(root, &["update-ref", &reference, commit]).await?;
+    prune_snapshot_refs(root, MAX_SNAPSHOT_REFS).await
+}
+
+/// Deletes all but the `keep` most recent snapshot refs.
+async fn prune_snapshot_refs(root: &PathBuf, keep: usize) -> Result<(), String> {
+    let refs = run_git_command(
+        root,
+        &[
+            "for-each-ref",
+            "--sort=-creatordate",
+            "--format=%(refname)",
+            SNAPSHOT_REF_PREFIX,
+        ],
+    )
+    .await?;
+    for stale in refs.lines().filter(|line| !line.is_empty()).skip(keep) {
+        run_git_command(root, &["update-ref", "-d", stale]).await?;
+    }
+    Ok(())
+}
+
+/// Records the working tree under `cwd` before a turn so its edits can be reverted later.
+pub(crate) async fn create_turn_snapshot(
+    cwd: &Path,
+    turn_id: &str,
+) -> Result<TurnSnapshot, String> {
+    let cwd = cwd.to_path_buf();
+    let root = run_git_command(&cwd, &["rev-parse", "--show-toplevel"])
+        .await
+        .map_err(|error| format!("Turn snapshots need a git repository: {error}"))?;
+    let root_path = PathBuf::from(&root);
+    let head = run_git_command(&root_path, &["rev-parse", "HEAD"])
+        .await
+        .map_err(|error| format!("Turn snapshots need at least one commit: {error}"))?;
+    let index_tree = run_git_command(&root_path, &["write-tree"])
+        .await
+        .map_err(|error| format!("Failed to snapshot the index: {error}"))?;
+    let index_message = format!("PolyCode index before {turn_id}");
+    let index = run_git_command_with_env(
+        &root_path,
+        &[
+            "commit-tree",
+            &index_tree,
+            "-p",
+            &head,
+            "-m",
+            &index_message,
+        ],
+        SNAPSHOT_IDENTITY,
+    )
+    .await?;
+    let tree = write_working_tree(&root_path).await?;
+    let message = format!("PolyCode snapshot before {turn_id}");
+    let commit = run_git_command_with_env(
+        &root_path,
+        &[
+            "commit-tree",
+            &tree,
+            "-p",
+            &head,
+            "-p",
+            &index,
+            "-m",
+            &message,
+        ],
+        SNAPSHOT_IDENTITY,
+    )
+    .await?;
+    keep_snapshot(&root_path, turn_id, &commit).await?;
+    Ok(TurnSnapshot {
+        commit,
+        head,
+        index,
+        root,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct RestoreOutcome {
+    removed_files: Vec<String>,
+    head_reset: bool,
+}
+
+/// Puts the repository back to the snapshot: commits made since are undone with a soft reset,
+/// the index is reloaded, files are rewritten from the snapshot and files created since are
+/// deleted.
+async fn restore_turn_snapshot(snapshot: &TurnSnapshot) -> Result<RestoreOutcome, String> {
+    let root = PathBuf::from(&snapshot.root);
+    let object = format!("{}^{{commit}}", snapshot.commit);
+    run_git_command(&root, &["cat-file", "-e", &object])
+        .await
+        .map_err(|_| "This turn's snapshot has been pruned.".to_string())?;
+    let current_tree = write_working_tree(&root).await?;
+    let created = run_git_command(
+        &root,
+        &[
+            "-c",
+            "core.quotepath=off",
+            "diff",
+            "--name-only",
+            "--no-renames",
+            "--diff-filter=A",
+            &snapshot.commit,
+            &current_tree,
+        ],
+    )
+    .await?;
+
+    let head = run_git_command(&root, &["rev-parse", "HEAD"]).await?;
+    let head_reset = head != snapshot.head;
+    if head_reset {
+        run_git_command(&root, &["reset", "-q", "--soft", &snapshot.head]).await?;
+    }
+    run_git_command(&root, &["read-tree", &snapshot.index]).await?;
+    let index = ScratchIndex::new();
+    index.git(&root, &["read-tree", &snapshot.commit]).await?;
+    index.git(&root, &["checkout-index", "-a", "-f"]).await?;
+
+    let mut removed_files = Vec::new();
+    for path in created.lines().filter(|line| !line.is_empty()) {
+        match std::fs::remove_file(root.join(path)) {
+            Ok(()) => removed_files.push(path.to_string()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => return Err(format!("Failed to remove {path}: {error}")),
+        }
+    }
+    Ok(RestoreOutcome {
+        removed_files,
+        head_reset,
+    })
+}
+
+/// Restores the workspace to how it was before `turn_id` ran, which also undoes every later
+/// turn.
+pub(crate) async fn revert_turn_changes_core(
+    claude_threads: &ClaudeThreadsStore,
+    claude_turn_cancels: &ClaudeTurnCancelsStore,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+) -> Result<Value, String> {
+    if thread_has_running_turn(claude_turn_cancels, &workspace_id, &thread_id).await {
+        return Err("Stop the running turn before reverting changes.".to_string());
+    }
+    let snapshot = {
+        let store = claude_threads.lock().await;
+        let thread = store
+            .get(&workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == thread_id))
+            .ok_or_else(|| "thread not found".to_string())?;
+        let turn = thread
+            .turns
+            .iter()
+            .find(|turn| turn.id == turn_id)
+            .ok_or_else(|| "turn not found".to_string())?;
+        turn.snapshot
+            .clone()
+            .ok_or_else(|| "This turn has no snapshot to revert to.".to_string())?
+    };
+    let outcome = restore_turn_snapshot(&snapshot).await?;
+    Ok(json!({
+        "result": {
+            "threadId": thread_id,
+            "turnId": turn_id,
+            "snapshot": snapshot.commit,
+            "removedFiles": outcome.removed_files,
+            "headReset": outcome.head_reset,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_turn_snapshot, prune_snapshot_refs, restore_turn_snapshot};
+    use crate::shared::git_core::run_git_command;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn restores_edits_new_files_and_commits_made_after_the_snapshot() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let root = std::env::temp_dir().join(format!("polycode-snapshot-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).expect("create repo dir");
+        let root = root.canonicalize().expect("canonicalize");
+        runtime.block_on(async {
+            let git = |args: &'static [&'static str]| run_git_command(&root, args);
+            git(&["init", "-q"]).await.expect("init");
+            git(&["config", "user.name", "Test"]).await.expect("config");
+            git(&["config", "user.email", "test@example.com"])
+                .await
+                .expect("config");
+            fs::write(root.join("tracked.txt"), "original\n").expect("write");
+            git(&["add", "."]).await.expect("add");
+            git(&["commit", "-q", "-m", "init"]).await.expect("commit");
+            fs::write(root.join("notes.txt"), "untracked before the turn\n").expect("write");
+            fs::write(root.join("tracked.txt"), "user edit\n").expect("write");
+            git(&["add", "tracked.txt"]).await.expect("stage");
+
+            let snapshot = create_turn_snapshot(&root, "turn-1")
+                .await
+                .expect("snapshot");
+            assert_eq!(
+                git(&["rev-parse", "refs/polycode/snapshots/turn-1"])
+                    .await
+                    .expect("ref"),
+                snapshot.commit
+            );
+
+            fs::write(root.join("tracked.txt"), "agent edit\n").expect("write");
+            fs::remove_file(root.join("notes.txt")).expect("remove");
+            fs::create_dir_all(root.join("src")).expect("mkdir");
+            fs::write(root.join("src/new.rs"), "fn main() {}\n").expect("write");
+            git(&["add", "-A"]).await.expect("add");
+            git(&["commit", "-q", "-m", "agent"]).await.expect("commit");
+            fs::write(root.join("scratch.txt"), "left over\n").expect("write");
+
+            let outcome = restore_turn_snapshot(&snapshot).await.expect("restore");
+            assert!(outcome.head_reset);
+            let mut removed = outcome.removed_files.clone();
+            removed.sort();
+            assert_eq!(removed, vec!["scratch.txt", "src/new.rs"]);
+            assert_eq!(
+                fs::read_to_string(root.join("tracked.txt")).expect("read"),
+                "user edit\n"
+            );
+            assert!(root.join("notes.txt").exists());
+            let staged = git(&["diff", "--cached", "--name-only"])
+                .await
+                .expect("staged");
+            assert_eq!(staged, "tracked.txt");
+            assert_eq!(
+                git(&["rev-parse", "HEAD"]).await.expect("head"),
+                snapshot.head
+            );
+
+            create_turn_snapshot(&root, "turn-2")
+                .await
+                .expect("snapshot");
+            prune_snapshot_refs(&root, 1).await.expect("prune");
+            let refs = git(&[
+                "for-each-ref",
+                "--format=%(refname)",
+                "refs/polycode/snapshots/",
+            ])
+            .await
+            .expect("refs");
+            assert_eq!(refs.lines().count(), 1);
+            assert_eq!(git(&["stash", "list"]).await.expect("stash"), "");
+        });
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,135 @@
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::backend::events::EventSink;
+use crate::shared::claude_core::{
+    emit, persist_threads_store, recent_transcript, run_claude_prompt, ClaudeThreadsStore,
+};
+use crate::types::{AppSettings, WorkspaceEntry};
+
+const MAX_SUGGESTIONS: usize = 3;
+const MAX_SUGGESTION_CHARS: usize = 200;
+const SUGGESTIONS_TRANSCRIPT_MAX_CHARS: usize = 12_000;
+const SUGGESTIONS_TIMEOUT: Duration = Duration::from_secs(60);
+const SUGGESTIONS_PROMPT: &str = "Below is a conversation between a user and a coding agent. \
+Suggest up to three short follow-up prompts the user is likely to send next. Reply with one \
+prompt per line and nothing else.\n\n";
+
+/// A finished turn to suggest follow-ups for, with what is needed to run the provider.
+pub(crate) struct SuggestionRequest {
+    pub(crate) entry: WorkspaceEntry,
+    pub(crate) parent_entry: Option<WorkspaceEntry>,
+    pub(crate) settings: AppSettings,
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+}
+
+/// One suggestion per line; list markers, numbering and surrounding quotes are dropped.
+fn parse_suggestions(reply: &str) -> Vec<String> {
+    reply
+        .lines()
+        .map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+            let unnumbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            let line = match unnumbered.strip_prefix(['.', ')']) {
+                Some(rest) if unnumbered.len() < line.len() => rest,
+                _ => line,
+            };
+            line.trim().trim_matches('"').trim().to_string()
+        })
+        .filter(|line| !line.is_empty() && line.chars().count() <= MAX_SUGGESTION_CHARS)
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// Asks the follow-up model for next prompts, stores them on the turn and emits
+/// `turn/suggestions`. Nothing is stored when the reply holds no usable suggestion.
+pub(crate) async fn suggest_follow_ups<E: EventSink>(
+    claude_threads: &ClaudeThreadsStore,
+    claude_threads_path: &Path,
+    request: SuggestionRequest,
+    event_sink: &E,
+) -> Result<(), String> {
+    let transcript = {
+        let store = claude_threads.lock().await;
+        let turns = store
+            .get(&request.workspace_id)
+            .and_then(|threads| threads.iter().find(|thread| thread.id == request.thread_id))
+            .map(|thread| thread.turns.as_slice())
+            .unwrap_or_default();
+        let end = turns
+            .iter()
+            .position(|turn| turn.id == request.turn_id)
+            .ok_or_else(|| "turn not found".to_string())?;
+        recent_transcript(&turns[..=end], SUGGESTIONS_TRANSCRIPT_MAX_CHARS)
+    };
+    let model = request.settings.follow_up_suggestions_model.trim();
+    let reply = run_claude_prompt(
+        &request.entry,
+        request.parent_entry.as_ref(),
+        &request.settings,
+        &format!("{SUGGESTIONS_PROMPT}{transcript}"),
+        (!model.is_empty()).then_some(model),
+        SUGGESTIONS_TIMEOUT,
+    )
+    .await?;
+    let suggestions = parse_suggestions(&reply);
+    if suggestions.is_empty() {
+        return Ok(());
+    }
+
+    {
+        let mut store = claude_threads.lock().await;
+        let Some(turn) = store
+            .get_mut(&request.workspace_id)
+            .and_then(|threads| {
+                threads
+                    .iter_mut()
+                    .find(|thread| thread.id == request.thread_id)
+            })
+            .and_then(|thread| {
+                thread
+                    .turns
+                    .iter_mut()
+                    .find(|turn| turn.id == request.turn_id)
+            })
+        else {
+            return Ok(());
+        };
+        turn.suggestions = suggestions.clone();
+    }
+    persist_threads_store(claude_threads, claude_threads_path).await?;
+    emit(
+        event_sink,
+        &request.workspace_id,
+        "turn/suggestions",
+        json!({
+            "threadId": request.thread_id,
+            "turnId": request.turn_id,
+            "suggestions": suggestions,
+        }),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_suggestions;
+
+    #[test]
+    fn parses_one_suggestion_per_line_without_markers() {
+        let reply = "1. Add tests for the parser\n\n- \"Run the linter\"\n2) Open a PR\n\
+                     Update the changelog";
+        assert_eq!(
+            parse_suggestions(reply),
+            vec!["Add tests for the parser", "Run the linter", "Open a PR"]
+        );
+        assert_eq!(
+            parse_suggestions("2024 release notes\n* Bump to v2.0"),
+            vec!["2024 release notes", "Bump to v2.0"]
+        );
+        assert!(parse_suggestions(&"x".repeat(300)).is_empty());
+    }
+}
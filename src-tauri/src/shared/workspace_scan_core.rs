@@ -0,0 +1,240 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::types::{AppSettings, WorkspaceEntry, WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+
+const DEFAULT_SCAN_DEPTH: u32 = 3;
+const MAX_SCAN_DEPTH: u32 = 8;
+/// Directories that never hold projects worth adding and can be huge.
+const SKIPPED_DIR_NAMES: &[&str] = &["node_modules", "target", "dist", "build", "vendor"];
+
+/// A repository found on disk before it is matched against known workspaces.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ScannedRepo {
+    pub(crate) path: PathBuf,
+    /// Linked worktrees as `(path, branch)`, read from `.git/worktrees`.
+    worktrees: Vec<(PathBuf, Option<String>)>,
+}
+
+fn branch_from_head(git_dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string)
+}
+
+fn linked_worktrees(git_dir: &Path) -> Vec<(PathBuf, Option<String>)> {
+    let Ok(entries) = std::fs::read_dir(git_dir.join("worktrees")) else {
+        return Vec::new();
+    };
+    let mut worktrees = entries
+        .flatten()
+        .filter_map(|entry| {
+            let admin_dir = entry.path();
+            // `gitdir` points at the worktree's `.git` file.
+            let gitdir = std::fs::read_to_string(admin_dir.join("gitdir")).ok()?;
+            let worktree_path = Path::new(gitdir.trim()).parent()?.to_path_buf();
+            worktree_path
+                .is_dir()
+                .then(|| (worktree_path, branch_from_head(&admin_dir)))
+        })
+        .collect::<Vec<_>>();
+    worktrees.sort();
+    worktrees
+}
+
+/// Walks `root` for main repositories, without descending into them. Hidden directories and
+/// common build output are skipped; linked worktrees are found through their main repo.
+pub(crate) fn scan_repositories(root: &Path, max_depth: u32) -> Vec<ScannedRepo> {
+    let mut repos = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0u32)];
+    while let Some((dir, depth)) = pending.pop() {
+        let git_dir = dir.join(".git");
+        if git_dir.is_dir() {
+            repos.push(ScannedRepo {
+                worktrees: linked_worktrees(&git_dir),
+                path: dir,
+            });
+            continue;
+        }
+        // A `.git` file marks a linked worktree, which its main repo already reports.
+        if git_dir.is_file() || depth >= max_depth {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false);
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !is_dir || name.starts_with('.') || SKIPPED_DIR_NAMES.contains(&name.as_str()) {
+                continue;
+            }
+            pending.push((entry.path(), depth + 1));
+        }
+    }
+    repos.sort_by(|left, right| left.path.cmp(&right.path));
+    repos
+}
+
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+pub(crate) fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("Workspace")
+        .to_string()
+}
+
+/// Turns scanned repositories into `WorkspaceEntry` proposals. Paths that are already
+/// workspaces are skipped, but still serve as parents for newly found worktrees.
+fn build_candidates(
+    repos: Vec<ScannedRepo>,
+    known: &HashMap<PathBuf, String>,
+    default_settings: &WorkspaceSettings,
+) -> (Vec<WorkspaceEntry>, usize) {
+    let mut candidates = Vec::new();
+    let mut skipped = 0usize;
+    for repo in repos {
+        let repo_path = normalize_path(&repo.path);
+        let parent_id = match known.get(&repo_path) {
+            Some(id) => {
+                skipped += 1;
+                id.clone()
+            }
+            None => {
+                let id = Uuid::new_v4().to_string();
+                candidates.push(WorkspaceEntry {
+                    id: id.clone(),
+                    name: dir_name(&repo_path),
+                    path: repo_path.to_string_lossy().to_string(),
+                    codex_bin: None,
+                    kind: WorkspaceKind::Main,
+                    parent_id: None,
+                    worktree: None,
+                    settings: default_settings.clone(),
+                });
+                id
+            }
+        };
+        for (worktree_path, branch) in repo.worktrees {
+            let worktree_path = normalize_path(&worktree_path);
+            if known.contains_key(&worktree_path) {
+                skipped += 1;
+                continue;
+            }
+            candidates.push(WorkspaceEntry {
+                id: Uuid::new_v4().to_string(),
+                name: branch.clone().unwrap_or_else(|| dir_name(&worktree_path)),
+                path: worktree_path.to_string_lossy().to_string(),
+                codex_bin: None,
+                kind: WorkspaceKind::Worktree,
+                parent_id: Some(parent_id.clone()),
+                worktree: branch.map(|branch| WorktreeInfo { branch }),
+                settings: default_settings.clone(),
+            });
+        }
+    }
+    (candidates, skipped)
+}
+
+/// Proposes workspaces for every git repository (and linked worktree) under `root`, falling
+/// back to the `workspaceScanRoot` setting. Nothing is added; the caller picks candidates.
+pub(crate) async fn scan_workspaces_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    root: Option<String>,
+    max_depth: Option<u32>,
+) -> Result<Value, String> {
+    let (configured_root, default_provider) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.workspace_scan_root.clone(),
+            settings.default_provider.clone(),
+        )
+    };
+    let root = root
+        .or(configured_root)
+        .map(|root| root.trim().to_string())
+        .filter(|root| !root.is_empty())
+        .ok_or_else(|| "No scan root given and `workspaceScanRoot` is not set.".to_string())?;
+    let root_path = PathBuf::from(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Scan root is not a folder: {root}"));
+    }
+    let max_depth = max_depth.unwrap_or(DEFAULT_SCAN_DEPTH).min(MAX_SCAN_DEPTH);
+
+    let known = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .values()
+            .map(|entry| (normalize_path(Path::new(&entry.path)), entry.id.clone()))
+            .collect::<HashMap<_, _>>()
+    };
+    let repos = tokio::task::spawn_blocking(move || scan_repositories(&root_path, max_depth))
+        .await
+        .map_err(|error| format!("failed to scan workspaces: {error}"))?;
+    let default_settings = WorkspaceSettings {
+        provider: default_provider,
+        ..WorkspaceSettings::default()
+    };
+    let (candidates, skipped) = build_candidates(repos, &known, &default_settings);
+    Ok(json!({
+        "result": {
+            "root": root,
+            "candidates": candidates,
+            "skipped": skipped,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_candidates, scan_repositories};
+    use crate::types::{WorkspaceKind, WorkspaceSettings};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[test]
+    fn scan_finds_repos_and_linked_worktrees() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-scan-{}", Uuid::new_v4()));
+        let repo = root.join("group").join("app");
+        let worktree = root.join("app-feature");
+        std::fs::create_dir_all(repo.join(".git").join("worktrees").join("feature")).unwrap();
+        std::fs::create_dir_all(repo.join("nested").join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules").join("dep").join(".git")).unwrap();
+        std::fs::create_dir_all(&worktree).unwrap();
+        let admin_dir = repo.join(".git").join("worktrees").join("feature");
+        std::fs::write(
+            admin_dir.join("gitdir"),
+            format!("{}\n", worktree.join(".git").display()),
+        )
+        .unwrap();
+        std::fs::write(admin_dir.join("HEAD"), "ref: refs/heads/feature\n").unwrap();
+        std::fs::write(worktree.join(".git"), "gitdir: elsewhere\n").unwrap();
+
+        let repos = scan_repositories(&root, 3);
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].path, repo);
+        assert_eq!(
+            repos[0].worktrees,
+            vec![(worktree.clone(), Some("feature".to_string()))]
+        );
+
+        let known = HashMap::from([(super::normalize_path(&repo), "existing".to_string())]);
+        let (candidates, skipped) = build_candidates(repos, &known, &WorkspaceSettings::default());
+        assert_eq!(skipped, 1);
+        assert_eq!(candidates.len(), 1);
+        assert!(matches!(candidates[0].kind, WorkspaceKind::Worktree));
+        assert_eq!(candidates[0].parent_id.as_deref(), Some("existing"));
+        assert_eq!(candidates[0].name, "feature");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -2,11 +2,14 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::sync::Mutex;
 
 use crate::backend::app_server::WorkspaceSession;
 use crate::providers;
+use crate::shared::sandbox_core;
+use crate::shared::settings_core::SettingsChange;
 use crate::storage::write_workspaces;
 use crate::types::{
     AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
@@ -17,6 +20,8 @@ use uuid::Uuid;
 pub(crate) const WORKTREE_SETUP_MARKERS_DIR: &str = "worktree-setup";
 pub(crate) const WORKTREE_SETUP_MARKER_EXT: &str = "ran";
 const AGENTS_MD_FILE_NAME: &str = "AGENTS.md";
+/// A session replaced after a settings change finishes its running turns for at most this long.
+const RETIRED_SESSION_MAX_WAIT: Duration = Duration::from_secs(10 * 60);
 
 fn copy_agents_md_from_parent_to_worktree(
     parent_repo_root: &PathBuf,
@@ -233,8 +238,7 @@ where
             let mut workspaces = workspaces.lock().await;
             workspaces.remove(&entry.id);
         }
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.close().await;
         return Err(error);
     }
 
@@ -465,8 +469,7 @@ where
 
 async fn kill_session_by_id(sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>, id: &str) {
     if let Some(session) = sessions.lock().await.remove(id) {
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.close().await;
     }
 }
 
@@ -963,6 +966,7 @@ where
     FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
 {
     settings.worktree_setup_script = normalize_setup_script(settings.worktree_setup_script);
+    sandbox_core::validate_sandbox(&settings.sandbox)?;
 
     let (
         previous_entry,
@@ -1040,8 +1044,7 @@ where
             .await
             .insert(entry_snapshot.id.clone(), new_session)
         {
-            let mut child = old_session.child.lock().await;
-            let _ = child.kill().await;
+            old_session.close().await;
         }
     }
     if runtime_changed {
@@ -1085,8 +1088,7 @@ where
                 }
             };
             if let Some(old_session) = sessions.lock().await.insert(child.id.clone(), new_session) {
-                let mut child = old_session.child.lock().await;
-                let _ = child.kill().await;
+                old_session.close().await;
             }
         }
     }
@@ -1158,6 +1160,75 @@ pub(crate) async fn update_workspace_codex_bin_core(
     })
 }
 
+/// Restarts connected sessions whose bin, args or home the settings change alters. The new
+/// process takes over at once; the old one is closed when its running turns finish. Returns
+/// the restarted workspace ids.
+pub(crate) async fn apply_settings_change_core<FSpawn, FutSpawn>(
+    change: &SettingsChange,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    spawn_session: FSpawn,
+) -> Vec<String>
+where
+    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
+    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+{
+    let connected = sessions.lock().await.keys().cloned().collect::<Vec<_>>();
+    let targets = {
+        let workspaces = workspaces.lock().await;
+        connected
+            .into_iter()
+            .filter_map(|id| {
+                let entry = workspaces.get(&id)?;
+                let parent_entry = entry
+                    .parent_id
+                    .as_ref()
+                    .and_then(|parent_id| workspaces.get(parent_id));
+                let runtime = |settings: &AppSettings| {
+                    providers::resolve_runtime_config(entry, parent_entry, Some(settings))
+                };
+                let next = runtime(&change.current);
+                (runtime(&change.previous) != next).then(|| (entry.clone(), next))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut restarted = Vec::new();
+    for (entry, (provider, default_bin, session_args, session_home)) in targets {
+        if providers::ensure_provider_spawn_supported(&provider).is_err() {
+            continue;
+        }
+        let new_session =
+            match spawn_session(entry.clone(), default_bin, session_args, session_home).await {
+                Ok(session) => session,
+                Err(error) => {
+                    eprintln!(
+                        "settings change: restart failed for workspace {}: {error}",
+                        entry.id
+                    );
+                    continue;
+                }
+            };
+        let old_session = {
+            let mut sessions = sessions.lock().await;
+            // Disconnected while the new process was starting.
+            if !sessions.contains_key(&entry.id) {
+                drop(sessions);
+                new_session.close().await;
+                continue;
+            }
+            sessions.insert(entry.id.clone(), new_session)
+        };
+        if let Some(old_session) = old_session {
+            tokio::spawn(async move {
+                old_session.close_when_idle(RETIRED_SESSION_MAX_WAIT).await;
+            });
+        }
+        restarted.push(entry.id);
+    }
+    restarted
+}
+
 pub(crate) async fn list_workspace_files_core<F>(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
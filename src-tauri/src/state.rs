@@ -2,14 +2,33 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 
+use crate::backend::events::AppServerEvent;
 use crate::dictation::DictationState;
 use crate::shared::claude_core::{
     claude_threads_path as resolve_claude_threads_path, read_threads_snapshot, ClaudeThreadsStore,
     ClaudeTurnCancelsStore,
 };
+use crate::shared::claude_follow_core::ClaudeFollowsStore;
+use crate::shared::claude_pool_core::{ClaudeApprovalsStore, ClaudeProcessPool};
 use crate::shared::codex_core::CodexLoginCancelState;
+use crate::shared::data_dir_core::apply_pending_move;
+use crate::shared::drafts_core::{drafts_path, read_drafts, DraftsStore};
+use crate::shared::event_filter_core::EventFiltersStore;
+use crate::shared::gemini_core::GeminiModelsCache;
+use crate::shared::lan_sync_core::LanSyncState;
+use crate::shared::playbooks_core::{playbooks_path, PlaybookRunsStore};
+use crate::shared::prompt_history_core::{
+    prompt_history_path, read_prompt_history, PromptHistoryStore,
+};
+use crate::shared::rate_limit_core::RateLimitsStore;
+use crate::shared::render_cache_core::RenderCacheStore;
+use crate::shared::shutdown_core::recover_after_unclean_shutdown;
+use crate::shared::status_summary_core::StatusTrackerStore;
+use crate::shared::thread_locks_core::ThreadLocksStore;
+use crate::shared::thread_quick_find_core::index_snapshot;
+use crate::shared::turn_slots_core::TurnSlotsStore;
 use crate::storage::{read_settings, read_workspaces};
 use crate::types::{AppSettings, WorkspaceEntry};
 
@@ -19,6 +38,7 @@ pub(crate) struct AppState {
     pub(crate) terminal_sessions:
         Mutex<HashMap<String, Arc<crate::terminal::TerminalSession>>>,
     pub(crate) remote_backend: Mutex<Option<crate::remote_backend::RemoteBackend>>,
+    pub(crate) data_dir: PathBuf,
     pub(crate) storage_path: PathBuf,
     pub(crate) settings_path: PathBuf,
     pub(crate) app_settings: Mutex<AppSettings>,
@@ -27,20 +47,54 @@ pub(crate) struct AppState {
     pub(crate) claude_threads_path: PathBuf,
     pub(crate) claude_threads: ClaudeThreadsStore,
     pub(crate) claude_turn_cancels: ClaudeTurnCancelsStore,
+    pub(crate) claude_follows: ClaudeFollowsStore,
+    pub(crate) claude_processes: ClaudeProcessPool,
+    pub(crate) claude_approvals: ClaudeApprovalsStore,
+    pub(crate) turn_slots: TurnSlotsStore,
+    pub(crate) thread_locks: ThreadLocksStore,
+    pub(crate) rate_limits: RateLimitsStore,
+    pub(crate) gemini_models: GeminiModelsCache,
+    pub(crate) render_cache: RenderCacheStore,
+    pub(crate) turn_notifier: crate::notifications::TurnNotifier,
+    /// Turn events on their way to workspaces' `eventMirrorPath` and the status summary.
+    pub(crate) event_mirror: mpsc::UnboundedSender<AppServerEvent>,
+    /// `subscribe_events` filters, keyed by window label.
+    pub(crate) event_filters: EventFiltersStore,
+    pub(crate) status: StatusTrackerStore,
+    pub(crate) playbooks_path: PathBuf,
+    pub(crate) playbook_runs: PlaybookRunsStore,
+    pub(crate) drafts_path: PathBuf,
+    pub(crate) drafts: DraftsStore,
+    pub(crate) prompt_history_path: PathBuf,
+    pub(crate) prompt_history: PromptHistoryStore,
+    pub(crate) lan_sync: LanSyncState,
 }
 
 impl AppState {
-    pub(crate) fn load(app: &AppHandle) -> Self {
-        let data_dir = app
+    pub(crate) fn load(
+        app: &AppHandle,
+        event_mirror: mpsc::UnboundedSender<AppServerEvent>,
+    ) -> Self {
+        let default_data_dir = app
             .path()
             .app_data_dir()
             .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+        let location = apply_pending_move(&default_data_dir);
+        let data_dir = location.path;
         let storage_path = data_dir.join("workspaces.json");
         let settings_path = data_dir.join("settings.json");
         let claude_threads_path = resolve_claude_threads_path(&data_dir);
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
-        let app_settings = read_settings(&settings_path).unwrap_or_default();
-        let claude_threads = read_threads_snapshot(&claude_threads_path).unwrap_or_default();
+        let mut app_settings = read_settings(&settings_path).unwrap_or_default();
+        app_settings.data_dir = Some(data_dir.to_string_lossy().to_string());
+        app_settings.portable_mode = location.portable;
+        let mut claude_threads = read_threads_snapshot(&claude_threads_path).unwrap_or_default();
+        recover_after_unclean_shutdown(&data_dir, &mut claude_threads);
+        index_snapshot(&claude_threads);
+        let drafts_path = drafts_path(&data_dir);
+        let drafts = read_drafts(&drafts_path).unwrap_or_default();
+        let prompt_history_path = prompt_history_path(&data_dir);
+        let prompt_history = read_prompt_history(&prompt_history_path).unwrap_or_default();
         Self {
             workspaces: Mutex::new(workspaces),
             sessions: Mutex::new(HashMap::new()),
@@ -54,6 +108,26 @@ impl AppState {
             claude_threads_path,
             claude_threads: Arc::new(Mutex::new(claude_threads)),
             claude_turn_cancels: Arc::new(Mutex::new(HashMap::new())),
+            claude_follows: Arc::new(Mutex::new(HashMap::new())),
+            claude_processes: Arc::new(Mutex::new(HashMap::new())),
+            claude_approvals: Arc::new(Mutex::new(HashMap::new())),
+            turn_slots: Default::default(),
+            thread_locks: Default::default(),
+            rate_limits: Default::default(),
+            gemini_models: Arc::new(Mutex::new(None)),
+            render_cache: Default::default(),
+            turn_notifier: Default::default(),
+            event_mirror,
+            event_filters: Default::default(),
+            status: Default::default(),
+            playbooks_path: playbooks_path(&data_dir),
+            data_dir,
+            playbook_runs: Arc::new(Mutex::new(HashMap::new())),
+            drafts_path,
+            drafts: Arc::new(Mutex::new(drafts)),
+            prompt_history_path,
+            prompt_history: Arc::new(Mutex::new(prompt_history)),
+            lan_sync: Mutex::new(None),
         }
     }
 }
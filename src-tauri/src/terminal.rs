@@ -313,3 +313,15 @@ pub(crate) async fn terminal_close(
     .await;
     Ok(())
 }
+
+/// Kills every terminal's shell; called on app exit.
+pub(crate) async fn close_all_terminals(state: &AppState) {
+    let sessions = std::mem::take(&mut *state.terminal_sessions.lock().await);
+    for session in sessions.into_values() {
+        let _ = tokio::task::spawn_blocking(move || {
+            let mut child = session.child.blocking_lock();
+            let _ = child.kill();
+        })
+        .await;
+    }
+}
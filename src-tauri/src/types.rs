@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct GitFileStatus {
@@ -314,6 +315,91 @@ pub(crate) struct WorkspaceSettings {
     pub(crate) launch_scripts: Option<Vec<LaunchScriptEntry>>,
     #[serde(default, rename = "worktreeSetupScript")]
     pub(crate) worktree_setup_script: Option<String>,
+    #[serde(default, rename = "contextFiles")]
+    pub(crate) context_files: Vec<String>,
+    #[serde(default, rename = "turnNotifications")]
+    pub(crate) turn_notifications: Option<bool>,
+    /// Shell command run after each agent turn finishes.
+    #[serde(default, rename = "onTurnCompleted")]
+    pub(crate) on_turn_completed: Option<String>,
+    /// Start each new thread in its own git worktree and branch.
+    #[serde(default, rename = "threadWorktrees")]
+    pub(crate) thread_worktrees: bool,
+    /// Snapshot the working tree into a git ref before each turn so its edits can be reverted.
+    #[serde(default, rename = "turnSnapshots")]
+    pub(crate) turn_snapshots: bool,
+    /// Id of an `AppSettings.providerProfiles` entry for this workspace's provider.
+    #[serde(default, rename = "providerProfileId")]
+    pub(crate) provider_profile_id: Option<String>,
+    /// Claude provider profile ids to retry a turn with, in order, when the CLI can't be
+    /// started or rejects its credentials before answering.
+    #[serde(default, rename = "providerFallbacks")]
+    pub(crate) provider_fallbacks: Vec<String>,
+    /// Names usable as `{{secret:NAME}}` in prompts; the values live in the OS keyring.
+    #[serde(default, rename = "secretNames")]
+    pub(crate) secret_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "SandboxSettings::is_default")]
+    pub(crate) sandbox: SandboxSettings,
+    /// Standing instructions for every thread: Claude gets them via `--append-system-prompt`,
+    /// Codex as developer instructions when a thread starts.
+    #[serde(default)]
+    pub(crate) instructions: Option<String>,
+    /// Files or directories, relative to the turn's directory, copied into the turn's artifacts
+    /// folder when a turn writes to them (test reports, coverage, build output).
+    #[serde(default, rename = "artifactPaths")]
+    pub(crate) artifact_paths: Vec<String>,
+    /// Language agents answer in (`German`, `pt-BR`, ...), sent along with `instructions`.
+    #[serde(default, rename = "responseLanguage")]
+    pub(crate) response_language: Option<String>,
+    /// Named pipe, unix socket or file that turn events are mirrored to as NDJSON.
+    #[serde(default, rename = "eventMirrorPath")]
+    pub(crate) event_mirror_path: Option<String>,
+}
+
+/// Limits on what agents spawned for a workspace may do.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SandboxSettings {
+    /// Codex sandbox forced for every turn: `read-only`, `workspace-write` or `full-access`.
+    /// Unset keeps the access mode picked in the composer.
+    #[serde(default, rename = "codexMode")]
+    pub(crate) codex_mode: Option<String>,
+    /// Pass `--dangerously-skip-permissions` to Claude; never on unless the user opts in.
+    #[serde(default, rename = "claudeSkipPermissions")]
+    pub(crate) claude_skip_permissions: bool,
+    /// Claude tool rules such as `Read` or `Bash(git diff:*)` passed as `--allowedTools`.
+    /// Worktrees with an empty list use the parent's.
+    #[serde(default, rename = "claudeAllowedTools")]
+    pub(crate) claude_allowed_tools: Vec<String>,
+    /// Rules passed as `--disallowedTools`; inherited like `claudeAllowedTools`.
+    #[serde(default, rename = "claudeDisallowedTools")]
+    pub(crate) claude_disallowed_tools: Vec<String>,
+    /// Start the agent with only a baseline of environment variables (PATH, HOME, locale, ...)
+    /// plus `allowedEnv` and the provider profile's variables.
+    #[serde(default, rename = "restrictEnv")]
+    pub(crate) restrict_env: bool,
+    #[serde(default, rename = "allowedEnv")]
+    pub(crate) allowed_env: Vec<String>,
+    /// Cut the agent off from the network at the OS level (Linux and macOS only).
+    #[serde(default, rename = "noNetwork")]
+    pub(crate) no_network: bool,
+    /// Gitignore-style globs, relative to the workspace, that Claude's file tools may use
+    /// without asking. Empty allows every path in the workspace.
+    #[serde(default, rename = "fileAllow")]
+    pub(crate) file_allow: Vec<String>,
+    /// Globs Claude's file tools must ask before touching, even when allowed. Checked on turns
+    /// that run over the permission prompt; plan turns and retried attempts are not gated.
+    #[serde(default, rename = "fileDeny")]
+    pub(crate) file_deny: Vec<String>,
+}
+
+impl SandboxSettings {
+    pub(crate) fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub(crate) fn has_file_rules(&self) -> bool {
+        !self.file_allow.is_empty() || !self.file_deny.is_empty()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -345,18 +431,130 @@ pub(crate) struct OpenAppTarget {
     pub(crate) args: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OutputFilterKind {
+    Regex,
+    JsonPath,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct OutputFilterRule {
+    pub(crate) id: String,
+    pub(crate) kind: OutputFilterKind,
+    pub(crate) pattern: String,
+    #[serde(default = "default_output_filter_enabled")]
+    pub(crate) enabled: bool,
+    #[serde(default)]
+    pub(crate) provider: Option<ProviderKind>,
+}
+
+/// A regex whose matches in assistant text are replaced before the text is stored or exported.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RedactionRule {
+    pub(crate) id: String,
+    pub(crate) pattern: String,
+    #[serde(default = "default_redaction_replacement")]
+    pub(crate) replacement: String,
+    #[serde(default = "default_output_filter_enabled")]
+    pub(crate) enabled: bool,
+}
+
+/// Named account settings for one provider, such as "work Claude" and "personal Claude".
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct ProviderProfile {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) provider: ProviderKind,
+    #[serde(default)]
+    pub(crate) bin: Option<String>,
+    #[serde(default)]
+    pub(crate) args: Option<String>,
+    /// Extra environment for the CLI processes started per turn.
+    #[serde(default)]
+    pub(crate) env: HashMap<String, String>,
+    /// Exported as the provider's API key variable, e.g. `ANTHROPIC_API_KEY`.
+    #[serde(default, rename = "apiKey")]
+    pub(crate) api_key: Option<String>,
+}
+
+/// Claude's `--permission-mode` values.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ClaudePermissionMode {
+    Default,
+    AcceptEdits,
+    Plan,
+    BypassPermissions,
+}
+
+impl ClaudePermissionMode {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ClaudePermissionMode::Default => "default",
+            ClaudePermissionMode::AcceptEdits => "acceptEdits",
+            ClaudePermissionMode::Plan => "plan",
+            ClaudePermissionMode::BypassPermissions => "bypassPermissions",
+        }
+    }
+}
+
+/// Common Claude CLI options, rendered to args ahead of `claudeArgs`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct ClaudeOptions {
+    #[serde(default, rename = "permissionMode")]
+    pub(crate) permission_mode: Option<ClaudePermissionMode>,
+    #[serde(default, rename = "maxTurns")]
+    pub(crate) max_turns: Option<u32>,
+    /// Tool rules such as `Read` or `Bash(git diff:*)`.
+    #[serde(default, rename = "allowedTools")]
+    pub(crate) allowed_tools: Vec<String>,
+}
+
+/// Codex's `--sandbox` values.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CodexSandboxMode {
+    ReadOnly,
+    WorkspaceWrite,
+    DangerFullAccess,
+}
+
+impl CodexSandboxMode {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            CodexSandboxMode::ReadOnly => "read-only",
+            CodexSandboxMode::WorkspaceWrite => "workspace-write",
+            CodexSandboxMode::DangerFullAccess => "danger-full-access",
+        }
+    }
+}
+
+/// Common Codex CLI options, rendered to the lowest layer of the Codex args.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub(crate) struct CodexOptions {
+    #[serde(default)]
+    pub(crate) sandbox: Option<CodexSandboxMode>,
+    #[serde(default)]
+    pub(crate) model: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct AppSettings {
     #[serde(default, rename = "codexBin")]
     pub(crate) codex_bin: Option<String>,
     #[serde(default, rename = "codexArgs")]
     pub(crate) codex_args: Option<String>,
+    #[serde(default, rename = "codexOptions")]
+    pub(crate) codex_options: CodexOptions,
     #[serde(default, rename = "defaultProvider")]
     pub(crate) default_provider: Option<ProviderKind>,
     #[serde(default, rename = "claudeBin")]
     pub(crate) claude_bin: Option<String>,
     #[serde(default, rename = "claudeArgs")]
     pub(crate) claude_args: Option<String>,
+    #[serde(default, rename = "claudeOptions")]
+    pub(crate) claude_options: ClaudeOptions,
     #[serde(default, rename = "geminiBin")]
     pub(crate) gemini_bin: Option<String>,
     #[serde(default, rename = "geminiArgs")]
@@ -489,6 +687,11 @@ pub(crate) struct AppSettings {
         rename = "systemNotificationsEnabled"
     )]
     pub(crate) system_notifications_enabled: bool,
+    /// "HH:MM" local time; notifications are suppressed between start and end.
+    #[serde(default, rename = "notificationQuietHoursStart")]
+    pub(crate) notification_quiet_hours_start: Option<String>,
+    #[serde(default, rename = "notificationQuietHoursEnd")]
+    pub(crate) notification_quiet_hours_end: Option<String>,
     #[serde(
         default = "default_experimental_collab_enabled",
         rename = "experimentalCollabEnabled"
@@ -524,6 +727,9 @@ pub(crate) struct AppSettings {
     pub(crate) dictation_model_id: String,
     #[serde(default, rename = "dictationPreferredLanguage")]
     pub(crate) dictation_preferred_language: Option<String>,
+    /// When set, audio prompts are posted here instead of the local Whisper model.
+    #[serde(default, rename = "dictationEndpoint")]
+    pub(crate) dictation_endpoint: Option<String>,
     #[serde(default = "default_dictation_hold_key", rename = "dictationHoldKey")]
     pub(crate) dictation_hold_key: String,
     #[serde(
@@ -577,6 +783,145 @@ pub(crate) struct AppSettings {
     pub(crate) open_app_targets: Vec<OpenAppTarget>,
     #[serde(default = "default_selected_open_app_id", rename = "selectedOpenAppId")]
     pub(crate) selected_open_app_id: String,
+    /// Drops matching lines from Claude output; Codex and Gemini output is not filtered.
+    #[serde(default, rename = "outputFilters")]
+    pub(crate) output_filters: Vec<OutputFilterRule>,
+    /// Strip every terminal escape and control character from agent output, not just colors.
+    #[serde(default = "default_sanitize_output", rename = "sanitizeOutput")]
+    pub(crate) sanitize_output: bool,
+    /// TCP port the LAN sync listener binds; 0 picks a free one.
+    #[serde(default = "default_lan_sync_port", rename = "lanSyncPort")]
+    pub(crate) lan_sync_port: u16,
+    /// Interface address the LAN sync listener binds, e.g. one LAN address or `127.0.0.1`.
+    /// Unset listens on every IPv4 interface.
+    #[serde(default, rename = "lanSyncBindAddress")]
+    pub(crate) lan_sync_bind_address: Option<String>,
+    /// Shared secret both devices must hold to sync threads over the local network.
+    #[serde(default, rename = "lanSyncSecret")]
+    pub(crate) lan_sync_secret: Option<String>,
+    /// Where threads, logs and settings are stored. Filled in at startup; moved with
+    /// `storage_migrate`, never by saving settings.
+    #[serde(default, rename = "dataDir")]
+    pub(crate) data_dir: Option<String>,
+    /// Data lives next to the executable instead of the OS app data dir.
+    #[serde(default, rename = "portableMode")]
+    pub(crate) portable_mode: bool,
+    /// Browse-only mode: thread history stays readable but agent runs and edits are refused.
+    #[serde(default, rename = "readOnly")]
+    pub(crate) read_only: bool,
+    /// Extra attempts for turns that fail with rate-limit or overload errors.
+    #[serde(default = "default_retry_max_attempts", rename = "retryMaxAttempts")]
+    pub(crate) retry_max_attempts: u32,
+    #[serde(default = "default_retry_base_delay_ms", rename = "retryBaseDelayMs")]
+    pub(crate) retry_base_delay_ms: u64,
+    /// Seconds between `turn/heartbeat` events while a turn runs; `0` turns them off.
+    #[serde(
+        default = "default_turn_heartbeat_interval_secs",
+        rename = "turnHeartbeatIntervalSecs"
+    )]
+    pub(crate) turn_heartbeat_interval_secs: u64,
+    /// Provider processes allowed to run turns at once across all workspaces; further turns
+    /// wait in a queue. `0` removes the limit.
+    #[serde(
+        default = "default_max_concurrent_turns",
+        rename = "maxConcurrentTurns"
+    )]
+    pub(crate) max_concurrent_turns: u32,
+    /// Bytes of assistant output a turn keeps and streams; the rest only reaches the raw log.
+    /// `0` removes the cap.
+    #[serde(
+        default = "default_max_turn_output_bytes",
+        rename = "maxTurnOutputBytes"
+    )]
+    pub(crate) max_turn_output_bytes: u64,
+    /// Threads idle for longer than this are cleaned up by the retention task.
+    #[serde(default, rename = "retentionMaxThreadAgeDays")]
+    pub(crate) retention_max_thread_age_days: Option<u32>,
+    /// Only the most recently updated threads up to this count are kept per workspace.
+    #[serde(default, rename = "retentionMaxThreadsPerWorkspace")]
+    pub(crate) retention_max_threads_per_workspace: Option<u32>,
+    #[serde(default, rename = "retentionAction")]
+    pub(crate) retention_action: RetentionAction,
+    /// Whether deleted threads also lose the Claude CLI's own session logs once they are
+    /// purged from the trash.
+    #[serde(default, rename = "retentionDeleteSessionLogs")]
+    pub(crate) retention_delete_session_logs: bool,
+    /// Days archived or deleted threads stay restorable; 0 keeps them until restored.
+    #[serde(
+        default = "default_trash_retention_days",
+        rename = "trashRetentionDays"
+    )]
+    pub(crate) trash_retention_days: u32,
+    /// Claude threads idle for this many minutes get a one-paragraph summary for listings.
+    /// Unset leaves threads unsummarized.
+    #[serde(default, rename = "idleSummaryAfterMinutes")]
+    pub(crate) idle_summary_after_minutes: Option<u32>,
+    /// Re-import Claude CLI history for every workspace this often. Unset syncs once at
+    /// startup and otherwise only on `threads_sync`.
+    #[serde(default, rename = "historySyncIntervalMinutes")]
+    pub(crate) history_sync_interval_minutes: Option<u32>,
+    /// Ask for a few follow-up prompts after each successful Claude turn.
+    #[serde(default, rename = "followUpSuggestions")]
+    pub(crate) follow_up_suggestions: bool,
+    /// Model the follow-ups are asked from; empty uses the CLI's default model.
+    #[serde(
+        default = "default_follow_up_suggestions_model",
+        rename = "followUpSuggestionsModel"
+    )]
+    pub(crate) follow_up_suggestions_model: String,
+    /// Keep a stream-json Claude process alive per workspace instead of spawning one per turn.
+    #[serde(default, rename = "claudeWarmProcess")]
+    pub(crate) claude_warm_process: bool,
+    #[serde(
+        default = "default_claude_warm_idle_timeout_secs",
+        rename = "claudeWarmIdleTimeoutSecs"
+    )]
+    pub(crate) claude_warm_idle_timeout_secs: u64,
+    /// Ask before Claude runs Bash commands instead of running in bypass mode.
+    #[serde(default, rename = "claudeCommandApprovals")]
+    pub(crate) claude_command_approvals: bool,
+    /// Shell (`zsh`, `fish`, `pwsh`, ...) that Claude CLI runs and turn hooks are launched
+    /// through, so CLIs set up in its profile resolve. Unset spawns them directly.
+    #[serde(default, rename = "launchShell")]
+    pub(crate) launch_shell: Option<String>,
+    /// Oldest CLI version accepted per provider id (`claude`, `codex`, `gemini`).
+    #[serde(default, rename = "providerMinVersions")]
+    pub(crate) provider_min_versions: HashMap<String, String>,
+    /// Folder `scan_workspaces` walks for repositories when no root is passed.
+    #[serde(default, rename = "workspaceScanRoot")]
+    pub(crate) workspace_scan_root: Option<String>,
+    #[serde(default = "default_redaction_rules", rename = "redactionRules")]
+    pub(crate) redaction_rules: Vec<RedactionRule>,
+    /// Keep the unredacted text in an encrypted per-workspace log under the app data dir.
+    #[serde(default, rename = "redactionRawLog")]
+    pub(crate) redaction_raw_log: bool,
+    /// Selected per workspace through `providerProfileId`.
+    #[serde(default, rename = "providerProfiles")]
+    pub(crate) provider_profiles: Vec<ProviderProfile>,
+    /// Cheaper model per provider id that turns drop to under the downgrade policy; providers
+    /// without one always use the thread's model.
+    #[serde(default, rename = "cheaperModels")]
+    pub(crate) cheaper_models: HashMap<String, String>,
+    /// Threads with any of these tags run on the cheaper model.
+    #[serde(default = "default_downgrade_tags", rename = "downgradeTags")]
+    pub(crate) downgrade_tags: Vec<String>,
+    /// Turns allowed across all workspaces in a rolling day; unset means no budget.
+    #[serde(default, rename = "dailyTurnBudget")]
+    pub(crate) daily_turn_budget: Option<u32>,
+    /// Share of the daily budget after which every turn runs on the cheaper model.
+    #[serde(
+        default = "default_downgrade_budget_percent",
+        rename = "downgradeBudgetPercent"
+    )]
+    pub(crate) downgrade_budget_percent: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RetentionAction {
+    #[default]
+    Archive,
+    Delete,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -741,6 +1086,54 @@ fn default_personality() -> String {
     "friendly".to_string()
 }
 
+fn default_sanitize_output() -> bool {
+    true
+}
+
+fn default_lan_sync_port() -> u16 {
+    47_340
+}
+
+fn default_retry_max_attempts() -> u32 {
+    2
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    2000
+}
+
+fn default_turn_heartbeat_interval_secs() -> u64 {
+    5
+}
+
+fn default_max_concurrent_turns() -> u32 {
+    3
+}
+
+fn default_max_turn_output_bytes() -> u64 {
+    2 * 1024 * 1024
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_follow_up_suggestions_model() -> String {
+    "haiku".to_string()
+}
+
+fn default_downgrade_tags() -> Vec<String> {
+    vec!["chat".to_string(), "simple".to_string()]
+}
+
+fn default_downgrade_budget_percent() -> u32 {
+    80
+}
+
+fn default_claude_warm_idle_timeout_secs() -> u64 {
+    300
+}
+
 fn default_dictation_enabled() -> bool {
     false
 }
@@ -850,14 +1243,51 @@ fn default_selected_open_app_id() -> String {
     "vscode".to_string()
 }
 
+fn default_output_filter_enabled() -> bool {
+    true
+}
+
+fn default_redaction_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+fn default_redaction_rules() -> Vec<RedactionRule> {
+    let rule = |id: &str, pattern: &str, enabled: bool| RedactionRule {
+        id: id.to_string(),
+        pattern: pattern.to_string(),
+        replacement: default_redaction_replacement(),
+        enabled,
+    };
+    vec![
+        rule(
+            "api-keys",
+            r"\b(?:sk-(?:ant-)?[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16}|AIza[0-9A-Za-z_-]{35})\b",
+            true,
+        ),
+        rule(
+            "tokens",
+            r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|xox[abpr]-[A-Za-z0-9-]{10,}|glpat-[A-Za-z0-9_-]{20,})\b",
+            true,
+        ),
+        rule("bearer", r"(?i:bearer)\s+[A-Za-z0-9._~+/-]{20,}=*", true),
+        rule(
+            "emails",
+            r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+            false,
+        ),
+    ]
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             codex_bin: None,
             codex_args: None,
+            codex_options: CodexOptions::default(),
             default_provider: None,
             claude_bin: None,
             claude_args: None,
+            claude_options: ClaudeOptions::default(),
             gemini_bin: None,
             gemini_args: None,
             backend_mode: BackendMode::Local,
@@ -892,6 +1322,8 @@ impl Default for AppSettings {
             code_font_size: default_code_font_size(),
             notification_sounds_enabled: true,
             system_notifications_enabled: true,
+            notification_quiet_hours_start: None,
+            notification_quiet_hours_end: None,
             preload_git_diffs: default_preload_git_diffs(),
             git_diff_ignore_whitespace_changes: default_git_diff_ignore_whitespace_changes(),
             experimental_collab_enabled: false,
@@ -903,6 +1335,7 @@ impl Default for AppSettings {
             dictation_enabled: false,
             dictation_model_id: default_dictation_model_id(),
             dictation_preferred_language: None,
+            dictation_endpoint: None,
             dictation_hold_key: default_dictation_hold_key(),
             composer_editor_preset: default_composer_editor_preset(),
             composer_fence_expand_on_space: default_composer_fence_expand_on_space(),
@@ -918,6 +1351,41 @@ impl Default for AppSettings {
             workspace_groups: default_workspace_groups(),
             open_app_targets: default_open_app_targets(),
             selected_open_app_id: default_selected_open_app_id(),
+            output_filters: Vec::new(),
+            sanitize_output: default_sanitize_output(),
+            lan_sync_port: default_lan_sync_port(),
+            lan_sync_bind_address: None,
+            lan_sync_secret: None,
+            data_dir: None,
+            portable_mode: false,
+            read_only: false,
+            retry_max_attempts: default_retry_max_attempts(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            turn_heartbeat_interval_secs: default_turn_heartbeat_interval_secs(),
+            max_concurrent_turns: default_max_concurrent_turns(),
+            max_turn_output_bytes: default_max_turn_output_bytes(),
+            retention_max_thread_age_days: None,
+            retention_max_threads_per_workspace: None,
+            retention_action: RetentionAction::default(),
+            retention_delete_session_logs: false,
+            trash_retention_days: default_trash_retention_days(),
+            idle_summary_after_minutes: None,
+            history_sync_interval_minutes: None,
+            follow_up_suggestions: false,
+            follow_up_suggestions_model: default_follow_up_suggestions_model(),
+            claude_warm_process: false,
+            claude_warm_idle_timeout_secs: default_claude_warm_idle_timeout_secs(),
+            claude_command_approvals: false,
+            launch_shell: None,
+            provider_min_versions: HashMap::new(),
+            workspace_scan_root: None,
+            redaction_rules: default_redaction_rules(),
+            redaction_raw_log: false,
+            provider_profiles: Vec::new(),
+            cheaper_models: HashMap::new(),
+            downgrade_tags: default_downgrade_tags(),
+            daily_turn_budget: None,
+            downgrade_budget_percent: default_downgrade_budget_percent(),
         }
     }
 }
@@ -925,8 +1393,8 @@ impl Default for AppSettings {
 #[cfg(test)]
 mod tests {
     use super::{
-        AppSettings, BackendMode, ProviderKind, WorkspaceEntry, WorkspaceGroup, WorkspaceKind,
-        WorkspaceSettings,
+        AppSettings, BackendMode, ClaudeOptions, CodexOptions, ProviderKind, RetentionAction,
+        WorkspaceEntry, WorkspaceGroup, WorkspaceKind, WorkspaceSettings,
     };
 
     #[test]
@@ -1006,6 +1474,8 @@ mod tests {
         assert_eq!(settings.code_font_size, 11);
         assert!(settings.notification_sounds_enabled);
         assert!(settings.system_notifications_enabled);
+        assert!(settings.notification_quiet_hours_start.is_none());
+        assert!(settings.notification_quiet_hours_end.is_none());
         assert!(settings.preload_git_diffs);
         assert!(!settings.git_diff_ignore_whitespace_changes);
         assert!(settings.collaboration_modes_enabled);
@@ -1016,6 +1486,7 @@ mod tests {
         assert!(!settings.dictation_enabled);
         assert_eq!(settings.dictation_model_id, "base");
         assert!(settings.dictation_preferred_language.is_none());
+        assert!(settings.dictation_endpoint.is_none());
         assert_eq!(settings.dictation_hold_key, "alt");
         assert_eq!(settings.composer_editor_preset, "default");
         assert!(!settings.composer_fence_expand_on_space);
@@ -1030,6 +1501,43 @@ mod tests {
         assert_eq!(settings.selected_open_app_id, "vscode");
         assert_eq!(settings.open_app_targets.len(), 6);
         assert_eq!(settings.open_app_targets[0].id, "vscode");
+        assert!(settings.output_filters.is_empty());
+        assert!(settings.sanitize_output);
+        assert_eq!(settings.lan_sync_port, 47_340);
+        assert!(settings.lan_sync_bind_address.is_none());
+        assert!(settings.lan_sync_secret.is_none());
+        assert!(settings.data_dir.is_none());
+        assert!(!settings.portable_mode);
+        assert!(!settings.read_only);
+        assert_eq!(settings.retry_max_attempts, 2);
+        assert_eq!(settings.retry_base_delay_ms, 2000);
+        assert_eq!(settings.turn_heartbeat_interval_secs, 5);
+        assert_eq!(settings.max_concurrent_turns, 3);
+        assert_eq!(settings.max_turn_output_bytes, 2 * 1024 * 1024);
+        assert!(settings.retention_max_thread_age_days.is_none());
+        assert!(settings.retention_max_threads_per_workspace.is_none());
+        assert_eq!(settings.retention_action, RetentionAction::Archive);
+        assert!(!settings.retention_delete_session_logs);
+        assert_eq!(settings.trash_retention_days, 30);
+        assert!(settings.idle_summary_after_minutes.is_none());
+        assert!(settings.history_sync_interval_minutes.is_none());
+        assert!(!settings.follow_up_suggestions);
+        assert_eq!(settings.follow_up_suggestions_model, "haiku");
+        assert!(!settings.claude_warm_process);
+        assert_eq!(settings.claude_warm_idle_timeout_secs, 300);
+        assert!(!settings.claude_command_approvals);
+        assert!(settings.launch_shell.is_none());
+        assert!(settings.provider_min_versions.is_empty());
+        assert!(settings.workspace_scan_root.is_none());
+        assert_eq!(settings.redaction_rules.len(), 4);
+        assert!(!settings.redaction_raw_log);
+        assert!(settings.provider_profiles.is_empty());
+        assert!(settings.cheaper_models.is_empty());
+        assert_eq!(settings.downgrade_tags, vec!["chat", "simple"]);
+        assert!(settings.daily_turn_budget.is_none());
+        assert_eq!(settings.downgrade_budget_percent, 80);
+        assert_eq!(settings.claude_options, ClaudeOptions::default());
+        assert_eq!(settings.codex_options, CodexOptions::default());
     }
 
     #[test]
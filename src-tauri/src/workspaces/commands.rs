@@ -2,8 +2,8 @@ use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 
-use serde_json::json;
-use tauri::{AppHandle, Emitter, Manager, State};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
@@ -24,11 +24,13 @@ use super::worktree::{
 use crate::backend::app_server::{spawn_passthrough_workspace_session, WorkspaceSession};
 use crate::backend::events::AppServerEvent;
 use crate::codex::spawn_workspace_session;
+use crate::event_sink::emit_app_server_event;
 use crate::git_utils::resolve_git_root;
 use crate::providers;
 use crate::remote_backend;
 use crate::shared::process_core::tokio_command;
-use crate::shared::workspaces_core;
+use crate::shared::settings_core::SettingsChange;
+use crate::shared::{secrets_core, setup_core, workspace_scan_core, workspaces_core};
 use crate::state::AppState;
 use crate::storage::write_workspaces;
 use crate::types::{
@@ -59,8 +61,8 @@ fn spawn_with_app(
             }
         };
         if matches!(provider, ProviderKind::Claude) {
-            let _ = app.emit(
-                "app-server-event",
+            emit_app_server_event(
+                &app,
                 AppServerEvent {
                     workspace_id: workspace_id.clone(),
                     message: json!({
@@ -74,6 +76,20 @@ fn spawn_with_app(
     }
 }
 
+/// Restarts the sessions a saved settings change affects.
+pub(crate) async fn apply_settings_change(app: AppHandle, change: SettingsChange) {
+    let state = app.state::<AppState>();
+    workspaces_core::apply_settings_change_core(
+        &change,
+        &state.workspaces,
+        &state.sessions,
+        |entry, default_bin, codex_args, codex_home| {
+            spawn_with_app(app.clone(), entry, default_bin, codex_args, codex_home)
+        },
+    )
+    .await;
+}
+
 #[tauri::command]
 pub(crate) async fn read_workspace_file(
     workspace_id: String,
@@ -134,6 +150,43 @@ pub(crate) async fn is_workspace_path_dir(
     Ok(workspaces_core::is_workspace_path_dir_core(&path))
 }
 
+#[tauri::command]
+pub(crate) async fn scan_workspaces(
+    root: Option<String>,
+    max_depth: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let root = root.map(remote_backend::normalize_path_for_remote);
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "scan_workspaces",
+            json!({ "root": root, "maxDepth": max_depth }),
+        )
+        .await;
+    }
+    workspace_scan_core::scan_workspaces_core(
+        &state.workspaces,
+        &state.app_settings,
+        root,
+        max_depth,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn setup_probe(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(&*state, app, "setup_probe", json!({})).await;
+    }
+    setup_core::setup_probe_core(&state.workspaces, &state.app_settings).await
+}
+
 #[tauri::command]
 pub(crate) async fn add_workspace(
     path: String,
@@ -276,8 +329,7 @@ pub(crate) async fn add_clone(
             let mut workspaces = state.workspaces.lock().await;
             workspaces.remove(&entry.id);
         }
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        session.close().await;
         let _ = tokio::fs::remove_dir_all(&destination_path).await;
         return Err(error);
     }
@@ -327,17 +379,12 @@ pub(crate) async fn add_worktree(
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
-    let data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
-
     workspaces_core::add_worktree_core(
         parent_id,
         branch,
         name,
         copy_agents_md,
-        &data_dir,
+        &state.data_dir,
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
@@ -379,11 +426,8 @@ pub(crate) async fn worktree_setup_status(
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
-    let data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
-    workspaces_core::worktree_setup_status_core(&state.workspaces, &workspace_id, &data_dir).await
+    workspaces_core::worktree_setup_status_core(&state.workspaces, &workspace_id, &state.data_dir)
+        .await
 }
 
 #[tauri::command]
@@ -403,11 +447,58 @@ pub(crate) async fn worktree_setup_mark_ran(
         return Ok(());
     }
 
-    let data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
-    workspaces_core::worktree_setup_mark_ran_core(&state.workspaces, &workspace_id, &data_dir).await
+    workspaces_core::worktree_setup_mark_ran_core(&state.workspaces, &workspace_id, &state.data_dir)
+        .await
+}
+
+/// Saves a secret for `{{secret:NAME}}` prompt placeholders in the OS keyring.
+#[tauri::command]
+pub(crate) async fn secrets_set(
+    workspace_id: String,
+    name: String,
+    value: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "secrets_set",
+            json!({ "workspaceId": workspace_id, "name": name, "value": value }),
+        )
+        .await;
+    }
+
+    secrets_core::set_secret_core(
+        &state.workspaces,
+        &state.storage_path,
+        workspace_id,
+        name,
+        value,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn secrets_delete(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "secrets_delete",
+            json!({ "workspaceId": workspace_id, "name": name }),
+        )
+        .await;
+    }
+
+    secrets_core::delete_secret_core(&state.workspaces, &state.storage_path, workspace_id, name)
+        .await
 }
 
 #[tauri::command]
@@ -490,15 +581,10 @@ pub(crate) async fn rename_worktree(
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
-    let data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
-
     workspaces_core::rename_worktree_core(
         id,
         branch,
-        &data_dir,
+        &state.data_dir,
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
@@ -281,7 +281,6 @@ function MainApp() {
   } = useUpdaterController({
     notificationSoundsEnabled: appSettings.notificationSoundsEnabled,
     systemNotificationsEnabled: appSettings.systemNotificationsEnabled,
-    getWorkspaceName,
     onThreadNotificationSent: (workspaceId, threadId) =>
       recordPendingThreadLinkRef.current(workspaceId, threadId),
     onDebug: addDebugEntry,
@@ -49,6 +49,7 @@ describe("useAppServerEvents", () => {
       onWorkspaceConnected: vi.fn(),
       onThreadStarted: vi.fn(),
       onThreadNameUpdated: vi.fn(),
+      onThreadListUpdated: vi.fn(),
       onBackgroundThreadAction: vi.fn(),
       onAgentMessageDelta: vi.fn(),
       onReasoningSummaryBoundary: vi.fn(),
@@ -144,6 +145,23 @@ describe("useAppServerEvents", () => {
       threadName: "Renamed from server",
     });
 
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "thread/listUpdated",
+          params: {
+            threads: [{ id: "thread-2", preview: "Updated" }],
+            removedThreadIds: ["thread-3"],
+          },
+        },
+      });
+    });
+    expect(handlers.onThreadListUpdated).toHaveBeenCalledWith("ws-1", {
+      threads: [{ id: "thread-2", preview: "Updated" }],
+      removedThreadIds: ["thread-3"],
+    });
+
     act(() => {
       listener?.({
         workspace_id: "ws-1",
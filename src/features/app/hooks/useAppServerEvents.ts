@@ -35,6 +35,10 @@ type AppServerEventHandlers = {
     workspaceId: string,
     payload: { threadId: string; threadName: string | null },
   ) => void;
+  onThreadListUpdated?: (
+    workspaceId: string,
+    payload: { threads: Record<string, unknown>[]; removedThreadIds: string[] },
+  ) => void;
   onBackgroundThreadAction?: (
     workspaceId: string,
     threadId: string,
@@ -108,6 +112,7 @@ export const METHODS_ROUTED_IN_USE_APP_SERVER_EVENTS = [
   "item/reasoning/textDelta",
   "item/started",
   "item/tool/requestUserInput",
+  "thread/listUpdated",
   "thread/name/updated",
   "thread/started",
   "thread/tokenUsage/updated",
@@ -226,6 +231,17 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
         return;
       }
 
+      if (method === "thread/listUpdated") {
+        const threads = Array.isArray(params.threads)
+          ? (params.threads as Record<string, unknown>[])
+          : [];
+        const removedThreadIds = Array.isArray(params.removedThreadIds)
+          ? (params.removedThreadIds as unknown[]).map(String)
+          : [];
+        handlers.onThreadListUpdated?.(workspace_id, { threads, removedThreadIds });
+        return;
+      }
+
       if (method === "thread/name/updated") {
         const threadId = String(params.threadId ?? params.thread_id ?? "").trim();
         const threadNameRaw = params.threadName ?? params.thread_name ?? null;
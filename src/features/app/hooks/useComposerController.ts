@@ -1,5 +1,7 @@
-import { useCallback, useMemo, useState } from "react";
+import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import type { QueuedMessage, WorkspaceInfo } from "../../../types";
+import { getDraft, saveDraft } from "../../../services/tauri";
+import type { ThreadDraft } from "../../../services/tauri";
 import { useComposerImages } from "../../composer/hooks/useComposerImages";
 import { useQueuedSend } from "../../threads/hooks/useQueuedSend";
 
@@ -100,6 +102,98 @@ export function useComposerController({
     [activeThreadId, composerDraftsByThread],
   );
 
+  // Drafts are persisted per thread so they survive thread switches and restarts. A thread's
+  // stored draft is loaded before any save, so an empty composer never wipes it.
+  const loadedDraftThreadsRef = useRef<Set<string>>(new Set());
+  const [loadedDraftThreadId, setLoadedDraftThreadId] = useState<string | null>(
+    null,
+  );
+
+  useEffect(() => {
+    if (!activeWorkspaceId || !activeThreadId) {
+      return;
+    }
+    if (loadedDraftThreadsRef.current.has(activeThreadId)) {
+      setLoadedDraftThreadId(activeThreadId);
+      return;
+    }
+    let canceled = false;
+    const threadId = activeThreadId;
+    void getDraft(activeWorkspaceId, threadId)
+      .then((response) => {
+        const draft: ThreadDraft | null =
+          response?.result?.draft ?? response?.draft ?? null;
+        if (canceled || !draft) {
+          return;
+        }
+        setComposerDraftsByThread((prev) =>
+          prev[threadId] ? prev : { ...prev, [threadId]: draft.text },
+        );
+        if (draft.images.length > 0) {
+          setImagesForThread(threadId, draft.images);
+        }
+      })
+      .catch(() => {})
+      .finally(() => {
+        if (!canceled) {
+          loadedDraftThreadsRef.current.add(threadId);
+          setLoadedDraftThreadId(threadId);
+        }
+      });
+    return () => {
+      canceled = true;
+    };
+  }, [activeThreadId, activeWorkspaceId, setImagesForThread]);
+
+  const activeImagesKey = activeImages.join("\n");
+  const pendingDraftSaveRef = useRef<{
+    workspaceId: string;
+    threadId: string;
+    text: string;
+    images: string[];
+  } | null>(null);
+
+  const flushDraftSave = useCallback(() => {
+    const pending = pendingDraftSaveRef.current;
+    pendingDraftSaveRef.current = null;
+    if (pending) {
+      void saveDraft(
+        pending.workspaceId,
+        pending.threadId,
+        pending.text,
+        pending.images,
+      ).catch(() => {});
+    }
+  }, []);
+
+  useEffect(() => {
+    if (
+      !activeWorkspaceId ||
+      !activeThreadId ||
+      loadedDraftThreadId !== activeThreadId
+    ) {
+      return;
+    }
+    pendingDraftSaveRef.current = {
+      workspaceId: activeWorkspaceId,
+      threadId: activeThreadId,
+      text: activeDraft,
+      images: activeImagesKey ? activeImagesKey.split("\n") : [],
+    };
+    const handle = window.setTimeout(flushDraftSave, 400);
+    return () => window.clearTimeout(handle);
+  }, [
+    activeDraft,
+    activeImagesKey,
+    activeThreadId,
+    activeWorkspaceId,
+    flushDraftSave,
+    loadedDraftThreadId,
+  ]);
+
+  // Switching threads saves the previous thread's draft right away.
+  useEffect(() => flushDraftSave, [activeThreadId, flushDraftSave]);
+
   const handleDraftChange = useCallback(
     (next: string) => {
       if (!activeThreadId) {
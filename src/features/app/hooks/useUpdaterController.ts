@@ -1,18 +1,19 @@
 import { useCallback, useRef } from "react";
 import { useUpdater } from "../../update/hooks/useUpdater";
 import { useAgentSoundNotifications } from "../../notifications/hooks/useAgentSoundNotifications";
-import { useAgentSystemNotifications } from "../../notifications/hooks/useAgentSystemNotifications";
 import { useWindowFocusState } from "../../layout/hooks/useWindowFocusState";
 import { useTauriEvent } from "./useTauriEvent";
 import { playNotificationSound } from "../../../utils/notificationSounds";
-import { subscribeUpdaterCheck } from "../../../services/events";
+import {
+  subscribeThreadNotificationSent,
+  subscribeUpdaterCheck,
+} from "../../../services/events";
 import { sendNotification } from "../../../services/tauri";
 import type { DebugEntry } from "../../../types";
 
 type Params = {
   notificationSoundsEnabled: boolean;
   systemNotificationsEnabled: boolean;
-  getWorkspaceName?: (workspaceId: string) => string | undefined;
   onThreadNotificationSent?: (workspaceId: string, threadId: string) => void;
   onDebug: (entry: DebugEntry) => void;
   successSoundUrl: string;
@@ -22,7 +23,6 @@ type Params = {
 export function useUpdaterController({
   notificationSoundsEnabled,
   systemNotificationsEnabled,
-  getWorkspaceName,
   onThreadNotificationSent,
   onDebug,
   successSoundUrl,
@@ -60,12 +60,9 @@ export function useUpdaterController({
     onDebug,
   });
 
-  useAgentSystemNotifications({
-    enabled: systemNotificationsEnabled,
-    isWindowFocused,
-    getWorkspaceName,
-    onThreadNotificationSent,
-    onDebug,
+  // Turn notifications are raised by the backend; it reports back which thread to open on click.
+  useTauriEvent(subscribeThreadNotificationSent, (event) => {
+    onThreadNotificationSent?.(event.workspaceId, event.threadId);
   });
 
   const handleTestNotificationSound = useCallback(() => {
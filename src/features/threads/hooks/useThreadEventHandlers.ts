@@ -86,6 +86,7 @@ export function useThreadEventHandlers({
 
   const {
     onThreadStarted,
+    onThreadListUpdated,
     onThreadNameUpdated,
     onTurnStarted,
     onTurnCompleted,
@@ -151,6 +152,7 @@ export function useThreadEventHandlers({
       onTerminalInteraction,
       onFileChangeOutputDelta,
       onThreadStarted,
+      onThreadListUpdated,
       onThreadNameUpdated,
       onTurnStarted,
       onTurnCompleted,
@@ -177,6 +179,7 @@ export function useThreadEventHandlers({
       onTerminalInteraction,
       onFileChangeOutputDelta,
       onThreadStarted,
+      onThreadListUpdated,
       onThreadNameUpdated,
       onTurnStarted,
       onTurnCompleted,
@@ -82,6 +82,40 @@ export function useThreadTurnEvents({
     [dispatch, getCustomName, isThreadHidden, recordThreadActivity, safeMessageActivity],
   );
 
+  const onThreadListUpdated = useCallback(
+    (
+      workspaceId: string,
+      payload: { threads: Record<string, unknown>[]; removedThreadIds: string[] },
+    ) => {
+      payload.removedThreadIds.forEach((threadId) => {
+        dispatch({ type: "removeThread", workspaceId, threadId });
+      });
+      payload.threads.forEach((thread) => {
+        const threadId = asString(thread.id);
+        if (!threadId || isThreadHidden(workspaceId, threadId)) {
+          return;
+        }
+        dispatch({ type: "ensureThread", workspaceId, threadId });
+        const timestamp = getThreadTimestamp(thread);
+        if (timestamp > 0) {
+          recordThreadActivity(workspaceId, threadId, timestamp);
+          dispatch({ type: "setThreadTimestamp", workspaceId, threadId, timestamp });
+        }
+        if (getCustomName(workspaceId, threadId)) {
+          return;
+        }
+        const preview = asString(thread.preview).trim();
+        const name =
+          asString(thread.name).trim() ||
+          (preview.length > 38 ? `${preview.slice(0, 38)}…` : preview);
+        if (name) {
+          dispatch({ type: "setThreadName", workspaceId, threadId, name });
+        }
+      });
+    },
+    [dispatch, getCustomName, isThreadHidden, recordThreadActivity],
+  );
+
   const onThreadNameUpdated = useCallback(
     (
       workspaceId: string,
@@ -217,6 +251,7 @@ export function useThreadTurnEvents({
 
   return {
     onThreadStarted,
+    onThreadListUpdated,
     onThreadNameUpdated,
     onTurnStarted,
     onTurnCompleted,
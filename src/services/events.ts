@@ -1,5 +1,12 @@
 import { listen } from "@tauri-apps/api/event";
-import type { AppServerEvent, DictationEvent, DictationModelStatus } from "../types";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import type {
+  AppServerEvent,
+  AppServerEventFilter,
+  DictationEvent,
+  DictationModelStatus,
+} from "../types";
+import { subscribeEvents } from "./tauri";
 
 export type Unsubscribe = () => void;
 
@@ -14,6 +21,11 @@ export type TerminalExitEvent = {
   terminalId: string;
 };
 
+export type ThreadNotificationSentEvent = {
+  workspaceId: string;
+  threadId: string;
+};
+
 type SubscriptionOptions = {
   onError?: (error: unknown) => void;
 };
@@ -86,6 +98,9 @@ const dictationDownloadHub = createEventHub<DictationModelStatus>("dictation-dow
 const dictationEventHub = createEventHub<DictationEvent>("dictation-event");
 const terminalOutputHub = createEventHub<TerminalOutputEvent>("terminal-output");
 const terminalExitHub = createEventHub<TerminalExitEvent>("terminal-exit");
+const threadNotificationSentHub = createEventHub<ThreadNotificationSentEvent>(
+  "thread-notification-sent",
+);
 const updaterCheckHub = createEventHub<void>("updater-check");
 const menuNewAgentHub = createEventHub<void>("menu-new-agent");
 const menuNewWorktreeAgentHub = createEventHub<void>("menu-new-worktree-agent");
@@ -118,6 +133,46 @@ export function subscribeAppServerEvents(
   return appServerHub.subscribe(onEvent, options);
 }
 
+/**
+ * Receives only the app-server events `filter` lets through; the rest are dropped before they
+ * reach this window. The filter belongs to the window, so a later call replaces it, and
+ * `subscribeAppServerEvents` listeners in the same window still get every event.
+ */
+export function subscribeFilteredAppServerEvents(
+  filter: AppServerEventFilter,
+  onEvent: (event: AppServerEvent) => void,
+  options?: SubscriptionOptions,
+): Unsubscribe {
+  let unlisten: Unsubscribe | null = null;
+  let cancelled = false;
+  // Listening on the window's label, not on any target, is what lets the filter apply.
+  const target = getCurrentWindow().label;
+  subscribeEvents(filter)
+    .then(() =>
+      listen<AppServerEvent>("app-server-event", (event) => onEvent(event.payload), {
+        target,
+      }),
+    )
+    .then((handler) => {
+      if (cancelled) {
+        handler();
+        return;
+      }
+      unlisten = handler;
+    })
+    .catch((error) => {
+      options?.onError?.(error);
+    });
+  return () => {
+    cancelled = true;
+    unlisten?.();
+    unlisten = null;
+    subscribeEvents(null).catch(() => {
+      // The window may already be closing.
+    });
+  };
+}
+
 export function subscribeDictationDownload(
   onEvent: (event: DictationModelStatus) => void,
   options?: SubscriptionOptions,
@@ -146,6 +201,13 @@ export function subscribeTerminalExit(
   return terminalExitHub.subscribe(onEvent, options);
 }
 
+export function subscribeThreadNotificationSent(
+  onEvent: (event: ThreadNotificationSentEvent) => void,
+  options?: SubscriptionOptions,
+): Unsubscribe {
+  return threadNotificationSentHub.subscribe(onEvent, options);
+}
+
 export function subscribeUpdaterCheck(
   onEvent: () => void,
   options?: SubscriptionOptions,
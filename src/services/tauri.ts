@@ -3,10 +3,18 @@ import { open } from "@tauri-apps/plugin-dialog";
 import type { Options as NotificationOptions } from "@tauri-apps/plugin-notification";
 import type {
   AppSettings,
+  AppServerEventFilter,
+  AnswerDiff,
+  AppProfiles,
+  AuditQueryResult,
   CodexDoctorResult,
+  CommitMessageSuggestion,
   DictationModelStatus,
   DictationSessionState,
   LocalUsageSnapshot,
+  PinnedItem,
+  SetupProbe,
+  ThreadExport,
   WorkspaceInfo,
   WorkspaceSettings,
 } from "../types";
@@ -130,6 +138,10 @@ export async function getConfigModel(workspaceId: string): Promise<string | null
   return trimmed.length > 0 ? trimmed : null;
 }
 
+export async function getEffectiveCodexConfig(workspaceId: string) {
+  return invoke<any>("config_effective", { workspaceId });
+}
+
 export async function addWorkspace(
   path: string,
   codex_bin: string | null,
@@ -141,6 +153,15 @@ export async function isWorkspacePathDir(path: string): Promise<boolean> {
   return invoke<boolean>("is_workspace_path_dir", { path });
 }
 
+export async function scanWorkspaces(root?: string | null, maxDepth?: number) {
+  return invoke<any>("scan_workspaces", { root: root ?? null, maxDepth });
+}
+
+export async function probeSetup(): Promise<SetupProbe> {
+  const response = await invoke<{ result: SetupProbe }>("setup_probe");
+  return response.result;
+}
+
 export async function addClone(
   sourceWorkspaceId: string,
   copiesFolder: string,
@@ -177,6 +198,18 @@ export async function markWorktreeSetupRan(workspaceId: string): Promise<void> {
   return invoke("worktree_setup_mark_ran", { workspaceId });
 }
 
+export async function setWorkspaceSecret(
+  workspaceId: string,
+  name: string,
+  value: string,
+) {
+  return invoke<any>("secrets_set", { workspaceId, name, value });
+}
+
+export async function deleteWorkspaceSecret(workspaceId: string, name: string) {
+  return invoke<any>("secrets_delete", { workspaceId, name });
+}
+
 export async function updateWorkspaceSettings(
   id: string,
   settings: WorkspaceSettings,
@@ -264,6 +297,8 @@ export async function sendUserMessage(
     accessMode?: "read-only" | "current" | "full-access";
     images?: string[];
     collaborationMode?: Record<string, unknown> | null;
+    dryRun?: boolean;
+    plan?: boolean;
   },
 ) {
   const payload: Record<string, unknown> = {
@@ -278,9 +313,111 @@ export async function sendUserMessage(
   if (options?.collaborationMode) {
     payload.collaborationMode = options.collaborationMode;
   }
+  if (options?.dryRun) {
+    payload.dryRun = true;
+  }
+  if (options?.plan) {
+    payload.plan = true;
+  }
   return invoke("send_user_message", payload);
 }
 
+export async function executePlan(
+  workspaceId: string,
+  threadId: string,
+  turnId?: string | null,
+) {
+  return invoke<any>("execute_plan", {
+    workspaceId,
+    threadId,
+    turnId: turnId ?? null,
+  });
+}
+
+/**
+ * Replaces a past user message and runs the thread again from it; without `text` the
+ * message is resent as is. The reply carries `removedTurns`.
+ */
+export async function editMessage(
+  workspaceId: string,
+  threadId: string,
+  itemId: string,
+  text?: string | null,
+) {
+  return invoke<any>("edit_message", {
+    workspaceId,
+    threadId,
+    itemId,
+    text: text ?? null,
+  });
+}
+
+export async function undoEditMessage(workspaceId: string, threadId: string) {
+  return invoke<any>("undo_edit_message", { workspaceId, threadId });
+}
+
+export type SelectionContextBlock = {
+  path?: string | null;
+  language?: string | null;
+  startLine?: number | null;
+  endLine?: number | null;
+  content: string;
+};
+
+export async function sendWithContext(
+  workspaceId: string,
+  threadId: string,
+  text: string,
+  context: SelectionContextBlock[],
+  images?: string[],
+) {
+  return invoke<any>("send_with_context", {
+    workspaceId,
+    threadId,
+    text,
+    context,
+    images: images ?? null,
+  });
+}
+
+export async function getPromptHistory(
+  workspaceId: string,
+  query?: string | null,
+  limit?: number | null,
+) {
+  return invoke<any>("prompts_history", {
+    workspaceId,
+    query: query ?? null,
+    limit: limit ?? null,
+  });
+}
+
+export async function resendPrompt(
+  workspaceId: string,
+  threadId: string,
+  promptId: string,
+) {
+  return invoke<any>("prompts_history_resend", { workspaceId, threadId, promptId });
+}
+
+export async function replyLatest(workspaceId: string, text: string) {
+  return invoke<any>("reply_latest", { workspaceId, text });
+}
+
+export async function sendAudioMessage(
+  workspaceId: string,
+  threadId: string,
+  audio: string,
+  mimeType?: string | null,
+) {
+  return invoke<{ transcript: string; response: unknown }>("send_audio_message", {
+    workspaceId,
+    threadId,
+    audio,
+    mimeType: mimeType ?? null,
+  });
+}
+
 export async function interruptTurn(
   workspaceId: string,
   threadId: string,
@@ -595,6 +732,11 @@ export async function setMenuAccelerators(
   return invoke("menu_set_accelerators", { updates });
 }
 
+/** Sets this window's `app-server-event` filter, or clears it with `null`. */
+export async function subscribeEvents(filter: AppServerEventFilter | null) {
+  return invoke<any>("subscribe_events", { filter });
+}
+
 export async function runCodexDoctor(
   codexBin: string | null,
   codexArgs: string | null,
@@ -602,6 +744,10 @@ export async function runCodexDoctor(
   return invoke<CodexDoctorResult>("codex_doctor", { codexBin, codexArgs });
 }
 
+export async function detectProviders() {
+  return invoke<any>("providers_detect");
+}
+
 export async function getWorkspaceFiles(workspaceId: string) {
   return invoke<string[]>("list_workspace_files", { workspaceId });
 }
@@ -735,6 +881,46 @@ export async function listThreads(
   return invoke<any>("list_threads", { workspaceId, cursor, limit });
 }
 
+export async function listWorktreeThreads(workspaceId: string) {
+  return invoke<any>("list_worktree_threads", { workspaceId });
+}
+
+export async function syncThreads(workspaceId: string) {
+  return invoke<any>("threads_sync", { workspaceId });
+}
+
+export async function quickFindThreads(
+  query: string,
+  workspaceId?: string | null,
+  limit?: number | null,
+) {
+  return invoke<any>("threads_quick_find", {
+    workspaceId: workspaceId ?? null,
+    query,
+    limit: limit ?? null,
+  });
+}
+
+export async function inboxList(limit?: number | null) {
+  return invoke<any>("inbox_list", { limit });
+}
+
+export async function lanSyncStart() {
+  return invoke<any>("lan_sync_start");
+}
+
+export async function lanSyncStop() {
+  return invoke<any>("lan_sync_stop");
+}
+
+export async function lanSyncDiscover(timeoutMs?: number | null) {
+  return invoke<any>("lan_sync_discover", { timeoutMs });
+}
+
+export async function lanSyncWithPeer(address: string) {
+  return invoke<any>("lan_sync_with_peer", { address });
+}
+
 export async function listMcpServerStatus(
   workspaceId: string,
   cursor?: string | null,
@@ -743,14 +929,350 @@ export async function listMcpServerStatus(
   return invoke<any>("list_mcp_server_status", { workspaceId, cursor, limit });
 }
 
-export async function resumeThread(workspaceId: string, threadId: string) {
-  return invoke<any>("resume_thread", { workspaceId, threadId });
+export async function resumeThread(
+  workspaceId: string,
+  threadId: string,
+  cursor?: string | null,
+  limit?: number | null,
+) {
+  return invoke<any>("resume_thread", { workspaceId, threadId, cursor, limit });
 }
 
 export async function archiveThread(workspaceId: string, threadId: string) {
   return invoke<any>("archive_thread", { workspaceId, threadId });
 }
 
+export type TrashedThread = {
+  workspaceId: string;
+  threadId: string;
+  name: string | null;
+  preview: string;
+  turns: number;
+  updatedAt: number;
+  trashedAt: number;
+  reason: "archive" | "delete";
+};
+
+export async function listTrash(workspaceId?: string | null) {
+  return invoke<any>("list_trash", { workspaceId: workspaceId ?? null });
+}
+
+export async function restoreTrash(workspaceId: string, threadIds: string[]) {
+  return invoke<any>("restore_trash", { workspaceId, threadIds });
+}
+
+export async function listActions(query?: string | null) {
+  return invoke<any>("actions_list", { query: query ?? null });
+}
+
+export async function getProviderRateLimitState() {
+  return invoke<any>("provider_rate_limit_state");
+}
+
+export async function getStatusSummary() {
+  return invoke<any>("status_summary");
+}
+
+export async function listOrphans() {
+  return invoke<any>("orphans_list");
+}
+
+export async function purgeOrphans(workspaceIds: string[]) {
+  return invoke<any>("orphans_purge", { workspaceIds });
+}
+
+export async function bulkThreadOp(
+  workspaceId: string,
+  threadIds: string[],
+  op: "archive" | "delete" | "tag" | "export",
+  tags?: string[],
+) {
+  return invoke<any>("bulk_thread_op", {
+    workspaceId,
+    threadIds,
+    op,
+    tags: tags ?? null,
+  });
+}
+
+export async function followClaudeSession(
+  workspaceId: string,
+  threadId: string,
+) {
+  return invoke<any>("follow_claude_session", { workspaceId, threadId });
+}
+
+export async function unfollowClaudeSession(
+  workspaceId: string,
+  threadId: string,
+) {
+  return invoke<any>("unfollow_claude_session", { workspaceId, threadId });
+}
+
+export async function importCodexSessions(workspaceId: string) {
+  return invoke<any>("import_codex_sessions", { workspaceId });
+}
+
+export type ExternalImportFormat = "cursor" | "continue" | "chatgpt";
+
+export async function importExternal(
+  workspaceId: string,
+  format: ExternalImportFormat,
+  path: string,
+) {
+  return invoke<any>("import_external", { workspaceId, format, path });
+}
+
+export async function exportThreads(
+  workspaceId: string,
+  threadIds?: string[],
+): Promise<ThreadExport> {
+  const response = await invoke<{ result: ThreadExport }>("export_threads", {
+    workspaceId,
+    threadIds: threadIds ?? null,
+  });
+  return response.result;
+}
+
+export async function importThreads(workspaceId: string, path: string) {
+  return invoke<any>("import_threads", { workspaceId, path });
+}
+
+export async function getThreadExportSchema() {
+  return invoke<any>("thread_export_schema");
+}
+
+export async function queryAuditLog(filter: {
+  since?: number;
+  until?: number;
+  workspaceId?: string;
+  limit?: number;
+} = {}): Promise<AuditQueryResult> {
+  const response = await invoke<{ result: AuditQueryResult }>("audit_query", {
+    since: filter.since ?? null,
+    until: filter.until ?? null,
+    workspaceId: filter.workspaceId ?? null,
+    limit: filter.limit ?? null,
+  });
+  return response.result;
+}
+
+export async function storageUsage() {
+  return invoke<any>("storage_usage");
+}
+
+export async function storageMigrate(options: { path?: string; portable?: boolean }) {
+  return invoke<any>("storage_migrate", {
+    path: options.path ?? null,
+    portable: options.portable ?? null,
+  });
+}
+
+export async function listProfiles(): Promise<AppProfiles> {
+  const response = await invoke<{ result: AppProfiles }>("profile_list");
+  return response.result;
+}
+
+/** Locally the app restarts into the profile right after this resolves. */
+export async function switchProfile(name: string) {
+  return invoke<any>("profile_switch", { name });
+}
+
+export type ExtractedPatch = {
+  id: string;
+  kind: "diff" | "file";
+  files: string[];
+  content: string;
+};
+
+export async function getTurnPatches(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+) {
+  return invoke<any>("turn_patches", { workspaceId, threadId, turnId });
+}
+
+export async function getTurnArtifacts(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+) {
+  return invoke<any>("turn_artifacts", { workspaceId, threadId, turnId });
+}
+
+export async function getTurnDetails(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+) {
+  return invoke<any>("turn_details", { workspaceId, threadId, turnId });
+}
+
+export async function applyPatch(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+  patchId: string,
+) {
+  return invoke<any>("apply_patch", { workspaceId, threadId, turnId, patchId });
+}
+
+export type PlaybookStep = {
+  prompt: string;
+  requireConfirmation?: boolean;
+};
+
+export type Playbook = {
+  id: string;
+  name: string;
+  steps: PlaybookStep[];
+  createdAt: number;
+  updatedAt: number;
+};
+
+export type ThreadDraft = {
+  text: string;
+  images: string[];
+  updatedAt: number;
+};
+
+export type WorkspaceMemory = {
+  path: string;
+  absolutePath: string;
+  exists: boolean;
+  content: string;
+};
+
+export async function memoryRead(workspaceId: string) {
+  return invoke<any>("memory_read", { workspaceId });
+}
+
+export async function memoryWrite(workspaceId: string, content: string) {
+  return invoke<any>("memory_write", { workspaceId, content });
+}
+
+export async function memoryAppend(
+  workspaceId: string,
+  entry: { text?: string; threadId?: string; turnId?: string },
+) {
+  return invoke<any>("memory_append", {
+    workspaceId,
+    text: entry.text ?? null,
+    threadId: entry.threadId ?? null,
+    turnId: entry.turnId ?? null,
+  });
+}
+
+export async function memoryClear(workspaceId: string) {
+  return invoke<any>("memory_clear", { workspaceId });
+}
+
+export type ClaudeHookCommand = {
+  type: "command";
+  command: string;
+  timeout?: number;
+};
+
+export type ClaudeHookMatcher = {
+  matcher?: string;
+  hooks: ClaudeHookCommand[];
+};
+
+export type ClaudeHooksConfig = Record<string, ClaudeHookMatcher[]>;
+
+export async function claudeHooksRead(workspaceId: string) {
+  return invoke<any>("claude_hooks_read", { workspaceId });
+}
+
+export async function claudeHooksWrite(
+  workspaceId: string,
+  hooks: ClaudeHooksConfig,
+) {
+  return invoke<any>("claude_hooks_write", { workspaceId, hooks });
+}
+
+export async function saveDraft(
+  workspaceId: string,
+  threadId: string,
+  text: string,
+  images?: string[],
+) {
+  return invoke<any>("save_draft", { workspaceId, threadId, text, images });
+}
+
+export async function getDraft(workspaceId: string, threadId: string) {
+  return invoke<any>("get_draft", { workspaceId, threadId });
+}
+
+export type PromptEstimate = {
+  provider: string;
+  model: string | null;
+  method: "heuristic";
+  tokens: { prompt: number; context: number; images: number; total: number };
+  contextFiles: { path: string; tokens: number }[];
+  inputPricePerMillion: number | null;
+  estimatedCostUsd: number | null;
+};
+
+export async function estimatePrompt(
+  workspaceId: string,
+  text: string,
+  images?: string[],
+  model?: string | null,
+) {
+  return invoke<any>("prompt_estimate", {
+    workspaceId,
+    text,
+    images,
+    model: model ?? null,
+  });
+}
+
+export async function listPlaybooks() {
+  return invoke<any>("list_playbooks");
+}
+
+export async function savePlaybook(
+  name: string,
+  steps: PlaybookStep[],
+  id?: string | null,
+) {
+  return invoke<any>("save_playbook", { id: id ?? null, name, steps });
+}
+
+export async function deletePlaybook(id: string) {
+  return invoke<any>("delete_playbook", { id });
+}
+
+export async function runPlaybook(
+  workspaceId: string,
+  threadId: string,
+  playbookId: string,
+) {
+  return invoke<any>("run_playbook", { workspaceId, threadId, playbookId });
+}
+
+export async function respondPlaybookStep(runId: string, approved: boolean) {
+  return invoke<any>("respond_playbook_step", { runId, approved });
+}
+
+export async function cancelPlaybook(runId: string) {
+  return invoke<any>("cancel_playbook", { runId });
+}
+
+export async function respondToApproval(
+  approvalId: string,
+  allow: boolean,
+  message?: string | null,
+) {
+  return invoke<any>("respond_to_approval", {
+    approvalId,
+    allow,
+    message: message ?? null,
+  });
+}
+
 export async function setThreadName(
   workspaceId: string,
   threadId: string,
@@ -759,6 +1281,134 @@ export async function setThreadName(
   return invoke<any>("set_thread_name", { workspaceId, threadId, name });
 }
 
+export async function markThreadRead(workspaceId: string, threadId: string) {
+  return invoke<any>("mark_thread_read", { workspaceId, threadId });
+}
+
+export async function setItemPinned(
+  workspaceId: string,
+  threadId: string,
+  itemId: string,
+  pinned: boolean,
+) {
+  return invoke<any>("set_item_pinned", { workspaceId, threadId, itemId, pinned });
+}
+
+export async function getPinnedItems(
+  workspaceId: string,
+  threadId: string,
+): Promise<PinnedItem[]> {
+  const response = await invoke<{ result: { items: PinnedItem[] } }>(
+    "thread_pinned_items",
+    { workspaceId, threadId },
+  );
+  return response.result.items;
+}
+
+export type AnswerRef = { threadId: string; itemId: string };
+
+export async function diffAnswers(
+  workspaceId: string,
+  left: AnswerRef,
+  right: AnswerRef,
+): Promise<AnswerDiff> {
+  const response = await invoke<{ result: AnswerDiff }>("diff_items", {
+    workspaceId,
+    leftThreadId: left.threadId,
+    leftItemId: left.itemId,
+    rightThreadId: right.threadId,
+    rightItemId: right.itemId,
+  });
+  return response.result;
+}
+
+export async function setThreadCwd(
+  workspaceId: string,
+  threadId: string,
+  cwd: string | null,
+) {
+  return invoke<any>("set_thread_cwd", { workspaceId, threadId, cwd });
+}
+
+export async function setThreadGeneration(
+  workspaceId: string,
+  threadId: string,
+  generation: {
+    model?: string | null;
+    temperature?: number | null;
+    maxTokens?: number | null;
+  },
+) {
+  return invoke<any>("set_thread_generation", {
+    workspaceId,
+    threadId,
+    model: generation.model ?? null,
+    temperature: generation.temperature ?? null,
+    maxTokens: generation.maxTokens ?? null,
+  });
+}
+
+export async function mergeThreadWorktree(
+  workspaceId: string,
+  threadId: string,
+  removeWorktree = true,
+) {
+  return invoke<any>("merge_thread_worktree", {
+    workspaceId,
+    threadId,
+    removeWorktree,
+  });
+}
+
+export async function revertTurnChanges(
+  workspaceId: string,
+  threadId: string,
+  turnId: string,
+) {
+  return invoke<any>("revert_turn_changes", { workspaceId, threadId, turnId });
+}
+
+export async function duplicateThread(workspaceId: string, threadId: string) {
+  return invoke<any>("duplicate_thread", { workspaceId, threadId });
+}
+
+export async function moveThread(
+  workspaceId: string,
+  threadId: string,
+  targetWorkspaceId: string,
+) {
+  return invoke<any>("move_thread", {
+    workspaceId,
+    threadId,
+    targetWorkspaceId,
+  });
+}
+
+/** Points a thread whose folder is gone at `targetWorkspaceId`; see `CwdRepair`. */
+export async function rebindThreadWorkspace(
+  workspaceId: string,
+  threadId: string,
+  targetWorkspaceId: string,
+) {
+  return invoke<any>("rebind_thread_workspace", {
+    workspaceId,
+    threadId,
+    targetWorkspaceId,
+  });
+}
+
+export async function copyThread(
+  workspaceId: string,
+  threadId: string,
+  targetWorkspaceId: string,
+) {
+  return invoke<any>("copy_thread", {
+    workspaceId,
+    threadId,
+    targetWorkspaceId,
+  });
+}
+
 export async function getCommitMessagePrompt(
   workspaceId: string,
 ): Promise<string> {
@@ -767,8 +1417,13 @@ export async function getCommitMessagePrompt(
 
 export async function generateCommitMessage(
   workspaceId: string,
-): Promise<string> {
-  return invoke("generate_commit_message", { workspaceId });
+  commit = false,
+): Promise<CommitMessageSuggestion> {
+  const response = await invoke<{ result: CommitMessageSuggestion }>(
+    "generate_commit_message",
+    { workspaceId, commit },
+  );
+  return response.result;
 }
 
 export async function sendNotification(
@@ -13,6 +13,31 @@ export type WorkspaceSettings = {
   launchScript?: string | null;
   launchScripts?: LaunchScriptEntry[] | null;
   worktreeSetupScript?: string | null;
+  contextFiles?: string[];
+  turnNotifications?: boolean | null;
+  onTurnCompleted?: string | null;
+  threadWorktrees?: boolean;
+  turnSnapshots?: boolean;
+  providerProfileId?: string | null;
+  providerFallbacks?: string[];
+  secretNames?: string[];
+  sandbox?: SandboxSettings;
+  instructions?: string | null;
+  artifactPaths?: string[];
+  responseLanguage?: string | null;
+  eventMirrorPath?: string | null;
+};
+
+export type SandboxSettings = {
+  codexMode?: "read-only" | "workspace-write" | "full-access" | null;
+  claudeSkipPermissions?: boolean;
+  claudeAllowedTools?: string[];
+  claudeDisallowedTools?: string[];
+  restrictEnv?: boolean;
+  allowedEnv?: string[];
+  noNetwork?: boolean;
+  fileAllow?: string[];
+  fileDeny?: string[];
 };
 
 export type LaunchScriptIconId =
@@ -69,12 +94,265 @@ export type AppServerEvent = {
   message: Record<string, unknown>;
 };
 
+/** Which `app-server-event`s a window receives; an empty or missing list does not restrict. */
+export type AppServerEventFilter = {
+  workspaceIds?: string[];
+  /** Exact methods such as `error`, or families ending in `/` such as `turn/`. */
+  methods?: string[];
+};
+
 export type Message = {
   id: string;
   role: "user" | "assistant";
   text: string;
 };
 
+export type FileReference = {
+  text: string;
+  path: string;
+  absolutePath: string;
+  line?: number;
+  endLine?: number;
+};
+
+export type TurnArtifact = {
+  path: string;
+  source: "reply" | "declared";
+  storedPath: string;
+  size: number;
+};
+
+export type TurnRequest = {
+  bin: string;
+  args: string[];
+  cwd: string;
+  envNames: string[];
+  envRemoved?: string[];
+  envCleared: boolean;
+  sessionFlags: string[];
+  pooled: boolean;
+  attempts: number;
+  exitStatus: number | null;
+  shell?: string;
+};
+
+export type ChecklistStep = {
+  step: string;
+  status: "pending" | "inProgress" | "completed";
+  activeForm?: string;
+};
+
+export type ModelDowngrade = {
+  model: string;
+  from?: string;
+  reason: "simpleThread" | "nearBudget";
+  detail: string;
+};
+
+/** Mirrors `docs/thread-export.schema.json`. */
+export type ThreadExport = {
+  format: "polycode.threads";
+  schemaVersion: number;
+  exportedAt: number;
+  threads: ExportedThread[];
+};
+
+export type ExportedThread = {
+  id: string;
+  name?: string;
+  cwd: string;
+  preview: string;
+  createdAt: number;
+  updatedAt: number;
+  tags: string[];
+  turns: ExportedTurn[];
+};
+
+export type ExportedTurn = {
+  id: string;
+  startedAt: number;
+  completedAt: number | null;
+  durationMs?: number;
+  plan: boolean;
+  items: { id: string; role: "user" | "assistant"; text: string }[];
+};
+
+export type PinnedItem = {
+  turnId: string;
+  itemId: string;
+  role: "user" | "assistant";
+  text: string;
+  startedAt: number;
+};
+
+/** Word-level comparison of two assistant answers from `diff_items`. */
+export type AnswerDiff = {
+  left: { threadId: string; turnId: string; itemId: string };
+  right: { threadId: string; turnId: string; itemId: string };
+  /** Joining `equal` and `removed` hunks gives the left text; `equal` and `added` the right. */
+  hunks: { kind: "equal" | "removed" | "added"; text: string }[];
+  stats: {
+    unchangedWords: number;
+    removedWords: number;
+    addedWords: number;
+    /** 0 to 1; share of words both answers have. */
+    similarity: number;
+  };
+};
+
+/**
+ * App profiles from `profile_list`. Each has its own settings, workspaces and threads;
+ * `default` is the data dir itself.
+ */
+export type AppProfiles = {
+  active: string;
+  profiles: { name: string; dataDir: string; active: boolean }[];
+};
+
+/** Reply of `generate_commit_message`. */
+export type CommitMessageSuggestion = {
+  message: string;
+  provider: "codex" | "claude" | "gemini";
+  /** False when nothing was staged and the message covers all uncommitted changes. */
+  staged: boolean;
+  /** Claude workspaces keep the exchange as a thread tagged `commit-message`. */
+  threadId: string | null;
+  /** Sha of the commit, when `commit` was requested. */
+  commit: string | null;
+};
+
+/**
+ * Set on a resumed Claude thread whose folder no longer exists. Pass a candidate's
+ * `workspaceId` to `rebind_thread_workspace` to fix it.
+ */
+export type CwdRepair = {
+  cwd: string;
+  /** `host/owner/repo` of the thread's workspace folder, when that still exists. */
+  gitRemote: string | null;
+  candidates: {
+    workspaceId: string;
+    name: string;
+    path: string;
+    matchedBy: "gitRemote" | "folderName";
+  }[];
+};
+
+/** First-run onboarding plan from `setup_probe`. Nothing is changed by probing. */
+export type SetupProbe = {
+  needsSetup: boolean;
+  steps: {
+    id: "providers" | "defaultProvider" | "workspaces";
+    done: boolean;
+    detail: string;
+  }[];
+  providers: {
+    provider: "codex" | "claude" | "gemini";
+    suggested: string | null;
+    candidates: { path: string; source: string; version: string | null }[];
+  }[];
+  suggestedDefaultProvider: "codex" | "claude" | "gemini" | null;
+  histories: {
+    provider: "codex" | "claude";
+    root: string | null;
+    sessions: number;
+    folders: number;
+  }[];
+  scannedDirs: string[];
+  suggestions: {
+    path: string;
+    name: string;
+    isGitRepo: boolean;
+    history: ("codex" | "claude")[];
+    sessions: number;
+    lastActiveAt: number | null;
+  }[];
+  alreadyAdded: number;
+};
+
+/** One agent process from the audit log. */
+export type AuditEntry = {
+  workspaceId: string;
+  threadId?: string;
+  turnId?: string;
+  provider: "codex" | "claude" | "gemini";
+  action: string;
+  bin: string;
+  argvHash: string;
+  attempts: number;
+  startedAt: number;
+  finishedAt: number;
+  exitCode: number | null;
+  error?: string;
+};
+
+export type AuditQueryResult = {
+  entries: AuditEntry[];
+  total: number;
+  unreadable: number;
+};
+
+export type TurnDetails = {
+  threadId: string;
+  turnId: string;
+  startedAt: number;
+  completedAt: number | null;
+  durationMs: number | null;
+  cliVersion: string | null;
+  plan: boolean;
+  answeredBy: string | null;
+  modelDowngrade?: ModelDowngrade | null;
+  request: TurnRequest | null;
+};
+
+export type RateLimitState = {
+  provider: "codex" | "claude" | "gemini";
+  coolingDown: boolean;
+  until: number | null;
+  retryInMs: number;
+  reason: "rateLimited" | "overloaded" | null;
+};
+
+export type RecentError = {
+  workspaceId: string;
+  threadId: string | null;
+  message: string;
+  at: number;
+};
+
+export type StatusSummary = {
+  running: number;
+  queued: number;
+  recentErrors: number;
+  lastError: RecentError | null;
+};
+
+export type OrphanedWorkspace = {
+  workspaceId: string;
+  threads: number;
+  archivedIds: number;
+  trashedThreads: number;
+  drafts: number;
+  paths: string[];
+  bytes: number;
+};
+
+export type ActionParam = {
+  name: string;
+  type: "string" | "string[]" | "number" | "boolean" | "object";
+  required: boolean;
+  description: string;
+};
+
+export type ActionSpec = {
+  id: string;
+  title: string;
+  category: string;
+  description: string;
+  params: ActionParam[];
+  mutating: boolean;
+  enabled: boolean;
+};
+
 export type ConversationItem =
   | {
       id: string;
@@ -82,6 +360,8 @@ export type ConversationItem =
       role: "user" | "assistant";
       text: string;
       images?: string[];
+      references?: FileReference[];
+      pinned?: boolean;
     }
   | { id: string; kind: "reasoning"; summary: string; content: string }
   | { id: string; kind: "diff"; title: string; diff: string; status?: string }
@@ -109,6 +389,38 @@ export type ThreadSummary = {
   name: string;
   updatedAt: number;
   provider?: ProviderKind;
+  summary?: string | null;
+  stats?: ThreadStats;
+  lastReadAt?: number | null;
+  unreadTurns?: number;
+};
+
+export type InheritedThreadSummary = ThreadSummary & {
+  workspaceId: string;
+  origin: "worktree" | "parent";
+};
+
+export type QuickFindThread = {
+  id: string;
+  workspaceId: string;
+  name: string | null;
+  preview: string;
+  updatedAt: number;
+};
+
+export type PromptHistoryEntry = {
+  id: string;
+  text: string;
+  threadId: string;
+  lastUsedAt: number;
+  useCount: number;
+};
+
+export type ThreadStats = {
+  turnCount: number;
+  assistantChars: number;
+  lastProvider: ProviderKind | null;
+  errorCount: number;
 };
 
 export type ReviewTarget =
@@ -146,12 +458,50 @@ export type OpenAppTarget = {
   args: string[];
 };
 
+export type OutputFilterRule = {
+  id: string;
+  kind: "regex" | "jsonPath";
+  pattern: string;
+  enabled?: boolean;
+  provider?: ProviderKind | null;
+};
+
+export type RedactionRule = {
+  id: string;
+  pattern: string;
+  replacement?: string;
+  enabled?: boolean;
+};
+
+export type ProviderProfile = {
+  id: string;
+  name: string;
+  provider: ProviderKind;
+  bin?: string | null;
+  args?: string | null;
+  env?: Record<string, string>;
+  apiKey?: string | null;
+};
+
+export type ClaudeOptions = {
+  permissionMode?: "default" | "acceptEdits" | "plan" | "bypassPermissions" | null;
+  maxTurns?: number | null;
+  allowedTools?: string[];
+};
+
+export type CodexOptions = {
+  sandbox?: "read-only" | "workspace-write" | "danger-full-access" | null;
+  model?: string | null;
+};
+
 export type AppSettings = {
   codexBin: string | null;
   codexArgs: string | null;
+  codexOptions?: CodexOptions;
   defaultProvider?: ProviderKind | null;
   claudeBin?: string | null;
   claudeArgs?: string | null;
+  claudeOptions?: ClaudeOptions;
   geminiBin?: string | null;
   geminiArgs?: string | null;
   backendMode: BackendMode;
@@ -187,6 +537,8 @@ export type AppSettings = {
   codeFontSize: number;
   notificationSoundsEnabled: boolean;
   systemNotificationsEnabled: boolean;
+  notificationQuietHoursStart?: string | null;
+  notificationQuietHoursEnd?: string | null;
   preloadGitDiffs: boolean;
   gitDiffIgnoreWhitespaceChanges: boolean;
   experimentalCollabEnabled: boolean;
@@ -198,6 +550,7 @@ export type AppSettings = {
   dictationEnabled: boolean;
   dictationModelId: string;
   dictationPreferredLanguage: string | null;
+  dictationEndpoint?: string | null;
   dictationHoldKey: string | null;
   composerEditorPreset: ComposerEditorPreset;
   composerFenceExpandOnSpace: boolean;
@@ -211,6 +564,72 @@ export type AppSettings = {
   workspaceGroups: WorkspaceGroup[];
   openAppTargets: OpenAppTarget[];
   selectedOpenAppId: string;
+  /** Applied to Claude output only. */
+  outputFilters?: OutputFilterRule[];
+  sanitizeOutput?: boolean;
+  lanSyncPort?: number;
+  lanSyncBindAddress?: string | null;
+  lanSyncSecret?: string | null;
+  dataDir?: string | null;
+  portableMode?: boolean;
+  readOnly?: boolean;
+  retryMaxAttempts?: number;
+  retryBaseDelayMs?: number;
+  turnHeartbeatIntervalSecs?: number;
+  maxConcurrentTurns?: number;
+  maxTurnOutputBytes?: number;
+  retentionMaxThreadAgeDays?: number | null;
+  retentionMaxThreadsPerWorkspace?: number | null;
+  retentionAction?: "archive" | "delete";
+  retentionDeleteSessionLogs?: boolean;
+  trashRetentionDays?: number;
+  idleSummaryAfterMinutes?: number | null;
+  historySyncIntervalMinutes?: number | null;
+  followUpSuggestions?: boolean;
+  followUpSuggestionsModel?: string;
+  claudeWarmProcess?: boolean;
+  claudeWarmIdleTimeoutSecs?: number;
+  claudeCommandApprovals?: boolean;
+  launchShell?: string | null;
+  providerMinVersions?: Record<string, string>;
+  workspaceScanRoot?: string | null;
+  redactionRules?: RedactionRule[];
+  redactionRawLog?: boolean;
+  providerProfiles?: ProviderProfile[];
+  cheaperModels?: Record<string, string>;
+  downgradeTags?: string[];
+  dailyTurnBudget?: number | null;
+  downgradeBudgetPercent?: number;
+};
+
+export type ProviderCliCandidate = {
+  path: string;
+  source: "path" | "local" | "npm" | "nvm" | "homebrew" | "bun" | "system";
+  version: string | null;
+};
+
+export type ProviderDetection = {
+  provider: ProviderKind;
+  suggested: string | null;
+  candidates: ProviderCliCandidate[];
+};
+
+export type CodexArgLayer = {
+  layer: "options" | "app" | "parent" | "workspace";
+  args: string[];
+};
+
+export type CodexArgConflict = {
+  setting: string;
+  winner: CodexArgLayer;
+  overridden: CodexArgLayer[];
+};
+
+export type EffectiveCodexConfig = {
+  workspaceId: string;
+  argv: string[];
+  layers: CodexArgLayer[];
+  conflicts: CodexArgConflict[];
 };
 
 export type CodexDoctorResult = {
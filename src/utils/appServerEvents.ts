@@ -19,6 +19,7 @@ export const SUPPORTED_APP_SERVER_METHODS = [
   "item/reasoning/textDelta",
   "item/started",
   "item/tool/requestUserInput",
+  "thread/listUpdated",
   "thread/name/updated",
   "thread/started",
   "thread/tokenUsage/updated",